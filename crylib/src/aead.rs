@@ -1,4 +1,5 @@
 pub mod chacha;
+pub mod committing;
 pub mod gcm;
 
 pub const IV_SIZE: usize = 12;
@@ -19,7 +20,17 @@ impl core::fmt::Display for BadData {
 
 impl core::error::Error for BadData {}
 
+/// An authenticated cipher with associated data.
+///
+/// Every AEAD used by TLS 1.3 takes a 12-byte nonce and produces a 16-byte tag, so those sizes
+/// are fixed at [`IV_SIZE`] and [`TAG_SIZE`].
+/// This trait is dyn-compatible, so a connection can hold whichever cipher was negotiated;
+/// see [`NewAead`] for constructing one generically.
 pub trait Aead {
+    /// Encrypts `msg` in place, returning the authentication tag.
+    ///
+    /// WARNING: for security purposes,
+    /// users MUST NOT use the same `init_vector` twice for the same key.
     fn encrypt_inline(
         &self,
         msg: &mut [u8],
@@ -27,6 +38,15 @@ pub trait Aead {
         init_vector: &[u8; IV_SIZE],
     ) -> [u8; TAG_SIZE];
 
+    /// Encrypts `plain_text`, writing the cipher text to `buf` and returning the authentication
+    /// tag.
+    ///
+    /// WARNING: for security purposes,
+    /// users MUST NOT use the same `init_vector` twice for the same key.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if `plain_text.len()` > `buf.len()`.
     fn encrypt(
         &self,
         buf: &mut [u8],
@@ -35,6 +55,10 @@ pub trait Aead {
         init_vector: &[u8; IV_SIZE],
     ) -> [u8; TAG_SIZE];
 
+    /// Decrypts `msg` in place.
+    ///
+    /// If `tag` doesn't match, `msg` is zeroed and [`BadData`] is returned, so no unauthenticated
+    /// plaintext is ever exposed.
     fn decrypt_inline(
         &self,
         msg: &mut [u8],
@@ -43,6 +67,14 @@ pub trait Aead {
         tag: &[u8; TAG_SIZE],
     ) -> Result<(), BadData>;
 
+    /// Decrypts `cipher_text`, writing the plaintext to `buf`.
+    ///
+    /// If `tag` doesn't match, the first `cipher_text.len()` bytes of `buf` are zeroed and
+    /// [`BadData`] is returned.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if `cipher_text.len()` > `buf.len()`.
     fn decrypt(
         &self,
         buf: &mut [u8],
@@ -52,3 +84,69 @@ pub trait Aead {
         tag: &[u8; TAG_SIZE],
     ) -> Result<(), BadData>;
 }
+
+/// An [`Aead`] that can be created from a key.
+///
+/// This lets code such as the TLS record layer be generic over the negotiated cipher suite
+/// instead of matching on concrete types. It is separate from [`Aead`] because associated
+/// constants would stop [`Aead`] from being dyn-compatible.
+pub trait NewAead: Aead + Sized {
+    /// The length of the key, in bytes.
+    const KEY_SIZE: usize;
+
+    /// The length of the nonce, in bytes.
+    // TODO: use this and `TAG_SIZE` in `Aead`'s signatures once const-generic expressions are
+    // stabilized
+    const NONCE_SIZE: usize = IV_SIZE;
+
+    /// The length of the tag, in bytes.
+    const TAG_SIZE: usize = TAG_SIZE;
+
+    /// The type used to represent a key.
+    ///
+    /// It can be built from a slice of [`Self::KEY_SIZE`] bytes, such as a derived traffic key.
+    type Key: for<'a> TryFrom<&'a [u8]>;
+
+    /// Creates a new cipher from `key`.
+    fn new(key: Self::Key) -> Self;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::gcm::{Aes128, Aes256, Gcm};
+    use super::{BadData, NewAead};
+
+    /// Builds `A` from a slice the way the record layer would, then round-trips a message.
+    fn round_trip<A: NewAead>(key: &[u8]) {
+        let Ok(key) = A::Key::try_from(&key[..A::KEY_SIZE]) else {
+            panic!("key has the wrong length");
+        };
+        let cipher = A::new(key);
+        let init_vector = [0x5a; 12];
+
+        let mut msg = *b"generic over the cipher suite";
+        let tag = cipher.encrypt_inline(&mut msg, b"header", &init_vector);
+        assert_ne!(&msg, b"generic over the cipher suite");
+        assert_eq!(tag.len(), A::TAG_SIZE);
+
+        let mut opened = [0; 29];
+        assert_eq!(
+            cipher.decrypt(&mut opened, &msg, b"other header", &init_vector, &tag),
+            Err(BadData)
+        );
+        cipher
+            .decrypt_inline(&mut msg, b"header", &init_vector, &tag)
+            .unwrap();
+        assert_eq!(&msg, b"generic over the cipher suite");
+    }
+
+    #[test]
+    fn generic_gcm() {
+        let key: [u8; 32] = core::array::from_fn(|i| i as u8);
+        assert_eq!(Gcm::<Aes128>::KEY_SIZE, 16);
+        assert_eq!(Gcm::<Aes256>::KEY_SIZE, 32);
+        assert_eq!(Gcm::<Aes128>::NONCE_SIZE, 12);
+        round_trip::<Gcm<Aes128>>(&key);
+        round_trip::<Gcm<Aes256>>(&key);
+    }
+}
@@ -94,9 +94,17 @@ fn config_state(key: [u8; 32], nonce: [u8; 12], counter: u32) -> [u32; 16] {
 ///
 /// WARNING: users MUST NOT use the same `nonce`
 /// more than once with the same key
+///
+/// # Panics
+///
+/// The function will panic if the block counter would overflow, which would reuse key stream
 pub fn encrypt_inline(msg: &mut [u8], key: [u8; 32], nonce: [u8; 12], counter: u32) {
     for (index, chunk) in msg.chunks_mut(64).enumerate() {
-        let key_stream = block(key, nonce, counter + index as u32);
+        let block_counter = u32::try_from(index)
+            .ok()
+            .and_then(|index| counter.checked_add(index))
+            .expect("ChaCha20 block counter overflowed");
+        let key_stream = block(key, nonce, block_counter);
         for (chunk_byte, key_stream_byte) in chunk.iter_mut().zip(key_stream.iter()) {
             *chunk_byte ^= key_stream_byte
         }
@@ -107,7 +115,7 @@ pub fn encrypt_inline(msg: &mut [u8], key: [u8; 32], nonce: [u8; 12], counter: u
 ///
 /// # Panics
 ///
-/// The function will panic if `msg.len()` > `buf.len()`
+/// The function will panic if `msg.len()` > `buf.len()`, or if the block counter would overflow
 ///
 /// # Usage notes
 ///
@@ -194,4 +202,40 @@ mod tests {
         super::encrypt_inline(&mut plain_text, key, nonce, counter);
         assert_eq!(plain_text, cipher_text);
     }
+
+    mod differential {
+        extern crate std;
+
+        use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+        use chacha20::ChaCha20;
+        use proptest::collection::vec;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn encrypt(
+                key in any::<[u8; 32]>(),
+                nonce in any::<[u8; 12]>(),
+                // stay far enough from the end of the counter space for the longest message
+                counter in prop_oneof![0u32..4, any::<u32>().prop_map(|n| n % (u32::MAX - 16))],
+                msg in vec(any::<u8>(), 0..600),
+            ) {
+                let mut cipher_text = msg.clone();
+                super::super::encrypt_inline(&mut cipher_text, key, nonce, counter);
+
+                let mut expected = msg.clone();
+                let mut reference = ChaCha20::new(&key.into(), &nonce.into());
+                reference.seek(counter as u64 * 64);
+                reference.apply_keystream(&mut expected);
+                prop_assert_eq!(cipher_text, expected);
+            }
+        }
+
+        #[test]
+        #[should_panic]
+        fn counter_overflow() {
+            let mut msg = [0; 65];
+            super::super::encrypt_inline(&mut msg, [1; 32], [2; 12], u32::MAX);
+        }
+    }
 }
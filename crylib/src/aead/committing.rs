@@ -0,0 +1,281 @@
+//! A key-committing wrapper for any [`Aead`].
+//!
+//! Standard AEADs such as GCM are not key-committing: an attacker can craft a single cipher text
+//! and tag that decrypt successfully under several different keys. Protocols that let an attacker
+//! influence which key is used (e.g. password-based or multi-recipient encryption) can be broken
+//! this way.
+//!
+//! [`CommittingAead`] fixes this by deriving both the AEAD key and a commitment from one master
+//! key with HKDF-SHA256. The commitment is sent alongside the tag and is checked before
+//! decrypting, so a cipher text only ever decrypts under the key it was created with.
+//!
+//! # Examples
+//!
+//! ```
+//! use crylib::aead::committing::CommittingAead;
+//! use crylib::aead::gcm::{Aes256, Gcm};
+//!
+//! let cipher = CommittingAead::new(b"a high-entropy master key", |key| Gcm::<Aes256>::new(*key));
+//!
+//! let init_vector = [0; 12];
+//! let mut msg = *b"Top secret message";
+//! let tag = cipher.encrypt_inline(&mut msg, b"Public information", &init_vector);
+//!
+//! cipher
+//!     .decrypt_inline(&mut msg, b"Public information", &init_vector, &tag)
+//!     .unwrap();
+//! assert_eq!(&msg, b"Top secret message");
+//! ```
+use super::{Aead, BadData, IV_SIZE, TAG_SIZE};
+use crate::ct::ct_eq;
+use crate::hash::Sha256;
+use crate::hkdf;
+
+/// The size of the key commitment.
+pub const COMMITMENT_SIZE: usize = Sha256::HASH_SIZE;
+
+/// The size of a [`CommittingAead`] tag: the underlying AEAD's tag followed by the commitment.
+pub const COMMITTING_TAG_SIZE: usize = TAG_SIZE + COMMITMENT_SIZE;
+
+const ENCRYPTION_KEY_INFO: &[u8] = b"crylib committing aead encryption key";
+const COMMITMENT_INFO: &[u8] = b"crylib committing aead commitment";
+
+/// An [`Aead`] whose tags commit to the key.
+pub struct CommittingAead<A: Aead> {
+    aead: A,
+    commitment: [u8; COMMITMENT_SIZE],
+}
+
+impl<A: Aead> CommittingAead<A> {
+    /// Creates a new cipher from `key`.
+    ///
+    /// `init` constructs the underlying AEAD from a 32-byte key derived from `key`; it should use
+    /// as much of that key as the AEAD accepts. `key` itself is never passed to the AEAD.
+    pub fn new(key: &[u8], init: impl FnOnce(&[u8; 32]) -> A) -> Self {
+        let pr_key =
+            hkdf::extract::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>(&[], key);
+        let encryption_key =
+            hkdf::expand::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, 32, Sha256>(
+                &pr_key,
+                ENCRYPTION_KEY_INFO,
+            );
+        let commitment =
+            hkdf::expand::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, COMMITMENT_SIZE, Sha256>(
+                &pr_key,
+                COMMITMENT_INFO,
+            );
+
+        Self {
+            aead: init(&encryption_key),
+            commitment,
+        }
+    }
+
+    /// Encrypts `msg` inline, returning the underlying tag followed by the key commitment.
+    ///
+    /// WARNING: for security purposes,
+    /// users MUST NOT use the same `init_vector` twice for the same key.
+    pub fn encrypt_inline(
+        &self,
+        msg: &mut [u8],
+        add_data: &[u8],
+        init_vector: &[u8; IV_SIZE],
+    ) -> [u8; COMMITTING_TAG_SIZE] {
+        let tag = self.aead.encrypt_inline(msg, add_data, init_vector);
+        self.committing_tag(tag)
+    }
+
+    /// Encrypts `msg`, writing the encrypted msg to `buf` and returning the underlying tag
+    /// followed by the key commitment.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if `msg.len()` > `buf.len()`
+    pub fn encrypt(
+        &self,
+        buf: &mut [u8],
+        msg: &[u8],
+        add_data: &[u8],
+        init_vector: &[u8; IV_SIZE],
+    ) -> [u8; COMMITTING_TAG_SIZE] {
+        let tag = self.aead.encrypt(buf, msg, add_data, init_vector);
+        self.committing_tag(tag)
+    }
+
+    /// Decrypts `msg` in place.
+    ///
+    /// The commitment is checked before anything is decrypted. If either it or the underlying tag
+    /// doesn't match, `msg` is zeroed and [`BadData`] is returned.
+    pub fn decrypt_inline(
+        &self,
+        msg: &mut [u8],
+        add_data: &[u8],
+        init_vector: &[u8; IV_SIZE],
+        tag: &[u8; COMMITTING_TAG_SIZE],
+    ) -> Result<(), BadData> {
+        let (tag, commitment) = split_tag(tag);
+        if !self.commitment_matches(commitment) {
+            msg.fill(0);
+            return Err(BadData);
+        }
+        self.aead.decrypt_inline(msg, add_data, init_vector, tag)
+    }
+
+    /// Decrypts `cipher_text`, writing the plaintext to `buf`.
+    ///
+    /// If the commitment or the underlying tag doesn't match, the first `cipher_text.len()`
+    /// bytes of `buf` are zeroed and [`BadData`] is returned.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if `cipher_text.len()` > `buf.len()`
+    pub fn decrypt(
+        &self,
+        buf: &mut [u8],
+        cipher_text: &[u8],
+        add_data: &[u8],
+        init_vector: &[u8; IV_SIZE],
+        tag: &[u8; COMMITTING_TAG_SIZE],
+    ) -> Result<(), BadData> {
+        let (tag, commitment) = split_tag(tag);
+        if !self.commitment_matches(commitment) {
+            buf[..cipher_text.len()].fill(0);
+            return Err(BadData);
+        }
+        self.aead
+            .decrypt(buf, cipher_text, add_data, init_vector, tag)
+    }
+
+    fn committing_tag(&self, tag: [u8; TAG_SIZE]) -> [u8; COMMITTING_TAG_SIZE] {
+        let mut committing_tag = [0; COMMITTING_TAG_SIZE];
+        committing_tag[..TAG_SIZE].copy_from_slice(&tag);
+        committing_tag[TAG_SIZE..].copy_from_slice(&self.commitment);
+        committing_tag
+    }
+
+    /// Compares `commitment` to ours in constant time.
+    fn commitment_matches(&self, commitment: &[u8; COMMITMENT_SIZE]) -> bool {
+        ct_eq(&self.commitment, commitment)
+    }
+}
+
+fn split_tag(tag: &[u8; COMMITTING_TAG_SIZE]) -> (&[u8; TAG_SIZE], &[u8; COMMITMENT_SIZE]) {
+    let (tag, commitment) = tag.split_at(TAG_SIZE);
+    // we can safely unwrap because the halves are guaranteed to have lengths of `TAG_SIZE` and
+    // `COMMITMENT_SIZE`
+    (tag.try_into().unwrap(), commitment.try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CommittingAead;
+    use crate::aead::gcm::{Aes128, Aes256, Gcm};
+    use crate::aead::{Aead, BadData, IV_SIZE, TAG_SIZE};
+
+    const INIT_VECTOR: [u8; 12] = [
+        0xca, 0xfe, 0xba, 0xbe, 0xfa, 0xce, 0xdb, 0xad, 0xde, 0xca, 0xf8, 0x88,
+    ];
+
+    fn cipher(key: &[u8]) -> CommittingAead<Gcm<Aes128>> {
+        CommittingAead::new(key, |key| Gcm::<Aes128>::new(key[..16].try_into().unwrap()))
+    }
+
+    #[test]
+    fn round_trip() {
+        let cipher = cipher(b"key");
+        let mut msg = *b"Top secret message";
+        let tag = cipher.encrypt_inline(&mut msg, b"ad", &INIT_VECTOR);
+        assert_ne!(&msg, b"Top secret message");
+
+        let mut buf = [0; 18];
+        cipher
+            .decrypt(&mut buf, &msg, b"ad", &INIT_VECTOR, &tag)
+            .unwrap();
+        assert_eq!(&buf, b"Top secret message");
+
+        cipher
+            .decrypt_inline(&mut msg, b"ad", &INIT_VECTOR, &tag)
+            .unwrap();
+        assert_eq!(&msg, b"Top secret message");
+    }
+
+    /// An AEAD that accepts every tag, i.e. the worst case of a non-committing AEAD.
+    struct AcceptAll;
+
+    impl Aead for AcceptAll {
+        fn encrypt_inline(&self, _: &mut [u8], _: &[u8], _: &[u8; IV_SIZE]) -> [u8; TAG_SIZE] {
+            [0; TAG_SIZE]
+        }
+
+        fn encrypt(
+            &self,
+            buf: &mut [u8],
+            msg: &[u8],
+            _: &[u8],
+            _: &[u8; IV_SIZE],
+        ) -> [u8; TAG_SIZE] {
+            buf[..msg.len()].copy_from_slice(msg);
+            [0; TAG_SIZE]
+        }
+
+        fn decrypt_inline(
+            &self,
+            _: &mut [u8],
+            _: &[u8],
+            _: &[u8; IV_SIZE],
+            _: &[u8; TAG_SIZE],
+        ) -> Result<(), BadData> {
+            Ok(())
+        }
+
+        fn decrypt(
+            &self,
+            buf: &mut [u8],
+            cipher_text: &[u8],
+            _: &[u8],
+            _: &[u8; IV_SIZE],
+            _: &[u8; TAG_SIZE],
+        ) -> Result<(), BadData> {
+            buf[..cipher_text.len()].copy_from_slice(cipher_text);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn wrong_key() {
+        let cipher = CommittingAead::new(b"key", |_| AcceptAll);
+        let other = CommittingAead::new(b"other key", |_| AcceptAll);
+
+        let mut msg = *b"Top secret message";
+        let tag = cipher.encrypt_inline(&mut msg, b"ad", &INIT_VECTOR);
+        assert_eq!(
+            other.decrypt_inline(&mut msg, b"ad", &INIT_VECTOR, &tag),
+            Err(BadData)
+        );
+        assert_eq!(msg, [0; 18]);
+
+        let cipher = CommittingAead::new(b"key", |key| Gcm::<Aes256>::new(*key));
+        let other = CommittingAead::new(b"other key", |key| Gcm::<Aes256>::new(*key));
+        let mut msg = *b"Top secret message";
+        let tag = cipher.encrypt_inline(&mut msg, b"ad", &INIT_VECTOR);
+        assert_eq!(
+            other.decrypt_inline(&mut msg, b"ad", &INIT_VECTOR, &tag),
+            Err(BadData)
+        );
+    }
+
+    #[test]
+    fn bad_commitment() {
+        let cipher = cipher(b"key");
+        let mut msg = *b"Top secret message";
+        let mut tag = cipher.encrypt_inline(&mut msg, b"ad", &INIT_VECTOR);
+        *tag.last_mut().unwrap() ^= 1;
+
+        let mut buf = [0xff; 18];
+        assert_eq!(
+            cipher.decrypt(&mut buf, &msg, b"ad", &INIT_VECTOR, &tag),
+            Err(BadData)
+        );
+        assert_eq!(buf, [0; 18]);
+    }
+}
@@ -51,12 +51,16 @@
 //!
 //! [`Gallois/Counter Mode`]: https://en.wikipedia.org/wiki/Galois/Counter_Mode
 mod aes;
+#[cfg(target_arch = "x86_64")]
+mod cpu;
+mod ghash;
 pub use aes::*;
 
 use crate::aead::{BadData, IV_SIZE, TAG_SIZE};
+use crate::ct::ct_eq;
 
-use super::Aead;
-const R: u128 = 0xe1 << 120;
+use super::{Aead, NewAead};
+use ghash::HashKey;
 
 /// A type that allows for authenticated
 /// encryption and decryption in GCM via AES.
@@ -64,7 +68,20 @@ const R: u128 = 0xe1 << 120;
 /// See [`Gcm`]'s implementations for examples.
 pub struct Gcm<C: aes::AesCipher> {
     cipher: C,
-    h: u128,
+    h: HashKey,
+}
+
+impl<C: aes::AesCipher> NewAead for Gcm<C>
+where
+    C::Key: for<'a> TryFrom<&'a [u8]>,
+{
+    const KEY_SIZE: usize = C::KEY_SIZE;
+
+    type Key = C::Key;
+
+    fn new(key: Self::Key) -> Self {
+        Gcm::new(key)
+    }
 }
 
 impl<C: aes::AesCipher> Aead for Gcm<C> {
@@ -74,14 +91,9 @@ impl<C: aes::AesCipher> Aead for Gcm<C> {
         add_data: &[u8],
         init_vector: &[u8; IV_SIZE],
     ) -> [u8; TAG_SIZE] {
-        let counter = {
-            let mut counter = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
-            counter[..init_vector.len()].copy_from_slice(init_vector);
-            counter
-        };
-        self.xor_bit_stream(msg, &counter);
-
-        self.g_hash(msg, add_data, &counter)
+        let mut encryptor = self.encryptor(add_data, init_vector);
+        encryptor.update(msg);
+        encryptor.finalize()
     }
 
     /// Encrypts `msg`, writing the encrypted msg to `buf` and returning an authentication tag
@@ -111,16 +123,13 @@ impl<C: aes::AesCipher> Aead for Gcm<C> {
         init_vector: &[u8; IV_SIZE],
         tag: &[u8; TAG_SIZE],
     ) -> Result<(), BadData> {
-        let counter = {
-            let mut counter = [0; aes::BLOCK_SIZE];
-            counter[aes::BLOCK_SIZE - 1] = 1;
-            counter[..init_vector.len()].copy_from_slice(init_vector);
-            counter
-        };
-        if self.g_hash(msg, add_data, &counter) != *tag {
+        let mut decryptor = self.decryptor(add_data, init_vector);
+        decryptor.update(msg);
+        if decryptor.finalize(tag).is_err() {
+            // don't let callers mistake the unauthenticated data for plaintext
+            msg.fill(0);
             return Err(BadData);
         }
-        self.xor_bit_stream(msg, &counter);
         Ok(())
     }
 
@@ -152,119 +161,285 @@ impl<C: aes::AesCipher> Gcm<C> {
 
         Self {
             cipher,
-            h: u128::from_be_bytes(h),
+            h: HashKey::new(u128::from_be_bytes(h)),
+        }
+    }
+
+    /// Starts encrypting a message that is supplied in pieces.
+    ///
+    /// See [`GcmEncryptor`] for details.
+    ///
+    /// WARNING: for security purposes,
+    /// users MUST NOT use the same `init_vector` twice for the same key.
+    pub fn encryptor(&self, add_data: &[u8], init_vector: &[u8; IV_SIZE]) -> GcmEncryptor<'_, C> {
+        GcmEncryptor {
+            state: GcmState::new(self, add_data, &initial_counter(init_vector)),
         }
     }
 
-    /// Encrypts or decrypts `data` in counter mode.
+    /// Starts decrypting a message that is supplied in pieces.
     ///
-    /// Because XOR is its own inverse,
-    /// the same operation can be used for encryption and decryption
+    /// See [`GcmDecryptor`] for details.
+    pub fn decryptor(&self, add_data: &[u8], init_vector: &[u8; IV_SIZE]) -> GcmDecryptor<'_, C> {
+        GcmDecryptor {
+            state: GcmState::new(self, add_data, &initial_counter(init_vector)),
+        }
+    }
+}
+
+/// Encrypts a message one piece at a time.
+///
+/// The pieces may have any length; the cipher text and tag are the same as encrypting the whole
+/// message at once with [`Aead::encrypt_inline`].
+///
+/// # Examples
+///
+/// ```
+/// use crylib::aead::gcm::{Aes128, Gcm};
+/// use crylib::aead::Aead;
+///
+/// let cipher = Gcm::<Aes128>::new([0x42; 16]);
+/// let init_vector = [0; 12];
+///
+/// let mut msg = *b"a message that arrives in several pieces";
+/// let mut encryptor = cipher.encryptor(b"Public information", &init_vector);
+/// for chunk in msg.chunks_mut(7) {
+///     encryptor.update(chunk);
+/// }
+/// let tag = encryptor.finalize();
+///
+/// let mut expected = *b"a message that arrives in several pieces";
+/// let expected_tag = cipher.encrypt_inline(&mut expected, b"Public information", &init_vector);
+/// assert_eq!(msg, expected);
+/// assert_eq!(tag, expected_tag);
+/// ```
+pub struct GcmEncryptor<'a, C: aes::AesCipher> {
+    state: GcmState<'a, C>,
+}
+
+impl<C: aes::AesCipher> GcmEncryptor<'_, C> {
+    /// Encrypts the next piece of the message in place.
     ///
-    /// This is a linear operation.
+    /// # Panics
     ///
-    /// This process can be parallel-ized,
-    /// but that has not been implemented yet.
-    fn xor_bit_stream(&self, data: &mut [u8], counter: &[u8; aes::BLOCK_SIZE]) {
-        let iv_as_int = u128::from_be_bytes(*counter);
+    /// The function will panic if the message grows past [`MAX_MESSAGE_LEN`] bytes.
+    pub fn update(&mut self, chunk: &mut [u8]) {
+        self.state.update(chunk, Direction::Encrypt);
+    }
 
-        for (counter, block) in data.chunks_mut(aes::BLOCK_SIZE).enumerate() {
-            let mut stream = (iv_as_int + 1 + counter as u128).to_be_bytes();
-            self.cipher.encrypt_inline(&mut stream);
+    /// Returns the authentication tag of everything passed to [`GcmEncryptor::update`].
+    pub fn finalize(self) -> [u8; TAG_SIZE] {
+        self.state.finish()
+    }
+}
 
-            for (data_byte, stream_byte) in block.iter_mut().zip(stream) {
-                *data_byte ^= stream_byte;
-            }
+/// Decrypts a message one piece at a time.
+///
+/// <div class="warning">
+/// WARNING: the plaintext returned by [`GcmDecryptor::update`] has not been authenticated yet.
+/// It MUST NOT be used until [`GcmDecryptor::finalize`] returns `Ok`,
+/// and should be discarded if it doesn't.
+/// </div>
+pub struct GcmDecryptor<'a, C: aes::AesCipher> {
+    state: GcmState<'a, C>,
+}
+
+impl<C: aes::AesCipher> GcmDecryptor<'_, C> {
+    /// Decrypts the next piece of the message in place.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if the message grows past [`MAX_MESSAGE_LEN`] bytes.
+    pub fn update(&mut self, chunk: &mut [u8]) {
+        self.state.update(chunk, Direction::Decrypt);
+    }
+
+    /// Checks `tag` against everything passed to [`GcmDecryptor::update`].
+    ///
+    /// Returns [`BadData`] if the message has been modified.
+    pub fn finalize(self, tag: &[u8; TAG_SIZE]) -> Result<(), BadData> {
+        match ct_eq(&self.state.finish(), tag) {
+            true => Ok(()),
+            false => Err(BadData),
         }
     }
+}
 
-    /// produce an authentication tag for given data
-    /// this tag can be used to verify the authenticity of the data
-    fn g_hash(
-        &self,
-        cipher_text: &[u8],
-        add_data: &[u8],
-        counter: &[u8; aes::BLOCK_SIZE],
-    ) -> [u8; aes::BLOCK_SIZE] {
+/// The counter-mode and GHASH state shared by [`GcmEncryptor`] and [`GcmDecryptor`].
+///
+/// Each block is hashed right next to being encrypted or decrypted,
+/// so data is only walked once, which keeps large messages in cache.
+///
+/// Whole blocks are processed [`BATCH_BLOCKS`] at a time, so their key stream can be generated
+/// together with [`AesCipher::encrypt_blocks`](aes::AesCipher::encrypt_blocks).
+struct GcmState<'a, C: aes::AesCipher> {
+    gcm: &'a Gcm<C>,
+    counter: [u8; aes::BLOCK_SIZE],
+    tag: u128,
+    add_len: u64,
+    len: u64,
+    /// The key stream of the block that `len` points into.
+    key_stream: [u8; aes::BLOCK_SIZE],
+    /// The cipher text of the block that `len` points into, collected for GHASH.
+    cipher_text: [u8; aes::BLOCK_SIZE],
+}
+
+impl<'a, C: aes::AesCipher> GcmState<'a, C> {
+    fn new(gcm: &'a Gcm<C>, add_data: &[u8], counter: &[u8; aes::BLOCK_SIZE]) -> Self {
         let mut tag = 0;
+        for block in add_data.chunks(aes::BLOCK_SIZE) {
+            add_block(&mut tag, block, &gcm.h);
+        }
+        Self {
+            gcm,
+            counter: *counter,
+            tag,
+            add_len: add_data.len() as u64,
+            len: 0,
+            key_stream: [0; aes::BLOCK_SIZE],
+            cipher_text: [0; aes::BLOCK_SIZE],
+        }
+    }
 
-        // TODO: use `array_chunks` once stabilized
-        let chunks = add_data.chunks_exact(aes::BLOCK_SIZE);
-
-        let last_block = {
-            let remainder = chunks.remainder();
-            // TODO: consider using uninitialized array
-            let mut last_block = [0; aes::BLOCK_SIZE];
-            last_block[..remainder.len()].copy_from_slice(remainder);
-            last_block
-        };
-
-        for block in chunks {
-            // we can safely unwrap because `block` is guaranteed to have a length of
-            // `aes_core::BLOCK_SIZE`
-            add_block(&mut tag, block.try_into().unwrap(), self.h);
+    /// Encrypts or decrypts `data` in counter mode, adding the cipher text to the tag.
+    fn update(&mut self, mut data: &mut [u8], direction: Direction) {
+        // past the limit, the 32-bit block counter would wrap and reuse key stream
+        assert!(
+            self.len
+                .checked_add(data.len() as u64)
+                .is_some_and(|len| len <= MAX_MESSAGE_LEN),
+            "GCM messages can't be longer than 2^32 - 2 blocks"
+        );
+        let mut offset = self.len as usize % aes::BLOCK_SIZE;
+        while !data.is_empty() {
+            if offset == 0 && data.len() >= BATCH_BLOCKS * aes::BLOCK_SIZE {
+                let (batch, rest) =
+                    core::mem::take(&mut data).split_at_mut(BATCH_BLOCKS * aes::BLOCK_SIZE);
+                self.update_batch(batch, direction);
+                data = rest;
+                continue;
+            }
+            if offset == 0 {
+                self.key_stream = self.counter_block(self.len / aes::BLOCK_SIZE as u64);
+                self.gcm.cipher.encrypt_inline(&mut self.key_stream);
+            }
+            let len = data.len().min(aes::BLOCK_SIZE - offset);
+            let (block, rest) = core::mem::take(&mut data).split_at_mut(len);
+            let cipher_text = &mut self.cipher_text[offset..offset + len];
+
+            // the tag always authenticates the cipher text
+            if direction == Direction::Decrypt {
+                cipher_text.copy_from_slice(block);
+            }
+            for (data_byte, stream_byte) in block.iter_mut().zip(&self.key_stream[offset..]) {
+                *data_byte ^= stream_byte;
+            }
+            if direction == Direction::Encrypt {
+                cipher_text.copy_from_slice(block);
+            }
+
+            self.len += len as u64;
+            offset = (offset + len) % aes::BLOCK_SIZE;
+            if offset == 0 {
+                add_block(&mut self.tag, &self.cipher_text, &self.gcm.h);
+            }
+            data = rest;
         }
+    }
 
-        add_block(&mut tag, last_block, self.h);
+    /// Encrypts or decrypts [`BATCH_BLOCKS`] whole blocks, starting on a block boundary.
+    fn update_batch(&mut self, batch: &mut [u8], direction: Direction) {
+        let first_block = self.len / aes::BLOCK_SIZE as u64;
+        let mut key_stream = [[0; aes::BLOCK_SIZE]; BATCH_BLOCKS];
+        for (block_num, block) in (first_block..).zip(key_stream.iter_mut()) {
+            *block = self.counter_block(block_num);
+        }
+        self.gcm.cipher.encrypt_blocks(&mut key_stream);
 
         // TODO: use `array_chunks` once stabilized
-        let chunks = cipher_text.chunks_exact(aes::BLOCK_SIZE);
-
-        let last_block = {
-            let remainder = chunks.remainder();
-            // TODO: consider using uninitialized array
-            let mut last_block = [0; aes::BLOCK_SIZE];
-            last_block[..remainder.len()].copy_from_slice(remainder);
-            last_block
-        };
-
-        for block in chunks {
-            // we can safely unwrap because `block` is guaranteed to have a length of
-            // `aes_core::BLOCK_SIZE`
-            add_block(&mut tag, block.try_into().unwrap(), self.h);
+        for (block, stream) in batch.chunks_exact_mut(aes::BLOCK_SIZE).zip(&key_stream) {
+            if direction == Direction::Decrypt {
+                add_block(&mut self.tag, block, &self.gcm.h);
+            }
+            for (data_byte, stream_byte) in block.iter_mut().zip(stream) {
+                *data_byte ^= stream_byte;
+            }
+            if direction == Direction::Encrypt {
+                add_block(&mut self.tag, block, &self.gcm.h);
+            }
         }
+        self.len += batch.len() as u64;
+    }
+
+    /// Returns the counter block for block `block_num` of the message.
+    ///
+    /// Like GCM's `inc32`, this only counts in the last 32 bits of the block, which wrap around
+    /// without carrying into the rest.
+    fn counter_block(&self, block_num: u64) -> [u8; aes::BLOCK_SIZE] {
+        let mut block = self.counter;
+        let (_, low) = block.split_at_mut(aes::BLOCK_SIZE - 4);
+        // we can safely unwrap because `low` is guaranteed to have a length of 4
+        let count = u32::from_be_bytes((&*low).try_into().unwrap());
+        // truncating `block_num` is the same as counting modulo 2^32
+        let count = count.wrapping_add(1).wrapping_add(block_num as u32);
+        low.copy_from_slice(&count.to_be_bytes());
+        block
+    }
 
-        add_block(&mut tag, last_block, self.h);
+    fn finish(mut self) -> [u8; aes::BLOCK_SIZE] {
+        let offset = self.len as usize % aes::BLOCK_SIZE;
+        if offset != 0 {
+            add_block(&mut self.tag, &self.cipher_text[..offset], &self.gcm.h);
+        }
 
-        tag ^= ((add_data.len() as u128 * 8) << 64) + cipher_text.len() as u128 * 8;
-        tag = gf_2to128_mul(tag, self.h);
+        self.tag ^= ((self.add_len as u128 * 8) << 64) + self.len as u128 * 8;
+        self.tag = self.gcm.h.mul(self.tag);
 
-        let encrypted_iv = u128::from_be_bytes(self.cipher.encrypt(counter));
+        let encrypted_iv = u128::from_be_bytes(self.gcm.cipher.encrypt(&self.counter));
 
-        tag ^= encrypted_iv;
-        tag.to_be_bytes()
+        self.tag ^= encrypted_iv;
+        self.tag.to_be_bytes()
     }
 }
 
-/// Multiplication in GF(2^128)
-fn gf_2to128_mul(a: u128, b: u128) -> u128 {
-    let mut product = 0;
-    let mut temp = a;
-    for i in (0..128).rev() {
-        if b & (1 << i) == 1 << i {
-            product ^= temp;
-        }
-        if temp & 1 == 0 {
-            temp >>= 1;
-        } else {
-            temp = (temp >> 1) ^ R;
-        }
-    }
-    product
+/// The longest message GCM can encrypt or decrypt with one initialization vector, in bytes:
+/// 2^32 - 2 blocks (NIST SP 800-38D section 5.2.1.1).
+pub const MAX_MESSAGE_LEN: u64 = ((1 << 32) - 2) * aes::BLOCK_SIZE as u64;
+
+/// The number of blocks [`GcmState::update`] generates key stream for at once.
+const BATCH_BLOCKS: usize = 8;
+
+/// Whether [`GcmState::update`] is encrypting or decrypting its data.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Encrypt,
+    Decrypt,
+}
+
+/// Returns the pre-counter block for a 96-bit `init_vector`.
+fn initial_counter(init_vector: &[u8; IV_SIZE]) -> [u8; aes::BLOCK_SIZE] {
+    let mut counter = [0; aes::BLOCK_SIZE];
+    counter[aes::BLOCK_SIZE - 1] = 1;
+    counter[..init_vector.len()].copy_from_slice(init_vector);
+    counter
 }
 
-/// A helper function for g_hash()
+/// Adds a block of at most [`aes::BLOCK_SIZE`] bytes to `tag`, zero-padding it if needed.
 #[inline]
-fn add_block(tag: &mut u128, block: [u8; aes::BLOCK_SIZE], h: u128) {
-    *tag ^= u128::from_be_bytes(block);
-    *tag = gf_2to128_mul(*tag, h);
+fn add_block(tag: &mut u128, block: &[u8], h: &HashKey) {
+    // TODO: consider using uninitialized array
+    let mut padded = [0; aes::BLOCK_SIZE];
+    padded[..block.len()].copy_from_slice(block);
+    *tag ^= u128::from_be_bytes(padded);
+    *tag = h.mul(*tag);
 }
 
 #[cfg(test)]
 mod tests {
     use super::aes::Aes128;
     use super::Aead;
-    use super::Gcm;
+    use super::{Direction, Gcm, GcmState, MAX_MESSAGE_LEN};
+    use crate::aead::BadData;
 
     #[test]
     fn ctr_mode() {
@@ -291,10 +466,43 @@ mod tests {
             0x3d, 0x58, 0xe0, 0x91,
         ];
         let cipher = Gcm::<Aes128>::new(key);
-        cipher.xor_bit_stream(&mut plain_text, &counter);
+        GcmState::new(&cipher, &[], &counter).update(&mut plain_text, Direction::Encrypt);
         assert_eq!(plain_text, cipher_text);
     }
 
+    #[test]
+    fn counter_wraps_in_32_bits() {
+        let cipher = Gcm::<Aes128>::new([0x42; 16]);
+        let mut counter = [0xff; 16];
+        counter[11] = 0x12;
+        let state = GcmState::new(&cipher, &[], &counter);
+        let mut expected = [0xff; 16];
+        expected[11] = 0x12;
+        expected[12..].copy_from_slice(&[0, 0, 0, 0]);
+        assert_eq!(state.counter_block(0), expected);
+        expected[12..].copy_from_slice(&[0, 0, 0, 2]);
+        assert_eq!(state.counter_block(2), expected);
+        assert_eq!(state.counter_block(2 + (1 << 32)), expected);
+    }
+
+    #[test]
+    fn longest_message() {
+        let cipher = Gcm::<Aes128>::new([0x42; 16]);
+        let mut state = GcmState::new(&cipher, &[], &[0; 16]);
+        state.len = MAX_MESSAGE_LEN - 16;
+        state.update(&mut [0; 16], Direction::Encrypt);
+        assert_eq!(state.len, MAX_MESSAGE_LEN);
+    }
+
+    #[test]
+    #[should_panic]
+    fn message_too_long() {
+        let cipher = Gcm::<Aes128>::new([0x42; 16]);
+        let mut state = GcmState::new(&cipher, &[], &[0; 16]);
+        state.len = MAX_MESSAGE_LEN - 16;
+        state.update(&mut [0; 17], Direction::Decrypt);
+    }
+
     #[test]
     fn g_hash() {
         let key = [
@@ -326,17 +534,14 @@ mod tests {
         ];
 
         let h = 0xb83b533708bf535d0aa6e52980d53b78;
-        assert_eq!(cipher.h, h);
-
-        assert_eq!(tag, cipher.g_hash(&cipher_text, &add_data, &counter));
-    }
-
-    #[test]
-    fn mul() {
-        let a = 0x66e94bd4ef8a2c3b884cfa59ca342b2e;
-        let b = 0x0388dace60b6a392f328c2b971b2fe78;
-        let product = 0x5e2ec746917062882c85b0685353deb7;
-        assert_eq!(super::gf_2to128_mul(a, b), product);
+        // `x^0`, the multiplicative identity, is the most significant bit
+        assert_eq!(cipher.h.mul(1 << 127), h);
+
+        // decrypting hashes the cipher text before it is overwritten
+        let mut msg = cipher_text;
+        let mut state = GcmState::new(&cipher, &add_data, &counter);
+        state.update(&mut msg, Direction::Decrypt);
+        assert_eq!(tag, state.finish());
     }
 
     #[test]
@@ -421,4 +626,146 @@ mod tests {
             .unwrap();
         assert_eq!(plain_text, cipher_text);
     }
+
+    #[test]
+    fn decrypt_bad_tag() {
+        let key = [
+            0xfe, 0xff, 0xe9, 0x92, 0x86, 0x65, 0x73, 0x1c, 0x6d, 0x6a, 0x8f, 0x94, 0x67, 0x30,
+            0x83, 0x08,
+        ];
+        let cipher = Gcm::<Aes128>::new(key);
+
+        let init_vector = [
+            0xca, 0xfe, 0xba, 0xbe, 0xfa, 0xce, 0xdb, 0xad, 0xde, 0xca, 0xf8, 0x88,
+        ];
+        let mut msg = [0x42; 40];
+        let tag = cipher.encrypt_inline(&mut msg, &[], &init_vector);
+
+        let mut bad_tag = tag;
+        bad_tag[0] ^= 1;
+        let mut cipher_text = msg;
+        assert_eq!(
+            cipher.decrypt_inline(&mut cipher_text, &[], &init_vector, &bad_tag),
+            Err(BadData)
+        );
+        assert_eq!(cipher_text, [0; 40]);
+
+        let mut buf = [0xff; 48];
+        assert_eq!(
+            cipher.decrypt(&mut buf, &msg, &[0x01], &init_vector, &tag),
+            Err(BadData)
+        );
+        assert_eq!(buf[..msg.len()], [0; 40]);
+    }
+
+    mod streaming {
+        extern crate std;
+
+        use super::super::{Aes128, Gcm};
+        use crate::aead::{Aead, BadData};
+        use proptest::collection::vec;
+        use proptest::prelude::*;
+
+        /// Calls `f` on consecutive pieces of `data`, cycling through `sizes`.
+        fn split(mut data: &mut [u8], sizes: &[usize], mut f: impl FnMut(&mut [u8])) {
+            for &size in sizes.iter().cycle() {
+                if data.is_empty() {
+                    break;
+                }
+                let (chunk, rest) = data.split_at_mut(size.min(data.len()));
+                f(chunk);
+                data = rest;
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn matches_one_shot(
+                key in any::<[u8; 16]>(),
+                init_vector in any::<[u8; 12]>(),
+                msg in vec(any::<u8>(), 0..300),
+                add_data in vec(any::<u8>(), 0..64),
+                sizes in vec(1..40usize, 1..20),
+            ) {
+                let cipher = Gcm::<Aes128>::new(key);
+                let mut expected = msg.clone();
+                let expected_tag = cipher.encrypt_inline(&mut expected, &add_data, &init_vector);
+
+                let mut cipher_text = msg.clone();
+                let mut encryptor = cipher.encryptor(&add_data, &init_vector);
+                split(&mut cipher_text, &sizes, |chunk| encryptor.update(chunk));
+                let tag = encryptor.finalize();
+                prop_assert_eq!(&cipher_text, &expected);
+                prop_assert_eq!(tag, expected_tag);
+
+                let mut plain_text = cipher_text.clone();
+                let mut decryptor = cipher.decryptor(&add_data, &init_vector);
+                split(&mut plain_text, &sizes, |chunk| decryptor.update(chunk));
+                prop_assert_eq!(decryptor.finalize(&tag), Ok(()));
+                prop_assert_eq!(&plain_text, &msg);
+
+                let mut bad_tag = tag;
+                bad_tag[0] ^= 1;
+                let mut decryptor = cipher.decryptor(&add_data, &init_vector);
+                split(&mut cipher_text, &sizes, |chunk| decryptor.update(chunk));
+                prop_assert_eq!(decryptor.finalize(&bad_tag), Err(BadData));
+            }
+        }
+    }
+
+    mod differential {
+        extern crate std;
+
+        use super::super::{Aes128, Aes192, Aes256, Gcm};
+        use crate::aead::{Aead, BadData};
+        use aes_gcm::aead::consts::U12;
+        use aes_gcm::aead::{AeadInPlace, KeyInit};
+        use aes_gcm::{Aes128Gcm, Aes256Gcm, AesGcm};
+        use proptest::collection::vec;
+        use proptest::prelude::*;
+
+        type Aes192Gcm = AesGcm<aes::Aes192, U12>;
+
+        macro_rules! differential {
+            ($name:ident, $cipher:ident, $reference:ident, $key_size:literal) => {
+                proptest! {
+                    #[test]
+                    fn $name(
+                        key in any::<[u8; $key_size]>(),
+                        init_vector in any::<[u8; 12]>(),
+                        msg in vec(any::<u8>(), 0..300),
+                        add_data in vec(any::<u8>(), 0..64),
+                    ) {
+                        let cipher = Gcm::<$cipher>::new(key);
+                        let mut cipher_text = msg.clone();
+                        let tag = cipher.encrypt_inline(&mut cipher_text, &add_data, &init_vector);
+
+                        let mut expected = msg.clone();
+                        let expected_tag = $reference::new(&key.into())
+                            .encrypt_in_place_detached(&init_vector.into(), &add_data, &mut expected)
+                            .unwrap();
+                        prop_assert_eq!(&cipher_text, &expected);
+                        prop_assert_eq!(&tag[..], &expected_tag[..]);
+
+                        let mut plain_text = cipher_text.clone();
+                        cipher
+                            .decrypt_inline(&mut plain_text, &add_data, &init_vector, &tag)
+                            .unwrap();
+                        prop_assert_eq!(&plain_text, &msg);
+
+                        let mut bad_tag = tag;
+                        bad_tag[0] ^= 1;
+                        prop_assert_eq!(
+                            cipher.decrypt_inline(&mut cipher_text, &add_data, &init_vector, &bad_tag),
+                            Err(BadData)
+                        );
+                    }
+                }
+            };
+        }
+
+        differential!(aes128, Aes128, Aes128Gcm, 16);
+        differential!(aes192, Aes192, Aes192Gcm, 24);
+        differential!(aes256, Aes256, Aes256Gcm, 32);
+    }
 }
@@ -1,12 +1,16 @@
-//! A software implementation of [`AES`].
+//! An implementation of [`AES`].
 //!
 //! 128, 192, and 256-bit keys are supported.
 //!
-//! Decryption is not yet supported because
-//! we only ever use AES in counter mode (for GCM), which only needs the encryption half.
+//! The AES instructions are used when the CPU has them (AES-NI on x86-64, the Cryptography
+//! Extensions on AArch64); otherwise a portable software implementation is used.
+//! <div class="warning">
+//! WARNING: the software implementation uses lookup tables, so it is not safe against
+//! cache-timing attacks. Use [`hardware_accelerated`] to check which one is in use.
+//! </div>
 //!
-//! Encryption works in 16-byte blocks.
-//! This module only provides one-block encryption.
+//! Encryption and decryption work in 16-byte blocks.
+//! This module only provides one-block encryption and decryption.
 //!
 //! Generally, this module will not be used on its own.
 //! It is paired with a "mode of operation," such as GCM or CBC.
@@ -28,12 +32,22 @@
 //!     0x8c, 0x54, 0x62, 0x38, 0x14,
 //! ];
 //! assert_eq!(plain_text, cipher_text);
+//!
+//! cipher.decrypt_inline(&mut plain_text);
+//! assert_eq!(plain_text, *b"Hello, world!!!!");
 //! ```
 //! [`AES`]: https://en.wikipedia.org/wiki/Advanced_Encryption_Standard
 
+mod hw;
+
 /// The size of a single AES block.
 pub const BLOCK_SIZE: usize = 16;
 
+/// Returns whether AES is done with the CPU's AES instructions instead of in software.
+pub fn hardware_accelerated() -> bool {
+    hw::available()
+}
+
 /// a substitution table for the SBox transformation.
 const S_BOX: [u8; 256] = [
     0x63, 0x7C, 0x77, 0x7B, 0xF2, 0x6B, 0x6F, 0xC5, 0x30, 0x01, 0x67, 0x2B, 0xFE, 0xD7, 0xAB, 0x76,
@@ -54,6 +68,26 @@ const S_BOX: [u8; 256] = [
     0x8C, 0xA1, 0x89, 0x0D, 0xBF, 0xE6, 0x42, 0x68, 0x41, 0x99, 0x2D, 0x0F, 0xB0, 0x54, 0xBB, 0x16,
 ];
 
+/// The inverse of [`S_BOX`], used for decryption.
+const INV_S_BOX: [u8; 256] = [
+    0x52, 0x09, 0x6A, 0xD5, 0x30, 0x36, 0xA5, 0x38, 0xBF, 0x40, 0xA3, 0x9E, 0x81, 0xF3, 0xD7, 0xFB,
+    0x7C, 0xE3, 0x39, 0x82, 0x9B, 0x2F, 0xFF, 0x87, 0x34, 0x8E, 0x43, 0x44, 0xC4, 0xDE, 0xE9, 0xCB,
+    0x54, 0x7B, 0x94, 0x32, 0xA6, 0xC2, 0x23, 0x3D, 0xEE, 0x4C, 0x95, 0x0B, 0x42, 0xFA, 0xC3, 0x4E,
+    0x08, 0x2E, 0xA1, 0x66, 0x28, 0xD9, 0x24, 0xB2, 0x76, 0x5B, 0xA2, 0x49, 0x6D, 0x8B, 0xD1, 0x25,
+    0x72, 0xF8, 0xF6, 0x64, 0x86, 0x68, 0x98, 0x16, 0xD4, 0xA4, 0x5C, 0xCC, 0x5D, 0x65, 0xB6, 0x92,
+    0x6C, 0x70, 0x48, 0x50, 0xFD, 0xED, 0xB9, 0xDA, 0x5E, 0x15, 0x46, 0x57, 0xA7, 0x8D, 0x9D, 0x84,
+    0x90, 0xD8, 0xAB, 0x00, 0x8C, 0xBC, 0xD3, 0x0A, 0xF7, 0xE4, 0x58, 0x05, 0xB8, 0xB3, 0x45, 0x06,
+    0xD0, 0x2C, 0x1E, 0x8F, 0xCA, 0x3F, 0x0F, 0x02, 0xC1, 0xAF, 0xBD, 0x03, 0x01, 0x13, 0x8A, 0x6B,
+    0x3A, 0x91, 0x11, 0x41, 0x4F, 0x67, 0xDC, 0xEA, 0x97, 0xF2, 0xCF, 0xCE, 0xF0, 0xB4, 0xE6, 0x73,
+    0x96, 0xAC, 0x74, 0x22, 0xE7, 0xAD, 0x35, 0x85, 0xE2, 0xF9, 0x37, 0xE8, 0x1C, 0x75, 0xDF, 0x6E,
+    0x47, 0xF1, 0x1A, 0x71, 0x1D, 0x29, 0xC5, 0x89, 0x6F, 0xB7, 0x62, 0x0E, 0xAA, 0x18, 0xBE, 0x1B,
+    0xFC, 0x56, 0x3E, 0x4B, 0xC6, 0xD2, 0x79, 0x20, 0x9A, 0xDB, 0xC0, 0xFE, 0x78, 0xCD, 0x5A, 0xF4,
+    0x1F, 0xDD, 0xA8, 0x33, 0x88, 0x07, 0xC7, 0x31, 0xB1, 0x12, 0x10, 0x59, 0x27, 0x80, 0xEC, 0x5F,
+    0x60, 0x51, 0x7F, 0xA9, 0x19, 0xB5, 0x4A, 0x0D, 0x2D, 0xE5, 0x7A, 0x9F, 0x93, 0xC9, 0x9C, 0xEF,
+    0xA0, 0xE0, 0x3B, 0x4D, 0xAE, 0x2A, 0xF5, 0xB0, 0xC8, 0xEB, 0xBB, 0x3C, 0x83, 0x53, 0x99, 0x61,
+    0x17, 0x2B, 0x04, 0x7E, 0xBA, 0x77, 0xD6, 0x26, 0xE1, 0x69, 0x14, 0x63, 0x55, 0x21, 0x0C, 0x7D,
+];
+
 /// GF(2^8) multiplication table
 ///
 /// If multipling `a * b`, use `a - 1` as `a`
@@ -150,6 +184,7 @@ const R_CON: [u32; 256] = [
 /// cases.
 pub struct Aes128 {
     round_keys: [[u8; BLOCK_SIZE]; Self::NUM_ROUNDS + 1],
+    hardware: bool,
 }
 
 /// AES encryption with a 192-bit key.
@@ -157,6 +192,7 @@ pub struct Aes128 {
 /// This is the least-commonly used mode.
 pub struct Aes192 {
     round_keys: [[u8; BLOCK_SIZE]; Self::NUM_ROUNDS + 1],
+    hardware: bool,
 }
 
 /// AES encryption with a 256-bit key.
@@ -164,9 +200,17 @@ pub struct Aes192 {
 /// This is useful when security is of utmost importance, even at the cost of performance.
 pub struct Aes256 {
     round_keys: [[u8; BLOCK_SIZE]; Self::NUM_ROUNDS + 1],
+    hardware: bool,
 }
 
 /// A common interface for AES ciphers.
+///
+/// These methods are the raw block cipher, for implementing modes of operation on top of AES.
+/// <div class="warning">
+/// WARNING: the raw block cipher is not semantically secure on its own.
+/// Equal blocks encrypt to equal blocks under the same key, so it must not be used to encrypt
+/// messages directly. Use a mode such as [`Gcm`](super::Gcm) instead.
+/// </div>
 pub trait AesCipher {
     /// The length of the key, in bytes.
     ///
@@ -197,6 +241,26 @@ pub trait AesCipher {
         buffer
     }
 
+    /// Encrypts each of `blocks` inline.
+    ///
+    /// When the blocks don't depend on each other, as in counter mode, this can be much faster
+    /// than encrypting them one at a time.
+    fn encrypt_blocks(&self, blocks: &mut [[u8; BLOCK_SIZE]]) {
+        for block in blocks {
+            self.encrypt_inline(block);
+        }
+    }
+
+    /// Decrypts `block` inline, mutating `block`.
+    fn decrypt_inline(&self, block: &mut [u8; BLOCK_SIZE]);
+
+    /// Copies `block` into a new buffer and decrypts the buffer
+    fn decrypt(&self, block: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        let mut buffer = *block;
+        self.decrypt_inline(&mut buffer);
+        buffer
+    }
+
     /// Create a new cipher using `key`.
     fn new(key: Self::Key) -> Self;
 }
@@ -243,6 +307,11 @@ macro_rules! impl_aes_cipher {
             type Key = [u8; Self::KEY_SIZE];
 
             fn encrypt_inline(&self, block: &mut [u8; BLOCK_SIZE]) {
+                if self.hardware {
+                    // SAFETY: `hardware` is only set if `hw::available()` returned `true`
+                    unsafe { hw::encrypt(&self.round_keys, block) };
+                    return;
+                }
                 add_round_key(block, self.round_keys[0]);
                 for round_key in self.round_keys[1..self.round_keys.len() - 1].iter() {
                     sub_bytes(block);
@@ -255,9 +324,39 @@ macro_rules! impl_aes_cipher {
                 add_round_key(block, self.round_keys[Self::NUM_ROUNDS]);
             }
 
+            fn encrypt_blocks(&self, blocks: &mut [[u8; BLOCK_SIZE]]) {
+                if self.hardware {
+                    // SAFETY: `hardware` is only set if `hw::available()` returned `true`
+                    unsafe { hw::encrypt_blocks(&self.round_keys, blocks) };
+                    return;
+                }
+                for block in blocks {
+                    self.encrypt_inline(block);
+                }
+            }
+
+            fn decrypt_inline(&self, block: &mut [u8; BLOCK_SIZE]) {
+                if self.hardware {
+                    // SAFETY: `hardware` is only set if `hw::available()` returned `true`
+                    unsafe { hw::decrypt(&self.round_keys, block) };
+                    return;
+                }
+                add_round_key(block, self.round_keys[Self::NUM_ROUNDS]);
+                for round_key in self.round_keys[1..self.round_keys.len() - 1].iter().rev() {
+                    inv_shift_rows(block);
+                    inv_sub_bytes(block);
+                    add_round_key(block, *round_key);
+                    inv_mix_columns(block);
+                }
+                inv_shift_rows(block);
+                inv_sub_bytes(block);
+                add_round_key(block, self.round_keys[0]);
+            }
+
             fn new(key: Self::Key) -> Self {
                 Self {
                     round_keys: Self::expand_key(key),
+                    hardware: hw::available(),
                 }
             }
         }
@@ -318,6 +417,64 @@ fn mix_columns(state: &mut [u8; BLOCK_SIZE]) {
     }
 }
 
+#[inline]
+fn inv_sub_bytes(state: &mut [u8; BLOCK_SIZE]) {
+    for byte in state {
+        *byte = INV_S_BOX[*byte as usize];
+    }
+}
+
+#[inline]
+fn inv_shift_rows(state: &mut [u8; BLOCK_SIZE]) {
+    // TODO: consider using uninitialized array
+    let mut auxiliary = [0; 4];
+    for row in 0..4 {
+        for col in 0..4 {
+            auxiliary[col] = state[col * 4 + row];
+        }
+        for col in 0..4 {
+            state[col * 4 + row] = auxiliary[(col + 4 - row) % 4];
+        }
+    }
+}
+
+#[inline]
+fn inv_mix_columns(state: &mut [u8; BLOCK_SIZE]) {
+    let mult_matrix = [
+        [0x0e, 0x0b, 0x0d, 0x09],
+        [0x09, 0x0e, 0x0b, 0x0d],
+        [0x0d, 0x09, 0x0e, 0x0b],
+        [0x0b, 0x0d, 0x09, 0x0e],
+    ];
+    // TODO: use `array_chunks` once stabilized
+    for col in state.chunks_exact_mut(4) {
+        let auxiliary: [u8; 4] = col.try_into().unwrap();
+
+        for row in 0..4 {
+            let mut byte = 0;
+            for i in 0..4 {
+                byte ^= gf_mul(auxiliary[i], mult_matrix[row][i]);
+            }
+            col[row] = byte;
+        }
+    }
+}
+
+/// Multiplication in GF(2^8)
+///
+/// The loop only depends on `b`, which is always a constant from the multiplication matrix,
+/// and `a` never affects control flow.
+#[inline]
+const fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        product ^= a & (b & 1).wrapping_neg();
+        a = (a << 1) ^ (0x1b & (a >> 7).wrapping_neg());
+        b >>= 1;
+    }
+    product
+}
+
 #[inline]
 const fn s_box(byte: u8) -> u8 {
     S_BOX[byte as usize]
@@ -338,7 +495,7 @@ const fn rotate_word(word: u32) -> u32 {
 
 #[cfg(test)]
 mod tests {
-    use super::{Aes128, Aes256, AesCipher, BLOCK_SIZE};
+    use super::{Aes128, Aes192, Aes256, AesCipher, BLOCK_SIZE};
 
     #[test]
     fn add_round_key() {
@@ -559,4 +716,122 @@ mod tests {
         cipher.encrypt_inline(&mut plain_text);
         assert_eq!(plain_text, cipher_text);
     }
+
+    #[test]
+    fn inverse_steps() {
+        let state: [u8; 16] = [
+            0x19, 0x3d, 0xe3, 0xbe, 0xa0, 0xf4, 0xe2, 0x2b, 0x9a, 0xc6, 0x8d, 0x2a, 0xe9, 0xf8,
+            0x48, 0x08,
+        ];
+
+        let mut output_state = state;
+        super::sub_bytes(&mut output_state);
+        super::inv_sub_bytes(&mut output_state);
+        assert_eq!(output_state, state);
+
+        super::shift_rows(&mut output_state);
+        super::inv_shift_rows(&mut output_state);
+        assert_eq!(output_state, state);
+
+        super::mix_columns(&mut output_state);
+        super::inv_mix_columns(&mut output_state);
+        assert_eq!(output_state, state);
+    }
+
+    #[test]
+    fn decrypt_128() {
+        let plain_text = [
+            0x32, 0x43, 0xf6, 0xa8, 0x88, 0x5a, 0x30, 0x8d, 0x31, 0x31, 0x98, 0xa2, 0xe0, 0x37,
+            0x07, 0x34,
+        ];
+        let key = [
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf,
+            0x4f, 0x3c,
+        ];
+        let mut cipher_text = [
+            0x39, 0x25, 0x84, 0x1d, 0x02, 0xdc, 0x09, 0xfb, 0xdc, 0x11, 0x85, 0x97, 0x19, 0x6a,
+            0x0b, 0x32,
+        ];
+        let cipher = Aes128::new(key);
+        cipher.decrypt_inline(&mut cipher_text);
+        assert_eq!(cipher_text, plain_text);
+    }
+
+    #[test]
+    fn decrypt_192() {
+        // test vector from FIPS 197, appendix C.2
+        let key = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d,
+            0x0e, 0x0f, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17,
+        ];
+        let plain_text = [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd,
+            0xee, 0xff,
+        ];
+        let cipher_text = [
+            0xdd, 0xa9, 0x7c, 0xa4, 0x86, 0x4c, 0xdf, 0xe0, 0x6e, 0xaf, 0x70, 0xa0, 0xec, 0x0d,
+            0x71, 0x91,
+        ];
+        let cipher = Aes192::new(key);
+        assert_eq!(cipher.encrypt(&plain_text), cipher_text);
+        assert_eq!(cipher.decrypt(&cipher_text), plain_text);
+    }
+
+    #[test]
+    fn decrypt_256() {
+        let key = [0u8; 32];
+        let plain_text: [u8; 16] = [0xff; 16];
+        let mut cipher_text: [u8; 16] = [
+            0xac, 0xda, 0xce, 0x80, 0x78, 0xa3, 0x2b, 0x1a, 0x18, 0x2b, 0xfa, 0x49, 0x87, 0xca,
+            0x13, 0x47,
+        ];
+        let cipher = Aes256::new(key);
+        cipher.decrypt_inline(&mut cipher_text);
+        assert_eq!(cipher_text, plain_text);
+    }
+
+    mod hardware {
+        extern crate std;
+
+        use super::super::hw;
+        use super::{Aes128, Aes192, Aes256, AesCipher};
+        use proptest::collection::vec;
+        use proptest::prelude::*;
+
+        macro_rules! matches_software {
+            ($name:ident, $cipher:ident) => {
+                proptest! {
+                    #[test]
+                    fn $name(
+                        key in any::<[u8; $cipher::KEY_SIZE]>(),
+                        block in any::<[u8; 16]>(),
+                        blocks in vec(any::<[u8; 16]>(), 0..20),
+                    ) {
+                        prop_assume!(hw::available());
+                        let hardware = $cipher::new(key);
+                        prop_assert!(hardware.hardware);
+                        let software = $cipher {
+                            round_keys: $cipher::expand_key(key),
+                            hardware: false,
+                        };
+
+                        let cipher_text = hardware.encrypt(&block);
+                        prop_assert_eq!(cipher_text, software.encrypt(&block));
+                        prop_assert_eq!(hardware.decrypt(&block), software.decrypt(&block));
+                        prop_assert_eq!(hardware.decrypt(&cipher_text), block);
+
+                        let mut encrypted = blocks.clone();
+                        hardware.encrypt_blocks(&mut encrypted);
+                        for (encrypted, block) in encrypted.iter().zip(&blocks) {
+                            prop_assert_eq!(*encrypted, software.encrypt(block));
+                        }
+                    }
+                }
+            };
+        }
+
+        matches_software!(aes_128, Aes128);
+        matches_software!(aes_192, Aes192);
+        matches_software!(aes_256, Aes256);
+    }
 }
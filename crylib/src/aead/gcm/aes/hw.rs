@@ -0,0 +1,285 @@
+//! Hardware AES, using AES-NI on x86-64 and the Cryptography Extensions on AArch64.
+//!
+//! Besides being much faster, the AES instructions don't use lookup tables,
+//! so unlike the software implementation they don't leak the key through the cache.
+//!
+//! `crylib` is `no_std`, so it can't ask the operating system what the CPU supports.
+//! On x86-64, `cpuid` is unprivileged, so support is checked at runtime.
+//! AArch64 has no unprivileged equivalent, so the extensions are only used when the `aes` target
+//! feature is enabled at compile time, as it is by default on Apple silicon.
+
+use super::BLOCK_SIZE;
+
+/// The number of blocks [`encrypt_blocks`] works on at once.
+///
+/// Each AES instruction takes several cycles, but a new one can start every cycle,
+/// so interleaving independent blocks keeps the pipeline full.
+#[cfg(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_feature = "aes")
+))]
+const LANES: usize = 8;
+
+/// Returns whether the CPU supports the AES instructions.
+#[cfg(target_arch = "x86_64")]
+pub(super) fn available() -> bool {
+    super::super::cpu::aes_ni()
+}
+
+/// Returns whether the CPU supports the AES instructions.
+#[cfg(all(target_arch = "aarch64", target_feature = "aes"))]
+pub(super) fn available() -> bool {
+    true
+}
+
+/// Returns whether the CPU supports the AES instructions.
+#[cfg(not(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_feature = "aes")
+)))]
+pub(super) fn available() -> bool {
+    false
+}
+
+/// Encrypts `block` with the expanded key `round_keys`.
+///
+/// # Safety
+///
+/// [`available`] must have returned `true`.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "aes")]
+pub(super) unsafe fn encrypt(round_keys: &[[u8; BLOCK_SIZE]], block: &mut [u8; BLOCK_SIZE]) {
+    use core::arch::x86_64::*;
+
+    let [first, middle @ .., last] = round_keys else {
+        unreachable!("AES always has more than one round key");
+    };
+    // SAFETY: every pointer points to 16 readable bytes, and the unaligned loads and stores
+    // don't need any alignment.
+    unsafe {
+        let mut state = _mm_loadu_si128(block.as_ptr().cast());
+        state = _mm_xor_si128(state, _mm_loadu_si128(first.as_ptr().cast()));
+        for round_key in middle {
+            state = _mm_aesenc_si128(state, _mm_loadu_si128(round_key.as_ptr().cast()));
+        }
+        state = _mm_aesenclast_si128(state, _mm_loadu_si128(last.as_ptr().cast()));
+        _mm_storeu_si128(block.as_mut_ptr().cast(), state);
+    }
+}
+
+/// Encrypts each of `blocks` with the expanded key `round_keys`.
+///
+/// # Safety
+///
+/// [`available`] must have returned `true`.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "aes")]
+pub(super) unsafe fn encrypt_blocks(
+    round_keys: &[[u8; BLOCK_SIZE]],
+    blocks: &mut [[u8; BLOCK_SIZE]],
+) {
+    use core::arch::x86_64::*;
+
+    let [first, middle @ .., last] = round_keys else {
+        unreachable!("AES always has more than one round key");
+    };
+    let mut chunks = blocks.chunks_exact_mut(LANES);
+    for chunk in &mut chunks {
+        // SAFETY: every pointer points to 16 readable bytes, and the unaligned loads and stores
+        // don't need any alignment.
+        unsafe {
+            let round_key = _mm_loadu_si128(first.as_ptr().cast());
+            let mut states = [_mm_setzero_si128(); LANES];
+            for (state, block) in states.iter_mut().zip(chunk.iter()) {
+                *state = _mm_xor_si128(_mm_loadu_si128(block.as_ptr().cast()), round_key);
+            }
+            for round_key in middle {
+                let round_key = _mm_loadu_si128(round_key.as_ptr().cast());
+                for state in &mut states {
+                    *state = _mm_aesenc_si128(*state, round_key);
+                }
+            }
+            let round_key = _mm_loadu_si128(last.as_ptr().cast());
+            for (state, block) in states.into_iter().zip(chunk.iter_mut()) {
+                _mm_storeu_si128(
+                    block.as_mut_ptr().cast(),
+                    _mm_aesenclast_si128(state, round_key),
+                );
+            }
+        }
+    }
+    for block in chunks.into_remainder() {
+        // SAFETY: the caller guarantees that the AES instructions are available
+        unsafe { encrypt(round_keys, block) };
+    }
+}
+
+/// Decrypts `block` with the expanded key `round_keys`.
+///
+/// # Safety
+///
+/// [`available`] must have returned `true`.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "aes")]
+pub(super) unsafe fn decrypt(round_keys: &[[u8; BLOCK_SIZE]], block: &mut [u8; BLOCK_SIZE]) {
+    use core::arch::x86_64::*;
+
+    let [first, middle @ .., last] = round_keys else {
+        unreachable!("AES always has more than one round key");
+    };
+    // SAFETY: every pointer points to 16 readable bytes, and the unaligned loads and stores
+    // don't need any alignment.
+    unsafe {
+        let mut state = _mm_loadu_si128(block.as_ptr().cast());
+        state = _mm_xor_si128(state, _mm_loadu_si128(last.as_ptr().cast()));
+        // `aesdec` uses the equivalent inverse cipher, which needs `InvMixColumns` applied to
+        // the middle round keys
+        for round_key in middle.iter().rev() {
+            let round_key = _mm_aesimc_si128(_mm_loadu_si128(round_key.as_ptr().cast()));
+            state = _mm_aesdec_si128(state, round_key);
+        }
+        state = _mm_aesdeclast_si128(state, _mm_loadu_si128(first.as_ptr().cast()));
+        _mm_storeu_si128(block.as_mut_ptr().cast(), state);
+    }
+}
+
+/// Encrypts `block` with the expanded key `round_keys`.
+///
+/// # Safety
+///
+/// [`available`] must have returned `true`.
+#[cfg(all(target_arch = "aarch64", target_feature = "aes"))]
+pub(super) unsafe fn encrypt(round_keys: &[[u8; BLOCK_SIZE]], block: &mut [u8; BLOCK_SIZE]) {
+    use core::arch::aarch64::*;
+
+    let [middle @ .., second_last, last] = round_keys else {
+        unreachable!("AES always has more than one round key");
+    };
+    // SAFETY: every pointer points to 16 readable bytes, and the loads and stores don't need
+    // any alignment.
+    unsafe {
+        let mut state = vld1q_u8(block.as_ptr());
+        // `aese` adds the round key before substituting, so the rounds are offset by one
+        for round_key in middle {
+            state = vaesmcq_u8(vaeseq_u8(state, vld1q_u8(round_key.as_ptr())));
+        }
+        state = vaeseq_u8(state, vld1q_u8(second_last.as_ptr()));
+        state = veorq_u8(state, vld1q_u8(last.as_ptr()));
+        vst1q_u8(block.as_mut_ptr(), state);
+    }
+}
+
+/// Encrypts each of `blocks` with the expanded key `round_keys`.
+///
+/// # Safety
+///
+/// [`available`] must have returned `true`.
+#[cfg(all(target_arch = "aarch64", target_feature = "aes"))]
+pub(super) unsafe fn encrypt_blocks(
+    round_keys: &[[u8; BLOCK_SIZE]],
+    blocks: &mut [[u8; BLOCK_SIZE]],
+) {
+    use core::arch::aarch64::*;
+
+    let [middle @ .., second_last, last] = round_keys else {
+        unreachable!("AES always has more than one round key");
+    };
+    let mut chunks = blocks.chunks_exact_mut(LANES);
+    for chunk in &mut chunks {
+        // SAFETY: every pointer points to 16 readable bytes, and the loads and stores don't need
+        // any alignment.
+        unsafe {
+            let mut states = [vdupq_n_u8(0); LANES];
+            for (state, block) in states.iter_mut().zip(chunk.iter()) {
+                *state = vld1q_u8(block.as_ptr());
+            }
+            for round_key in middle {
+                let round_key = vld1q_u8(round_key.as_ptr());
+                for state in &mut states {
+                    *state = vaesmcq_u8(vaeseq_u8(*state, round_key));
+                }
+            }
+            let second_last = vld1q_u8(second_last.as_ptr());
+            let last = vld1q_u8(last.as_ptr());
+            for (state, block) in states.into_iter().zip(chunk.iter_mut()) {
+                vst1q_u8(
+                    block.as_mut_ptr(),
+                    veorq_u8(vaeseq_u8(state, second_last), last),
+                );
+            }
+        }
+    }
+    for block in chunks.into_remainder() {
+        // SAFETY: the caller guarantees that the AES instructions are available
+        unsafe { encrypt(round_keys, block) };
+    }
+}
+
+/// Decrypts `block` with the expanded key `round_keys`.
+///
+/// # Safety
+///
+/// [`available`] must have returned `true`.
+#[cfg(all(target_arch = "aarch64", target_feature = "aes"))]
+pub(super) unsafe fn decrypt(round_keys: &[[u8; BLOCK_SIZE]], block: &mut [u8; BLOCK_SIZE]) {
+    use core::arch::aarch64::*;
+
+    let [first, middle @ .., last] = round_keys else {
+        unreachable!("AES always has more than one round key");
+    };
+    // SAFETY: every pointer points to 16 readable bytes, and the loads and stores don't need
+    // any alignment.
+    unsafe {
+        let mut state = vaesdq_u8(vld1q_u8(block.as_ptr()), vld1q_u8(last.as_ptr()));
+        // `InvMixColumns` is linear, so applying it to both the state and the round key is the
+        // same as applying it after adding the round key
+        for round_key in middle.iter().rev() {
+            let round_key = vaesimcq_u8(vld1q_u8(round_key.as_ptr()));
+            state = vaesdq_u8(vaesimcq_u8(state), round_key);
+        }
+        state = veorq_u8(state, vld1q_u8(first.as_ptr()));
+        vst1q_u8(block.as_mut_ptr(), state);
+    }
+}
+
+/// Encrypts `block` with the expanded key `round_keys`.
+///
+/// # Safety
+///
+/// [`available`] must have returned `true`, which it never does on this architecture.
+#[cfg(not(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_feature = "aes")
+)))]
+pub(super) unsafe fn encrypt(_round_keys: &[[u8; BLOCK_SIZE]], _block: &mut [u8; BLOCK_SIZE]) {
+    unreachable!("hardware AES isn't available on this architecture");
+}
+
+/// Encrypts each of `blocks` with the expanded key `round_keys`.
+///
+/// # Safety
+///
+/// [`available`] must have returned `true`, which it never does on this architecture.
+#[cfg(not(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_feature = "aes")
+)))]
+pub(super) unsafe fn encrypt_blocks(
+    _round_keys: &[[u8; BLOCK_SIZE]],
+    _blocks: &mut [[u8; BLOCK_SIZE]],
+) {
+    unreachable!("hardware AES isn't available on this architecture");
+}
+
+/// Decrypts `block` with the expanded key `round_keys`.
+///
+/// # Safety
+///
+/// [`available`] must have returned `true`, which it never does on this architecture.
+#[cfg(not(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_feature = "aes")
+)))]
+pub(super) unsafe fn decrypt(_round_keys: &[[u8; BLOCK_SIZE]], _block: &mut [u8; BLOCK_SIZE]) {
+    unreachable!("hardware AES isn't available on this architecture");
+}
@@ -0,0 +1,34 @@
+//! Runtime detection of the x86-64 instructions GCM can use.
+//!
+//! `crylib` is `no_std`, so it can't use `is_x86_feature_detected!`.
+//! `cpuid` is unprivileged, though, so it is queried directly.
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// The AES-NI flag in `ecx` of `cpuid` leaf 1.
+const AES_NI: u32 = 1 << 25;
+/// The PCLMULQDQ flag in `ecx` of `cpuid` leaf 1.
+const PCLMULQDQ: u32 = 1 << 1;
+
+/// Returns whether the CPU supports AES-NI.
+pub(super) fn aes_ni() -> bool {
+    leaf_1_ecx() & AES_NI != 0
+}
+
+/// Returns whether the CPU supports carry-less multiplication.
+pub(super) fn pclmulqdq() -> bool {
+    leaf_1_ecx() & PCLMULQDQ != 0
+}
+
+fn leaf_1_ecx() -> u32 {
+    // `cpuid` can be slow under virtualization, so only run it once.
+    // Bit 32 marks the cached value as valid.
+    static ECX: AtomicU64 = AtomicU64::new(0);
+
+    let cached = ECX.load(Ordering::Relaxed);
+    if cached != 0 {
+        return cached as u32;
+    }
+    let ecx = core::arch::x86_64::__cpuid(1).ecx;
+    ECX.store(ecx as u64 | 1 << 32, Ordering::Relaxed);
+    ecx
+}
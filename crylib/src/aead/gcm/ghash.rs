@@ -0,0 +1,249 @@
+//! Multiplication in GF(2^128), the core of GHASH.
+//!
+//! GCM reflects the bits of its field elements: the most significant bit of a block is the
+//! coefficient of `x^0`, and the least significant is the coefficient of `x^127`.
+//!
+//! Carry-less multiplication is used when the CPU has it (PCLMULQDQ on x86-64, PMULL on
+//! AArch64). Otherwise, Shoup's 4-bit table method is used: 16 multiples of `H` are computed
+//! once per key, and each multiplication then works a nibble at a time.
+//! <div class="warning">
+//! WARNING: like software AES, the table method indexes memory with secret data,
+//! so it is not safe against cache-timing attacks.
+//! </div>
+
+/// The reduction polynomial, `x^128 = x^7 + x^2 + x + 1`, in GCM's bit order.
+const R: u128 = 0xe1 << 120;
+
+/// The reductions of each 4-bit overflow out of the bottom of a [`HashKey::mul_table`] step.
+const REDUCTIONS: [u128; 16] = {
+    let mut reductions = [0; 16];
+    let mut overflow = 0;
+    while overflow < 16 {
+        let mut bit = 0;
+        while bit < 4 {
+            // the bit `bit` places from the bottom was the coefficient of `x^(128 + 3 - bit)`
+            if overflow & (1 << bit) != 0 {
+                reductions[overflow] ^= R >> (3 - bit);
+            }
+            bit += 1;
+        }
+        overflow += 1;
+    }
+    reductions
+};
+
+/// The GHASH key `H`, with whatever multiplying by it needs precomputed.
+pub(super) struct HashKey {
+    h: u128,
+    /// `table[n]` is `H` times the 4-bit polynomial `n`.
+    table: [u128; 16],
+    clmul: bool,
+}
+
+impl HashKey {
+    pub(super) fn new(h: u128) -> Self {
+        let mut table = [0; 16];
+        table[8] = h;
+        table[4] = mul_x(table[8]);
+        table[2] = mul_x(table[4]);
+        table[1] = mul_x(table[2]);
+        // every other entry is the sum of its lowest set bit's entry and the rest's
+        for n in 1..table.len() {
+            let lowest_bit = n & n.wrapping_neg();
+            if n != lowest_bit {
+                table[n] = table[lowest_bit] ^ table[n ^ lowest_bit];
+            }
+        }
+
+        Self {
+            h,
+            table,
+            clmul: clmul_available(),
+        }
+    }
+
+    /// Returns `x * H`.
+    #[inline]
+    pub(super) fn mul(&self, x: u128) -> u128 {
+        if self.clmul {
+            // SAFETY: `clmul` is only set if `clmul_available()` returned `true`
+            unsafe { mul_clmul(x, self.h) }
+        } else {
+            self.mul_table(x)
+        }
+    }
+
+    fn mul_table(&self, x: u128) -> u128 {
+        // Horner's method from the highest-degree nibble, which is the least significant one
+        let mut product = 0;
+        for i in 0..u128::BITS / 4 {
+            let overflow = (product & 0xf) as usize;
+            product = (product >> 4) ^ REDUCTIONS[overflow];
+            product ^= self.table[(x >> (4 * i)) as usize & 0xf];
+        }
+        product
+    }
+}
+
+/// Returns `a * x`.
+const fn mul_x(a: u128) -> u128 {
+    (a >> 1) ^ (R & (a & 1).wrapping_neg())
+}
+
+/// Returns `a * b`, given a 64-bit carry-less multiplication.
+#[cfg_attr(
+    not(any(
+        target_arch = "x86_64",
+        all(target_arch = "aarch64", target_feature = "aes")
+    )),
+    allow(dead_code)
+)]
+#[inline(always)]
+fn mul_with(a: u128, b: u128, clmul64: impl Fn(u64, u64) -> u128) -> u128 {
+    let (a_high, a_low) = ((a >> 64) as u64, a as u64);
+    let (b_high, b_low) = ((b >> 64) as u64, b as u64);
+
+    let low = clmul64(a_low, b_low);
+    let high = clmul64(a_high, b_high);
+    let middle = clmul64(a_low, b_high) ^ clmul64(a_high, b_low);
+    let low = low ^ (middle << 64);
+    let high = high ^ (middle >> 64);
+
+    // Multiplying the reflected values gives the reflected 255-bit product, so shifting it left
+    // by one leaves the coefficients of `x^0..x^127` in `high` and `x^128..x^255` in `low`,
+    // both in GCM's bit order.
+    let (product, overflow) = ((high << 1) | (low >> 127), low << 1);
+
+    // `x^128 = x^7 + x^2 + x + 1`, and multiplying by `x^k` shifts right by `k`.
+    // The bits that shift out the bottom are past `x^127` and need reducing once more.
+    let spilled = (overflow << 127) ^ (overflow << 126) ^ (overflow << 121);
+    let overflow = overflow ^ spilled;
+    product ^ overflow ^ (overflow >> 1) ^ (overflow >> 2) ^ (overflow >> 7)
+}
+
+/// Returns whether the CPU supports carry-less multiplication.
+#[cfg(target_arch = "x86_64")]
+fn clmul_available() -> bool {
+    super::cpu::pclmulqdq()
+}
+
+/// Returns whether the CPU supports carry-less multiplication.
+///
+/// PMULL is part of the Cryptography Extensions, which can only be detected at compile time.
+#[cfg(all(target_arch = "aarch64", target_feature = "aes"))]
+fn clmul_available() -> bool {
+    true
+}
+
+/// Returns whether the CPU supports carry-less multiplication.
+#[cfg(not(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_feature = "aes")
+)))]
+fn clmul_available() -> bool {
+    false
+}
+
+/// Returns `a * b` using carry-less multiplication.
+///
+/// # Safety
+///
+/// [`clmul_available`] must have returned `true`.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "pclmulqdq")]
+unsafe fn mul_clmul(a: u128, b: u128) -> u128 {
+    use core::arch::x86_64::*;
+
+    mul_with(a, b, |a, b| {
+        let a = _mm_set_epi64x(0, a as i64);
+        let b = _mm_set_epi64x(0, b as i64);
+        let product = _mm_clmulepi64_si128::<0x00>(a, b);
+        // SAFETY: `__m128i` and `u128` have the same size, and any bit pattern is valid for both
+        unsafe { core::mem::transmute::<__m128i, u128>(product) }
+    })
+}
+
+/// Returns `a * b` using carry-less multiplication.
+///
+/// # Safety
+///
+/// [`clmul_available`] must have returned `true`.
+#[cfg(all(target_arch = "aarch64", target_feature = "aes"))]
+unsafe fn mul_clmul(a: u128, b: u128) -> u128 {
+    mul_with(a, b, |a, b| core::arch::aarch64::vmull_p64(a, b))
+}
+
+/// Returns `a * b` using carry-less multiplication.
+///
+/// # Safety
+///
+/// [`clmul_available`] must have returned `true`, which it never does on this architecture.
+#[cfg(not(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_feature = "aes")
+)))]
+unsafe fn mul_clmul(_a: u128, _b: u128) -> u128 {
+    unreachable!("carry-less multiplication isn't available on this architecture");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mul_x, HashKey, R};
+    use proptest::prelude::*;
+
+    /// Bit-by-bit multiplication, straight from the GCM specification.
+    fn reference(a: u128, b: u128) -> u128 {
+        let mut product = 0;
+        let mut temp = a;
+        for i in (0..128).rev() {
+            if b & (1 << i) != 0 {
+                product ^= temp;
+            }
+            temp = mul_x(temp);
+        }
+        product
+    }
+
+    /// Carry-less multiplication in software, to check [`super::mul_with`] on any CPU.
+    fn clmul64(a: u64, b: u64) -> u128 {
+        (0..64)
+            .filter(|bit| b & (1 << bit) != 0)
+            .fold(0, |product, bit| product ^ (a as u128) << bit)
+    }
+
+    #[test]
+    fn mul() {
+        let a = 0x66e94bd4ef8a2c3b884cfa59ca342b2e;
+        let b = 0x0388dace60b6a392f328c2b971b2fe78;
+        let product = 0x5e2ec746917062882c85b0685353deb7;
+        assert_eq!(reference(a, b), product);
+        assert_eq!(HashKey::new(b).mul(a), product);
+        assert_eq!(HashKey::new(b).mul_table(a), product);
+        assert_eq!(super::mul_with(a, b, clmul64), product);
+    }
+
+    #[test]
+    fn edge_cases() {
+        // `x^0` is the multiplicative identity, and `x^127 * x` wraps around to the reduction
+        let one = 1 << 127;
+        for key in [HashKey::new(one), HashKey::new(1), HashKey::new(R)] {
+            for x in [0, 1, one, R, u128::MAX] {
+                assert_eq!(key.mul(x), reference(x, key.h));
+                assert_eq!(key.mul_table(x), reference(x, key.h));
+            }
+        }
+        assert_eq!(HashKey::new(one).mul(0x1234), 0x1234);
+        assert_eq!(HashKey::new(1 << 126).mul(1), R);
+    }
+
+    proptest! {
+        #[test]
+        fn matches_reference(a in any::<u128>(), b in any::<u128>()) {
+            let expected = reference(a, b);
+            let key = HashKey::new(b);
+            prop_assert_eq!(key.mul(a), expected);
+            prop_assert_eq!(key.mul_table(a), expected);
+            prop_assert_eq!(super::mul_with(a, b, clmul64), expected);
+        }
+    }
+}
@@ -0,0 +1,508 @@
+//! The Argon2id password hashing function, as defined in [RFC 9106].
+//!
+//! Argon2id is memory-hard like [`scrypt`](crate::scrypt), but resists both side-channel and
+//! time-memory trade-off attacks, and is the recommended choice for password storage.
+//!
+//! Because this crate does not allocate, the caller provides the working memory as a slice of at
+//! least [`Params::block_count()`] [`Block`]s.
+//!
+//! # Examples
+//!
+//! ```
+//! use crylib::argon2::{self, Block, Params};
+//!
+//! let params = Params::new(64, 3, 1).unwrap();
+//! let mut memory = vec![Block::ZERO; params.block_count()];
+//! let mut key = [0; 32];
+//!
+//! argon2::derive(b"password", b"somesalt", &[], &[], &params, &mut memory, &mut key).unwrap();
+//! ```
+//!
+//! [RFC 9106]: https://datatracker.ietf.org/doc/html/rfc9106
+use crate::hash::{Blake2b, Hasher};
+
+/// The Argon2 version implemented here (1.3).
+const VERSION: u32 = 0x13;
+/// The type identifier of Argon2id.
+const ARGON2ID: u32 = 2;
+/// The number of slices each lane is split into.
+const SYNC_POINTS: usize = 4;
+/// The number of 64-bit words in a [`Block`].
+const BLOCK_WORDS: usize = 128;
+
+/// A single 1 KiB block of Argon2 working memory.
+#[derive(Clone, Copy)]
+pub struct Block([u64; BLOCK_WORDS]);
+
+impl Block {
+    /// The size of a block in bytes.
+    pub const SIZE: usize = BLOCK_WORDS * size_of::<u64>();
+
+    /// A block of all zeroes.
+    pub const ZERO: Self = Self([0; BLOCK_WORDS]);
+
+    fn from_le_bytes(bytes: &[u8; Self::SIZE]) -> Self {
+        let mut block = Self::ZERO;
+        // TODO: use `array_chunks` once stabilized
+        for (word, chunk) in block.0.iter_mut().zip(bytes.chunks_exact(8)) {
+            // we can safely unwrap because `chunk` is guaranteed to have a length of `8`
+            *word = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        block
+    }
+
+    fn to_le_bytes(self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        // TODO: use `array_chunks` once stabilized
+        for (chunk, word) in bytes.chunks_exact_mut(8).zip(self.0) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn xor_assign(&mut self, rhs: &Self) {
+        for (word, rhs_word) in self.0.iter_mut().zip(rhs.0) {
+            *word ^= rhs_word;
+        }
+    }
+}
+
+impl Default for Block {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+/// The cost parameters of Argon2id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Params {
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+}
+
+impl Params {
+    /// Creates a new set of parameters using `m_cost` KiB of memory, `t_cost` passes over that
+    /// memory, and `p_cost` lanes.
+    ///
+    /// Returns an [`Err`] if the parameters are out of the range allowed by RFC 9106.
+    pub const fn new(m_cost: u32, t_cost: u32, p_cost: u32) -> Result<Self, InvalidParams> {
+        if p_cost == 0 || p_cost >= 1 << 24 || t_cost == 0 {
+            return Err(InvalidParams);
+        }
+        if (m_cost as u64) < 8 * p_cost as u64 {
+            return Err(InvalidParams);
+        }
+        Ok(Self {
+            m_cost,
+            t_cost,
+            p_cost,
+        })
+    }
+
+    /// The number of [`Block`]s of memory required by [`derive`].
+    ///
+    /// This is `m_cost` rounded down to a multiple of `4 * p_cost`.
+    pub const fn block_count(&self) -> usize {
+        let lane_quantum = SYNC_POINTS as u32 * self.p_cost;
+        (self.m_cost / lane_quantum * lane_quantum) as usize
+    }
+}
+
+/// The error that is returned when Argon2 parameters are out of range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InvalidParams;
+
+impl core::fmt::Display for InvalidParams {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("Argon2 parameters are out of range")
+    }
+}
+
+impl core::error::Error for InvalidParams {}
+
+/// The error that is returned when the memory passed to [`derive`] is too small.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MemoryTooSmall;
+
+impl core::fmt::Display for MemoryTooSmall {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("memory is smaller than Params::block_count()")
+    }
+}
+
+impl core::error::Error for MemoryTooSmall {}
+
+/// The shape of the working memory for one invocation.
+struct Layout {
+    lanes: usize,
+    lane_len: usize,
+    segment_len: usize,
+    block_count: usize,
+    passes: u32,
+}
+
+/// Derives `out.len()` bytes of key material from `password` and `salt`.
+///
+/// `secret` is an optional key (a "pepper") and `ad` is optional associated data; either may be
+/// empty. RFC 9106 recommends a salt of at least 16 bytes.
+///
+/// `memory` must hold at least [`params.block_count()`](Params::block_count) blocks. Its
+/// contents are overwritten, and it is left holding intermediate values derived from the password,
+/// so callers should clear it afterwards if it is reused.
+///
+/// # Panics
+/// This function panics if `out.len()` is less than `4`.
+pub fn derive(
+    password: &[u8],
+    salt: &[u8],
+    secret: &[u8],
+    ad: &[u8],
+    params: &Params,
+    memory: &mut [Block],
+    out: &mut [u8],
+) -> Result<(), MemoryTooSmall> {
+    assert!(out.len() >= 4);
+
+    let block_count = params.block_count();
+    if memory.len() < block_count {
+        return Err(MemoryTooSmall);
+    }
+    let memory = &mut memory[..block_count];
+
+    let lanes = params.p_cost as usize;
+    let lane_len = block_count / lanes;
+    let layout = Layout {
+        lanes,
+        lane_len,
+        segment_len: lane_len / SYNC_POINTS,
+        block_count,
+        passes: params.t_cost,
+    };
+
+    let mut hasher = Blake2b::new();
+    for param in [
+        params.p_cost,
+        out.len() as u32,
+        params.m_cost,
+        params.t_cost,
+        VERSION,
+        ARGON2ID,
+    ] {
+        hasher.update_with(&param.to_le_bytes());
+    }
+    for input in [password, salt, secret, ad] {
+        hasher.update_with(&(input.len() as u32).to_le_bytes());
+        hasher.update_with(input);
+    }
+    let h0 = hasher.finish();
+
+    for lane in 0..lanes {
+        for i in 0..2 {
+            let mut bytes = [0; Block::SIZE];
+            hash_long(
+                &[&h0, &(i as u32).to_le_bytes(), &(lane as u32).to_le_bytes()],
+                &mut bytes,
+            );
+            memory[lane * lane_len + i] = Block::from_le_bytes(&bytes);
+        }
+    }
+
+    for pass in 0..params.t_cost {
+        for slice in 0..SYNC_POINTS {
+            // segments in the same slice never reference each other, so lanes could run in parallel
+            for lane in 0..lanes {
+                fill_segment(memory, &layout, pass, slice, lane);
+            }
+        }
+    }
+
+    let mut last = memory[lane_len - 1];
+    for lane in 1..lanes {
+        last.xor_assign(&memory[lane * lane_len + lane_len - 1]);
+    }
+    hash_long(&[&last.to_le_bytes()], out);
+    Ok(())
+}
+
+/// Computes one segment of a lane.
+fn fill_segment(memory: &mut [Block], layout: &Layout, pass: u32, slice: usize, lane: usize) {
+    // Argon2id uses data-independent addressing for the first half of the first pass
+    let data_independent = pass == 0 && slice < SYNC_POINTS / 2;
+
+    let mut input = Block::ZERO;
+    let mut addresses = Block::ZERO;
+    if data_independent {
+        input.0[0] = pass as u64;
+        input.0[1] = lane as u64;
+        input.0[2] = slice as u64;
+        input.0[3] = layout.block_count as u64;
+        input.0[4] = layout.passes as u64;
+        input.0[5] = ARGON2ID as u64;
+    }
+
+    // the first two blocks of each lane have already been computed
+    let start = if pass == 0 && slice == 0 {
+        if data_independent {
+            next_addresses(&mut input, &mut addresses);
+        }
+        2
+    } else {
+        0
+    };
+
+    let segment_start = lane * layout.lane_len + slice * layout.segment_len;
+    for index in start..layout.segment_len {
+        let curr = segment_start + index;
+        let prev = if curr.is_multiple_of(layout.lane_len) {
+            curr + layout.lane_len - 1
+        } else {
+            curr - 1
+        };
+
+        let rand = if data_independent {
+            if index.is_multiple_of(BLOCK_WORDS) {
+                next_addresses(&mut input, &mut addresses);
+            }
+            addresses.0[index % BLOCK_WORDS]
+        } else {
+            memory[prev].0[0]
+        };
+
+        let ref_lane = if pass == 0 && slice == 0 {
+            lane
+        } else {
+            (rand >> 32) as usize % layout.lanes
+        };
+        let ref_index = reference_index(layout, pass, slice, index, rand as u32, ref_lane == lane);
+
+        let mut next = compress(
+            &memory[prev],
+            &memory[ref_lane * layout.lane_len + ref_index],
+        );
+        if pass != 0 {
+            next.xor_assign(&memory[curr]);
+        }
+        memory[curr] = next;
+    }
+}
+
+/// Generates the next block of pseudo-random values for data-independent addressing.
+fn next_addresses(input: &mut Block, addresses: &mut Block) {
+    input.0[6] += 1;
+    *addresses = compress(&Block::ZERO, input);
+    *addresses = compress(&Block::ZERO, addresses);
+}
+
+/// Maps the pseudo-random value `rand` onto the set of blocks the current block may reference.
+fn reference_index(
+    layout: &Layout,
+    pass: u32,
+    slice: usize,
+    index: usize,
+    rand: u32,
+    same_lane: bool,
+) -> usize {
+    let area_len = if pass == 0 {
+        if slice == 0 || same_lane {
+            slice * layout.segment_len + index - 1
+        } else if index == 0 {
+            slice * layout.segment_len - 1
+        } else {
+            slice * layout.segment_len
+        }
+    } else if same_lane {
+        layout.lane_len - layout.segment_len + index - 1
+    } else if index == 0 {
+        layout.lane_len - layout.segment_len - 1
+    } else {
+        layout.lane_len - layout.segment_len
+    };
+
+    let x = (rand as u64 * rand as u64) >> 32;
+    let relative = area_len - 1 - ((area_len as u64 * x) >> 32) as usize;
+
+    let start = if pass == 0 || slice == SYNC_POINTS - 1 {
+        0
+    } else {
+        (slice + 1) * layout.segment_len
+    };
+    (start + relative) % layout.lane_len
+}
+
+/// The compression function `G`.
+fn compress(x: &Block, y: &Block) -> Block {
+    let mut r = *x;
+    r.xor_assign(y);
+
+    let mut q = r;
+    for row in q.0.chunks_exact_mut(16) {
+        // we can safely unwrap because `row` is guaranteed to have a length of `16`
+        permute(row.try_into().unwrap());
+    }
+    for col in 0..8 {
+        let mut v = [0; 16];
+        for i in 0..8 {
+            v[2 * i] = q.0[2 * col + 16 * i];
+            v[2 * i + 1] = q.0[2 * col + 16 * i + 1];
+        }
+        permute(&mut v);
+        for i in 0..8 {
+            q.0[2 * col + 16 * i] = v[2 * i];
+            q.0[2 * col + 16 * i + 1] = v[2 * i + 1];
+        }
+    }
+
+    q.xor_assign(&r);
+    q
+}
+
+/// The permutation `P`, based on the BLAKE2b round function.
+fn permute(v: &mut [u64; 16]) {
+    mix(v, 0, 4, 8, 12);
+    mix(v, 1, 5, 9, 13);
+    mix(v, 2, 6, 10, 14);
+    mix(v, 3, 7, 11, 15);
+    mix(v, 0, 5, 10, 15);
+    mix(v, 1, 6, 11, 12);
+    mix(v, 2, 7, 8, 13);
+    mix(v, 3, 4, 9, 14);
+}
+
+/// The BLAKE2b mixing function with its additions replaced by `x + y + 2 * x_lo * y_lo`.
+fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize) {
+    v[a] = blamka(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = blamka(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = blamka(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = blamka(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+const fn blamka(x: u64, y: u64) -> u64 {
+    let product = (x as u32 as u64) * (y as u32 as u64);
+    x.wrapping_add(y).wrapping_add(product.wrapping_mul(2))
+}
+
+/// The variable-length hash function `H'`, hashing the concatenation of `inputs` into `out`.
+fn hash_long(inputs: &[&[u8]], out: &mut [u8]) {
+    let out_len = (out.len() as u32).to_le_bytes();
+
+    if out.len() <= Blake2b::HASH_SIZE {
+        let mut hasher = Blake2b::with_len(out.len());
+        hasher.update_with(&out_len);
+        for input in inputs {
+            hasher.update_with(input);
+        }
+        hasher.finish_into(out);
+        return;
+    }
+
+    let mut hasher = Blake2b::new();
+    hasher.update_with(&out_len);
+    for input in inputs {
+        hasher.update_with(input);
+    }
+    let mut v = hasher.finish();
+
+    // all but the last hash contribute their first 32 bytes
+    let half = Blake2b::HASH_SIZE / 2;
+    let r = out.len().div_ceil(half) - 2;
+    out[..half].copy_from_slice(&v[..half]);
+    for i in 1..r {
+        v = Blake2b::hash(&v);
+        out[i * half..][..half].copy_from_slice(&v[..half]);
+    }
+
+    let rest = &mut out[r * half..];
+    let mut hasher = Blake2b::with_len(rest.len());
+    hasher.update_with(&v);
+    hasher.finish_into(rest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive() {
+        // test vector from https://datatracker.ietf.org/doc/html/rfc9106#section-5.3
+        let tag = [
+            0x0d, 0x64, 0x0d, 0xf5, 0x8d, 0x78, 0x76, 0x6c, 0x08, 0xc0, 0x37, 0xa3, 0x4a, 0x8b,
+            0x53, 0xc9, 0xd0, 0x1e, 0xf0, 0x45, 0x2d, 0x75, 0xb6, 0x5e, 0xb5, 0x25, 0x20, 0xe9,
+            0x6b, 0x01, 0xe6, 0x59,
+        ];
+        let params = Params::new(32, 3, 4).unwrap();
+        let mut memory = [Block::ZERO; 32];
+        let mut out = [0; 32];
+        super::derive(
+            &[0x01; 32],
+            &[0x02; 16],
+            &[0x03; 8],
+            &[0x04; 12],
+            &params,
+            &mut memory,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(out, tag);
+    }
+
+    #[test]
+    fn derive_long() {
+        extern crate std;
+        use std::vec;
+
+        // segments longer than one address block, and a tag longer than one BLAKE2b digest
+        let tag = [
+            0xf0, 0x28, 0x80, 0x34, 0x33, 0x44, 0x38, 0xdd, 0x9f, 0x52, 0xb0, 0x81, 0x4f, 0x15,
+            0x90, 0x32, 0x07, 0x4a, 0x8f, 0xd4, 0xde, 0x6e, 0xee, 0xbb, 0xa9, 0xa3, 0xb6, 0x32,
+            0xe4, 0x05, 0xef, 0x84, 0x05, 0x08, 0xb4, 0xd1, 0xd2, 0x57, 0x1f, 0xe3, 0x5a, 0x52,
+            0x2a, 0x0f, 0xa3, 0x7f, 0x0d, 0xb7, 0xac, 0x81, 0x81, 0x7b, 0xfb, 0x66, 0x62, 0x96,
+            0x38, 0xc9, 0x1e, 0x9f, 0x84, 0xaa, 0x15, 0xff, 0x71, 0x55, 0x3e, 0x9c, 0xf8, 0xf8,
+            0xa1, 0xd3, 0xfb, 0xa3, 0x7e, 0xeb, 0x87, 0xa1, 0x77, 0x16,
+        ];
+        let params = Params::new(2048, 2, 2).unwrap();
+        let mut memory = vec![Block::ZERO; params.block_count()];
+        let mut out = [0; 80];
+        super::derive(
+            b"password",
+            b"somesalt",
+            &[],
+            &[],
+            &params,
+            &mut memory,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(out, tag);
+    }
+
+    #[test]
+    fn invalid_params() {
+        assert_eq!(Params::new(32, 0, 1), Err(InvalidParams));
+        assert_eq!(Params::new(32, 1, 0), Err(InvalidParams));
+        assert_eq!(Params::new(31, 1, 4), Err(InvalidParams));
+        assert_eq!(Params::new(37, 1, 4).unwrap().block_count(), 32);
+    }
+
+    #[test]
+    fn memory_too_small() {
+        let params = Params::new(8, 1, 1).unwrap();
+        let mut memory = [Block::ZERO; 7];
+        assert_eq!(
+            super::derive(
+                b"password",
+                b"somesalt",
+                &[],
+                &[],
+                &params,
+                &mut memory,
+                &mut [0; 32]
+            ),
+            Err(MemoryTooSmall)
+        );
+    }
+}
@@ -0,0 +1,945 @@
+//! A streaming reader and writer for the DER encoding of ASN.1 ([X.690]).
+//!
+//! [`Reader`] walks the elements of a byte slice in order, and [`Writer`] appends elements to a
+//! caller-provided buffer, so nothing is allocated. Both only deal with the distinguished
+//! encoding: lengths and integers must be minimal, and the elements of a `SET` must be sorted,
+//! so every value has exactly one encoding.
+//!
+//! Only the structure is checked here. What the elements mean, such as which OIDs are expected,
+//! is left to the caller. Nothing in this module runs in constant time, so it is meant for
+//! public data, such as certificates and signatures. Private keys should only go through it
+//! after they've been decoded from [`pem`](crate::encoding::pem).
+//!
+//! # Examples
+//!
+//! ```
+//! use crylib::asn1::{self, Reader, Writer};
+//!
+//! let mut buf = [0; 16];
+//! let mut writer = Writer::new(&mut buf);
+//! writer
+//!     .write_sequence(|seq| {
+//!         seq.write_unsigned(&[0x80])?;
+//!         seq.write_null()
+//!     })
+//!     .unwrap();
+//! let der = writer.into_written();
+//! assert_eq!(der, [0x30, 0x06, 0x02, 0x02, 0x00, 0x80, 0x05, 0x00]);
+//!
+//! let mut reader = Reader::new(der);
+//! let mut seq = reader.read_sequence().unwrap();
+//! assert_eq!(seq.read_unsigned(), Ok(&[0x80][..]));
+//! assert_eq!(seq.read(asn1::NULL), Ok(&[][..]));
+//! assert!(seq.finish().is_ok());
+//! assert!(reader.finish().is_ok());
+//! ```
+//!
+//! [X.690]: https://www.itu.int/rec/T-REC-X.690
+
+/// The tag of a `BOOLEAN`.
+pub const BOOLEAN: u8 = 0x01;
+/// The tag of an `INTEGER`.
+pub const INTEGER: u8 = 0x02;
+/// The tag of a `BIT STRING`.
+pub const BIT_STRING: u8 = 0x03;
+/// The tag of an `OCTET STRING`.
+pub const OCTET_STRING: u8 = 0x04;
+/// The tag of a `NULL`.
+pub const NULL: u8 = 0x05;
+/// The tag of an `OBJECT IDENTIFIER`.
+pub const OID: u8 = 0x06;
+/// The tag of a `UTF8String`.
+pub const UTF8_STRING: u8 = 0x0c;
+/// The tag of a `PrintableString`.
+pub const PRINTABLE_STRING: u8 = 0x13;
+/// The tag of an `IA5String`.
+pub const IA5_STRING: u8 = 0x16;
+/// The tag of a `UTCTime`.
+pub const UTC_TIME: u8 = 0x17;
+/// The tag of a `GeneralizedTime`.
+pub const GENERALIZED_TIME: u8 = 0x18;
+/// The tag of a `SEQUENCE` or `SEQUENCE OF`.
+pub const SEQUENCE: u8 = 0x30;
+/// The tag of a `SET` or `SET OF`.
+pub const SET: u8 = 0x31;
+
+/// Returns the context-specific tag `[number]` of a constructed element.
+///
+/// This is the tag of every explicitly tagged field, such as a certificate's version, which is
+/// `[0]`, and of implicitly tagged fields whose type is constructed, such as a `SEQUENCE`.
+///
+/// # Panics
+///
+/// The function will panic if `number` > 30, since larger numbers need more than one byte.
+pub const fn context(number: u8) -> u8 {
+    assert!(number <= 30);
+    0xa0 | number
+}
+
+/// Returns the context-specific tag `[number]` of an implicitly tagged field whose type isn't
+/// constructed, such as the IP address of a `GeneralName`, which is `[7]`.
+///
+/// # Panics
+///
+/// The function will panic if `number` > 30, since larger numbers need more than one byte.
+pub const fn context_primitive(number: u8) -> u8 {
+    assert!(number <= 30);
+    0x80 | number
+}
+
+/// A DER element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Element<'a> {
+    pub tag: u8,
+    /// The whole element, including its tag and length.
+    pub encoding: &'a [u8],
+    pub contents: &'a [u8],
+}
+
+/// The error that is returned when DER is truncated or isn't the distinguished encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InvalidDer;
+
+impl core::fmt::Display for InvalidDer {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("malformed DER")
+    }
+}
+
+impl core::error::Error for InvalidDer {}
+
+/// The error that is returned when the buffer of a [`Writer`] can't fit an element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BufferTooSmall;
+
+impl core::fmt::Display for BufferTooSmall {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("buffer is too small for the encoding")
+    }
+}
+
+impl core::error::Error for BufferTooSmall {}
+
+/// A cursor that reads DER elements from the front of a byte slice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub const fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Returns the tag of the next element without reading it.
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.buf.first().copied()
+    }
+
+    /// Returns an error unless every element has been read.
+    pub fn finish(self) -> Result<(), InvalidDer> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(InvalidDer),
+        }
+    }
+
+    /// Reads the next element, whatever its tag.
+    ///
+    /// Only tags that fit in one byte are supported, which covers everything in X.509 and PKCS.
+    pub fn read_any(&mut self) -> Result<Element<'a>, InvalidDer> {
+        let [tag, len, rest @ ..] = self.buf else {
+            return Err(InvalidDer);
+        };
+        if tag & 0x1f == 0x1f {
+            return Err(InvalidDer);
+        }
+        let (len, rest) = match *len {
+            len @ 0..=0x7f => (len as usize, rest),
+            // the indefinite form (0x80) isn't allowed in DER
+            0x81..=0x84 => {
+                let (len_bytes, rest) = rest
+                    .split_at_checked((len & 0x7f) as usize)
+                    .ok_or(InvalidDer)?;
+                // the length must not have leading zeros or fit in the short form
+                if len_bytes[0] == 0 || (len_bytes.len() == 1 && len_bytes[0] < 0x80) {
+                    return Err(InvalidDer);
+                }
+                let len = len_bytes
+                    .iter()
+                    .fold(0u64, |len, &byte| (len << 8) | byte as u64);
+                (usize::try_from(len).map_err(|_| InvalidDer)?, rest)
+            },
+            _ => return Err(InvalidDer),
+        };
+        if rest.len() < len {
+            return Err(InvalidDer);
+        }
+        let header_len = self.buf.len() - rest.len();
+        let (encoding, rest) = self.buf.split_at(header_len + len);
+        self.buf = rest;
+        Ok(Element {
+            tag: *tag,
+            encoding,
+            contents: &encoding[header_len..],
+        })
+    }
+
+    /// Reads the next element, which must have the tag `tag`, returning its contents.
+    pub fn read(&mut self, tag: u8) -> Result<&'a [u8], InvalidDer> {
+        let element = self.read_any()?;
+        match element.tag == tag {
+            true => Ok(element.contents),
+            false => Err(InvalidDer),
+        }
+    }
+
+    /// Reads the next element if it has the tag `tag`, returning its contents.
+    pub fn read_optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>, InvalidDer> {
+        match self.peek_tag() {
+            Some(next) if next == tag => self.read(tag).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Reads a `SEQUENCE`, returning a reader over its elements.
+    pub fn read_sequence(&mut self) -> Result<Reader<'a>, InvalidDer> {
+        self.read(SEQUENCE).map(Reader::new)
+    }
+
+    /// Reads a `SET`, returning a reader over its elements.
+    ///
+    /// The elements must be sorted by their encodings, which is what DER requires of a `SET OF`.
+    /// Since the elements of a `SET` have distinct tags, this also puts them in the order DER
+    /// requires for a `SET`.
+    pub fn read_set(&mut self) -> Result<Reader<'a>, InvalidDer> {
+        let set = Reader::new(self.read(SET)?);
+        let mut elements = set;
+        let mut prev: Option<&[u8]> = None;
+        while !elements.is_empty() {
+            let element = elements.read_any()?.encoding;
+            if prev.is_some_and(|prev| prev > element) {
+                return Err(InvalidDer);
+            }
+            prev = Some(element);
+        }
+        Ok(set)
+    }
+
+    /// Reads a `BOOLEAN`, which must be encoded as `0x00` or `0xff`.
+    pub fn read_bool(&mut self) -> Result<bool, InvalidDer> {
+        match self.read(BOOLEAN)? {
+            [0x00] => Ok(false),
+            [0xff] => Ok(true),
+            _ => Err(InvalidDer),
+        }
+    }
+
+    /// Reads an `INTEGER`, returning its minimal big-endian two's complement bytes.
+    pub fn read_integer(&mut self) -> Result<&'a [u8], InvalidDer> {
+        match self.read(INTEGER)? {
+            [] => Err(InvalidDer),
+            // the first nine bits can't all be the same
+            [0x00, next, ..] if next & 0x80 == 0 => Err(InvalidDer),
+            [0xff, next, ..] if next & 0x80 != 0 => Err(InvalidDer),
+            bytes => Ok(bytes),
+        }
+    }
+
+    /// Reads a non-negative `INTEGER`, returning its big-endian bytes without the leading zero
+    /// that DER adds when the high bit is set.
+    ///
+    /// Zero is returned as an empty slice.
+    pub fn read_unsigned(&mut self) -> Result<&'a [u8], InvalidDer> {
+        match self.read_integer()? {
+            [0] => Ok(&[]),
+            [0, rest @ ..] => Ok(rest),
+            [first, ..] if first & 0x80 != 0 => Err(InvalidDer),
+            bytes => Ok(bytes),
+        }
+    }
+
+    /// Reads a `BIT STRING` that is a whole number of bytes, returning the bytes.
+    pub fn read_bit_string(&mut self) -> Result<&'a [u8], InvalidDer> {
+        match self.read_bits()? {
+            (bytes, 0) => Ok(bytes),
+            _ => Err(InvalidDer),
+        }
+    }
+
+    /// Reads a `BIT STRING`, returning its bytes and how many of the lowest bits of the last
+    /// byte aren't part of it.
+    ///
+    /// DER requires those unused bits to be zero.
+    pub fn read_bits(&mut self) -> Result<(&'a [u8], u8), InvalidDer> {
+        let contents = self.read(BIT_STRING)?;
+        match contents {
+            [0, bytes @ ..] => Ok((bytes, 0)),
+            [unused @ 1..=7, .., last] if last & ((1 << unused) - 1) == 0 => {
+                Ok((&contents[1..], *unused))
+            },
+            _ => Err(InvalidDer),
+        }
+    }
+
+    /// Reads an `OBJECT IDENTIFIER`, returning its contents, which can be compared with the
+    /// contents of a known OID.
+    ///
+    /// Every arc must be minimally encoded.
+    pub fn read_oid(&mut self) -> Result<&'a [u8], InvalidDer> {
+        let oid = self.read(OID)?;
+        // each arc ends with the first byte whose high bit is clear, and may not start with 0x80
+        let mut starts_arc = true;
+        for &byte in oid {
+            if starts_arc && byte == 0x80 {
+                return Err(InvalidDer);
+            }
+            starts_arc = byte & 0x80 == 0;
+        }
+        match starts_arc && !oid.is_empty() {
+            true => Ok(oid),
+            false => Err(InvalidDer),
+        }
+    }
+
+    /// Reads a `UTCTime` or a `GeneralizedTime`.
+    ///
+    /// Both must be in the form RFC 5280 requires of certificates: in UTC, with seconds, and
+    /// without fractions of a second. Two-digit years from 50 to 99 are in the 1900s, and the
+    /// rest are in the 2000s.
+    pub fn read_time(&mut self) -> Result<Time, InvalidDer> {
+        let element = self.read_any()?;
+        let year_len = match element.tag {
+            UTC_TIME => 2,
+            GENERALIZED_TIME => 4,
+            _ => return Err(InvalidDer),
+        };
+        let [digits @ .., b'Z'] = element.contents else {
+            return Err(InvalidDer);
+        };
+        if digits.len() != year_len + 10 {
+            return Err(InvalidDer);
+        }
+        let (year, rest) = digits.split_at(year_len);
+        let year = match (parse_digits(year)?, year_len) {
+            (year @ 0..50, 2) => 2000 + year,
+            (year, 2) => 1900 + year,
+            (year, _) => year,
+        };
+        let field = |i: usize| parse_digits(&rest[2 * i..2 * i + 2]).map(|field| field as u8);
+        Time::new(year, field(0)?, field(1)?, field(2)?, field(3)?, field(4)?).ok_or(InvalidDer)
+    }
+}
+
+/// Parses ASCII decimal digits, of which there must be at most four.
+fn parse_digits(digits: &[u8]) -> Result<u16, InvalidDer> {
+    digits.iter().try_fold(0, |val: u16, &digit| match digit {
+        b'0'..=b'9' => Ok(val * 10 + (digit - b'0') as u16),
+        _ => Err(InvalidDer),
+    })
+}
+
+/// A time in UTC, to the second, as used by the validity of a certificate.
+///
+/// Times are ordered chronologically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+}
+
+impl Time {
+    /// Creates a time from a date in the Gregorian calendar and a time of day.
+    ///
+    /// Returns [`None`] if the year is after 9999, which `GeneralizedTime` can't hold, or if
+    /// any of the other fields is out of range. Leap seconds aren't supported.
+    pub const fn new(
+        year: u16,
+        month: u8,
+        day: u8,
+        hour: u8,
+        minute: u8,
+        second: u8,
+    ) -> Option<Self> {
+        let days_in_month = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if year.is_multiple_of(4)
+                && (!year.is_multiple_of(100) || year.is_multiple_of(400)) =>
+            {
+                29
+            },
+            2 => 28,
+            _ => return None,
+        };
+        if year > 9999 || day == 0 || day > days_in_month || hour > 23 || minute > 59 || second > 59
+        {
+            return None;
+        }
+        Some(Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        })
+    }
+
+    pub const fn year(&self) -> u16 {
+        self.year
+    }
+
+    pub const fn month(&self) -> u8 {
+        self.month
+    }
+
+    pub const fn day(&self) -> u8 {
+        self.day
+    }
+
+    pub const fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    pub const fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    pub const fn second(&self) -> u8 {
+        self.second
+    }
+
+    /// Returns the number of seconds since 1970-01-01 00:00:00 UTC, which is negative for
+    /// earlier times.
+    pub const fn unix_time(&self) -> i64 {
+        // days since 0000-03-01, counting from March so that leap days come last in each year
+        let year = self.year as i64 - (self.month <= 2) as i64;
+        let month = (self.month as i64 + 9) % 12;
+        let day_of_year = (153 * month + 2) / 5 + self.day as i64 - 1;
+        let days = year * 365 + year.div_euclid(4) - year.div_euclid(100)
+            + year.div_euclid(400)
+            + day_of_year;
+        // 0000-03-01 is 719468 days before 1970-01-01
+        let days = days - 719468;
+        days * 86400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64
+    }
+}
+
+/// A cursor that appends DER elements to a caller-provided buffer.
+///
+/// Constructed elements, such as sequences, are written by a closure that writes their contents,
+/// so their lengths never have to be known in advance.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Writer<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    /// The length of the longest header the writer produces: a tag, then a length of up to four
+    /// bytes in the long form.
+    const MAX_HEADER_LEN: usize = 6;
+
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// Returns everything written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Returns everything written, for as long as the buffer is borrowed.
+    pub fn into_written(self) -> &'a [u8] {
+        let buf: &'a [u8] = self.buf;
+        &buf[..self.len]
+    }
+
+    /// Reserves `len` bytes, returning them.
+    fn reserve(&mut self, len: usize) -> Result<&mut [u8], BufferTooSmall> {
+        let start = self.len;
+        let reserved = self
+            .buf
+            .get_mut(start..start.checked_add(len).ok_or(BufferTooSmall)?)
+            .ok_or(BufferTooSmall)?;
+        self.len += len;
+        Ok(reserved)
+    }
+
+    fn write_header(&mut self, tag: u8, len: usize) -> Result<(), BufferTooSmall> {
+        let mut header = [0; Self::MAX_HEADER_LEN];
+        let header_len = encode_header(tag, len, &mut header)?;
+        self.reserve(header_len)?
+            .copy_from_slice(&header[..header_len]);
+        Ok(())
+    }
+
+    /// Writes an element with the tag `tag` and the contents `contents`.
+    pub fn write_element(&mut self, tag: u8, contents: &[u8]) -> Result<(), BufferTooSmall> {
+        self.write_header(tag, contents.len())?;
+        self.reserve(contents.len())?.copy_from_slice(contents);
+        Ok(())
+    }
+
+    /// Writes elements that are already encoded, such as a `SubjectPublicKeyInfo` that was read
+    /// with [`Reader::read_any`].
+    pub fn write_der(&mut self, der: &[u8]) -> Result<(), BufferTooSmall> {
+        self.reserve(der.len())?.copy_from_slice(der);
+        Ok(())
+    }
+
+    /// Writes a constructed element with the tag `tag`, whose contents are written by
+    /// `contents`.
+    pub fn write_constructed(
+        &mut self,
+        tag: u8,
+        contents: impl FnOnce(&mut Writer<'_>) -> Result<(), BufferTooSmall>,
+    ) -> Result<(), BufferTooSmall> {
+        self.write_constructed_with(tag, contents).map(|_| ())
+    }
+
+    /// Does what [`Self::write_constructed`] does, but also returns the contents.
+    fn write_constructed_with(
+        &mut self,
+        tag: u8,
+        contents: impl FnOnce(&mut Writer<'_>) -> Result<(), BufferTooSmall>,
+    ) -> Result<&mut [u8], BufferTooSmall> {
+        // leave room for a short header, and move the contents over if they need a longer one
+        let start = self.len;
+        let contents_start = start + 2;
+        let mut writer = Writer::new(self.buf.get_mut(contents_start..).ok_or(BufferTooSmall)?);
+        contents(&mut writer)?;
+        let contents_len = writer.len;
+
+        let mut header = [0; Self::MAX_HEADER_LEN];
+        let header_len = encode_header(tag, contents_len, &mut header)?;
+        let end = start + header_len + contents_len;
+        if end > self.buf.len() {
+            return Err(BufferTooSmall);
+        }
+        self.buf.copy_within(
+            contents_start..contents_start + contents_len,
+            start + header_len,
+        );
+        self.buf[start..start + header_len].copy_from_slice(&header[..header_len]);
+        self.len = end;
+        Ok(&mut self.buf[start + header_len..end])
+    }
+
+    /// Writes a `SEQUENCE`, whose elements are written by `elements`.
+    pub fn write_sequence(
+        &mut self,
+        elements: impl FnOnce(&mut Writer<'_>) -> Result<(), BufferTooSmall>,
+    ) -> Result<(), BufferTooSmall> {
+        self.write_constructed(SEQUENCE, elements)
+    }
+
+    /// Writes a `SET`, whose elements are written by `elements` in any order.
+    ///
+    /// The elements are then sorted by their encodings, as DER requires.
+    pub fn write_set(
+        &mut self,
+        elements: impl FnOnce(&mut Writer<'_>) -> Result<(), BufferTooSmall>,
+    ) -> Result<(), BufferTooSmall> {
+        let elements = self.write_constructed_with(SET, elements)?;
+        sort_elements(elements);
+        Ok(())
+    }
+
+    /// Writes a `BOOLEAN`.
+    pub fn write_bool(&mut self, val: bool) -> Result<(), BufferTooSmall> {
+        self.write_element(BOOLEAN, &[if val { 0xff } else { 0x00 }])
+    }
+
+    /// Writes a `NULL`.
+    pub fn write_null(&mut self) -> Result<(), BufferTooSmall> {
+        self.write_element(NULL, &[])
+    }
+
+    /// Writes the non-negative big-endian integer `int` as an `INTEGER`.
+    ///
+    /// `int` may have leading zeros, which are removed. A zero is added back if the high bit is
+    /// set, so that the integer stays positive.
+    pub fn write_unsigned(&mut self, int: &[u8]) -> Result<(), BufferTooSmall> {
+        let start = int.iter().position(|&byte| byte != 0).unwrap_or(int.len());
+        let int = match &int[start..] {
+            [] => &[0],
+            int => int,
+        };
+        let padding = (int[0] >> 7) as usize;
+        self.write_header(INTEGER, padding + int.len())?;
+        let contents = self.reserve(padding + int.len())?;
+        contents[0] = 0;
+        contents[padding..].copy_from_slice(int);
+        Ok(())
+    }
+
+    /// Writes a `BIT STRING` that is a whole number of bytes.
+    pub fn write_bit_string(&mut self, bytes: &[u8]) -> Result<(), BufferTooSmall> {
+        self.write_header(BIT_STRING, 1 + bytes.len())?;
+        let contents = self.reserve(1 + bytes.len())?;
+        contents[0] = 0;
+        contents[1..].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Writes an `OBJECT IDENTIFIER` whose contents are `oid`.
+    pub fn write_oid(&mut self, oid: &[u8]) -> Result<(), BufferTooSmall> {
+        self.write_element(OID, oid)
+    }
+
+    /// Writes `time` as a `UTCTime` if its year is from 1950 to 2049, and as a `GeneralizedTime`
+    /// otherwise, which is what RFC 5280 requires of certificates.
+    pub fn write_time(&mut self, time: &Time) -> Result<(), BufferTooSmall> {
+        let (tag, year_len) = match time.year {
+            1950..2050 => (UTC_TIME, 2),
+            _ => (GENERALIZED_TIME, 4),
+        };
+        let mut digits = [0; 15];
+        let fields = [
+            time.year,
+            time.month as u16,
+            time.day as u16,
+            time.hour as u16,
+            time.minute as u16,
+            time.second as u16,
+        ];
+        let mut len = 0;
+        for (i, mut field) in fields.into_iter().enumerate() {
+            let field_len = if i == 0 { year_len } else { 2 };
+            for digit in digits[len..len + field_len].iter_mut().rev() {
+                *digit = b'0' + (field % 10) as u8;
+                field /= 10;
+            }
+            len += field_len;
+        }
+        digits[len] = b'Z';
+        self.write_element(tag, &digits[..len + 1])
+    }
+}
+
+/// Encodes the tag and length of an element into `header`, returning the length of the header.
+///
+/// Returns [`BufferTooSmall`] if `len` doesn't fit in four bytes, since nothing could read it.
+fn encode_header(
+    tag: u8,
+    len: usize,
+    header: &mut [u8; Writer::MAX_HEADER_LEN],
+) -> Result<usize, BufferTooSmall> {
+    header[0] = tag;
+    if len < 0x80 {
+        header[1] = len as u8;
+        return Ok(2);
+    }
+    let len = u32::try_from(len).map_err(|_| BufferTooSmall)?;
+    let len_bytes = 4 - len.leading_zeros() as usize / 8;
+    header[1] = 0x80 | len_bytes as u8;
+    header[2..2 + len_bytes].copy_from_slice(&len.to_be_bytes()[4 - len_bytes..]);
+    Ok(2 + len_bytes)
+}
+
+/// Sorts the DER elements in `elements` by their encodings, in place.
+///
+/// Each pass moves the smallest of the remaining elements in front of the others, so this takes
+/// quadratic time, which is fine for the few elements a `SET` usually has.
+fn sort_elements(elements: &mut [u8]) {
+    let mut sorted = 0;
+    while sorted < elements.len() {
+        let mut reader = Reader::new(&elements[sorted..]);
+        // (offset, len) of the smallest element so far
+        let mut smallest: Option<(usize, usize)> = None;
+        while !reader.is_empty() {
+            let offset = elements.len() - reader.buf.len();
+            // the elements were written by a `Writer`, so they can only be invalid if
+            // `write_der` was given invalid DER, which is left as it is
+            let Ok(element) = reader.read_any() else {
+                return;
+            };
+            let len = element.encoding.len();
+            if smallest.is_none_or(|(start, smallest_len)| {
+                element.encoding < &elements[start..start + smallest_len]
+            }) {
+                smallest = Some((offset, len));
+            }
+        }
+        let Some((start, len)) = smallest else {
+            return;
+        };
+        elements[sorted..start + len].rotate_right(len);
+        sorted += len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read() {
+        let der = [0x30, 0x06, 0x02, 0x01, 0x05, 0x02, 0x01, 0x00, 0x05, 0x00];
+        let mut reader = Reader::new(&der);
+        let mut sequence = reader.read_sequence().unwrap();
+        assert_eq!(sequence.read_unsigned(), Ok(&[0x05][..]));
+        assert_eq!(sequence.read_optional(NULL), Ok(None));
+        assert_eq!(sequence.read_unsigned(), Ok(&[][..]));
+        assert!(sequence.finish().is_ok());
+        assert_eq!(reader.read_optional(NULL), Ok(Some(&[][..])));
+        assert!(reader.finish().is_ok());
+
+        let mut long = [0xaa; 3 + 0x80];
+        long[..3].copy_from_slice(&[0x04, 0x81, 0x80]);
+        let element = Reader::new(&long).read_any().unwrap();
+        assert_eq!(element.contents, [0xaa; 0x80]);
+        assert_eq!(element.encoding, long);
+
+        let mut longer = [0xbb; 5 + 0x10000];
+        longer[..5].copy_from_slice(&[0x04, 0x83, 0x01, 0x00, 0x00]);
+        let element = Reader::new(&longer).read_any().unwrap();
+        assert_eq!(element.contents.len(), 0x10000);
+    }
+
+    #[test]
+    fn invalid() {
+        let invalid: [&[u8]; 9] = [
+            // truncated
+            &[0x30, 0x03, 0x02, 0x01],
+            &[0x02, 0x82, 0x01],
+            // long form for a short length
+            &[0x02, 0x81, 0x01, 0x05],
+            &[0x02, 0x82, 0x00, 0x81, 0x05],
+            // indefinite length
+            &[0x02, 0x80, 0x05, 0x00, 0x00],
+            // negative
+            &[0x02, 0x01, 0x80],
+            // unnecessary leading zero
+            &[0x02, 0x02, 0x00, 0x05],
+            // wrong tag
+            &[0x30, 0x01, 0x05],
+            // multi-byte tag
+            &[0x1f, 0x01, 0x01, 0x00],
+        ];
+        for der in invalid {
+            assert_eq!(
+                Reader::new(der).read_unsigned(),
+                Err(InvalidDer),
+                "{der:02x?}"
+            );
+        }
+        assert_eq!(Reader::new(&[0x02, 0x01]).read(SEQUENCE), Err(InvalidDer));
+        assert_eq!(Reader::new(&[0x05, 0x00, 0x05]).finish(), Err(InvalidDer));
+    }
+
+    #[test]
+    fn integers() {
+        fn read(der: &[u8]) -> Result<&[u8], InvalidDer> {
+            Reader::new(der).read_integer()
+        }
+        assert_eq!(read(&[0x02, 0x01, 0x80]), Ok(&[0x80][..]));
+        assert_eq!(read(&[0x02, 0x02, 0xff, 0x7f]), Ok(&[0xff, 0x7f][..]));
+        assert_eq!(read(&[0x02, 0x02, 0xff, 0x80]), Err(InvalidDer));
+        assert_eq!(read(&[0x02, 0x00]), Err(InvalidDer));
+
+        assert_eq!(Reader::new(&[0x01, 0x01, 0xff]).read_bool(), Ok(true));
+        assert_eq!(Reader::new(&[0x01, 0x01, 0x00]).read_bool(), Ok(false));
+        assert_eq!(
+            Reader::new(&[0x01, 0x01, 0x01]).read_bool(),
+            Err(InvalidDer)
+        );
+    }
+
+    #[test]
+    fn bits() {
+        fn read(der: &[u8]) -> Result<(&[u8], u8), InvalidDer> {
+            Reader::new(der).read_bits()
+        }
+        // the key usage of a CA: keyCertSign and cRLSign
+        assert_eq!(read(&[0x03, 0x02, 0x01, 0x06]), Ok((&[0x06][..], 1)));
+        assert_eq!(read(&[0x03, 0x01, 0x00]), Ok((&[][..], 0)));
+        // unused bits that aren't zero
+        assert_eq!(read(&[0x03, 0x02, 0x01, 0x07]), Err(InvalidDer));
+        // unused bits without any bytes
+        assert_eq!(read(&[0x03, 0x01, 0x01]), Err(InvalidDer));
+        assert_eq!(read(&[0x03, 0x02, 0x08, 0x00]), Err(InvalidDer));
+        assert_eq!(read(&[0x03, 0x00]), Err(InvalidDer));
+
+        let mut reader = Reader::new(&[0x03, 0x02, 0x01, 0x06]);
+        assert_eq!(reader.read_bit_string(), Err(InvalidDer));
+        assert_eq!(
+            Reader::new(&[0x03, 0x02, 0x00, 0x06]).read_bit_string(),
+            Ok(&[0x06][..])
+        );
+    }
+
+    #[test]
+    fn oids() {
+        // prime256v1
+        let p256 = [0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+        assert_eq!(Reader::new(&p256).read_oid(), Ok(&p256[2..]));
+
+        let invalid: [&[u8]; 3] = [
+            &[0x06, 0x00],
+            // the last arc isn't finished
+            &[0x06, 0x02, 0x2a, 0x86],
+            // an arc with a leading zero
+            &[0x06, 0x03, 0x2a, 0x80, 0x01],
+        ];
+        for der in invalid {
+            assert_eq!(Reader::new(der).read_oid(), Err(InvalidDer), "{der:02x?}");
+        }
+    }
+
+    #[test]
+    fn sets() {
+        let sorted = [0x31, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x02];
+        let mut set = Reader::new(&sorted).read_set().unwrap();
+        assert_eq!(set.read_unsigned(), Ok(&[1][..]));
+        assert_eq!(set.read_unsigned(), Ok(&[2][..]));
+        assert!(set.finish().is_ok());
+
+        let unsorted = [0x31, 0x06, 0x02, 0x01, 0x02, 0x02, 0x01, 0x01];
+        assert_eq!(Reader::new(&unsorted).read_set(), Err(InvalidDer));
+    }
+
+    #[test]
+    fn times() {
+        let times = [
+            (
+                &b"\x17\x0d491231235959Z"[..],
+                (2049, 12, 31, 23, 59, 59),
+                2524607999,
+            ),
+            (b"\x17\x0d500101000000Z", (1950, 1, 1, 0, 0, 0), -631152000),
+            (b"\x17\x0d000229120000Z", (2000, 2, 29, 12, 0, 0), 951825600),
+            (b"\x18\x0f00010101000000Z", (1, 1, 1, 0, 0, 0), -62135596800),
+            (
+                b"\x18\x0f99991231235959Z",
+                (9999, 12, 31, 23, 59, 59),
+                253402300799,
+            ),
+            (b"\x17\x0d700101000000Z", (1970, 1, 1, 0, 0, 0), 0),
+        ];
+        for (der, (year, month, day, hour, minute, second), unix_time) in times {
+            let time = Time::new(year, month, day, hour, minute, second).unwrap();
+            assert_eq!(Reader::new(der).read_time(), Ok(time));
+            assert_eq!(time.unix_time(), unix_time);
+
+            let mut buf = [0; 17];
+            let mut writer = Writer::new(&mut buf);
+            writer.write_time(&time).unwrap();
+            assert_eq!(writer.written(), der);
+        }
+
+        let invalid: [&[u8]; 7] = [
+            // no seconds
+            b"\x17\x0b4912312359Z",
+            // not UTC
+            b"\x17\x11491231235959+0100",
+            // fractions of a second
+            b"\x18\x1320000229120000.123Z",
+            // not a leap year
+            b"\x18\x0f19000229120000Z",
+            b"\x17\x0d491331235959Z",
+            b"\x17\x0d49123123595aZ",
+            // not a time
+            b"\x04\x0d491231235959Z",
+        ];
+        for der in invalid {
+            assert_eq!(Reader::new(der).read_time(), Err(InvalidDer), "{der:02x?}");
+        }
+        assert_eq!(Time::new(2024, 1, 1, 23, 59, 60), None);
+        assert_eq!(Time::new(10000, 1, 1, 0, 0, 0), None);
+        assert!(Time::new(1999, 12, 31, 0, 0, 0) < Time::new(2000, 1, 1, 0, 0, 0));
+    }
+
+    #[test]
+    fn write() {
+        let mut buf = [0; 64];
+        let mut writer = Writer::new(&mut buf);
+        writer
+            .write_sequence(|seq| {
+                seq.write_constructed(context(0), |version| version.write_unsigned(&[2]))?;
+                seq.write_unsigned(&[0, 0, 0x80, 0x01])?;
+                seq.write_unsigned(&[])?;
+                seq.write_bool(true)?;
+                seq.write_oid(&[0x2a, 0x03])?;
+                seq.write_bit_string(&[0xff])?;
+                seq.write_element(OCTET_STRING, b"hi")
+            })
+            .unwrap();
+        let der = [
+            0x30, 0x1c, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x03, 0x00, 0x80, 0x01, 0x02, 0x01,
+            0x00, 0x01, 0x01, 0xff, 0x06, 0x02, 0x2a, 0x03, 0x03, 0x02, 0x00, 0xff, 0x04, 0x02,
+            b'h', b'i',
+        ];
+        assert_eq!(writer.into_written(), der);
+    }
+
+    #[test]
+    fn write_long() {
+        // the contents are written before their length is known, so they have to be moved over
+        // when the length needs the long form
+        for len in [0x7d, 0x7e, 0xfd, 0xfe, 0x1000] {
+            let mut buf = [0; 0x1010];
+            let mut writer = Writer::new(&mut buf);
+            writer
+                .write_sequence(|seq| seq.write_element(OCTET_STRING, &[0x42; 0x1000][..len]))
+                .unwrap();
+            let der = writer.into_written();
+
+            let mut reader = Reader::new(der);
+            let mut seq = reader.read_sequence().unwrap();
+            assert_eq!(seq.read(OCTET_STRING), Ok(&[0x42; 0x1000][..len]));
+            assert!(seq.finish().is_ok());
+            assert!(reader.finish().is_ok());
+        }
+
+        let mut buf = [0; 0x82];
+        let mut writer = Writer::new(&mut buf);
+        // fits with a short header, but not with a long one
+        let contents = [0; 0x80];
+        assert_eq!(
+            writer.write_sequence(|seq| seq.write_der(&contents)),
+            Err(BufferTooSmall)
+        );
+        assert_eq!(
+            Writer::new(&mut [0; 3]).write_unsigned(&[0x80]),
+            Err(BufferTooSmall)
+        );
+        assert_eq!(
+            Writer::new(&mut [0; 1]).write_sequence(|_| Ok(())),
+            Err(BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn write_set() {
+        let mut buf = [0; 32];
+        let mut writer = Writer::new(&mut buf);
+        writer
+            .write_set(|set| {
+                set.write_element(OCTET_STRING, &[3])?;
+                set.write_unsigned(&[0x80])?;
+                set.write_unsigned(&[2])?;
+                set.write_element(OCTET_STRING, &[1, 2])
+            })
+            .unwrap();
+        let der = writer.into_written();
+        assert_eq!(
+            der,
+            [
+                0x31, 0x0e, 0x02, 0x01, 0x02, 0x02, 0x02, 0x00, 0x80, 0x04, 0x01, 0x03, 0x04, 0x02,
+                0x01, 0x02
+            ]
+        );
+        assert!(Reader::new(der).read_set().is_ok());
+    }
+}
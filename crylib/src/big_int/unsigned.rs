@@ -89,6 +89,16 @@ impl<const N: usize> UBigInt<N> {
         one
     };
 
+    /// The number of bits in a [`UBigInt<N>`].
+    ///
+    /// # Examples
+    /// ```
+    /// use crylib::big_int::UBigInt;
+    ///
+    /// assert_eq!(UBigInt::<4>::BITS, 256);
+    /// ```
+    pub const BITS: usize = N * u64::BITS as usize;
+
     /// Subtracts `rhs` from `self`, returning the result and whether the operation
     /// overflowed.
     ///
@@ -96,7 +106,7 @@ impl<const N: usize> UBigInt<N> {
     ///
     /// # Constant-timedness
     /// This operation is constant-time.
-    pub fn overflowing_sub(&self, rhs: &Self) -> (Self, bool) {
+    pub const fn overflowing_sub(&self, rhs: &Self) -> (Self, bool) {
         let mut buf = *self;
         let overflowed = buf.overflowing_sub_assign(rhs);
         (buf, overflowed)
@@ -109,11 +119,14 @@ impl<const N: usize> UBigInt<N> {
     ///
     /// # Constant-timedness
     /// This operation is constant-time.
-    pub fn overflowing_sub_assign(&mut self, rhs: &Self) -> bool {
+    pub const fn overflowing_sub_assign(&mut self, rhs: &Self) -> bool {
         let mut carry = false;
-        for i in 0..N {
+        // TODO: use `for` loops once they are allowed in const fns
+        let mut i = 0;
+        while i < N {
             // TODO: use libcore implementation once stabilized
             (self.0[i], carry) = super::carry_sub(self.0[i], rhs.0[i], carry);
+            i += 1;
         }
         carry
     }
@@ -125,7 +138,7 @@ impl<const N: usize> UBigInt<N> {
     ///
     /// # Constant-timedness
     /// This operation is constant-time.
-    pub fn overflowing_add(&self, rhs: &Self) -> (Self, bool) {
+    pub const fn overflowing_add(&self, rhs: &Self) -> (Self, bool) {
         let mut buf = *self;
         let overflowed = buf.overflowing_add_assign(rhs);
         (buf, overflowed)
@@ -138,11 +151,13 @@ impl<const N: usize> UBigInt<N> {
     ///
     /// # Constant-timedness
     /// This operation is constant-time.
-    pub fn overflowing_add_assign(&mut self, rhs: &Self) -> bool {
+    pub const fn overflowing_add_assign(&mut self, rhs: &Self) -> bool {
         let mut carry = false;
-        for i in 0..N {
+        let mut i = 0;
+        while i < N {
             // TODO: use core implementation once stabilized
             (self.0[i], carry) = super::carry_add(self.0[i], rhs.0[i], carry);
+            i += 1;
         }
         carry
     }
@@ -183,11 +198,13 @@ impl<const N: usize> UBigInt<N> {
     /// # Constant-timedness
     /// This is a constant-time operation.
     /// If constant-time is not needed, consider using [`Self::count_digits_fast()`].
-    pub fn count_digits(&self) -> usize {
+    pub const fn count_digits(&self) -> usize {
         let mut num_digts = 1;
         let mut digit_encounterd = false;
-        for digit in self.0.iter().skip(1).rev() {
-            digit_encounterd |= *digit != 0;
+        let mut i = N;
+        while i > 1 {
+            i -= 1;
+            digit_encounterd |= self.0[i] != 0;
             num_digts += digit_encounterd as usize;
         }
         num_digts
@@ -207,7 +224,7 @@ impl<const N: usize> UBigInt<N> {
     ///
     /// # Constant-timedness
     /// This is a constant-time operation.
-    pub fn add(&self, rhs: &Self) -> Self {
+    pub const fn add(&self, rhs: &Self) -> Self {
         let mut buf = *self;
         buf.add_assign(rhs);
         buf
@@ -230,54 +247,45 @@ impl<const N: usize> UBigInt<N> {
     ///
     /// # Constant-timedness
     /// This is a constant-time operation.
-    pub fn add_assign(&mut self, rhs: &Self) {
-        let mut carry = false;
-        for i in 0..N {
-            // TODO: use core implementation once stabilized
-            (self.0[i], carry) = super::carry_add(self.0[i], rhs.0[i], carry);
-        }
+    pub const fn add_assign(&mut self, rhs: &Self) {
+        self.overflowing_add_assign(rhs);
     }
 
-    pub fn double(&self) -> Self {
+    pub const fn double(&self) -> Self {
         self.add(self)
     }
 
-    pub fn double_assign(&mut self) {
-        let mut carry = false;
-        for i in 0..N {
-            // TODO: use core implementation once stabilized
-            (self.0[i], carry) = super::carry_add(self.0[i], self.0[i], carry);
-        }
+    pub const fn double_assign(&mut self) {
+        *self = self.double();
     }
 
     /// Sets `self` to `self * digit`, wrapping on overflow.
     ///
     /// # Constant-timedness
     /// This is a constant-time operation.
-    pub fn mul_digit_assign(&mut self, digit: u64) {
-        let mut carry = 0;
-        for i in self.0.iter_mut() {
-            (*i, carry) = carry_mul(*i, digit, carry);
-        }
+    pub const fn mul_digit_assign(&mut self, digit: u64) {
+        self.overflowing_mul_digit_assign(digit);
     }
 
     /// Returns `self * digit`, wrapping on overflow.
-    pub fn mul_digit(&self, digit: u64) -> Self {
+    pub const fn mul_digit(&self, digit: u64) -> Self {
         let mut buf = *self;
         buf.mul_digit_assign(digit);
         buf
     }
 
-    pub fn overflowing_mul_digit(&self, digit: u64) -> (Self, u64) {
+    pub const fn overflowing_mul_digit(&self, digit: u64) -> (Self, u64) {
         let mut buf = *self;
         let overflow = buf.overflowing_mul_digit_assign(digit);
         (buf, overflow)
     }
 
-    pub fn overflowing_mul_digit_assign(&mut self, digit: u64) -> u64 {
+    pub const fn overflowing_mul_digit_assign(&mut self, digit: u64) -> u64 {
         let mut carry = 0;
-        for i in self.0.iter_mut() {
-            (*i, carry) = carry_mul(*i, digit, carry);
+        let mut i = 0;
+        while i < N {
+            (self.0[i], carry) = carry_mul(self.0[i], digit, carry);
+            i += 1;
         }
         carry
     }
@@ -294,7 +302,7 @@ impl<const N: usize> UBigInt<N> {
     ///
     /// # Constant-timedness
     /// This is a constant-time operation
-    pub fn sub(&self, rhs: &Self) -> Self {
+    pub const fn sub(&self, rhs: &Self) -> Self {
         let mut buf = *self;
         buf.sub_assign(rhs);
         buf
@@ -317,19 +325,15 @@ impl<const N: usize> UBigInt<N> {
     ///
     /// # Constant-timedness
     /// This is a constant-time operation
-    pub fn sub_assign(&mut self, rhs: &Self) {
-        let mut carry = false;
-        for i in 0..N {
-            // TODO: use libcore implementation once stabilized
-            (self.0[i], carry) = super::carry_sub(self.0[i], rhs.0[i], carry);
-        }
+    pub const fn sub_assign(&mut self, rhs: &Self) {
+        self.overflowing_sub_assign(rhs);
     }
 
     /// Returns `self` if `rhs` is `true`, otherwise `Self::ZERO`.
     ///
     /// # Constant-timedness
     /// This is a constant-time operation.
-    pub fn and_bool(&self, rhs: bool) -> Self {
+    pub const fn and_bool(&self, rhs: bool) -> Self {
         let mut buf = *self;
         buf.and_bool_assign(rhs);
         buf
@@ -339,11 +343,67 @@ impl<const N: usize> UBigInt<N> {
     ///
     /// # Constant-timedness
     /// This is a constant-time operation.
-    pub fn and_bool_assign(&mut self, rhs: bool) {
+    pub const fn and_bool_assign(&mut self, rhs: bool) {
         let mask = (rhs as u64).wrapping_neg();
-        for digit in self.0.iter_mut() {
-            *digit &= mask
+        let mut i = 0;
+        while i < N {
+            self.0[i] &= mask;
+            i += 1;
+        }
+    }
+
+    /// Swaps `self` and `other` if `choice` is `true`, otherwise leaves both unchanged.
+    ///
+    /// # Examples
+    /// ```
+    /// use crylib::big_int::UBigInt;
+    ///
+    /// let mut a = UBigInt([1, 2]);
+    /// let mut b = UBigInt([3, 4]);
+    ///
+    /// a.conditional_swap(&mut b, false);
+    /// assert_eq!(a, UBigInt([1, 2]));
+    ///
+    /// a.conditional_swap(&mut b, true);
+    /// assert_eq!(a, UBigInt([3, 4]));
+    /// assert_eq!(b, UBigInt([1, 2]));
+    /// ```
+    ///
+    /// # Constant-timedness
+    /// This is a constant-time operation.
+    pub const fn conditional_swap(&mut self, other: &mut Self, choice: bool) {
+        let mask = (choice as u64).wrapping_neg();
+        let mut i = 0;
+        while i < N {
+            let diff = (self.0[i] ^ other.0[i]) & mask;
+            self.0[i] ^= diff;
+            other.0[i] ^= diff;
+            i += 1;
+        }
+    }
+
+    /// Returns `true` if `self` is [`Self::ZERO`], otherwise `false`.
+    ///
+    /// Unlike `==`, this can be used in const contexts.
+    ///
+    /// # Examples
+    /// ```
+    /// use crylib::big_int::UBigInt;
+    ///
+    /// assert!(UBigInt::<4>::ZERO.is_zero());
+    /// assert!(!UBigInt([0x0, 0x0, 0x0, 0x1]).is_zero());
+    /// ```
+    ///
+    /// # Constant-timedness
+    /// This is a constant-time operation.
+    pub const fn is_zero(&self) -> bool {
+        let mut digits = 0;
+        let mut i = 0;
+        while i < N {
+            digits |= self.0[i];
+            i += 1;
         }
+        digits == 0
     }
 
     /// Shifts `self` to the left until the most significant bit is on.
@@ -353,64 +413,152 @@ impl<const N: usize> UBigInt<N> {
     ///
     /// # Constant-timedness
     /// This is a constant-time operation.
-    pub fn left_align(&mut self) -> u64 {
+    pub const fn left_align(&mut self) -> u64 {
         let num_digits = self.count_digits();
-        assert_ne!(num_digits, 0);
+        assert!(num_digits != 0);
         let left_shift = self.0[num_digits - 1].leading_zeros() as u64;
         self.shift_left_assign(left_shift);
         left_shift
     }
 
-    /// Performs a bitshift `rhs % 64` to the right and stores the result in `self`.
+    /// Shifts `self` to the right by `rhs` bits.
+    ///
+    /// Shifting by [`Self::BITS`] or more clears `self`.
     ///
     /// # Constant-timedness
     /// This is a constant-time operation.
-    pub fn shift_right_assign(&mut self, mut rhs: u64) {
-        rhs %= 64;
-        let left_shift = (64 - rhs) % 64;
-        let mask = ((rhs != 0) as u64).wrapping_neg();
-
-        for i in 0..N - 1 {
-            self.0[i] >>= rhs;
-            self.0[i] |= (self.0[i + 1] << left_shift) & mask;
-        }
-        self.0[N - 1] >>= rhs;
+    pub const fn shift_right_assign(&mut self, rhs: u64) {
+        self.shift_digits_right(Self::whole_digits(rhs));
+        self.shift_bits_right(rhs % u64::BITS as u64);
     }
 
-    /// Performs a bitshift `rhs % 64` to the right and returns the result.
+    /// Returns `self` shifted to the right by `rhs` bits.
+    ///
+    /// Shifting by [`Self::BITS`] or more returns [`Self::ZERO`].
+    ///
+    /// # Examples
+    /// ```
+    /// use crylib::big_int::UBigInt;
+    ///
+    /// let x = UBigInt([0x0, 0x0, 0xf0, 0x0]);
+    ///
+    /// assert_eq!(x.shift_right(132), UBigInt([0xf, 0x0, 0x0, 0x0]));
+    /// assert_eq!(x.shift_right(256), UBigInt::ZERO);
+    /// ```
     ///
     /// # Constant-timedness
     /// This is a constant-time operation.
-    pub fn shift_right(&self, rhs: u64) -> Self {
+    pub const fn shift_right(&self, rhs: u64) -> Self {
         let mut buf = *self;
         buf.shift_right_assign(rhs);
         buf
     }
 
-    /// Performs a bitshift `rhs % 64` to the left and stores the result in `self`.
+    /// Shifts `self` to the left by `rhs` bits, discarding the bits shifted out.
+    ///
+    /// Shifting by [`Self::BITS`] or more clears `self`.
+    ///
+    /// # Constant-timedness
+    /// This is a constant-time operation.
+    pub const fn shift_left_assign(&mut self, rhs: u64) {
+        self.shift_digits_left(Self::whole_digits(rhs));
+        self.shift_bits_left(rhs % u64::BITS as u64);
+    }
+
+    /// Returns `self` shifted to the left by `rhs` bits, discarding the bits shifted out.
+    ///
+    /// Shifting by [`Self::BITS`] or more returns [`Self::ZERO`].
+    ///
+    /// # Examples
+    /// ```
+    /// use crylib::big_int::UBigInt;
+    ///
+    /// let x = UBigInt([0xf, 0x0, 0x0, 0x0]);
+    ///
+    /// assert_eq!(x.shift_left(132), UBigInt([0x0, 0x0, 0xf0, 0x0]));
+    /// assert_eq!(x.shift_left(256), UBigInt::ZERO);
+    /// ```
     ///
     /// # Constant-timedness
     /// This is a constant-time operation.
-    pub fn shift_left_assign(&mut self, mut rhs: u64) {
-        rhs %= 64;
+    pub const fn shift_left(&self, rhs: u64) -> Self {
+        let mut buf = *self;
+        buf.shift_left_assign(rhs);
+        buf
+    }
+
+    /// Returns the number of whole digits in a shift by `rhs` bits, capped at `N`.
+    const fn whole_digits(rhs: u64) -> usize {
+        let digits = rhs / u64::BITS as u64;
+        if digits < N as u64 {
+            digits as usize
+        } else {
+            N
+        }
+    }
+
+    /// Shifts `self` to the right by `rhs` bits, where `rhs` < 64.
+    const fn shift_bits_right(&mut self, rhs: u64) {
+        let left_shift = (64 - rhs) % 64;
+        let mask = ((rhs != 0) as u64).wrapping_neg();
+
+        let mut i = 0;
+        while i < N - 1 {
+            self.0[i] >>= rhs;
+            self.0[i] |= (self.0[i + 1] << left_shift) & mask;
+            i += 1;
+        }
+        self.0[N - 1] >>= rhs;
+    }
+
+    /// Shifts `self` to the left by `rhs` bits, where `rhs` < 64.
+    const fn shift_bits_left(&mut self, rhs: u64) {
         let right_shift = (64 - rhs) % 64;
         let mask = ((rhs != 0) as u64).wrapping_neg();
 
-        for i in (1..N).rev() {
+        let mut i = N - 1;
+        while i > 0 {
             self.0[i] <<= rhs;
             self.0[i] |= (self.0[i - 1] >> right_shift) & mask;
+            i -= 1;
         }
         self.0[0] <<= rhs;
     }
 
-    /// Performs a bitshift `rhs` to the right and returns the result.
+    /// Shifts `self` to the right by `digits` whole digits, where `digits` <= `N`.
     ///
-    /// # Constant-timedness
-    /// This is a constant-time operation.
-    pub fn shift_left(&self, rhs: u64) -> Self {
-        let mut buf = *self;
-        buf.shift_left_assign(rhs);
-        buf
+    /// The shift is done in one pass per bit of `digits`, so the memory access pattern doesn't
+    /// depend on it.
+    const fn shift_digits_right(&mut self, digits: usize) {
+        let mut step = 1;
+        while step <= N {
+            let mask = (((digits & step) != 0) as u64).wrapping_neg();
+            let mut i = 0;
+            while i < N {
+                let shifted = if i + step < N { self.0[i + step] } else { 0 };
+                self.0[i] = (self.0[i] & !mask) | (shifted & mask);
+                i += 1;
+            }
+            step <<= 1;
+        }
+    }
+
+    /// Shifts `self` to the left by `digits` whole digits, where `digits` <= `N`.
+    ///
+    /// The shift is done in one pass per bit of `digits`, so the memory access pattern doesn't
+    /// depend on it.
+    const fn shift_digits_left(&mut self, digits: usize) {
+        let mut step = 1;
+        while step <= N {
+            let mask = (((digits & step) != 0) as u64).wrapping_neg();
+            let mut i = N;
+            while i > 0 {
+                i -= 1;
+                let shifted = if i >= step { self.0[i - step] } else { 0 };
+                self.0[i] = (self.0[i] & !mask) | (shifted & mask);
+            }
+            step <<= 1;
+        }
     }
 
     /// Converts `self` into its one's compliment.
@@ -437,9 +585,11 @@ impl<const N: usize> UBigInt<N> {
     ///
     /// # Constant-timedness
     /// This is a constant-time operation.
-    pub fn xor_assign(&mut self, rhs: &Self) {
-        for (digit, rhs_digit) in self.0.iter_mut().zip(rhs.0) {
-            *digit ^= rhs_digit;
+    pub const fn xor_assign(&mut self, rhs: &Self) {
+        let mut i = 0;
+        while i < N {
+            self.0[i] ^= rhs.0[i];
+            i += 1;
         }
     }
 
@@ -447,7 +597,7 @@ impl<const N: usize> UBigInt<N> {
     ///
     /// # Constant-timedness
     /// This is a constant-time operation.
-    pub fn xor(&self, rhs: &Self) -> Self {
+    pub const fn xor(&self, rhs: &Self) -> Self {
         let mut buf = *self;
         buf.xor_assign(rhs);
         buf
@@ -571,44 +721,107 @@ impl<const N: usize> UBigInt<N> {
     }
 
     /// Resizes a `UBigInt<N>` to a `UBigInt<O>`, truncating most significant bits if necessary.
-    pub fn resize<const O: usize>(self) -> UBigInt<O> {
-        let min = core::cmp::min(O, N);
+    pub const fn resize<const O: usize>(self) -> UBigInt<O> {
         let mut new = UBigInt([0; O]);
-        new.0[..min].copy_from_slice(&self.0[..min]);
+        let mut i = 0;
+        while i < O && i < N {
+            new.0[i] = self.0[i];
+            i += 1;
+        }
         new
     }
 
-    pub fn get_bit(&self, bit: usize) -> bool {
-        assert!(bit < size_of::<[u64; N]>() * 8);
+    /// Returns whether bit `bit` of `self` is set, counting from the least significant bit.
+    ///
+    /// # Panics
+    /// This function will panic if `bit` >= [`Self::BITS`].
+    ///
+    /// # Constant-timedness
+    /// This is a constant-time operation with respect to `self`.
+    pub const fn get_bit(&self, bit: usize) -> bool {
+        assert!(bit < Self::BITS);
         self.0[bit / (u64::BITS as usize)] & 1 << (bit % (u64::BITS as usize)) != 0
     }
 
-    pub fn count_bits(&self) -> usize {
-        let num_ditis = self.count_digits().saturating_sub(1);
-        let bits = u64::BITS as usize - self.0[num_ditis].leading_zeros() as usize;
-        num_ditis * u64::BITS as usize + bits
+    /// Returns the `width` bits of `self` starting at bit `start`, counting from the least
+    /// significant bit.
+    ///
+    /// Bits past the end of `self` are read as `0`, so the last window of a scalar can be read
+    /// without special-casing it.
+    ///
+    /// # Examples
+    /// ```
+    /// use crylib::big_int::UBigInt;
+    ///
+    /// let x = UBigInt([0xfedcba9876543210, 0x0123456789abcdef]);
+    ///
+    /// assert_eq!(x.get_window(4, 8), 0x21);
+    /// assert_eq!(x.get_window(60, 8), 0xff);
+    /// assert_eq!(x.get_window(124, 8), 0x0);
+    /// ```
+    ///
+    /// # Panics
+    /// This function will panic if `width` > 64.
+    ///
+    /// # Constant-timedness
+    /// This is a constant-time operation with respect to `self`.
+    pub fn get_window(&self, start: usize, width: u32) -> u64 {
+        assert!(width <= u64::BITS, "windows can be at most 64 bits wide");
+        let digit = start / u64::BITS as usize;
+        let offset = (start % u64::BITS as usize) as u32;
+
+        let low = self.0.get(digit).map_or(0, |low| low >> offset);
+        let high = match self.0.get(digit + 1) {
+            // `checked_shl` avoids overflowing when `offset` is 0
+            Some(high) => high.checked_shl(u64::BITS - offset).unwrap_or(0),
+            None => 0,
+        };
+        (low | high) & u64::MAX.checked_shr(u64::BITS - width).unwrap_or(0)
+    }
+
+    /// Returns the number of bits needed to represent `self`, which is `0` for [`Self::ZERO`].
+    ///
+    /// # Examples
+    /// ```
+    /// use crylib::big_int::UBigInt;
+    ///
+    /// assert_eq!(UBigInt([0x0, 0x1, 0x0, 0x0]).bit_len(), 65);
+    /// assert_eq!(UBigInt::<4>::MAX.bit_len(), 256);
+    /// assert_eq!(UBigInt::<4>::ZERO.bit_len(), 0);
+    /// ```
+    ///
+    /// # Constant-timedness
+    /// This is a constant-time operation.
+    pub const fn bit_len(&self) -> usize {
+        let num_digits = self.count_digits().saturating_sub(1);
+        let bits = u64::BITS as usize - self.0[num_digits].leading_zeros() as usize;
+        num_digits * u64::BITS as usize + bits
     }
 }
 
 // TODO: figure out what this does to see if it can be simplified
-fn partial_div(m0: u64, m1: u64, d1: u64, d0: u64) -> u64 {
+const fn partial_div(m0: u64, m1: u64, d1: u64, d0: u64) -> u64 {
     let mut r = ((m0 as u128) << 64) | m1 as u128;
     let mut d = ((d0 as u128) << 64) | d1 as u128;
     let mut q: u64 = 0;
 
-    for _ in 0..64 {
+    let mut i = 0;
+    while i < 64 {
         q <<= 1;
-        if r >= d {
-            q |= 1;
-            r -= d;
-        }
+        // subtract and keep the difference only if it didn't underflow, without a branch, so that
+        // the time doesn't depend on the digits
+        let (difference, underflowed) = r.overflowing_sub(d);
+        let keep = (underflowed as u128).wrapping_sub(1);
+        r = (difference & keep) | (r & !keep);
+        q |= !underflowed as u64;
         d >>= 1;
+        i += 1;
     }
 
     let mask = (q >> (64 - 1)).wrapping_neg();
 
     q <<= 1;
-    q |= (r >= d) as u64;
+    q |= !r.overflowing_sub(d).1 as u64;
 
     q | mask
 }
@@ -621,21 +834,25 @@ macro_rules! impl_non_generic {
             ///
             /// # Constant-timedness
             /// This is a constant-time operation.
-            pub fn widening_mul(&self, rhs: &Self) -> UBigInt<{ $n * 2 }> {
+            pub const fn widening_mul(&self, rhs: &Self) -> UBigInt<{ $n * 2 }> {
                 let mut product = [0u64; $n * 2];
-                for i in 0..self.len() {
+                let mut i = 0;
+                while i < $n {
                     let mut carry = 0;
-                    for j in 0..rhs.len() {
+                    let mut j = 0;
+                    while j < $n {
                         // TODO: use libcore carry_mul once stabilized
                         let partial_product;
                         (partial_product, carry) = super::carry_mul(self.0[i], rhs.0[j], carry);
                         let (sum, overflowed) = product[i + j].overflowing_add(partial_product);
                         product[i + j] = sum;
                         carry += overflowed as u64;
+                        j += 1;
                     }
-                    product[i + rhs.len()] = carry;
+                    product[i + $n] = carry;
+                    i += 1;
                 }
-                product.into()
+                UBigInt(product)
             }
 
             /// Left-shifts `self` by `rhs % 64` bits.
@@ -644,19 +861,21 @@ macro_rules! impl_non_generic {
             ///
             /// # Constant-timedness
             /// This is a constant-time operation.
-            pub fn widening_shift_left(&self, mut rhs: u64) -> UBigInt<{ $n + 1 }> {
+            pub const fn widening_shift_left(&self, mut rhs: u64) -> UBigInt<{ $n + 1 }> {
                 rhs %= 64;
                 let mut expanded = [0u64; $n + 1];
                 let right_shift = (64 - rhs) % 64;
                 let mask = ((rhs != 0) as u64).wrapping_neg();
 
                 expanded[$n] = self.0[$n - 1] >> right_shift & mask;
-                for i in (1..$n).rev() {
+                let mut i = $n - 1;
+                while i > 0 {
                     expanded[i] = self.0[i] << rhs;
                     expanded[i] |= (self.0[i - 1] >> right_shift) & mask;
+                    i -= 1;
                 }
                 expanded[0] = self.0[0] << rhs;
-                expanded.into()
+                UBigInt(expanded)
             }
 
             /// Calculates `self / rhs`, returning the quotient and the remainder.
@@ -665,11 +884,13 @@ macro_rules! impl_non_generic {
             /// This function will panic if `divisor` equals `Self::ZERO`.
             ///
             /// # Constant-timedness
-            /// TODO: document constant-timedness
-            pub fn div(&self, rhs: &Self) -> (Self, Self) {
-                assert_ne!(*rhs, Self::ZERO);
+            /// This is a constant-time operation with respect to `self`, but not `rhs`.
+            pub const fn div(&self, rhs: &Self) -> (Self, Self) {
+                assert!(!rhs.is_zero(), "attempt to divide by zero");
 
-                let num_len = self.count_digits() + 1;
+                // every digit of `self` is processed, even leading zeros, so that a smaller
+                // numerator doesn't take less time
+                let num_len: usize = $n + 1;
                 let div_len = rhs.count_digits();
 
                 // Normalize both numerator and denominator
@@ -694,24 +915,31 @@ macro_rules! impl_non_generic {
                 let mut quotient = Self::ZERO;
                 let mut quotient_pos = num_loops;
 
-                for (win_bot, win_top) in (0..num_loops).zip(num_len - num_loops..num_len).rev() {
+                let mut win_bot = num_loops;
+                while win_bot > 0 {
+                    win_bot -= 1;
+                    let win_top = win_bot + num_len - num_loops;
                     let mut temp = UBigInt::<{ $n + 1 }>::ZERO;
                     let mut partial_quotient =
                         partial_div(snum.0[win_top], snum.0[win_top - 1], d1, d0);
 
                     // multiply `sdiv` by `partial_quotient`
                     let mut mul_carry = 0;
-                    for i in 0..div_len {
+                    let mut i = 0;
+                    while i < div_len {
                         (temp.0[i], mul_carry) =
                             super::carry_mul(sdiv.0[i], partial_quotient, mul_carry);
+                        i += 1;
                     }
                     temp.0[div_len] = mul_carry;
 
                     // subtract result from `snum`
                     let mut sub_carry = false;
-                    for i in 0..div_len + 1 {
+                    let mut i = 0;
+                    while i < div_len + 1 {
                         (snum.0[win_bot + i], sub_carry) =
                             super::carry_sub(snum.0[win_bot + i], temp.0[i], sub_carry);
+                        i += 1;
                     }
 
                     partial_quotient -= sub_carry as u64;
@@ -719,9 +947,11 @@ macro_rules! impl_non_generic {
                     // add back if overflow occured
                     let mask = (sub_carry as u64).wrapping_neg();
                     let mut add_carry = false;
-                    for i in 0..div_len {
+                    let mut i = 0;
+                    while i < div_len {
                         (snum.0[win_bot + i], add_carry) =
                             super::carry_add(snum.0[win_bot + i], sdiv.0[i] & mask, add_carry);
+                        i += 1;
                     }
                     snum.0[win_top] = snum.0[win_top].wrapping_add(add_carry as u64);
                     debug_assert!(snum.0[win_top] == 0);
@@ -731,12 +961,12 @@ macro_rules! impl_non_generic {
                 }
                 // Un-normalize remainder
                 snum.shift_right_assign(norm_shift);
-                // we can safely unwrap because because `snum.len()` is 5
-                (quotient, snum.0[..$n].try_into().unwrap())
+                // the remainder is less than `rhs`, so the top digit of `snum` is zero
+                (quotient, snum.resize())
             }
 
             /// Divides `self` by `rhs` and stores the result in `self`
-            pub fn div_assign(&mut self, rhs: &Self) {
+            pub const fn div_assign(&mut self, rhs: &Self) {
                 *self = self.div(rhs).0;
             }
 
@@ -1113,23 +1343,183 @@ mod tests {
     }
 
     #[test]
-    fn count_bits() {
+    fn bit_len() {
         let z = UBigInt([
             0x0000000000000000,
             0x0123456789abcdef,
             0x0000000000000000,
             0xf123456789abcdef,
         ]);
-        assert_eq!(z.count_bits(), 256);
+        assert_eq!(z.bit_len(), 256);
         let x = UBigInt([
             0x0123456789abcdef,
             0xfedcba9876543210,
             0x0123456789abcdef,
             0x0000000000000000,
         ]);
-        assert_eq!(x.count_bits(), 185);
+        assert_eq!(x.bit_len(), 185);
+
+        assert_eq!(UBigInt::<4>::ZERO.bit_len(), 0);
+        assert_eq!(UBigInt::<4>::ONE.bit_len(), 1);
+    }
 
-        assert_eq!(UBigInt::<4>::ZERO.count_bits(), 0);
-        assert_eq!(UBigInt::<4>::ONE.count_bits(), 1);
+    /// Reads bit `bit` of `x`, treating bits past the end as `0`.
+    fn bit_or_zero(x: &UBigInt<4>, bit: usize) -> bool {
+        bit < UBigInt::<4>::BITS && x.get_bit(bit)
+    }
+
+    #[test]
+    fn shift_every_amount() {
+        let x = UBigInt([
+            0x0123456789abcdef,
+            0xfedcba9876543210,
+            0x8000000000000001,
+            0xf0f0f0f0f0f0f0f0,
+        ]);
+        for value in [x, UBigInt::MAX, UBigInt::ONE] {
+            for shift in 0..=UBigInt::<4>::BITS + 64 {
+                let left = value.shift_left(shift as u64);
+                let right = value.shift_right(shift as u64);
+                for bit in 0..UBigInt::<4>::BITS {
+                    let expected_left = bit >= shift && value.get_bit(bit - shift);
+                    assert_eq!(left.get_bit(bit), expected_left, "{shift} {bit}");
+                    assert_eq!(right.get_bit(bit), bit_or_zero(&value, bit + shift));
+                }
+            }
+        }
+
+        assert_eq!(x.shift_left(u64::MAX), UBigInt::ZERO);
+        assert_eq!(x.shift_right(u64::MAX), UBigInt::ZERO);
+    }
+
+    #[test]
+    fn get_window() {
+        let x = UBigInt([
+            0x0123456789abcdef,
+            0xfedcba9876543210,
+            0x8000000000000001,
+            0xf0f0f0f0f0f0f0f0,
+        ]);
+        for start in 0..=UBigInt::<4>::BITS + 64 {
+            for width in 0..=u64::BITS {
+                let mut expected = 0;
+                for bit in (0..width as usize).rev() {
+                    expected = expected << 1 | bit_or_zero(&x, start + bit) as u64;
+                }
+                assert_eq!(x.get_window(start, width), expected, "{start} {width}");
+            }
+        }
+
+        assert_eq!(UBigInt::<4>::MAX.get_window(192, 64), u64::MAX);
+        assert_eq!(UBigInt::<4>::MAX.get_window(250, 8), 0x3f);
+        assert_eq!(UBigInt::<4>::MAX.get_window(usize::MAX, 64), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn get_window_too_wide() {
+        UBigInt::<4>::MAX.get_window(0, 65);
+    }
+
+    mod props {
+        extern crate std;
+
+        use super::UBigInt;
+        use num_bigint::BigUint;
+        use proptest::prelude::*;
+
+        fn reference<const N: usize>(x: &UBigInt<N>) -> BigUint {
+            BigUint::from_slice(
+                &x.0.iter()
+                    .flat_map(|digit| [*digit as u32, (digit >> 32) as u32])
+                    .collect::<std::vec::Vec<_>>(),
+            )
+        }
+
+        fn modulus() -> BigUint {
+            BigUint::from(1u8) << 256
+        }
+
+        fn ubig() -> impl Strategy<Value = UBigInt<4>> {
+            // sparse values exercise carries and leading zeros more than uniform ones do
+            prop_oneof![
+                any::<[u64; 4]>(),
+                any::<[u64; 4]>().prop_map(|mut digits| {
+                    digits[2] = 0;
+                    digits[3] = 0;
+                    digits
+                }),
+                Just([u64::MAX; 4]),
+            ]
+            .prop_map(UBigInt)
+        }
+
+        proptest! {
+            #[test]
+            fn add(x in ubig(), y in ubig()) {
+                let (sum, overflowed) = x.overflowing_add(&y);
+                let expected = reference(&x) + reference(&y);
+                prop_assert_eq!(reference(&sum), &expected % modulus());
+                prop_assert_eq!(overflowed, expected >= modulus());
+            }
+
+            #[test]
+            fn sub(x in ubig(), y in ubig()) {
+                let (diff, overflowed) = x.overflowing_sub(&y);
+                let expected = (reference(&x) + modulus() - reference(&y)) % modulus();
+                prop_assert_eq!(reference(&diff), expected);
+                prop_assert_eq!(overflowed, x < y);
+                prop_assert_eq!(diff.add(&y), x);
+            }
+
+            #[test]
+            fn widening_mul(x in ubig(), y in ubig()) {
+                prop_assert_eq!(
+                    reference(&x.widening_mul(&y)),
+                    reference(&x) * reference(&y)
+                );
+            }
+
+            #[test]
+            fn div(x in ubig(), y in ubig()) {
+                prop_assume!(y != UBigInt::ZERO);
+                let (quotient, remainder) = x.div(&y);
+                prop_assert_eq!(reference(&quotient), reference(&x) / reference(&y));
+                prop_assert_eq!(reference(&remainder), reference(&x) % reference(&y));
+            }
+
+            #[test]
+            fn shift(x in ubig(), shift in 0u64..320) {
+                prop_assert_eq!(
+                    reference(&x.shift_left(shift)),
+                    (reference(&x) << shift) % modulus()
+                );
+                prop_assert_eq!(reference(&x.shift_right(shift)), reference(&x) >> shift);
+            }
+
+            #[test]
+            fn cmp(x in ubig(), y in ubig()) {
+                prop_assert_eq!(x.cmp(&y), reference(&x).cmp(&reference(&y)));
+            }
+
+            #[test]
+            fn bit_len(x in ubig()) {
+                prop_assert_eq!(x.bit_len() as u64, reference(&x).bits());
+            }
+
+            #[test]
+            fn get_window(x in ubig(), start in 0usize..320, width in 0u32..=64) {
+                let mask = (BigUint::from(1u8) << width) - 1u8;
+                let expected = (reference(&x) >> start) & mask;
+                prop_assert_eq!(BigUint::from(x.get_window(start, width)), expected);
+            }
+
+            #[test]
+            fn be_bytes(x in ubig()) {
+                let bytes = x.to_be_bytes();
+                prop_assert_eq!(BigUint::from_bytes_be(&bytes), reference(&x));
+                prop_assert_eq!(UBigInt::<4>::from_be_bytes(bytes), x);
+            }
+        }
     }
 }
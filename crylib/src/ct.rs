@@ -0,0 +1,53 @@
+//! Constant-time operations on secret data.
+//!
+//! Anything that compares tags, MACs, or other secrets should use these instead of `==`,
+//! which returns as soon as it finds a difference and so leaks where the difference is.
+
+/// Returns whether `a` and `b` are equal, taking the same time wherever they differ.
+///
+/// The lengths are not considered secret: slices of different lengths are unequal immediately.
+///
+/// # Examples
+/// ```
+/// use crylib::ct::ct_eq;
+///
+/// assert!(ct_eq(b"tag", b"tag"));
+/// assert!(!ct_eq(b"tag", b"tab"));
+/// assert!(!ct_eq(b"tag", b"tags"));
+/// ```
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut diff = 0;
+    for (a, b) in a.iter().zip(b) {
+        diff |= a ^ b;
+    }
+    // stop the compiler from turning the loop back into an early-exit comparison
+    core::hint::black_box(diff) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ct_eq;
+
+    #[test]
+    fn every_position() {
+        let a = [0x5a; 32];
+        assert!(ct_eq(&a, &a));
+        for i in 0..a.len() {
+            for bit in 0..8 {
+                let mut b = a;
+                b[i] ^= 1 << bit;
+                assert!(!ct_eq(&a, &b), "{i} {bit}");
+            }
+        }
+    }
+
+    #[test]
+    fn lengths() {
+        assert!(ct_eq(&[], &[]));
+        assert!(!ct_eq(&[0], &[]));
+        assert!(!ct_eq(&[0; 16], &[0; 32]));
+    }
+}
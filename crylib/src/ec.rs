@@ -1,10 +1,12 @@
 //! Elliptic curve cryptography.
+pub mod ecdh;
 pub mod ecdsa;
 mod point;
 mod secp256r1;
 
-pub use point::affine::AffinePoint;
+pub use point::affine::{AffinePoint, COMPRESSED_POINT_SIZE, UNCOMPRESSED_POINT_SIZE};
 pub use point::projective::ProjectivePoint;
+pub use point::{AffineInfinity, InvalidPointEncoding};
 pub use secp256r1::Secp256r1;
 
 use crate::finite_field::{FieldElement, FiniteField};
@@ -12,6 +14,9 @@ use crate::finite_field::{FieldElement, FiniteField};
 /// A trait for describining an elliptic curve over a finite field in Weierstrass form.
 ///
 /// The curve is defined by the equation `Y^2 = X^3 + A*X + B`.
+///
+/// Generic code should get everything it needs to know about a curve from here, so adding a
+/// curve only means implementing this trait.
 pub trait EllipticCurve: FiniteField {
     /// The generator point used for elliptic-curve cryptography.
     const BASE_POINT: AffinePoint<Self>;
@@ -22,5 +27,70 @@ pub trait EllipticCurve: FiniteField {
     /// The constant-term coefficient of the curve.
     const B: FieldElement<Self>;
 
+    /// The number of points on the curve divided by the order of [`Self::BASE_POINT`].
+    ///
+    /// If this isn't 1, the curve has small subgroups, and points received from a peer must be
+    /// checked against them.
+    const COFACTOR: u64;
+
+    /// The field of scalars, whose modulus is the order of [`Self::BASE_POINT`].
     type Order: FiniteField;
+
+    /// Returns whether `(x, y)` satisfies the curve equation.
+    fn is_on_curve(x: &FieldElement<Self>, y: &FieldElement<Self>) -> bool {
+        satisfies_equation::<Self>(x, y)
+    }
+}
+
+/// The body of [`EllipticCurve::is_on_curve`], which can't be a `const fn` itself.
+const fn satisfies_equation<C: EllipticCurve>(x: &FieldElement<C>, y: &FieldElement<C>) -> bool {
+    y.sqr().sub(&curve_rhs::<C>(x)).inner().is_zero()
+}
+
+/// Returns `x^3 + A*x + B`, which is `y^2` for the points on the curve with x-coordinate `x`.
+const fn curve_rhs<C: EllipticCurve>(x: &FieldElement<C>) -> FieldElement<C> {
+    let mut rhs = x.sqr();
+    rhs.add_assign(&C::A);
+    rhs.mul_assign(x);
+    rhs.add_assign(&C::B);
+    rhs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EllipticCurve, Secp256r1};
+    use crate::big_int::UBigInt;
+    use crate::finite_field::{FieldElement, FiniteField};
+
+    /// Checks that a curve's constants are consistent with each other.
+    fn check_params<C: EllipticCurve>() {
+        let base_point = C::BASE_POINT;
+        assert!(C::is_on_curve(base_point.x_ref(), base_point.y_ref()));
+        assert!(!C::is_on_curve(
+            base_point.x_ref(),
+            &base_point.y().double()
+        ));
+
+        // the discriminant, 4a^3 + 27b^2, must be non-zero
+        let mut discriminant = C::A.sqr().mul(&C::A);
+        discriminant.mul_digit_assign(4);
+        let mut b_term = C::B.sqr();
+        b_term.mul_digit_assign(27);
+        discriminant.add_assign(&b_term);
+        assert_ne!(discriminant, FieldElement::ZERO);
+
+        let order = C::Order::MODULUS;
+        assert!(base_point.as_projective().mul_scalar(&order).is_infinity());
+        assert!(!base_point
+            .as_projective()
+            .mul_scalar(&order.sub(&UBigInt::ONE))
+            .is_infinity());
+        assert_ne!(C::COFACTOR, 0);
+    }
+
+    #[test]
+    fn secp256r1() {
+        check_params::<Secp256r1>();
+        assert_eq!(Secp256r1::COFACTOR, 1);
+    }
 }
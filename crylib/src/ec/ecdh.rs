@@ -0,0 +1,276 @@
+//! Elliptic-curve Diffie-Hellman key agreement.
+//!
+//! Each party generates a [`KeyPair`] and sends the other its public key, encoded as an
+//! uncompressed SEC 1 point:
+//!
+//! ```text
+//! 0x04 || X || Y
+//! ```
+//!
+//! This is the format of the `key_share` extension of TLS 1.3 for the NIST curves
+//! (RFC 8446 section 4.2.8.2). The shared secret is the x-coordinate of the product of one party's
+//! private key and the other's public key. Points received from a peer are checked to be on the
+//! curve before they are used, since multiplying by a point on a weaker curve could leak the
+//! private key.
+//!
+//! # Examples
+//!
+//! ```
+//! use crylib::big_int::UBigInt;
+//! use crylib::ec::ecdh::KeyPair;
+//! use crylib::ec::Secp256r1;
+//! use crylib::finite_field::FieldElement;
+//!
+//! // a real application MUST use a cryptographically secure random number generator
+//! let alice = KeyPair::<Secp256r1>::generate(|| FieldElement::new(UBigInt([1, 2, 3, 4])));
+//! let bob = KeyPair::<Secp256r1>::generate(|| FieldElement::new(UBigInt([5, 6, 7, 8])));
+//!
+//! let alice_secret = alice.shared_secret(&bob.encoded_public_key()).unwrap();
+//! let bob_secret = bob.shared_secret(&alice.encoded_public_key()).unwrap();
+//! assert_eq!(alice_secret, bob_secret);
+//! ```
+use super::{AffinePoint, EllipticCurve};
+use crate::finite_field::{FieldElement, FiniteField};
+
+/// The size of a field element, in bytes.
+pub const ELEMENT_SIZE: usize = 32;
+
+/// The size of an uncompressed point.
+pub const POINT_SIZE: usize = super::UNCOMPRESSED_POINT_SIZE;
+
+/// The error that is returned when a peer's public key isn't a valid point.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct InvalidPublicKey;
+
+impl core::fmt::Display for InvalidPublicKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("the public key is not a valid point")
+    }
+}
+
+impl core::error::Error for InvalidPublicKey {}
+
+/// A private key and the public key that goes with it.
+///
+/// The private key is overwritten with zeros when the key pair is dropped.
+pub struct KeyPair<C: EllipticCurve> {
+    priv_key: FieldElement<C::Order>,
+    pub_key: AffinePoint<C>,
+}
+
+impl<C: EllipticCurve> KeyPair<C> {
+    /// Generates a new key pair.
+    ///
+    /// `random_num_gen` must be a cryptographically secure random number generator.
+    /// It is called again if it returns zero.
+    pub fn generate(random_num_gen: impl Fn() -> FieldElement<C::Order>) -> Self {
+        loop {
+            if let Some(key_pair) = Self::from_private_key(random_num_gen()) {
+                return key_pair;
+            }
+        }
+    }
+
+    /// Creates a key pair from an existing private key.
+    ///
+    /// Returns [`None`] if `priv_key` is zero.
+    pub fn from_private_key(priv_key: FieldElement<C::Order>) -> Option<Self> {
+        let pub_key = public_key(&priv_key)?;
+        Some(Self { priv_key, pub_key })
+    }
+
+    /// Returns the public key.
+    pub const fn public_key(&self) -> &AffinePoint<C> {
+        &self.pub_key
+    }
+
+    /// Returns the public key as an uncompressed point.
+    pub fn encoded_public_key(&self) -> [u8; POINT_SIZE] {
+        encode_point(&self.pub_key)
+    }
+
+    /// Returns the shared secret with the peer whose public key is the uncompressed point
+    /// `peer_key`.
+    pub fn shared_secret(&self, peer_key: &[u8]) -> Result<[u8; ELEMENT_SIZE], InvalidPublicKey> {
+        shared_secret::<C>(&self.priv_key, peer_key)
+    }
+}
+
+impl<C: EllipticCurve> Drop for KeyPair<C> {
+    fn drop(&mut self) {
+        // SAFETY: `self.priv_key` is a valid reference, and zero is a valid field element
+        unsafe { core::ptr::write_volatile(&mut self.priv_key, FieldElement::ZERO) };
+    }
+}
+
+/// Returns the public key that goes with `priv_key`, or [`None`] if `priv_key` is zero.
+pub fn public_key<C: EllipticCurve>(priv_key: &FieldElement<C::Order>) -> Option<AffinePoint<C>> {
+    C::BASE_POINT
+        .as_projective()
+        .mul_scalar(priv_key.inner())
+        .as_affine()
+}
+
+/// Returns the shared secret between `priv_key` and the peer whose public key is the uncompressed
+/// point `peer_key`.
+///
+/// Returns [`InvalidPublicKey`] if `peer_key` isn't a point on the curve, or if the product is
+/// the point at infinity.
+pub fn shared_secret<C: EllipticCurve>(
+    priv_key: &FieldElement<C::Order>,
+    peer_key: &[u8],
+) -> Result<[u8; ELEMENT_SIZE], InvalidPublicKey> {
+    let peer_key = decode_point::<C>(peer_key)?;
+    let shared_point = peer_key
+        .as_projective()
+        .mul_scalar(priv_key.inner())
+        .as_affine()
+        .ok_or(InvalidPublicKey)?;
+    Ok(shared_point.x().to_bytes())
+}
+
+/// Encodes `point` as an uncompressed point.
+pub fn encode_point<C: EllipticCurve>(point: &AffinePoint<C>) -> [u8; POINT_SIZE] {
+    point.to_bytes()
+}
+
+/// Decodes an uncompressed point.
+///
+/// Returns [`InvalidPublicKey`] if `bytes` isn't an uncompressed point, if a coordinate isn't
+/// reduced, or if the point isn't on the curve. On curves with a cofactor, points outside of the
+/// subgroup generated by the base point are rejected as well.
+///
+/// Compressed points are rejected, since TLS only allows uncompressed ones
+/// ([RFC 8446 section 4.2.8.2](https://datatracker.ietf.org/doc/html/rfc8446#section-4.2.8.2)).
+pub fn decode_point<C: EllipticCurve>(bytes: &[u8]) -> Result<AffinePoint<C>, InvalidPublicKey> {
+    if bytes.first() != Some(&0x04) {
+        return Err(InvalidPublicKey);
+    }
+    let point = AffinePoint::from_bytes(bytes).map_err(|_| InvalidPublicKey)?;
+
+    if C::COFACTOR != 1
+        && !point
+            .as_projective()
+            .mul_scalar(&C::Order::MODULUS)
+            .is_infinity()
+    {
+        return Err(InvalidPublicKey);
+    }
+    Ok(point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_point, encode_point, InvalidPublicKey, KeyPair, POINT_SIZE};
+    use crate::big_int::UBigInt;
+    use crate::ec::{EllipticCurve, Secp256r1};
+    use crate::finite_field::{FieldElement, FiniteField};
+
+    fn key_pair(priv_key: [u8; 32]) -> KeyPair<Secp256r1> {
+        KeyPair::from_private_key(FieldElement::new(UBigInt::<4>::from_be_bytes(priv_key))).unwrap()
+    }
+
+    fn point(x: [u8; 32], y: [u8; 32]) -> [u8; POINT_SIZE] {
+        let mut point = [0x04; POINT_SIZE];
+        point[1..33].copy_from_slice(&x);
+        point[33..].copy_from_slice(&y);
+        point
+    }
+
+    #[test]
+    fn rfc_5903() {
+        // https://datatracker.ietf.org/doc/html/rfc5903#section-8.1
+        let initiator = key_pair([
+            0xc8, 0x8f, 0x01, 0xf5, 0x10, 0xd9, 0xac, 0x3f, 0x70, 0xa2, 0x92, 0xda, 0xa2, 0x31,
+            0x6d, 0xe5, 0x44, 0xe9, 0xaa, 0xb8, 0xaf, 0xe8, 0x40, 0x49, 0xc6, 0x2a, 0x9c, 0x57,
+            0x86, 0x2d, 0x14, 0x33,
+        ]);
+        let initiator_pub = point(
+            [
+                0xda, 0xd0, 0xb6, 0x53, 0x94, 0x22, 0x1c, 0xf9, 0xb0, 0x51, 0xe1, 0xfe, 0xca, 0x57,
+                0x87, 0xd0, 0x98, 0xdf, 0xe6, 0x37, 0xfc, 0x90, 0xb9, 0xef, 0x94, 0x5d, 0x0c, 0x37,
+                0x72, 0x58, 0x11, 0x80,
+            ],
+            [
+                0x52, 0x71, 0xa0, 0x46, 0x1c, 0xdb, 0x82, 0x52, 0xd6, 0x1f, 0x1c, 0x45, 0x6f, 0xa3,
+                0xe5, 0x9a, 0xb1, 0xf4, 0x5b, 0x33, 0xac, 0xcf, 0x5f, 0x58, 0x38, 0x9e, 0x05, 0x77,
+                0xb8, 0x99, 0x0b, 0xb3,
+            ],
+        );
+        let responder = key_pair([
+            0xc6, 0xef, 0x9c, 0x5d, 0x78, 0xae, 0x01, 0x2a, 0x01, 0x11, 0x64, 0xac, 0xb3, 0x97,
+            0xce, 0x20, 0x88, 0x68, 0x5d, 0x8f, 0x06, 0xbf, 0x9b, 0xe0, 0xb2, 0x83, 0xab, 0x46,
+            0x47, 0x6b, 0xee, 0x53,
+        ]);
+        let responder_pub = point(
+            [
+                0xd1, 0x2d, 0xfb, 0x52, 0x89, 0xc8, 0xd4, 0xf8, 0x12, 0x08, 0xb7, 0x02, 0x70, 0x39,
+                0x8c, 0x34, 0x22, 0x96, 0x97, 0x0a, 0x0b, 0xcc, 0xb7, 0x4c, 0x73, 0x6f, 0xc7, 0x55,
+                0x44, 0x94, 0xbf, 0x63,
+            ],
+            [
+                0x56, 0xfb, 0xf3, 0xca, 0x36, 0x6c, 0xc2, 0x3e, 0x81, 0x57, 0x85, 0x4c, 0x13, 0xc5,
+                0x8d, 0x6a, 0xac, 0x23, 0xf0, 0x46, 0xad, 0xa3, 0x0f, 0x83, 0x53, 0xe7, 0x4f, 0x33,
+                0x03, 0x98, 0x72, 0xab,
+            ],
+        );
+        let shared_secret = [
+            0xd6, 0x84, 0x0f, 0x6b, 0x42, 0xf6, 0xed, 0xaf, 0xd1, 0x31, 0x16, 0xe0, 0xe1, 0x25,
+            0x65, 0x20, 0x2f, 0xef, 0x8e, 0x9e, 0xce, 0x7d, 0xce, 0x03, 0x81, 0x24, 0x64, 0xd0,
+            0x4b, 0x94, 0x42, 0xde,
+        ];
+
+        assert_eq!(initiator.encoded_public_key(), initiator_pub);
+        assert_eq!(responder.encoded_public_key(), responder_pub);
+        assert_eq!(initiator.shared_secret(&responder_pub), Ok(shared_secret));
+        assert_eq!(responder.shared_secret(&initiator_pub), Ok(shared_secret));
+    }
+
+    #[test]
+    fn zero_private_key() {
+        assert!(KeyPair::<Secp256r1>::from_private_key(FieldElement::ZERO).is_none());
+    }
+
+    #[test]
+    fn invalid_public_keys() {
+        let key_pair = key_pair([7; 32]);
+        let valid = encode_point(&Secp256r1::BASE_POINT);
+        assert_eq!(decode_point::<Secp256r1>(&valid), Ok(Secp256r1::BASE_POINT));
+
+        // not on the curve
+        let mut off_curve = valid;
+        off_curve[64] ^= 1;
+        assert_eq!(key_pair.shared_secret(&off_curve), Err(InvalidPublicKey));
+
+        // compressed points and the point at infinity aren't accepted
+        let mut compressed = [0; 33];
+        compressed.copy_from_slice(&valid[..33]);
+        compressed[0] = 0x02 | (valid[64] & 1);
+        assert_eq!(key_pair.shared_secret(&compressed), Err(InvalidPublicKey));
+        assert_eq!(key_pair.shared_secret(&[0x00]), Err(InvalidPublicKey));
+
+        // the wrong length
+        assert_eq!(key_pair.shared_secret(&valid[..64]), Err(InvalidPublicKey));
+        let mut long = [0; POINT_SIZE + 1];
+        long[..POINT_SIZE].copy_from_slice(&valid);
+        assert_eq!(key_pair.shared_secret(&long), Err(InvalidPublicKey));
+
+        // a coordinate that isn't reduced
+        let mut unreduced = valid;
+        unreduced[1..33].copy_from_slice(&Secp256r1::MODULUS.to_be_bytes());
+        assert_eq!(key_pair.shared_secret(&unreduced), Err(InvalidPublicKey));
+    }
+
+    #[test]
+    fn generate() {
+        let calls = core::cell::Cell::new(0);
+        let key_pair = KeyPair::<Secp256r1>::generate(|| {
+            calls.set(calls.get() + 1);
+            // the first value is rejected
+            FieldElement::new(UBigInt([calls.get() - 1, 0, 0, 0]))
+        });
+        assert_eq!(calls.get(), 2);
+        // the private key is 1
+        assert_eq!(key_pair.public_key(), &Secp256r1::BASE_POINT);
+    }
+}
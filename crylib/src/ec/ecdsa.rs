@@ -1,8 +1,11 @@
 //! The Elliptic Curve Digital Signature Algorithm.
 
 use super::{EllipticCurve, ProjectivePoint};
+use crate::asn1::{InvalidDer, Reader, Writer};
 use crate::big_int::UBigInt;
 use crate::finite_field::{FieldElement, FiniteField};
+use crate::hash::Sha256;
+use crate::hmac::Hmac;
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub struct Signature<C: FiniteField> {
@@ -11,9 +14,100 @@ pub struct Signature<C: FiniteField> {
 }
 
 impl<C: FiniteField> Signature<C> {
+    /// The length of the longest DER encoding of a signature.
+    ///
+    /// Each integer takes at most 33 bytes, including the leading zero that keeps it positive,
+    /// plus a tag and a length byte. The sequence adds another tag and length byte.
+    pub const DER_MAX_LEN: usize = 2 + 2 * (2 + 33);
+
     pub const fn new(r: FieldElement<C>, s: FieldElement<C>) -> Self {
         Self { r, s }
     }
+
+    pub const fn r(&self) -> &FieldElement<C> {
+        &self.r
+    }
+
+    pub const fn s(&self) -> &FieldElement<C> {
+        &self.s
+    }
+
+    /// Returns whether `s` is at most `(n - 1) / 2`, where `n` is the modulus of `C`.
+    ///
+    /// If `(r, s)` is a valid signature, so is `(r, n - s)`, and anyone can turn one into the other
+    /// without the private key. Only accepting the low `s` leaves a single valid signature for
+    /// each secret number.
+    pub fn is_low_s(&self) -> bool {
+        self.s.inner() <= &C::MODULUS.shift_right(1)
+    }
+
+    /// Returns whichever of `(r, s)` and `(r, n - s)` has the low `s`, which is the one
+    /// [`verify_signature_strict`] accepts.
+    pub fn normalize_s(&self) -> Self {
+        match self.is_low_s() {
+            true => *self,
+            false => Self::new(self.r, self.s.neg()),
+        }
+    }
+
+    /// Encodes the signature as a DER `Ecdsa-Sig-Value` into `buf`, returning the encoded part
+    /// of `buf`.
+    ///
+    /// This is the format used by TLS and X.509, as defined in
+    /// [RFC 3279](https://datatracker.ietf.org/doc/html/rfc3279#section-2.2.3).
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if `buf.len()` < [`Self::DER_MAX_LEN`].
+    pub fn to_der<'a>(&self, buf: &'a mut [u8]) -> &'a [u8] {
+        let mut writer = Writer::new(&mut buf[..Self::DER_MAX_LEN]);
+        writer
+            .write_sequence(|seq| {
+                seq.write_unsigned(&self.r.inner().to_be_bytes())?;
+                seq.write_unsigned(&self.s.inner().to_be_bytes())
+            })
+            // `DER_MAX_LEN` fits the largest integers
+            .unwrap();
+        writer.into_written()
+    }
+
+    /// Decodes a DER `Ecdsa-Sig-Value`.
+    ///
+    /// Only the distinguished encoding is accepted: integers must be positive and minimally
+    /// encoded, and nothing may follow the sequence.
+    /// Returns [`InvalidSig`] if the encoding isn't valid or if `r` or `s` isn't in `1..n`,
+    /// where `n` is the modulus of `C`.
+    pub fn from_der(der: &[u8]) -> Result<Self, InvalidSig> {
+        let decode = || {
+            let mut reader = Reader::new(der);
+            let mut seq = reader.read_sequence()?;
+            reader.finish()?;
+            let r = seq.read_unsigned()?;
+            let s = seq.read_unsigned()?;
+            seq.finish()?;
+            Ok::<_, InvalidDer>((r, s))
+        };
+        let (r, s) = decode().map_err(|_| InvalidSig)?;
+        match (decode_integer(r), decode_integer(s)) {
+            (Some(r), Some(s)) if !r.is_zero() && !s.is_zero() => {
+                match (FieldElement::try_new(r), FieldElement::try_new(s)) {
+                    (Ok(r), Ok(s)) => Ok(Self::new(r, s)),
+                    _ => Err(InvalidSig),
+                }
+            },
+            _ => Err(InvalidSig),
+        }
+    }
+}
+
+/// Converts the big-endian bytes of an `INTEGER` read with [`Reader::read_unsigned`] into an
+/// integer, returning [`None`] if it's more than 256 bits long.
+fn decode_integer(int: &[u8]) -> Option<UBigInt<4>> {
+    let mut bytes = [0; 32];
+    bytes
+        .get_mut(32usize.checked_sub(int.len())?..)?
+        .copy_from_slice(int);
+    Some(UBigInt::<4>::from_be_bytes(bytes))
 }
 
 /// The value that represents a valid signature.
@@ -51,12 +145,41 @@ pub fn sign<C: EllipticCurve>(
     hash_func: impl FnOnce(&[u8]) -> [u8; 32],
     random_num_gen: impl Fn() -> FieldElement<C::Order>,
 ) -> Signature<C::Order> {
-    let mut hash: FieldElement<C::Order> =
-        FieldElement::new(UBigInt::<4>::from_be_bytes(hash_func(msg)));
+    let hash = hash_func(msg);
+    sign_hash::<C>(&hash, priv_key, random_num_gen)
+}
+
+/// Creates a signature for `msg` whose secret number is derived from `priv_key` and the hash of
+/// `msg`, as specified in [RFC 6979](https://datatracker.ietf.org/doc/html/rfc6979).
+///
+/// This doesn't need a random number generator, so a broken one can't leak the private key.
+/// Signing the same message with the same key always gives the same signature.
+///
+/// The secret number is derived with HMAC-SHA256, so the signatures only match those of other
+/// RFC 6979 implementations if `hash_func` is SHA-256.
+pub fn sign_deterministic<C: EllipticCurve>(
+    msg: &[u8],
+    priv_key: &FieldElement<C::Order>,
+    hash_func: impl FnOnce(&[u8]) -> [u8; 32],
+) -> Signature<C::Order> {
+    let hash = hash_func(msg);
+    let mut secret_nums = SecretNumGenerator::new(priv_key, &hash);
+    sign_hash::<C>(&hash, priv_key, || secret_nums.next())
+}
+
+/// Signs the message whose hash is `hash`, calling `secret_num_gen` until it returns a secret
+/// number that gives a valid signature.
+fn sign_hash<C: EllipticCurve>(
+    hash: &[u8; 32],
+    priv_key: &FieldElement<C::Order>,
+    mut secret_num_gen: impl FnMut() -> FieldElement<C::Order>,
+) -> Signature<C::Order> {
+    let hash: FieldElement<C::Order> = FieldElement::new(UBigInt::<4>::from_be_bytes(*hash));
 
     loop {
-        let secret_num = random_num_gen();
-        let mut inverse = secret_num.inverse();
+        let secret_num = secret_num_gen();
+        // the secret number gives the private key away, so it can't leak through the inversion
+        let mut inverse = secret_num.inverse_const_time();
 
         let Some(new_point) = C::BASE_POINT
             .as_projective()
@@ -68,8 +191,7 @@ pub fn sign<C: EllipticCurve>(
 
         let r = new_point.x_ref().convert();
 
-        hash.add_assign(&r.mul(priv_key));
-        inverse.mul_assign(&hash);
+        inverse.mul_assign(&hash.add(&r.mul(priv_key)));
 
         if r != FieldElement::ZERO && inverse != FieldElement::ZERO {
             return Signature::new(r, inverse);
@@ -77,13 +199,80 @@ pub fn sign<C: EllipticCurve>(
     }
 }
 
+/// The HMAC-DRBG that RFC 6979 derives secret numbers with (section 3.2).
+///
+/// This assumes that the order of the curve and the hash are both 256 bits long, so no bits of
+/// the output have to be dropped.
+struct SecretNumGenerator {
+    k: [u8; 32],
+    v: [u8; 32],
+}
+
+impl SecretNumGenerator {
+    fn new<F: FiniteField>(priv_key: &FieldElement<F>, hash: &[u8; 32]) -> Self {
+        // the hash is reduced, so that the same secret number is used for hashes that are
+        // equivalent modulo the order
+        let hash: FieldElement<F> = FieldElement::new(UBigInt::<4>::from_be_bytes(*hash));
+
+        let mut generator = Self {
+            k: [0; 32],
+            v: [1; 32],
+        };
+        // V || 0x00 || private key || hash
+        let mut seed = [0; 32 + 1 + 32 + 32];
+        seed[33..65].copy_from_slice(&priv_key.to_bytes());
+        seed[65..].copy_from_slice(&hash.to_bytes());
+        for separator in [0x00, 0x01] {
+            seed[..32].copy_from_slice(&generator.v);
+            seed[32] = separator;
+            generator.k = hmac(&generator.k, &seed);
+            generator.v = hmac(&generator.k, &generator.v);
+        }
+        generator
+    }
+
+    /// Returns the next candidate, which is in `1..n`.
+    ///
+    /// The state is updated as if every candidate is rejected, so calling this again gives the
+    /// candidate to use if the last one led to an invalid signature.
+    fn next<F: FiniteField>(&mut self) -> FieldElement<F> {
+        loop {
+            self.v = hmac(&self.k, &self.v);
+            let candidate = FieldElement::from_bytes(&self.v);
+            self.reseed();
+            match candidate {
+                Ok(candidate) if candidate != FieldElement::ZERO => return candidate,
+                _ => continue,
+            }
+        }
+    }
+
+    /// Updates the state after a candidate is rejected.
+    fn reseed(&mut self) {
+        let mut msg = [0; 33];
+        msg[..32].copy_from_slice(&self.v);
+        self.k = hmac(&self.k, &msg);
+        self.v = hmac(&self.k, &self.v);
+    }
+}
+
+fn hmac(key: &[u8; 32], msg: &[u8]) -> [u8; 32] {
+    Hmac::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>::auth(key, msg)
+}
+
 /// Verifies the authenticity of `sig` using the signer's public key.
+///
+/// Signatures whose `r` or `s` is zero are always rejected, however they were made.
 pub fn verify_signature<C: EllipticCurve>(
     msg: &[u8],
     pub_key: &ProjectivePoint<C>,
     hash_func: impl FnOnce(&[u8]) -> [u8; 32],
     sig: &Signature<C::Order>,
 ) -> Result<ValidSig, InvalidSig> {
+    // `Signature::new` doesn't check this, and zero has no inverse
+    if sig.r.inner().is_zero() || sig.s.inner().is_zero() {
+        return Err(InvalidSig);
+    }
     let hash: FieldElement<C::Order> =
         FieldElement::new(UBigInt::<4>::from_be_bytes(hash_func(msg)));
     let inverse = sig.s.inverse();
@@ -107,8 +296,28 @@ pub fn verify_signature<C: EllipticCurve>(
     }
 }
 
+/// Verifies the authenticity of `sig` like [`verify_signature`], but also rejects `sig` if its
+/// `s` isn't low (see [`Signature::is_low_s`]).
+///
+/// Together with [`Signature::from_der`], which only accepts the distinguished encoding, this
+/// makes signatures non-malleable: nobody but the signer can produce another accepted signature
+/// of the same message. TLS doesn't need this, but protocols that identify messages by their
+/// signatures do. Signers for those protocols should call [`Signature::normalize_s`].
+pub fn verify_signature_strict<C: EllipticCurve>(
+    msg: &[u8],
+    pub_key: &ProjectivePoint<C>,
+    hash_func: impl FnOnce(&[u8]) -> [u8; 32],
+    sig: &Signature<C::Order>,
+) -> Result<ValidSig, InvalidSig> {
+    if !sig.is_low_s() {
+        return Err(InvalidSig);
+    }
+    verify_signature(msg, pub_key, hash_func, sig)
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::ec::secp256r1::P256Order;
     use crate::ec::Secp256r1;
 
     use super::FieldElement;
@@ -117,6 +326,8 @@ mod tests {
     use super::Signature;
     use super::UBigInt;
     use super::ValidSig;
+    use crate::ec::EllipticCurve;
+    use crate::finite_field::FiniteField;
     use crate::hash::{Hasher, Sha256};
 
     // test vectors from http://csrc.nist.gov/groups/STM/cavp/documents/dss/186-3ecdsatestvectors.zip
@@ -136,41 +347,39 @@ mod tests {
             0x9c, 0xe8,
         ];
 
-        let priv_key = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0xca54a56dda72b464,
-                0x5b44c8130b4e3eac,
-                0x1f4fa8ee59f4771a,
-                0x519b423d715f8b58,
-            ]))
-        };
-
-        let r = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0xcabb5e6f79c8c2ac,
-                0x2afd6b1f6a555a7a,
-                0x8843e3d6629527ed,
-                0xf3ac8061b514795b,
-            ]))
-        };
-
-        let s = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x3ccdda2acc058903,
-                0xef97b218e96f175a,
-                0x786c76262bf7371c,
-                0x8bf77819ca05a6b2,
-            ]))
-        };
+        let priv_key = FieldElement::try_new(UBigInt([
+            0xca54a56dda72b464,
+            0x5b44c8130b4e3eac,
+            0x1f4fa8ee59f4771a,
+            0x519b423d715f8b58,
+        ]))
+        .unwrap();
+
+        let r = FieldElement::try_new(UBigInt([
+            0xcabb5e6f79c8c2ac,
+            0x2afd6b1f6a555a7a,
+            0x8843e3d6629527ed,
+            0xf3ac8061b514795b,
+        ]))
+        .unwrap();
+
+        let s = FieldElement::try_new(UBigInt([
+            0x3ccdda2acc058903,
+            0xef97b218e96f175a,
+            0x786c76262bf7371c,
+            0x8bf77819ca05a6b2,
+        ]))
+        .unwrap();
         let signature = Signature::new(r, s);
 
-        let super_secure_random_num_generator = || unsafe {
-            FieldElement::new_unchecked(UBigInt([
+        let super_secure_random_num_generator = || {
+            FieldElement::try_new(UBigInt([
                 0xb9670787642a68de,
                 0x3b4a6247824f5d33,
                 0xa280f245f9e93c7f,
                 0x94a1bbb14b906a61,
             ]))
+            .unwrap()
         };
 
         let generated_signature = super::sign::<Secp256r1>(
@@ -194,41 +403,39 @@ mod tests {
             0x4a, 0xf1,
         ];
 
-        let priv_key = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0xc201537b85479813,
-                0x9a25aaf48ebb519a,
-                0x5c500064824bed99,
-                0x0f56db78ca460b05,
-            ]))
-        };
+        let priv_key = FieldElement::try_new(UBigInt([
+            0xc201537b85479813,
+            0x9a25aaf48ebb519a,
+            0x5c500064824bed99,
+            0x0f56db78ca460b05,
+        ]))
+        .unwrap();
 
-        let super_secure_random_num_generator = || unsafe {
-            FieldElement::new_unchecked(UBigInt([
+        let super_secure_random_num_generator = || {
+            FieldElement::try_new(UBigInt([
                 0x421187ae0b2f34c6,
                 0xfb728068d3ae9fac,
                 0x56bb14e0ab184aa9,
                 0x6d3e71882c3b83b1,
             ]))
+            .unwrap()
         };
 
-        let r = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x6473b6a11079b2db,
-                0x53f42864f508483a,
-                0xc0baa9fa560b7c4e,
-                0x976d3a4e9d23326d,
-            ]))
-        };
-
-        let s = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x55b8eeefe36e1932,
-                0x4cfa652ae5017d45,
-                0x01dcd46e0af462cd,
-                0x1b766e9ceb71ba6c,
-            ]))
-        };
+        let r = FieldElement::try_new(UBigInt([
+            0x6473b6a11079b2db,
+            0x53f42864f508483a,
+            0xc0baa9fa560b7c4e,
+            0x976d3a4e9d23326d,
+        ]))
+        .unwrap();
+
+        let s = FieldElement::try_new(UBigInt([
+            0x55b8eeefe36e1932,
+            0x4cfa652ae5017d45,
+            0x01dcd46e0af462cd,
+            0x1b766e9ceb71ba6c,
+        ]))
+        .unwrap();
         let signature = Signature::new(r, s);
 
         let generated_signature = super::sign::<Secp256r1>(
@@ -240,6 +447,79 @@ mod tests {
         assert_eq!(generated_signature, signature);
     }
 
+    #[test]
+    fn sign_deterministic() {
+        // https://datatracker.ietf.org/doc/html/rfc6979#appendix-A.2.5
+        let priv_key = FieldElement::new(UBigInt([
+            0x7b8a622b120f6721,
+            0x4e50c3db36e89b12,
+            0x6b5c215767b1d693,
+            0xc9afa9d845ba7516,
+        ]));
+
+        let signature = Signature::new(
+            FieldElement::new(UBigInt([
+                0xc34d0ea84eaf3716,
+                0x9d2c877b56aaf991,
+                0x1140dd9cd45e81d6,
+                0xefd48b2aacb6a8fd,
+            ])),
+            FieldElement::new(UBigInt([
+                0x4dc4ab2f843acda8,
+                0xf3e900dbb9aff406,
+                0xd436c7a1b6e29f65,
+                0xf7cb1c942d657c41,
+            ])),
+        );
+        assert_eq!(
+            super::sign_deterministic::<Secp256r1>(b"sample", &priv_key, Sha256::hash),
+            signature
+        );
+
+        let signature = Signature::new(
+            FieldElement::new(UBigInt([
+                0x4f28d3b0b7d38367,
+                0xed3efcf6c5132b35,
+                0x71d881567b1ea663,
+                0xf1abb023518351cd,
+            ])),
+            FieldElement::new(UBigInt([
+                0x0cc84250e46f0083,
+                0x5f267e60d3814b4c,
+                0xbd25926b49c64915,
+                0x019f4113742a2b14,
+            ])),
+        );
+        assert_eq!(
+            super::sign_deterministic::<Secp256r1>(b"test", &priv_key, Sha256::hash),
+            signature
+        );
+    }
+
+    #[test]
+    fn secret_num_generator() {
+        // https://datatracker.ietf.org/doc/html/rfc6979#appendix-A.2.5
+        let priv_key = FieldElement::<P256Order>::new(UBigInt([
+            0x7b8a622b120f6721,
+            0x4e50c3db36e89b12,
+            0x6b5c215767b1d693,
+            0xc9afa9d845ba7516,
+        ]));
+        let mut generator = super::SecretNumGenerator::new(&priv_key, &Sha256::hash(b"sample"));
+        let secret_num: FieldElement<P256Order> = generator.next();
+        assert_eq!(
+            secret_num,
+            FieldElement::new(UBigInt([
+                0x4d6129493d8aad60,
+                0x3b17aa873382b0f2,
+                0x086538398355dd4c,
+                0xa6e3c57dd01abe90,
+            ]))
+        );
+        // a rejected secret number is replaced by a different one
+        assert_ne!(generator.next::<P256Order>(), secret_num);
+    }
+
     #[test]
     fn verify_signature() {
         let msg = &[
@@ -255,44 +535,39 @@ mod tests {
             0x9c, 0xe8,
         ];
 
-        let pub_key_x = unsafe {
-            FieldElement::<Secp256r1>::new_unchecked(UBigInt([
-                0x3c59ff46c271bf83,
-                0xd3565de94bbfb12f,
-                0xf033bfa248db8fcc,
-                0x1ccbe91c075fc7f4,
-            ]))
-        };
-
-        let pub_key_y = unsafe {
-            FieldElement::<Secp256r1>::new_unchecked(UBigInt([
-                0xdc7ccd5ca89a4ca9,
-                0x6db7ca93b7404e78,
-                0x1a1fdb2c0e6113e0,
-                0xce4014c68811f9a2,
-            ]))
-        };
-
-        let pub_key =
-            unsafe { ProjectivePoint::new_unchecked(pub_key_x, pub_key_y, FieldElement::ONE) };
-
-        let r = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0xcabb5e6f79c8c2ac,
-                0x2afd6b1f6a555a7a,
-                0x8843e3d6629527ed,
-                0xf3ac8061b514795b,
-            ]))
-        };
-
-        let s = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x3ccdda2acc058903,
-                0xef97b218e96f175a,
-                0x786c76262bf7371c,
-                0x8bf77819ca05a6b2,
-            ]))
-        };
+        let pub_key_x = FieldElement::<Secp256r1>::try_new(UBigInt([
+            0x3c59ff46c271bf83,
+            0xd3565de94bbfb12f,
+            0xf033bfa248db8fcc,
+            0x1ccbe91c075fc7f4,
+        ]))
+        .unwrap();
+
+        let pub_key_y = FieldElement::<Secp256r1>::try_new(UBigInt([
+            0xdc7ccd5ca89a4ca9,
+            0x6db7ca93b7404e78,
+            0x1a1fdb2c0e6113e0,
+            0xce4014c68811f9a2,
+        ]))
+        .unwrap();
+
+        let pub_key = ProjectivePoint::new(pub_key_x, pub_key_y, FieldElement::ONE).unwrap();
+
+        let r = FieldElement::try_new(UBigInt([
+            0xcabb5e6f79c8c2ac,
+            0x2afd6b1f6a555a7a,
+            0x8843e3d6629527ed,
+            0xf3ac8061b514795b,
+        ]))
+        .unwrap();
+
+        let s = FieldElement::try_new(UBigInt([
+            0x3ccdda2acc058903,
+            0xef97b218e96f175a,
+            0x786c76262bf7371c,
+            0x8bf77819ca05a6b2,
+        ]))
+        .unwrap();
         let signature = Signature::new(r, s);
 
         assert_eq!(
@@ -332,45 +607,40 @@ mod tests {
             0x4a, 0xf1,
         ];
 
-        let r = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x6473b6a11079b2db,
-                0x53f42864f508483a,
-                0xc0baa9fa560b7c4e,
-                0x976d3a4e9d23326d,
-            ]))
-        };
-
-        let s = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x55b8eeefe36e1932,
-                0x4cfa652ae5017d45,
-                0x01dcd46e0af462cd,
-                0x1b766e9ceb71ba6c,
-            ]))
-        };
+        let r = FieldElement::try_new(UBigInt([
+            0x6473b6a11079b2db,
+            0x53f42864f508483a,
+            0xc0baa9fa560b7c4e,
+            0x976d3a4e9d23326d,
+        ]))
+        .unwrap();
+
+        let s = FieldElement::try_new(UBigInt([
+            0x55b8eeefe36e1932,
+            0x4cfa652ae5017d45,
+            0x01dcd46e0af462cd,
+            0x1b766e9ceb71ba6c,
+        ]))
+        .unwrap();
         let signature = Signature::new(r, s);
 
-        let pub_key_x = unsafe {
-            FieldElement::<Secp256r1>::new_unchecked(UBigInt([
-                0x0bf3d4012aeffa8a,
-                0x2c416044f2d2b8c1,
-                0x30d4ca3e8f774943,
-                0xe266ddfdc12668db,
-            ]))
-        };
+        let pub_key_x = FieldElement::<Secp256r1>::try_new(UBigInt([
+            0x0bf3d4012aeffa8a,
+            0x2c416044f2d2b8c1,
+            0x30d4ca3e8f774943,
+            0xe266ddfdc12668db,
+        ]))
+        .unwrap();
 
-        let pub_key_y = unsafe {
-            FieldElement::<Secp256r1>::new_unchecked(UBigInt([
-                0x6928973ab5b1cb39,
-                0xf456b863b4d02cfc,
-                0x7d47c587ef7a97a7,
-                0xbfa86404a2e9ffe6,
-            ]))
-        };
+        let pub_key_y = FieldElement::<Secp256r1>::try_new(UBigInt([
+            0x6928973ab5b1cb39,
+            0xf456b863b4d02cfc,
+            0x7d47c587ef7a97a7,
+            0xbfa86404a2e9ffe6,
+        ]))
+        .unwrap();
 
-        let pub_key =
-            unsafe { ProjectivePoint::new_unchecked(pub_key_x, pub_key_y, FieldElement::ONE) };
+        let pub_key = ProjectivePoint::new(pub_key_x, pub_key_y, FieldElement::ONE).unwrap();
 
         assert_eq!(
             super::verify_signature(msg, &pub_key, Sha256::hash, &signature),
@@ -378,19 +648,178 @@ mod tests {
         );
 
         // flip one bit in the signature
-        let r = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x7473b6a11079b2db,
-                0x53f42864f508483a,
-                0xc0baa9fa560b7c4e,
-                0x976d3a4e9d23326d,
-            ]))
-        };
+        let r = FieldElement::try_new(UBigInt([
+            0x7473b6a11079b2db,
+            0x53f42864f508483a,
+            0xc0baa9fa560b7c4e,
+            0x976d3a4e9d23326d,
+        ]))
+        .unwrap();
 
         let signature = Signature::new(r, s);
         assert_eq!(
             super::verify_signature(msg, &pub_key, Sha256::hash, &signature),
             Err(InvalidSig)
         );
+
+        for signature in [
+            Signature::new(FieldElement::ZERO, s),
+            Signature::new(r, FieldElement::ZERO),
+        ] {
+            assert_eq!(
+                super::verify_signature(msg, &pub_key, Sha256::hash, &signature),
+                Err(InvalidSig)
+            );
+        }
+    }
+
+    // generated with pyca/cryptography
+    #[test]
+    fn der() {
+        let r = FieldElement::try_new(UBigInt([
+            0x6473b6a11079b2db,
+            0x53f42864f508483a,
+            0xc0baa9fa560b7c4e,
+            0x976d3a4e9d23326d,
+        ]))
+        .unwrap();
+        let s = FieldElement::try_new(UBigInt([
+            0x55b8eeefe36e1932,
+            0x4cfa652ae5017d45,
+            0x01dcd46e0af462cd,
+            0x1b766e9ceb71ba6c,
+        ]))
+        .unwrap();
+        let signature = Signature::<P256Order>::new(r, s);
+        // only `r` has its top bit set, so only `r` needs a leading zero
+        let der = [
+            0x30, 0x45, 0x02, 0x21, 0x00, 0x97, 0x6d, 0x3a, 0x4e, 0x9d, 0x23, 0x32, 0x6d, 0xc0,
+            0xba, 0xa9, 0xfa, 0x56, 0x0b, 0x7c, 0x4e, 0x53, 0xf4, 0x28, 0x64, 0xf5, 0x08, 0x48,
+            0x3a, 0x64, 0x73, 0xb6, 0xa1, 0x10, 0x79, 0xb2, 0xdb, 0x02, 0x20, 0x1b, 0x76, 0x6e,
+            0x9c, 0xeb, 0x71, 0xba, 0x6c, 0x01, 0xdc, 0xd4, 0x6e, 0x0a, 0xf4, 0x62, 0xcd, 0x4c,
+            0xfa, 0x65, 0x2a, 0xe5, 0x01, 0x7d, 0x45, 0x55, 0xb8, 0xee, 0xef, 0xe3, 0x6e, 0x19,
+            0x32,
+        ];
+        let mut buf = [0; Signature::<P256Order>::DER_MAX_LEN];
+        assert_eq!(signature.to_der(&mut buf), der);
+        assert_eq!(Signature::from_der(&der), Ok(signature));
+
+        let r = FieldElement::try_new(UBigInt([
+            0xcabb5e6f79c8c2ac,
+            0x2afd6b1f6a555a7a,
+            0x8843e3d6629527ed,
+            0xf3ac8061b514795b,
+        ]))
+        .unwrap();
+        let s = FieldElement::try_new(UBigInt([
+            0x3ccdda2acc058903,
+            0xef97b218e96f175a,
+            0x786c76262bf7371c,
+            0x8bf77819ca05a6b2,
+        ]))
+        .unwrap();
+        let signature = Signature::<P256Order>::new(r, s);
+        let der = [
+            0x30, 0x46, 0x02, 0x21, 0x00, 0xf3, 0xac, 0x80, 0x61, 0xb5, 0x14, 0x79, 0x5b, 0x88,
+            0x43, 0xe3, 0xd6, 0x62, 0x95, 0x27, 0xed, 0x2a, 0xfd, 0x6b, 0x1f, 0x6a, 0x55, 0x5a,
+            0x7a, 0xca, 0xbb, 0x5e, 0x6f, 0x79, 0xc8, 0xc2, 0xac, 0x02, 0x21, 0x00, 0x8b, 0xf7,
+            0x78, 0x19, 0xca, 0x05, 0xa6, 0xb2, 0x78, 0x6c, 0x76, 0x26, 0x2b, 0xf7, 0x37, 0x1c,
+            0xef, 0x97, 0xb2, 0x18, 0xe9, 0x6f, 0x17, 0x5a, 0x3c, 0xcd, 0xda, 0x2a, 0xcc, 0x05,
+            0x89, 0x03,
+        ];
+        assert_eq!(der.len(), Signature::<P256Order>::DER_MAX_LEN);
+        assert_eq!(signature.to_der(&mut buf), der);
+        assert_eq!(Signature::from_der(&der), Ok(signature));
+
+        let signature = Signature::<P256Order>::new(
+            FieldElement::new(UBigInt([1, 0, 0, 0])),
+            FieldElement::new(UBigInt([0x7f, 0, 0, 0])),
+        );
+        let der = [0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x7f];
+        assert_eq!(signature.to_der(&mut buf), der);
+        assert_eq!(Signature::from_der(&der), Ok(signature));
+    }
+
+    #[test]
+    fn der_invalid() {
+        let invalid: [&[u8]; 11] = [
+            &[],
+            // trailing data
+            &[0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x7f, 0x00],
+            &[
+                0x30, 0x09, 0x02, 0x01, 0x01, 0x02, 0x01, 0x7f, 0x02, 0x01, 0x01,
+            ],
+            // wrong length
+            &[0x30, 0x07, 0x02, 0x01, 0x01, 0x02, 0x01, 0x7f],
+            &[0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x02, 0x7f],
+            // long-form length
+            &[0x30, 0x81, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x7f],
+            // negative
+            &[0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x80],
+            // unnecessary leading zero
+            &[0x30, 0x07, 0x02, 0x01, 0x01, 0x02, 0x02, 0x00, 0x7f],
+            // zero
+            &[0x30, 0x06, 0x02, 0x01, 0x00, 0x02, 0x01, 0x7f],
+            // empty integer
+            &[0x30, 0x05, 0x02, 0x00, 0x02, 0x01, 0x7f],
+            // wrong tag
+            &[0x30, 0x06, 0x04, 0x01, 0x01, 0x02, 0x01, 0x7f],
+        ];
+        for der in invalid {
+            assert_eq!(
+                Signature::<P256Order>::from_der(der),
+                Err(InvalidSig),
+                "{der:x?}"
+            );
+        }
+
+        // `r` is the order of the curve
+        let der = [
+            0x30, 0x26, 0x02, 0x21, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xbc, 0xe6, 0xfa, 0xad, 0xa7, 0x17, 0x9e,
+            0x84, 0xf3, 0xb9, 0xca, 0xc2, 0xfc, 0x63, 0x25, 0x51, 0x02, 0x01, 0x01,
+        ];
+        assert_eq!(Signature::<P256Order>::from_der(&der), Err(InvalidSig));
+    }
+
+    #[test]
+    fn strict() {
+        let priv_key = FieldElement::new(UBigInt([
+            0x7b8a622b120f6721,
+            0x4e50c3db36e89b12,
+            0x6b5c215767b1d693,
+            0xc9afa9d845ba7516,
+        ]));
+        let pub_key = Secp256r1::BASE_POINT
+            .as_projective()
+            .mul_scalar(priv_key.inner());
+
+        // the RFC 6979 signature of "sample" has a high `s`
+        let signature = super::sign_deterministic::<Secp256r1>(b"sample", &priv_key, Sha256::hash);
+        assert!(!signature.is_low_s());
+        assert_eq!(
+            super::verify_signature(b"sample", &pub_key, Sha256::hash, &signature),
+            Ok(ValidSig)
+        );
+        assert_eq!(
+            super::verify_signature_strict(b"sample", &pub_key, Sha256::hash, &signature),
+            Err(InvalidSig)
+        );
+
+        let low = signature.normalize_s();
+        assert!(low.is_low_s());
+        assert_eq!(low.r(), signature.r());
+        assert_eq!(low.normalize_s(), low);
+        assert_eq!(
+            super::verify_signature_strict(b"sample", &pub_key, Sha256::hash, &low),
+            Ok(ValidSig)
+        );
+
+        // `(n - 1) / 2` is the largest low `s`
+        let half = P256Order::MODULUS.shift_right(1);
+        let r = *signature.r();
+        assert!(Signature::new(r, FieldElement::new(half)).is_low_s());
+        let above = FieldElement::new(half).add(&FieldElement::ONE);
+        assert!(!Signature::new(r, above).is_low_s());
     }
 }
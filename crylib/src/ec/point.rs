@@ -4,6 +4,8 @@ pub mod projective;
 pub use affine::AffinePoint;
 pub use projective::ProjectivePoint;
 
+/// The error returned when the result of an operation would be the point at infinity, which
+/// [`AffinePoint`] can't represent.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct AffineInfinity;
 
@@ -14,3 +16,15 @@ impl core::fmt::Display for AffineInfinity {
 }
 
 impl core::error::Error for AffineInfinity {}
+
+/// The error returned when bytes aren't a valid SEC1 encoding of a point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InvalidPointEncoding;
+
+impl core::fmt::Display for InvalidPointEncoding {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("invalid point encoding")
+    }
+}
+
+impl core::error::Error for InvalidPointEncoding {}
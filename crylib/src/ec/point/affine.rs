@@ -1,6 +1,17 @@
 use crate::finite_field::FieldElement;
 
-use super::{super::EllipticCurve, AffineInfinity, ProjectivePoint};
+use super::super::{curve_rhs, satisfies_equation, EllipticCurve};
+use super::{AffineInfinity, InvalidPointEncoding, ProjectivePoint};
+
+/// The size of a coordinate in the SEC1 encoding of a point.
+const COORDINATE_SIZE: usize = 32;
+
+/// The size of an uncompressed SEC1 encoding of a point, which holds both coordinates.
+pub const UNCOMPRESSED_POINT_SIZE: usize = 1 + 2 * COORDINATE_SIZE;
+
+/// The size of a compressed SEC1 encoding of a point, which holds only the x-coordinate.
+pub const COMPRESSED_POINT_SIZE: usize = 1 + COORDINATE_SIZE;
+
 /// A point on an elliptic curve in affine representation.
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub struct AffinePoint<C: EllipticCurve> {
@@ -34,30 +45,42 @@ impl<C: EllipticCurve> core::fmt::Debug for AffinePoint<C> {
 
 impl<C: EllipticCurve> AffinePoint<C> {
     /// Returns the x-value of `self`.
-    pub fn x(&self) -> FieldElement<C> {
+    pub const fn x(&self) -> FieldElement<C> {
         self.x
     }
 
-    pub fn x_ref(&self) -> &FieldElement<C> {
+    pub const fn x_ref(&self) -> &FieldElement<C> {
         &self.x
     }
 
     /// Returns the y-value of `self`.
-    pub fn y(&self) -> FieldElement<C> {
+    pub const fn y(&self) -> FieldElement<C> {
         self.y
     }
 
-    pub fn y_ref(&self) -> &FieldElement<C> {
+    pub const fn y_ref(&self) -> &FieldElement<C> {
         &self.y
     }
 
     /// Converts `self` into its projective representation.
     pub const fn as_projective(self) -> ProjectivePoint<C> {
-        // # SAFETY: The projective value is still on the curve.
+        // SAFETY: `(x, y, 1)` is the projective form of a point on the curve.
         unsafe { ProjectivePoint::new_unchecked(self.x, self.y, FieldElement::ONE) }
     }
 
-    /// Creates a new [`AffinePoint`] without verifying that it is on the curve specified b `P`.
+    /// Creates a new [`AffinePoint`], returning [`None`] if it isn't on the curve.
+    ///
+    /// This can be used in const contexts, so a curve's constants are checked at compile time.
+    pub const fn new(x: FieldElement<C>, y: FieldElement<C>) -> Option<Self> {
+        if !satisfies_equation::<C>(&x, &y) {
+            return None;
+        }
+        Some(Self { x, y })
+    }
+
+    /// Creates a new [`AffinePoint`] without verifying that it is on the curve.
+    ///
+    /// In most cases, it's better to use the safe version: [`Self::new()`]
     ///
     /// # Safety
     /// The point must be on the curve. If the point isn't on the curve, it will result in
@@ -65,23 +88,115 @@ impl<C: EllipticCurve> AffinePoint<C> {
     pub const unsafe fn new_unchecked(x: FieldElement<C>, y: FieldElement<C>) -> Self {
         Self { x, y }
     }
-    pub fn add(&self, rhs: &Self) -> Self {
+    /// Encodes `self` uncompressed, as `0x04` followed by both coordinates
+    /// ([SEC 1 section 2.3.3](https://www.secg.org/sec1-v2.pdf)).
+    pub fn to_bytes(&self) -> [u8; UNCOMPRESSED_POINT_SIZE] {
+        let mut out = [0x04; UNCOMPRESSED_POINT_SIZE];
+        out[1..][..COORDINATE_SIZE].copy_from_slice(&self.x.to_bytes());
+        out[1 + COORDINATE_SIZE..].copy_from_slice(&self.y.to_bytes());
+        out
+    }
+
+    /// Encodes `self` compressed, as the x-coordinate prefixed by `0x02` if the y-coordinate is
+    /// even, or by `0x03` if it is odd.
+    pub fn to_compressed_bytes(&self) -> [u8; COMPRESSED_POINT_SIZE] {
+        let mut out = [0x02 | self.y_is_odd() as u8; COMPRESSED_POINT_SIZE];
+        out[1..].copy_from_slice(&self.x.to_bytes());
+        out
+    }
+
+    /// Decodes an uncompressed or compressed point
+    /// ([SEC 1 section 2.3.4](https://www.secg.org/sec1-v2.pdf)).
+    ///
+    /// Returns [`InvalidPointEncoding`] if `bytes` is neither encoding, if a coordinate isn't
+    /// reduced, or if the point isn't on the curve. The point at infinity, which is encoded as a
+    /// single `0x00`, can't be represented, so it is rejected as well.
+    ///
+    /// Points aren't checked against small subgroups. Public keys on curves with a
+    /// [`EllipticCurve::COFACTOR`] other than 1 need to be checked separately.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, InvalidPointEncoding> {
+        fn coordinate<C: EllipticCurve>(
+            bytes: &[u8],
+        ) -> Result<FieldElement<C>, InvalidPointEncoding> {
+            let bytes: [u8; COORDINATE_SIZE] =
+                bytes.try_into().map_err(|_| InvalidPointEncoding)?;
+            FieldElement::from_bytes(&bytes).map_err(|_| InvalidPointEncoding)
+        }
+
+        match bytes {
+            [0x04, coords @ ..] if coords.len() == 2 * COORDINATE_SIZE => {
+                let (x, y) = coords.split_at(COORDINATE_SIZE);
+                Self::new(coordinate(x)?, coordinate(y)?).ok_or(InvalidPointEncoding)
+            },
+            [prefix @ (0x02 | 0x03), x @ ..] => {
+                let x = coordinate(x)?;
+                let mut point = Self {
+                    x,
+                    y: curve_rhs::<C>(&x).sqrt().ok_or(InvalidPointEncoding)?,
+                };
+                if point.y_is_odd() != (*prefix == 0x03) {
+                    point.neg_assign();
+                }
+                // a y-coordinate of zero has no odd counterpart
+                if point.y_is_odd() != (*prefix == 0x03) {
+                    return Err(InvalidPointEncoding);
+                }
+                Ok(point)
+            },
+            _ => Err(InvalidPointEncoding),
+        }
+    }
+
+    const fn y_is_odd(&self) -> bool {
+        self.y.inner().0[0] & 1 == 1
+    }
+
+    /// Returns `self + rhs`.
+    ///
+    /// Adding a point to itself doubles it. Adding a point to its negation gives the point at
+    /// infinity, which can't be represented in affine coordinates, so [`AffineInfinity`] is
+    /// returned instead.
+    ///
+    /// Telling these cases apart takes branches that depend on the points, and every addition
+    /// needs an inversion, so [`ProjectivePoint::add`] should be used for anything secret.
+    pub fn add(&self, rhs: &Self) -> Result<Self, AffineInfinity> {
+        if self.x == rhs.x {
+            // the points are either equal or each other's negation
+            return match self.y == rhs.y {
+                true => self.double(),
+                false => Err(AffineInfinity),
+            };
+        }
         let slope = rhs.y.sub(&self.y).div(&rhs.x.sub(&self.x));
-        self.third_point_on_line(rhs, &slope)
+        Ok(self.third_point_on_line(rhs, &slope))
+    }
+
+    /// Sets `self` to `self + rhs`, leaving it unchanged if the sum is the point at infinity.
+    pub fn add_assign(&mut self, rhs: &Self) -> Result<(), AffineInfinity> {
+        *self = self.add(rhs)?;
+        Ok(())
     }
 
-    pub fn neg(&self) -> Self {
+    pub const fn neg(&self) -> Self {
         Self {
             x: self.x,
             y: self.y.neg(),
         }
     }
 
-    pub fn neg_assign(&mut self) {
+    pub const fn neg_assign(&mut self) {
         self.y.neg_assign();
     }
 
-    pub fn double(&self) -> Self {
+    /// Returns `self + self`.
+    ///
+    /// A point whose y-coordinate is zero is its own negation, so doubling it gives the point at
+    /// infinity, and [`AffineInfinity`] is returned. Curves with a [`EllipticCurve::COFACTOR`] of
+    /// 1 have no such points.
+    pub fn double(&self) -> Result<Self, AffineInfinity> {
+        if self.y == FieldElement::ZERO {
+            return Err(AffineInfinity);
+        }
         let slope = {
             let mut slope = self.x.sqr();
             slope.mul_digit_assign(3);
@@ -89,11 +204,13 @@ impl<C: EllipticCurve> AffinePoint<C> {
             let tmp = self.y.double();
             slope.div(&tmp)
         };
-        self.third_point_on_line(self, &slope)
+        Ok(self.third_point_on_line(self, &slope))
     }
 
-    pub fn double_assign(&mut self) {
-        *self = self.double();
+    /// Sets `self` to `self + self`, leaving it unchanged if the result is the point at infinity.
+    pub fn double_assign(&mut self) -> Result<(), AffineInfinity> {
+        *self = self.double()?;
+        Ok(())
     }
 
     fn third_point_on_line(&self, other: &Self, slope: &FieldElement<C>) -> Self {
@@ -119,78 +236,168 @@ mod tests {
     use super::*;
     use crate::big_int::UBigInt;
     use crate::ec::Secp256r1;
+    use crate::finite_field::FiniteField;
 
     // test vectors from http://point-at-infinity.org/ecc/nisttv
 
     #[test]
     fn add() {
-        let x = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0xa60b48fc47669978,
-                0xc08969e277f21b35,
-                0x8a52380304b51ac3,
-                0x7cf27b188d034f7e,
-            ]))
-        };
-        let y = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x9e04b79d227873d1,
-                0xba7dade63ce98229,
-                0x293d9ac69f7430db,
-                0x07775510db8ed040,
-            ]))
-        };
+        let x = FieldElement::try_new(UBigInt([
+            0xa60b48fc47669978,
+            0xc08969e277f21b35,
+            0x8a52380304b51ac3,
+            0x7cf27b188d034f7e,
+        ]))
+        .unwrap();
+        let y = FieldElement::try_new(UBigInt([
+            0x9e04b79d227873d1,
+            0xba7dade63ce98229,
+            0x293d9ac69f7430db,
+            0x07775510db8ed040,
+        ]))
+        .unwrap();
         // Secp256r1::BASE_POINT * 2
-        let k_2 = unsafe { AffinePoint::new_unchecked(x, y) };
-
-        let x = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0xfb41661bc6e7fd6c,
-                0xe6c6b721efada985,
-                0xc8f7ef951d4bf165,
-                0x5ecbe4d1a6330a44,
-            ]))
-        };
-        let y = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x9a79b127a27d5032,
-                0xd82ab036384fb83d,
-                0x374b06ce1a64a2ec,
-                0x8734640c4998ff7e,
-            ]))
-        };
+        let k_2 = AffinePoint::new(x, y).unwrap();
+
+        let x = FieldElement::try_new(UBigInt([
+            0xfb41661bc6e7fd6c,
+            0xe6c6b721efada985,
+            0xc8f7ef951d4bf165,
+            0x5ecbe4d1a6330a44,
+        ]))
+        .unwrap();
+        let y = FieldElement::try_new(UBigInt([
+            0x9a79b127a27d5032,
+            0xd82ab036384fb83d,
+            0x374b06ce1a64a2ec,
+            0x8734640c4998ff7e,
+        ]))
+        .unwrap();
         // Secp256r1::BASE_POINT * 3
-        let k_3 = unsafe { AffinePoint::new_unchecked(x, y) };
+        let k_3 = AffinePoint::new(x, y).unwrap();
 
         let sum = k_2.add(&Secp256r1::BASE_POINT);
-        assert_eq!(sum, k_3);
+        assert_eq!(sum, Ok(k_3));
 
         let also_sum = Secp256r1::BASE_POINT.add(&k_2);
-        assert_eq!(also_sum, k_3);
+        assert_eq!(also_sum, Ok(k_3));
+    }
+
+    #[test]
+    fn sec1_encoding() {
+        let base_point = Secp256r1::BASE_POINT;
+        let compressed = base_point.to_compressed_bytes();
+        assert_eq!(compressed[0], 0x03);
+        assert_eq!(compressed[1..], base_point.x().into_inner().to_be_bytes());
+        assert_eq!(AffinePoint::from_bytes(&compressed), Ok(base_point));
+        let uncompressed = base_point.to_bytes();
+        assert_eq!(uncompressed[0], 0x04);
+        assert_eq!(AffinePoint::from_bytes(&uncompressed), Ok(base_point));
+
+        // the other prefix selects the other y-coordinate
+        let mut flipped = compressed;
+        flipped[0] = 0x02;
+        assert_eq!(AffinePoint::from_bytes(&flipped), Ok(base_point.neg()));
+
+        // Secp256r1::BASE_POINT * 3 has an even y-coordinate
+        let k_3 = base_point.add(&base_point.double().unwrap()).unwrap();
+        let compressed = k_3.to_compressed_bytes();
+        assert_eq!(compressed[0], 0x02);
+        assert_eq!(AffinePoint::from_bytes(&compressed), Ok(k_3));
+        assert_eq!(AffinePoint::from_bytes(&k_3.to_bytes()), Ok(k_3));
+    }
+
+    #[test]
+    fn sec1_invalid_encodings() {
+        let base_point = Secp256r1::BASE_POINT;
+        let decode = AffinePoint::<Secp256r1>::from_bytes;
+
+        // no point has an x-coordinate of 1
+        let mut compressed = [0; COMPRESSED_POINT_SIZE];
+        compressed[0] = 0x02;
+        compressed[COMPRESSED_POINT_SIZE - 1] = 1;
+        assert_eq!(decode(&compressed), Err(InvalidPointEncoding));
+
+        // coordinates must be reduced
+        compressed[1..].copy_from_slice(&Secp256r1::MODULUS.to_be_bytes());
+        assert_eq!(decode(&compressed), Err(InvalidPointEncoding));
+
+        let mut uncompressed = base_point.to_bytes();
+        uncompressed[UNCOMPRESSED_POINT_SIZE - 1] ^= 1;
+        assert_eq!(decode(&uncompressed), Err(InvalidPointEncoding));
+
+        let compressed = base_point.to_compressed_bytes();
+        let uncompressed = base_point.to_bytes();
+        assert_eq!(decode(&compressed[..32]), Err(InvalidPointEncoding));
+        assert_eq!(decode(&uncompressed[..64]), Err(InvalidPointEncoding));
+        assert_eq!(decode(&[0x00]), Err(InvalidPointEncoding));
+        assert_eq!(decode(&[]), Err(InvalidPointEncoding));
+        let mut prefix = compressed;
+        prefix[0] = 0x05;
+        assert_eq!(decode(&prefix), Err(InvalidPointEncoding));
+    }
+
+    #[test]
+    fn add_exceptional() {
+        let base_point = Secp256r1::BASE_POINT;
+        assert_eq!(base_point.add(&base_point), base_point.double());
+        assert_eq!(base_point.add(&base_point.neg()), Err(AffineInfinity));
+
+        let mut point = base_point;
+        assert_eq!(point.add_assign(&base_point.neg()), Err(AffineInfinity));
+        assert_eq!(point, base_point);
+        point.add_assign(&base_point).unwrap();
+        assert_eq!(Ok(point), base_point.double());
     }
 
     #[test]
     fn double() {
-        let x = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0xa60b48fc47669978,
-                0xc08969e277f21b35,
-                0x8a52380304b51ac3,
-                0x7cf27b188d034f7e,
-            ]))
-        };
-        let y = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x9e04b79d227873d1,
-                0xba7dade63ce98229,
-                0x293d9ac69f7430db,
-                0x07775510db8ed040,
-            ]))
-        };
+        let x = FieldElement::try_new(UBigInt([
+            0xa60b48fc47669978,
+            0xc08969e277f21b35,
+            0x8a52380304b51ac3,
+            0x7cf27b188d034f7e,
+        ]))
+        .unwrap();
+        let y = FieldElement::try_new(UBigInt([
+            0x9e04b79d227873d1,
+            0xba7dade63ce98229,
+            0x293d9ac69f7430db,
+            0x07775510db8ed040,
+        ]))
+        .unwrap();
         // Secp256r1::BASE_POINT * 2
-        let k_2 = unsafe { AffinePoint::new_unchecked(x, y) };
+        let k_2 = AffinePoint::new(x, y).unwrap();
 
         let sum = Secp256r1::BASE_POINT.double();
-        assert_eq!(sum, k_2);
+        assert_eq!(sum, Ok(k_2));
+
+        let x = FieldElement::try_new(UBigInt([
+            0x509302446b030852,
+            0x031fe2db785596ef,
+            0xa02dde659ee62bd0,
+            0xe2534a3532d08fbb,
+        ]))
+        .unwrap();
+        let y = FieldElement::try_new(UBigInt([
+            0x5c42c23f184ed8c6,
+            0x4efc96c3f30ee005,
+            0x19dfee5fda862d76,
+            0xe0f1575a4c633cc7,
+        ]))
+        .unwrap();
+        // Secp256r1::BASE_POINT * 4
+        let k_4 = AffinePoint::new(x, y).unwrap();
+
+        let mut point = k_2;
+        point.double_assign().unwrap();
+        assert_eq!(point, k_4);
+        assert_eq!(
+            Secp256r1::BASE_POINT
+                .as_projective()
+                .mul_scalar(&UBigInt::from(4))
+                .as_affine(),
+            Some(k_4)
+        );
     }
 }
@@ -25,18 +25,26 @@ impl<C: EllipticCurve> ProjectivePoint<C> {
         z: FieldElement::ZERO,
     };
 
+    /// `3 * B`, which the complete formulas use in place of `B`.
+    const B3: FieldElement<C> = C::B.mul_digit(3);
+
     /// Returns `true` if `self` is the point at infinity and `false` otherwise.
-    pub fn is_infinity(&self) -> bool {
-        self.z == FieldElement::ZERO
+    pub const fn is_infinity(&self) -> bool {
+        self.z.inner().is_zero()
     }
 
     /// Converts `self` into its affine representation.
+    ///
+    /// # Constant-timedness
+    /// This is constant-time, except for whether `self` is the point at infinity. The `z`
+    /// coordinate of a product depends on the scalar, so it is inverted in constant time.
     pub fn as_affine(self) -> Option<AffinePoint<C>> {
         if self.is_infinity() {
             return None;
         }
-        let z_inv = self.z.inverse();
+        let z_inv = self.z.inverse_const_time();
 
+        // SAFETY: `(x/z, y/z)` is the affine form of a point on the curve.
         unsafe {
             Some(AffinePoint::new_unchecked(
                 self.x.mul(&z_inv),
@@ -45,7 +53,35 @@ impl<C: EllipticCurve> ProjectivePoint<C> {
         }
     }
 
-    /// Creates a new [`ProjectivePoint`] along the curve.
+    /// Creates a new [`ProjectivePoint`], returning [`None`] if it isn't on the curve.
+    ///
+    /// `(x, y, z)` stands for the affine point `(x/z, y/z)`. The point at infinity is `(0, y, 0)`
+    /// for any non-zero `y`.
+    pub const fn new(x: FieldElement<C>, y: FieldElement<C>, z: FieldElement<C>) -> Option<Self> {
+        let point = Self { x, y, z };
+        if z.inner().is_zero() {
+            return match x.inner().is_zero() && !y.inner().is_zero() {
+                true => Some(point),
+                false => None,
+            };
+        }
+        // the curve equation, multiplied through by `z^3`
+        let zz = z.sqr();
+        let lhs = y.sqr().mul(&z);
+        let rhs = x
+            .sqr()
+            .mul(&x)
+            .add(&C::A.mul(&x).mul(&zz))
+            .add(&C::B.mul(&zz).mul(&z));
+        match lhs.sub(&rhs).inner().is_zero() {
+            true => Some(point),
+            false => None,
+        }
+    }
+
+    /// Creates a new [`ProjectivePoint`] without verifying that it is on the curve.
+    ///
+    /// In most cases, it's better to use the safe version: [`Self::new()`]
     ///
     /// # Safety
     /// The point must be on the curve.
@@ -57,30 +93,59 @@ impl<C: EllipticCurve> ProjectivePoint<C> {
         Self { x, y, z }
     }
 
-    /// Adds `self` and `rhs`, returning the other point if either point is infinity.
+    /// Returns `self + rhs`.
     ///
-    /// Generally, use this method instead of [`add_fast`]. If, however, it is guaranteed that neither
-    /// point is infinity, consider using [`add_fast`].
+    /// This uses the complete addition formula of [Renes, Costello, and Batina], so it has no
+    /// exceptional cases: adding a point to itself, to its negation, or to the point at infinity
+    /// all give the right result without branching.
     ///
-    /// [`add_fast`]: ProjectivePoint::add_fast
-    pub fn add(&self, rhs: &Self) -> Self {
-        if self.is_infinity() {
-            return *rhs;
-        }
-        if rhs.is_infinity() {
-            return *self;
-        }
-        Self::add_fast(self, rhs)
+    /// The formula is only complete on curves of odd order,
+    /// which includes every curve whose [`EllipticCurve::COFACTOR`] is 1.
+    ///
+    /// [Renes, Costello, and Batina]: https://eprint.iacr.org/2015/1060
+    pub const fn add(&self, rhs: &Self) -> Self {
+        // Algorithm 1 of the paper
+        let xx = self.x.mul(&rhs.x);
+        let yy = self.y.mul(&rhs.y);
+        let zz = self.z.mul(&rhs.z);
+        // `x1*y2 + x2*y1`, and so on, with one multiplication each
+        let xy = self
+            .x
+            .add(&self.y)
+            .mul(&rhs.x.add(&rhs.y))
+            .sub(&xx.add(&yy));
+        let xz = self
+            .x
+            .add(&self.z)
+            .mul(&rhs.x.add(&rhs.z))
+            .sub(&xx.add(&zz));
+        let yz = self
+            .y
+            .add(&self.z)
+            .mul(&rhs.y.add(&rhs.z))
+            .sub(&yy.add(&zz));
+
+        let u = C::A.mul(&xz).add(&Self::B3.mul(&zz));
+        let v = yy.sub(&u);
+        let w = yy.add(&u);
+        let a_zz = C::A.mul(&zz);
+        let three_xx = xx.mul_digit(3).add(&a_zz);
+        let s = Self::B3.mul(&xz).add(&C::A.mul(&xx.sub(&a_zz)));
+
+        let x = xy.mul(&v).sub(&yz.mul(&s));
+        let y = v.mul(&w).add(&three_xx.mul(&s));
+        let z = yz.mul(&w).add(&xy.mul(&three_xx));
+        Self { x, y, z }
     }
 
     /// Adds `self` and `rhs`, returning [`POINT_AT_INF`] if either point is infinity.
     ///
-    /// Generally, use [`add`] instead of this method. If, however, it is guaranteed that neither
-    /// point is infinity, consider using this method.
+    /// This formula also fails when `self` and `rhs` are the same point.
+    /// Generally, use [`add`] instead of this method, which has no exceptional cases.
     ///
     /// [`add`]: ProjectivePoint::add
     /// [`POINT_AT_INF`]: ProjectivePoint::POINT_AT_INF
-    pub fn add_fast(&self, rhs: &Self) -> Self {
+    pub const fn add_fast(&self, rhs: &Self) -> Self {
         let u_2 = self.y.mul(&rhs.z);
         let u = {
             let mut u_1 = rhs.y.mul(&self.z);
@@ -115,78 +180,51 @@ impl<C: EllipticCurve> ProjectivePoint<C> {
             y
         };
         let z = v_cube.mul(&w);
-        unsafe { Self::new_unchecked(x, y, z) }
+        Self { x, y, z }
     }
 
-    pub fn add_assign_fast(&mut self, rhs: &Self) {
+    pub const fn add_assign_fast(&mut self, rhs: &Self) {
         // SAFETY: the caller guarantees that neither point is POINT_AT_INF.
         *self = self.add_fast(rhs);
     }
 
-    pub fn add_assign(&mut self, rhs: &Self) {
-        if self.is_infinity() {
-            *self = *rhs;
-            return;
-        }
-        if rhs.is_infinity() {
-            return;
-        }
-        self.add_assign_fast(rhs);
+    pub const fn add_assign(&mut self, rhs: &Self) {
+        *self = self.add(rhs);
     }
 
-    pub fn double(&self) -> Self {
-        let w = {
-            let mut three_x_sqr = self.x.sqr();
-            three_x_sqr.mul_digit_assign(3);
-
-            let mut w = self.z.sqr();
-            w.mul_assign(&C::A);
-            w.add_assign(&three_x_sqr);
-            w
-        };
-        let s = self.y.mul(&self.z);
-
-        // TODO: only reduce once?
-        let eight_s_sqr = {
-            let mut temp = s.sqr();
-            temp.mul_digit_assign(8);
-            temp
-        };
-        let b = {
-            let mut b = self.x.mul(&self.y);
-            b.mul_assign(&s);
-            b
-        };
-        let h = {
-            let mut h = w.sqr();
-            h.sub_assign(&b.mul_digit(8));
-            h
-        };
-        let x = {
-            let mut x = h.mul(&s);
-            x.double_assign();
-            x
-        };
-        let y = {
-            let mut temp = self.y.sqr();
-            // TODO: only reduce once?
-            temp.mul_assign(&eight_s_sqr);
-
-            let mut y = b.mul_digit(4);
-            y.sub_assign(&h);
-            y.mul_assign(&w);
-            y.sub_assign(&temp);
-            y
-        };
-        let z = eight_s_sqr.mul(&s);
+    /// Returns `self + self`.
+    ///
+    /// Like [`ProjectivePoint::add`], this uses a complete formula from
+    /// [Renes, Costello, and Batina], so doubling the point at infinity or a point of order 2
+    /// needs no special case.
+    ///
+    /// [Renes, Costello, and Batina]: https://eprint.iacr.org/2015/1060
+    pub const fn double(&self) -> Self {
+        // Algorithm 3 of the paper
+        let xx = self.x.sqr();
+        let yy = self.y.sqr();
+        let zz = self.z.sqr();
+        let two_xy = self.x.mul(&self.y).double();
+        let two_xz = self.x.mul(&self.z).double();
+        let two_yz = self.y.mul(&self.z).double();
+
+        let u = C::A.mul(&two_xz).add(&Self::B3.mul(&zz));
+        let v = yy.sub(&u);
+        let w = yy.add(&u);
+        let a_zz = C::A.mul(&zz);
+        let s = Self::B3.mul(&two_xz).add(&C::A.mul(&xx.sub(&a_zz)));
+
+        let x = two_xy.mul(&v).sub(&two_yz.mul(&s));
+        let y = v.mul(&w).add(&xx.mul_digit(3).add(&a_zz).mul(&s));
+        let z = two_yz.mul(&yy).double().double();
         Self { x, y, z }
     }
 
-    pub fn double_assign(&mut self) {
+    pub const fn double_assign(&mut self) {
         *self = self.double()
     }
 
-    pub fn neg(&self) -> Self {
+    pub const fn neg(&self) -> Self {
         Self {
             x: self.x,
             y: self.y.neg(),
@@ -194,27 +232,50 @@ impl<C: EllipticCurve> ProjectivePoint<C> {
         }
     }
 
-    pub fn neg_assign(&mut self) {
+    pub const fn neg_assign(&mut self) {
         self.y.neg_assign();
     }
 
-    pub fn mul_scalar(&self, scalar: &UBigInt<4>) -> Self {
+    /// Swaps `self` and `other` if `choice` is `true`, otherwise leaves both unchanged.
+    ///
+    /// # Constant-timedness
+    /// This is a constant-time operation.
+    pub const fn conditional_swap(&mut self, other: &mut Self, choice: bool) {
+        self.x.conditional_swap(&mut other.x, choice);
+        self.y.conditional_swap(&mut other.y, choice);
+        self.z.conditional_swap(&mut other.z, choice);
+    }
+
+    /// Returns `self * scalar`.
+    ///
+    /// This is a Montgomery ladder over every bit of `scalar`, leading zeros included. Each step
+    /// does one addition and one doubling with the complete formulas, and the bit only decides
+    /// which points they are applied to, through [`ProjectivePoint::conditional_swap`]. So the
+    /// sequence of operations, and the memory they touch, is the same for every scalar.
+    ///
+    /// # Constant-timedness
+    /// This is a constant-time operation with respect to `scalar`, as long as the field
+    /// arithmetic of `C` is.
+    pub const fn mul_scalar(&self, scalar: &UBigInt<4>) -> Self {
+        // `result + self` is always `temp`
         let mut result = Self::POINT_AT_INF;
         let mut temp = *self;
-        let num_bits = scalar.count_bits();
-        for i in (0..num_bits).rev() {
-            if scalar.get_bit(i) {
-                result.add_assign(&temp);
-                temp.double_assign();
-            } else {
-                temp.add_assign(&result);
-                result.double_assign();
-            }
+        let mut swapped = false;
+        let mut i = UBigInt::<4>::BITS;
+        while i > 0 {
+            i -= 1;
+            let bit = scalar.get_bit(i);
+            // swaps are only undone when the next bit differs, which saves one per step
+            result.conditional_swap(&mut temp, swapped ^ bit);
+            swapped = bit;
+            temp.add_assign(&result);
+            result.double_assign();
         }
+        result.conditional_swap(&mut temp, swapped);
         result
     }
 
-    pub fn mul_scalar_assign(&mut self, scalar: UBigInt<4>) {
+    pub const fn mul_scalar_assign(&mut self, scalar: UBigInt<4>) {
         *self = self.mul_scalar(&scalar);
     }
 }
@@ -236,44 +297,40 @@ mod tests {
 
     #[test]
     fn add() {
-        let x = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0xa60b48fc47669978,
-                0xc08969e277f21b35,
-                0x8a52380304b51ac3,
-                0x7cf27b188d034f7e,
-            ]))
-        };
-        let y = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x9e04b79d227873d1,
-                0xba7dade63ce98229,
-                0x293d9ac69f7430db,
-                0x07775510db8ed040,
-            ]))
-        };
+        let x = FieldElement::try_new(UBigInt([
+            0xa60b48fc47669978,
+            0xc08969e277f21b35,
+            0x8a52380304b51ac3,
+            0x7cf27b188d034f7e,
+        ]))
+        .unwrap();
+        let y = FieldElement::try_new(UBigInt([
+            0x9e04b79d227873d1,
+            0xba7dade63ce98229,
+            0x293d9ac69f7430db,
+            0x07775510db8ed040,
+        ]))
+        .unwrap();
         // Secp256r1::BASE_POINT * 2
-        let k_2 = unsafe { AffinePoint::new_unchecked(x, y) }.as_projective();
-
-        let x = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0xfb41661bc6e7fd6c,
-                0xe6c6b721efada985,
-                0xc8f7ef951d4bf165,
-                0x5ecbe4d1a6330a44,
-            ]))
-        };
-
-        let y = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x9a79b127a27d5032,
-                0xd82ab036384fb83d,
-                0x374b06ce1a64a2ec,
-                0x8734640c4998ff7e,
-            ]))
-        };
+        let k_2 = AffinePoint::new(x, y).unwrap().as_projective();
+
+        let x = FieldElement::try_new(UBigInt([
+            0xfb41661bc6e7fd6c,
+            0xe6c6b721efada985,
+            0xc8f7ef951d4bf165,
+            0x5ecbe4d1a6330a44,
+        ]))
+        .unwrap();
+
+        let y = FieldElement::try_new(UBigInt([
+            0x9a79b127a27d5032,
+            0xd82ab036384fb83d,
+            0x374b06ce1a64a2ec,
+            0x8734640c4998ff7e,
+        ]))
+        .unwrap();
         // Secp256r1::BASE_POINT * 3
-        let k_3 = unsafe { AffinePoint::new_unchecked(x, y) }.as_projective();
+        let k_3 = AffinePoint::new(x, y).unwrap().as_projective();
 
         let sum = k_2.add(&Secp256r1::BASE_POINT.as_projective());
         assert_eq!(sum, k_3);
@@ -292,44 +349,40 @@ mod tests {
 
     #[test]
     fn add_fast() {
-        let x = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0xa60b48fc47669978,
-                0xc08969e277f21b35,
-                0x8a52380304b51ac3,
-                0x7cf27b188d034f7e,
-            ]))
-        };
-        let y = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x9e04b79d227873d1,
-                0xba7dade63ce98229,
-                0x293d9ac69f7430db,
-                0x07775510db8ed040,
-            ]))
-        };
+        let x = FieldElement::try_new(UBigInt([
+            0xa60b48fc47669978,
+            0xc08969e277f21b35,
+            0x8a52380304b51ac3,
+            0x7cf27b188d034f7e,
+        ]))
+        .unwrap();
+        let y = FieldElement::try_new(UBigInt([
+            0x9e04b79d227873d1,
+            0xba7dade63ce98229,
+            0x293d9ac69f7430db,
+            0x07775510db8ed040,
+        ]))
+        .unwrap();
         // Secp256r1::BASE_POINT * 2
-        let k_2 = unsafe { AffinePoint::new_unchecked(x, y) }.as_projective();
-
-        let x = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0xfb41661bc6e7fd6c,
-                0xe6c6b721efada985,
-                0xc8f7ef951d4bf165,
-                0x5ecbe4d1a6330a44,
-            ]))
-        };
-
-        let y = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x9a79b127a27d5032,
-                0xd82ab036384fb83d,
-                0x374b06ce1a64a2ec,
-                0x8734640c4998ff7e,
-            ]))
-        };
+        let k_2 = AffinePoint::new(x, y).unwrap().as_projective();
+
+        let x = FieldElement::try_new(UBigInt([
+            0xfb41661bc6e7fd6c,
+            0xe6c6b721efada985,
+            0xc8f7ef951d4bf165,
+            0x5ecbe4d1a6330a44,
+        ]))
+        .unwrap();
+
+        let y = FieldElement::try_new(UBigInt([
+            0x9a79b127a27d5032,
+            0xd82ab036384fb83d,
+            0x374b06ce1a64a2ec,
+            0x8734640c4998ff7e,
+        ]))
+        .unwrap();
         // Secp256r1::BASE_POINT * 3
-        let k_3 = unsafe { AffinePoint::new_unchecked(x, y) }.as_projective();
+        let k_3 = AffinePoint::new(x, y).unwrap().as_projective();
 
         let sum = k_2.add_fast(&Secp256r1::BASE_POINT.as_projective());
         assert_eq!(sum, k_3);
@@ -343,26 +396,61 @@ mod tests {
         assert_eq!(inf, ProjectivePoint::POINT_AT_INF);
     }
 
+    #[test]
+    fn complete() {
+        let base_point = Secp256r1::BASE_POINT.as_projective();
+        let inf = ProjectivePoint::<Secp256r1>::POINT_AT_INF;
+
+        assert_eq!(base_point.add(&base_point), base_point.double());
+        assert!(base_point.add(&base_point.neg()).is_infinity());
+        assert!(inf.add(&inf).is_infinity());
+        assert!(inf.double().is_infinity());
+
+        // the same point with a different `z`
+        let z = FieldElement::new(UBigInt::from(0x0123456789abcdef));
+        let scaled = ProjectivePoint::new(base_point.x.mul(&z), base_point.y.mul(&z), z).unwrap();
+        assert_eq!(scaled.add(&base_point), base_point.double());
+        assert_eq!(scaled.double(), base_point.double());
+        assert!(scaled.add(&base_point.neg()).is_infinity());
+    }
+
+    #[test]
+    fn new() {
+        let base_point = Secp256r1::BASE_POINT.as_projective();
+        let z = FieldElement::new(UBigInt::from(7));
+        let scaled = ProjectivePoint::new(base_point.x.mul(&z), base_point.y.mul(&z), z);
+        assert_eq!(scaled, Some(base_point));
+        // only `x` was scaled
+        assert_eq!(
+            ProjectivePoint::new(base_point.x.mul(&z), base_point.y, z),
+            None
+        );
+
+        let infinity = ProjectivePoint::<Secp256r1>::new(FieldElement::ZERO, z, FieldElement::ZERO);
+        assert!(infinity.unwrap().is_infinity());
+        let zero = FieldElement::ZERO;
+        assert_eq!(ProjectivePoint::<Secp256r1>::new(zero, zero, zero), None);
+        assert_eq!(ProjectivePoint::<Secp256r1>::new(z, z, zero), None);
+    }
+
     #[test]
     fn double() {
         let point = Secp256r1::BASE_POINT.as_projective().double();
-        let x = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0xa60b48fc47669978,
-                0xc08969e277f21b35,
-                0x8a52380304b51ac3,
-                0x7cf27b188d034f7e,
-            ]))
-        };
-        let y = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x9e04b79d227873d1,
-                0xba7dade63ce98229,
-                0x293d9ac69f7430db,
-                0x07775510db8ed040,
-            ]))
-        };
-        let sum = unsafe { AffinePoint::new_unchecked(x, y) }.as_projective();
+        let x = FieldElement::try_new(UBigInt([
+            0xa60b48fc47669978,
+            0xc08969e277f21b35,
+            0x8a52380304b51ac3,
+            0x7cf27b188d034f7e,
+        ]))
+        .unwrap();
+        let y = FieldElement::try_new(UBigInt([
+            0x9e04b79d227873d1,
+            0xba7dade63ce98229,
+            0x293d9ac69f7430db,
+            0x07775510db8ed040,
+        ]))
+        .unwrap();
+        let sum = AffinePoint::new(x, y).unwrap().as_projective();
         assert_eq!(point, sum);
     }
 
@@ -375,28 +463,106 @@ mod tests {
 
         let scalar = UBigInt::from(112233445566778899);
 
-        let x = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x22795513aeaab82f,
-                0x77dbfb3ae3d96f4c,
-                0x807fe862a86be779,
-                0x339150844ec15234,
-            ]))
-        };
+        let x = FieldElement::try_new(UBigInt([
+            0x22795513aeaab82f,
+            0x77dbfb3ae3d96f4c,
+            0x807fe862a86be779,
+            0x339150844ec15234,
+        ]))
+        .unwrap();
 
-        let y = unsafe {
-            FieldElement::new_unchecked(UBigInt([
-                0x5ada38b674336a21,
-                0x55840f2034730e9b,
-                0x583f51e85a5eb3a1,
-                0xb1c14ddfdc8ec1b2,
-            ]))
-        };
+        let y = FieldElement::try_new(UBigInt([
+            0x5ada38b674336a21,
+            0x55840f2034730e9b,
+            0x583f51e85a5eb3a1,
+            0xb1c14ddfdc8ec1b2,
+        ]))
+        .unwrap();
 
-        let product = unsafe { AffinePoint::new_unchecked(x, y) }.as_projective();
+        let product = AffinePoint::new(x, y).unwrap().as_projective();
 
         let point = Secp256r1::BASE_POINT.as_projective().mul_scalar(&scalar);
 
         assert_eq!(point, product)
     }
+
+    #[test]
+    fn mul_scalar_leading_zeros() {
+        let base_point = Secp256r1::BASE_POINT.as_projective();
+        let scalar = UBigInt([0, 0, 0, 1 << 63]);
+        let mut expected = base_point;
+        for _ in 0..255 {
+            expected.double_assign();
+        }
+        assert_eq!(base_point.mul_scalar(&scalar), expected);
+        assert!(base_point.mul_scalar(&UBigInt::ZERO).is_infinity());
+    }
+
+    /// Checks that the time `mul_scalar` takes doesn't depend on the scalar, with Welch's t-test
+    /// as in [dudect].
+    ///
+    /// Timings are too noisy on shared machines for this to run by default. Run it with
+    /// `cargo test --release -- --ignored`.
+    ///
+    /// [dudect]: https://eprint.iacr.org/2016/1123
+    #[test]
+    #[ignore = "timing measurements are noisy; run in release mode on an idle machine"]
+    fn mul_scalar_timing() {
+        extern crate std;
+        use std::time::Instant;
+
+        // beyond this, the timings are very likely to depend on the class
+        const MAX_T: f64 = 10.0;
+        const SAMPLES: usize = 2000;
+
+        let base_point = Secp256r1::BASE_POINT.as_projective();
+        // a xorshift generator, which is plenty for picking classes and scalars
+        let mut state = 0x2545f4914f6cdd1d_u64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        // the scalar with the fewest bits set against random ones
+        let mut times = [[0.0; SAMPLES]; 2];
+        let mut counts = [0; 2];
+        while counts[0] < SAMPLES || counts[1] < SAMPLES {
+            let class = (next() & 1) as usize;
+            if counts[class] == SAMPLES {
+                continue;
+            }
+            let scalar = match class {
+                0 => UBigInt::ONE,
+                _ => UBigInt([next(), next(), next(), next()]),
+            };
+            let start = Instant::now();
+            core::hint::black_box(base_point.mul_scalar(core::hint::black_box(&scalar)));
+            times[class][counts[class]] = start.elapsed().as_nanos() as f64;
+            counts[class] += 1;
+        }
+
+        let stats = times.map(|times| {
+            let mean = times.iter().sum::<f64>() / SAMPLES as f64;
+            let var =
+                times.iter().map(|t| (t - mean) * (t - mean)).sum::<f64>() / (SAMPLES - 1) as f64;
+            (mean, var)
+        });
+        let t = (stats[0].0 - stats[1].0)
+            / (stats[0].1 / SAMPLES as f64 + stats[1].1 / SAMPLES as f64).sqrt();
+        assert!(t.abs() < MAX_T, "t = {t}");
+    }
+
+    #[test]
+    fn const_eval() {
+        const BASE_POINT: ProjectivePoint<Secp256r1> = Secp256r1::BASE_POINT.as_projective();
+        // evaluated at compile time
+        // `mul_scalar` is a `const fn` too, but it processes all 256 bits of the scalar, which
+        // takes the const evaluator longer than it allows by default
+        const K_3: ProjectivePoint<Secp256r1> = BASE_POINT.double().add(&BASE_POINT);
+
+        let k_3 = BASE_POINT.mul_scalar(&UBigInt::from(3));
+        assert_eq!(K_3, k_3);
+    }
 }
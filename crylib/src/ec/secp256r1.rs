@@ -29,40 +29,37 @@ unsafe impl FiniteField for P256Order {
 }
 
 impl EllipticCurve for Secp256r1 {
-    const BASE_POINT: AffinePoint<Self> = unsafe {
-        AffinePoint::new_unchecked(
-            FieldElement::new_unchecked(UBigInt([
-                0xf4a13945d898c296,
-                0x77037d812deb33a0,
-                0xf8bce6e563a440f2,
-                0x6b17d1f2e12c4247,
-            ])),
-            FieldElement::new_unchecked(UBigInt([
-                0xcbb6406837bf51f5,
-                0x2bce33576b315ece,
-                0x8ee7eb4a7c0f9e16,
-                0x4fe342e2fe1a7f9b,
-            ])),
-        )
-    };
+    const BASE_POINT: AffinePoint<Self> = AffinePoint::new(
+        FieldElement::new(UBigInt([
+            0xf4a13945d898c296,
+            0x77037d812deb33a0,
+            0xf8bce6e563a440f2,
+            0x6b17d1f2e12c4247,
+        ])),
+        FieldElement::new(UBigInt([
+            0xcbb6406837bf51f5,
+            0x2bce33576b315ece,
+            0x8ee7eb4a7c0f9e16,
+            0x4fe342e2fe1a7f9b,
+        ])),
+    )
+    .expect("the base point is on the curve");
 
-    const A: FieldElement<Self> = unsafe {
-        FieldElement::new_unchecked(UBigInt([
-            0xfffffffffffffffc,
-            0x00000000ffffffff,
-            0x0000000000000000,
-            0xffffffff00000001,
-        ]))
-    };
+    const A: FieldElement<Self> = FieldElement::new(UBigInt([
+        0xfffffffffffffffc,
+        0x00000000ffffffff,
+        0x0000000000000000,
+        0xffffffff00000001,
+    ]));
+
+    const B: FieldElement<Self> = FieldElement::new(UBigInt([
+        0x3bce3c3e27d2604b,
+        0x651d06b0cc53b0f6,
+        0xb3ebbd55769886bc,
+        0x5ac635d8aa3a93e7,
+    ]));
 
-    const B: FieldElement<Self> = unsafe {
-        FieldElement::new_unchecked(UBigInt([
-            0x3bce3c3e27d2604b,
-            0x651d06b0cc53b0f6,
-            0xb3ebbd55769886bc,
-            0x5ac635d8aa3a93e7,
-        ]))
-    };
+    const COFACTOR: u64 = 1;
 
     type Order = P256Order;
 }
@@ -0,0 +1,283 @@
+//! One-shot public-key encryption with the Elliptic Curve Integrated Encryption Scheme.
+//!
+//! The sender generates an ephemeral key pair, performs ECDH with the recipient's public key,
+//! and derives an AES-256-GCM key and nonce from the shared secret with HKDF-SHA256. The sealed
+//! message is the ephemeral public key, followed by the cipher text, followed by the tag:
+//!
+//! ```text
+//! 0x04 || X || Y || cipher text || tag
+//! ```
+//!
+//! Because every message has its own ephemeral key, the nonce never repeats.
+//! `info` is bound into the key derivation, so it must match on both ends; it is a good place
+//! for an application-specific context string.
+//!
+//! # Examples
+//!
+//! ```
+//! use crylib::big_int::UBigInt;
+//! use crylib::ec::{EllipticCurve, Secp256r1};
+//! use crylib::ecies;
+//! use crylib::finite_field::FieldElement;
+//!
+//! let priv_key = FieldElement::new(UBigInt([1, 2, 3, 4]));
+//! let pub_key = Secp256r1::BASE_POINT
+//!     .as_projective()
+//!     .mul_scalar(priv_key.inner())
+//!     .as_affine()
+//!     .unwrap();
+//!
+//! // a real application MUST use a cryptographically secure random number generator
+//! let random_num_gen = || FieldElement::new(UBigInt([5, 6, 7, 8]));
+//!
+//! let msg = b"Top secret message";
+//! let mut sealed = [0; 18 + ecies::OVERHEAD];
+//! ecies::encrypt(&pub_key, msg, b"example", random_num_gen, &mut sealed);
+//!
+//! let mut opened = [0; 18];
+//! ecies::decrypt::<Secp256r1>(&priv_key, &sealed, b"example", &mut opened).unwrap();
+//! assert_eq!(&opened, msg);
+//! ```
+use crate::aead::gcm::{Aes256, Gcm};
+use crate::aead::{Aead, IV_SIZE, TAG_SIZE};
+use crate::ec::{ecdh, AffinePoint, EllipticCurve};
+use crate::finite_field::FieldElement;
+use crate::hash::Sha256;
+use crate::hkdf;
+
+/// The size of an uncompressed public key.
+pub const POINT_SIZE: usize = ecdh::POINT_SIZE;
+
+/// The number of bytes a sealed message is longer than its plain text.
+pub const OVERHEAD: usize = POINT_SIZE + TAG_SIZE;
+
+/// The size of the derived AES-256 key.
+const KEY_SIZE: usize = 32;
+
+/// An error that is returned when a sealed message can't be opened.
+///
+/// This happens if the message is malformed, if the ephemeral key is not a valid point,
+/// if the message was created for another key or with another `info`, or if it was modified.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DecryptError;
+
+impl core::fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "sealed message could not be opened")
+    }
+}
+
+impl core::error::Error for DecryptError {}
+
+/// Encrypts `msg` to the holder of the private key corresponding to `pub_key`,
+/// writing the sealed message to `buf`.
+///
+/// `random_num_gen` must be a cryptographically secure random number generator.
+/// It is used to create the ephemeral key.
+///
+/// # Panics
+///
+/// The function will panic if `buf.len()` < `msg.len() + OVERHEAD`
+pub fn encrypt<C: EllipticCurve>(
+    pub_key: &AffinePoint<C>,
+    msg: &[u8],
+    info: &[u8],
+    random_num_gen: impl Fn() -> FieldElement<C::Order>,
+    buf: &mut [u8],
+) {
+    let buf = &mut buf[..msg.len() + OVERHEAD];
+
+    let (eph_pub_key, shared_secret) = loop {
+        let eph_priv_key = random_num_gen();
+        let Some(eph_pub_key) = C::BASE_POINT
+            .as_projective()
+            .mul_scalar(eph_priv_key.inner())
+            .as_affine()
+        else {
+            continue;
+        };
+        // the recipient's key is trusted to be valid, so the product is only infinite for a zero
+        // ephemeral key, which was already excluded
+        let Some(shared_point) = pub_key
+            .as_projective()
+            .mul_scalar(eph_priv_key.inner())
+            .as_affine()
+        else {
+            continue;
+        };
+        break (eph_pub_key, shared_point.x());
+    };
+
+    let (point, rest) = buf.split_at_mut(POINT_SIZE);
+    point.copy_from_slice(&ecdh::encode_point(&eph_pub_key));
+    let (cipher_text, tag) = rest.split_at_mut(msg.len());
+
+    let (cipher, init_vector) = derive_cipher(point, &shared_secret, info);
+    cipher_text.copy_from_slice(msg);
+    tag.copy_from_slice(&cipher.encrypt_inline(cipher_text, &[], &init_vector));
+}
+
+/// Decrypts the sealed message `sealed` with the recipient's private key,
+/// writing the plain text to `buf`.
+///
+/// The plain text is `sealed.len() - OVERHEAD` bytes long.
+/// If the message can't be opened, that part of `buf` is zeroed and [`DecryptError`] is returned.
+///
+/// # Panics
+///
+/// The function will panic if `buf.len()` < `sealed.len() - OVERHEAD`
+pub fn decrypt<C: EllipticCurve>(
+    priv_key: &FieldElement<C::Order>,
+    sealed: &[u8],
+    info: &[u8],
+    buf: &mut [u8],
+) -> Result<(), DecryptError> {
+    let Some(msg_len) = sealed.len().checked_sub(OVERHEAD) else {
+        return Err(DecryptError);
+    };
+    let buf = &mut buf[..msg_len];
+
+    let (point, rest) = sealed.split_at(POINT_SIZE);
+    let (cipher_text, tag) = rest.split_at(msg_len);
+
+    let Ok(eph_pub_key) = ecdh::decode_point::<C>(point) else {
+        buf.fill(0);
+        return Err(DecryptError);
+    };
+    let Some(shared_point) = eph_pub_key
+        .as_projective()
+        .mul_scalar(priv_key.inner())
+        .as_affine()
+    else {
+        buf.fill(0);
+        return Err(DecryptError);
+    };
+
+    let (cipher, init_vector) = derive_cipher(point, &shared_point.x(), info);
+    // we can safely unwrap because `tag` is guaranteed to have a length of `TAG_SIZE`
+    cipher
+        .decrypt(buf, cipher_text, &[], &init_vector, tag.try_into().unwrap())
+        .map_err(|_| DecryptError)
+}
+
+/// Derives the AEAD key and nonce from the ECDH shared secret.
+fn derive_cipher<C: EllipticCurve>(
+    eph_pub_key: &[u8],
+    shared_secret: &FieldElement<C>,
+    info: &[u8],
+) -> (Gcm<Aes256>, [u8; IV_SIZE]) {
+    let pr_key = hkdf::extract::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>(
+        eph_pub_key,
+        &shared_secret.to_bytes(),
+    );
+    let okm = hkdf::expand::<
+        { Sha256::HASH_SIZE },
+        { Sha256::BLOCK_SIZE },
+        { KEY_SIZE + IV_SIZE },
+        Sha256,
+    >(&pr_key, info);
+
+    let (key, init_vector) = okm.split_at(KEY_SIZE);
+    // we can safely unwrap because the halves are guaranteed to have lengths of `KEY_SIZE` and
+    // `IV_SIZE`
+    (
+        Gcm::new(key.try_into().unwrap()),
+        init_vector.try_into().unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decrypt, encrypt, DecryptError, OVERHEAD};
+    use crate::big_int::UBigInt;
+    use crate::ec::{AffinePoint, EllipticCurve, Secp256r1};
+    use crate::finite_field::FieldElement;
+
+    type Scalar = FieldElement<<Secp256r1 as EllipticCurve>::Order>;
+
+    fn key_pair(priv_key: [u64; 4]) -> (Scalar, AffinePoint<Secp256r1>) {
+        let priv_key = FieldElement::new(UBigInt(priv_key));
+        let pub_key = Secp256r1::BASE_POINT
+            .as_projective()
+            .mul_scalar(priv_key.inner())
+            .as_affine()
+            .unwrap();
+        (priv_key, pub_key)
+    }
+
+    #[test]
+    fn known_answer() {
+        // generated with pyca/cryptography using the same construction
+        let sealed = [
+            0x04, 0x98, 0x0e, 0xcc, 0x17, 0xc2, 0x4e, 0x2a, 0xa9, 0x14, 0x82, 0x41, 0xc5, 0xb8,
+            0xa9, 0xb7, 0xe4, 0x1d, 0xee, 0x50, 0x94, 0x19, 0xb0, 0x83, 0x81, 0xd7, 0x9f, 0xd6,
+            0xa0, 0x17, 0xf6, 0x0d, 0x06, 0xa7, 0x6e, 0x92, 0xf8, 0x58, 0xd8, 0x43, 0xc1, 0x2e,
+            0x76, 0x0e, 0xa2, 0x6a, 0x7c, 0xcb, 0x31, 0x2c, 0xc8, 0xdb, 0xd1, 0x3d, 0x4b, 0x18,
+            0xdd, 0x48, 0xa0, 0x05, 0x8c, 0x20, 0xd5, 0x63, 0xd4, 0x96, 0xca, 0x3c, 0xab, 0x27,
+            0x0f, 0x04, 0xb7, 0xff, 0x68, 0x3d, 0xdb, 0x48, 0x84, 0x8a, 0x9a, 0xc4, 0xc2, 0x26,
+            0x4b, 0xa0, 0x14, 0x10, 0x67, 0x14, 0xf4, 0x92, 0xf9, 0xfe, 0xa8, 0xaf, 0x89, 0x74,
+            0xf2,
+        ];
+        let (priv_key, pub_key) = key_pair([1, 2, 3, 4]);
+        let mut out = [0; 18 + OVERHEAD];
+        encrypt(
+            &pub_key,
+            b"Top secret message",
+            b"info",
+            || FieldElement::new(UBigInt([5, 6, 7, 8])),
+            &mut out,
+        );
+        assert_eq!(out, sealed);
+
+        let mut msg = [0; 18];
+        decrypt::<Secp256r1>(&priv_key, &sealed, b"info", &mut msg).unwrap();
+        assert_eq!(&msg, b"Top secret message");
+    }
+
+    #[test]
+    fn failures() {
+        let (priv_key, pub_key) = key_pair([1, 2, 3, 4]);
+        let (other_key, _) = key_pair([9, 9, 9, 9]);
+        let mut sealed = [0; 18 + OVERHEAD];
+        encrypt(
+            &pub_key,
+            b"Top secret message",
+            b"info",
+            || FieldElement::new(UBigInt([5, 6, 7, 8])),
+            &mut sealed,
+        );
+
+        let mut msg = [0xff; 18];
+        assert_eq!(
+            decrypt::<Secp256r1>(&other_key, &sealed, b"info", &mut msg),
+            Err(DecryptError)
+        );
+        assert_eq!(msg, [0; 18]);
+        assert_eq!(
+            decrypt::<Secp256r1>(&priv_key, &sealed, b"other info", &mut msg),
+            Err(DecryptError)
+        );
+
+        // a modified cipher text
+        let mut modified = sealed;
+        modified[70] ^= 1;
+        assert_eq!(
+            decrypt::<Secp256r1>(&priv_key, &modified, b"info", &mut msg),
+            Err(DecryptError)
+        );
+
+        // an ephemeral key that isn't on the curve
+        let mut modified = sealed;
+        modified[64] ^= 1;
+        assert_eq!(
+            decrypt::<Secp256r1>(&priv_key, &modified, b"info", &mut msg),
+            Err(DecryptError)
+        );
+
+        // too short to hold the ephemeral key and tag
+        assert_eq!(
+            decrypt::<Secp256r1>(&priv_key, &sealed[..OVERHEAD - 1], b"info", &mut msg),
+            Err(DecryptError)
+        );
+    }
+}
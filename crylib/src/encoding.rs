@@ -0,0 +1,34 @@
+//! Binary-to-text encodings that run in constant time.
+//!
+//! The encoders and decoders in this module are meant for key material, such as PEM files or
+//! logged secrets. They don't use lookup tables or branch on the data being converted, so the
+//! time they take only depends on its length, not on the secret bytes themselves.
+//!
+//! Everything writes into caller-provided buffers, so nothing is allocated.
+//! Private keys are usually stored as [`pem`], which builds on [`base64`].
+pub mod base64;
+pub mod hex;
+pub mod pem;
+
+/// The error that is returned when the input to a decoder isn't valid.
+///
+/// This includes input with the wrong length, characters outside of the alphabet,
+/// and, for base64, non-zero padding bits.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct InvalidEncoding;
+
+impl core::fmt::Display for InvalidEncoding {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "input is not validly encoded")
+    }
+}
+
+impl core::error::Error for InvalidEncoding {}
+
+/// Returns `-1` if `low <= val <= high`, and `0` otherwise, without branching.
+///
+/// All arguments must be in the range `0..=255`.
+#[inline]
+const fn in_range(val: i32, low: i32, high: i32) -> i32 {
+    ((low - 1 - val) & (val - high - 1)) >> 8
+}
@@ -0,0 +1,259 @@
+//! Base64 encoding with the standard alphabet and padding, as defined in [RFC 4648].
+//!
+//! This is the variant used by PEM.
+//! Decoding is strict: the input must be padded, and the unused bits of the last character must
+//! be zero, so every piece of data has exactly one valid encoding.
+//!
+//! # Examples
+//!
+//! ```
+//! use crylib::encoding::base64;
+//!
+//! let mut buf = [0; 8];
+//! assert_eq!(base64::encode(b"hello", &mut buf), "aGVsbG8=");
+//!
+//! let mut bytes = [0; 5];
+//! assert_eq!(base64::decode(b"aGVsbG8=", &mut bytes).as_deref(), Ok(&b"hello"[..]));
+//! ```
+//!
+//! [RFC 4648]: https://datatracker.ietf.org/doc/html/rfc4648#section-4
+use super::{in_range, InvalidEncoding};
+
+/// Returns the length of the base64 encoding of `len` bytes, including padding.
+pub const fn encoded_len(len: usize) -> usize {
+    len.div_ceil(3) * 4
+}
+
+/// Returns the length of the data encoded by `encoded`.
+///
+/// Returns [`InvalidEncoding`] if `encoded` isn't a multiple of 4 long or is over-padded.
+/// Only the padding is inspected; the other characters are checked by [`decode`].
+pub const fn decoded_len(encoded: &[u8]) -> Result<usize, InvalidEncoding> {
+    if !encoded.len().is_multiple_of(4) {
+        return Err(InvalidEncoding);
+    }
+    let padding = match encoded {
+        [.., b'=', b'='] => 2,
+        [.., b'='] => 1,
+        _ => 0,
+    };
+    Ok(encoded.len() / 4 * 3 - padding)
+}
+
+/// Encodes `data` into `buf`, returning the encoded part of `buf`.
+///
+/// # Panics
+///
+/// The function will panic if `buf.len()` < [`encoded_len`]`(data.len())`.
+pub fn encode<'a>(data: &[u8], buf: &'a mut [u8]) -> &'a str {
+    let buf = &mut buf[..encoded_len(data.len())];
+    // TODO: use `array_chunks` once stabilized
+    for (quad, chunk) in buf.chunks_exact_mut(4).zip(data.chunks(3)) {
+        let mut triple = [0; 3];
+        triple[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes([0, triple[0], triple[1], triple[2]]);
+
+        for (i, char) in quad.iter_mut().enumerate() {
+            *char = encode_sextet((bits >> (18 - 6 * i)) as u8 & 0x3f);
+        }
+        // the padding only depends on the length of `data`, which isn't secret
+        quad[chunk.len() + 1..].fill(b'=');
+    }
+    // SAFETY: `encode_sextet` only returns characters of the base64 alphabet, which are ASCII.
+    unsafe { core::str::from_utf8_unchecked(buf) }
+}
+
+/// Decodes `encoded` into `buf`, returning the decoded part of `buf`.
+///
+/// Every character is checked, even after an invalid one is found.
+/// On failure, the decoded part of `buf` is zeroed and [`InvalidEncoding`] is returned.
+///
+/// # Panics
+///
+/// The function will panic if `buf.len()` < [`decoded_len`]`(encoded)`.
+pub fn decode<'a>(encoded: &[u8], buf: &'a mut [u8]) -> Result<&'a mut [u8], InvalidEncoding> {
+    decode_chars(encoded.iter().copied(), buf)
+}
+
+/// Returns the length of the data encoded by the characters yielded by `chars`.
+///
+/// Like [`decoded_len`], only the padding is inspected.
+pub(super) fn chars_decoded_len(chars: impl Iterator<Item = u8>) -> Result<usize, InvalidEncoding> {
+    let mut count = 0usize;
+    let mut last = [0; 2];
+    for char in chars {
+        count += 1;
+        last = [last[1], char];
+    }
+    if !count.is_multiple_of(4) {
+        return Err(InvalidEncoding);
+    }
+    let padding = match last {
+        [b'=', b'='] => 2,
+        [_, b'='] => 1,
+        _ => 0,
+    };
+    Ok(count / 4 * 3 - padding)
+}
+
+/// Decodes the characters yielded by `chars` into `buf`.
+///
+/// `chars` is walked twice: once to find its length and padding, then once to decode it.
+/// Only the length and padding affect control flow, so the decoding itself is constant-time as
+/// long as `chars` is.
+///
+/// # Panics
+///
+/// The function will panic if `buf` is shorter than the decoded data.
+pub(super) fn decode_chars(
+    chars: impl Iterator<Item = u8> + Clone,
+    buf: &mut [u8],
+) -> Result<&mut [u8], InvalidEncoding> {
+    let len = chars_decoded_len(chars.clone())?;
+    let buf = &mut buf[..len];
+    let quads = len.div_ceil(3);
+    let padding = quads * 3 - len;
+
+    let mut chars = chars;
+    let mut invalid = 0;
+    for (i, chunk) in buf.chunks_mut(3).enumerate() {
+        // the padding characters have already been checked
+        let significant = if i + 1 == quads { 4 - padding } else { 4 };
+
+        let mut bits = 0;
+        for j in 0..4 {
+            // we can safely unwrap because `chars` was just counted
+            let char = chars.next().unwrap();
+            if j < significant {
+                let sextet = decode_char(char);
+                // `-1` for invalid characters, `0` otherwise
+                invalid |= sextet >> 8;
+                bits |= (sextet as u32 & 0x3f) << (18 - 6 * j);
+            }
+        }
+        let [_, bytes @ ..] = bits.to_be_bytes();
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+
+        // bits that don't fit in the output must be zero for the encoding to be canonical
+        for &unused in &bytes[chunk.len()..] {
+            invalid |= unused as i32;
+        }
+    }
+
+    if invalid != 0 {
+        buf.fill(0);
+        return Err(InvalidEncoding);
+    }
+    Ok(buf)
+}
+
+/// Converts a value in `0..64` to its base64 character.
+#[inline]
+const fn encode_sextet(sextet: u8) -> u8 {
+    let sextet = sextet as i32;
+    // start from 'A' and shift the offset at each boundary of the alphabet
+    let mut offset = b'A' as i32;
+    offset += ((25 - sextet) >> 8) & (b'a' as i32 - 26 - b'A' as i32);
+    offset -= ((51 - sextet) >> 8) & (b'a' as i32 - 26 - (b'0' as i32 - 52));
+    offset -= ((61 - sextet) >> 8) & (b'0' as i32 - 52 - (b'+' as i32 - 62));
+    offset += ((62 - sextet) >> 8) & (b'/' as i32 - 63 - (b'+' as i32 - 62));
+    (sextet + offset) as u8
+}
+
+/// Converts a base64 character to its value, or returns `-1` if `char` isn't one.
+#[inline]
+const fn decode_char(char: u8) -> i32 {
+    let char = char as i32;
+    let mut sextet = -1;
+    // each range adds one more than its value, cancelling out the `-1`
+    sextet += in_range(char, b'A' as i32, b'Z' as i32) & (char - b'A' as i32 + 1);
+    sextet += in_range(char, b'a' as i32, b'z' as i32) & (char - b'a' as i32 + 26 + 1);
+    sextet += in_range(char, b'0' as i32, b'9' as i32) & (char - b'0' as i32 + 52 + 1);
+    sextet += in_range(char, b'+' as i32, b'+' as i32) & (62 + 1);
+    sextet += in_range(char, b'/' as i32, b'/' as i32) & (63 + 1);
+    sextet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InvalidEncoding;
+
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    // test vectors from https://datatracker.ietf.org/doc/html/rfc4648#section-10
+    #[test]
+    fn rfc_4648() {
+        let vectors: [(&[u8], &str); 7] = [
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"fooba", "Zm9vYmE="),
+            (b"foobar", "Zm9vYmFy"),
+        ];
+        for (data, encoded) in vectors {
+            let mut buf = [0; 8];
+            assert_eq!(super::encode(data, &mut buf), encoded);
+            let mut buf = [0; 6];
+            assert_eq!(
+                super::decode(encoded.as_bytes(), &mut buf).as_deref(),
+                Ok(data)
+            );
+        }
+    }
+
+    #[test]
+    fn every_byte() {
+        let mut data = [0; 256];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let encoded =
+            "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8gISIjJCUmJygpKissLS4vMDEyMzQ1Njc4\
+            OTo7PD0+P0BBQkNERUZHSElKS0xNTk9QUVJTVFVWV1hZWltcXV5fYGFiY2RlZmdoaWprbG1ub3BxcnN0dXZ3\
+            eHl6e3x9fn+AgYKDhIWGh4iJiouMjY6PkJGSk5SVlpeYmZqbnJ2en6ChoqOkpaanqKmqq6ytrq+wsbKztLW2\
+            t7i5uru8vb6/wMHCw8TFxsfIycrLzM3Oz9DR0tPU1dbX2Nna29zd3t/g4eLj5OXm5+jp6uvs7e7v8PHy8/T1\
+            9vf4+fr7/P3+/w==";
+
+        let mut buf = [0; 344];
+        assert_eq!(super::encode(&data, &mut buf), encoded);
+
+        let mut decoded = [0; 256];
+        assert_eq!(
+            super::decode(encoded.as_bytes(), &mut decoded),
+            Ok(&mut data[..])
+        );
+    }
+
+    #[test]
+    fn alphabet() {
+        for (sextet, &char) in ALPHABET.iter().enumerate() {
+            assert_eq!(super::encode_sextet(sextet as u8), char);
+        }
+        for char in 0..=u8::MAX {
+            let expected = ALPHABET
+                .iter()
+                .position(|&c| c == char)
+                .map_or(-1, |sextet| sextet as i32);
+            assert_eq!(super::decode_char(char), expected, "{char}");
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let mut buf = [0xff; 6];
+        // not a multiple of 4
+        assert_eq!(super::decode(b"Zm9", &mut buf), Err(InvalidEncoding));
+        // not in the alphabet
+        assert_eq!(super::decode(b"Zm-v", &mut buf), Err(InvalidEncoding));
+        assert_eq!(super::decode(b"Zm9vYm\nF", &mut buf), Err(InvalidEncoding));
+        // padding in the middle
+        assert_eq!(super::decode(b"Zg==Zm9v", &mut buf), Err(InvalidEncoding));
+        assert_eq!(super::decode(b"Z===", &mut buf), Err(InvalidEncoding));
+        // non-zero padding bits
+        assert_eq!(super::decode(b"Zh==", &mut buf), Err(InvalidEncoding));
+        assert_eq!(super::decode(b"Zm9=", &mut buf), Err(InvalidEncoding));
+        assert_eq!(buf[..3], [0; 3]);
+    }
+}
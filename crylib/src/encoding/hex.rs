@@ -0,0 +1,141 @@
+//! Hexadecimal encoding.
+//!
+//! Encoding produces lowercase digits. Decoding accepts both cases.
+//!
+//! # Examples
+//!
+//! ```
+//! use crylib::encoding::hex;
+//!
+//! let mut buf = [0; 8];
+//! assert_eq!(hex::encode(&[0xde, 0xad, 0xbe, 0xef], &mut buf), "deadbeef");
+//!
+//! let mut bytes = [0; 4];
+//! assert_eq!(hex::decode(b"DEADbeef", &mut bytes), Ok(&mut [0xde, 0xad, 0xbe, 0xef][..]));
+//! ```
+use super::{in_range, InvalidEncoding};
+
+/// Returns the length of the hex encoding of `len` bytes.
+pub const fn encoded_len(len: usize) -> usize {
+    len * 2
+}
+
+/// Returns the length of the data encoded by `encoded`.
+///
+/// Returns [`InvalidEncoding`] if `encoded` has an odd length.
+pub const fn decoded_len(encoded: &[u8]) -> Result<usize, InvalidEncoding> {
+    match encoded.len() % 2 {
+        0 => Ok(encoded.len() / 2),
+        _ => Err(InvalidEncoding),
+    }
+}
+
+/// Encodes `data` into `buf`, returning the encoded part of `buf`.
+///
+/// # Panics
+///
+/// The function will panic if `buf.len()` < [`encoded_len`]`(data.len())`.
+pub fn encode<'a>(data: &[u8], buf: &'a mut [u8]) -> &'a str {
+    let buf = &mut buf[..encoded_len(data.len())];
+    // TODO: use `array_chunks` once stabilized
+    for (pair, byte) in buf.chunks_exact_mut(2).zip(data) {
+        pair[0] = encode_nibble(byte >> 4);
+        pair[1] = encode_nibble(byte & 0xf);
+    }
+    // SAFETY: `encode_nibble` only returns ASCII digits and letters.
+    unsafe { core::str::from_utf8_unchecked(buf) }
+}
+
+/// Decodes `encoded` into `buf`, returning the decoded part of `buf`.
+///
+/// Every character is checked, even after an invalid one is found.
+/// On failure, the decoded part of `buf` is zeroed and [`InvalidEncoding`] is returned.
+///
+/// # Panics
+///
+/// The function will panic if `buf.len()` < [`decoded_len`]`(encoded)`.
+pub fn decode<'a>(encoded: &[u8], buf: &'a mut [u8]) -> Result<&'a mut [u8], InvalidEncoding> {
+    let buf = &mut buf[..decoded_len(encoded)?];
+
+    let mut invalid = 0;
+    // TODO: use `array_chunks` once stabilized
+    for (byte, pair) in buf.iter_mut().zip(encoded.chunks_exact(2)) {
+        let high = decode_char(pair[0]);
+        let low = decode_char(pair[1]);
+        invalid |= high | low;
+        *byte = ((high << 4) | low) as u8;
+    }
+
+    if invalid < 0 {
+        buf.fill(0);
+        return Err(InvalidEncoding);
+    }
+    Ok(buf)
+}
+
+/// Converts a value in `0..16` to its lowercase hex digit.
+#[inline]
+const fn encode_nibble(nibble: u8) -> u8 {
+    let nibble = nibble as i32;
+    // skip from just after '9' to 'a' if `nibble` >= 10
+    (nibble + b'0' as i32 + (((9 - nibble) >> 8) & (b'a' as i32 - b'9' as i32 - 1))) as u8
+}
+
+/// Converts a hex digit of either case to its value, or returns `-1` if `char` isn't one.
+#[inline]
+const fn decode_char(char: u8) -> i32 {
+    let char = char as i32;
+    // setting this bit maps uppercase letters to lowercase ones without touching digits
+    let lower = char | 0x20;
+    let digit = in_range(char, b'0' as i32, b'9' as i32);
+    let letter = in_range(lower, b'a' as i32, b'f' as i32);
+    let valid = digit | letter;
+    (digit & (char - b'0' as i32)) | (letter & (lower - b'a' as i32 + 10)) | !valid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InvalidEncoding;
+
+    #[test]
+    fn round_trip() {
+        let mut data = [0; 256];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let mut encoded = [0; 512];
+        let encoded = super::encode(&data, &mut encoded);
+        for (i, pair) in encoded.as_bytes().chunks_exact(2).enumerate() {
+            let expected = [b"0123456789abcdef"[i >> 4], b"0123456789abcdef"[i & 0xf]];
+            assert_eq!(pair, expected);
+        }
+
+        let mut decoded = [0; 256];
+        assert_eq!(
+            super::decode(encoded.as_bytes(), &mut decoded),
+            Ok(&mut data[..])
+        );
+    }
+
+    #[test]
+    fn decode_char() {
+        for char in 0..=u8::MAX {
+            let expected = match char {
+                b'0'..=b'9' => (char - b'0') as i32,
+                b'a'..=b'f' => (char - b'a' + 10) as i32,
+                b'A'..=b'F' => (char - b'A' + 10) as i32,
+                _ => -1,
+            };
+            assert_eq!(super::decode_char(char), expected, "{char}");
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let mut buf = [0xff; 4];
+        assert_eq!(super::decode(b"abc", &mut buf), Err(InvalidEncoding));
+        assert_eq!(super::decode(b"0g", &mut buf), Err(InvalidEncoding));
+        assert_eq!(super::decode(b"12 4", &mut buf), Err(InvalidEncoding));
+        assert_eq!(buf[..2], [0; 2]);
+    }
+}
@@ -0,0 +1,391 @@
+//! FF1 format-preserving encryption, as defined in [NIST SP 800-38G].
+//!
+//! FF1 encrypts a string of numerals in some radix into another string of numerals of the same
+//! length and radix, so that e.g. a 16-digit card number encrypts to another 16-digit number.
+//! Numerals are represented as `u16`s, each of which must be less than the radix.
+//!
+//! Because this crate does not allocate, numbers are handled as 128-bit integers.
+//! This limits each half of the numeral string to at most 96 bits
+//! (`radix.pow(n.div_ceil(2)) <= 2^96`), which still allows e.g. 56 decimal digits.
+//!
+//! # Examples
+//!
+//! ```
+//! use crylib::aead::gcm::Aes128;
+//! use crylib::ff1::Ff1;
+//!
+//! let key = [
+//!     0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f,
+//!     0x3c,
+//! ];
+//! let cipher = Ff1::<Aes128>::new(key, 10).unwrap();
+//!
+//! let mut digits = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+//! cipher.encrypt(&mut digits, b"").unwrap();
+//! assert_eq!(digits, [2, 4, 3, 3, 4, 7, 7, 4, 8, 4]);
+//!
+//! cipher.decrypt(&mut digits, b"").unwrap();
+//! assert_eq!(digits, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+//! ```
+//!
+//! [NIST SP 800-38G]: https://csrc.nist.gov/pubs/sp/800/38/g/upd1/final
+use crate::aead::gcm::{AesCipher, BLOCK_SIZE};
+
+/// The number of Feistel rounds.
+const NUM_ROUNDS: u8 = 10;
+
+/// The largest supported value of `radix.pow(v)`, where `v` is the length of the longer half.
+const MAX_HALF: u128 = 1 << 96;
+
+/// The smallest allowed domain size, `radix.pow(n)`.
+const MIN_DOMAIN: u128 = 1_000_000;
+
+/// An error that is returned when the radix is not between `2` and `2^16`.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct InvalidRadix;
+
+impl core::fmt::Display for InvalidRadix {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "radix must be between 2 and 65536")
+    }
+}
+
+impl core::error::Error for InvalidRadix {}
+
+/// An error that is returned when a numeral string can't be encrypted or decrypted.
+///
+/// This happens if a numeral is not less than the radix, if the string is too short for FF1's
+/// minimum domain size of one million, or if it is too long for this implementation.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct InvalidInput;
+
+impl core::fmt::Display for InvalidInput {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "numeral string is invalid for this radix")
+    }
+}
+
+impl core::error::Error for InvalidInput {}
+
+/// The FF1 cipher over AES, for a fixed radix.
+pub struct Ff1<C: AesCipher> {
+    cipher: C,
+    radix: u32,
+}
+
+impl<C: AesCipher> Ff1<C> {
+    /// Creates a new cipher for numeral strings in base `radix`.
+    ///
+    /// Returns [`InvalidRadix`] if `radix` is not between `2` and `2^16`.
+    pub fn new(key: C::Key, radix: u32) -> Result<Self, InvalidRadix> {
+        if !(2..=1 << 16).contains(&radix) {
+            return Err(InvalidRadix);
+        }
+        Ok(Self {
+            cipher: C::new(key),
+            radix,
+        })
+    }
+
+    /// Encrypts the numeral string `numerals` in place under `tweak`.
+    ///
+    /// Returns [`InvalidInput`] if `numerals` can't be encrypted, in which case it is unchanged.
+    pub fn encrypt(&self, numerals: &mut [u16], tweak: &[u8]) -> Result<(), InvalidInput> {
+        let layout = self.layout(numerals, tweak)?;
+        let (a, b) = numerals.split_at(layout.u);
+        let (mut a, mut b) = (self.num(a), self.num(b));
+
+        for round in 0..NUM_ROUNDS {
+            let modulus = layout.modulus(round);
+            let y = self.round_function(&layout, tweak, round, b) % modulus;
+            let c = (a + y) % modulus;
+            a = b;
+            b = c;
+        }
+
+        let (a_str, b_str) = numerals.split_at_mut(layout.u);
+        self.str(a, a_str);
+        self.str(b, b_str);
+        Ok(())
+    }
+
+    /// Decrypts the numeral string `numerals` in place under `tweak`.
+    ///
+    /// Returns [`InvalidInput`] if `numerals` can't be decrypted, in which case it is unchanged.
+    pub fn decrypt(&self, numerals: &mut [u16], tweak: &[u8]) -> Result<(), InvalidInput> {
+        let layout = self.layout(numerals, tweak)?;
+        let (a, b) = numerals.split_at(layout.u);
+        let (mut a, mut b) = (self.num(a), self.num(b));
+
+        for round in (0..NUM_ROUNDS).rev() {
+            let modulus = layout.modulus(round);
+            let y = self.round_function(&layout, tweak, round, a) % modulus;
+            let c = (b + modulus - y) % modulus;
+            b = a;
+            a = c;
+        }
+
+        let (a_str, b_str) = numerals.split_at_mut(layout.u);
+        self.str(a, a_str);
+        self.str(b, b_str);
+        Ok(())
+    }
+
+    /// Validates `numerals` and computes the values that stay fixed across rounds.
+    fn layout(&self, numerals: &[u16], tweak: &[u8]) -> Result<Layout, InvalidInput> {
+        if numerals.iter().any(|&numeral| numeral as u32 >= self.radix) {
+            return Err(InvalidInput);
+        }
+        let n = numerals.len();
+        let Ok(n_u32) = u32::try_from(n) else {
+            return Err(InvalidInput);
+        };
+        let Ok(t) = u32::try_from(tweak.len()) else {
+            return Err(InvalidInput);
+        };
+        let u = n / 2;
+        let v = n - u;
+
+        let radix = self.radix as u128;
+        // `v >= u`, so bounding `radix^v` bounds both halves
+        let Some(radix_v) = radix.checked_pow(v as u32).filter(|&pow| pow <= MAX_HALF) else {
+            return Err(InvalidInput);
+        };
+        let radix_u = radix.pow(u as u32);
+        if radix_u.saturating_mul(radix_v) < MIN_DOMAIN {
+            return Err(InvalidInput);
+        }
+
+        // the number of bytes needed to hold a `v`-numeral number: ceil(ceil(v * log2(radix)) / 8)
+        let b = (u128::BITS - (radix_v - 1).leading_zeros()).div_ceil(8) as usize;
+        let d = 4 * b.div_ceil(4) + 4;
+
+        let mut p = [0; BLOCK_SIZE];
+        p[..3].copy_from_slice(&[1, 2, 1]);
+        p[3..6].copy_from_slice(&self.radix.to_be_bytes()[1..]);
+        p[6] = NUM_ROUNDS;
+        p[7] = u as u8;
+        p[8..12].copy_from_slice(&n_u32.to_be_bytes());
+        p[12..].copy_from_slice(&t.to_be_bytes());
+
+        Ok(Layout {
+            u,
+            radix_u,
+            radix_v,
+            b,
+            d,
+            p,
+        })
+    }
+
+    /// The FF1 round function: a CBC-MAC over `P || Q`, truncated to `d` bytes.
+    fn round_function(&self, layout: &Layout, tweak: &[u8], round: u8, half: u128) -> u128 {
+        let mut mac = CbcMac::new(&self.cipher);
+        mac.update(&layout.p);
+
+        mac.update(tweak);
+        // pad `Q` to a whole number of blocks
+        let padding = (BLOCK_SIZE - (tweak.len() + layout.b + 1) % BLOCK_SIZE) % BLOCK_SIZE;
+        for _ in 0..padding {
+            mac.update(&[0]);
+        }
+        mac.update(&[round]);
+        mac.update(&half.to_be_bytes()[size_of::<u128>() - layout.b..]);
+
+        // `d` is at most 16 because `b` is at most 12, so `S` is just the start of `R`
+        let r = mac.finish();
+        let mut s = [0; size_of::<u128>()];
+        s[size_of::<u128>() - layout.d..].copy_from_slice(&r[..layout.d]);
+        u128::from_be_bytes(s)
+    }
+
+    /// Interprets `numerals` as a big-endian number in base `radix`.
+    fn num(&self, numerals: &[u16]) -> u128 {
+        numerals.iter().fold(0, |acc, &numeral| {
+            acc * self.radix as u128 + numeral as u128
+        })
+    }
+
+    /// Writes `value` to `numerals` as a big-endian number in base `radix`.
+    fn str(&self, mut value: u128, numerals: &mut [u16]) {
+        for numeral in numerals.iter_mut().rev() {
+            *numeral = (value % self.radix as u128) as u16;
+            value /= self.radix as u128;
+        }
+    }
+}
+
+/// The parts of the FF1 computation that only depend on the lengths of the input.
+struct Layout {
+    /// The length of the first half.
+    u: usize,
+    radix_u: u128,
+    radix_v: u128,
+    /// The byte length of a half, as used in `Q`.
+    b: usize,
+    /// The byte length of the round function's output.
+    d: usize,
+    /// The fixed first block, `P`.
+    p: [u8; BLOCK_SIZE],
+}
+
+impl Layout {
+    /// The modulus of the half produced in `round`: `radix^u` in even rounds and `radix^v`
+    /// in odd ones.
+    fn modulus(&self, round: u8) -> u128 {
+        if round.is_multiple_of(2) {
+            self.radix_u
+        } else {
+            self.radix_v
+        }
+    }
+}
+
+/// A CBC-MAC with a zero IV, over data that is a whole number of blocks long.
+struct CbcMac<'a, C: AesCipher> {
+    cipher: &'a C,
+    state: [u8; BLOCK_SIZE],
+    pos: usize,
+}
+
+impl<'a, C: AesCipher> CbcMac<'a, C> {
+    fn new(cipher: &'a C) -> Self {
+        Self {
+            cipher,
+            state: [0; BLOCK_SIZE],
+            pos: 0,
+        }
+    }
+
+    fn update(&mut self, msg: &[u8]) {
+        for &byte in msg {
+            self.state[self.pos] ^= byte;
+            self.pos += 1;
+            if self.pos == BLOCK_SIZE {
+                self.cipher.encrypt_inline(&mut self.state);
+                self.pos = 0;
+            }
+        }
+    }
+
+    fn finish(self) -> [u8; BLOCK_SIZE] {
+        debug_assert_eq!(self.pos, 0);
+        self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ff1, InvalidInput, InvalidRadix};
+    use crate::aead::gcm::{Aes128, Aes256};
+
+    const KEY: [u8; 16] = [
+        0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f,
+        0x3c,
+    ];
+
+    fn round_trip<const N: usize>(
+        cipher: &Ff1<impl crate::aead::gcm::AesCipher>,
+        plain_text: [u16; N],
+        tweak: &[u8],
+        cipher_text: [u16; N],
+    ) {
+        let mut numerals = plain_text;
+        cipher.encrypt(&mut numerals, tweak).unwrap();
+        assert_eq!(numerals, cipher_text);
+        cipher.decrypt(&mut numerals, tweak).unwrap();
+        assert_eq!(numerals, plain_text);
+    }
+
+    #[test]
+    fn aes_128() {
+        // test vectors from https://csrc.nist.gov/csrc/media/projects/cryptographic-standards-and-guidelines/documents/examples/ff1samples.pdf
+        let cipher = Ff1::<Aes128>::new(KEY, 10).unwrap();
+        let plain_text = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+        round_trip(&cipher, plain_text, b"", [2, 4, 3, 3, 4, 7, 7, 4, 8, 4]);
+        round_trip(
+            &cipher,
+            plain_text,
+            &[0x39, 0x38, 0x37, 0x36, 0x35, 0x34, 0x33, 0x32, 0x31, 0x30],
+            [6, 1, 2, 4, 2, 0, 0, 7, 7, 3],
+        );
+
+        let cipher = Ff1::<Aes128>::new(KEY, 36).unwrap();
+        round_trip(
+            &cipher,
+            [
+                0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18,
+            ],
+            &[
+                0x37, 0x37, 0x37, 0x37, 0x70, 0x71, 0x72, 0x73, 0x37, 0x37, 0x37,
+            ],
+            [
+                10, 9, 29, 31, 4, 0, 22, 21, 21, 9, 20, 13, 30, 5, 0, 9, 14, 30, 22,
+            ],
+        );
+    }
+
+    #[test]
+    fn aes_256() {
+        let mut key = [0; 32];
+        key[..16].copy_from_slice(&KEY);
+        key[16..].copy_from_slice(&[
+            0xef, 0x43, 0x59, 0xd8, 0xd5, 0x80, 0xaa, 0x4f, 0x7f, 0x03, 0x6d, 0x6f, 0x04, 0xfc,
+            0x6a, 0x94,
+        ]);
+        let cipher = Ff1::<Aes256>::new(key, 10).unwrap();
+        round_trip(
+            &cipher,
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+            b"",
+            [6, 6, 5, 7, 6, 6, 7, 0, 0, 9],
+        );
+    }
+
+    #[test]
+    fn edge_cases() {
+        // the largest radix, an odd length, and a tweak spanning several blocks
+        let cipher = Ff1::<Aes128>::new(KEY, 1 << 16).unwrap();
+        let mut tweak = [0; 40];
+        for (i, byte) in tweak.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        round_trip(
+            &cipher,
+            [1, 65535, 300, 4000, 0],
+            &tweak,
+            [9505, 64187, 8835, 56146, 57408],
+        );
+
+        // the longest supported decimal string
+        let cipher = Ff1::<Aes128>::new(KEY, 10).unwrap();
+        let mut plain_text = [1; 56];
+        plain_text[0] = 4;
+        plain_text[55] = 9;
+        let cipher_text = [
+            1, 5, 7, 3, 1, 3, 3, 0, 1, 6, 6, 7, 8, 7, 8, 2, 6, 3, 6, 3, 9, 6, 3, 9, 0, 2, 6, 4, 4,
+            2, 3, 0, 2, 8, 1, 3, 6, 7, 6, 2, 5, 3, 3, 8, 4, 4, 6, 8, 7, 6, 2, 0, 0, 2, 1, 7,
+        ];
+        round_trip(&cipher, plain_text, b"", cipher_text);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(matches!(Ff1::<Aes128>::new(KEY, 1), Err(InvalidRadix)));
+        assert!(matches!(
+            Ff1::<Aes128>::new(KEY, (1 << 16) + 1),
+            Err(InvalidRadix)
+        ));
+
+        let cipher = Ff1::<Aes128>::new(KEY, 10).unwrap();
+        // numeral out of range
+        assert_eq!(
+            cipher.encrypt(&mut [0, 1, 2, 3, 4, 10], b""),
+            Err(InvalidInput)
+        );
+        // domain smaller than one million
+        assert_eq!(cipher.encrypt(&mut [0; 5], b""), Err(InvalidInput));
+        // halves too large for 128-bit arithmetic
+        assert_eq!(cipher.decrypt(&mut [0; 58], b""), Err(InvalidInput));
+    }
+}
@@ -1,6 +1,8 @@
 //! Finite field arithmetic.
+mod fe25519;
 mod field_element;
 
+pub use fe25519::Fe25519;
 pub use field_element::FieldElement;
 
 use crate::big_int::UBigInt;
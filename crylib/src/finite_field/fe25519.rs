@@ -0,0 +1,258 @@
+//! Arithmetic modulo `2^255 - 19`, the prime that Curve25519 is defined over.
+//!
+//! [`FieldElement`](super::FieldElement) works for any prime, but reduces every product with a
+//! long division. This prime is close enough to a power of two that reducing is just multiplying
+//! the high half by 19, which is much faster.
+//!
+//! Elements are held in radix `2^51`: five 64-bit limbs of (about) 51 bits each, so the products
+//! of two limbs fit in a `u128` with room to add them up, and carries only need to be propagated
+//! once per operation.
+//!
+//! With the `fiat-crypto` feature, the limbs are handled by code generated by
+//! [fiat-crypto](https://github.com/mit-plv/fiat-crypto), which is proven correct, instead of by
+//! the handwritten implementation.
+#[cfg(feature = "fiat-crypto")]
+mod fiat;
+#[cfg(not(feature = "fiat-crypto"))]
+mod limbs;
+
+#[cfg(feature = "fiat-crypto")]
+use fiat as backend;
+#[cfg(not(feature = "fiat-crypto"))]
+use limbs as backend;
+
+/// An element of the field of integers modulo `2^255 - 19`.
+///
+/// Limbs aren't always fully reduced, so the same element can have different representations.
+/// Comparisons go through [`Fe25519::to_bytes`], which is canonical.
+///
+/// # Constant-timedness
+/// Every operation is constant-time.
+#[derive(Clone, Copy, Debug)]
+pub struct Fe25519([u64; 5]);
+
+impl Fe25519 {
+    pub const ZERO: Self = Self([0; 5]);
+
+    pub const ONE: Self = Self([1, 0, 0, 0, 0]);
+
+    /// Decodes a little-endian integer, as X25519 encodes its coordinates.
+    ///
+    /// The top bit is ignored, and values of `2^255 - 19` or more are reduced
+    /// ([RFC 7748 section 5](https://datatracker.ietf.org/doc/html/rfc7748#section-5)).
+    pub fn from_bytes(bytes: &[u8; 32]) -> Self {
+        Self(backend::from_bytes(bytes))
+    }
+
+    /// Encodes `self` as a little-endian integer less than `2^255 - 19`.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        backend::to_bytes(&self.0)
+    }
+
+    /// Returns whether `self` is zero.
+    pub fn is_zero(&self) -> bool {
+        self.to_bytes().iter().fold(0, |acc, byte| acc | byte) == 0
+    }
+
+    /// Returns `self + rhs`.
+    pub fn add(&self, rhs: &Self) -> Self {
+        Self(backend::add(&self.0, &rhs.0))
+    }
+
+    /// Returns `self - rhs`.
+    pub fn sub(&self, rhs: &Self) -> Self {
+        Self(backend::sub(&self.0, &rhs.0))
+    }
+
+    /// Returns `-self`.
+    pub fn neg(&self) -> Self {
+        Self::ZERO.sub(self)
+    }
+
+    /// Returns `self * rhs`.
+    pub fn mul(&self, rhs: &Self) -> Self {
+        Self(backend::mul(&self.0, &rhs.0))
+    }
+
+    /// Returns `self * self`.
+    pub fn sqr(&self) -> Self {
+        Self(backend::sqr(&self.0))
+    }
+
+    /// Returns `self` squared `k` times.
+    fn pow2k(&self, k: u32) -> Self {
+        (0..k).fold(*self, |x, _| x.sqr())
+    }
+
+    /// Returns `self * small`.
+    ///
+    /// This is faster than [`Fe25519::mul`] for constants like the `(A - 2) / 4 = 121665` of the
+    /// X25519 ladder.
+    pub fn mul_small(&self, small: u32) -> Self {
+        Self(backend::mul_small(&self.0, small))
+    }
+
+    /// Returns the multiplicative inverse of `self`, or zero if `self` is zero.
+    ///
+    /// This raises `self` to the power `2^255 - 21` with a fixed chain of squarings and
+    /// multiplications, so unlike [`FieldElement::inverse`](super::FieldElement::inverse), it takes
+    /// the same time for every input.
+    pub fn inverse(&self) -> Self {
+        // the exponents are in the comments
+        let x = self;
+        let t0 = x.sqr(); // 2
+        let t1 = t0.pow2k(2); // 8
+        let t2 = x.mul(&t1); // 9
+        let t3 = t0.mul(&t2); // 11
+        let t4 = t3.sqr(); // 22
+        let t5 = t2.mul(&t4); // 2^5 - 1
+        let t7 = t5.pow2k(5).mul(&t5); // 2^10 - 1
+        let t9 = t7.pow2k(10).mul(&t7); // 2^20 - 1
+        let t11 = t9.pow2k(20).mul(&t9); // 2^40 - 1
+        let t13 = t11.pow2k(10).mul(&t7); // 2^50 - 1
+        let t15 = t13.pow2k(50).mul(&t13); // 2^100 - 1
+        let t17 = t15.pow2k(100).mul(&t15); // 2^200 - 1
+        let t19 = t17.pow2k(50).mul(&t13); // 2^250 - 1
+        t19.pow2k(5).mul(&t3) // 2^255 - 32 + 11
+    }
+
+    /// Swaps `self` and `other` if `choice` is `true`, otherwise leaves both unchanged.
+    pub const fn conditional_swap(&mut self, other: &mut Self, choice: bool) {
+        let mask = (choice as u64).wrapping_neg();
+        let mut i = 0;
+        while i < 5 {
+            let diff = (self.0[i] ^ other.0[i]) & mask;
+            self.0[i] ^= diff;
+            other.0[i] ^= diff;
+            i += 1;
+        }
+    }
+}
+
+impl PartialEq for Fe25519 {
+    fn eq(&self, other: &Self) -> bool {
+        crate::ct::ct_eq(&self.to_bytes(), &other.to_bytes())
+    }
+}
+
+impl Eq for Fe25519 {}
+
+#[cfg(test)]
+mod tests {
+    use super::Fe25519;
+    use crate::big_int::UBigInt;
+    use crate::finite_field::{FieldElement, FiniteField};
+    use proptest::prelude::*;
+
+    /// The same field, through the generic implementation.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    struct P25519;
+    // SAFETY: `2^255 - 19` is prime.
+    unsafe impl FiniteField for P25519 {
+        const MODULUS: UBigInt<4> = UBigInt([
+            0xffffffffffffffed,
+            0xffffffffffffffff,
+            0xffffffffffffffff,
+            0x7fffffffffffffff,
+        ]);
+    }
+
+    fn generic(x: &Fe25519) -> FieldElement<P25519> {
+        let mut bytes = x.to_bytes();
+        bytes.reverse();
+        FieldElement::try_new(UBigInt::<4>::from_be_bytes(bytes)).unwrap()
+    }
+
+    fn element() -> impl Strategy<Value = Fe25519> {
+        prop_oneof![
+            any::<[u8; 32]>().prop_map(|bytes| Fe25519::from_bytes(&bytes)),
+            // values near the modulus are the most likely to expose reduction bugs
+            (1u8..40).prop_map(|n| {
+                let mut bytes = [0xff; 32];
+                bytes[0] = 0xed - n + 1;
+                bytes[31] = 0x7f;
+                Fe25519::from_bytes(&bytes)
+            }),
+            (0u8..16).prop_map(|n| {
+                let mut bytes = [0; 32];
+                bytes[0] = n;
+                Fe25519::from_bytes(&bytes)
+            }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn add_sub(x in element(), y in element()) {
+            prop_assert_eq!(generic(&x.add(&y)), generic(&x).add(&generic(&y)));
+            prop_assert_eq!(generic(&x.sub(&y)), generic(&x).sub(&generic(&y)));
+            prop_assert_eq!(generic(&x.neg()), generic(&x).neg());
+        }
+
+        #[test]
+        fn mul(x in element(), y in element(), small in any::<u32>()) {
+            prop_assert_eq!(generic(&x.mul(&y)), generic(&x).mul(&generic(&y)));
+            prop_assert_eq!(generic(&x.sqr()), generic(&x).sqr());
+            prop_assert_eq!(
+                generic(&x.mul_small(small)),
+                generic(&x).mul_digit(small as u64)
+            );
+        }
+
+        #[test]
+        fn inverse(x in element()) {
+            prop_assume!(!x.is_zero());
+            prop_assert_eq!(generic(&x.inverse()), generic(&x).inverse());
+            prop_assert_eq!(x.mul(&x.inverse()), Fe25519::ONE);
+        }
+
+        #[test]
+        fn chained(x in element(), y in element()) {
+            // limbs that were never carried by `to_bytes` are fed back in
+            let mut acc = x;
+            let mut reference = generic(&x);
+            for _ in 0..8 {
+                acc = acc.add(&y).mul(&acc).sub(&x);
+                reference = reference.add(&generic(&y)).mul(&reference).sub(&generic(&x));
+            }
+            prop_assert_eq!(generic(&acc), reference);
+        }
+
+        #[test]
+        fn bytes(bytes in any::<[u8; 32]>()) {
+            let mut masked = bytes;
+            masked[31] &= 0x7f;
+            let mut be = masked;
+            be.reverse();
+            let expected = FieldElement::<P25519>::new(UBigInt::<4>::from_be_bytes(be));
+            prop_assert_eq!(generic(&Fe25519::from_bytes(&bytes)), expected);
+        }
+    }
+
+    #[test]
+    fn canonical_bytes() {
+        // the modulus itself, and the modulus plus one, are reduced
+        let mut p = [0xff; 32];
+        p[0] = 0xed;
+        p[31] = 0x7f;
+        assert!(Fe25519::from_bytes(&p).is_zero());
+        p[0] = 0xee;
+        assert_eq!(Fe25519::from_bytes(&p), Fe25519::ONE);
+        assert_eq!(Fe25519::ONE.to_bytes()[0], 1);
+
+        // `-1` encodes as the modulus minus one
+        p[0] = 0xec;
+        assert_eq!(Fe25519::ONE.neg().to_bytes(), p);
+        assert!(Fe25519::ZERO.inverse().is_zero());
+    }
+
+    #[test]
+    fn conditional_swap() {
+        let mut a = Fe25519::ONE;
+        let mut b = Fe25519::ZERO;
+        a.conditional_swap(&mut b, false);
+        assert_eq!((a, b), (Fe25519::ONE, Fe25519::ZERO));
+        a.conditional_swap(&mut b, true);
+        assert_eq!((a, b), (Fe25519::ZERO, Fe25519::ONE));
+    }
+}
@@ -0,0 +1,73 @@
+//! The implementation of [`Fe25519`](super::Fe25519) generated by
+//! [fiat-crypto](https://github.com/mit-plv/fiat-crypto), which is proven correct.
+//!
+//! Limbs are always within fiat-crypto's tight bounds.
+use fiat_crypto::curve25519_64::{
+    fiat_25519_add, fiat_25519_carry, fiat_25519_carry_mul, fiat_25519_carry_square,
+    fiat_25519_from_bytes, fiat_25519_loose_field_element, fiat_25519_relax, fiat_25519_sub,
+    fiat_25519_tight_field_element, fiat_25519_to_bytes,
+};
+
+pub(super) fn from_bytes(bytes: &[u8; 32]) -> [u64; 5] {
+    // fiat-crypto requires the top bit to be clear
+    let mut bytes = *bytes;
+    bytes[31] &= 0x7f;
+    let mut out = fiat_25519_tight_field_element([0; 5]);
+    fiat_25519_from_bytes(&mut out, &bytes);
+    out.0
+}
+
+pub(super) fn to_bytes(limbs: &[u64; 5]) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    fiat_25519_to_bytes(&mut bytes, &fiat_25519_tight_field_element(*limbs));
+    bytes
+}
+
+fn relax(limbs: &[u64; 5]) -> fiat_25519_loose_field_element {
+    let mut out = fiat_25519_loose_field_element([0; 5]);
+    fiat_25519_relax(&mut out, &fiat_25519_tight_field_element(*limbs));
+    out
+}
+
+fn carry(loose: &fiat_25519_loose_field_element) -> [u64; 5] {
+    let mut out = fiat_25519_tight_field_element([0; 5]);
+    fiat_25519_carry(&mut out, loose);
+    out.0
+}
+
+pub(super) fn add(lhs: &[u64; 5], rhs: &[u64; 5]) -> [u64; 5] {
+    let mut sum = fiat_25519_loose_field_element([0; 5]);
+    fiat_25519_add(
+        &mut sum,
+        &fiat_25519_tight_field_element(*lhs),
+        &fiat_25519_tight_field_element(*rhs),
+    );
+    carry(&sum)
+}
+
+pub(super) fn sub(lhs: &[u64; 5], rhs: &[u64; 5]) -> [u64; 5] {
+    let mut diff = fiat_25519_loose_field_element([0; 5]);
+    fiat_25519_sub(
+        &mut diff,
+        &fiat_25519_tight_field_element(*lhs),
+        &fiat_25519_tight_field_element(*rhs),
+    );
+    carry(&diff)
+}
+
+pub(super) fn mul(lhs: &[u64; 5], rhs: &[u64; 5]) -> [u64; 5] {
+    let mut out = fiat_25519_tight_field_element([0; 5]);
+    fiat_25519_carry_mul(&mut out, &relax(lhs), &relax(rhs));
+    out.0
+}
+
+pub(super) fn sqr(limbs: &[u64; 5]) -> [u64; 5] {
+    let mut out = fiat_25519_tight_field_element([0; 5]);
+    fiat_25519_carry_square(&mut out, &relax(limbs));
+    out.0
+}
+
+pub(super) fn mul_small(limbs: &[u64; 5], small: u32) -> [u64; 5] {
+    // fiat-crypto only has a multiplication by 121666, so this is a full multiplication
+    mul(limbs, &[small.into(), 0, 0, 0, 0])
+}
@@ -0,0 +1,175 @@
+//! The handwritten implementation of [`Fe25519`](super::Fe25519).
+//!
+//! Limbs aren't always fully reduced: after each operation, every limb is at most 51 bits, except
+//! that the first two can be slightly larger.
+
+/// The bits of a limb that are kept after carrying.
+const LOW_51_BITS: u64 = (1 << 51) - 1;
+
+/// `16 * (2^255 - 19)`, which is added before subtracting so that no limb underflows.
+const SIXTEEN_P: [u64; 5] = [
+    16 * (LOW_51_BITS - 18),
+    16 * LOW_51_BITS,
+    16 * LOW_51_BITS,
+    16 * LOW_51_BITS,
+    16 * LOW_51_BITS,
+];
+
+pub(super) const fn from_bytes(bytes: &[u8; 32]) -> [u64; 5] {
+    const fn load(bytes: &[u8; 32], start: usize) -> u64 {
+        let mut word = 0;
+        let mut i = 0;
+        while i < 8 {
+            word |= (bytes[start + i] as u64) << (8 * i);
+            i += 1;
+        }
+        word
+    }
+
+    [
+        load(bytes, 0) & LOW_51_BITS,
+        (load(bytes, 6) >> 3) & LOW_51_BITS,
+        (load(bytes, 12) >> 6) & LOW_51_BITS,
+        (load(bytes, 19) >> 1) & LOW_51_BITS,
+        (load(bytes, 24) >> 12) & LOW_51_BITS,
+    ]
+}
+
+pub(super) const fn to_bytes(limbs: &[u64; 5]) -> [u8; 32] {
+    let mut limbs = carry(*limbs);
+
+    // `limbs` is now less than `2 * (2^255 - 19)`, so subtracting the modulus at most once
+    // reduces it. Adding 19 overflows 255 bits exactly when it is at least the modulus.
+    let mut q = (limbs[0] + 19) >> 51;
+    q = (limbs[1] + q) >> 51;
+    q = (limbs[2] + q) >> 51;
+    q = (limbs[3] + q) >> 51;
+    q = (limbs[4] + q) >> 51;
+
+    // subtract the modulus `q` times, by adding `19 * q` and dropping bit 255
+    limbs[0] += 19 * q;
+    limbs[1] += limbs[0] >> 51;
+    limbs[0] &= LOW_51_BITS;
+    limbs[2] += limbs[1] >> 51;
+    limbs[1] &= LOW_51_BITS;
+    limbs[3] += limbs[2] >> 51;
+    limbs[2] &= LOW_51_BITS;
+    limbs[4] += limbs[3] >> 51;
+    limbs[3] &= LOW_51_BITS;
+    limbs[4] &= LOW_51_BITS;
+
+    let mut bytes = [0; 32];
+    let mut bit = 0;
+    let mut i = 0;
+    while i < 5 {
+        // write the 51 bits of the limb, starting at `bit`
+        let mut written = 0;
+        while written < 51 {
+            let pos = bit + written;
+            let chunk = (limbs[i] >> written) as u8;
+            bytes[pos / 8] |= chunk << (pos % 8);
+            written += 8 - pos % 8;
+        }
+        bit += 51;
+        i += 1;
+    }
+    bytes
+}
+
+/// Propagates the carries of `limbs`, so that each limb is at most 51 bits, except that the
+/// first can be slightly larger.
+const fn carry(mut limbs: [u64; 5]) -> [u64; 5] {
+    let c0 = limbs[0] >> 51;
+    let c1 = limbs[1] >> 51;
+    let c2 = limbs[2] >> 51;
+    let c3 = limbs[3] >> 51;
+    let c4 = limbs[4] >> 51;
+    limbs[0] &= LOW_51_BITS;
+    limbs[1] &= LOW_51_BITS;
+    limbs[2] &= LOW_51_BITS;
+    limbs[3] &= LOW_51_BITS;
+    limbs[4] &= LOW_51_BITS;
+    // `2^255` is `19` modulo the prime
+    limbs[0] += c4 * 19;
+    limbs[1] += c0;
+    limbs[2] += c1;
+    limbs[3] += c2;
+    limbs[4] += c3;
+    limbs
+}
+
+pub(super) const fn add(lhs: &[u64; 5], rhs: &[u64; 5]) -> [u64; 5] {
+    let mut limbs = *lhs;
+    let mut i = 0;
+    while i < 5 {
+        limbs[i] += rhs[i];
+        i += 1;
+    }
+    carry(limbs)
+}
+
+pub(super) const fn sub(lhs: &[u64; 5], rhs: &[u64; 5]) -> [u64; 5] {
+    let mut limbs = *lhs;
+    let mut i = 0;
+    while i < 5 {
+        limbs[i] = limbs[i] + SIXTEEN_P[i] - rhs[i];
+        i += 1;
+    }
+    carry(limbs)
+}
+
+pub(super) const fn mul(lhs: &[u64; 5], rhs: &[u64; 5]) -> [u64; 5] {
+    const fn m(a: u64, b: u64) -> u128 {
+        a as u128 * b as u128
+    }
+
+    let [a0, a1, a2, a3, a4] = *lhs;
+    let [b0, b1, b2, b3, b4] = *rhs;
+    // products that overflow 255 bits wrap around times 19
+    let (b1_19, b2_19, b3_19, b4_19) = (b1 * 19, b2 * 19, b3 * 19, b4 * 19);
+
+    let c0 = m(a0, b0) + m(a4, b1_19) + m(a3, b2_19) + m(a2, b3_19) + m(a1, b4_19);
+    let c1 = m(a1, b0) + m(a0, b1) + m(a4, b2_19) + m(a3, b3_19) + m(a2, b4_19);
+    let c2 = m(a2, b0) + m(a1, b1) + m(a0, b2) + m(a4, b3_19) + m(a3, b4_19);
+    let c3 = m(a3, b0) + m(a2, b1) + m(a1, b2) + m(a0, b3) + m(a4, b4_19);
+    let c4 = m(a4, b0) + m(a3, b1) + m(a2, b2) + m(a1, b3) + m(a0, b4);
+
+    reduce_wide([c0, c1, c2, c3, c4])
+}
+
+pub(super) const fn sqr(limbs: &[u64; 5]) -> [u64; 5] {
+    mul(limbs, limbs)
+}
+
+pub(super) const fn mul_small(limbs: &[u64; 5], small: u32) -> [u64; 5] {
+    let mut c = [0; 5];
+    let mut i = 0;
+    while i < 5 {
+        c[i] = limbs[i] as u128 * small as u128;
+        i += 1;
+    }
+    reduce_wide(c)
+}
+
+/// Reduces the 128-bit limbs of a product.
+const fn reduce_wide(mut c: [u128; 5]) -> [u64; 5] {
+    c[1] += c[0] >> 51;
+    c[2] += c[1] >> 51;
+    c[3] += c[2] >> 51;
+    c[4] += c[3] >> 51;
+    let carry = (c[4] >> 51) as u64;
+
+    let mut limbs = [
+        c[0] as u64 & LOW_51_BITS,
+        c[1] as u64 & LOW_51_BITS,
+        c[2] as u64 & LOW_51_BITS,
+        c[3] as u64 & LOW_51_BITS,
+        c[4] as u64 & LOW_51_BITS,
+    ];
+    // `carry` is less than `2^64 / 19`, since the product of two elements is less than
+    // `2^115`
+    limbs[0] += carry * 19;
+    limbs[1] += limbs[0] >> 51;
+    limbs[0] &= LOW_51_BITS;
+    limbs
+}
@@ -6,6 +6,9 @@ use super::FiniteField;
 /// An element of the finite field `F`.
 ///
 /// All operations are performed modulo [`F::MODULUS`](super::FiniteField::MODULUS).
+///
+/// Elements are always stored fully reduced, so each element has exactly one representation and
+/// one encoding (see [`FieldElement::to_bytes`]).
 #[derive(Eq, PartialOrd, Ord, PartialEq, Clone, Copy)]
 #[repr(transparent)]
 // TODO: use const generics instead of a type once custom const generics types are stabilized
@@ -39,7 +42,7 @@ impl<F: FiniteField> FieldElement<F> {
     /// Because it always performs a division operation, this function is much slower than a simple
     /// type conversion. If higher performance, at the cost of falibility, is necessary, use
     /// [`Self::try_new()`] or its unsafe counterpart, [`Self::new_unchecked()`]
-    pub fn new(value: UBigInt<4>) -> Self {
+    pub const fn new(value: UBigInt<4>) -> Self {
         Self(value.div(&F::MODULUS).1, PhantomData)
     }
 
@@ -58,19 +61,37 @@ impl<F: FiniteField> FieldElement<F> {
     /// is greater than or equal to [`F::MODULUS`](super::FiniteField::MODULUS).
     ///
     /// This is the safe version of [`Self::new_unchecked()`]
-    pub fn try_new(int: UBigInt<4>) -> Result<Self, InputTooLargeError> {
-        if int >= F::MODULUS {
+    pub const fn try_new(int: UBigInt<4>) -> Result<Self, InputTooLargeError> {
+        // `int` is less than the modulus exactly when subtracting the modulus underflows
+        if !int.overflowing_sub(&F::MODULUS).1 {
             return Err(InputTooLargeError);
         };
         // SAFETY: we already checked to guarantee that `int` is less than `F::MODULUS`.
         Ok(unsafe { Self::new_unchecked(int) })
     }
 
-    pub fn inner(&self) -> &UBigInt<4> {
-        self
+    /// Decodes a fixed-length big-endian integer, returning an [`Err`] if it is greater than or
+    /// equal to [`F::MODULUS`](super::FiniteField::MODULUS).
+    ///
+    /// Unlike [`Self::new()`], this doesn't reduce its input, so every element has exactly one
+    /// accepted encoding. Accepting `x + MODULUS` as well as `x` would let an attacker alter an
+    /// encoded signature or key share without changing its meaning.
+    pub fn from_bytes(bytes: &[u8; 32]) -> Result<Self, InputTooLargeError> {
+        Self::try_new(UBigInt::<4>::from_be_bytes(*bytes))
     }
 
-    pub fn into_inner(self) -> UBigInt<4> {
+    /// Encodes `self` as a fixed-length big-endian integer.
+    ///
+    /// This is the inverse of [`Self::from_bytes()`].
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_be_bytes()
+    }
+
+    pub const fn inner(&self) -> &UBigInt<4> {
+        &self.0
+    }
+
+    pub const fn into_inner(self) -> UBigInt<4> {
         self.0
     }
 
@@ -83,7 +104,8 @@ impl<F: FiniteField> FieldElement<F> {
     ///
     /// In release mode, `FieldElement::ZERO.inverse()` returns `FieldElement::ZERO`.
     /// # Constant-timedness
-    /// TODO: document constant-timedness
+    /// This isn't constant-time: the extended Euclidean algorithm takes as many steps as `self`
+    /// needs. Use [`Self::inverse_const_time`] for secret values.
     pub fn inverse(&self) -> Self {
         debug_assert_ne!(self, &Self::ZERO);
         let mut t = BigInt::ZERO;
@@ -103,15 +125,28 @@ impl<F: FiniteField> FieldElement<F> {
         }
         debug_assert!(t.is_positive());
         debug_assert!(t.digits < F::MODULUS);
-        // TODO: is it better to use safe or unsafe version?
+        // SAFETY: the Bézout coefficient is less than the modulus in absolute value, and it was
+        // made positive above.
         unsafe { Self::new_unchecked(t.digits) }
     }
 
+    /// Returns the modular multiplicative inverse of `self`, or zero if `self` is zero.
+    ///
+    /// This raises `self` to the power `MODULUS - 2`, which is its inverse by Fermat's little
+    /// theorem, so the modulus has to be prime. It is much slower than [`Self::inverse`], but the
+    /// exponent is public, so it is meant for secret values.
+    ///
+    /// # Constant-timedness
+    /// This is a constant-time operation.
+    pub fn inverse_const_time(&self) -> Self {
+        self.pow(&F::MODULUS.sub(&UBigInt::from(2)))
+    }
+
     /// Returns the number of digits in `self`, not counting leading zeros
     ///
     /// # Constant-timedness
     /// This function is constant-time.
-    pub fn count_digits(&self) -> usize {
+    pub const fn count_digits(&self) -> usize {
         self.0.count_digits()
     }
 
@@ -119,27 +154,29 @@ impl<F: FiniteField> FieldElement<F> {
     ///
     /// # Constant-timedness
     /// This is a constant-time operation.
-    pub fn add(&self, rhs: &Self) -> Self {
+    pub const fn add(&self, rhs: &Self) -> Self {
         let mut sum;
         let mut mask;
         (sum, mask) = self.0.overflowing_add(&rhs.0);
         mask ^= sum.overflowing_sub_assign(&F::MODULUS);
         sum.add_assign(&F::MODULUS.and_bool(mask));
+        // SAFETY: both operands are less than the modulus, so subtracting it at most once reduces
+        // the sum, and it was only added back if subtracting underflowed.
         unsafe { Self::new_unchecked(sum) }
     }
 
-    pub fn add_assign(&mut self, rhs: &Self) {
+    pub const fn add_assign(&mut self, rhs: &Self) {
         let mut mask = self.0.overflowing_add_assign(&rhs.0);
         mask ^= self.0.overflowing_sub_assign(&F::MODULUS);
         self.0.add_assign(&F::MODULUS.and_bool(mask));
     }
 
-    pub fn double(&self) -> Self {
+    pub const fn double(&self) -> Self {
         // TODO: can this be more efficient?
         self.add(self)
     }
 
-    pub fn double_assign(&mut self) {
+    pub const fn double_assign(&mut self) {
         // TODO: can this be more efficient?
         *self = self.add(self);
     }
@@ -148,7 +185,7 @@ impl<F: FiniteField> FieldElement<F> {
     ///
     /// # Constant-timedness
     /// This is a constant-time operation.
-    pub fn sub(&self, rhs: &Self) -> Self {
+    pub const fn sub(&self, rhs: &Self) -> Self {
         let (difference, mask) = self.0.overflowing_sub(&rhs.0);
         // SAFETY: we we guarantee that underflow doesn't occur by adding the modulus back if it
         // does.
@@ -159,7 +196,7 @@ impl<F: FiniteField> FieldElement<F> {
     ///
     /// # Constant-timedness
     /// This is a constant-time operation.
-    pub fn sub_assign(&mut self, rhs: &Self) {
+    pub const fn sub_assign(&mut self, rhs: &Self) {
         let mask = self.0.overflowing_sub_assign(&rhs.0);
         // make sure self < MODULUS
         self.0.add_assign(&(F::MODULUS.and_bool(mask)));
@@ -169,7 +206,7 @@ impl<F: FiniteField> FieldElement<F> {
     ///
     /// # Constant-timedness
     /// TODO: document constant-timedness
-    pub fn mul(&self, rhs: &Self) -> Self {
+    pub const fn mul(&self, rhs: &Self) -> Self {
         // TODO: use barret reduction instead of division.
         let product = self
             .0
@@ -177,26 +214,29 @@ impl<F: FiniteField> FieldElement<F> {
             .div(&F::MODULUS.resize())
             .1
             .resize();
-        //debug_assert!(product < F::MODULUS)
+        // SAFETY: the product is the remainder of a division by the modulus.
         unsafe { Self::new_unchecked(product) }
     }
 
     /// Sets `self` to `self * rhs` modulo [`F::MODULUS`](super::FiniteField::MODULUS).
-    pub fn mul_assign(&mut self, rhs: &Self) {
+    pub const fn mul_assign(&mut self, rhs: &Self) {
         *self = self.mul(rhs);
     }
 
-    pub fn mul_digit_assign(&mut self, digit: u64) {
+    pub const fn mul_digit_assign(&mut self, digit: u64) {
         *self = self.mul_digit(digit)
     }
 
-    pub fn mul_digit(&self, digit: u64) -> Self {
+    pub const fn mul_digit(&self, digit: u64) -> Self {
         let mut carry = 0;
         let mut buf = UBigInt::<5>::ZERO;
-        for i in 0..self.0.len() {
+        let mut i = 0;
+        while i < self.0.len() {
             (buf.0[i], carry) = crate::big_int::carry_mul(self.0 .0[i], digit, carry);
+            i += 1;
         }
         buf.0[buf.len() - 1] = carry;
+        // SAFETY: the product is the remainder of a division by the modulus.
         unsafe { Self::new_unchecked(buf.div(&F::MODULUS.resize()).1.resize()) }
     }
 
@@ -209,32 +249,121 @@ impl<F: FiniteField> FieldElement<F> {
     }
 
     /// Returns the square of `self` modulo [`F::MODULUS`](super::FiniteField::MODULUS).
-    pub fn sqr(&self) -> Self {
+    pub const fn sqr(&self) -> Self {
         self.mul(self)
     }
 
     /// Squares `self` module [`F::MODULUS`](super::FiniteField::MODULUS) and stores the result of `self`.
-    pub fn sqr_assign(&mut self) {
+    pub const fn sqr_assign(&mut self) {
         *self = self.sqr();
     }
 
+    /// Returns `self` raised to the power `exp` modulo [`F::MODULUS`](super::FiniteField::MODULUS).
+    ///
+    /// # Constant-timedness
+    /// This is constant-time with respect to `self`, but not `exp`.
+    pub fn pow(&self, exp: &UBigInt<4>) -> Self {
+        let mut result = Self::ONE;
+        for bit in (0..exp.bit_len()).rev() {
+            result.sqr_assign();
+            if exp.get_bit(bit) {
+                result.mul_assign(self);
+            }
+        }
+        result
+    }
+
+    /// Returns a square root of `self` modulo [`F::MODULUS`](super::FiniteField::MODULUS), or
+    /// [`None`] if `self` isn't a square.
+    ///
+    /// The other square root is the [negation](Self::neg) of the one that is returned.
+    ///
+    /// This uses the Tonelli-Shanks algorithm, which, for moduli that are 3 modulo 4 (like the one
+    /// of [`Secp256r1`](crate::ec::Secp256r1)), is a single exponentiation.
+    ///
+    /// # Constant-timedness
+    /// This isn't constant-time.
+    pub fn sqrt(&self) -> Option<Self> {
+        // write `MODULUS - 1` as `q * 2^s`, with `q` odd
+        let p_minus_one = F::MODULUS.sub(&UBigInt::ONE);
+        let mut s = 0;
+        while !p_minus_one.get_bit(s) {
+            s += 1;
+        }
+        let q = p_minus_one.shift_right(s as u64);
+
+        // `r^2 = self * t`, and `t` is a `2^(s - 1)`th root of unity exactly when `self` is a
+        // square, so the loop below makes `t` 1 while keeping that relation
+        let mut r = self.pow(&q.add(&UBigInt::ONE).shift_right(1));
+        let mut t = self.pow(&q);
+        if t == Self::ONE || *self == Self::ZERO {
+            return Some(r);
+        }
+        if s == 1 {
+            // `t` is `self^((MODULUS - 1) / 2)`, which is -1 for non-squares
+            return None;
+        }
+
+        // a non-square, found by Euler's criterion
+        let half = p_minus_one.shift_right(1);
+        let minus_one = Self::ONE.neg();
+        let mut z = Self::new(UBigInt::from(2));
+        while z.pow(&half) != minus_one {
+            z.add_assign(&Self::ONE);
+        }
+        let mut c = z.pow(&q);
+        let mut m = s;
+
+        while t != Self::ONE {
+            // the least `i` with `t^(2^i) = 1`
+            let mut i = 0;
+            let mut t_pow = t;
+            while t_pow != Self::ONE {
+                t_pow.sqr_assign();
+                i += 1;
+                if i == m {
+                    return None;
+                }
+            }
+            let mut b = c;
+            for _ in 0..m - i - 1 {
+                b.sqr_assign();
+            }
+            m = i;
+            c = b.sqr();
+            t.mul_assign(&c);
+            r.mul_assign(&b);
+        }
+        Some(r)
+    }
+
+    /// Swaps `self` and `other` if `choice` is `true`, otherwise leaves both unchanged.
+    ///
+    /// # Constant-timedness
+    /// This is a constant-time operation.
+    pub const fn conditional_swap(&mut self, other: &mut Self, choice: bool) {
+        self.0.conditional_swap(&mut other.0, choice);
+    }
+
     /// Returns the modular additive inverse of `self`.
     ///
     /// The returned value has the property that, when added to `self`, the sum is
     /// [`FieldElement::ZERO`].
-    pub fn neg(&self) -> Self {
-        unsafe {
-            let mut neg = self.neg_unchecked();
-            neg.0.and_bool_assign(self != &Self::ZERO);
-            neg
-        }
+    pub const fn neg(&self) -> Self {
+        // the modulus itself isn't in the field, so the negation of zero has to be masked before
+        // it becomes a `FieldElement`
+        let mut neg = F::MODULUS.sub(&self.0);
+        neg.and_bool_assign(!self.0.is_zero());
+        // SAFETY: `MODULUS - self` is less than the modulus unless `self` is zero, in which case
+        // it was cleared.
+        unsafe { Self::new_unchecked(neg) }
     }
 
     /// Returns the modular additive inverse of `self`, assuming `self` isn't [`FieldElement::ZERO`].
     ///
     /// # Safety
     /// `self` cannot be [`FieldElement::ZERO`].
-    pub unsafe fn neg_unchecked(&self) -> Self {
+    pub const unsafe fn neg_unchecked(&self) -> Self {
         // SAFETY: the caller guarnantees that `self` isn't zero.
         unsafe { Self::new_unchecked(F::MODULUS.sub(&self.0)) }
     }
@@ -243,13 +372,13 @@ impl<F: FiniteField> FieldElement<F> {
     ///
     /// The returned value has the property that, when added to `self`, the sum is
     /// [`FieldElement::ZERO`].
-    pub fn neg_assign(&mut self) {
+    pub const fn neg_assign(&mut self) {
         *self = self.neg();
     }
 
     /// # Safety
     /// `self` cannot be `FieldElement::ZERO`
-    pub unsafe fn neg_assign_unchecked(&mut self) {
+    pub const unsafe fn neg_assign_unchecked(&mut self) {
         // SAFETY: the caller guarnantees that `self` isn't zero.
         *self = unsafe { self.neg_unchecked() }
     }
@@ -304,6 +433,11 @@ mod tests {
         );
         assert_eq!(a.inverse(), inverse);
         assert_eq!(FieldElement::<Secp256r1>::ONE.inverse(), FieldElement::ONE);
+        assert_eq!(a.inverse_const_time(), inverse);
+        assert_eq!(
+            FieldElement::<Secp256r1>::ZERO.inverse_const_time(),
+            FieldElement::ZERO
+        );
         let a = FieldElement::<Secp256r1>(
             UBigInt([
                 0x1001039120910903,
@@ -607,4 +741,163 @@ mod tests {
         assert_eq!(a.div(&b), quotient);
         assert_eq!(c.div(&d), quotient);
     }
+
+    mod props {
+        use super::FieldElement;
+        use crate::big_int::UBigInt;
+        use crate::ec::{EllipticCurve, Secp256r1};
+        use crate::finite_field::FiniteField;
+        use num_bigint::BigUint;
+        use proptest::prelude::*;
+
+        type P256Order = <Secp256r1 as EllipticCurve>::Order;
+
+        fn reference(x: &UBigInt<4>) -> BigUint {
+            BigUint::from_bytes_be(&x.to_be_bytes())
+        }
+
+        fn element<F: FiniteField>() -> impl Strategy<Value = FieldElement<F>> {
+            prop_oneof![
+                any::<[u64; 4]>().prop_map(|digits| FieldElement::new(UBigInt(digits))),
+                // values near the modulus are the most likely to expose reduction bugs
+                (1u64..16).prop_map(|n| FieldElement::new(F::MODULUS.sub(&UBigInt::from(n)))),
+                (0u64..16).prop_map(|n| FieldElement::new(UBigInt::from(n))),
+            ]
+        }
+
+        macro_rules! field_props {
+            ($field:ident, $name:ident) => {
+                mod $name {
+                    use super::*;
+
+                    fn modulus() -> BigUint {
+                        reference(&$field::MODULUS)
+                    }
+
+                    proptest! {
+                        #[test]
+                        fn new_reduces(digits in any::<[u64; 4]>()) {
+                            let x = FieldElement::<$field>::new(UBigInt(digits));
+                            prop_assert_eq!(reference(&x), reference(&UBigInt(digits)) % modulus());
+                            prop_assert!(x.inner() < &$field::MODULUS);
+                        }
+
+                        #[test]
+                        fn try_new(digits in any::<[u64; 4]>(), n in 0u64..4) {
+                            let x = UBigInt(digits);
+                            prop_assert_eq!(
+                                FieldElement::<$field>::try_new(x).is_ok(),
+                                x < $field::MODULUS
+                            );
+                            let near = $field::MODULUS.sub(&UBigInt::from(n));
+                            prop_assert_eq!(FieldElement::<$field>::try_new(near).is_ok(), n != 0);
+                        }
+
+                        #[test]
+                        fn bytes(x in element::<$field>(), n in 0u64..4) {
+                            let bytes = x.to_bytes();
+                            let be = reference(&x).to_bytes_be();
+                            prop_assert_eq!(&bytes[32 - be.len()..], &be[..]);
+                            prop_assert!(bytes[..32 - be.len()].iter().all(|&byte| byte == 0));
+                            prop_assert_eq!(FieldElement::<$field>::from_bytes(&bytes), Ok(x));
+
+                            // values past the modulus are rejected, even though they reduce to an element
+                            let unreduced = $field::MODULUS.add(&UBigInt::from(n)).to_be_bytes();
+                            prop_assert!(FieldElement::<$field>::from_bytes(&unreduced).is_err());
+                            prop_assert!(FieldElement::<$field>::from_bytes(&[0xff; 32]).is_err());
+                        }
+
+                        #[test]
+                        fn reduced(x in element::<$field>(), y in element::<$field>()) {
+                            // every operation leaves its result fully reduced
+                            for z in [
+                                x.add(&y),
+                                x.sub(&y),
+                                x.neg(),
+                                x.double(),
+                                x.mul(&y),
+                                x.sqr(),
+                                x.mul_digit(u64::MAX),
+                            ] {
+                                prop_assert!(z.inner() < &$field::MODULUS);
+                            }
+                        }
+
+                        #[test]
+                        fn add_sub(x in element::<$field>(), y in element::<$field>()) {
+                            prop_assert_eq!(
+                                reference(&x.add(&y)),
+                                (reference(&x) + reference(&y)) % modulus()
+                            );
+                            prop_assert_eq!(
+                                reference(&x.sub(&y)),
+                                (reference(&x) + modulus() - reference(&y)) % modulus()
+                            );
+                            prop_assert_eq!(x.add(&y).sub(&y), x);
+                            prop_assert_eq!(x.add(&x.neg()), FieldElement::ZERO);
+                        }
+
+                        #[test]
+                        fn mul(x in element::<$field>(), y in element::<$field>()) {
+                            prop_assert_eq!(
+                                reference(&x.mul(&y)),
+                                reference(&x) * reference(&y) % modulus()
+                            );
+                            prop_assert_eq!(x.sqr(), x.mul(&x));
+                        }
+
+                        #[test]
+                        fn ring_axioms(
+                            x in element::<$field>(),
+                            y in element::<$field>(),
+                            z in element::<$field>(),
+                        ) {
+                            prop_assert_eq!(x.add(&y), y.add(&x));
+                            prop_assert_eq!(x.mul(&y), y.mul(&x));
+                            prop_assert_eq!(x.add(&y).add(&z), x.add(&y.add(&z)));
+                            prop_assert_eq!(x.mul(&y).mul(&z), x.mul(&y.mul(&z)));
+                            prop_assert_eq!(x.mul(&y.add(&z)), x.mul(&y).add(&x.mul(&z)));
+                            prop_assert_eq!(x.mul(&FieldElement::ONE), x);
+                            prop_assert_eq!(x.add(&FieldElement::ZERO), x);
+                        }
+
+                        #[test]
+                        fn inverse(x in element::<$field>()) {
+                            prop_assume!(x != FieldElement::ZERO);
+                            prop_assert_eq!(x.mul(&x.inverse()), FieldElement::ONE);
+                            prop_assert_eq!(x.div(&x), FieldElement::ONE);
+                            prop_assert_eq!(x.inverse_const_time(), x.inverse());
+                        }
+
+                        #[test]
+                        fn pow(x in element::<$field>(), exp in any::<[u64; 4]>()) {
+                            let exp = UBigInt(exp);
+                            prop_assert_eq!(
+                                reference(&x.pow(&exp)),
+                                reference(&x).modpow(&reference(&exp), &modulus())
+                            );
+                        }
+
+                        #[test]
+                        fn sqrt(x in element::<$field>()) {
+                            let square = x.sqr();
+                            let root = square.sqrt().unwrap();
+                            prop_assert!(root == x || root == x.neg());
+
+                            // Euler's criterion tells whether `x` is a square
+                            let half = (modulus() - 1u32) / 2u32;
+                            let is_square = reference(&x).modpow(&half, &modulus()) != modulus() - 1u32;
+                            match x.sqrt() {
+                                Some(root) => prop_assert!(is_square && root.sqr() == x),
+                                None => prop_assert!(!is_square),
+                            }
+                        }
+                    }
+                }
+            };
+        }
+
+        field_props!(Secp256r1, secp256r1);
+        field_props!(P256Order, p256_order);
+    }
 }
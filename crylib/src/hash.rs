@@ -1,15 +1,26 @@
-//! The SHA2 family of hash functions.
+//! Cryptographic hash functions.
+mod blake2b;
 mod buf_hasher;
+mod sha1;
 mod sha256;
+mod sha384;
 mod sha512;
 
+pub use blake2b::Blake2b;
 pub use buf_hasher::BufHasher;
+pub use sha1::Sha1;
 pub use sha256::Sha256;
+pub use sha384::Sha384;
 pub use sha512::Sha512;
 
 pub trait Hasher<const H_LEN: usize> {
     fn new() -> Self;
 
+    /// Adds `msg` to the hashed data.
+    ///
+    /// `msg` can be any length: input that doesn't fill a block is buffered until it does.
+    fn update_with(&mut self, msg: &[u8]);
+
     fn finish_with(self, msg: &[u8]) -> [u8; H_LEN];
 
     fn hash(msg: &[u8]) -> [u8; H_LEN];
@@ -20,3 +31,34 @@ pub trait Hasher<const H_LEN: usize> {
 pub trait BlockHasher<const H_LEN: usize, const B_LEN: usize>: Hasher<H_LEN> {
     fn update(&mut self, block: &[u8; B_LEN]);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockHasher, Sha1, Sha256, Sha384, Sha512};
+
+    /// Checks that `H` gives the same hash for input fed to it in uneven chunks, for every length
+    /// up to two blocks, so around both places the padding can end up.
+    fn check_update_with<const H_LEN: usize, const B_LEN: usize, H: BlockHasher<H_LEN, B_LEN>>() {
+        // two blocks and a byte of the largest block size
+        let mut msg = [0; 2 * 128 + 1];
+        let msg = &mut msg[..2 * B_LEN + 1];
+        for (i, byte) in msg.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        for len in 0..msg.len() {
+            let mut hasher = H::new();
+            for chunk in msg[..len].chunks(7) {
+                hasher.update_with(chunk);
+            }
+            assert_eq!(hasher.finish(), H::hash(&msg[..len]));
+        }
+    }
+
+    #[test]
+    fn update_with() {
+        check_update_with::<{ Sha1::HASH_SIZE }, { Sha1::BLOCK_SIZE }, Sha1>();
+        check_update_with::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>();
+        check_update_with::<{ Sha384::HASH_SIZE }, { Sha384::BLOCK_SIZE }, Sha384>();
+        check_update_with::<{ Sha512::HASH_SIZE }, { Sha512::BLOCK_SIZE }, Sha512>();
+    }
+}
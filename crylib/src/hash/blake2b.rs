@@ -0,0 +1,223 @@
+//! A software implementation of BLAKE2b, as defined in [RFC 7693].
+//!
+//! [RFC 7693]: https://datatracker.ietf.org/doc/html/rfc7693
+
+use super::Hasher;
+
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// The BLAKE2b hash function.
+///
+/// BLAKE2b supports any digest length from 1 to 64 bytes, selected with [`Blake2b::with_len`].
+/// The [`Hasher`] implementation always produces the full 64-byte digest.
+///
+/// Unlike the SHA-2 hashers, BLAKE2b must know which block is the last one before compressing it,
+/// so it doesn't implement [`BlockHasher`](super::BlockHasher).
+#[derive(Clone)]
+pub struct Blake2b {
+    state: [u64; 8],
+    len: u128,
+    buf: [u8; Self::BLOCK_SIZE],
+    buf_len: usize,
+    out_len: usize,
+}
+
+impl Hasher<{ Blake2b::HASH_SIZE }> for Blake2b {
+    fn new() -> Self {
+        Self::with_len(Self::HASH_SIZE)
+    }
+
+    fn update_with(&mut self, mut msg: &[u8]) {
+        while !msg.is_empty() {
+            // the buffered block can only be compressed once we know it isn't the last one
+            if self.buf_len == Self::BLOCK_SIZE {
+                self.len += Self::BLOCK_SIZE as u128;
+                let block = self.buf;
+                self.compress(&block, false);
+                self.buf_len = 0;
+            }
+
+            let to_copy = msg.len().min(Self::BLOCK_SIZE - self.buf_len);
+            self.buf[self.buf_len..][..to_copy].copy_from_slice(&msg[..to_copy]);
+            self.buf_len += to_copy;
+            msg = &msg[to_copy..];
+        }
+    }
+
+    fn finish_with(mut self, msg: &[u8]) -> [u8; Self::HASH_SIZE] {
+        self.update_with(msg);
+        self.finish()
+    }
+
+    fn hash(msg: &[u8]) -> [u8; Self::HASH_SIZE] {
+        let hasher = Self::new();
+        hasher.finish_with(msg)
+    }
+
+    fn finish(self) -> [u8; Self::HASH_SIZE] {
+        let mut out = [0; Self::HASH_SIZE];
+        self.finish_into(&mut out);
+        out
+    }
+}
+
+impl Blake2b {
+    /// The maximum digest length.
+    pub const HASH_SIZE: usize = 64;
+    pub const BLOCK_SIZE: usize = 128;
+
+    /// Creates a new hasher that produces a digest of `out_len` bytes.
+    ///
+    /// # Panics
+    /// This function panics if `out_len` is `0` or greater than [`Blake2b::HASH_SIZE`].
+    pub fn with_len(out_len: usize) -> Self {
+        assert!(out_len > 0 && out_len <= Self::HASH_SIZE);
+
+        let mut state = IV;
+        state[0] ^= 0x01010000 ^ out_len as u64;
+        Self {
+            state,
+            len: 0,
+            buf: [0; Self::BLOCK_SIZE],
+            buf_len: 0,
+            out_len,
+        }
+    }
+
+    /// The length of the digest this hasher produces.
+    pub fn out_len(&self) -> usize {
+        self.out_len
+    }
+
+    /// Writes the digest to `out`.
+    ///
+    /// # Panics
+    /// This function panics if `out.len()` is not equal to [`Blake2b::out_len()`].
+    pub fn finish_into(mut self, out: &mut [u8]) {
+        assert_eq!(out.len(), self.out_len);
+
+        self.len += self.buf_len as u128;
+        let mut last_block = [0; Self::BLOCK_SIZE];
+        last_block[..self.buf_len].copy_from_slice(&self.buf[..self.buf_len]);
+        self.compress(&last_block, true);
+
+        for (chunk, int) in out.chunks_mut(size_of::<u64>()).zip(self.state) {
+            chunk.copy_from_slice(&int.to_le_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn compress(&mut self, block: &[u8; Self::BLOCK_SIZE], last: bool) {
+        let mut msg = [0u64; 16];
+        // TODO: use `array_chunks` once stabilized
+        for (word, chunk) in msg.iter_mut().zip(block.chunks_exact(8)) {
+            // we can safely unwrap because `chunk` is guaranteed to have a length of `8`
+            *word = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+
+        let mut v = [0u64; 16];
+        v[..8].copy_from_slice(&self.state);
+        v[8..].copy_from_slice(&IV);
+        v[12] ^= self.len as u64;
+        v[13] ^= (self.len >> 64) as u64;
+        if last {
+            v[14] = !v[14];
+        }
+
+        for round in 0..12 {
+            let s = &SIGMA[round % 10];
+            mix(&mut v, 0, 4, 8, 12, msg[s[0]], msg[s[1]]);
+            mix(&mut v, 1, 5, 9, 13, msg[s[2]], msg[s[3]]);
+            mix(&mut v, 2, 6, 10, 14, msg[s[4]], msg[s[5]]);
+            mix(&mut v, 3, 7, 11, 15, msg[s[6]], msg[s[7]]);
+            mix(&mut v, 0, 5, 10, 15, msg[s[8]], msg[s[9]]);
+            mix(&mut v, 1, 6, 11, 12, msg[s[10]], msg[s[11]]);
+            mix(&mut v, 2, 7, 8, 13, msg[s[12]], msg[s[13]]);
+            mix(&mut v, 3, 4, 9, 14, msg[s[14]], msg[s[15]]);
+        }
+
+        for (i, word) in self.state.iter_mut().enumerate() {
+            *word ^= v[i] ^ v[i + 8];
+        }
+    }
+}
+
+/// The BLAKE2b mixing function `G`.
+fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blake2b() {
+        // test vector from https://datatracker.ietf.org/doc/html/rfc7693#appendix-A
+        let hash = [
+            0xba, 0x80, 0xa5, 0x3f, 0x98, 0x1c, 0x4d, 0x0d, 0x6a, 0x27, 0x97, 0xb6, 0x9f, 0x12,
+            0xf6, 0xe9, 0x4c, 0x21, 0x2f, 0x14, 0x68, 0x5a, 0xc4, 0xb7, 0x4b, 0x12, 0xbb, 0x6f,
+            0xdb, 0xff, 0xa2, 0xd1, 0x7d, 0x87, 0xc5, 0x39, 0x2a, 0xab, 0x79, 0x2d, 0xc2, 0x52,
+            0xd5, 0xde, 0x45, 0x33, 0xcc, 0x95, 0x18, 0xd3, 0x8a, 0xa8, 0xdb, 0xf1, 0x92, 0x5a,
+            0xb9, 0x23, 0x86, 0xed, 0xd4, 0x00, 0x99, 0x23,
+        ];
+        assert_eq!(Blake2b::hash(b"abc"), hash);
+
+        let hash = [
+            0x33, 0x45, 0x52, 0x4a, 0xbf, 0x6b, 0xbe, 0x18, 0x09, 0x44, 0x92, 0x24, 0xb5, 0x97,
+            0x2c, 0x41, 0x79, 0x0b, 0x6c, 0xf2,
+        ];
+        let mut out = [0; 20];
+        Blake2b::with_len(20).finish_into(&mut out);
+        assert_eq!(out, hash);
+    }
+
+    #[test]
+    fn update_with() {
+        // the message is exactly two blocks long, so the second block must be the final one
+        let mut msg = [0; 2 * Blake2b::BLOCK_SIZE];
+        for (i, byte) in msg.iter_mut().enumerate() {
+            *byte = (i % Blake2b::BLOCK_SIZE) as u8;
+        }
+        let hash = [
+            0x05, 0x54, 0x85, 0x7f, 0x22, 0xe2, 0x37, 0x25, 0x43, 0xbe, 0x84, 0xf6, 0x56, 0x3a,
+            0x78, 0xf6, 0xba, 0x90, 0x06, 0x0d, 0x3a, 0x19, 0x20, 0xda, 0x52, 0xfc, 0x87, 0x11,
+            0x25, 0x0a, 0xd3, 0x3d,
+        ];
+        let mut hasher = Blake2b::with_len(32);
+        for chunk in msg.chunks(7) {
+            hasher.update_with(chunk);
+        }
+        let mut out = [0; 32];
+        hasher.finish_into(&mut out);
+        assert_eq!(out, hash);
+    }
+}
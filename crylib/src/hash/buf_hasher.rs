@@ -9,11 +9,19 @@ where
     len: usize,
 }
 
-impl<const H_LEN: usize, const B_LEN: usize, H> BufHasher<H_LEN, B_LEN, H>
+impl<const H_LEN: usize, const B_LEN: usize, H> Hasher<H_LEN> for BufHasher<H_LEN, B_LEN, H>
 where
     H: BlockHasher<H_LEN, B_LEN>,
 {
-    pub fn update_with(&mut self, msg: &[u8]) {
+    fn new() -> Self {
+        Self {
+            hasher: H::new(),
+            buf: [0; B_LEN],
+            len: 0,
+        }
+    }
+
+    fn update_with(&mut self, msg: &[u8]) {
         if msg.len() <= B_LEN - self.len {
             self.buf[self.len..][..msg.len()].copy_from_slice(msg);
             self.len += msg.len();
@@ -33,19 +41,6 @@ where
         self.buf[..remainder.len()].copy_from_slice(remainder);
         self.len = remainder.len();
     }
-}
-
-impl<const H_LEN: usize, const B_LEN: usize, H> Hasher<H_LEN> for BufHasher<H_LEN, B_LEN, H>
-where
-    H: BlockHasher<H_LEN, B_LEN>,
-{
-    fn new() -> Self {
-        Self {
-            hasher: H::new(),
-            buf: [0; B_LEN],
-            len: 0,
-        }
-    }
 
     fn finish(self) -> [u8; H_LEN] {
         self.hasher.finish_with(&self.buf[..self.len])
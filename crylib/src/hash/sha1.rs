@@ -0,0 +1,188 @@
+//! A software implementation of SHA-1.
+//!
+//! <div class="warning">
+//! WARNING: SHA-1 is broken: practical collision attacks exist.
+//! It is only provided for legacy protocols such as HOTP and TOTP, where HMAC-SHA-1 is still the
+//! most widely deployed choice. Do not use it for signatures or anything that needs collision
+//! resistance.
+//! </div>
+
+use super::{BlockHasher, Hasher};
+
+#[derive(Clone)]
+pub struct Sha1 {
+    state: [u32; Self::HASH_SIZE / size_of::<u32>()],
+    /// The number of bytes hashed so far, including the ones in `buf`.
+    len: u64,
+    buf: [u8; Self::BLOCK_SIZE],
+    buf_len: usize,
+}
+
+impl Sha1 {
+    pub const HASH_SIZE: usize = 20;
+    pub const BLOCK_SIZE: usize = 64;
+    fn update_countless(&mut self, block: &[u8; Self::BLOCK_SIZE]) {
+        let mut message_schedule = [0u32; 80];
+        // TODO: use `array_chunks` once stabilized
+        for (int, chunk) in message_schedule.iter_mut().zip(block.chunks_exact(4)) {
+            *int = u32::from_be_bytes(chunk.try_into().unwrap());
+        }
+        for i in 16..message_schedule.len() {
+            message_schedule[i] = (message_schedule[i - 3]
+                ^ message_schedule[i - 8]
+                ^ message_schedule[i - 14]
+                ^ message_schedule[i - 16])
+                .rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = self.state;
+
+        for (i, word) in message_schedule.into_iter().enumerate() {
+            let (f, k) = match i {
+                0..20 => ((b & c) | (!b & d), 0x5a827999),
+                20..40 => (b ^ c ^ d, 0x6ed9eba1),
+                40..60 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        self.state[0] = self.state[0].wrapping_add(a);
+        self.state[1] = self.state[1].wrapping_add(b);
+        self.state[2] = self.state[2].wrapping_add(c);
+        self.state[3] = self.state[3].wrapping_add(d);
+        self.state[4] = self.state[4].wrapping_add(e);
+    }
+}
+
+impl Hasher<{ Sha1::HASH_SIZE }> for Sha1 {
+    fn new() -> Self {
+        Self {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0],
+            len: 0,
+            buf: [0; Self::BLOCK_SIZE],
+            buf_len: 0,
+        }
+    }
+
+    fn update_with(&mut self, mut msg: &[u8]) {
+        self.len += msg.len() as u64;
+
+        // top up a partially filled block first
+        if self.buf_len > 0 {
+            let to_copy = msg.len().min(Self::BLOCK_SIZE - self.buf_len);
+            self.buf[self.buf_len..][..to_copy].copy_from_slice(&msg[..to_copy]);
+            self.buf_len += to_copy;
+            msg = &msg[to_copy..];
+            if self.buf_len < Self::BLOCK_SIZE {
+                return;
+            }
+            let block = self.buf;
+            self.update_countless(&block);
+            self.buf_len = 0;
+        }
+
+        // TODO: use `array_chunks` once stabilized
+        let blocks = msg.chunks_exact(Self::BLOCK_SIZE);
+        let remainder = blocks.remainder();
+
+        for block in blocks {
+            self.update_countless(block.try_into().unwrap());
+        }
+        self.buf[..remainder.len()].copy_from_slice(remainder);
+        self.buf_len = remainder.len();
+    }
+
+    fn finish_with(mut self, msg: &[u8]) -> [u8; Self::HASH_SIZE] {
+        self.update_with(msg);
+        self.finish()
+    }
+
+    fn hash(msg: &[u8]) -> [u8; Self::HASH_SIZE] {
+        let hasher = Self::new();
+        hasher.finish_with(msg)
+    }
+
+    fn finish(mut self) -> [u8; Self::HASH_SIZE] {
+        let mut last_block = [0; Self::BLOCK_SIZE];
+        // we can safely write here because the buffer is never full
+        last_block[..self.buf_len].copy_from_slice(&self.buf[..self.buf_len]);
+
+        last_block[self.buf_len] = 0x80;
+
+        // does the length info fit without adding an extra block?
+        if self.buf_len >= Self::BLOCK_SIZE - size_of::<u64>() {
+            self.update_countless(&last_block);
+            last_block = [0; Self::BLOCK_SIZE];
+        }
+        last_block[Self::BLOCK_SIZE - size_of::<u64>()..]
+            .copy_from_slice(&(self.len * 8).to_be_bytes());
+
+        self.update_countless(&last_block);
+        u32_array_to_bytes(&self.state)
+    }
+}
+
+impl BlockHasher<{ Self::HASH_SIZE }, { Self::BLOCK_SIZE }> for Sha1 {
+    fn update(&mut self, block: &[u8; Self::BLOCK_SIZE]) {
+        self.update_with(block);
+    }
+}
+
+fn u32_array_to_bytes(array: &[u32; Sha1::HASH_SIZE / size_of::<u32>()]) -> [u8; Sha1::HASH_SIZE] {
+    // TODO: consider using uninitialized array
+    let mut as_bytes = [0u8; Sha1::HASH_SIZE];
+    // TODO: use `array_chunks` once stabilized
+    for (chunk, int) in as_bytes.chunks_exact_mut(size_of::<u32>()).zip(array) {
+        chunk.copy_from_slice(&int.to_be_bytes())
+    }
+    as_bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash() {
+        let digest = [
+            0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e, 0x25, 0x71, 0x78, 0x50,
+            0xc2, 0x6c, 0x9c, 0xd0, 0xd8, 0x9d,
+        ];
+        assert_eq!(Sha1::hash(b"abc"), digest);
+
+        // the padding doesn't fit in the last block
+        let msg = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        let digest = [
+            0x84, 0x98, 0x3e, 0x44, 0x1c, 0x3b, 0xd2, 0x6e, 0xba, 0xae, 0x4a, 0xa1, 0xf9, 0x51,
+            0x29, 0xe5, 0xe5, 0x46, 0x70, 0xf1,
+        ];
+        assert_eq!(Sha1::hash(msg), digest);
+    }
+
+    #[test]
+    fn update() {
+        let mut msg = [0; 512];
+        for (i, byte) in msg.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let digest = [
+            0xdb, 0xe6, 0x49, 0xda, 0xba, 0x34, 0x0b, 0xce, 0x7a, 0x44, 0xb8, 0x09, 0x01, 0x6d,
+            0x91, 0x48, 0x39, 0xb9, 0x9f, 0x10,
+        ];
+        let mut hasher = Sha1::new();
+        for block in msg.chunks_exact(Sha1::BLOCK_SIZE) {
+            hasher.update(block.try_into().unwrap());
+        }
+        assert_eq!(hasher.finish(), digest);
+    }
+}
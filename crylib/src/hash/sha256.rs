@@ -58,9 +58,13 @@ const fn little_sigma_1(x: u32) -> u32 {
     x.rotate_right(17) ^ x.rotate_right(19) ^ x >> 10
 }
 
+#[derive(Clone)]
 pub struct Sha256 {
     state: [u32; Self::HASH_SIZE / size_of::<u32>()],
+    /// The number of bytes hashed so far, including the ones in `buf`.
     len: u64,
+    buf: [u8; Self::BLOCK_SIZE],
+    buf_len: usize,
 }
 
 impl Sha256 {
@@ -124,9 +128,28 @@ impl Hasher<{ Sha256::HASH_SIZE }> for Sha256 {
                 0x5be0cd19,
             ],
             len: 0,
+            buf: [0; Self::BLOCK_SIZE],
+            buf_len: 0,
         }
     }
-    fn finish_with(mut self, msg: &[u8]) -> [u8; Self::HASH_SIZE] {
+
+    fn update_with(&mut self, mut msg: &[u8]) {
+        self.len += msg.len() as u64;
+
+        // top up a partially filled block first
+        if self.buf_len > 0 {
+            let to_copy = msg.len().min(Self::BLOCK_SIZE - self.buf_len);
+            self.buf[self.buf_len..][..to_copy].copy_from_slice(&msg[..to_copy]);
+            self.buf_len += to_copy;
+            msg = &msg[to_copy..];
+            if self.buf_len < Self::BLOCK_SIZE {
+                return;
+            }
+            let block = self.buf;
+            self.update_countless(&block);
+            self.buf_len = 0;
+        }
+
         // TODO: use `array_chunks` once stabilized
         let blocks = msg.chunks_exact(Self::BLOCK_SIZE);
         let remainder = blocks.remainder();
@@ -134,47 +157,43 @@ impl Hasher<{ Sha256::HASH_SIZE }> for Sha256 {
         for block in blocks {
             self.update_countless(block.try_into().unwrap());
         }
+        self.buf[..remainder.len()].copy_from_slice(remainder);
+        self.buf_len = remainder.len();
+    }
 
-        let mut last_block = [0; Self::BLOCK_SIZE];
-        // we can safely write here because the excess must be less than `BLOCK_SIZE`
-        last_block[..remainder.len()].copy_from_slice(remainder);
-
-        last_block[remainder.len()] = 0x80;
-
-        // does the length info fit without adding an extra block?
-        if remainder.len() < Self::BLOCK_SIZE - size_of::<u64>() {
-            last_block[Self::BLOCK_SIZE - size_of::<u64>()..]
-                .copy_from_slice(&((msg.len() as u64 + self.len) * 8).to_be_bytes());
-        } else {
-            self.update_countless(&last_block);
-            last_block = [0; Self::BLOCK_SIZE];
-            last_block[Self::BLOCK_SIZE - size_of::<u64>()..]
-                .copy_from_slice(&((msg.len() as u64 + self.len) * 8).to_be_bytes());
-        }
-
-        self.update(&last_block);
-        u32_array_to_bytes(&self.state)
+    fn finish_with(mut self, msg: &[u8]) -> [u8; Self::HASH_SIZE] {
+        self.update_with(msg);
+        self.finish()
     }
 
-    fn hash(msg: &[u8]) -> [u8; Sha256::HASH_SIZE] {
+    fn hash(msg: &[u8]) -> [u8; Self::HASH_SIZE] {
         let hasher = Self::new();
         hasher.finish_with(msg)
     }
 
     fn finish(mut self) -> [u8; Self::HASH_SIZE] {
-        let mut padding = [0; Self::BLOCK_SIZE];
-        padding[0] = 0x80;
-        padding[Sha256::BLOCK_SIZE - size_of::<u64>()..]
+        let mut last_block = [0; Self::BLOCK_SIZE];
+        // we can safely write here because the buffer is never full
+        last_block[..self.buf_len].copy_from_slice(&self.buf[..self.buf_len]);
+
+        last_block[self.buf_len] = 0x80;
+
+        // does the length info fit without adding an extra block?
+        if self.buf_len >= Self::BLOCK_SIZE - size_of::<u64>() {
+            self.update_countless(&last_block);
+            last_block = [0; Self::BLOCK_SIZE];
+        }
+        last_block[Self::BLOCK_SIZE - size_of::<u64>()..]
             .copy_from_slice(&(self.len * 8).to_be_bytes());
-        self.update_countless(&padding);
+
+        self.update_countless(&last_block);
         u32_array_to_bytes(&self.state)
     }
 }
 
 impl BlockHasher<{ Self::HASH_SIZE }, { Self::BLOCK_SIZE }> for Sha256 {
     fn update(&mut self, block: &[u8; Self::BLOCK_SIZE]) {
-        self.update_countless(block);
-        self.len += Self::BLOCK_SIZE as u64;
+        self.update_with(block);
     }
 }
 
@@ -0,0 +1,116 @@
+//! A software implementation of SHA-384.
+//!
+//! SHA-384 is SHA-512 with a different initial hash value, truncated to 48 bytes
+//! ([FIPS 180-4 section 6.5](https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.180-4.pdf)).
+
+use super::{BlockHasher, Hasher, Sha512};
+
+#[derive(Clone)]
+pub struct Sha384(Sha512);
+
+impl Hasher<{ Sha384::HASH_SIZE }> for Sha384 {
+    fn new() -> Self {
+        Self(Sha512::with_state([
+            0xcbbb9d5dc1059ed8,
+            0x629a292a367cd507,
+            0x9159015a3070dd17,
+            0x152fecd8f70e5939,
+            0x67332667ffc00b31,
+            0x8eb44a8768581511,
+            0xdb0c2e0d64f98fa7,
+            0x47b5481dbefa4fa4,
+        ]))
+    }
+
+    fn update_with(&mut self, msg: &[u8]) {
+        self.0.update_with(msg);
+    }
+
+    fn finish_with(self, msg: &[u8]) -> [u8; Self::HASH_SIZE] {
+        truncate(self.0.finish_with(msg))
+    }
+
+    fn finish(self) -> [u8; Self::HASH_SIZE] {
+        truncate(self.0.finish())
+    }
+
+    fn hash(msg: &[u8]) -> [u8; Self::HASH_SIZE] {
+        let hasher = Self::new();
+        hasher.finish_with(msg)
+    }
+}
+
+impl BlockHasher<{ Self::HASH_SIZE }, { Self::BLOCK_SIZE }> for Sha384 {
+    fn update(&mut self, block: &[u8; Self::BLOCK_SIZE]) {
+        self.0.update(block);
+    }
+}
+
+impl Sha384 {
+    pub const HASH_SIZE: usize = 48;
+    pub const BLOCK_SIZE: usize = Sha512::BLOCK_SIZE;
+}
+
+fn truncate(digest: [u8; Sha512::HASH_SIZE]) -> [u8; Sha384::HASH_SIZE] {
+    // we can safely unwrap because the SHA-512 digest is longer
+    digest[..Sha384::HASH_SIZE].try_into().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // test vectors from https://csrc.nist.gov/projects/cryptographic-standards-and-guidelines/example-values
+
+    #[test]
+    fn hash() {
+        let digest = [
+            0xcb, 0x00, 0x75, 0x3f, 0x45, 0xa3, 0x5e, 0x8b, 0xb5, 0xa0, 0x3d, 0x69, 0x9a, 0xc6,
+            0x50, 0x07, 0x27, 0x2c, 0x32, 0xab, 0x0e, 0xde, 0xd1, 0x63, 0x1a, 0x8b, 0x60, 0x5a,
+            0x43, 0xff, 0x5b, 0xed, 0x80, 0x86, 0x07, 0x2b, 0xa1, 0xe7, 0xcc, 0x23, 0x58, 0xba,
+            0xec, 0xa1, 0x34, 0xc8, 0x25, 0xa7,
+        ];
+        assert_eq!(Sha384::hash(b"abc"), digest);
+
+        // the padding needs a block of its own
+        let msg = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+        let digest = [
+            0x09, 0x33, 0x0c, 0x33, 0xf7, 0x11, 0x47, 0xe8, 0x3d, 0x19, 0x2f, 0xc7, 0x82, 0xcd,
+            0x1b, 0x47, 0x53, 0x11, 0x1b, 0x17, 0x3b, 0x3b, 0x05, 0xd2, 0x2f, 0xa0, 0x80, 0x86,
+            0xe3, 0xb0, 0xf7, 0x12, 0xfc, 0xc7, 0xc7, 0x1a, 0x55, 0x7e, 0x2d, 0xb9, 0x66, 0xc3,
+            0xe9, 0xfa, 0x91, 0x74, 0x60, 0x39,
+        ];
+        assert_eq!(Sha384::hash(msg), digest);
+
+        let digest = [
+            0x38, 0xb0, 0x60, 0xa7, 0x51, 0xac, 0x96, 0x38, 0x4c, 0xd9, 0x32, 0x7e, 0xb1, 0xb1,
+            0xe3, 0x6a, 0x21, 0xfd, 0xb7, 0x11, 0x14, 0xbe, 0x07, 0x43, 0x4c, 0x0c, 0xc7, 0xbf,
+            0x63, 0xf6, 0xe1, 0xda, 0x27, 0x4e, 0xde, 0xbf, 0xe7, 0x6f, 0x65, 0xfb, 0xd5, 0x1a,
+            0xd2, 0xf1, 0x48, 0x98, 0xb9, 0x5b,
+        ];
+        assert_eq!(Sha384::hash(&[]), digest);
+        assert_eq!(Sha384::new().finish(), digest);
+    }
+
+    #[test]
+    fn update() {
+        let mut msg = [0; 2 * 256];
+        for (byte, i) in msg.iter_mut().zip((0..=255).cycle()) {
+            *byte = i;
+        }
+        let digest = [
+            0x45, 0x82, 0xfc, 0x82, 0x43, 0x0e, 0x52, 0x68, 0x86, 0xa1, 0x85, 0x34, 0x11, 0xe6,
+            0x06, 0x45, 0xfe, 0xf7, 0xe8, 0xea, 0x0c, 0x85, 0x46, 0xb7, 0xc9, 0xba, 0x0c, 0x84,
+            0x16, 0xd9, 0xa9, 0x8f, 0xb5, 0x2e, 0xbd, 0x0c, 0x60, 0x5f, 0xbb, 0x70, 0x74, 0x9c,
+            0x4e, 0x3e, 0x5d, 0xa3, 0xdb, 0xac,
+        ];
+        assert_eq!(Sha384::hash(&msg), digest);
+
+        let mut hasher = Sha384::new();
+        for block in msg.chunks_exact(Sha384::BLOCK_SIZE) {
+            hasher.update(block.try_into().unwrap());
+        }
+        assert_eq!(hasher.finish(), digest);
+    }
+}
@@ -109,9 +109,13 @@ const fn little_sigma_1(x: u64) -> u64 {
     x.rotate_right(19) ^ x.rotate_right(61) ^ x >> 6
 }
 
+#[derive(Clone)]
 pub struct Sha512 {
     state: [u64; Self::HASH_SIZE / size_of::<u64>()],
+    /// The number of bytes hashed so far, including the ones in `buf`.
     len: u128,
+    buf: [u8; Self::BLOCK_SIZE],
+    buf_len: usize,
 }
 
 impl Hasher<{ Sha512::HASH_SIZE }> for Sha512 {
@@ -128,59 +132,72 @@ impl Hasher<{ Sha512::HASH_SIZE }> for Sha512 {
                 0x5be0cd19137e2179,
             ],
             len: 0,
+            buf: [0; Self::BLOCK_SIZE],
+            buf_len: 0,
         }
     }
 
-    fn finish_with(mut self, msg: &[u8]) -> [u8; Self::HASH_SIZE] {
+    fn update_with(&mut self, mut msg: &[u8]) {
+        self.len += msg.len() as u128;
+
+        // top up a partially filled block first
+        if self.buf_len > 0 {
+            let to_copy = msg.len().min(Self::BLOCK_SIZE - self.buf_len);
+            self.buf[self.buf_len..][..to_copy].copy_from_slice(&msg[..to_copy]);
+            self.buf_len += to_copy;
+            msg = &msg[to_copy..];
+            if self.buf_len < Self::BLOCK_SIZE {
+                return;
+            }
+            let block = self.buf;
+            self.update_countless(&block);
+            self.buf_len = 0;
+        }
+
         // TODO: use `array_chunks` once stabilized
-        let chunks = msg.chunks_exact(Self::BLOCK_SIZE);
-        let remainder = chunks.remainder();
+        let blocks = msg.chunks_exact(Self::BLOCK_SIZE);
+        let remainder = blocks.remainder();
 
-        for block in chunks {
-            self.update_countless(&block.try_into().unwrap());
+        for block in blocks {
+            self.update_countless(block.try_into().unwrap());
         }
+        self.buf[..remainder.len()].copy_from_slice(remainder);
+        self.buf_len = remainder.len();
+    }
 
+    fn finish_with(mut self, msg: &[u8]) -> [u8; Self::HASH_SIZE] {
+        self.update_with(msg);
+        self.finish()
+    }
+
+    fn hash(msg: &[u8]) -> [u8; Self::HASH_SIZE] {
+        let hasher = Self::new();
+        hasher.finish_with(msg)
+    }
+
+    fn finish(mut self) -> [u8; Self::HASH_SIZE] {
         let mut last_block = [0; Self::BLOCK_SIZE];
-        // we can safely write here because the excess must be less than `BLOCK_SIZE`
-        last_block[..remainder.len()].copy_from_slice(remainder);
+        // we can safely write here because the buffer is never full
+        last_block[..self.buf_len].copy_from_slice(&self.buf[..self.buf_len]);
 
-        last_block[remainder.len()] = 0x80;
+        last_block[self.buf_len] = 0x80;
 
         // does the length info fit without adding an extra block?
-        if remainder.len() < Self::BLOCK_SIZE - size_of::<u128>() {
-            last_block[Self::BLOCK_SIZE - size_of::<u128>()..]
-                .copy_from_slice(&((msg.len() as u128 + self.len) * 8).to_be_bytes());
-        } else {
+        if self.buf_len >= Self::BLOCK_SIZE - size_of::<u128>() {
             self.update_countless(&last_block);
             last_block = [0; Self::BLOCK_SIZE];
-            last_block[Self::BLOCK_SIZE - size_of::<u128>()..]
-                .copy_from_slice(&((msg.len() as u128 + self.len) * 8).to_be_bytes());
         }
-
-        self.update(&last_block);
-        u64_array_to_be_bytes(&self.state)
-    }
-
-    fn finish(mut self) -> [u8; Self::HASH_SIZE] {
-        let mut padding = [0; Self::BLOCK_SIZE];
-        padding[0] = 0x80;
-        padding[Self::BLOCK_SIZE - size_of::<u128>()..]
+        last_block[Self::BLOCK_SIZE - size_of::<u128>()..]
             .copy_from_slice(&(self.len * 8).to_be_bytes());
-        self.update_countless(&padding);
 
+        self.update_countless(&last_block);
         u64_array_to_be_bytes(&self.state)
     }
-
-    fn hash(msg: &[u8]) -> [u8; Self::HASH_SIZE] {
-        let hasher = Self::new();
-        hasher.finish_with(msg)
-    }
 }
 
 impl BlockHasher<{ Self::HASH_SIZE }, { Self::BLOCK_SIZE }> for Sha512 {
     fn update(&mut self, block: &[u8; Self::BLOCK_SIZE]) {
-        self.update_countless(block);
-        self.len += Self::BLOCK_SIZE as u128;
+        self.update_with(block);
     }
 }
 
@@ -188,6 +205,17 @@ impl Sha512 {
     pub const HASH_SIZE: usize = 64;
     pub const BLOCK_SIZE: usize = 128;
 
+    /// Creates a hasher that starts from `state` instead of the SHA-512 initial hash value, which
+    /// is all that sets [`Sha384`](super::Sha384) apart.
+    pub(super) const fn with_state(state: [u64; Self::HASH_SIZE / size_of::<u64>()]) -> Self {
+        Self {
+            state,
+            len: 0,
+            buf: [0; Self::BLOCK_SIZE],
+            buf_len: 0,
+        }
+    }
+
     fn update_countless(&mut self, block: &[u8; Self::BLOCK_SIZE]) {
         let block = be_bytes_to_u64_array(block);
         let mut message_schedule = [0; 80];
@@ -1,4 +1,4 @@
-use crate::hash::{BlockHasher, BufHasher};
+use crate::hash::BlockHasher;
 use crate::hmac::Hmac;
 
 pub fn extract<const H_LEN: usize, const B_LEN: usize, H: BlockHasher<H_LEN, B_LEN>>(
@@ -21,7 +21,7 @@ pub fn expand<
 
     let mut prev_mac: &[u8] = &[];
     for (i, key_chunk) in key.chunks_mut(H_LEN).enumerate() {
-        let mut hmac = Hmac::<H_LEN, B_LEN, BufHasher<H_LEN, B_LEN, H>>::new(pr_key);
+        let mut hmac = Hmac::<H_LEN, B_LEN, H>::new(pr_key);
         hmac.update_with(prev_mac);
         hmac.update_with(info);
         let mac = hmac.finish_with(&[i as u8 + 1]);
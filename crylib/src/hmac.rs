@@ -1,4 +1,19 @@
-use crate::hash::{BlockHasher, BufHasher};
+use crate::ct::ct_eq;
+use crate::hash::BlockHasher;
+
+/// An error that is returned when a tag isn't the MAC of its message.
+///
+/// If this error is found, the message cannot be considered authentic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InvalidMac;
+
+impl core::fmt::Display for InvalidMac {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("MAC did not match message")
+    }
+}
+
+impl core::error::Error for InvalidMac {}
 
 pub struct Hmac<const H_LEN: usize, const B_LEN: usize, H>
 where
@@ -13,6 +28,15 @@ where
     H: BlockHasher<H_LEN, B_LEN>,
 {
     pub fn new(key: &[u8]) -> Self {
+        // keys longer than the block size are replaced by their hash
+        let hashed_key;
+        let key = if key.len() > B_LEN {
+            hashed_key = H::hash(key);
+            &hashed_key[..]
+        } else {
+            key
+        };
+
         let mut ipad = [0x36; B_LEN];
         let mut opad = [0x5c; B_LEN];
         for ((ipad_byte, opad_byte), key_byte) in ipad.iter_mut().zip(opad.iter_mut()).zip(key) {
@@ -29,6 +53,10 @@ where
         self.state.update(block);
     }
 
+    pub fn update_with(&mut self, msg: &[u8]) {
+        self.state.update_with(msg);
+    }
+
     pub fn finish_with(self, msg: &[u8]) -> [u8; H_LEN] {
         let inner_hash = self.state.finish_with(msg);
         outer_finish::<H_LEN, B_LEN, H>(&self.opad, &inner_hash)
@@ -43,14 +71,25 @@ where
         let state = Self::new(key);
         state.finish_with(msg)
     }
-}
 
-impl<const H_LEN: usize, const B_LEN: usize, H> Hmac<H_LEN, B_LEN, BufHasher<H_LEN, B_LEN, H>>
-where
-    H: BlockHasher<H_LEN, B_LEN>,
-{
-    pub fn update_with(&mut self, msg: &[u8]) {
-        self.state.update_with(msg)
+    /// Checks that `tag` is the MAC of everything added so far.
+    ///
+    /// The comparison takes the same time wherever `tag` differs, and `tag` must be the full
+    /// `H_LEN` bytes.
+    pub fn finish_verify(self, tag: &[u8]) -> Result<(), InvalidMac> {
+        match ct_eq(&self.finish(), tag) {
+            true => Ok(()),
+            false => Err(InvalidMac),
+        }
+    }
+
+    /// Checks that `tag` is the MAC of `msg` under `key`.
+    ///
+    /// This is the one-shot version of [`Self::finish_verify()`].
+    pub fn verify(key: &[u8], msg: &[u8], tag: &[u8]) -> Result<(), InvalidMac> {
+        let mut state = Self::new(key);
+        state.update_with(msg);
+        state.finish_verify(tag)
     }
 }
 
@@ -69,7 +108,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::hash::{Sha256, Sha512};
+    use crate::hash::{Sha256, Sha384, Sha512};
     // test vectors from https://datatracker.ietf.org/doc/html/rfc4231
 
     #[test]
@@ -88,6 +127,16 @@ mod tests {
             Hmac::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>::auth(&key, &msg),
             mac_sha256
         );
+        let mac_sha384 = [
+            0xaf, 0xd0, 0x39, 0x44, 0xd8, 0x48, 0x95, 0x62, 0x6b, 0x08, 0x25, 0xf4, 0xab, 0x46,
+            0x90, 0x7f, 0x15, 0xf9, 0xda, 0xdb, 0xe4, 0x10, 0x1e, 0xc6, 0x82, 0xaa, 0x03, 0x4c,
+            0x7c, 0xeb, 0xc5, 0x9c, 0xfa, 0xea, 0x9e, 0xa9, 0x07, 0x6e, 0xde, 0x7f, 0x4a, 0xf1,
+            0x52, 0xe8, 0xb2, 0xfa, 0x9c, 0xb6,
+        ];
+        assert_eq!(
+            Hmac::<{ Sha384::HASH_SIZE }, { Sha384::BLOCK_SIZE }, Sha384>::auth(&key, &msg),
+            mac_sha384
+        );
         let mac_sha512 = [
             0x87, 0xaa, 0x7c, 0xde, 0xa5, 0xef, 0x61, 0x9d, 0x4f, 0xf0, 0xb4, 0x24, 0x1a, 0x1d,
             0x6c, 0xb0, 0x23, 0x79, 0xf4, 0xe2, 0xce, 0x4e, 0xc2, 0x78, 0x7a, 0xd0, 0xb3, 0x05,
@@ -119,6 +168,16 @@ mod tests {
             Hmac::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>::auth(&key, &msg),
             mac_sha256
         );
+        let mac_sha384 = [
+            0x88, 0x06, 0x26, 0x08, 0xd3, 0xe6, 0xad, 0x8a, 0x0a, 0xa2, 0xac, 0xe0, 0x14, 0xc8,
+            0xa8, 0x6f, 0x0a, 0xa6, 0x35, 0xd9, 0x47, 0xac, 0x9f, 0xeb, 0xe8, 0x3e, 0xf4, 0xe5,
+            0x59, 0x66, 0x14, 0x4b, 0x2a, 0x5a, 0xb3, 0x9d, 0xc1, 0x38, 0x14, 0xb9, 0x4e, 0x3a,
+            0xb6, 0xe1, 0x01, 0xa3, 0x4f, 0x27,
+        ];
+        assert_eq!(
+            Hmac::<{ Sha384::HASH_SIZE }, { Sha384::BLOCK_SIZE }, Sha384>::auth(&key, &msg),
+            mac_sha384
+        );
         let mac_sha512 = [
             0xfa, 0x73, 0xb0, 0x08, 0x9d, 0x56, 0xa2, 0x84, 0xef, 0xb0, 0xf0, 0x75, 0x6c, 0x89,
             0x0b, 0xe9, 0xb1, 0xb5, 0xdb, 0xdd, 0x8e, 0xe8, 0x1a, 0x36, 0x55, 0xf8, 0x3e, 0x33,
@@ -132,6 +191,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn long_key() {
+        let key = [0xaa; 131];
+        let msg = b"Test Using Larger Than Block-Size Key - Hash Key First";
+        let mac_sha256 = [
+            0x60, 0xe4, 0x31, 0x59, 0x1e, 0xe0, 0xb6, 0x7f, 0x0d, 0x8a, 0x26, 0xaa, 0xcb, 0xf5,
+            0xb7, 0x7f, 0x8e, 0x0b, 0xc6, 0x21, 0x37, 0x28, 0xc5, 0x14, 0x05, 0x46, 0x04, 0x0f,
+            0x0e, 0xe3, 0x7f, 0x54,
+        ];
+        assert_eq!(
+            Hmac::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>::auth(&key, msg),
+            mac_sha256
+        );
+    }
+
     #[test]
     fn update_with() {
         let msg = [
@@ -149,11 +223,7 @@ mod tests {
             0x81, 0xa7, 0x29, 0x59, 0x09, 0x8b, 0x3e, 0xf8, 0xc1, 0x22, 0xd9, 0x63, 0x55, 0x14,
             0xce, 0xd5, 0x65, 0xfe,
         ];
-        let mut hmac = Hmac::<
-            { Sha256::HASH_SIZE },
-            { Sha256::BLOCK_SIZE },
-            BufHasher<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>,
-        >::new(&key);
+        let mut hmac = Hmac::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>::new(&key);
         for block in msg.chunks(3) {
             hmac.update_with(block);
         }
@@ -166,11 +236,7 @@ mod tests {
             0x85, 0xa4, 0x7e, 0x67, 0xc8, 0x07, 0xb9, 0x46, 0xa3, 0x37, 0xbe, 0xe8, 0x94, 0x26,
             0x74, 0x27, 0x88, 0x59, 0xe1, 0x32, 0x92, 0xfb,
         ];
-        let mut hmac = Hmac::<
-            { Sha512::HASH_SIZE },
-            { Sha512::BLOCK_SIZE },
-            BufHasher<{ Sha512::HASH_SIZE }, { Sha512::BLOCK_SIZE }, Sha512>,
-        >::new(&key);
+        let mut hmac = Hmac::<{ Sha512::HASH_SIZE }, { Sha512::BLOCK_SIZE }, Sha512>::new(&key);
         for block in msg.chunks(3) {
             hmac.update_with(block);
         }
@@ -178,4 +244,29 @@ mod tests {
         assert_eq!(mac, mac_sha512);
         assert_eq!(mac, mac_sha512);
     }
+
+    #[test]
+    fn verify() {
+        type HmacSha256 = Hmac<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>;
+        let key = [0x0b; 20];
+        let msg = b"Hi There";
+        let mut tag = [
+            0xb0, 0x34, 0x4c, 0x61, 0xd8, 0xdb, 0x38, 0x53, 0x5c, 0xa8, 0xaf, 0xce, 0xaf, 0x0b,
+            0xf1, 0x2b, 0x88, 0x1d, 0xc2, 0x00, 0xc9, 0x83, 0x3d, 0xa7, 0x26, 0xe9, 0x37, 0x6c,
+            0x2e, 0x32, 0xcf, 0xf7,
+        ];
+        assert_eq!(HmacSha256::verify(&key, msg, &tag), Ok(()));
+
+        let mut hmac = HmacSha256::new(&key);
+        for chunk in msg.chunks(3) {
+            hmac.update_with(chunk);
+        }
+        assert_eq!(hmac.finish_verify(&tag), Ok(()));
+
+        // truncated tags aren't accepted
+        assert_eq!(HmacSha256::verify(&key, msg, &tag[..16]), Err(InvalidMac));
+        assert_eq!(HmacSha256::verify(&key, b"Hi there", &tag), Err(InvalidMac));
+        tag[31] ^= 1;
+        assert_eq!(HmacSha256::verify(&key, msg, &tag), Err(InvalidMac));
+    }
 }
@@ -4,13 +4,24 @@
 //! WARNING: This code has not been audited. Use it at your own risk.
 //! </div>
 //#![warn(missing_docs, clippy::cargo)]
-#![warn(clippy::cargo)]
+#![warn(clippy::cargo, clippy::undocumented_unsafe_blocks)]
 #![no_std]
 
 pub mod aead;
+pub mod argon2;
+pub mod asn1;
 pub mod big_int;
+pub mod ct;
 pub mod ec;
+pub mod ecies;
+pub mod encoding;
+pub mod ff1;
 pub mod finite_field;
 pub mod hash;
 pub mod hkdf;
 pub mod hmac;
+pub mod otp;
+pub mod pbkdf2;
+pub mod scrypt;
+pub mod self_test;
+pub mod xts;
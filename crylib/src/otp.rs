@@ -0,0 +1,210 @@
+//! HMAC-based one-time passwords, as defined in [RFC 4226] (HOTP) and [RFC 6238] (TOTP).
+//!
+//! Most authenticator apps use HMAC-SHA-1 with 6 digits and a 30 second step, which is why
+//! [`Sha1`](crate::hash::Sha1) is provided. RFC 6238 also allows SHA-256 and SHA-512.
+//!
+//! # Examples
+//!
+//! ```
+//! use crylib::hash::Sha1;
+//! use crylib::otp;
+//!
+//! let key = b"12345678901234567890";
+//! let code = otp::totp::<{ Sha1::HASH_SIZE }, { Sha1::BLOCK_SIZE }, Sha1>(key, 59, 30, 8);
+//! assert_eq!(code, 94287082);
+//!
+//! // accept codes from one step either side to allow for clock drift
+//! assert!(otp::verify_totp::<{ Sha1::HASH_SIZE }, { Sha1::BLOCK_SIZE }, Sha1>(
+//!     key, 75, 30, 1, 8, code
+//! ));
+//! ```
+//!
+//! [RFC 4226]: https://datatracker.ietf.org/doc/html/rfc4226
+//! [RFC 6238]: https://datatracker.ietf.org/doc/html/rfc6238
+use crate::ct::ct_eq;
+use crate::hash::BlockHasher;
+use crate::hmac::Hmac;
+
+/// The maximum number of digits a code can have.
+///
+/// Dynamic truncation yields a 31-bit number, so more than 9 digits would be biased.
+pub const MAX_DIGITS: u32 = 9;
+
+/// Generates the HOTP code for `counter`.
+///
+/// # Panics
+///
+/// The function will panic if `digits` is 0 or greater than [`MAX_DIGITS`],
+/// or if `H_LEN` is less than 20.
+pub fn hotp<const H_LEN: usize, const B_LEN: usize, H: BlockHasher<H_LEN, B_LEN>>(
+    key: &[u8],
+    counter: u64,
+    digits: u32,
+) -> u32 {
+    assert!(
+        (1..=MAX_DIGITS).contains(&digits),
+        "HOTP codes must have between 1 and 9 digits"
+    );
+    let mac = Hmac::<H_LEN, B_LEN, H>::auth(key, &counter.to_be_bytes());
+
+    // dynamic truncation; RFC 4226 always takes the offset from the last byte
+    let offset = (mac[H_LEN - 1] & 0xf) as usize;
+    let truncated = u32::from_be_bytes(mac[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+    truncated % 10u32.pow(digits)
+}
+
+/// Generates the TOTP code for `unix_time`, in seconds.
+///
+/// `step` is the length of each time step in seconds, usually 30.
+/// Callers with a non-zero epoch (`T0`) should subtract it from `unix_time` first.
+///
+/// # Panics
+///
+/// The function will panic if `step` is 0 or for any of the reasons [`hotp`] panics.
+pub fn totp<const H_LEN: usize, const B_LEN: usize, H: BlockHasher<H_LEN, B_LEN>>(
+    key: &[u8],
+    unix_time: u64,
+    step: u64,
+    digits: u32,
+) -> u32 {
+    hotp::<H_LEN, B_LEN, H>(key, unix_time / step, digits)
+}
+
+/// Checks `code` against the counters `counter..=counter + look_ahead`.
+///
+/// Returns the counter that matched, so the caller can resynchronize by storing the next
+/// counter. Every counter in the window is checked, even after a match, and each comparison is
+/// constant-time.
+///
+/// # Panics
+///
+/// The function will panic for any of the reasons [`hotp`] panics.
+pub fn verify_hotp<const H_LEN: usize, const B_LEN: usize, H: BlockHasher<H_LEN, B_LEN>>(
+    key: &[u8],
+    counter: u64,
+    look_ahead: u64,
+    digits: u32,
+    code: u32,
+) -> Option<u64> {
+    let mut matched = None;
+    for counter in counter..=counter.saturating_add(look_ahead) {
+        let expected = hotp::<H_LEN, B_LEN, H>(key, counter, digits);
+        if ct_eq(&expected.to_be_bytes(), &code.to_be_bytes()) && matched.is_none() {
+            matched = Some(counter);
+        }
+    }
+    matched
+}
+
+/// Checks `code` against the time steps within `skew` steps of `unix_time`.
+///
+/// Every step in the window is checked, even after a match.
+///
+/// <div class="warning">
+/// WARNING: a code remains valid for the whole window.
+/// Callers should remember the last accepted step and reject codes that reuse it.
+/// </div>
+///
+/// # Panics
+///
+/// The function will panic for any of the reasons [`totp`] panics.
+pub fn verify_totp<const H_LEN: usize, const B_LEN: usize, H: BlockHasher<H_LEN, B_LEN>>(
+    key: &[u8],
+    unix_time: u64,
+    step: u64,
+    skew: u64,
+    digits: u32,
+    code: u32,
+) -> bool {
+    let counter = unix_time / step;
+    let first = counter.saturating_sub(skew);
+    let look_ahead = counter.saturating_add(skew) - first;
+    verify_hotp::<H_LEN, B_LEN, H>(key, first, look_ahead, digits, code).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hash::{Sha1, Sha256, Sha512};
+
+    const SHA1_KEY: &[u8] = b"12345678901234567890";
+
+    fn hotp_sha1(counter: u64, digits: u32) -> u32 {
+        super::hotp::<{ Sha1::HASH_SIZE }, { Sha1::BLOCK_SIZE }, Sha1>(SHA1_KEY, counter, digits)
+    }
+
+    // test vectors from https://datatracker.ietf.org/doc/html/rfc4226#appendix-D
+    #[test]
+    fn hotp() {
+        let codes = [
+            755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489,
+        ];
+        for (counter, code) in codes.into_iter().enumerate() {
+            assert_eq!(hotp_sha1(counter as u64, 6), code);
+        }
+    }
+
+    // test vectors from https://datatracker.ietf.org/doc/html/rfc6238#appendix-B
+    #[test]
+    fn totp() {
+        let sha256_key = b"12345678901234567890123456789012";
+        let sha512_key = b"1234567890123456789012345678901234567890123456789012345678901234";
+        let codes = [
+            (59, 94287082, 46119246, 90693936),
+            (1111111109, 7081804, 68084774, 25091201),
+            (20000000000, 65353130, 77737706, 47863826),
+        ];
+        for (time, sha1, sha256, sha512) in codes {
+            assert_eq!(
+                super::totp::<{ Sha1::HASH_SIZE }, { Sha1::BLOCK_SIZE }, Sha1>(
+                    SHA1_KEY, time, 30, 8
+                ),
+                sha1
+            );
+            assert_eq!(
+                super::totp::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>(
+                    sha256_key, time, 30, 8
+                ),
+                sha256
+            );
+            assert_eq!(
+                super::totp::<{ Sha512::HASH_SIZE }, { Sha512::BLOCK_SIZE }, Sha512>(
+                    sha512_key, time, 30, 8
+                ),
+                sha512
+            );
+        }
+    }
+
+    #[test]
+    fn verify_hotp() {
+        let verify = |counter, look_ahead, code| {
+            super::verify_hotp::<{ Sha1::HASH_SIZE }, { Sha1::BLOCK_SIZE }, Sha1>(
+                SHA1_KEY, counter, look_ahead, 6, code,
+            )
+        };
+        assert_eq!(verify(0, 0, 755224), Some(0));
+        assert_eq!(verify(2, 3, 338314), Some(4));
+        assert_eq!(verify(2, 1, 338314), None);
+        assert_eq!(verify(5, 3, 755224), None);
+    }
+
+    #[test]
+    fn verify_totp() {
+        let verify = |time, skew| {
+            super::verify_totp::<{ Sha1::HASH_SIZE }, { Sha1::BLOCK_SIZE }, Sha1>(
+                SHA1_KEY, time, 30, skew, 8, 94287082,
+            )
+        };
+        assert!(verify(59, 0));
+        assert!(!verify(60, 0));
+        assert!(verify(60, 1));
+        assert!(verify(0, 1));
+        assert!(!verify(90, 1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn too_many_digits() {
+        hotp_sha1(0, 10);
+    }
+}
@@ -0,0 +1,98 @@
+//! Password-Based Key Derivation Function 2, as defined in [RFC 8018].
+//!
+//! PBKDF2 stretches a low-entropy password into a key of arbitrary length using repeated HMAC
+//! invocations. The iteration count should be chosen to be as high as is tolerable.
+//!
+//! [RFC 8018]: https://datatracker.ietf.org/doc/html/rfc8018#section-5.2
+use crate::hash::BlockHasher;
+use crate::hmac::Hmac;
+
+/// Derives `out.len()` bytes of key material from `password` and `salt`.
+///
+/// # Panics
+/// This function panics if `iterations` is `0`, or if `out` is longer than `(2^32 - 1) * H_LEN`
+/// bytes, since the blocks of the key are numbered with 32-bit integers.
+pub fn derive<const H_LEN: usize, const B_LEN: usize, H: BlockHasher<H_LEN, B_LEN>>(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    out: &mut [u8],
+) {
+    assert_ne!(iterations, 0);
+    // RFC 8018 section 5.2, step 1
+    assert!(
+        out.len().div_ceil(H_LEN) <= u32::MAX as usize,
+        "derived key too long"
+    );
+
+    for (i, key_chunk) in out.chunks_mut(H_LEN).enumerate() {
+        let mut hmac = Hmac::<H_LEN, B_LEN, H>::new(password);
+        hmac.update_with(salt);
+        let mut mac = hmac.finish_with(&(i as u32 + 1).to_be_bytes());
+
+        let mut block = mac;
+        for _ in 1..iterations {
+            mac = Hmac::<H_LEN, B_LEN, H>::auth(password, &mac);
+            for (block_byte, mac_byte) in block.iter_mut().zip(mac) {
+                *block_byte ^= mac_byte;
+            }
+        }
+        key_chunk.copy_from_slice(&block[..key_chunk.len()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hash::{Sha256, Sha512};
+
+    #[test]
+    fn derive_sha256() {
+        // test vector from https://datatracker.ietf.org/doc/html/rfc7914#section-11
+        let key = [
+            0x55, 0xac, 0x04, 0x6e, 0x56, 0xe3, 0x08, 0x9f, 0xec, 0x16, 0x91, 0xc2, 0x25, 0x44,
+            0xb6, 0x05, 0xf9, 0x41, 0x85, 0x21, 0x6d, 0xde, 0x04, 0x65, 0xe6, 0x8b, 0x9d, 0x57,
+            0xc2, 0x0d, 0xac, 0xbc, 0x49, 0xca, 0x9c, 0xcc, 0xf1, 0x79, 0xb6, 0x45, 0x99, 0x16,
+            0x64, 0xb3, 0x9d, 0x77, 0xef, 0x31, 0x7c, 0x71, 0xb8, 0x45, 0xb1, 0xe3, 0x0b, 0xd5,
+            0x09, 0x11, 0x20, 0x41, 0xd3, 0xa1, 0x97, 0x83,
+        ];
+        let mut out = [0; 64];
+        super::derive::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>(
+            b"passwd", b"salt", 1, &mut out,
+        );
+        assert_eq!(out, key);
+
+        let key = [
+            0x4d, 0xdc, 0xd8, 0xf6, 0x0b, 0x98, 0xbe, 0x21, 0x83, 0x0c, 0xee, 0x5e, 0xf2, 0x27,
+            0x01, 0xf9, 0x64, 0x1a, 0x44, 0x18, 0xd0, 0x4c, 0x04, 0x14, 0xae, 0xff, 0x08, 0x87,
+            0x6b, 0x34, 0xab, 0x56, 0xa1, 0xd4, 0x25, 0xa1, 0x22, 0x58, 0x33, 0x54, 0x9a, 0xdb,
+            0x84, 0x1b, 0x51, 0xc9, 0xb3, 0x17, 0x6a, 0x27, 0x2b, 0xde, 0xbb, 0xa1, 0xd0, 0x78,
+            0x47, 0x8f, 0x62, 0xb3, 0x97, 0xf3, 0x3c, 0x8d,
+        ];
+        super::derive::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>(
+            b"Password",
+            b"NaCl",
+            80000,
+            &mut out,
+        );
+        assert_eq!(out, key);
+    }
+
+    #[test]
+    fn derive_sha512() {
+        let key = [
+            0xe1, 0xd9, 0xc1, 0x6a, 0xa6, 0x81, 0x70, 0x8a, 0x45, 0xf5, 0xc7, 0xc4, 0xe2, 0x15,
+            0xce, 0xb6, 0x6e, 0x01, 0x1a, 0x2e, 0x9f, 0x00, 0x40, 0x71, 0x3f, 0x18, 0xae, 0xfd,
+            0xb8, 0x66, 0xd5, 0x3c, 0xf7, 0x6c, 0xab, 0x28, 0x68, 0xa3, 0x9b, 0x9f, 0x78, 0x40,
+            0xed, 0xce, 0x4f, 0xef, 0x5a, 0x82, 0xbe, 0x67, 0x33, 0x5c, 0x77, 0xa6, 0x06, 0x8e,
+            0x04, 0x11, 0x27, 0x54, 0xf2, 0x7c, 0xcf, 0x4e,
+        ];
+        let mut out = [0; 64];
+        super::derive::<{ Sha512::HASH_SIZE }, { Sha512::BLOCK_SIZE }, Sha512>(
+            b"password",
+            b"salt",
+            2,
+            &mut out,
+        );
+        assert_eq!(out, key);
+    }
+}
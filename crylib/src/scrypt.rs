@@ -0,0 +1,282 @@
+//! The scrypt password-based key derivation function, as defined in [RFC 7914].
+//!
+//! Unlike [`pbkdf2`](crate::pbkdf2), scrypt is memory-hard: computing it requires
+//! `128 * r * N` bytes of working memory, which makes large-scale hardware attacks expensive.
+//!
+//! Because this crate does not allocate, the caller provides that memory as a scratch buffer of
+//! at least [`Params::scratch_len()`] bytes.
+//!
+//! # Examples
+//!
+//! ```
+//! use crylib::scrypt::{self, Params};
+//!
+//! let params = Params::new(4, 1, 1).unwrap();
+//! let mut scratch = vec![0; params.scratch_len()];
+//! let mut key = [0; 32];
+//!
+//! scrypt::derive(b"password", b"salt", &params, &mut scratch, &mut key).unwrap();
+//! ```
+//!
+//! [RFC 7914]: https://datatracker.ietf.org/doc/html/rfc7914
+use crate::hash::Sha256;
+use crate::pbkdf2;
+
+/// The size of a single Salsa20/8 block.
+const SALSA_BLOCK_SIZE: usize = 64;
+
+/// The cost parameters of scrypt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Params {
+    log_n: u8,
+    r: u32,
+    p: u32,
+}
+
+impl Params {
+    /// Creates a new set of parameters with a CPU/memory cost of `2^log_n`, a block size of `r`,
+    /// and a parallelization factor of `p`.
+    ///
+    /// Returns an [`Err`] if the parameters are out of the range allowed by RFC 7914 or if the
+    /// required scratch space would not fit in memory.
+    pub const fn new(log_n: u8, r: u32, p: u32) -> Result<Self, InvalidParams> {
+        if log_n == 0 || log_n as u32 >= usize::BITS || r == 0 || p == 0 {
+            return Err(InvalidParams);
+        }
+        if (r as u64) * (p as u64) >= 1 << 30 {
+            return Err(InvalidParams);
+        }
+        // N must be less than 2^(128 * r / 8)
+        if (log_n as u64) >= 16 * r as u64 {
+            return Err(InvalidParams);
+        }
+        let params = Self { log_n, r, p };
+        match params.checked_scratch_len() {
+            Some(_) => Ok(params),
+            None => Err(InvalidParams),
+        }
+    }
+
+    /// The number of bytes of scratch space required by [`derive`].
+    pub const fn scratch_len(&self) -> usize {
+        match self.checked_scratch_len() {
+            Some(len) => len,
+            // `Params::new` rejects parameters that overflow
+            None => unreachable!(),
+        }
+    }
+
+    const fn checked_scratch_len(&self) -> Option<usize> {
+        // B (p blocks), V (N blocks), and two blocks for BlockMix
+        let Some(num_blocks) = (1usize << self.log_n).checked_add(self.p as usize + 2) else {
+            return None;
+        };
+        num_blocks.checked_mul(self.block_len())
+    }
+
+    /// The length of a single ROMix block, `128 * r`.
+    const fn block_len(&self) -> usize {
+        2 * SALSA_BLOCK_SIZE * self.r as usize
+    }
+}
+
+/// The error that is returned when scrypt parameters are out of range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InvalidParams;
+
+impl core::fmt::Display for InvalidParams {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("scrypt parameters are out of range")
+    }
+}
+
+impl core::error::Error for InvalidParams {}
+
+/// The error that is returned when the scratch buffer passed to [`derive`] is too small.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ScratchTooSmall;
+
+impl core::fmt::Display for ScratchTooSmall {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("scratch buffer is smaller than Params::scratch_len()")
+    }
+}
+
+impl core::error::Error for ScratchTooSmall {}
+
+/// Derives `out.len()` bytes of key material from `password` and `salt`.
+///
+/// `scratch` must be at least [`params.scratch_len()`](Params::scratch_len) bytes long. Its
+/// contents are overwritten, and it is left holding intermediate values derived from the password,
+/// so callers should clear it afterwards if it is reused.
+pub fn derive(
+    password: &[u8],
+    salt: &[u8],
+    params: &Params,
+    scratch: &mut [u8],
+    out: &mut [u8],
+) -> Result<(), ScratchTooSmall> {
+    let block_len = params.block_len();
+    if scratch.len() < params.scratch_len() {
+        return Err(ScratchTooSmall);
+    }
+
+    let (b, rest) = scratch.split_at_mut(params.p as usize * block_len);
+    let (v, rest) = rest.split_at_mut(block_len << params.log_n);
+    let (x, rest) = rest.split_at_mut(block_len);
+    let y = &mut rest[..block_len];
+
+    pbkdf2::derive::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>(password, salt, 1, b);
+
+    for block in b.chunks_exact_mut(block_len) {
+        ro_mix(block, v, x, y, params.log_n);
+    }
+
+    pbkdf2::derive::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>(password, b, 1, out);
+    Ok(())
+}
+
+/// The sequential memory-hard function at the core of scrypt.
+///
+/// `v` holds `2^log_n` blocks; `x` and `y` hold one block each.
+fn ro_mix<'a>(block: &mut [u8], v: &mut [u8], mut x: &'a mut [u8], mut y: &'a mut [u8], log_n: u8) {
+    let block_len = block.len();
+    let mask = (1u64 << log_n) - 1;
+
+    x.copy_from_slice(block);
+    for v_block in v.chunks_exact_mut(block_len) {
+        v_block.copy_from_slice(x);
+        block_mix(x, y);
+        core::mem::swap(&mut x, &mut y);
+    }
+
+    for _ in 0..1u64 << log_n {
+        let j = (integerify(x) & mask) as usize;
+        for (x_byte, v_byte) in x.iter_mut().zip(&v[j * block_len..][..block_len]) {
+            *x_byte ^= v_byte;
+        }
+        block_mix(x, y);
+        core::mem::swap(&mut x, &mut y);
+    }
+    block.copy_from_slice(x);
+}
+
+/// Interprets the first 8 bytes of the last Salsa20/8 block of `block` as a little-endian integer.
+fn integerify(block: &[u8]) -> u64 {
+    let last = &block[block.len() - SALSA_BLOCK_SIZE..];
+    // we can safely unwrap because the slice is guaranteed to have a length of 8
+    u64::from_le_bytes(last[..8].try_into().unwrap())
+}
+
+/// The scryptBlockMix function, writing the shuffled output to `output`.
+fn block_mix(input: &[u8], output: &mut [u8]) {
+    let half = input.len() / 2;
+    let mut x: [u8; SALSA_BLOCK_SIZE] = input[input.len() - SALSA_BLOCK_SIZE..].try_into().unwrap();
+
+    for (i, block) in input.chunks_exact(SALSA_BLOCK_SIZE).enumerate() {
+        for (x_byte, block_byte) in x.iter_mut().zip(block) {
+            *x_byte ^= block_byte;
+        }
+        salsa20_8(&mut x);
+
+        // even blocks go in the first half, odd blocks in the second
+        let pos = (i / 2) * SALSA_BLOCK_SIZE + (i % 2) * half;
+        output[pos..][..SALSA_BLOCK_SIZE].copy_from_slice(&x);
+    }
+}
+
+/// The Salsa20/8 core function.
+fn salsa20_8(block: &mut [u8; SALSA_BLOCK_SIZE]) {
+    let mut input = [0u32; 16];
+    // TODO: use `array_chunks` once stabilized
+    for (word, chunk) in input.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes(chunk.try_into().unwrap());
+    }
+
+    let mut x = input;
+    for _ in 0..4 {
+        // columns
+        quarter_round(&mut x, 0, 4, 8, 12);
+        quarter_round(&mut x, 5, 9, 13, 1);
+        quarter_round(&mut x, 10, 14, 2, 6);
+        quarter_round(&mut x, 15, 3, 7, 11);
+        // rows
+        quarter_round(&mut x, 0, 1, 2, 3);
+        quarter_round(&mut x, 5, 6, 7, 4);
+        quarter_round(&mut x, 10, 11, 8, 9);
+        quarter_round(&mut x, 15, 12, 13, 14);
+    }
+
+    for ((chunk, word), input_word) in block.chunks_exact_mut(4).zip(x).zip(input) {
+        chunk.copy_from_slice(&word.wrapping_add(input_word).to_le_bytes());
+    }
+}
+
+#[inline]
+fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+    x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+    x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+    x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::vec;
+
+    use super::{InvalidParams, Params, ScratchTooSmall};
+
+    // test vectors from https://datatracker.ietf.org/doc/html/rfc7914#section-12
+
+    #[test]
+    fn derive() {
+        let key = [
+            0x77, 0xd6, 0x57, 0x62, 0x38, 0x65, 0x7b, 0x20, 0x3b, 0x19, 0xca, 0x42, 0xc1, 0x8a,
+            0x04, 0x97, 0xf1, 0x6b, 0x48, 0x44, 0xe3, 0x07, 0x4a, 0xe8, 0xdf, 0xdf, 0xfa, 0x3f,
+            0xed, 0xe2, 0x14, 0x42, 0xfc, 0xd0, 0x06, 0x9d, 0xed, 0x09, 0x48, 0xf8, 0x32, 0x6a,
+            0x75, 0x3a, 0x0f, 0xc8, 0x1f, 0x17, 0xe8, 0xd3, 0xe0, 0xfb, 0x2e, 0x0d, 0x36, 0x28,
+            0xcf, 0x35, 0xe2, 0x0c, 0x38, 0xd1, 0x89, 0x06,
+        ];
+        let params = Params::new(4, 1, 1).unwrap();
+        let mut scratch = [0; 128 * (16 + 1 + 2)];
+        assert_eq!(params.scratch_len(), scratch.len());
+
+        let mut out = [0; 64];
+        super::derive(b"", b"", &params, &mut scratch, &mut out).unwrap();
+        assert_eq!(out, key);
+
+        let key = [
+            0xfd, 0xba, 0xbe, 0x1c, 0x9d, 0x34, 0x72, 0x00, 0x78, 0x56, 0xe7, 0x19, 0x0d, 0x01,
+            0xe9, 0xfe, 0x7c, 0x6a, 0xd7, 0xcb, 0xc8, 0x23, 0x78, 0x30, 0xe7, 0x73, 0x76, 0x63,
+            0x4b, 0x37, 0x31, 0x62, 0x2e, 0xaf, 0x30, 0xd9, 0x2e, 0x22, 0xa3, 0x88, 0x6f, 0xf1,
+            0x09, 0x27, 0x9d, 0x98, 0x30, 0xda, 0xc7, 0x27, 0xaf, 0xb9, 0x4a, 0x83, 0xee, 0x6d,
+            0x83, 0x60, 0xcb, 0xdf, 0xa2, 0xcc, 0x06, 0x40,
+        ];
+        let params = Params::new(10, 8, 16).unwrap();
+        let mut scratch = vec![0; params.scratch_len()];
+        super::derive(b"password", b"NaCl", &params, &mut scratch, &mut out).unwrap();
+        assert_eq!(out, key);
+    }
+
+    #[test]
+    fn invalid_params() {
+        assert_eq!(Params::new(0, 1, 1), Err(InvalidParams));
+        assert_eq!(Params::new(4, 0, 1), Err(InvalidParams));
+        assert_eq!(Params::new(4, 1, 0), Err(InvalidParams));
+        assert_eq!(Params::new(16, 1, 1), Err(InvalidParams));
+        assert_eq!(Params::new(4, 1 << 15, 1 << 15), Err(InvalidParams));
+        assert_eq!(Params::new(60, 8, 1), Err(InvalidParams));
+    }
+
+    #[test]
+    fn scratch_too_small() {
+        let params = Params::new(4, 1, 1).unwrap();
+        let mut scratch = [0; 128];
+        let mut out = [0; 32];
+        assert_eq!(
+            super::derive(b"", b"", &params, &mut scratch, &mut out),
+            Err(ScratchTooSmall)
+        );
+    }
+}
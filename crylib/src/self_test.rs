@@ -0,0 +1,205 @@
+//! Power-on self-tests.
+//!
+//! [`self_test`] runs a known-answer test for each core primitive and reports which ones produced
+//! the expected output. It is meant for deployments that must check the implementation at
+//! startup, in the style of FIPS 140 power-on self-tests. Nothing runs it automatically.
+//!
+//! crylib has no DRBG: randomness is always supplied by the caller, so there is no generator to
+//! test here.
+//!
+//! # Examples
+//!
+//! ```
+//! let report = crylib::self_test::self_test();
+//! assert!(report.passed());
+//! ```
+use crate::aead::gcm::{Aes128, Gcm};
+use crate::aead::Aead;
+use crate::big_int::UBigInt;
+use crate::ec::{ecdsa, EllipticCurve, Secp256r1};
+use crate::finite_field::FieldElement;
+use crate::hash::{Hasher, Sha256, Sha512};
+use crate::hkdf;
+use crate::hmac::Hmac;
+
+/// The result of each known-answer test. `true` means the test passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Report {
+    pub aes_gcm: bool,
+    pub sha256: bool,
+    pub sha512: bool,
+    pub hmac: bool,
+    pub hkdf: bool,
+    pub ecdsa: bool,
+}
+
+impl Report {
+    /// Returns whether every test passed.
+    pub const fn passed(&self) -> bool {
+        self.aes_gcm && self.sha256 && self.sha512 && self.hmac && self.hkdf && self.ecdsa
+    }
+}
+
+/// Runs every known-answer test.
+pub fn self_test() -> Report {
+    Report {
+        aes_gcm: aes_gcm(),
+        sha256: sha256(),
+        sha512: sha512(),
+        hmac: hmac(),
+        hkdf: hkdf(),
+        ecdsa: ecdsa(),
+    }
+}
+
+// test case 4 from the GCM specification
+fn aes_gcm() -> bool {
+    let key = [
+        0xfe, 0xff, 0xe9, 0x92, 0x86, 0x65, 0x73, 0x1c, 0x6d, 0x6a, 0x8f, 0x94, 0x67, 0x30, 0x83,
+        0x08,
+    ];
+    let init_vector = [
+        0xca, 0xfe, 0xba, 0xbe, 0xfa, 0xce, 0xdb, 0xad, 0xde, 0xca, 0xf8, 0x88,
+    ];
+    let plain_text = [
+        0xd9, 0x31, 0x32, 0x25, 0xf8, 0x84, 0x06, 0xe5, 0xa5, 0x59, 0x09, 0xc5, 0xaf, 0xf5, 0x26,
+        0x9a, 0x86, 0xa7, 0xa9, 0x53, 0x15, 0x34, 0xf7, 0xda, 0x2e, 0x4c, 0x30, 0x3d, 0x8a, 0x31,
+        0x8a, 0x72, 0x1c, 0x3c, 0x0c, 0x95, 0x95, 0x68, 0x09, 0x53, 0x2f, 0xcf, 0x0e, 0x24, 0x49,
+        0xa6, 0xb5, 0x25, 0xb1, 0x6a, 0xed, 0xf5, 0xaa, 0x0d, 0xe6, 0x57, 0xba, 0x63, 0x7b, 0x39,
+    ];
+    let add_data = [
+        0xfe, 0xed, 0xfa, 0xce, 0xde, 0xad, 0xbe, 0xef, 0xfe, 0xed, 0xfa, 0xce, 0xde, 0xad, 0xbe,
+        0xef, 0xab, 0xad, 0xda, 0xd2,
+    ];
+    let cipher_text = [
+        0x42, 0x83, 0x1e, 0xc2, 0x21, 0x77, 0x74, 0x24, 0x4b, 0x72, 0x21, 0xb7, 0x84, 0xd0, 0xd4,
+        0x9c, 0xe3, 0xaa, 0x21, 0x2f, 0x2c, 0x02, 0xa4, 0xe0, 0x35, 0xc1, 0x7e, 0x23, 0x29, 0xac,
+        0xa1, 0x2e, 0x21, 0xd5, 0x14, 0xb2, 0x54, 0x66, 0x93, 0x1c, 0x7d, 0x8f, 0x6a, 0x5a, 0xac,
+        0x84, 0xaa, 0x05, 0x1b, 0xa3, 0x0b, 0x39, 0x6a, 0x0a, 0xac, 0x97, 0x3d, 0x58, 0xe0, 0x91,
+    ];
+    let tag = [
+        0x5b, 0xc9, 0x4f, 0xbc, 0x32, 0x21, 0xa5, 0xdb, 0x94, 0xfa, 0xe9, 0x5a, 0xe7, 0x12, 0x1a,
+        0x47,
+    ];
+    let cipher = Gcm::<Aes128>::new(key);
+
+    let mut msg = plain_text;
+    if cipher.encrypt_inline(&mut msg, &add_data, &init_vector) != tag || msg != cipher_text {
+        return false;
+    }
+    cipher
+        .decrypt_inline(&mut msg, &add_data, &init_vector, &tag)
+        .is_ok()
+        && msg == plain_text
+}
+
+fn sha256() -> bool {
+    let digest = [
+        0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22,
+        0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00,
+        0x15, 0xad,
+    ];
+    Sha256::hash(b"abc") == digest
+}
+
+fn sha512() -> bool {
+    let digest = [
+        0xdd, 0xaf, 0x35, 0xa1, 0x93, 0x61, 0x7a, 0xba, 0xcc, 0x41, 0x73, 0x49, 0xae, 0x20, 0x41,
+        0x31, 0x12, 0xe6, 0xfa, 0x4e, 0x89, 0xa9, 0x7e, 0xa2, 0x0a, 0x9e, 0xee, 0xe6, 0x4b, 0x55,
+        0xd3, 0x9a, 0x21, 0x92, 0x99, 0x2a, 0x27, 0x4f, 0xc1, 0xa8, 0x36, 0xba, 0x3c, 0x23, 0xa3,
+        0xfe, 0xeb, 0xbd, 0x45, 0x4d, 0x44, 0x23, 0x64, 0x3c, 0xe8, 0x0e, 0x2a, 0x9a, 0xc9, 0x4f,
+        0xa5, 0x4c, 0xa4, 0x9f,
+    ];
+    Sha512::hash(b"abc") == digest
+}
+
+// test case 2 from RFC 4231
+fn hmac() -> bool {
+    let mac = [
+        0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24, 0x26, 0x08, 0x95, 0x75,
+        0xc7, 0x5a, 0x00, 0x3f, 0x08, 0x9d, 0x27, 0x39, 0x83, 0x9d, 0xec, 0x58, 0xb9, 0x64, 0xec,
+        0x38, 0x43,
+    ];
+    Hmac::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>::auth(
+        b"Jefe",
+        b"what do ya want for nothing?",
+    ) == mac
+}
+
+// test case 1 from RFC 5869
+fn hkdf() -> bool {
+    let ikm = [0x0b; 22];
+    let salt = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c,
+    ];
+    let info = [0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9];
+    let okm = [
+        0x3c, 0xb2, 0x5f, 0x25, 0xfa, 0xac, 0xd5, 0x7a, 0x90, 0x43, 0x4f, 0x64, 0xd0, 0x36, 0x2f,
+        0x2a, 0x2d, 0x2d, 0x0a, 0x90, 0xcf, 0x1a, 0x5a, 0x4c, 0x5d, 0xb0, 0x2d, 0x56, 0xec, 0xc4,
+        0xc5, 0xbf, 0x34, 0x00, 0x72, 0x08, 0xd5, 0xb8, 0x87, 0x18, 0x58, 0x65,
+    ];
+    let pr_key =
+        hkdf::extract::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, Sha256>(&salt, &ikm);
+    hkdf::expand::<{ Sha256::HASH_SIZE }, { Sha256::BLOCK_SIZE }, 42, Sha256>(&pr_key, &info) == okm
+}
+
+// the first P-256 SHA-256 signing vector from NIST CAVP
+fn ecdsa() -> bool {
+    let msg = [
+        0x59, 0x05, 0x23, 0x88, 0x77, 0xc7, 0x74, 0x21, 0xf7, 0x3e, 0x43, 0xee, 0x3d, 0xa6, 0xf2,
+        0xd9, 0xe2, 0xcc, 0xad, 0x5f, 0xc9, 0x42, 0xdc, 0xec, 0x0c, 0xbd, 0x25, 0x48, 0x29, 0x35,
+        0xfa, 0xaf, 0x41, 0x69, 0x83, 0xfe, 0x16, 0x5b, 0x1a, 0x04, 0x5e, 0xe2, 0xbc, 0xd2, 0xe6,
+        0xdc, 0xa3, 0xbd, 0xf4, 0x6c, 0x43, 0x10, 0xa7, 0x46, 0x1f, 0x9a, 0x37, 0x96, 0x0c, 0xa6,
+        0x72, 0xd3, 0xfe, 0xb5, 0x47, 0x3e, 0x25, 0x36, 0x05, 0xfb, 0x1d, 0xdf, 0xd2, 0x80, 0x65,
+        0xb5, 0x3c, 0xb5, 0x85, 0x8a, 0x8a, 0xd2, 0x81, 0x75, 0xbf, 0x9b, 0xd3, 0x86, 0xa5, 0xe4,
+        0x71, 0xea, 0x7a, 0x65, 0xc1, 0x7c, 0xc9, 0x34, 0xa9, 0xd7, 0x91, 0xe9, 0x14, 0x91, 0xeb,
+        0x37, 0x54, 0xd0, 0x37, 0x99, 0x79, 0x0f, 0xe2, 0xd3, 0x08, 0xd1, 0x61, 0x46, 0xd5, 0xc9,
+        0xb0, 0xd0, 0xde, 0xbd, 0x97, 0xd7, 0x9c, 0xe8,
+    ];
+    let priv_key = [
+        0x51, 0x9b, 0x42, 0x3d, 0x71, 0x5f, 0x8b, 0x58, 0x1f, 0x4f, 0xa8, 0xee, 0x59, 0xf4, 0x77,
+        0x1a, 0x5b, 0x44, 0xc8, 0x13, 0x0b, 0x4e, 0x3e, 0xac, 0xca, 0x54, 0xa5, 0x6d, 0xda, 0x72,
+        0xb4, 0x64,
+    ];
+    let secret_num = [
+        0x94, 0xa1, 0xbb, 0xb1, 0x4b, 0x90, 0x6a, 0x61, 0xa2, 0x80, 0xf2, 0x45, 0xf9, 0xe9, 0x3c,
+        0x7f, 0x3b, 0x4a, 0x62, 0x47, 0x82, 0x4f, 0x5d, 0x33, 0xb9, 0x67, 0x07, 0x87, 0x64, 0x2a,
+        0x68, 0xde,
+    ];
+    let r = [
+        0xf3, 0xac, 0x80, 0x61, 0xb5, 0x14, 0x79, 0x5b, 0x88, 0x43, 0xe3, 0xd6, 0x62, 0x95, 0x27,
+        0xed, 0x2a, 0xfd, 0x6b, 0x1f, 0x6a, 0x55, 0x5a, 0x7a, 0xca, 0xbb, 0x5e, 0x6f, 0x79, 0xc8,
+        0xc2, 0xac,
+    ];
+    let s = [
+        0x8b, 0xf7, 0x78, 0x19, 0xca, 0x05, 0xa6, 0xb2, 0x78, 0x6c, 0x76, 0x26, 0x2b, 0xf7, 0x37,
+        0x1c, 0xef, 0x97, 0xb2, 0x18, 0xe9, 0x6f, 0x17, 0x5a, 0x3c, 0xcd, 0xda, 0x2a, 0xcc, 0x05,
+        0x89, 0x03,
+    ];
+    let priv_key = FieldElement::new(UBigInt::<4>::from_be_bytes(priv_key));
+    let expected = ecdsa::Signature::new(
+        FieldElement::new(UBigInt::<4>::from_be_bytes(r)),
+        FieldElement::new(UBigInt::<4>::from_be_bytes(s)),
+    );
+
+    let sig = ecdsa::sign::<Secp256r1>(&msg, &priv_key, Sha256::hash, || {
+        FieldElement::new(UBigInt::<4>::from_be_bytes(secret_num))
+    });
+    if sig != expected {
+        return false;
+    }
+
+    let pub_key = Secp256r1::BASE_POINT
+        .as_projective()
+        .mul_scalar(priv_key.inner());
+    ecdsa::verify_signature(&msg, &pub_key, Sha256::hash, &sig).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn self_test() {
+        let report = super::self_test();
+        assert!(report.passed(), "{report:?}");
+    }
+}
@@ -0,0 +1,268 @@
+//! XTS-AES, the tweakable block cipher mode for storage encryption defined in [IEEE 1619].
+//!
+//! XTS encrypts fixed-size data units, such as disk sectors, in place. Each data unit is
+//! encrypted under its own tweak, derived from the data unit's number, so identical sectors at
+//! different positions encrypt differently. Data units that aren't a multiple of the AES block
+//! size are handled with ciphertext stealing, so the cipher text is never longer than the
+//! plain text.
+//!
+//! <div class="warning">
+//! WARNING: XTS provides confidentiality only. It does not detect tampering,
+//! and re-encrypting a sector with the same tweak reveals whether its blocks changed.
+//! </div>
+//!
+//! # Examples
+//!
+//! ```
+//! use crylib::aead::gcm::Aes128;
+//! use crylib::xts::Xts;
+//!
+//! let cipher = Xts::<Aes128>::new([0x11; 16], [0x22; 16]);
+//!
+//! let mut sector = [0x42; 512];
+//! cipher.encrypt_unit(&mut sector, 7).unwrap();
+//! cipher.decrypt_unit(&mut sector, 7).unwrap();
+//! assert_eq!(sector, [0x42; 512]);
+//! ```
+//!
+//! [IEEE 1619]: https://en.wikipedia.org/wiki/Disk_encryption_theory#XEX-based_tweaked-codebook_mode_with_ciphertext_stealing_(XTS)
+use crate::aead::gcm::{AesCipher, BLOCK_SIZE};
+
+/// An error that is returned when a data unit is shorter than one AES block.
+///
+/// XTS cannot encrypt less than [`BLOCK_SIZE`] bytes.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DataTooShort;
+
+impl core::fmt::Display for DataTooShort {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "data unit is shorter than one block")
+    }
+}
+
+impl core::error::Error for DataTooShort {}
+
+/// XTS-AES with two independent keys.
+///
+/// The data key and the tweak key MUST be generated independently.
+/// Using the same key for both voids XTS's security proof.
+pub struct Xts<C: AesCipher> {
+    data_cipher: C,
+    tweak_cipher: C,
+}
+
+impl<C: AesCipher> Xts<C> {
+    /// Creates a new cipher from the key used to encrypt the data and the key used to encrypt
+    /// the tweak.
+    pub fn new(data_key: C::Key, tweak_key: C::Key) -> Self {
+        Self {
+            data_cipher: C::new(data_key),
+            tweak_cipher: C::new(tweak_key),
+        }
+    }
+
+    /// Encrypts the data unit `data` in place.
+    ///
+    /// `unit` is the data unit's sequence number, typically the sector number. It is encoded as
+    /// a 128-bit little-endian integer, as IEEE 1619 specifies.
+    ///
+    /// Returns [`DataTooShort`] if `data` is shorter than [`BLOCK_SIZE`].
+    pub fn encrypt_unit(&self, data: &mut [u8], unit: u128) -> Result<(), DataTooShort> {
+        if data.len() < BLOCK_SIZE {
+            return Err(DataTooShort);
+        }
+        let mut tweak = self.initial_tweak(unit);
+
+        let partial_len = data.len() % BLOCK_SIZE;
+        let (full_blocks, partial) = data.split_at_mut(data.len() - partial_len);
+        // TODO: use `array_chunks` once stabilized
+        for block in full_blocks.chunks_exact_mut(BLOCK_SIZE) {
+            // we can safely unwrap because `block` is guaranteed to have a length of `BLOCK_SIZE`
+            self.encrypt_block(block.try_into().unwrap(), tweak);
+            tweak = mul_alpha(tweak);
+        }
+
+        if !partial.is_empty() {
+            // the last full block has already been encrypted; steal its tail to pad `partial`
+            let last = full_blocks.len() - BLOCK_SIZE;
+            // we can safely unwrap because the slice is guaranteed to have a length of
+            // `BLOCK_SIZE`
+            let last_block: &mut [u8; BLOCK_SIZE] = (&mut full_blocks[last..]).try_into().unwrap();
+
+            let mut stolen = *last_block;
+            stolen[..partial_len].copy_from_slice(partial);
+            partial.copy_from_slice(&last_block[..partial_len]);
+
+            self.encrypt_block(&mut stolen, tweak);
+            *last_block = stolen;
+        }
+        Ok(())
+    }
+
+    /// Decrypts the data unit `data` in place.
+    ///
+    /// `unit` must be the same data unit number `data` was encrypted with.
+    ///
+    /// Returns [`DataTooShort`] if `data` is shorter than [`BLOCK_SIZE`].
+    pub fn decrypt_unit(&self, data: &mut [u8], unit: u128) -> Result<(), DataTooShort> {
+        if data.len() < BLOCK_SIZE {
+            return Err(DataTooShort);
+        }
+        let mut tweak = self.initial_tweak(unit);
+
+        let partial_len = data.len() % BLOCK_SIZE;
+        let (full_blocks, partial) = data.split_at_mut(data.len() - partial_len);
+        // with ciphertext stealing, the last full block is decrypted with the final tweak
+        let stolen_len = if partial.is_empty() { 0 } else { BLOCK_SIZE };
+        let (full_blocks, last_block) = full_blocks.split_at_mut(full_blocks.len() - stolen_len);
+
+        // TODO: use `array_chunks` once stabilized
+        for block in full_blocks.chunks_exact_mut(BLOCK_SIZE) {
+            // we can safely unwrap because `block` is guaranteed to have a length of `BLOCK_SIZE`
+            self.decrypt_block(block.try_into().unwrap(), tweak);
+            tweak = mul_alpha(tweak);
+        }
+
+        if !partial.is_empty() {
+            // we can safely unwrap because `last_block` is guaranteed to have a length of
+            // `BLOCK_SIZE`
+            let last_block: &mut [u8; BLOCK_SIZE] = last_block.try_into().unwrap();
+
+            let mut stolen = *last_block;
+            self.decrypt_block(&mut stolen, mul_alpha(tweak));
+
+            let mut block = stolen;
+            block[..partial_len].copy_from_slice(partial);
+            partial.copy_from_slice(&stolen[..partial_len]);
+
+            self.decrypt_block(&mut block, tweak);
+            *last_block = block;
+        }
+        Ok(())
+    }
+
+    fn initial_tweak(&self, unit: u128) -> u128 {
+        u128::from_le_bytes(self.tweak_cipher.encrypt(&unit.to_le_bytes()))
+    }
+
+    fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE], tweak: u128) {
+        xor_tweak(block, tweak);
+        self.data_cipher.encrypt_inline(block);
+        xor_tweak(block, tweak);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8; BLOCK_SIZE], tweak: u128) {
+        xor_tweak(block, tweak);
+        self.data_cipher.decrypt_inline(block);
+        xor_tweak(block, tweak);
+    }
+}
+
+#[inline]
+fn xor_tweak(block: &mut [u8; BLOCK_SIZE], tweak: u128) {
+    for (block_byte, tweak_byte) in block.iter_mut().zip(tweak.to_le_bytes()) {
+        *block_byte ^= tweak_byte;
+    }
+}
+
+/// Multiplies `tweak` by the primitive element `x` of GF(2^128),
+/// reducing by `x^128 + x^7 + x^2 + x + 1`.
+#[inline]
+const fn mul_alpha(tweak: u128) -> u128 {
+    (tweak << 1) ^ (0x87 & (tweak >> 127).wrapping_neg())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DataTooShort, Xts};
+    use crate::aead::gcm::{Aes128, Aes256};
+
+    fn plain_text<const N: usize>() -> [u8; N] {
+        let mut plain_text = [0; N];
+        for (i, byte) in plain_text.iter_mut().enumerate() {
+            *byte = (i * 7 + 3) as u8;
+        }
+        plain_text
+    }
+
+    fn aes_128() -> Xts<Aes128> {
+        let data_key = [
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+            0x0f, 0x10,
+        ];
+        let tweak_key = [
+            0x80, 0x81, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x8b, 0x8c, 0x8d,
+            0x8e, 0x8f,
+        ];
+        Xts::new(data_key, tweak_key)
+    }
+
+    #[test]
+    fn full_blocks() {
+        let cipher_text = [
+            0xe0, 0x22, 0x1b, 0xee, 0x45, 0xe0, 0x36, 0xa7, 0xfb, 0x0f, 0x8a, 0xcb, 0x8b, 0x41,
+            0x1e, 0x7f, 0xee, 0xa0, 0x51, 0xdf, 0x7b, 0x56, 0xe9, 0x5c, 0x90, 0xf7, 0xf1, 0x9b,
+            0x52, 0x85, 0x0a, 0x19,
+        ];
+        let cipher = aes_128();
+
+        let mut data = plain_text::<32>();
+        cipher.encrypt_unit(&mut data, 0x0123456789).unwrap();
+        assert_eq!(data, cipher_text);
+
+        cipher.decrypt_unit(&mut data, 0x0123456789).unwrap();
+        assert_eq!(data, plain_text());
+    }
+
+    #[test]
+    fn ciphertext_stealing() {
+        let cipher_text = [
+            0xd6, 0x1c, 0xa1, 0xda, 0xcc, 0xc8, 0x10, 0x2b, 0x53, 0xe1, 0x93, 0x0e, 0x2e, 0xb1,
+            0x02, 0xd9, 0x46, 0xd5, 0xe1, 0x10, 0x84, 0x28, 0xab, 0x40, 0xbc, 0xe3, 0xa6, 0xca,
+            0xd5, 0x1b, 0xd0, 0x99, 0xae, 0x53, 0x5b, 0xe8, 0x22,
+        ];
+        let cipher = aes_128();
+
+        let mut data = plain_text::<37>();
+        cipher.encrypt_unit(&mut data, 7).unwrap();
+        assert_eq!(data, cipher_text);
+
+        cipher.decrypt_unit(&mut data, 7).unwrap();
+        assert_eq!(data, plain_text());
+    }
+
+    #[test]
+    fn aes_256() {
+        let mut data_key = [0; 32];
+        let mut tweak_key = [0; 32];
+        for i in 0..32 {
+            data_key[i] = 0x20 + i as u8;
+            tweak_key[i] = 0x40 + i as u8;
+        }
+        let cipher = Xts::<Aes256>::new(data_key, tweak_key);
+
+        let plain_text = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d,
+            0x0e, 0x0f, 0x10,
+        ];
+        let cipher_text = [
+            0x24, 0xe6, 0xe3, 0xc7, 0xbf, 0xa6, 0xd5, 0x71, 0xee, 0xfa, 0xc0, 0x2b, 0x69, 0x2f,
+            0xcd, 0x89, 0x24,
+        ];
+
+        let mut data = plain_text;
+        cipher.encrypt_unit(&mut data, 1).unwrap();
+        assert_eq!(data, cipher_text);
+
+        cipher.decrypt_unit(&mut data, 1).unwrap();
+        assert_eq!(data, plain_text);
+    }
+
+    #[test]
+    fn too_short() {
+        let cipher = aes_128();
+        let mut data = [0; 15];
+        assert_eq!(cipher.encrypt_unit(&mut data, 0), Err(DataTooShort));
+        assert_eq!(cipher.decrypt_unit(&mut data, 0), Err(DataTooShort));
+    }
+}
@@ -1,8 +1,48 @@
 #ifndef TURTLS_H
 #define TURTLS_H
 #include <sys/types.h>
+#include <stdbool.h>
 #include <stddef.h>
+
+/* A connection established by client_shake_hands. */
 struct State;
 
-struct State *shake_hands(int fd, ssize_t (*write)(int, void *, size_t), ssize_t (*read)(int, void *, size_t));
+/* Writes up to len bytes of buf to fd, returning how many were written, or a negative value on
+ * failure. */
+typedef ssize_t (*WriteFn)(int fd, const void *buf, size_t len);
+/* Reads up to len bytes from fd into buf, returning how many were read, zero at the end of the
+ * stream, or a negative value on failure. */
+typedef ssize_t (*ReadFn)(int fd, void *buf, size_t len);
+/* Given the DER certificates the server sent, leaf first, as an array of pointers and an array of
+ * lengths, along with their count, returns whether to trust them. */
+typedef bool (*VerifyFn)(const unsigned char *const *certs, const size_t *lens, size_t count);
+
+enum ShakeResultTag {
+    /* The handshake succeeded. The connection has to be freed with free_state. */
+    SHAKE_OK,
+    /* Random numbers couldn't be generated. */
+    SHAKE_RNG_ERROR,
+    /* A callback failed, or the server closed the stream. */
+    SHAKE_IO_ERROR,
+    /* The handshake failed, and the server was sent an alert saying why. */
+    SHAKE_HANDSHAKE_FAILED,
+};
+
+/* The result of client_shake_hands. state is only set if tag is SHAKE_OK. */
+struct ShakeResult {
+    enum ShakeResultTag tag;
+    union {
+        struct State *state;
+    };
+};
+
+/* Runs a TLS 1.3 handshake with the server at the other end of fd. */
+struct ShakeResult client_shake_hands(int fd, WriteFn write, ReadFn read, VerifyFn verify);
+/* Encrypts the len bytes at buf and sends them, returning false on failure. */
+bool send_data(struct State *state, const void *buf, size_t len);
+/* Receives application data into the len bytes at buf, returning how many bytes were received,
+ * zero once the server has closed the connection, or a negative value on failure. */
+ssize_t receive_data(struct State *state, void *buf, size_t len);
+/* Frees a connection returned by client_shake_hands. */
+void free_state(struct State *state);
 #endif
@@ -1,46 +1,182 @@
-use crylib::aead::{Aead, BadData, IV_SIZE, TAG_SIZE};
+use crylib::aead::gcm::{Aes128, Gcm};
+use crylib::aead::{Aead, BadData, NewAead, IV_SIZE, TAG_SIZE};
 
-pub struct AeadWriter {
-    cipher: Box<dyn Aead>,
-    nonce: u64,
+/// The record protection keys for one direction of a connection.
+///
+/// The cipher's key schedule is run once, when the keys are created,
+/// so protecting a record never re-expands the key.
+pub struct TrafficKeys {
+    cipher: Box<dyn Aead + Send + Sync>,
     static_iv: [u8; IV_SIZE],
 }
 
-impl AeadWriter {
-    pub fn encrypt_inline(&mut self, msg: &mut [u8], add_data: &[u8]) -> [u8; TAG_SIZE] {
+impl TrafficKeys {
+    pub fn new(cipher: Box<dyn Aead + Send + Sync>, static_iv: [u8; IV_SIZE]) -> Self {
+        Self { cipher, static_iv }
+    }
+
+    /// Creates the keys for the negotiated cipher `A` from a derived traffic key.
+    ///
+    /// Returns `None` if `key` isn't [`NewAead::KEY_SIZE`] bytes long.
+    pub fn from_key<A: NewAead + Send + Sync + 'static>(
+        key: &[u8],
+        static_iv: [u8; IV_SIZE],
+    ) -> Option<Self> {
+        let key = A::Key::try_from(key).ok()?;
+        Some(Self::new(Box::new(A::new(key)), static_iv))
+    }
+
+    pub fn aes_128_gcm(key: [u8; 16], static_iv: [u8; IV_SIZE]) -> Self {
+        Self::from_key::<Gcm<Aes128>>(&key, static_iv).expect("the key is the size AES-128 takes")
+    }
+
+    /// The per-record nonce: the static IV XORed with the sequence number.
+    fn nonce(&self, seq: u64) -> [u8; IV_SIZE] {
         let mut init_vec = self.static_iv;
-        let counter = self.nonce.to_be_bytes();
+        let counter = seq.to_be_bytes();
         for (byte_1, byte_2) in init_vec.iter_mut().rev().zip(counter.into_iter().rev()) {
             *byte_1 ^= byte_2;
         }
-        // TODO: add an overflow check?
-        self.nonce += 1;
+        init_vec
+    }
+}
 
-        self.cipher.encrypt_inline(msg, add_data, &init_vec)
+pub struct AeadWriter {
+    keys: TrafficKeys,
+    /// The sequence number of the next record, or `None` once every one has been used.
+    nonce: Option<u64>,
+}
+
+impl AeadWriter {
+    pub fn new(keys: TrafficKeys) -> Self {
+        Self {
+            keys,
+            nonce: Some(0),
+        }
+    }
+
+    /// Installs new keys, e.g. after a `KeyUpdate`, and restarts the sequence number.
+    pub fn update_keys(&mut self, keys: TrafficKeys) {
+        *self = Self::new(keys);
+    }
+
+    /// Encrypts one record in place, returning its tag.
+    ///
+    /// Sequence numbers can't wrap (RFC 8446 section 5.3), so this returns [`SequenceExhausted`]
+    /// once 2^64 records have been encrypted with the same keys.
+    pub fn encrypt_inline(
+        &mut self,
+        msg: &mut [u8],
+        add_data: &[u8],
+    ) -> Result<[u8; TAG_SIZE], SequenceExhausted> {
+        let seq = self.nonce.ok_or(SequenceExhausted)?;
+        let init_vec = self.keys.nonce(seq);
+        self.nonce = seq.checked_add(1);
+
+        Ok(self.keys.cipher.encrypt_inline(msg, add_data, &init_vec))
     }
 }
 
 pub struct AeadReader {
-    cipher: Box<dyn Aead>,
-    nonce: u64,
-    static_iv: [u8; IV_SIZE],
+    keys: TrafficKeys,
+    /// The sequence number of the next record, or `None` once every one has been used.
+    nonce: Option<u64>,
 }
 
 impl AeadReader {
+    pub fn new(keys: TrafficKeys) -> Self {
+        Self {
+            keys,
+            nonce: Some(0),
+        }
+    }
+
+    /// Installs new keys, e.g. after a `KeyUpdate`, and restarts the sequence number.
+    pub fn update_keys(&mut self, keys: TrafficKeys) {
+        *self = Self::new(keys);
+    }
+
+    /// Decrypts one record in place.
+    ///
+    /// Once 2^64 records have been decrypted with the same keys, the peer would have to wrap its
+    /// sequence number to send another, so every later record is rejected like a forged one.
     pub fn decrypt_inline(
         &mut self,
         msg: &mut [u8],
         add_data: &[u8],
         tag: &[u8; TAG_SIZE],
     ) -> Result<(), BadData> {
-        let mut init_vec = self.static_iv;
-        let counter = self.nonce.to_be_bytes();
-        for (byte_1, byte_2) in init_vec.iter_mut().rev().zip(counter.into_iter().rev()) {
-            *byte_1 ^= byte_2;
+        let seq = self.nonce.ok_or(BadData)?;
+        let init_vec = self.keys.nonce(seq);
+        self.nonce = seq.checked_add(1);
+
+        self.keys
+            .cipher
+            .decrypt_inline(msg, add_data, &init_vec, tag)
+    }
+}
+
+#[cfg(feature = "dangerous_handoff")]
+impl AeadWriter {
+    /// Continues encrypting with keys that were already used for `seq` records.
+    pub fn resume_at(keys: TrafficKeys, seq: u64) -> Self {
+        Self {
+            keys,
+            nonce: Some(seq),
         }
-        // TODO: add an overflow check?
-        self.nonce += 1;
+    }
+
+    /// Returns the sequence number of the next record, or `None` once every one has been used.
+    pub fn sequence(&self) -> Option<u64> {
+        self.nonce
+    }
+}
+
+#[cfg(feature = "dangerous_handoff")]
+impl AeadReader {
+    /// Continues decrypting with keys that were already used for `seq` records.
+    pub fn resume_at(keys: TrafficKeys, seq: u64) -> Self {
+        Self {
+            keys,
+            nonce: Some(seq),
+        }
+    }
+
+    /// Returns the sequence number of the next record, or `None` once every one has been used.
+    pub fn sequence(&self) -> Option<u64> {
+        self.nonce
+    }
+}
+
+/// The error that is returned when every sequence number of a set of keys has been used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SequenceExhausted;
+
+#[cfg(test)]
+mod tests {
+    use super::{AeadReader, AeadWriter, SequenceExhausted, TrafficKeys};
+    use crylib::aead::BadData;
+
+    const KEY: [u8; 16] = [0x42; 16];
+    const IV: [u8; 12] = [0x24; 12];
+
+    #[test]
+    fn last_sequence_number() {
+        let mut writer = AeadWriter::new(TrafficKeys::aes_128_gcm(KEY, IV));
+        let mut reader = AeadReader::new(TrafficKeys::aes_128_gcm(KEY, IV));
+        writer.nonce = Some(u64::MAX);
+        reader.nonce = Some(u64::MAX);
+
+        let mut msg = *b"hello";
+        let tag = writer.encrypt_inline(&mut msg, b"").unwrap();
+        assert_eq!(reader.decrypt_inline(&mut msg, b"", &tag), Ok(()));
+        assert_eq!(msg, *b"hello");
+
+        assert_eq!(writer.encrypt_inline(&mut msg, b""), Err(SequenceExhausted));
+        assert_eq!(reader.decrypt_inline(&mut msg, b"", &tag), Err(BadData));
 
-        self.cipher.decrypt_inline(msg, add_data, &init_vec, tag)
+        // new keys start over
+        writer.update_keys(TrafficKeys::aes_128_gcm(KEY, IV));
+        assert!(writer.encrypt_inline(&mut msg, b"").is_ok());
     }
 }
@@ -1,10 +1,12 @@
 #[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum AlertLevel {
     Warning = 1,
     Fatal = 2,
 }
 
 #[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum AlertDescription {
     CloseNotify = 0,
     UnexpectedMessage = 10,
@@ -35,13 +37,92 @@ pub enum AlertDescription {
     NoAppProtocol = 120,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Alert {
     level: AlertLevel,
     description: AlertDescription,
 }
 
 impl Alert {
+    /// Creates a fatal alert.
+    ///
+    /// TLS 1.3 treats every alert except `close_notify` and `user_canceled` as fatal,
+    /// regardless of its level.
+    pub const fn fatal(description: AlertDescription) -> Self {
+        Self {
+            level: AlertLevel::Fatal,
+            description,
+        }
+    }
+
     pub const fn to_be_bytes(self) -> [u8; 2] {
         [self.level as u8, self.description as u8]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Alert;
+    use crate::codec::{DecodeError, EncodeError};
+    use crate::connection::ConnectionError;
+    use crate::public_key::PublicKeyError;
+    use crate::record::DecryptError;
+    use crate::server_hello::SerHelloParseError;
+    use crate::signature::{SignError, SignatureError};
+
+    /// Every protocol error paired with the exact bytes of the alert it sends.
+    #[test]
+    fn error_alerts() {
+        let alerts = [
+            (DecodeError.alert(), [2, 50]),
+            (EncodeError.alert(), [2, 80]),
+            (DecryptError.alert(), [2, 20]),
+            (SerHelloParseError::MissingData.alert(), [2, 50]),
+            (SerHelloParseError::InvalidLengthEncoding.alert(), [2, 50]),
+            (SerHelloParseError::InvalidCipherSuite.alert(), [2, 47]),
+            (SignatureError::MissingData.alert(), [2, 50]),
+            (SignatureError::InvalidEncoding.alert(), [2, 50]),
+            (SignatureError::UnsupportedScheme.alert(), [2, 47]),
+            (SignatureError::InvalidPublicKey.alert(), [2, 42]),
+            (SignatureError::KeyMismatch.alert(), [2, 47]),
+            (PublicKeyError::InvalidEncoding.alert(), [2, 42]),
+            (PublicKeyError::UnsupportedAlgorithm.alert(), [2, 43]),
+            (PublicKeyError::InvalidKey.alert(), [2, 42]),
+            (SignatureError::BadSignature.alert(), [2, 51]),
+            (SignError::UnsupportedScheme.alert(), [2, 40]),
+            (SignError::RngError.alert(), [2, 80]),
+            (ConnectionError::UnexpectedMessage.alert().unwrap(), [2, 10]),
+            (ConnectionError::MessageTooLong.alert().unwrap(), [2, 50]),
+            (ConnectionError::ProtocolVersion.alert().unwrap(), [2, 70]),
+            (ConnectionError::HelloRetryRequest.alert().unwrap(), [2, 40]),
+            (ConnectionError::IllegalParameter.alert().unwrap(), [2, 47]),
+            (ConnectionError::MissingExtension.alert().unwrap(), [2, 109]),
+            (
+                ConnectionError::UnsupportedExtension.alert().unwrap(),
+                [2, 110],
+            ),
+            (
+                ConnectionError::CertificateRejected.alert().unwrap(),
+                [2, 42],
+            ),
+            (
+                ConnectionError::NoCommonCipherSuite.alert().unwrap(),
+                [2, 40],
+            ),
+            (ConnectionError::NoCommonGroup.alert().unwrap(), [2, 40]),
+            (
+                ConnectionError::NoCommonSignatureScheme.alert().unwrap(),
+                [2, 40],
+            ),
+            (
+                ConnectionError::CertificateRequired.alert().unwrap(),
+                [2, 116],
+            ),
+            (ConnectionError::Rng.alert().unwrap(), [2, 80]),
+            (ConnectionError::BadFinished.alert().unwrap(), [2, 51]),
+        ];
+        for (description, bytes) in alerts {
+            assert_eq!(Alert::fatal(description).to_be_bytes(), bytes);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Connects to a TLS server and prints what was negotiated, which certificates were sent, and
+//! the plaintext handshake messages, as JSON.
+//!
+//! ```text
+//! turtls-probe [--ca <file>] <host> [port]
+//! ```
+//!
+//! With `--ca`, the chain is also checked against the certificates in the PEM file.
+//!
+//! The exit code is 0 if the handshake succeeded, 1 if it failed, and 2 if the arguments were
+//! invalid.
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+
+use turtls::probe;
+
+const USAGE: &str = "usage: turtls-probe [--ca <file>] <host> [port]";
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1).peekable();
+    let ca = match args.next_if(|arg| arg == "--ca") {
+        Some(_) => match args.next() {
+            Some(ca) => Some(ca),
+            None => {
+                eprintln!("{USAGE}");
+                return ExitCode::from(2);
+            },
+        },
+        None => None,
+    };
+    let (Some(host), port, None) = (args.next(), args.next(), args.next()) else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+    let port = match port.map(|port| port.parse()) {
+        None => 443,
+        Some(Ok(port)) => port,
+        Some(Err(_)) => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        },
+    };
+    let trust_anchors = match ca {
+        None => Vec::new(),
+        Some(ca) => match fs::read(&ca).map(|file| probe::parse_trust_anchors(&file)) {
+            Ok(Ok(anchors)) => anchors,
+            Ok(Err(_)) => {
+                eprintln!("{ca}: no valid certificates");
+                return ExitCode::from(2);
+            },
+            Err(err) => {
+                eprintln!("{ca}: {err}");
+                return ExitCode::from(2);
+            },
+        },
+    };
+
+    let report = probe::probe(&host, port, &trust_anchors);
+    println!("{}", report.to_json());
+    match report.error {
+        None => ExitCode::SUCCESS,
+        Some(_) => ExitCode::FAILURE,
+    }
+}
@@ -0,0 +1,368 @@
+//! Blocking I/O for clients: running a handshake over a stream, and connecting to a server over
+//! TCP.
+//!
+//! [`ClientConnection`] doesn't do any I/O itself, so [`complete_handshake`] moves its records
+//! over anything that is [`Read`] and [`Write`]. Records are read one at a time, so nothing the
+//! server sends after the handshake is taken from the stream, and the stream can be used for
+//! application data once the handshake is done.
+//!
+//! [`connect`] also opens the stream. The host's addresses are tried as RFC 8305 ("Happy
+//! Eyeballs") describes: IPv6 and IPv4 addresses are interleaved, starting with IPv6, and each
+//! attempt is given a head start of [`CONNECTION_ATTEMPT_DELAY`] before the next one is started
+//! alongside it. The first connection to succeed is used. Addresses are resolved with the
+//! standard library, which waits for both families, so the asynchronous resolution of section 3
+//! isn't done.
+use std::fmt;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::client::ClientConnection;
+use crate::connection::ConnectionError;
+use crate::record::RecordHeader;
+
+/// How long a connection attempt is given before the next address is tried alongside it, as
+/// recommended by RFC 8305 section 5.
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+/// How long a single connection attempt may take.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The error that is returned when a handshake over a stream fails.
+#[derive(Debug)]
+pub enum HandshakeError {
+    /// The stream couldn't be read from or written to, or no connection could be opened.
+    Io(io::Error),
+    /// The handshake failed. The server was sent an alert saying why, if it was still listening.
+    Tls(ConnectionError),
+    /// The server closed the stream before the handshake was done.
+    Closed,
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Tls(err) => write!(f, "{err:?}"),
+            Self::Closed => f.write_str("the server closed the connection"),
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Tls(_) | Self::Closed => None,
+        }
+    }
+}
+
+impl From<io::Error> for HandshakeError {
+    fn from(value: io::Error) -> Self {
+        match value.kind() {
+            ErrorKind::UnexpectedEof => Self::Closed,
+            _ => Self::Io(value),
+        }
+    }
+}
+
+impl From<ConnectionError> for HandshakeError {
+    fn from(value: ConnectionError) -> Self {
+        Self::Tls(value)
+    }
+}
+
+/// Moves records between `conn` and `stream` until the handshake is done, blocking on `stream`.
+///
+/// Whatever the server sent after its last handshake record is left in `stream`.
+pub fn complete_handshake(
+    conn: &mut ClientConnection,
+    stream: &mut (impl Read + Write),
+) -> Result<(), HandshakeError> {
+    loop {
+        stream.write_all(&conn.take_outgoing())?;
+        stream.flush()?;
+        if conn.is_closed() {
+            return Err(HandshakeError::Closed);
+        }
+        if !conn.is_handshaking() {
+            return Ok(());
+        }
+        if let Err(err) = read_record(conn, stream) {
+            if let HandshakeError::Tls(_) = err {
+                // let the server know why, if it's still listening
+                let _ = stream.write_all(&conn.take_outgoing());
+                let _ = stream.flush();
+            }
+            return Err(err);
+        }
+    }
+}
+
+/// Reads one record from `stream` and processes it.
+fn read_record(conn: &mut ClientConnection, stream: &mut impl Read) -> Result<(), HandshakeError> {
+    let mut header = [0; RecordHeader::SIZE];
+    stream.read_exact(&mut header)?;
+    // the header is checked before its fragment is read
+    let len = conn.read_header(&header)?.len as usize;
+    let mut record = vec![0; RecordHeader::SIZE + len];
+    record[..RecordHeader::SIZE].copy_from_slice(&header);
+    stream.read_exact(&mut record[RecordHeader::SIZE..])?;
+    conn.read_tls(&mut record)?;
+    Ok(())
+}
+
+/// Connects to `host` on `port`, and does a handshake, returning the connection along with the
+/// stream it runs over.
+///
+/// Unless `host` is an IP address, it is sent as the server name, so servers that host several
+/// names send the certificate for that one. `verify_certs` is given the DER certificates the
+/// server sends, leaf first, and returns whether to trust them, like in
+/// [`ClientConnection::new`]. Whether they are valid for `host` is up to it.
+///
+/// The stream has no timeouts, so a server that stops answering in the middle of the handshake
+/// blocks the call.
+///
+/// # Panics
+///
+/// The function will panic if `host` is not an IP address, and is empty or longer than 255 bytes.
+pub fn connect(
+    host: &str,
+    port: u16,
+    verify_certs: impl Fn(&[&[u8]]) -> bool + Send + Sync + 'static,
+) -> Result<(ClientConnection, TcpStream), HandshakeError> {
+    // IP addresses can't be sent as server names (RFC 6066 section 3)
+    let mut conn = match host.parse::<IpAddr>() {
+        Ok(_) => ClientConnection::new(verify_certs)?,
+        Err(_) => ClientConnection::with_server_name(host, verify_certs)?,
+    };
+    let addrs = (host, port).to_socket_addrs()?;
+    let mut stream = connect_tcp(interleave(addrs))?;
+    complete_handshake(&mut conn, &mut stream)?;
+    Ok((conn, stream))
+}
+
+/// Orders `addrs` as RFC 8305 section 4 describes: alternating between IPv6 and IPv4, starting
+/// with IPv6, and otherwise in the order they were resolved.
+fn interleave(addrs: impl IntoIterator<Item = SocketAddr>) -> Vec<SocketAddr> {
+    let (v6, v4): (Vec<_>, Vec<_>) = addrs.into_iter().partition(SocketAddr::is_ipv6);
+    let mut ordered = Vec::with_capacity(v6.len() + v4.len());
+    let (mut v6, mut v4) = (v6.into_iter(), v4.into_iter());
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => return ordered,
+            (first, second) => ordered.extend(first.into_iter().chain(second)),
+        }
+    }
+}
+
+/// Connects to the first of `addrs` that answers, starting an attempt at each one in turn.
+///
+/// An attempt is started whenever the previous one fails, or has been running for
+/// [`CONNECTION_ATTEMPT_DELAY`]. Attempts that are still running once one succeeds are left to
+/// finish on their own, and their streams are closed.
+fn connect_tcp(addrs: Vec<SocketAddr>) -> io::Result<TcpStream> {
+    let (sender, receiver) = mpsc::channel();
+    let mut last_err = None;
+    let mut running = 0;
+    let mut addrs = addrs.into_iter();
+    loop {
+        let started = match addrs.next() {
+            Some(addr) => {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    // the receiver is gone if another attempt won
+                    let _ = sender.send(TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT));
+                });
+                running += 1;
+                true
+            },
+            None => false,
+        };
+        if running == 0 {
+            return Err(last_err.unwrap_or_else(|| {
+                io::Error::new(ErrorKind::NotFound, "the host has no addresses")
+            }));
+        }
+        let result = match started {
+            true => match receiver.recv_timeout(CONNECTION_ATTEMPT_DELAY) {
+                Ok(result) => result,
+                // give the next address a try too
+                Err(_) => continue,
+            },
+            // nothing is left to start, so wait for whichever attempt finishes next
+            false => receiver.recv().expect("the sender is still held here"),
+        };
+        running -= 1;
+        match result {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::{complete_handshake, connect, connect_tcp, interleave, HandshakeError};
+    use crate::client::ClientConnection;
+    use crate::connection::tests::{cert_key, CERT};
+    use crate::connection::ConnectionError;
+    use crate::record::Message;
+    use crate::server::{ServerConfig, ServerConnection};
+    use crate::test_util::duplex;
+
+    /// Does a handshake with `conn` over `stream`, then sends `data` straight after it.
+    fn serve(
+        mut conn: ServerConnection,
+        mut stream: impl Read + Write,
+        data: &[u8],
+    ) -> ServerConnection {
+        let mut buf = vec![0; Message::MAX_SIZE];
+        let mut len = 0;
+        while conn.is_handshaking() {
+            let read = stream.read(&mut buf[len..]).unwrap();
+            len += read;
+            let used = conn.read_tls(&mut buf[..len]).unwrap();
+            buf.copy_within(used..len, 0);
+            len -= used;
+            if !conn.is_handshaking() {
+                conn.write(data).unwrap();
+            }
+            stream.write_all(&conn.take_outgoing()).unwrap();
+        }
+        conn
+    }
+
+    fn config() -> Arc<ServerConfig> {
+        let mut config = ServerConfig::new(vec![CERT.to_vec()], cert_key());
+        config.retain_handshake_messages();
+        Arc::new(config)
+    }
+
+    #[test]
+    fn handshake_over_stream() {
+        let (mut client_end, server_end) = duplex();
+        let server = thread::spawn(move || {
+            serve(ServerConnection::new(config()), server_end, b"hello");
+        });
+        let mut conn = ClientConnection::new(|certs| certs == [&CERT[..]]).unwrap();
+        complete_handshake(&mut conn, &mut client_end).unwrap();
+        assert!(!conn.is_handshaking());
+        server.join().unwrap();
+
+        // the data sent after the handshake is still in the stream
+        let mut rest = Vec::new();
+        client_end.read_to_end(&mut rest).unwrap();
+        assert_eq!(conn.read_tls(&mut rest), Ok(rest.len()));
+        assert_eq!(conn.take_received(), b"hello");
+    }
+
+    #[test]
+    fn rejected_handshake() {
+        let (mut client_end, mut server_end) = duplex();
+        let server = thread::spawn(move || {
+            let mut conn = ServerConnection::new(config());
+            let mut buf = vec![0; Message::MAX_SIZE];
+            let mut len = 0;
+            loop {
+                let read = server_end.read(&mut buf[len..]).unwrap();
+                if read == 0 {
+                    return conn;
+                }
+                len += read;
+                let Ok(used) = conn.read_tls(&mut buf[..len]) else {
+                    return conn;
+                };
+                buf.copy_within(used..len, 0);
+                len -= used;
+                let _ = server_end.write_all(&conn.take_outgoing());
+            }
+        });
+        let mut conn = ClientConnection::new(|_| false).unwrap();
+        let err = complete_handshake(&mut conn, &mut client_end).unwrap_err();
+        assert!(matches!(
+            err,
+            HandshakeError::Tls(ConnectionError::CertificateRejected)
+        ));
+        drop(client_end);
+        // the server was told why
+        assert!(server.join().unwrap().is_closed());
+
+        // a server that hangs up mid-handshake
+        let (mut client_end, server_end) = duplex();
+        drop(server_end);
+        let mut conn = ClientConnection::new(|_| true).unwrap();
+        assert!(matches!(
+            complete_handshake(&mut conn, &mut client_end),
+            Err(HandshakeError::Io(_))
+        ));
+        let (mut client_end, mut server_end) = duplex();
+        let server = thread::spawn(move || {
+            let mut client_hello = [0; 5];
+            server_end.read_exact(&mut client_hello).unwrap();
+        });
+        let mut conn = ClientConnection::new(|_| true).unwrap();
+        assert!(matches!(
+            complete_handshake(&mut conn, &mut client_end),
+            Err(HandshakeError::Closed)
+        ));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn connect_local_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let config = config();
+        let server_config = Arc::clone(&config);
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let conn = serve(ServerConnection::new(server_config), &stream, b"hello");
+            conn.handshake_messages().unwrap()[0].clone()
+        });
+        // "localhost" may resolve to ::1 too, which nothing listens on
+        let (mut conn, mut stream) =
+            connect("localhost", port, |certs| certs == [&CERT[..]]).unwrap();
+        assert!(!conn.is_handshaking());
+        let client_hello = server.join().unwrap();
+        assert!(client_hello.windows(9).any(|window| window == b"localhost"));
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).unwrap();
+        assert_eq!(conn.read_tls(&mut rest), Ok(rest.len()));
+        assert_eq!(conn.take_received(), b"hello");
+    }
+
+    #[test]
+    fn happy_eyeballs_order() {
+        let addrs: Vec<SocketAddr> = ["1.1.1.1:1", "2.2.2.2:1", "[::1]:1", "3.3.3.3:1", "[::2]:1"]
+            .map(|addr| addr.parse().unwrap())
+            .to_vec();
+        let ordered: Vec<String> = interleave(addrs).iter().map(|a| a.to_string()).collect();
+        assert_eq!(
+            ordered,
+            ["[::1]:1", "1.1.1.1:1", "[::2]:1", "2.2.2.2:1", "3.3.3.3:1"]
+        );
+    }
+
+    #[test]
+    fn fallback_address() {
+        // nothing listens on the first port, so the second address is connected to
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stream = connect_tcp(vec![closed_addr, addr]).unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+
+        assert!(connect_tcp(vec![closed_addr]).is_err());
+        assert!(connect_tcp(Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,363 @@
+//! X.509 certificates, parsed as far as checking who signed them and when they are valid.
+//!
+//! A [`Certificate`] borrows the DER it was parsed from, and only picks out the parts this crate
+//! uses: the signed `TBSCertificate`, its signature and the algorithm that made it, the issuer and
+//! subject, the validity period, and the `SubjectPublicKeyInfo`. The hash of the whole certificate
+//! is also the `tls-server-end-point` channel binding of connections it authenticates. The
+//! signature algorithm, the names and the validity period are only interpreted when they are
+//! asked for, so the key of a certificate can be read even if it was signed with something
+//! [`oid`](crate::oid) doesn't know.
+//!
+//! [`ValidityChecker`] is what applications use from their certificate verifiers to reject
+//! certificates outside their validity periods, against the time of a [`TimeProvider`].
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::name::DistinguishedName;
+use crate::oid::{AlgorithmError, HashAlgorithm, KeyAlgorithm, SignatureAlgorithm};
+use crate::signature::{self, SignatureError};
+use crylib::asn1::{self, InvalidDer};
+use crylib::hash::{Hasher, Sha256, Sha384, Sha512};
+
+/// A DER X.509 certificate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Certificate<'a> {
+    /// The whole certificate.
+    der: &'a [u8],
+    /// The encoding of the `TBSCertificate`, which is what the signature is over.
+    tbs: &'a [u8],
+    /// The contents of the `signature` `AlgorithmIdentifier` inside the `TBSCertificate`.
+    tbs_algorithm: &'a [u8],
+    /// The contents of the `signatureAlgorithm` `AlgorithmIdentifier`.
+    algorithm: &'a [u8],
+    signature: &'a [u8],
+    /// The encoding of the issuer's `Name`.
+    issuer: &'a [u8],
+    /// The encoding of the subject's `Name`.
+    subject: &'a [u8],
+    /// The contents of the `Validity`.
+    validity: &'a [u8],
+    /// The encoding of the `SubjectPublicKeyInfo`.
+    spki: &'a [u8],
+}
+
+impl<'a> Certificate<'a> {
+    /// Parses a DER X.509 certificate.
+    ///
+    /// The fields that follow the key, such as the extensions, aren't looked at.
+    pub fn parse(der: &'a [u8]) -> Result<Self, CertificateError> {
+        let mut reader = asn1::Reader::new(der);
+        let mut cert = reader.read_sequence()?;
+        reader.finish()?;
+        let tbs = cert.read_any()?;
+        let algorithm = cert.read(asn1::SEQUENCE)?;
+        let signature = cert.read_bit_string()?;
+        cert.finish()?;
+
+        if tbs.tag != asn1::SEQUENCE {
+            return Err(CertificateError::InvalidEncoding);
+        }
+        let mut tbs_cert = asn1::Reader::new(tbs.contents);
+        tbs_cert.read_optional(asn1::context(0))?;
+        tbs_cert.read_integer()?;
+        let tbs_algorithm = tbs_cert.read(asn1::SEQUENCE)?;
+        let issuer = tbs_cert.read_any()?.encoding;
+        let validity = tbs_cert.read(asn1::SEQUENCE)?;
+        let subject = tbs_cert.read_any()?.encoding;
+        let spki = tbs_cert.read_any()?;
+        if spki.tag != asn1::SEQUENCE {
+            return Err(CertificateError::InvalidEncoding);
+        }
+
+        Ok(Self {
+            der,
+            tbs: tbs.encoding,
+            tbs_algorithm,
+            algorithm,
+            signature,
+            issuer,
+            subject,
+            validity,
+            spki: spki.encoding,
+        })
+    }
+
+    /// Returns the DER `SubjectPublicKeyInfo` of the certificate.
+    pub const fn spki(&self) -> &'a [u8] {
+        self.spki
+    }
+
+    /// Returns the `tls-server-end-point` channel binding of the certificate (RFC 5929 section
+    /// 4.1): its hash, with the hash function of its signature.
+    pub fn tls_server_end_point(&self) -> Result<Vec<u8>, CertificateError> {
+        let hash = match HashAlgorithm::for_end_point(self.algorithm)? {
+            HashAlgorithm::Sha256 => Sha256::hash(self.der).to_vec(),
+            HashAlgorithm::Sha384 => Sha384::hash(self.der).to_vec(),
+            HashAlgorithm::Sha512 => Sha512::hash(self.der).to_vec(),
+        };
+        Ok(hash)
+    }
+
+    /// Parses the name of the certificate's issuer.
+    pub fn issuer(&self) -> Result<DistinguishedName<'a>, CertificateError> {
+        DistinguishedName::parse(self.issuer)
+    }
+
+    /// Parses the name of the certificate's subject.
+    pub fn subject(&self) -> Result<DistinguishedName<'a>, CertificateError> {
+        DistinguishedName::parse(self.subject)
+    }
+
+    /// Returns the first and the last second the certificate is valid, as Unix times.
+    pub fn validity(&self) -> Result<(i64, i64), CertificateError> {
+        let mut validity = asn1::Reader::new(self.validity);
+        let not_before = validity.read_time()?;
+        let not_after = validity.read_time()?;
+        validity.finish()?;
+        Ok((not_before.unix_time(), not_after.unix_time()))
+    }
+
+    /// Verifies that `self` is signed by the owner of `issuer_spki`, which is a DER
+    /// `SubjectPublicKeyInfo`.
+    ///
+    /// Only the signature is checked: whether the issuer is allowed to issue certificates, and
+    /// whether the names match, is up to the caller.
+    pub fn verify_signed_by(&self, issuer_spki: &[u8]) -> Result<(), CertificateError> {
+        // RFC 5280 section 4.1.1.2: the signed copy of the algorithm must match
+        if self.algorithm != self.tbs_algorithm {
+            return Err(CertificateError::InvalidEncoding);
+        }
+        let algorithm = SignatureAlgorithm::parse(asn1::Reader::new(self.algorithm))?;
+        let (key, _) =
+            KeyAlgorithm::from_spki(issuer_spki).map_err(|_| CertificateError::InvalidIssuerKey)?;
+        let scheme = algorithm.scheme(key)?;
+        // X.509 encodes signatures the same way TLS does
+        signature::verify(scheme, issuer_spki, self.tbs, self.signature)?;
+        Ok(())
+    }
+}
+
+/// A source of the current time.
+pub trait TimeProvider: Send + Sync {
+    /// Returns the number of seconds since 1970-01-01 00:00:00 UTC.
+    fn now(&self) -> i64;
+}
+
+/// The system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl TimeProvider for SystemClock {
+    fn now(&self) -> i64 {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_secs() as i64,
+            Err(err) => -(err.duration().as_secs() as i64),
+        }
+    }
+}
+
+/// Checks that certificates are within their validity periods.
+///
+/// ```
+/// use std::time::Duration;
+/// use turtls::{ClientConnection, ValidityChecker};
+///
+/// let mut validity = ValidityChecker::new();
+/// validity.set_clock_skew(Duration::from_secs(300));
+/// let client = ClientConnection::new(move |certs| {
+///     certs.iter().all(|cert| validity.check(cert).is_ok())
+///     // && the chain is trusted and valid for the server
+/// });
+/// ```
+pub struct ValidityChecker {
+    time: Box<dyn TimeProvider>,
+    /// The clock skew to tolerate, in seconds.
+    skew: i64,
+}
+
+impl ValidityChecker {
+    /// Creates a checker that uses the system clock, and doesn't tolerate any clock skew.
+    pub fn new() -> Self {
+        Self::with_time_provider(SystemClock)
+    }
+
+    /// Creates a checker that gets the current time from `time`, and doesn't tolerate any clock
+    /// skew.
+    pub fn with_time_provider(time: impl TimeProvider + 'static) -> Self {
+        Self {
+            time: Box::new(time),
+            skew: 0,
+        }
+    }
+
+    /// Accepts certificates that become valid up to `skew` in the future, or that expired up to
+    /// `skew` ago, in case the local clock and the issuer's disagree.
+    pub fn set_clock_skew(&mut self, skew: Duration) {
+        self.skew = skew.as_secs().try_into().unwrap_or(i64::MAX);
+    }
+
+    /// Checks that the DER certificate `cert` is valid now.
+    ///
+    /// Both ends of the validity period are included, as RFC 5280 section 4.1.2.5 says.
+    pub fn check(&self, cert: &[u8]) -> Result<(), CertificateError> {
+        let (not_before, not_after) = Certificate::parse(cert)?.validity()?;
+        let now = self.time.now();
+        if now.saturating_add(self.skew) < not_before {
+            return Err(CertificateError::NotYetValid);
+        }
+        if now.saturating_sub(self.skew) > not_after {
+            return Err(CertificateError::Expired);
+        }
+        Ok(())
+    }
+}
+
+impl Default for ValidityChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The error that is returned when a certificate can't be parsed, or its signature or validity
+/// period doesn't check out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CertificateError {
+    /// The certificate isn't valid DER, or isn't structured like a certificate.
+    InvalidEncoding,
+    /// The certificate is signed with an algorithm, or by a type of key, that can't be verified.
+    UnsupportedAlgorithm,
+    /// The certificate is signed with an algorithm the issuer's key can't sign with.
+    KeyMismatch,
+    /// The issuer's public key couldn't be parsed.
+    InvalidIssuerKey,
+    /// The signature doesn't match the certificate and the issuer's key.
+    BadSignature,
+    /// The certificate's validity period hasn't started yet.
+    NotYetValid,
+    /// The certificate's validity period is over.
+    Expired,
+}
+
+impl From<InvalidDer> for CertificateError {
+    fn from(_: InvalidDer) -> Self {
+        Self::InvalidEncoding
+    }
+}
+
+impl From<AlgorithmError> for CertificateError {
+    fn from(err: AlgorithmError) -> Self {
+        match err {
+            AlgorithmError::InvalidEncoding => Self::InvalidEncoding,
+            AlgorithmError::UnsupportedAlgorithm => Self::UnsupportedAlgorithm,
+            AlgorithmError::KeyMismatch => Self::KeyMismatch,
+        }
+    }
+}
+
+impl From<SignatureError> for CertificateError {
+    fn from(err: SignatureError) -> Self {
+        match err {
+            SignatureError::MissingData | SignatureError::InvalidEncoding => Self::InvalidEncoding,
+            SignatureError::UnsupportedScheme => Self::UnsupportedAlgorithm,
+            SignatureError::InvalidPublicKey => Self::InvalidIssuerKey,
+            SignatureError::KeyMismatch => Self::KeyMismatch,
+            SignatureError::BadSignature => Self::BadSignature,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Certificate, CertificateError, TimeProvider, ValidityChecker};
+    use crate::connection::tests::CERT;
+    use crate::public_key::PublicKey;
+    use crylib::hash::{Hasher, Sha256};
+    use std::time::Duration;
+
+    // the validity period of `CERT`: 2024-01-01 00:00:00 to 2034-01-01 00:00:00
+    const NOT_BEFORE: i64 = 1704067200;
+    const NOT_AFTER: i64 = 2019686400;
+
+    struct FixedTime(i64);
+
+    impl TimeProvider for FixedTime {
+        fn now(&self) -> i64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn self_signed() {
+        let cert = Certificate::parse(&CERT).unwrap();
+        assert_eq!(cert.verify_signed_by(cert.spki()), Ok(()));
+        assert!(PublicKey::from_spki(cert.spki()).is_ok());
+
+        // the same certificate, with one bit of its serial number flipped
+        let mut tampered = CERT.to_vec();
+        tampered[14] ^= 1;
+        let tampered = Certificate::parse(&tampered).unwrap();
+        assert_eq!(
+            tampered.verify_signed_by(cert.spki()),
+            Err(CertificateError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn end_point() {
+        // `CERT` is signed with ECDSA and SHA-256
+        let cert = Certificate::parse(&CERT).unwrap();
+        assert_eq!(
+            cert.tls_server_end_point(),
+            Ok(Sha256::hash(&CERT).to_vec())
+        );
+    }
+
+    #[test]
+    fn names() {
+        // `CERT` is self-signed
+        let cert = Certificate::parse(&CERT).unwrap();
+        let subject = cert.subject().unwrap();
+        assert_eq!(subject.to_string(), "CN=turtls");
+        assert_eq!(subject.common_name(), Some("turtls"));
+        assert_eq!(cert.issuer(), Ok(subject));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            Certificate::parse(&CERT[..CERT.len() - 1]),
+            Err(CertificateError::InvalidEncoding)
+        );
+        let cert = Certificate::parse(&CERT).unwrap();
+        assert_eq!(
+            cert.verify_signed_by(&CERT),
+            Err(CertificateError::InvalidIssuerKey)
+        );
+    }
+
+    #[test]
+    fn validity() {
+        assert_eq!(
+            Certificate::parse(&CERT).unwrap().validity(),
+            Ok((NOT_BEFORE, NOT_AFTER))
+        );
+
+        let check = |now, skew| {
+            let mut validity = ValidityChecker::with_time_provider(FixedTime(now));
+            validity.set_clock_skew(Duration::from_secs(skew));
+            validity.check(&CERT)
+        };
+        assert_eq!(check(NOT_BEFORE, 0), Ok(()));
+        assert_eq!(check(NOT_AFTER, 0), Ok(()));
+        assert_eq!(check(NOT_BEFORE - 1, 0), Err(CertificateError::NotYetValid));
+        assert_eq!(check(NOT_AFTER + 1, 0), Err(CertificateError::Expired));
+        assert_eq!(check(NOT_BEFORE - 60, 60), Ok(()));
+        assert_eq!(check(NOT_AFTER + 60, 60), Ok(()));
+        assert_eq!(
+            check(NOT_BEFORE - 61, 60),
+            Err(CertificateError::NotYetValid)
+        );
+        assert_eq!(check(i64::MAX, u64::MAX), Ok(()));
+
+        assert!(ValidityChecker::new().check(&CERT).is_ok());
+    }
+}
@@ -1,8 +1,6 @@
-use crylib::{
-    ec::{AffinePoint, EllipticCurve, Secp256r1},
-    finite_field::FieldElement,
-};
+use crylib::ec::{ecdh, AffinePoint, Secp256r1};
 
+#[derive(Clone, Copy)]
 #[repr(u16)]
 pub enum CipherSuite {
     Aes128GcmSha256 = 0x1301,
@@ -19,6 +17,7 @@ impl CipherSuite {
 }
 
 #[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NamedGroup {
     Secp256r1 = 0x17,
     Secp384r1 = 0x18,
@@ -40,11 +39,13 @@ impl NamedGroup {
     }
 }
 
-pub struct GroupKeys {
-    pub secp256r1: FieldElement<<Secp256r1 as EllipticCurve>::Order>,
+/// Decodes an uncompressed secp256r1 point, checking that it's on the curve.
+pub fn decode_secp256r1_point(encoded: &[u8]) -> Option<AffinePoint<Secp256r1>> {
+    ecdh::decode_point(encoded).ok()
 }
 
 #[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SignatureScheme {
     RsaPkcs1Sha256 = 0x401,
     RsaPkcs1Sha384 = 0x501,
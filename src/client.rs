@@ -0,0 +1,1255 @@
+//! The client side of a TLS 1.3 connection.
+//!
+//! [`ClientConnection`] doesn't do any I/O itself: records received from the server are passed to
+//! [`ClientConnection::read_tls`], and whatever has to be sent back is taken from
+//! [`ClientConnection::take_outgoing`]. This keeps the state machine independent of how bytes are
+//! moved, and lets the tests drive both sides of a handshake in memory. The record layer is shared
+//! with the server, and lives in [`connection`](crate::connection).
+//!
+//! Only `TLS_AES_128_GCM_SHA256`, secp256r1, and `ecdsa_secp256r1_sha256` are offered, since they
+//! are all `crylib` implements so far. `HelloRetryRequest`s aren't supported. A
+//! server that asks for a certificate is sent the one set with
+//! [`ClientConnection::set_client_cert`], or an empty one if there isn't one.
+//!
+//! The certificates the server sends are handed to a verifier chosen by the caller, which decides
+//! whether to trust them. The handshake itself only checks that the server owns the key of the
+//! first one.
+//!
+//! A connection started with [`ClientConnection::with_server_name`] names the server it is
+//! connecting to, which servers that host several names need to pick a certificate.
+//!
+//! A connection started with [`ClientConnection::with_psk`] also offers an external PSK. If the
+//! server accepts it, the PSK authenticates both sides, and no certificates are sent at all.
+//!
+//! The session tickets the server sends once the handshake is done are kept, and can be taken with
+//! [`ClientConnection::take_tickets`]. Passing one to [`ClientConnection::resume`] starts a new
+//! connection that is authenticated by the ticket instead of a certificate. Servers only accept
+//! each ticket once, so a ticket shouldn't be offered again after it has been used.
+use std::mem;
+use std::sync::Arc;
+
+use crate::certificate::Certificate;
+use crate::cipher_suites::{CipherSuite, NamedGroup};
+use crate::client_hello::ClientHello;
+use crate::codec::{Codec, Reader, TlsVec};
+use crate::connection::{
+    CertVerifier, CertifiedKey, ChannelBindingError, ConnectionCore, ConnectionError, Handshaker,
+    CLIENT_CONTEXT, SERVER_CONTEXT,
+};
+use crate::extensions::Extension;
+use crate::handshake::{Handshake, ShakeType};
+use crate::key_schedule::{EarlySecret, HandshakeSecrets, TrafficSecrets, HASH_LEN};
+use crate::key_share::{KeyShares, SUPPORTED_GROUPS};
+use crate::padding::PaddingPolicy;
+use crate::private_key::PrivateKey;
+use crate::psk::{ExternalPsk, PskKeyExchangeMode, PskOffer};
+use crate::public_key::PublicKey;
+use crate::record::{Message, RecordHeader};
+use crate::server_hello::ServerHelloRef;
+use crate::ticket::{NewSessionTicket, SessionTicket};
+use crate::versions::ProtocolVersion;
+use crate::zeroizing::Zeroizing;
+
+/// A TLS 1.3 connection to a server.
+pub struct ClientConnection {
+    core: ConnectionCore,
+    handshaker: ClientHandshaker,
+}
+
+struct ClientHandshaker {
+    state: State,
+    verify_certs: Box<CertVerifier>,
+    cert: Option<CertifiedKey>,
+    psk: Option<OfferedPsk>,
+    /// The name the `ClientHello` sent, which the server can acknowledge, and which tickets are
+    /// resumed with.
+    server_name: Option<String>,
+    /// What the server chose in its `ServerHello`, once it has been received.
+    negotiated: Option<Negotiated>,
+    /// The secret the PSKs of session tickets are derived from, once the handshake is done.
+    resumption_master_secret: Option<Zeroizing<[u8; HASH_LEN]>>,
+    /// The session tickets the server has sent, oldest first.
+    tickets: Vec<SessionTicket>,
+}
+
+/// A PSK, along with the key exchanges it was offered for.
+struct OfferedPsk {
+    psk: ClientPsk,
+    modes: Vec<PskKeyExchangeMode>,
+}
+
+/// A PSK that can be offered.
+enum ClientPsk {
+    External(Arc<ExternalPsk>),
+    Ticket(SessionTicket),
+}
+
+impl ClientPsk {
+    fn offer(&self) -> PskOffer<'_> {
+        match self {
+            Self::External(psk) => psk.offer(),
+            Self::Ticket(ticket) => ticket.offer(),
+        }
+    }
+
+    fn early_secret(&self) -> EarlySecret {
+        match self {
+            Self::External(psk) => psk.early_secret(),
+            Self::Ticket(ticket) => ticket.early_secret(),
+        }
+    }
+}
+
+/// The parameters the server chose in its `ServerHello`.
+#[cfg_attr(not(feature = "probe"), allow(dead_code))]
+pub(crate) struct Negotiated {
+    pub version: ProtocolVersion,
+    pub cipher_suite: CipherSuite,
+    /// The group of the key exchange, or `None` if the PSK was accepted without one.
+    pub group: Option<NamedGroup>,
+    /// Whether the server accepted the PSK that was offered.
+    pub psk_accepted: bool,
+}
+
+enum State {
+    /// Waiting for the `ServerHello`, with the `ClientHello` in case its messages are retained.
+    WaitServerHello(KeyShares, Vec<u8>),
+    WaitEncryptedExtensions(ServerFlight),
+    /// Waiting for a `Certificate`, or a `CertificateRequest` if there hasn't been one yet.
+    ///
+    /// Servers that accepted the PSK skip straight to `Finished`.
+    WaitCertificate(ServerFlight),
+    WaitCertificateVerify(ServerFlight),
+    WaitFinished(ServerFlight),
+    Connected(TrafficSecrets),
+    /// The connection failed while a message was being processed.
+    Closed,
+}
+
+/// What is known while the server's encrypted messages are being received.
+struct ServerFlight {
+    secrets: HandshakeSecrets,
+    /// Whether the server accepted the PSK, which authenticates it instead of a certificate.
+    psk_accepted: bool,
+    cert_request: Option<CertificateRequest>,
+}
+
+/// The parts of the server's `CertificateRequest` that matter to the response.
+struct CertificateRequest {
+    context: Vec<u8>,
+    /// The signature schemes the server accepts.
+    schemes: Vec<u16>,
+}
+
+impl ClientConnection {
+    /// Starts a handshake, queueing the `ClientHello`.
+    ///
+    /// `verify_certs` is given the DER certificates the server sends, leaf first,
+    /// and returns whether to trust them.
+    pub fn new(
+        verify_certs: impl Fn(&[&[u8]]) -> bool + Send + Sync + 'static,
+    ) -> Result<Self, ConnectionError> {
+        Self::start(Box::new(verify_certs), None, None)
+    }
+
+    /// Starts a handshake that tells the server it is being connected to as `server_name`,
+    /// queueing the `ClientHello`.
+    ///
+    /// Servers that host more than one name use it to pick a certificate. `server_name` has to be
+    /// a DNS host name, not an IP address (RFC 6066 section 3). Whether the certificates the
+    /// server sends are valid for the name is still up to `verify_certs`.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if `server_name` is empty or longer than 255 bytes.
+    pub fn with_server_name(
+        server_name: &str,
+        verify_certs: impl Fn(&[&[u8]]) -> bool + Send + Sync + 'static,
+    ) -> Result<Self, ConnectionError> {
+        assert!(
+            (1..=255).contains(&server_name.len()),
+            "a server name is between 1 and 255 bytes long"
+        );
+        Self::start(Box::new(verify_certs), Some(server_name), None)
+    }
+
+    /// Starts a handshake that offers `psk` for the key exchanges in `modes`, queueing the
+    /// `ClientHello`.
+    ///
+    /// A server that doesn't know the PSK can still authenticate with a certificate, which is
+    /// handed to `verify_certs` like in [`ClientConnection::new`]. Clients that only trust the PSK
+    /// can pass a verifier that always returns `false`.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if `modes` is empty.
+    pub fn with_psk(
+        psk: Arc<ExternalPsk>,
+        modes: &[PskKeyExchangeMode],
+        verify_certs: impl Fn(&[&[u8]]) -> bool + Send + Sync + 'static,
+    ) -> Result<Self, ConnectionError> {
+        assert!(
+            !modes.is_empty(),
+            "a PSK has to be offered for some key exchange"
+        );
+        let psk = OfferedPsk {
+            psk: ClientPsk::External(psk),
+            modes: modes.to_vec(),
+        };
+        Self::start(Box::new(verify_certs), None, Some(psk))
+    }
+
+    /// Starts a handshake that resumes the connection `ticket` was sent on, queueing the
+    /// `ClientHello`.
+    ///
+    /// The ticket is only offered with `psk_dhe_ke`, so the new connection still has forward
+    /// secrecy. A server that doesn't accept the ticket, which it won't if it has expired or was
+    /// already used, can still authenticate with a certificate, which is handed to
+    /// `verify_certs` like in [`ClientConnection::new`].
+    ///
+    /// If the connection the ticket came from named the server, the new one sends the same name.
+    pub fn resume(
+        ticket: SessionTicket,
+        verify_certs: impl Fn(&[&[u8]]) -> bool + Send + Sync + 'static,
+    ) -> Result<Self, ConnectionError> {
+        let server_name = ticket.server_name().map(str::to_string);
+        let psk = OfferedPsk {
+            psk: ClientPsk::Ticket(ticket),
+            modes: vec![PskKeyExchangeMode::PskDheKe],
+        };
+        Self::start(Box::new(verify_certs), server_name.as_deref(), Some(psk))
+    }
+
+    fn start(
+        verify_certs: Box<CertVerifier>,
+        server_name: Option<&str>,
+        psk: Option<OfferedPsk>,
+    ) -> Result<Self, ConnectionError> {
+        // a key share is sent even with `psk_ke`, in case the server doesn't accept the PSK
+        let keys = KeyShares::generate(&SUPPORTED_GROUPS)?;
+        let client_hello = match &psk {
+            Some(offered) => ClientHello::new(
+                &keys,
+                server_name,
+                Some(&offered.psk.offer()),
+                &offered.modes,
+            )?,
+            None => ClientHello::new(&keys, server_name, None, &[])?,
+        };
+        let mut core = ConnectionCore::default();
+        core.send_plaintext_handshake(&client_hello);
+        let client_hello = client_hello[Message::PREFIIX_SIZE..].to_vec();
+        Ok(Self {
+            core,
+            handshaker: ClientHandshaker {
+                state: State::WaitServerHello(keys, client_hello),
+                verify_certs,
+                cert: None,
+                psk,
+                server_name: server_name.map(str::to_string),
+                negotiated: None,
+                resumption_master_secret: None,
+                tickets: Vec::new(),
+            },
+        })
+    }
+
+    /// Sets the certificate chain, leaf first, that is sent if the server asks for one.
+    ///
+    /// `key` is the private key of the leaf. Without a certificate, or if the server doesn't accept
+    /// signatures made with `key`, an empty certificate list is sent.
+    pub fn set_client_cert(&mut self, cert_chain: Vec<Vec<u8>>, key: PrivateKey) {
+        self.handshaker.cert = Some(CertifiedKey { cert_chain, key });
+    }
+
+    /// Keeps a copy of every handshake message, to be returned by
+    /// [`ClientConnection::handshake_messages`] once the handshake is done.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if a handshake message has already been received.
+    pub fn retain_handshake_messages(&mut self) {
+        let State::WaitServerHello(_, client_hello) = &self.handshaker.state else {
+            panic!("handshake messages have to be retained before the server's are read");
+        };
+        let client_hello = client_hello.clone();
+        self.core.transcript.retain_messages(vec![client_hello]);
+    }
+
+    /// Returns whether the handshake is still in progress.
+    pub fn is_handshaking(&self) -> bool {
+        !self.core.is_closed() && !self.handshaker.is_connected()
+    }
+
+    /// Returns whether the connection has been closed, either by an error or by the server.
+    pub fn is_closed(&self) -> bool {
+        self.core.is_closed()
+    }
+
+    /// Returns the public key of the server's certificate.
+    ///
+    /// The server has only proven that it owns the key once the handshake is done. Servers that
+    /// accepted a PSK don't send a certificate.
+    pub fn peer_public_key(&self) -> Option<&PublicKey> {
+        self.core.peer_public_key.as_ref()
+    }
+
+    /// Returns the `tls-server-end-point` channel binding of the connection (RFC 5929 section
+    /// 4.1), which is the hash of the server's certificate.
+    ///
+    /// Protocols such as SCRAM-SHA-256-PLUS send it inside their own authentication, so that a
+    /// server that terminates TLS with a different certificate is noticed. The hash function is
+    /// the one the certificate is signed with, or SHA-256 if that is MD5 or SHA-1.
+    pub fn tls_server_end_point(&self) -> Result<Vec<u8>, ChannelBindingError> {
+        // a connection that failed may have a certificate that was never proven to be the server's
+        if !self.handshaker.is_connected() {
+            return Err(ChannelBindingError::Handshaking);
+        }
+        let cert = self
+            .core
+            .peer_certificate
+            .as_ref()
+            .ok_or(ChannelBindingError::NoCertificate)?;
+        Ok(Certificate::parse(cert)?.tls_server_end_point()?)
+    }
+
+    /// Returns what the server chose in its `ServerHello`, once it has been received.
+    #[cfg_attr(not(feature = "probe"), allow(dead_code))]
+    pub(crate) fn negotiated(&self) -> Option<&Negotiated> {
+        self.handshaker.negotiated.as_ref()
+    }
+
+    /// Exports `K_LEN` bytes of keying material for the application, as described in RFC 8446
+    /// section 7.5. The server exports the same bytes for the same `label` and `context`.
+    ///
+    /// Returns [`ConnectionError::Handshaking`] until the handshake is done.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if `label` is longer than 249 bytes.
+    pub fn export_keying_material<const K_LEN: usize>(
+        &self,
+        label: &[u8],
+        context: &[u8],
+    ) -> Result<[u8; K_LEN], ConnectionError> {
+        self.core
+            .export_keying_material(&self.handshaker, label, context)
+    }
+
+    /// Returns every handshake message that was sent or received, in order and as serialized,
+    /// including their headers.
+    ///
+    /// Returns `None` until the handshake is done, or if
+    /// [`ClientConnection::retain_handshake_messages`] wasn't called. Messages sent after the
+    /// handshake, such as session tickets, aren't included.
+    pub fn handshake_messages(&self) -> Option<&[Vec<u8>]> {
+        self.core.handshake_messages(&self.handshaker)
+    }
+
+    /// Takes the session tickets the server has sent so far, oldest first.
+    ///
+    /// Only the last few tickets are kept, if the server sends more than that.
+    pub fn take_tickets(&mut self) -> Vec<SessionTicket> {
+        mem::take(&mut self.handshaker.tickets)
+    }
+
+    /// Takes the bytes that need to be sent to the server.
+    pub fn take_outgoing(&mut self) -> Vec<u8> {
+        self.core.take_outgoing()
+    }
+
+    /// Takes the application data received from the server.
+    pub fn take_received(&mut self) -> Vec<u8> {
+        self.core.take_received()
+    }
+
+    /// Processes the whole records at the front of `buf`, returning how many bytes were used.
+    ///
+    /// The bytes of a record that hasn't been completely received are left for the next call.
+    /// Records are decrypted in place, so `buf` is overwritten.
+    ///
+    /// On failure, the connection is closed and the alert for the error is queued.
+    pub fn read_tls(&mut self, buf: &mut [u8]) -> Result<usize, ConnectionError> {
+        self.core.read_tls(&mut self.handshaker, buf)
+    }
+
+    /// Parses the header of the next record the server sent.
+    ///
+    /// On failure, the connection is closed and the alert for the error is queued.
+    pub(crate) fn read_header(
+        &mut self,
+        header: &[u8; RecordHeader::SIZE],
+    ) -> Result<RecordHeader, ConnectionError> {
+        self.core.read_header(header)
+    }
+
+    /// Encrypts `data` and queues it to be sent.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), ConnectionError> {
+        self.core.write(&self.handshaker, data)
+    }
+
+    /// Encrypts `data` and queues it to be sent, padded and split into records as `policy` says,
+    /// to hide its length from anyone watching the connection.
+    ///
+    /// The server removes the padding, so it receives `data` as if it had been written with
+    /// [`ClientConnection::write`].
+    pub fn write_all_padded(
+        &mut self,
+        data: &[u8],
+        policy: PaddingPolicy,
+    ) -> Result<(), ConnectionError> {
+        self.core.write_padded(&self.handshaker, data, policy)
+    }
+}
+
+impl Handshaker for ClientHandshaker {
+    fn is_connected(&self) -> bool {
+        matches!(self.state, State::Connected(_))
+    }
+
+    fn read_handshake(
+        &mut self,
+        core: &mut ConnectionCore,
+        msg: &[u8],
+    ) -> Result<(), ConnectionError> {
+        let msg_type = msg[0];
+        let body = &msg[Handshake::PREFIX_SIZE..];
+        self.state = match mem::replace(&mut self.state, State::Closed) {
+            State::WaitServerHello(keys, _) if msg_type == ShakeType::ServerHello as u8 => {
+                let (state, negotiated) = read_server_hello(core, keys, self.psk.as_ref(), msg)?;
+                self.negotiated = Some(negotiated);
+                state
+            },
+            State::WaitEncryptedExtensions(flight)
+                if msg_type == ShakeType::EncryptedExtensions as u8 =>
+            {
+                read_encrypted_extensions(body, self.server_name.is_some())?;
+                core.transcript.update(msg);
+                match flight.psk_accepted {
+                    true => State::WaitFinished(flight),
+                    false => State::WaitCertificate(flight),
+                }
+            },
+            State::WaitCertificate(mut flight)
+                if msg_type == ShakeType::CertificateRequest as u8
+                    && flight.cert_request.is_none() =>
+            {
+                flight.cert_request = Some(read_certificate_request(body)?);
+                core.transcript.update(msg);
+                State::WaitCertificate(flight)
+            },
+            State::WaitCertificate(flight) if msg_type == ShakeType::Certificate as u8 => {
+                // only certificates sent in response to a `CertificateRequest` have a context
+                if !core.read_certificate(msg, &[], &self.verify_certs)? {
+                    return Err(ConnectionError::Decode);
+                }
+                State::WaitCertificateVerify(flight)
+            },
+            State::WaitCertificateVerify(flight)
+                if msg_type == ShakeType::CertificateVerify as u8 =>
+            {
+                core.read_certificate_verify(msg, SERVER_CONTEXT)?;
+                State::WaitFinished(flight)
+            },
+            State::WaitFinished(flight) if msg_type == ShakeType::Finished as u8 => {
+                self.read_finished(core, flight, msg)?
+            },
+            State::Connected(secrets) if msg_type == ShakeType::NewSessionTicket as u8 => {
+                self.read_new_session_ticket(body)?;
+                State::Connected(secrets)
+            },
+            State::Connected(mut secrets) if msg_type == ShakeType::KeyUpdate as u8 => {
+                core.read_key_update(body, &mut secrets.client, &mut secrets.server)?;
+                State::Connected(secrets)
+            },
+            _ => return Err(ConnectionError::UnexpectedMessage),
+        };
+        Ok(())
+    }
+}
+
+impl ClientHandshaker {
+    /// How many session tickets are kept, after which the oldest are dropped.
+    const MAX_TICKETS: usize = 8;
+
+    fn read_finished(
+        &mut self,
+        core: &mut ConnectionCore,
+        flight: ServerFlight,
+        msg: &[u8],
+    ) -> Result<State, ConnectionError> {
+        core.read_finished(msg, &flight.secrets.server)?;
+        let transcript_hash = core.transcript_hash();
+        let secrets = flight.secrets.application_secrets(&transcript_hash);
+        core.set_reader_secret(&secrets.server);
+        let exporter_master_secret = flight
+            .secrets
+            .master_secret()
+            .exporter_master_secret(&transcript_hash);
+        core.exporter_master_secret = Some(Zeroizing::new(exporter_master_secret));
+
+        if let Some(request) = flight.cert_request {
+            match &self.cert {
+                Some(cert)
+                    if cert
+                        .key
+                        .scheme()
+                        .is_some_and(|scheme| request.schemes.contains(&(scheme as u16))) =>
+                {
+                    core.send_certificate(&request.context, &cert.cert_chain);
+                    core.send_certificate_verify(&cert.key, CLIENT_CONTEXT)?;
+                },
+                // without a certificate the server accepts, an empty list is sent
+                _ => core.send_certificate(&request.context, &[]),
+            }
+        }
+        core.send_finished(&flight.secrets.client);
+        core.set_writer_secret(&secrets.client);
+        // the transcript ends with our `Finished`
+        let resumption_master_secret = flight
+            .secrets
+            .master_secret()
+            .resumption_master_secret(&core.transcript_hash());
+        self.resumption_master_secret = Some(Zeroizing::new(resumption_master_secret));
+        Ok(State::Connected(secrets))
+    }
+
+    /// Reads the body of a `NewSessionTicket` message, keeping the ticket.
+    fn read_new_session_ticket(&mut self, body: &[u8]) -> Result<(), ConnectionError> {
+        let mut reader = Reader::new(body);
+        let msg = NewSessionTicket::decode(&mut reader)?;
+        reader.finish()?;
+        let Some(resumption_master_secret) = &self.resumption_master_secret else {
+            unreachable!("tickets are only read once the handshake is done");
+        };
+        let server_name = self.server_name.as_deref();
+        if let Some(ticket) = SessionTicket::new(&msg, resumption_master_secret, server_name) {
+            if self.tickets.len() == Self::MAX_TICKETS {
+                self.tickets.remove(0);
+            }
+            self.tickets.push(ticket);
+        }
+        Ok(())
+    }
+}
+
+/// Reads the `ServerHello`, installing the handshake traffic secrets and returning what the
+/// server chose.
+///
+/// `psk` is the PSK that was offered, if any.
+fn read_server_hello(
+    core: &mut ConnectionCore,
+    keys: KeyShares,
+    psk: Option<&OfferedPsk>,
+    msg: &[u8],
+) -> Result<(State, Negotiated), ConnectionError> {
+    const SUPPORTED_VERSIONS: u16 = Extension::SupportedVersions as u16;
+    const KEY_SHARE: u16 = Extension::KeyShare as u16;
+    const PRE_SHARED_KEY: u16 = Extension::PreSharedKey as u16;
+
+    let server_hello = ServerHelloRef::parse_from_handshake(&msg[Handshake::PREFIX_SIZE..])?;
+    if *server_hello.random_bytes == ServerHelloRef::HELLO_RETRY_RANDOM {
+        return Err(ConnectionError::HelloRetryRequest);
+    }
+    // the server has to echo our empty session ID
+    if !server_hello.session_id.is_empty() {
+        return Err(ConnectionError::IllegalParameter);
+    }
+
+    let mut version = None;
+    let mut key_share = None;
+    let mut selected_psk = None;
+    let mut extensions = Reader::new(server_hello.extensions);
+    while !extensions.is_empty() {
+        let extension = extensions.read_u16()?;
+        let mut data = extensions.read_u16_prefixed()?;
+        match extension {
+            SUPPORTED_VERSIONS if version.is_none() => version = Some(data.read_u16()?),
+            KEY_SHARE if key_share.is_none() => {
+                let group = data.read_u16()?;
+                let key_exchange = data.read_u16_prefixed()?.read_rest();
+                key_share = Some((group, key_exchange));
+            },
+            PRE_SHARED_KEY if selected_psk.is_none() && psk.is_some() => {
+                selected_psk = Some(data.read_u16()?);
+            },
+            _ => return Err(ConnectionError::UnsupportedExtension),
+        }
+        data.finish()?;
+    }
+
+    match version {
+        Some(version) if version == ProtocolVersion::TlsOnePointThree as u16 => {},
+        // only TLS 1.3 was offered
+        Some(_) => return Err(ConnectionError::IllegalParameter),
+        // without the extension, the server negotiated TLS 1.2 or earlier
+        None => return Err(ConnectionError::ProtocolVersion),
+    }
+    let psk = match selected_psk {
+        None => None,
+        // only one PSK is ever offered
+        Some(0) => psk,
+        Some(_) => return Err(ConnectionError::IllegalParameter),
+    };
+    let mut selected_group = None;
+    let shared_secret = match key_share {
+        // the server can only use a key exchange with the PSK if that was offered
+        Some(_) if psk.is_some_and(|psk| !psk.modes.contains(&PskKeyExchangeMode::PskDheKe)) => {
+            return Err(ConnectionError::IllegalParameter);
+        },
+        Some((group, key_exchange)) => {
+            // the server can only pick one of the groups that were offered
+            let key = keys
+                .select(group)
+                .ok_or(ConnectionError::IllegalParameter)?;
+            selected_group = Some(key.group());
+            key.shared_secret(key_exchange)
+                .ok_or(ConnectionError::IllegalParameter)?
+        },
+        // `psk_ke` has no shared secret, so zeros take its place (RFC 8446 section 7.1)
+        None if psk.is_some_and(|psk| psk.modes.contains(&PskKeyExchangeMode::PskKe)) => {
+            Zeroizing::new([0; HASH_LEN])
+        },
+        None => return Err(ConnectionError::MissingExtension),
+    };
+
+    // the keys change after this message
+    core.check_record_boundary()?;
+    core.transcript.update(msg);
+    let early_secret = match psk {
+        Some(offered) => offered.psk.early_secret(),
+        None => EarlySecret::new(None),
+    };
+    let secrets = early_secret.handshake_secrets(&*shared_secret, &core.transcript_hash());
+    core.set_reader_secret(&secrets.server);
+    core.set_writer_secret(&secrets.client);
+    let state = State::WaitEncryptedExtensions(ServerFlight {
+        secrets,
+        psk_accepted: psk.is_some(),
+        cert_request: None,
+    });
+    let negotiated = Negotiated {
+        version: ProtocolVersion::TlsOnePointThree,
+        cipher_suite: server_hello.cipher_suite,
+        group: selected_group,
+        psk_accepted: psk.is_some(),
+    };
+    Ok((state, negotiated))
+}
+
+/// Reads the body of an `EncryptedExtensions` message.
+///
+/// Servers can send `supported_groups` here to say which groups they prefer. That only matters to
+/// later connections, so it is ignored. If `server_name_sent`, the server can also acknowledge
+/// the name with an empty `server_name` extension (RFC 6066 section 3). Anything else wasn't
+/// offered, or doesn't belong in this message (RFC 8446 section 4.2).
+fn read_encrypted_extensions(body: &[u8], server_name_sent: bool) -> Result<(), ConnectionError> {
+    const SERVER_NAME: u16 = Extension::ServerName as u16;
+    const SUPPORTED_GROUPS: u16 = Extension::SupportedGroups as u16;
+    const OFFERED: [u16; 5] = [
+        Extension::SignatureAlgorithms as u16,
+        Extension::SupportedVersions as u16,
+        Extension::KeyShare as u16,
+        Extension::PskExchangeModes as u16,
+        Extension::PreSharedKey as u16,
+    ];
+
+    let mut reader = Reader::new(body);
+    let mut extensions = reader.read_u16_prefixed()?;
+    reader.finish()?;
+
+    let mut seen = Vec::new();
+    while !extensions.is_empty() {
+        let extension = extensions.read_u16()?;
+        let data = extensions.read_u16_prefixed()?;
+        if seen.contains(&extension) {
+            return Err(ConnectionError::IllegalParameter);
+        }
+        seen.push(extension);
+        match extension {
+            SUPPORTED_GROUPS => {},
+            SERVER_NAME if server_name_sent => data.finish()?,
+            SERVER_NAME => return Err(ConnectionError::UnsupportedExtension),
+            x if OFFERED.contains(&x) => return Err(ConnectionError::IllegalParameter),
+            _ => return Err(ConnectionError::UnsupportedExtension),
+        }
+    }
+    Ok(())
+}
+
+/// Reads the body of a `CertificateRequest` message.
+fn read_certificate_request(body: &[u8]) -> Result<CertificateRequest, ConnectionError> {
+    const SIGNATURE_ALGORITHMS: u16 = Extension::SignatureAlgorithms as u16;
+
+    let mut reader = Reader::new(body);
+    let context = reader.read_u8_prefixed()?.read_rest().to_vec();
+    let mut extensions = reader.read_u16_prefixed()?;
+    reader.finish()?;
+
+    let mut schemes = None;
+    while !extensions.is_empty() {
+        let extension = extensions.read_u16()?;
+        let mut data = extensions.read_u16_prefixed()?;
+        if extension == SIGNATURE_ALGORITHMS {
+            if schemes.is_some() {
+                return Err(ConnectionError::IllegalParameter);
+            }
+            schemes = Some(TlsVec::<u16, u16>::decode(&mut data)?.into_inner());
+            data.finish()?;
+        }
+        // the other extensions only narrow down which certificate to send, and we only have one
+    }
+    Ok(CertificateRequest {
+        context,
+        schemes: schemes.ok_or(ConnectionError::MissingExtension)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{read_encrypted_extensions, ClientConnection};
+    use crate::aead::{AeadReader, AeadWriter};
+    use crate::alert::AlertDescription;
+    use crate::cipher_suites::{CipherSuite, NamedGroup, SignatureScheme};
+    use crate::codec::{Reader, Writer};
+    use crate::connection::tests::seal;
+    use crate::connection::tests::{cert_key, CERT};
+    use crate::connection::{handshake_message, signed_content, ConnectionError, SERVER_CONTEXT};
+    use crate::extensions::Extension;
+    use crate::handshake::{Handshake, ShakeType};
+    use crate::key_schedule::{self, HandshakeSecrets};
+    use crate::key_share::EphemeralKey;
+    use crate::psk::{ExternalPsk, PskHash, PskKeyExchangeMode};
+    use crate::public_key::PublicKey;
+    use crate::record::{self, ContentType, Message, RecordHeader};
+    use crate::server_hello::{SerHelloParseError, ServerHelloRef};
+    use crate::signature::SignatureError;
+    use crate::versions::ProtocolVersion;
+    use crylib::hash::{Hasher, Sha256};
+
+    /// What the test server gets wrong on purpose.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Tamper {
+        Nothing,
+        RetryRequest,
+        Signature,
+        Finished,
+    }
+
+    /// The server side of a handshake, scripted with the same helpers as the client.
+    struct TestServer {
+        transcript: Vec<u8>,
+        secrets: HandshakeSecrets,
+        reader: AeadReader,
+        writer: AeadWriter,
+    }
+
+    impl TestServer {
+        /// Answers the `ClientHello` record `client_hello`, returning the server and its first
+        /// flight. The encrypted messages are split into records of at most `record_len` bytes.
+        fn respond(client_hello: &[u8], tamper: Tamper, record_len: usize) -> (Self, Vec<u8>) {
+            let client_hello = &client_hello[Message::PREFIIX_SIZE..];
+            let key = EphemeralKey::generate(NamedGroup::Secp256r1).unwrap();
+
+            let mut body = ProtocolVersion::TlsOnePointTwo.to_be_bytes().to_vec();
+            body.extend_from_slice(&match tamper {
+                Tamper::RetryRequest => ServerHelloRef::HELLO_RETRY_RANDOM,
+                _ => [0x42; 32],
+            });
+            body.push(0);
+            body.extend_from_slice(&CipherSuite::Aes128GcmSha256.to_be_bytes());
+            body.push(0);
+            let mut extensions = Extension::SupportedVersions.to_be_bytes().to_vec();
+            extensions.extend_from_slice(&[0x00, 0x02, 0x03, 0x04]);
+            extensions.extend_from_slice(&Extension::KeyShare.to_be_bytes());
+            extensions.extend_from_slice(&[0x00, 0x45, 0x00, 0x17, 0x00, 0x41]);
+            extensions.extend_from_slice(&key.entry().key_exchange);
+            body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+            body.extend_from_slice(&extensions);
+            let server_hello = handshake_message(ShakeType::ServerHello, &body);
+
+            let mut flight = plaintext(ContentType::Handshake, &server_hello);
+            let mut transcript = client_hello.to_vec();
+            transcript.extend_from_slice(&server_hello);
+            let shared_secret = key.shared_secret(&client_key_share(client_hello)).unwrap();
+            let secrets = HandshakeSecrets::new(&*shared_secret, &Sha256::hash(&transcript));
+            let mut writer = AeadWriter::new(key_schedule::traffic_keys(&secrets.server));
+            let reader = AeadReader::new(key_schedule::traffic_keys(&secrets.client));
+
+            let mut msgs = Vec::new();
+            let mut push = |msg: Vec<u8>, transcript: &mut Vec<u8>| {
+                transcript.extend_from_slice(&msg);
+                msgs.extend_from_slice(&msg);
+            };
+            push(
+                handshake_message(ShakeType::EncryptedExtensions, &[0, 0]),
+                &mut transcript,
+            );
+
+            let mut certificate = vec![0];
+            certificate.extend_from_slice(&u24(CERT.len() + 5));
+            certificate.extend_from_slice(&u24(CERT.len()));
+            certificate.extend_from_slice(&CERT);
+            certificate.extend_from_slice(&[0, 0]);
+            push(
+                handshake_message(ShakeType::Certificate, &certificate),
+                &mut transcript,
+            );
+
+            let mut content = signed_content(SERVER_CONTEXT, &Sha256::hash(&transcript));
+            if tamper == Tamper::Signature {
+                content[0] ^= 1;
+            }
+            let signature = cert_key()
+                .sign(SignatureScheme::EcdsaSecp256r1Sha256, &content)
+                .unwrap();
+            let mut buf = [0; 128];
+            let mut sig_writer = Writer::new(&mut buf);
+            signature.encode(&mut sig_writer).unwrap();
+            push(
+                handshake_message(ShakeType::CertificateVerify, sig_writer.written()),
+                &mut transcript,
+            );
+
+            let mut verify_data =
+                key_schedule::finished_verify_data(&secrets.server, &Sha256::hash(&transcript));
+            if tamper == Tamper::Finished {
+                verify_data[0] ^= 1;
+            }
+            push(
+                handshake_message(ShakeType::Finished, &verify_data),
+                &mut transcript,
+            );
+
+            for chunk in msgs.chunks(record_len) {
+                seal(&mut writer, ContentType::Handshake, chunk, &mut flight).unwrap();
+            }
+            let server = Self {
+                transcript,
+                secrets,
+                reader,
+                writer,
+            };
+            (server, flight)
+        }
+
+        /// Checks the client's `Finished` record, then switches to the application keys.
+        fn read_finished(&mut self, record: &mut [u8]) {
+            let (content_type, finished) = open(&mut self.reader, record);
+            assert_eq!(content_type, ContentType::Handshake);
+            let verify_data = key_schedule::finished_verify_data(
+                &self.secrets.client,
+                &Sha256::hash(&self.transcript),
+            );
+            assert_eq!(
+                finished,
+                handshake_message(ShakeType::Finished, &verify_data)
+            );
+
+            // the application secrets don't depend on the client's `Finished`
+            let secrets = self
+                .secrets
+                .application_secrets(&Sha256::hash(&self.transcript));
+            self.reader = AeadReader::new(key_schedule::traffic_keys(&secrets.client));
+            self.writer = AeadWriter::new(key_schedule::traffic_keys(&secrets.server));
+        }
+    }
+
+    fn u24(len: usize) -> [u8; 3] {
+        let [_, len @ ..] = (len as u32).to_be_bytes();
+        len
+    }
+
+    fn plaintext(content_type: ContentType, content: &[u8]) -> Vec<u8> {
+        let mut record = Message::start(content_type);
+        record.extend_from_slice(content);
+        record.finish();
+        record.to_vec()
+    }
+
+    /// Opens the single encrypted record `record`.
+    fn open(reader: &mut AeadReader, record: &mut [u8]) -> (ContentType, Vec<u8>) {
+        assert_eq!(record::record_len(record, true), Ok(Some(record.len())));
+        let (header, fragment) = record.split_at_mut(RecordHeader::SIZE);
+        let header = (&*header).try_into().unwrap();
+        let (content_type, len) = record::open_record(reader, header, fragment).unwrap();
+        (content_type, fragment[..len].to_vec())
+    }
+
+    /// Finds the secp256r1 key share in the body of a `ClientHello` message.
+    fn client_key_share(client_hello: &[u8]) -> Vec<u8> {
+        let mut reader = Reader::new(&client_hello[Handshake::PREFIX_SIZE..]);
+        reader.read_u16().unwrap();
+        reader.read_array::<32>().unwrap();
+        reader.read_u8_prefixed().unwrap();
+        reader.read_u16_prefixed().unwrap();
+        assert_eq!(reader.read_u8_prefixed().unwrap().read_rest(), [0]);
+        let mut extensions = reader.read_u16_prefixed().unwrap();
+        assert!(reader.is_empty());
+        loop {
+            let extension = extensions.read_u16().unwrap();
+            let mut data = extensions.read_u16_prefixed().unwrap();
+            if extension == Extension::KeyShare as u16 {
+                let mut shares = data.read_u16_prefixed().unwrap();
+                assert_eq!(shares.read_u16(), Ok(0x17));
+                return shares.read_u16_prefixed().unwrap().read_rest().to_vec();
+            }
+        }
+    }
+
+    fn client() -> ClientConnection {
+        ClientConnection::new(|certs| certs == [&CERT[..]]).unwrap()
+    }
+
+    /// Runs a whole handshake, with the server's encrypted messages split into records of at most
+    /// `record_len` bytes.
+    fn connect(record_len: usize) -> (ClientConnection, TestServer) {
+        let mut client = client();
+        let client_hello = client.take_outgoing();
+        let (mut server, mut flight) =
+            TestServer::respond(&client_hello, Tamper::Nothing, record_len);
+
+        assert_eq!(client.read_tls(&mut flight), Ok(flight.len()));
+        assert!(!client.is_handshaking());
+        server.read_finished(&mut client.take_outgoing());
+        (client, server)
+    }
+
+    #[test]
+    fn handshake() {
+        let (mut client, mut server) = connect(RecordHeader::MAX_PLAINTEXT_LEN as usize);
+        assert_eq!(
+            client.peer_public_key(),
+            Some(&PublicKey::from_spki(&CERT[97..][..91]).unwrap())
+        );
+        let negotiated = client.negotiated().unwrap();
+        assert_eq!(
+            negotiated.cipher_suite as u16,
+            CipherSuite::Aes128GcmSha256 as u16
+        );
+        assert_eq!(negotiated.group, Some(NamedGroup::Secp256r1));
+
+        client.write(b"hello").unwrap();
+        let (content_type, data) = open(&mut server.reader, &mut client.take_outgoing());
+        assert_eq!(
+            (content_type, &data[..]),
+            (ContentType::ApplicationData, &b"hello"[..])
+        );
+
+        let mut record = Vec::new();
+        seal(
+            &mut server.writer,
+            ContentType::ApplicationData,
+            b"world",
+            &mut record,
+        )
+        .unwrap();
+        assert_eq!(client.read_tls(&mut record), Ok(record.len()));
+        assert_eq!(client.take_received(), b"world");
+    }
+
+    #[test]
+    #[should_panic]
+    fn retain_after_server_hello() {
+        let (mut client, _) = connect(RecordHeader::MAX_PLAINTEXT_LEN as usize);
+        client.retain_handshake_messages();
+    }
+
+    #[test]
+    fn server_name() {
+        let mut client = ClientConnection::with_server_name("example.com", |_| false).unwrap();
+        let client_hello = client.take_outgoing();
+        let mut extension = vec![0x00, 0x00, 0x00, 0x10, 0x00, 0x0e, 0x00, 0x00, 0x0b];
+        extension.extend_from_slice(b"example.com");
+        assert!(client_hello
+            .windows(extension.len())
+            .any(|window| window == extension));
+    }
+
+    #[test]
+    fn fragmented() {
+        // every encrypted message is split across records
+        let mut client = client();
+        let client_hello = client.take_outgoing();
+        let (mut server, flight) = TestServer::respond(&client_hello, Tamper::Nothing, 50);
+
+        // and the records arrive a few bytes at a time
+        let mut buf = Vec::new();
+        for chunk in flight.chunks(7) {
+            buf.extend_from_slice(chunk);
+            let used = client.read_tls(&mut buf).unwrap();
+            buf.drain(..used);
+        }
+        assert!(buf.is_empty());
+        assert!(!client.is_handshaking());
+        server.read_finished(&mut client.take_outgoing());
+    }
+
+    #[test]
+    fn compatibility_change_cipher_spec() {
+        let mut client = client();
+        let client_hello = client.take_outgoing();
+        let (mut server, mut flight) = TestServer::respond(&client_hello, Tamper::Nothing, 0x4000);
+
+        let server_hello_len = record::record_len(&flight, false).unwrap().unwrap();
+        let change_cipher_spec = plaintext(ContentType::ChangeCipherSpec, &[1]);
+        flight.splice(server_hello_len..server_hello_len, change_cipher_spec);
+        assert_eq!(client.read_tls(&mut flight), Ok(flight.len()));
+        server.read_finished(&mut client.take_outgoing());
+
+        // but not once the handshake is done
+        let mut change_cipher_spec = plaintext(ContentType::ChangeCipherSpec, &[1]);
+        assert_eq!(
+            client.read_tls(&mut change_cipher_spec),
+            Err(ConnectionError::UnexpectedMessage)
+        );
+    }
+
+    #[test]
+    fn key_update() {
+        let (mut client, mut server) = connect(0x4000);
+
+        let mut record = Vec::new();
+        let key_update = handshake_message(ShakeType::KeyUpdate, &[1]);
+        seal(
+            &mut server.writer,
+            ContentType::Handshake,
+            &key_update,
+            &mut record,
+        )
+        .unwrap();
+        assert_eq!(client.read_tls(&mut record), Ok(record.len()));
+
+        // the client answers with the old keys
+        let (content_type, response) = open(&mut server.reader, &mut client.take_outgoing());
+        assert_eq!(content_type, ContentType::Handshake);
+        assert_eq!(response, handshake_message(ShakeType::KeyUpdate, &[0]));
+
+        let secrets = server
+            .secrets
+            .application_secrets(&Sha256::hash(&server.transcript));
+        let client_secret = key_schedule::next_traffic_secret(&secrets.client);
+        let server_secret = key_schedule::next_traffic_secret(&secrets.server);
+        server.reader = AeadReader::new(key_schedule::traffic_keys(&client_secret));
+        server.writer = AeadWriter::new(key_schedule::traffic_keys(&server_secret));
+
+        client.write(b"hello").unwrap();
+        let (_, data) = open(&mut server.reader, &mut client.take_outgoing());
+        assert_eq!(data, b"hello");
+
+        let mut record = Vec::new();
+        seal(
+            &mut server.writer,
+            ContentType::ApplicationData,
+            b"world",
+            &mut record,
+        )
+        .unwrap();
+        assert_eq!(client.read_tls(&mut record), Ok(record.len()));
+        assert_eq!(client.take_received(), b"world");
+    }
+
+    #[test]
+    fn close_notify() {
+        let (mut client, mut server) = connect(0x4000);
+        let mut record = Vec::new();
+        seal(&mut server.writer, ContentType::Alert, &[1, 0], &mut record).unwrap();
+        assert_eq!(client.read_tls(&mut record), Ok(record.len()));
+        assert!(client.is_closed());
+        assert_eq!(client.write(b"hello"), Err(ConnectionError::Closed));
+    }
+
+    #[test]
+    fn tampered() {
+        let failures = [
+            (Tamper::RetryRequest, ConnectionError::HelloRetryRequest),
+            (
+                Tamper::Signature,
+                ConnectionError::Signature(SignatureError::BadSignature),
+            ),
+            (Tamper::Finished, ConnectionError::BadFinished),
+        ];
+        for (tamper, err) in failures {
+            let mut client = client();
+            let client_hello = client.take_outgoing();
+            let (mut server, mut flight) = TestServer::respond(&client_hello, tamper, 0x4000);
+            assert_eq!(client.read_tls(&mut flight), Err(err));
+            assert!(client.is_closed());
+            assert_eq!(client.read_tls(&mut flight), Err(ConnectionError::Closed));
+
+            // the alert is encrypted once the client has keys
+            let description = err.alert().unwrap() as u8;
+            let mut alert = client.take_outgoing();
+            if tamper == Tamper::RetryRequest {
+                assert_eq!(alert, plaintext(ContentType::Alert, &[2, description]));
+            } else {
+                let (content_type, alert) = open(&mut server.reader, &mut alert);
+                assert_eq!(
+                    (content_type, &alert[..]),
+                    (ContentType::Alert, &[2, description][..])
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejected_certificate() {
+        let mut client = ClientConnection::new(|_| false).unwrap();
+        let client_hello = client.take_outgoing();
+        let (_, mut flight) = TestServer::respond(&client_hello, Tamper::Nothing, 0x4000);
+        assert_eq!(
+            client.read_tls(&mut flight),
+            Err(ConnectionError::CertificateRejected)
+        );
+    }
+
+    #[test]
+    fn not_connected() {
+        let mut client = client();
+        assert!(client.is_handshaking());
+        assert_eq!(client.write(b"hello"), Err(ConnectionError::Handshaking));
+
+        // application data isn't allowed before the handshake is encrypted
+        let mut record = plaintext(ContentType::ApplicationData, b"hello");
+        assert_eq!(
+            client.read_tls(&mut record),
+            Err(ConnectionError::InvalidRecord(
+                AlertDescription::UnexpectedMessage
+            ))
+        );
+    }
+
+    /// Returns the record of a `ServerHello` with the extensions `extensions`, each given as its
+    /// type and data, after `supported_versions`.
+    fn server_hello(extensions: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut body = ProtocolVersion::TlsOnePointTwo.to_be_bytes().to_vec();
+        body.extend_from_slice(&[0x42; 32]);
+        body.push(0);
+        body.extend_from_slice(&CipherSuite::Aes128GcmSha256.to_be_bytes());
+        body.push(0);
+        let mut encoded = Extension::SupportedVersions.to_be_bytes().to_vec();
+        encoded.extend_from_slice(&[0x00, 0x02, 0x03, 0x04]);
+        for (extension, data) in extensions {
+            encoded.extend_from_slice(&extension.to_be_bytes());
+            encoded.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            encoded.extend_from_slice(data);
+        }
+        body.extend_from_slice(&(encoded.len() as u16).to_be_bytes());
+        body.extend_from_slice(&encoded);
+        plaintext(
+            ContentType::Handshake,
+            &handshake_message(ShakeType::ServerHello, &body),
+        )
+    }
+
+    #[test]
+    fn psk_server_hello() {
+        const KEY_SHARE: u16 = Extension::KeyShare as u16;
+        const PRE_SHARED_KEY: u16 = Extension::PreSharedKey as u16;
+
+        let psk = Arc::new(
+            ExternalPsk::new(b"client1".to_vec(), vec![0x42; 32], PskHash::Sha256).unwrap(),
+        );
+        let key = EphemeralKey::generate(NamedGroup::Secp256r1).unwrap();
+        let mut key_share = vec![0x00, 0x17, 0x00, 0x41];
+        key_share.extend_from_slice(&key.entry().key_exchange);
+
+        let failures = [
+            // a PSK that wasn't offered
+            (
+                &[][..],
+                server_hello(&[(PRE_SHARED_KEY, &[0, 0])]),
+                ConnectionError::UnsupportedExtension,
+            ),
+            // only one PSK was offered
+            (
+                &[PskKeyExchangeMode::PskDheKe][..],
+                server_hello(&[(KEY_SHARE, &key_share), (PRE_SHARED_KEY, &[0, 1])]),
+                ConnectionError::IllegalParameter,
+            ),
+            // a key exchange that wasn't offered with the PSK
+            (
+                &[PskKeyExchangeMode::PskKe][..],
+                server_hello(&[(KEY_SHARE, &key_share), (PRE_SHARED_KEY, &[0, 0])]),
+                ConnectionError::IllegalParameter,
+            ),
+            // and no key exchange when that was required
+            (
+                &[PskKeyExchangeMode::PskDheKe][..],
+                server_hello(&[(PRE_SHARED_KEY, &[0, 0])]),
+                ConnectionError::MissingExtension,
+            ),
+        ];
+        for (modes, mut record, err) in failures {
+            let mut client = match modes {
+                [] => client(),
+                modes => ClientConnection::with_psk(psk.clone(), modes, |_| false).unwrap(),
+            };
+            client.take_outgoing();
+            assert_eq!(client.read_tls(&mut record), Err(err));
+        }
+    }
+
+    #[test]
+    fn encrypted_extensions() {
+        assert_eq!(read_encrypted_extensions(&[0, 0], false), Ok(()));
+        // the server's preferred groups
+        let groups = [0x00, 0x08, 0x00, 0x0a, 0x00, 0x04, 0x00, 0x02, 0x00, 0x17];
+        assert_eq!(read_encrypted_extensions(&groups, false), Ok(()));
+        // the server acknowledging the name it was sent
+        let server_name = [0x00, 0x04, 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(read_encrypted_extensions(&server_name, true), Ok(()));
+        assert_eq!(
+            read_encrypted_extensions(&server_name, false),
+            Err(ConnectionError::UnsupportedExtension)
+        );
+        assert_eq!(
+            read_encrypted_extensions(&[0x00, 0x05, 0x00, 0x00, 0x00, 0x01, 0x00], true),
+            Err(ConnectionError::Decode)
+        );
+
+        let failures = [
+            // application_layer_protocol_negotiation, which wasn't offered
+            (
+                &[0x00, 0x06, 0x00, 0x10, 0x00, 0x02, 0x00, 0x00][..],
+                ConnectionError::UnsupportedExtension,
+            ),
+            // supported_versions, which only belongs in the `ServerHello`
+            (
+                &[0x00, 0x06, 0x00, 0x2b, 0x00, 0x02, 0x03, 0x04],
+                ConnectionError::IllegalParameter,
+            ),
+            (
+                &[
+                    0x00, 0x10, 0x00, 0x0a, 0x00, 0x04, 0x00, 0x02, 0x00, 0x17, 0x00, 0x0a, 0x00,
+                    0x04, 0x00, 0x02, 0x00, 0x17,
+                ],
+                ConnectionError::IllegalParameter,
+            ),
+            (&[0x00, 0x04, 0x00, 0x0a, 0x00], ConnectionError::Decode),
+        ];
+        for (body, err) in failures {
+            assert_eq!(read_encrypted_extensions(body, false), Err(err));
+        }
+        assert_eq!(
+            ConnectionError::UnsupportedExtension.alert(),
+            Some(AlertDescription::UnsupportedExtension)
+        );
+    }
+
+    #[test]
+    fn compression_method() {
+        let mut client = client();
+        client.take_outgoing();
+        let mut record = server_hello(&[]);
+        // after the headers, version, random, empty session ID, and cipher suite
+        let compression_method = Message::PREFIIX_SIZE + Handshake::PREFIX_SIZE + 2 + 32 + 1 + 2;
+        assert_eq!(record[compression_method], 0);
+        record[compression_method] = 1;
+        let err = ConnectionError::ServerHello(SerHelloParseError::InvalidCompressionMethod);
+        assert_eq!(client.read_tls(&mut record), Err(err));
+        assert_eq!(err.alert(), Some(AlertDescription::IllegalParam));
+    }
+}
@@ -1,9 +1,13 @@
 use crate::cipher_suites::CipherSuite;
+use crate::codec::{Codec, DecodeError, Reader, TlsVec};
 use crate::extensions;
 use crate::handshake::Handshake;
 use crate::handshake::ShakeType;
+use crate::key_schedule::HASH_LEN;
+use crate::key_share::KeyShares;
+use crate::psk::{self, PskKeyExchangeMode, PskOffer};
+use crate::record::Message;
 use crate::versions::ProtocolVersion;
-use crate::versions::LEGACY_PROTO_VERS;
 use getrandom::{getrandom, Error};
 
 pub struct ClientHello {
@@ -11,15 +15,27 @@ pub struct ClientHello {
 }
 
 impl ClientHello {
-    pub fn new() -> Result<Self, Error> {
+    /// Creates a `ClientHello` that offers the public keys that go with `keys`.
+    ///
+    /// If there is a `server_name`, it is sent in the `server_name` extension. If there is a `psk`,
+    /// it is offered for the key exchanges in `psk_modes`.
+    pub fn new(
+        keys: &KeyShares,
+        server_name: Option<&str>,
+        psk: Option<&PskOffer>,
+        psk_modes: &[PskKeyExchangeMode],
+    ) -> Result<Self, Error> {
         let mut msg = Self::start();
         msg.legacy_protocol_version();
         msg.random_bytes()?;
         msg.legacy_session_id();
         msg.cipher_suites();
         msg.legacy_compression_methods();
-        msg.extensions();
+        msg.extensions(keys, server_name, psk, psk_modes);
         msg.finish();
+        if let Some(psk) = psk {
+            msg.binder(psk);
+        }
         Ok(msg)
     }
 
@@ -30,7 +46,8 @@ impl ClientHello {
     }
 
     fn legacy_protocol_version(&mut self) {
-        self.extend_from_slice(&LEGACY_PROTO_VERS.to_be_bytes());
+        // the real version is negotiated with the `supported_versions` extension
+        self.extend_from_slice(&ProtocolVersion::TlsOnePointTwo.to_be_bytes());
     }
 
     fn random_bytes(&mut self) -> Result<(), Error> {
@@ -44,29 +61,51 @@ impl ClientHello {
     }
 
     fn cipher_suites(&mut self) {
-        let len = 1u16.to_be_bytes();
-        self.extend_from_slice(&len);
-
-        let aes128_gcm_sha256 = CipherSuite::Aes128GcmSha256.to_be_bytes();
-        self.extend_from_slice(&aes128_gcm_sha256);
+        let cipher_suites = TlsVec::<u16, u16>::new(vec![CipherSuite::Aes128GcmSha256 as u16]);
+        self.write(|writer| cipher_suites.encode(writer))
+            .expect("cipher suites fit in the record");
     }
 
     fn legacy_compression_methods(&mut self) {
-        self.push(0x00);
+        // a vector holding only the "null" compression method
+        self.extend_from_slice(&[0x01, 0x00]);
     }
 
-    fn extensions(&mut self) {
+    fn extensions(
+        &mut self,
+        keys: &KeyShares,
+        server_name: Option<&str>,
+        psk: Option<&PskOffer>,
+        psk_modes: &[PskKeyExchangeMode],
+    ) {
         self.extend_from_slice(&[0, 0]);
         let original_len = self.len();
 
-        extensions::supported_groups(self);
+        if let Some(server_name) = server_name {
+            extensions::server_name(self, server_name);
+        }
+        extensions::supported_groups(self, keys);
         extensions::signature_algorithms(self);
         extensions::supported_versions_client(self);
-        extensions::supported_groups(self);
+        extensions::key_share_client_hello(self, keys);
+        if let Some(psk) = psk {
+            extensions::psk_key_exchange_modes(self, psk_modes);
+            extensions::pre_shared_key_client_hello(self, &psk.identity);
+        }
 
-        let extensions_len = ((original_len - self.len()) as u16).to_be_bytes();
+        let extensions_len = ((self.len() - original_len) as u16).to_be_bytes();
         self[original_len - 2..][..2].copy_from_slice(&extensions_len);
     }
+
+    /// Replaces the placeholder binder at the end of the finished message with the real one.
+    fn binder(&mut self, psk: &PskOffer) {
+        // the binder is computed over the message up to the list of binders, which holds only
+        // this one
+        let binders_start = self.len() - (2 + 1 + HASH_LEN);
+        let binder = psk::binder(&psk.binder_key, &self[Message::PREFIIX_SIZE..binders_start]);
+        let len = self.len();
+        self[len - HASH_LEN..len].copy_from_slice(&binder);
+    }
 }
 
 impl std::ops::Deref for ClientHello {
@@ -82,11 +121,42 @@ impl std::ops::DerefMut for ClientHello {
     }
 }
 
+/// A parsed `ClientHello` message.
 pub struct ClientHelloRef<'a> {
-    pub proto_version: ProtocolVersion,
+    pub legacy_version: u16,
+    /// The client's random, which TLS 1.3 servers have no use for beyond printing it.
+    #[cfg_attr(not(any(feature = "probe", feature = "dissect")), allow(dead_code))]
     pub random_bytes: &'a [u8; 32],
     pub session_id: &'a [u8],
-    pub cipher_suites: &'a [CipherSuite],
+    /// The offered cipher suites, including ones this crate doesn't know.
+    pub cipher_suites: Vec<u16>,
     pub compression_methods: &'a [u8],
     pub extensions: &'a [u8],
 }
+
+impl<'a> ClientHelloRef<'a> {
+    /// Parses the body of a `ClientHello` message.
+    ///
+    /// Only the framing is checked; what was offered is left to the server to judge.
+    pub fn parse(data: &'a [u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(data);
+        let legacy_version = reader.read_u16()?;
+        let random_bytes = reader.read_array()?;
+        let session_id = reader.read_u8_prefixed()?.read_rest();
+        if session_id.len() > 32 {
+            return Err(DecodeError);
+        }
+        let cipher_suites = TlsVec::<u16, u16>::decode(&mut reader)?.into_inner();
+        let compression_methods = reader.read_u8_prefixed()?.read_rest();
+        let extensions = reader.read_u16_prefixed()?.read_rest();
+        reader.finish()?;
+        Ok(Self {
+            legacy_version,
+            random_bytes,
+            session_id,
+            cipher_suites,
+            compression_methods,
+            extensions,
+        })
+    }
+}
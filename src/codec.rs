@@ -0,0 +1,502 @@
+//! Encoding and decoding of the TLS presentation language.
+//!
+//! [`Reader`] and [`Writer`] are cursors over borrowed buffers. Every length is checked against the
+//! space that is actually available, so malformed input results in a [`DecodeError`] instead of a
+//! panic, and output that doesn't fit results in an [`EncodeError`].
+use crate::alert::AlertDescription;
+use std::marker::PhantomData;
+
+/// A type that has a wire encoding.
+pub trait Codec<'a>: Sized {
+    /// Appends the encoding of `self` to `writer`.
+    fn encode(&self, writer: &mut Writer) -> Result<(), EncodeError>;
+
+    /// Reads a value from the front of `reader`.
+    fn decode(reader: &mut Reader<'a>) -> Result<Self, DecodeError>;
+}
+
+/// The error that is returned when input is truncated or has inconsistent lengths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DecodeError;
+
+impl DecodeError {
+    /// The alert to send in response to this error.
+    pub const fn alert(self) -> AlertDescription {
+        AlertDescription::DecodeError
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("malformed message")
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// The error that is returned when output doesn't fit in its buffer or its length prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EncodeError;
+
+impl EncodeError {
+    /// The alert to send in response to this error.
+    ///
+    /// Failing to encode our own message is a local fault, not something the peer did.
+    pub const fn alert(self) -> AlertDescription {
+        AlertDescription::InternalError
+    }
+}
+
+impl std::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("message is too long")
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+/// A cursor that reads from the front of a byte slice.
+pub struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    /// The number of unread bytes.
+    pub fn remaining(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Ensures that all input has been read.
+    pub fn finish(self) -> Result<(), DecodeError> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(DecodeError),
+        }
+    }
+
+    /// Consumes the reader, returning all unread bytes.
+    pub fn read_rest(self) -> &'a [u8] {
+        self.buf
+    }
+
+    pub fn read_slice(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let Some((slice, rest)) = self.buf.split_at_checked(len) else {
+            return Err(DecodeError);
+        };
+        self.buf = rest;
+        Ok(slice)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<&'a [u8; N], DecodeError> {
+        // we can safely unwrap because the slice is guaranteed to have a length of `N`
+        Ok(self.read_slice(N)?.try_into().unwrap())
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_be_bytes(*self.read_array()?))
+    }
+
+    pub fn read_u24(&mut self) -> Result<u32, DecodeError> {
+        let bytes = self.read_array::<3>()?;
+        Ok(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]))
+    }
+
+    /// Reads a vector with a length prefix of type `L`, returning a reader over its contents.
+    pub fn read_prefixed<L: LengthPrefix>(&mut self) -> Result<Reader<'a>, DecodeError> {
+        let len = L::decode(self)?.to_len();
+        Ok(Reader::new(self.read_slice(len)?))
+    }
+
+    /// Reads a vector with a one-byte length prefix, returning a reader over its contents.
+    pub fn read_u8_prefixed(&mut self) -> Result<Reader<'a>, DecodeError> {
+        self.read_prefixed::<u8>()
+    }
+
+    /// Reads a vector with a two-byte length prefix, returning a reader over its contents.
+    pub fn read_u16_prefixed(&mut self) -> Result<Reader<'a>, DecodeError> {
+        self.read_prefixed::<u16>()
+    }
+
+    /// Reads a vector with a three-byte length prefix, returning a reader over its contents.
+    pub fn read_u24_prefixed(&mut self) -> Result<Reader<'a>, DecodeError> {
+        self.read_prefixed::<U24>()
+    }
+}
+
+/// A cursor that appends to a fixed-size byte buffer.
+pub struct Writer<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// The number of bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    pub fn write_slice(&mut self, slice: &[u8]) -> Result<(), EncodeError> {
+        let Some(dest) = self.buf[self.len..].get_mut(..slice.len()) else {
+            return Err(EncodeError);
+        };
+        dest.copy_from_slice(slice);
+        self.len += slice.len();
+        Ok(())
+    }
+
+    pub fn write_u8(&mut self, val: u8) -> Result<(), EncodeError> {
+        self.write_slice(&[val])
+    }
+
+    pub fn write_u16(&mut self, val: u16) -> Result<(), EncodeError> {
+        self.write_slice(&val.to_be_bytes())
+    }
+
+    /// Writes the low three bytes of `val`.
+    ///
+    /// Returns an [`Err`] if `val` doesn't fit in three bytes.
+    pub fn write_u24(&mut self, val: u32) -> Result<(), EncodeError> {
+        if val >= 1 << 24 {
+            return Err(EncodeError);
+        }
+        self.write_slice(&val.to_be_bytes()[1..])
+    }
+
+    /// Writes a vector with a one-byte length prefix, with its contents written by `f`.
+    pub fn write_u8_prefixed(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<(), EncodeError>,
+    ) -> Result<(), EncodeError> {
+        self.write_prefixed::<u8>(f)
+    }
+
+    /// Writes a vector with a two-byte length prefix, with its contents written by `f`.
+    pub fn write_u16_prefixed(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<(), EncodeError>,
+    ) -> Result<(), EncodeError> {
+        self.write_prefixed::<u16>(f)
+    }
+
+    /// Writes a vector with a three-byte length prefix, with its contents written by `f`.
+    pub fn write_u24_prefixed(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<(), EncodeError>,
+    ) -> Result<(), EncodeError> {
+        self.write_prefixed::<U24>(f)
+    }
+
+    /// Writes a vector with a length prefix of type `L`, with its contents written by `f`.
+    ///
+    /// Returns an [`Err`] if the contents are longer than `L` can encode. Nothing is written if an
+    /// error is returned.
+    pub fn write_prefixed<L: LengthPrefix>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<(), EncodeError>,
+    ) -> Result<(), EncodeError> {
+        // leave room for the length and fill it in once the contents are known
+        let prefix_start = self.len;
+        self.write_slice(&[0; 4][..L::SIZE])?;
+
+        // don't leave a partially written vector behind
+        if let Err(err) = f(self) {
+            self.len = prefix_start;
+            return Err(err);
+        }
+        let len = self.len - prefix_start - L::SIZE;
+        if len > L::MAX {
+            self.len = prefix_start;
+            return Err(EncodeError);
+        }
+        let mut prefix = Writer::new(&mut self.buf[prefix_start..][..L::SIZE]);
+        L::from_len(len).encode(&mut prefix)
+    }
+}
+
+/// An integer type that is used as the length prefix of a vector.
+pub trait LengthPrefix: for<'a> Codec<'a> {
+    /// The size of the encoded prefix.
+    const SIZE: usize;
+    /// The largest length the prefix can encode.
+    const MAX: usize;
+
+    /// Converts `len` to a prefix. `len` must not be greater than [`LengthPrefix::MAX`].
+    fn from_len(len: usize) -> Self;
+
+    fn to_len(self) -> usize;
+}
+
+impl LengthPrefix for u8 {
+    const SIZE: usize = 1;
+    const MAX: usize = u8::MAX as usize;
+
+    fn from_len(len: usize) -> Self {
+        len as u8
+    }
+
+    fn to_len(self) -> usize {
+        self as usize
+    }
+}
+
+impl LengthPrefix for u16 {
+    const SIZE: usize = 2;
+    const MAX: usize = u16::MAX as usize;
+
+    fn from_len(len: usize) -> Self {
+        len as u16
+    }
+
+    fn to_len(self) -> usize {
+        self as usize
+    }
+}
+
+impl LengthPrefix for U24 {
+    const SIZE: usize = 3;
+    const MAX: usize = U24::MAX.get() as usize;
+
+    fn from_len(len: usize) -> Self {
+        Self(len as u32)
+    }
+
+    fn to_len(self) -> usize {
+        self.get() as usize
+    }
+}
+
+/// A 24-bit unsigned integer, as used for handshake message lengths.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct U24(u32);
+
+impl U24 {
+    pub const MAX: Self = Self((1 << 24) - 1);
+
+    /// Returns [`None`] if `val` doesn't fit in 24 bits.
+    pub const fn new(val: u32) -> Option<Self> {
+        match val <= Self::MAX.0 {
+            true => Some(Self(val)),
+            false => None,
+        }
+    }
+
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl Codec<'_> for U24 {
+    fn encode(&self, writer: &mut Writer) -> Result<(), EncodeError> {
+        writer.write_u24(self.0)
+    }
+
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        reader.read_u24().map(Self)
+    }
+}
+
+/// A vector of `T` with a length prefix of type `L`.
+///
+/// The prefix holds the length of the encoded items in bytes, not the number of items. Encoding
+/// fails if that length doesn't fit in `L`, and decoding fails if the items don't exactly fill the
+/// length.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TlsVec<L, T> {
+    items: Vec<T>,
+    prefix: PhantomData<L>,
+}
+
+impl<L, T> TlsVec<L, T> {
+    pub fn new(items: Vec<T>) -> Self {
+        Self {
+            items,
+            prefix: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> Vec<T> {
+        self.items
+    }
+}
+
+impl<L, T> std::ops::Deref for TlsVec<L, T> {
+    type Target = [T];
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
+impl<L, T> From<Vec<T>> for TlsVec<L, T> {
+    fn from(items: Vec<T>) -> Self {
+        Self::new(items)
+    }
+}
+
+impl<'a, L: LengthPrefix, T: Codec<'a>> Codec<'a> for TlsVec<L, T> {
+    fn encode(&self, writer: &mut Writer) -> Result<(), EncodeError> {
+        writer.write_prefixed::<L>(|writer| {
+            for item in &self.items {
+                item.encode(writer)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn decode(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        let mut contents = reader.read_prefixed::<L>()?;
+        let mut items = Vec::new();
+        while !contents.is_empty() {
+            items.push(T::decode(&mut contents)?);
+        }
+        Ok(Self::new(items))
+    }
+}
+
+impl Codec<'_> for u8 {
+    fn encode(&self, writer: &mut Writer) -> Result<(), EncodeError> {
+        writer.write_u8(*self)
+    }
+
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        reader.read_u8()
+    }
+}
+
+impl Codec<'_> for u16 {
+    fn encode(&self, writer: &mut Writer) -> Result<(), EncodeError> {
+        writer.write_u16(*self)
+    }
+
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        reader.read_u16()
+    }
+}
+
+impl<const N: usize> Codec<'_> for [u8; N] {
+    fn encode(&self, writer: &mut Writer) -> Result<(), EncodeError> {
+        writer.write_slice(self)
+    }
+
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        reader.read_array().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut buf = [0; 16];
+        let mut writer = Writer::new(&mut buf);
+        writer.write_u8(0x01).unwrap();
+        writer.write_u16(0x0203).unwrap();
+        writer.write_u24(0x040506).unwrap();
+        writer
+            .write_u16_prefixed(|writer| {
+                writer.write_u8_prefixed(|writer| [0x07, 0x08].encode(writer))
+            })
+            .unwrap();
+        assert_eq!(
+            writer.written(),
+            [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x00, 0x03, 0x02, 0x07, 0x08]
+        );
+
+        let len = writer.len();
+        let mut reader = Reader::new(&buf[..len]);
+        assert_eq!(u8::decode(&mut reader), Ok(0x01));
+        assert_eq!(u16::decode(&mut reader), Ok(0x0203));
+        assert_eq!(reader.read_u24(), Ok(0x040506));
+        let mut outer = reader.read_u16_prefixed().unwrap();
+        let mut inner = outer.read_u8_prefixed().unwrap();
+        assert_eq!(<[u8; 2]>::decode(&mut inner), Ok([0x07, 0x08]));
+        inner.finish().unwrap();
+        outer.finish().unwrap();
+        reader.finish().unwrap();
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(Reader::new(&[0x01]).read_u16(), Err(DecodeError));
+        assert_eq!(Reader::new(&[0x01, 0x02]).read_u24(), Err(DecodeError));
+        // the prefix claims more data than there is
+        assert!(Reader::new(&[0x00, 0x03, 0x01, 0x02])
+            .read_u16_prefixed()
+            .is_err());
+        assert_eq!(Reader::new(&[0x01]).finish(), Err(DecodeError));
+    }
+
+    #[test]
+    fn tls_vec() {
+        let mut buf = [0; 16];
+        let mut writer = Writer::new(&mut buf);
+        let vec = TlsVec::<U24, u16>::new(vec![0x0102, 0x0304]);
+        vec.encode(&mut writer).unwrap();
+        assert_eq!(writer.written(), [0x00, 0x00, 0x04, 0x01, 0x02, 0x03, 0x04]);
+
+        let mut reader = Reader::new(writer.written());
+        assert_eq!(TlsVec::<U24, u16>::decode(&mut reader), Ok(vec));
+        reader.finish().unwrap();
+
+        // the length covers half an item
+        let mut reader = Reader::new(&[0x03, 0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(TlsVec::<u8, u16>::decode(&mut reader), Err(DecodeError));
+
+        // the length is larger than a `u8` prefix can hold
+        let mut buf = [0; 512];
+        let mut writer = Writer::new(&mut buf);
+        let vec = TlsVec::<u8, u16>::new(vec![0; 128]);
+        assert_eq!(vec.encode(&mut writer), Err(EncodeError));
+        assert!(writer.is_empty());
+    }
+
+    #[test]
+    fn u24() {
+        assert_eq!(U24::new(1 << 24), None);
+        assert_eq!(U24::new(0xffffff), Some(U24::MAX));
+        assert_eq!(U24::MAX.get(), 0xffffff);
+    }
+
+    #[test]
+    fn overflow() {
+        let mut buf = [0; 4];
+        let mut writer = Writer::new(&mut buf);
+        assert_eq!(writer.write_slice(&[0; 5]), Err(EncodeError));
+        assert_eq!(writer.write_u24(1 << 24), Err(EncodeError));
+        assert!(writer.is_empty());
+
+        let mut buf = [0; 512];
+        let mut writer = Writer::new(&mut buf);
+        assert_eq!(
+            writer.write_u8_prefixed(|writer| writer.write_slice(&[0; 256])),
+            Err(EncodeError)
+        );
+        assert!(writer.is_empty());
+    }
+}
@@ -0,0 +1,593 @@
+//! Decrypts and prints captured records with a key log, for debugging the record layer.
+//!
+//! The input is the bytes each side of a connection sent, plus the secrets of the connection in
+//! the NSS key log format, which is what `SSLKEYLOGFILE` makes browsers, curl, and OpenSSL write.
+//! Getting the two byte streams out of a packet capture is left to a tool that reassembles TCP,
+//! such as Wireshark's "Follow TCP Stream" saved as raw data.
+//!
+//! Like the rest of the crate, this only understands TLS 1.3 with `TLS_AES_128_GCM_SHA256`.
+//! Early data isn't supported, so the records of a 0-RTT flight are reported as undecryptable.
+//!
+//! ```text
+//! let key_log = KeyLog::parse(&std::fs::read_to_string("keys.log")?)?;
+//! for record in dissect::dissect(&key_log, &client_bytes, &server_bytes)? {
+//!     println!("{record}");
+//! }
+//! ```
+use std::collections::HashMap;
+use std::fmt;
+
+use crylib::encoding::hex;
+
+use crate::aead::AeadReader;
+use crate::handshake::{Handshake, ShakeType};
+use crate::key_schedule::{self, HASH_LEN};
+use crate::pretty::{CodePoint, HandshakeMessage};
+use crate::record::{self, ContentType, RecordHeader};
+
+/// The length of the `random` of a `ClientHello`, which identifies a connection in a key log.
+const RANDOM_LEN: usize = 32;
+
+/// The traffic secrets of every connection in a key log.
+#[derive(Debug, Clone, Default)]
+pub struct KeyLog {
+    connections: HashMap<[u8; RANDOM_LEN], Secrets>,
+}
+
+/// The traffic secrets of one connection.
+#[derive(Debug, Clone, Default)]
+struct Secrets {
+    client_handshake: Option<[u8; HASH_LEN]>,
+    server_handshake: Option<[u8; HASH_LEN]>,
+    client_application: Option<[u8; HASH_LEN]>,
+    server_application: Option<[u8; HASH_LEN]>,
+}
+
+impl KeyLog {
+    /// Parses a key log in the NSS format.
+    ///
+    /// Each line is a label, the client's random, and a secret, all separated by spaces. Lines
+    /// that are empty or start with `#` are skipped, and so are labels other than the four
+    /// traffic secrets of TLS 1.3, such as those of TLS 1.2 and early data. Secrets that aren't
+    /// as long as a SHA-256 hash belong to a cipher suite the crate doesn't support, so they're
+    /// skipped as well.
+    ///
+    /// Returns the number of the first line, counting from one, that isn't in this format.
+    pub fn parse(key_log: &str) -> Result<Self, KeyLogError> {
+        let mut connections: HashMap<_, Secrets> = HashMap::new();
+        for (i, line) in key_log.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = KeyLogError { line: i + 1 };
+            let [label, random_hex, secret_hex] = line
+                .split_ascii_whitespace()
+                .collect::<Vec<_>>()
+                .try_into()
+                .map_err(|_| invalid)?;
+
+            let mut random = [0; RANDOM_LEN];
+            if hex::decoded_len(random_hex.as_bytes()) != Ok(RANDOM_LEN) {
+                return Err(invalid);
+            }
+            hex::decode(random_hex.as_bytes(), &mut random).map_err(|_| invalid)?;
+            let mut secret = vec![0; hex::decoded_len(secret_hex.as_bytes()).map_err(|_| invalid)?];
+            hex::decode(secret_hex.as_bytes(), &mut secret).map_err(|_| invalid)?;
+            let Ok(secret) = <[u8; HASH_LEN]>::try_from(&*secret) else {
+                continue;
+            };
+
+            let secrets = connections.entry(random).or_default();
+            match label {
+                "CLIENT_HANDSHAKE_TRAFFIC_SECRET" => secrets.client_handshake = Some(secret),
+                "SERVER_HANDSHAKE_TRAFFIC_SECRET" => secrets.server_handshake = Some(secret),
+                "CLIENT_TRAFFIC_SECRET_0" => secrets.client_application = Some(secret),
+                "SERVER_TRAFFIC_SECRET_0" => secrets.server_application = Some(secret),
+                _ => (),
+            }
+        }
+        Ok(Self { connections })
+    }
+}
+
+/// The error that is returned when a line of a key log isn't in the NSS format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KeyLogError {
+    /// The number of the line, counting from one.
+    pub line: usize,
+}
+
+impl fmt::Display for KeyLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {} of the key log is invalid", self.line)
+    }
+}
+
+impl std::error::Error for KeyLogError {}
+
+/// The side of a connection that sent a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Sender {
+    /// The client, which sent the `ClientHello`.
+    Client,
+    /// The server.
+    Server,
+}
+
+impl Sender {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Client => "client",
+            Self::Server => "server",
+        }
+    }
+}
+
+/// A record from a captured byte stream.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Record {
+    /// The side of the connection that sent the record.
+    pub sender: Sender,
+    /// Where the record starts in the bytes its sender sent.
+    pub offset: usize,
+    /// The content type of the record, which is the real one if the record was decrypted.
+    pub content_type: u8,
+    /// Whether the record was encrypted.
+    pub encrypted: bool,
+    /// The content of the record, or [`None`] if it couldn't be decrypted.
+    pub content: Option<Vec<u8>>,
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} @ {}: {}",
+            self.sender.as_str(),
+            self.offset,
+            CodePoint::content_type(self.content_type)
+        )?;
+        if self.encrypted {
+            f.write_str(" (encrypted)")?;
+        }
+        let Some(content) = &self.content else {
+            return f.write_str(", could not be decrypted");
+        };
+        write!(f, ", {} bytes", content.len())?;
+
+        if self.content_type == ContentType::Alert as u8 {
+            if let [level, description] = content[..] {
+                let level = CodePoint::alert_level(level);
+                let description = CodePoint::alert_description(description);
+                return write!(f, "\n  {level} {description}");
+            }
+        }
+        if self.content_type != ContentType::Handshake as u8 {
+            return hex_dump(f, content, "  ");
+        }
+        // a message may continue in the next record, in which case the rest is dumped as is
+        let from_client = self.sender == Sender::Client;
+        let mut rest = &content[..];
+        while let Some((msg, next)) = HandshakeMessage::split(rest, from_client) {
+            let printed = format!("{msg:#?}");
+            write!(f, "\n  {}", printed.replace('\n', "\n  "))?;
+            rest = next;
+        }
+        if !rest.is_empty() {
+            write!(f, "\n  incomplete message")?;
+            hex_dump(f, rest, "    ")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes `bytes` as lines of 16 hex bytes, each on a new line that starts with `indent` and the
+/// offset of the line.
+fn hex_dump(f: &mut fmt::Formatter<'_>, bytes: &[u8], indent: &str) -> fmt::Result {
+    for (i, line) in bytes.chunks(16).enumerate() {
+        write!(f, "\n{indent}{:04x} ", i * 16)?;
+        for byte in line {
+            write!(f, " {byte:02x}")?;
+        }
+    }
+    Ok(())
+}
+
+/// The error that is returned when captured bytes can't be split into records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DissectError {
+    /// The client's bytes don't start with a `ClientHello`, which is needed to find the
+    /// connection's secrets.
+    NoClientHello,
+    /// The bytes sent by `sender` were cut off in the middle of a record, at `offset`.
+    #[allow(missing_docs)]
+    Truncated { sender: Sender, offset: usize },
+}
+
+impl fmt::Display for DissectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoClientHello => {
+                f.write_str("the client's records don't start with a ClientHello")
+            },
+            Self::Truncated { sender, offset } => write!(
+                f,
+                "the {}'s records are cut off at {offset}",
+                sender.as_str()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DissectError {}
+
+/// Splits the bytes sent by each side of a connection into records, decrypting them with the
+/// secrets in `key_log`.
+///
+/// The streams are dissected separately, so the client's records come first, followed by the
+/// server's. Records that can't be decrypted, because the key log doesn't have the secret or
+/// because they were tampered with, are still returned, without their content.
+pub fn dissect(
+    key_log: &KeyLog,
+    client: &[u8],
+    server: &[u8],
+) -> Result<Vec<Record>, DissectError> {
+    // the `random` follows the record header, the handshake header, and `legacy_version`
+    let random_start = RecordHeader::SIZE + Handshake::PREFIX_SIZE + 2;
+    let random = match client {
+        [22, _, _, _, _, 1, ..] => client
+            .get(random_start..random_start + RANDOM_LEN)
+            .ok_or(DissectError::NoClientHello)?,
+        _ => return Err(DissectError::NoClientHello),
+    };
+    let secrets = key_log.connections.get(random).cloned().unwrap_or_default();
+
+    let mut records = Vec::new();
+    Stream::new(
+        Sender::Client,
+        secrets.client_handshake,
+        secrets.client_application,
+    )
+    .dissect(client, &mut records)?;
+    Stream::new(
+        Sender::Server,
+        secrets.server_handshake,
+        secrets.server_application,
+    )
+    .dissect(server, &mut records)?;
+    Ok(records)
+}
+
+/// The state of the records sent by one side of a connection.
+struct Stream {
+    sender: Sender,
+    /// The secret that the current records are protected with.
+    secret: Option<[u8; HASH_LEN]>,
+    /// The secret that replaces the handshake secret once the sender's `Finished` is sent.
+    application: Option<[u8; HASH_LEN]>,
+    reader: Option<AeadReader>,
+    /// Decrypted handshake messages that haven't been completely received yet.
+    handshake: Vec<u8>,
+}
+
+impl Stream {
+    fn new(
+        sender: Sender,
+        handshake: Option<[u8; HASH_LEN]>,
+        application: Option<[u8; HASH_LEN]>,
+    ) -> Self {
+        Self {
+            sender,
+            secret: handshake,
+            application,
+            reader: handshake
+                .as_ref()
+                .map(key_schedule::traffic_keys)
+                .map(AeadReader::new),
+            handshake: Vec::new(),
+        }
+    }
+
+    fn dissect(mut self, mut bytes: &[u8], records: &mut Vec<Record>) -> Result<(), DissectError> {
+        let mut offset = 0;
+        while !bytes.is_empty() {
+            let truncated = DissectError::Truncated {
+                sender: self.sender,
+                offset,
+            };
+            let [content_type, _, _, high, low, ..] = *bytes else {
+                return Err(truncated);
+            };
+            let len = RecordHeader::SIZE + u16::from_be_bytes([high, low]) as usize;
+            let (record, rest) = bytes.split_at_checked(len).ok_or(truncated)?;
+            let (header, fragment) = record.split_at(RecordHeader::SIZE);
+
+            let encrypted = content_type == ContentType::ApplicationData as u8;
+            let (content_type, content) = match encrypted {
+                true => match self.open(header.try_into().unwrap(), fragment) {
+                    Some((content_type, content)) => (content_type as u8, Some(content)),
+                    None => (content_type, None),
+                },
+                false => (content_type, Some(fragment.to_vec())),
+            };
+            records.push(Record {
+                sender: self.sender,
+                offset,
+                content_type,
+                encrypted,
+                content,
+            });
+            bytes = rest;
+            offset += len;
+        }
+        Ok(())
+    }
+
+    /// Decrypts a record, switching to the next keys if it ends the handshake or updates them.
+    fn open(
+        &mut self,
+        header: &[u8; RecordHeader::SIZE],
+        fragment: &[u8],
+    ) -> Option<(ContentType, Vec<u8>)> {
+        let mut content = fragment.to_vec();
+        let (content_type, len) =
+            record::open_record(self.reader.as_mut()?, header, &mut content).ok()?;
+        content.truncate(len);
+        if content_type == ContentType::Handshake {
+            self.handshake.extend_from_slice(&content);
+            self.read_handshake();
+        }
+        Some((content_type, content))
+    }
+
+    fn read_handshake(&mut self) {
+        while let Some((msg, _)) = HandshakeMessage::split(&self.handshake, false) {
+            let len = Handshake::PREFIX_SIZE + msg.body.len();
+            let finished = msg.shake_type == ShakeType::Finished as u8;
+            let key_update = msg.shake_type == ShakeType::KeyUpdate as u8;
+            self.handshake.drain(..len);
+            if !finished && !key_update {
+                continue;
+            }
+            self.secret = match finished {
+                true => self.application.take(),
+                false => self.secret.as_ref().map(key_schedule::next_traffic_secret),
+            };
+            self.reader = self
+                .secret
+                .as_ref()
+                .map(key_schedule::traffic_keys)
+                .map(AeadReader::new);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aead::AeadWriter;
+    use crate::record::EncryptedMessage;
+
+    const RANDOM: [u8; RANDOM_LEN] = [0xaa; RANDOM_LEN];
+    const CLIENT_HANDSHAKE: [u8; HASH_LEN] = [1; HASH_LEN];
+    const SERVER_HANDSHAKE: [u8; HASH_LEN] = [2; HASH_LEN];
+    const CLIENT_APPLICATION: [u8; HASH_LEN] = [3; HASH_LEN];
+    const SERVER_APPLICATION: [u8; HASH_LEN] = [4; HASH_LEN];
+
+    fn key_log_line(label: &str, random: &[u8], secret: &[u8]) -> String {
+        let hex = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>()
+        };
+        format!("{label} {} {}\n", hex(random), hex(secret))
+    }
+
+    fn key_log() -> String {
+        let mut key_log = String::from("# a comment, then the secrets of another connection\n\n");
+        key_log += &key_log_line("CLIENT_HANDSHAKE_TRAFFIC_SECRET", &[0xbb; 32], &[5; 32]);
+        key_log += &key_log_line("CLIENT_RANDOM", &RANDOM, &[6; 48]);
+        key_log += &key_log_line(
+            "CLIENT_HANDSHAKE_TRAFFIC_SECRET",
+            &RANDOM,
+            &CLIENT_HANDSHAKE,
+        );
+        key_log += &key_log_line(
+            "SERVER_HANDSHAKE_TRAFFIC_SECRET",
+            &RANDOM,
+            &SERVER_HANDSHAKE,
+        );
+        key_log += &key_log_line("CLIENT_TRAFFIC_SECRET_0", &RANDOM, &CLIENT_APPLICATION);
+        key_log += &key_log_line("SERVER_TRAFFIC_SECRET_0", &RANDOM, &SERVER_APPLICATION);
+        key_log
+    }
+
+    fn seal(content_type: ContentType, content: &[u8], writer: &mut AeadWriter) -> Vec<u8> {
+        let mut msg = EncryptedMessage::start(content_type, 2);
+        msg.extend_from_slice(content);
+        msg.finish(writer).unwrap();
+        msg.to_vec()
+    }
+
+    fn writer(secret: &[u8; HASH_LEN]) -> AeadWriter {
+        AeadWriter::new(key_schedule::traffic_keys(secret))
+    }
+
+    /// Returns the bytes sent by the client and the server.
+    fn connection() -> (Vec<u8>, Vec<u8>) {
+        let mut client = vec![22, 3, 1, 0, 38, 1, 0, 0, 34, 3, 3];
+        client.extend_from_slice(&RANDOM);
+        client.extend_from_slice(&[20, 3, 3, 0, 1, 1]);
+        let finished = [&[20, 0, 0, 32][..], &[0x42; 32]].concat();
+        client.extend(seal(
+            ContentType::Handshake,
+            &finished,
+            &mut writer(&CLIENT_HANDSHAKE),
+        ));
+        let mut application = writer(&CLIENT_APPLICATION);
+        client.extend(seal(
+            ContentType::ApplicationData,
+            b"hello",
+            &mut application,
+        ));
+        client.extend(seal(
+            ContentType::Handshake,
+            &[24, 0, 0, 1, 0],
+            &mut application,
+        ));
+        let updated = key_schedule::next_traffic_secret(&CLIENT_APPLICATION);
+        client.extend(seal(
+            ContentType::ApplicationData,
+            b"again",
+            &mut writer(&updated),
+        ));
+
+        let mut server = vec![22, 3, 3, 0, 6, 2, 0, 0, 2, 3, 3];
+        let mut handshake = writer(&SERVER_HANDSHAKE);
+        // split over two records
+        server.extend(seal(
+            ContentType::Handshake,
+            &[8, 0, 0, 2, 0, 0, 20, 0],
+            &mut handshake,
+        ));
+        server.extend(seal(ContentType::Handshake, &finished[2..], &mut handshake));
+        let mut application = writer(&SERVER_APPLICATION);
+        server.extend(seal(
+            ContentType::ApplicationData,
+            b"world",
+            &mut application,
+        ));
+        (client, server)
+    }
+
+    #[test]
+    fn dissect_connection() {
+        let (client, server) = connection();
+        let key_log = KeyLog::parse(&key_log()).unwrap();
+        let records = dissect(&key_log, &client, &server).unwrap();
+
+        let expected: [(Sender, ContentType, bool, &[u8]); 10] = [
+            (
+                Sender::Client,
+                ContentType::Handshake,
+                false,
+                &client[5..43],
+            ),
+            (Sender::Client, ContentType::ChangeCipherSpec, false, &[1]),
+            (
+                Sender::Client,
+                ContentType::Handshake,
+                true,
+                &[20, 0, 0, 32],
+            ),
+            (Sender::Client, ContentType::ApplicationData, true, b"hello"),
+            (
+                Sender::Client,
+                ContentType::Handshake,
+                true,
+                &[24, 0, 0, 1, 0],
+            ),
+            (Sender::Client, ContentType::ApplicationData, true, b"again"),
+            (
+                Sender::Server,
+                ContentType::Handshake,
+                false,
+                &[2, 0, 0, 2, 3, 3],
+            ),
+            (
+                Sender::Server,
+                ContentType::Handshake,
+                true,
+                &[8, 0, 0, 2, 0, 0, 20, 0],
+            ),
+            (Sender::Server, ContentType::Handshake, true, &[0, 32]),
+            (Sender::Server, ContentType::ApplicationData, true, b"world"),
+        ];
+        assert_eq!(records.len(), expected.len());
+        for (record, (sender, content_type, encrypted, content)) in records.iter().zip(expected) {
+            assert_eq!(record.sender, sender);
+            assert_eq!(record.content_type, content_type as u8);
+            assert_eq!(record.encrypted, encrypted);
+            // only the start of the `Finished` messages is checked
+            let decrypted = record.content.as_deref().unwrap();
+            assert_eq!(&decrypted[..content.len()], content, "{record}");
+        }
+        // the header, the `Finished`, the content type, the padding, and the tag
+        assert_eq!(records[3].offset, 49 + 5 + 36 + 1 + 2 + 16);
+
+        let printed = records[7].to_string();
+        assert!(printed.starts_with("server @ 11: handshake (encrypted), 8 bytes\n"));
+        assert!(printed.contains("\n  EncryptedExtensions {\n      extensions: [],\n  }"));
+        assert!(printed.contains("\n  incomplete message\n    0000  14 00"));
+    }
+
+    #[test]
+    fn missing_secrets() {
+        let (client, server) = connection();
+        let key_log: String = key_log()
+            .lines()
+            .take(6)
+            .map(|line| line.to_owned() + "\n")
+            .collect();
+        let key_log = KeyLog::parse(&key_log).unwrap();
+        let records = dissect(&key_log, &client, &server).unwrap();
+
+        // without the application secrets, only the handshake can be decrypted
+        let decrypted: Vec<_> = records
+            .iter()
+            .map(|record| record.content.is_some())
+            .collect();
+        assert_eq!(
+            decrypted,
+            [true, true, true, false, false, false, true, true, true, false]
+        );
+        assert_eq!(records[3].content_type, ContentType::ApplicationData as u8);
+        assert_eq!(
+            records[3].to_string(),
+            "client @ 109: application_data (encrypted), could not be decrypted"
+        );
+
+        let records = dissect(&KeyLog::default(), &client, &server).unwrap();
+        assert_eq!(
+            records
+                .iter()
+                .filter(|record| record.content.is_some())
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let (client, server) = connection();
+        let key_log = KeyLog::parse(&key_log()).unwrap();
+        assert_eq!(
+            dissect(&key_log, &server, &client),
+            Err(DissectError::NoClientHello)
+        );
+        assert_eq!(
+            dissect(&key_log, &client, &server[..server.len() - 1]),
+            Err(DissectError::Truncated {
+                sender: Sender::Server,
+                offset: server.len() - 5 - 5 - 1 - 2 - 16,
+            })
+        );
+
+        for (key_log, line) in [
+            ("\nCLIENT_TRAFFIC_SECRET_0 aa\n", 2),
+            ("# fine\nCLIENT_TRAFFIC_SECRET_0 zz 00\n", 2),
+            (
+                &key_log_line("CLIENT_TRAFFIC_SECRET_0", &[0; 31], &[0; 32]),
+                1,
+            ),
+            (
+                &key_log_line("CLIENT_TRAFFIC_SECRET_0", &[0; 32], &[0; 32]).replace("\n", "0\n"),
+                1,
+            ),
+        ] {
+            assert_eq!(KeyLog::parse(key_log).unwrap_err(), KeyLogError { line });
+        }
+    }
+}
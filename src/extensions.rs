@@ -1,9 +1,12 @@
-use crylib::ec::{EllipticCurve, Secp256r1};
-
 use crate::cipher_suites::{NamedGroup, SignatureScheme};
 use crate::client_hello::ClientHello;
+use crate::codec::{Codec, TlsVec};
+use crate::key_schedule::HASH_LEN;
+use crate::key_share::{KeyShareEntry, KeyShares};
+use crate::psk::{OfferedPsks, PskIdentity, PskKeyExchangeMode};
+use crate::record::Message;
+use crate::server_hello::ServerHello;
 use crate::versions::ProtocolVersion;
-use crate::State;
 
 #[repr(u16)]
 pub enum Extension {
@@ -37,78 +40,83 @@ impl Extension {
     }
 }
 
-pub fn supported_versions_client(buf: &mut ClientHello) {
-    let extension_name = Extension::SupportedVersions.to_be_bytes();
-    buf.extend_from_slice(&extension_name);
-
-    let extension_len = (size_of::<u8>() as u16 + 1).to_be_bytes();
-    buf.extend_from_slice(&extension_len);
-
-    let len = size_of::<u16>() as u8;
-    buf.push(len);
-    buf.extend_from_slice(&ProtocolVersion::TlsOnePointThree.to_be_bytes());
+/// Names the server the client is connecting to, as a DNS host name (RFC 6066 section 3).
+pub fn server_name(buf: &mut ClientHello, host_name: &str) {
+    const HOST_NAME: u8 = 0;
+    buf.write(|writer| {
+        writer.write_u16(Extension::ServerName as u16)?;
+        writer.write_u16_prefixed(|writer| {
+            writer.write_u16_prefixed(|writer| {
+                writer.write_u8(HOST_NAME)?;
+                writer.write_u16_prefixed(|writer| writer.write_slice(host_name.as_bytes()))
+            })
+        })
+    })
+    .expect("the server name fits in the record");
 }
 
-pub fn supported_versions_server(buf: &mut ClientHello) {
-    let extension_name = Extension::SupportedVersions.to_be_bytes();
-    buf.extend_from_slice(&extension_name);
+pub fn supported_versions_client(buf: &mut ClientHello) {
+    let versions = TlsVec::<u8, u16>::new(vec![ProtocolVersion::TlsOnePointThree as u16]);
+    write_extension(buf, Extension::SupportedVersions, &versions);
+}
 
-    let supported_versions = ProtocolVersion::TlsOnePointThree.to_be_bytes();
-    buf.extend_from_slice(&supported_versions);
+pub fn supported_versions_server(buf: &mut ServerHello) {
+    let version = ProtocolVersion::TlsOnePointThree as u16;
+    write_extension(buf, Extension::SupportedVersions, &version);
 }
 
 // TODO: support more algorithms and allow user to choose which to use
 pub fn signature_algorithms(buf: &mut ClientHello) {
-    let extension_name = Extension::SignatureAlgorithms.to_be_bytes();
-    buf.extend_from_slice(&extension_name);
-
-    let extension_len = (2 * size_of::<u16>() as u16).to_be_bytes();
-    buf.extend_from_slice(&extension_len);
-
-    let len = (size_of::<u16>() as u16).to_be_bytes();
-    buf.extend_from_slice(&len);
-
-    let scheme = SignatureScheme::EcdsaSecp256r1Sha256.to_be_bytes();
-    buf.extend_from_slice(&scheme);
+    let schemes = TlsVec::<u16, u16>::new(vec![SignatureScheme::EcdsaSecp256r1Sha256 as u16]);
+    write_extension(buf, Extension::SignatureAlgorithms, &schemes);
 }
 
-// TODO: support more groups and allow user to choose which to use
-pub fn supported_groups(buf: &mut ClientHello) {
-    let extension_name = (Extension::SupportedGroups as u16).to_be_bytes();
-    buf.extend_from_slice(&extension_name);
-
-    let extension_len = (2 * size_of::<u16>() as u16).to_be_bytes();
-    buf.extend_from_slice(&extension_len);
+/// Offers the groups that `keys` has key shares for.
+pub fn supported_groups(buf: &mut ClientHello, keys: &KeyShares) {
+    let groups = TlsVec::<u16, u16>::new(keys.groups().map(|group| group as u16).collect());
+    write_extension(buf, Extension::SupportedGroups, &groups);
+}
 
-    let len = (size_of::<u16>() as u16).to_be_bytes();
-    buf.extend_from_slice(&len);
+/// Writes an extension whose data is the encoding of `data`.
+fn write_extension<'a>(buf: &mut Message, extension: Extension, data: &impl Codec<'a>) {
+    buf.write(|writer| {
+        writer.write_u16(extension as u16)?;
+        writer.write_u16_prefixed(|writer| data.encode(writer))
+    })
+    .expect("extensions fit in the record");
+}
 
-    let groups = NamedGroup::Secp256r1.to_be_bytes();
-    buf.extend_from_slice(&groups);
+pub fn key_share_client_hello(buf: &mut ClientHello, keys: &KeyShares) {
+    let client_shares = TlsVec::<u16, KeyShareEntry>::new(keys.entries());
+    write_extension(buf, Extension::KeyShare, &client_shares);
 }
 
-pub fn key_share_client_hello(buf: &mut ClientHello, state: &State) {
-    let extension_name = Extension::KeyShare.to_be_bytes();
-    buf.extend_from_slice(&extension_name);
+pub fn key_share_server_hello(buf: &mut ServerHello, server_share: &KeyShareEntry) {
+    write_extension(buf, Extension::KeyShare, server_share);
+}
 
-    let original_len = buf.len();
-    buf.extend_from_slice(&[0; 2]);
+/// Asks the client to send a key share for `group` in its second `ClientHello`.
+pub fn key_share_hello_retry_request(buf: &mut ServerHello, group: NamedGroup) {
+    write_extension(buf, Extension::KeyShare, &(group as u16));
+}
 
-    secp256r1_key_share(buf, state);
+/// Offers to use a PSK with the key exchanges in `modes`.
+pub fn psk_key_exchange_modes(buf: &mut ClientHello, modes: &[PskKeyExchangeMode]) {
+    let modes = TlsVec::<u8, u8>::new(modes.iter().map(|&mode| mode as u8).collect());
+    write_extension(buf, Extension::PskExchangeModes, &modes);
+}
 
-    let len_diff = ((buf.len() - original_len) as u16).to_be_bytes();
-    buf[original_len..][..2].copy_from_slice(&len_diff);
+/// Offers the PSK `identity`, with a binder of zeros that is filled in once the rest of the
+/// `ClientHello` is known. This has to be the last extension.
+pub fn pre_shared_key_client_hello(buf: &mut ClientHello, identity: &PskIdentity) {
+    let offered = OfferedPsks {
+        identities: vec![*identity],
+        binders: vec![&[0; HASH_LEN]],
+    };
+    write_extension(buf, Extension::PreSharedKey, &offered);
 }
 
-fn secp256r1_key_share(buf: &mut ClientHello, state: &State) {
-    let named_group = NamedGroup::Secp256r1.to_be_bytes();
-    buf.extend_from_slice(&named_group);
-    buf.push(4);
-    let pub_key = Secp256r1::BASE_POINT
-        .as_projective()
-        .mul_scalar(state.group_keys.secp256r1.inner())
-        .as_affine()
-        .expect("private key isn't 0");
-    buf.extend_from_slice(&pub_key.x().to_be_bytes());
-    buf.extend_from_slice(&pub_key.y().to_be_bytes());
+/// Accepts the PSK at index `selected_identity` of the client's `pre_shared_key` extension.
+pub fn pre_shared_key_server_hello(buf: &mut ServerHello, selected_identity: u16) {
+    write_extension(buf, Extension::PreSharedKey, &selected_identity);
 }
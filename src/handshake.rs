@@ -26,7 +26,7 @@ impl Handshake {
         let mut handshake = Self {
             msg: Message::start(ContentType::Handshake),
         };
-        handshake[Message::PREFIIX_SIZE] = shake_type as u8;
+        handshake.push(shake_type as u8);
 
         // leave room for length encoding
         handshake.extend(3);
@@ -34,7 +34,8 @@ impl Handshake {
     }
 
     pub fn finish(&mut self) {
-        let len_diff = &((self.len() - Message::PREFIIX_SIZE) as u32).to_be_bytes()[1..4];
+        let len_diff =
+            &((self.len() - Message::PREFIIX_SIZE - Self::PREFIX_SIZE) as u32).to_be_bytes()[1..4];
         self[Message::PREFIIX_SIZE + 1..][..3].copy_from_slice(len_diff);
         self.msg.finish();
     }
@@ -1,32 +1,551 @@
-use crylib::{hash::BlockHasher, hkdf::expand};
-pub fn hkdf_expand_label<const H_LEN: usize, const B_LEN: usize, const K_LEN: usize, H>(
-    secret: &[u8; H_LEN],
+//! The TLS 1.3 key schedule (RFC 8446 section 7.1).
+//!
+//! Each stage of the schedule is a type that can only be derived from the one before it:
+//! [`EarlySecret`], then [`HandshakeSecrets`], then [`MasterSecret`]. The traffic secrets derived
+//! along the way are handed to [`traffic_keys`] for record protection.
+//!
+//! The `testing` feature makes this module public, and adds a `secret` method to each stage, so
+//! that every intermediate value can be compared with test vectors like those of RFC 8448 rather
+//! than only the keys that come out at the end.
+use crylib::hash::{Hasher, Sha256};
+use crylib::hkdf::{expand, extract};
+use crylib::hmac::{Hmac, InvalidMac};
+
+use crate::aead::TrafficKeys;
+use crate::codec::{Codec, DecodeError, EncodeError, Reader, Writer};
+
+/// The length of the hash of `TLS_AES_128_GCM_SHA256`, the only supported cipher suite.
+pub const HASH_LEN: usize = Sha256::HASH_SIZE;
+const BLOCK_LEN: usize = Sha256::BLOCK_SIZE;
+
+/// The prefix of every label, which keeps TLS 1.3 keys apart from other uses of HKDF.
+const LABEL_PREFIX: &[u8] = b"tls13 ";
+
+/// The `HkdfLabel` that `HKDF-Expand-Label` passes to `HKDF-Expand` as its `info` (RFC 8446
+/// section 7.1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HkdfLabel<'a> {
+    /// The length of the output.
+    pub length: u16,
+    /// The label, without the `"tls13 "` prefix.
+    pub label: &'a [u8],
+    /// The context, which is usually a transcript hash or empty.
+    pub context: &'a [u8],
+}
+
+impl HkdfLabel<'_> {
+    /// The length of the longest encoding.
+    pub const MAX_LEN: usize = size_of::<u16>() + 2 * (size_of::<u8>() + u8::MAX as usize);
+}
+
+impl<'a> Codec<'a> for HkdfLabel<'a> {
+    fn encode(&self, writer: &mut Writer) -> Result<(), EncodeError> {
+        writer.write_u16(self.length)?;
+        writer.write_u8_prefixed(|writer| {
+            writer.write_slice(LABEL_PREFIX)?;
+            writer.write_slice(self.label)
+        })?;
+        writer.write_u8_prefixed(|writer| writer.write_slice(self.context))
+    }
+
+    fn decode(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        let length = reader.read_u16()?;
+        let label = reader
+            .read_u8_prefixed()?
+            .read_rest()
+            .strip_prefix(LABEL_PREFIX)
+            .ok_or(DecodeError)?;
+        let context = reader.read_u8_prefixed()?.read_rest();
+        Ok(Self {
+            length,
+            label,
+            context,
+        })
+    }
+}
+
+/// `HKDF-Expand-Label` (RFC 8446 section 7.1), deriving `K_LEN` bytes from `secret`.
+///
+/// # Panics
+///
+/// Panics if `label` is longer than 249 bytes or `context` is longer than 255 bytes, which
+/// [`HkdfLabel`] can't encode.
+pub fn expand_label<const K_LEN: usize>(
+    secret: &[u8; HASH_LEN],
     label: &[u8],
     context: &[u8],
-) -> [u8; K_LEN]
-where
-    H: BlockHasher<H_LEN, B_LEN>,
-{
-    let mut hkdf_label =
-        Vec::with_capacity(size_of::<u16>() + 2 * size_of::<u8>() + label.len() + context.len());
-    hkdf_label.extend_from_slice(&K_LEN.to_be_bytes());
+) -> [u8; K_LEN] {
+    let hkdf_label = HkdfLabel {
+        length: K_LEN as u16,
+        label,
+        context,
+    };
+    let mut buf = [0; HkdfLabel::MAX_LEN];
+    let mut writer = Writer::new(&mut buf);
+    hkdf_label
+        .encode(&mut writer)
+        .expect("label and context fit in an HkdfLabel");
+    expand::<HASH_LEN, BLOCK_LEN, K_LEN, Sha256>(secret, writer.written())
+}
+
+/// `Derive-Secret` (RFC 8446 section 7.1), where `transcript_hash` is the hash of the messages.
+pub fn derive_secret(
+    secret: &[u8; HASH_LEN],
+    label: &[u8],
+    transcript_hash: &[u8; HASH_LEN],
+) -> [u8; HASH_LEN] {
+    expand_label(secret, label, transcript_hash)
+}
+
+/// The early secret, which the rest of the key schedule is derived from.
+///
+/// Only resumption and external PSKs give it any entropy; without one, it is extracted from zeros.
+pub struct EarlySecret {
+    secret: [u8; HASH_LEN],
+}
+
+impl EarlySecret {
+    /// Returns the early secret itself.
+    #[cfg(any(test, feature = "testing"))]
+    pub const fn secret(&self) -> &[u8; HASH_LEN] {
+        &self.secret
+    }
+
+    /// Extracts the early secret from `psk`, or from zeros if there is no PSK.
+    pub fn new(psk: Option<&[u8]>) -> Self {
+        Self {
+            secret: extract::<HASH_LEN, BLOCK_LEN, Sha256>(
+                &[0; HASH_LEN],
+                psk.unwrap_or(&[0; HASH_LEN]),
+            ),
+        }
+    }
+
+    /// Derives the key that the binders of the PSK are computed with.
+    ///
+    /// `external` is whether the PSK was provisioned externally rather than from a previous
+    /// connection, which keeps one type of PSK from being used as the other.
+    pub fn binder_key(&self, external: bool) -> [u8; HASH_LEN] {
+        let label: &[u8] = match external {
+            true => b"ext binder",
+            false => b"res binder",
+        };
+        derive_secret(&self.secret, label, &Sha256::hash(b""))
+    }
+
+    /// Derives the traffic secret of 0-RTT data.
+    ///
+    /// `transcript_hash` is the hash of the `ClientHello`.
+    pub fn client_early_traffic_secret(&self, transcript_hash: &[u8; HASH_LEN]) -> [u8; HASH_LEN] {
+        derive_secret(&self.secret, b"c e traffic", transcript_hash)
+    }
+
+    /// Derives the secret that 0-RTT exporters are derived from.
+    ///
+    /// `transcript_hash` is the hash of the `ClientHello`.
+    pub fn early_exporter_master_secret(&self, transcript_hash: &[u8; HASH_LEN]) -> [u8; HASH_LEN] {
+        derive_secret(&self.secret, b"e exp master", transcript_hash)
+    }
+
+    /// Derives the handshake traffic secrets from the shared secret of the key exchange.
+    ///
+    /// `transcript_hash` is the hash of every handshake message up to and including the
+    /// `ServerHello`.
+    pub fn handshake_secrets(
+        &self,
+        shared_secret: &[u8],
+        transcript_hash: &[u8; HASH_LEN],
+    ) -> HandshakeSecrets {
+        let secret = extract::<HASH_LEN, BLOCK_LEN, Sha256>(&derived(&self.secret), shared_secret);
+        HandshakeSecrets {
+            client: derive_secret(&secret, b"c hs traffic", transcript_hash),
+            server: derive_secret(&secret, b"s hs traffic", transcript_hash),
+            secret,
+        }
+    }
+}
+
+/// The secrets of the handshake phase.
+pub struct HandshakeSecrets {
+    secret: [u8; HASH_LEN],
+    /// The client's handshake traffic secret.
+    pub client: [u8; HASH_LEN],
+    /// The server's handshake traffic secret.
+    pub server: [u8; HASH_LEN],
+}
+
+impl HandshakeSecrets {
+    /// Returns the handshake secret, which the traffic secrets are derived from.
+    #[cfg(any(test, feature = "testing"))]
+    pub const fn secret(&self) -> &[u8; HASH_LEN] {
+        &self.secret
+    }
+
+    /// Derives the handshake traffic secrets of a handshake without a PSK.
+    ///
+    /// `transcript_hash` is the hash of every handshake message up to and including the
+    /// `ServerHello`.
+    pub fn new(shared_secret: &[u8], transcript_hash: &[u8; HASH_LEN]) -> Self {
+        EarlySecret::new(None).handshake_secrets(shared_secret, transcript_hash)
+    }
+
+    /// Derives the master secret, which every secret used after the handshake is derived from.
+    pub fn master_secret(&self) -> MasterSecret {
+        MasterSecret {
+            secret: extract::<HASH_LEN, BLOCK_LEN, Sha256>(&derived(&self.secret), &[0; HASH_LEN]),
+        }
+    }
+
+    /// Derives the application traffic secrets.
+    ///
+    /// `transcript_hash` is the hash of every handshake message up to and including the server's
+    /// `Finished`.
+    pub fn application_secrets(&self, transcript_hash: &[u8; HASH_LEN]) -> TrafficSecrets {
+        self.master_secret().traffic_secrets(transcript_hash)
+    }
+}
+
+/// The master secret.
+pub struct MasterSecret {
+    secret: [u8; HASH_LEN],
+}
+
+impl MasterSecret {
+    /// Returns the master secret itself.
+    #[cfg(any(test, feature = "testing"))]
+    pub const fn secret(&self) -> &[u8; HASH_LEN] {
+        &self.secret
+    }
+
+    /// Derives the application traffic secrets.
+    ///
+    /// `transcript_hash` is the hash of every handshake message up to and including the server's
+    /// `Finished`.
+    pub fn traffic_secrets(&self, transcript_hash: &[u8; HASH_LEN]) -> TrafficSecrets {
+        TrafficSecrets {
+            client: derive_secret(&self.secret, b"c ap traffic", transcript_hash),
+            server: derive_secret(&self.secret, b"s ap traffic", transcript_hash),
+        }
+    }
 
-    hkdf_label.push(label.len() as u8);
-    hkdf_label.extend_from_slice(label);
+    /// Derives the secret that exporters are derived from.
+    ///
+    /// `transcript_hash` is the hash of every handshake message up to and including the server's
+    /// `Finished`.
+    pub fn exporter_master_secret(&self, transcript_hash: &[u8; HASH_LEN]) -> [u8; HASH_LEN] {
+        derive_secret(&self.secret, b"exp master", transcript_hash)
+    }
 
-    hkdf_label.push(context.len() as u8);
-    hkdf_label.extend_from_slice(context);
+    /// Derives the secret that the PSKs of session tickets are derived from.
+    ///
+    /// `transcript_hash` is the hash of every handshake message up to and including the client's
+    /// `Finished`.
+    pub fn resumption_master_secret(&self, transcript_hash: &[u8; HASH_LEN]) -> [u8; HASH_LEN] {
+        derive_secret(&self.secret, b"res master", transcript_hash)
+    }
+}
+
+/// The application traffic secrets of both sides.
+pub struct TrafficSecrets {
+    /// The client's application traffic secret.
+    pub client: [u8; HASH_LEN],
+    /// The server's application traffic secret.
+    pub server: [u8; HASH_LEN],
+}
+
+/// Derives the salt that the stage after `secret` is extracted with.
+pub fn derived(secret: &[u8; HASH_LEN]) -> [u8; HASH_LEN] {
+    derive_secret(secret, b"derived", &Sha256::hash(b""))
+}
+
+/// Derives the record protection keys for `secret`.
+pub fn traffic_keys(secret: &[u8; HASH_LEN]) -> TrafficKeys {
+    TrafficKeys::aes_128_gcm(traffic_key(secret), traffic_iv(secret))
+}
+
+/// Derives the AES-128-GCM key of the record protection keys for `secret`.
+pub fn traffic_key(secret: &[u8; HASH_LEN]) -> [u8; 16] {
+    expand_label(secret, b"key", b"")
+}
 
-    expand::<H_LEN, B_LEN, K_LEN, H>(secret, &hkdf_label)
+/// Derives the static IV of the record protection keys for `secret`.
+pub fn traffic_iv(secret: &[u8; HASH_LEN]) -> [u8; 12] {
+    expand_label(secret, b"iv", b"")
 }
 
-pub fn hkdf_label<const H_LEN: usize, const B_LEN: usize, const K_LEN: usize, H>(
-    secret: &[u8; H_LEN],
+/// Returns the `verify_data` of the `Finished` message sent by the owner of `secret`.
+///
+/// `transcript_hash` is the hash of every handshake message before the `Finished` message.
+pub fn finished_verify_data(
+    secret: &[u8; HASH_LEN],
+    transcript_hash: &[u8; HASH_LEN],
+) -> [u8; HASH_LEN] {
+    let finished_key = expand_label::<HASH_LEN>(secret, b"finished", b"");
+    Hmac::<HASH_LEN, BLOCK_LEN, Sha256>::auth(&finished_key, transcript_hash)
+}
+
+/// Checks that `verify_data` is that of the `Finished` message sent by the owner of `secret`.
+///
+/// `transcript_hash` is the hash of every handshake message before the `Finished` message.
+pub fn verify_finished(
+    secret: &[u8; HASH_LEN],
+    transcript_hash: &[u8; HASH_LEN],
+    verify_data: &[u8],
+) -> Result<(), InvalidMac> {
+    let finished_key = expand_label::<HASH_LEN>(secret, b"finished", b"");
+    Hmac::<HASH_LEN, BLOCK_LEN, Sha256>::verify(&finished_key, transcript_hash, verify_data)
+}
+
+/// Returns the traffic secret that replaces `secret` after a `KeyUpdate`.
+pub fn next_traffic_secret(secret: &[u8; HASH_LEN]) -> [u8; HASH_LEN] {
+    expand_label(secret, b"traffic upd", b"")
+}
+
+/// Derives the PSK of the session ticket with the nonce `ticket_nonce`.
+pub fn resumption_psk(
+    resumption_master_secret: &[u8; HASH_LEN],
+    ticket_nonce: &[u8],
+) -> [u8; HASH_LEN] {
+    expand_label(resumption_master_secret, b"resumption", ticket_nonce)
+}
+
+/// Exports `K_LEN` bytes of keying material for the application (RFC 8446 section 7.5).
+///
+/// `exporter_master_secret` is the exporter master secret, or the early one for 0-RTT exporters.
+pub fn export<const K_LEN: usize>(
+    exporter_master_secret: &[u8; HASH_LEN],
     label: &[u8],
-    msgs: &[u8],
-) -> [u8; K_LEN]
-where
-    H: BlockHasher<H_LEN, B_LEN>,
-{
-    hkdf_expand_label::<H_LEN, B_LEN, K_LEN, H>(secret, label, &H::hash(msgs))
+    context: &[u8],
+) -> [u8; K_LEN] {
+    let secret = derive_secret(exporter_master_secret, label, &Sha256::hash(b""));
+    expand_label::<K_LEN>(&secret, b"exporter", &Sha256::hash(context))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HkdfLabel;
+    use crate::codec::{Codec, DecodeError, Reader, Writer};
+    use crylib::hash::{Hasher, Sha256};
+
+    // test vectors from https://datatracker.ietf.org/doc/html/rfc8448#section-3
+    #[test]
+    fn derive_secret() {
+        let early_secret = [
+            0x33, 0xad, 0x0a, 0x1c, 0x60, 0x7e, 0xc0, 0x3b, 0x09, 0xe6, 0xcd, 0x98, 0x93, 0x68,
+            0x0c, 0xe2, 0x10, 0xad, 0xf3, 0x00, 0xaa, 0x1f, 0x26, 0x60, 0xe1, 0xb2, 0x2e, 0x10,
+            0xf1, 0x70, 0xf9, 0x2a,
+        ];
+        let derived = [
+            0x6f, 0x26, 0x15, 0xa1, 0x08, 0xc7, 0x02, 0xc5, 0x67, 0x8f, 0x54, 0xfc, 0x9d, 0xba,
+            0xb6, 0x97, 0x16, 0xc0, 0x76, 0x18, 0x9c, 0x48, 0x25, 0x0c, 0xeb, 0xea, 0xc3, 0x57,
+            0x6c, 0x36, 0x11, 0xba,
+        ];
+        assert_eq!(
+            super::derive_secret(&early_secret, b"derived", &Sha256::hash(b"")),
+            derived
+        );
+    }
+
+    #[test]
+    fn expand_label() {
+        let server_handshake_secret = [
+            0xb6, 0x7b, 0x7d, 0x69, 0x0c, 0xc1, 0x6c, 0x4e, 0x75, 0xe5, 0x42, 0x13, 0xcb, 0x2d,
+            0x37, 0xb4, 0xe9, 0xc9, 0x12, 0xbc, 0xde, 0xd9, 0x10, 0x5d, 0x42, 0xbe, 0xfd, 0x59,
+            0xd3, 0x91, 0xad, 0x38,
+        ];
+        let key = [
+            0x3f, 0xce, 0x51, 0x60, 0x09, 0xc2, 0x17, 0x27, 0xd0, 0xf2, 0xe4, 0xe8, 0x6e, 0xe4,
+            0x03, 0xbc,
+        ];
+        let iv = [
+            0x5d, 0x31, 0x3e, 0xb2, 0x67, 0x12, 0x76, 0xee, 0x13, 0x00, 0x0b, 0x30,
+        ];
+        assert_eq!(
+            super::expand_label::<16>(&server_handshake_secret, b"key", b""),
+            key
+        );
+        assert_eq!(
+            super::expand_label::<12>(&server_handshake_secret, b"iv", b""),
+            iv
+        );
+    }
+
+    #[test]
+    fn hkdf_label() {
+        let label = HkdfLabel {
+            length: 16,
+            label: b"key",
+            context: b"",
+        };
+        let mut buf = [0; HkdfLabel::MAX_LEN];
+        let mut writer = Writer::new(&mut buf);
+        label.encode(&mut writer).unwrap();
+        assert_eq!(writer.written(), b"\x00\x10\x09tls13 key\x00");
+        assert_eq!(
+            HkdfLabel::decode(&mut Reader::new(writer.written())),
+            Ok(label)
+        );
+
+        // the prefix keeps TLS 1.3 labels apart from any other use of HKDF
+        assert_eq!(
+            HkdfLabel::decode(&mut Reader::new(b"\x00\x10\x03key\x00")),
+            Err(DecodeError)
+        );
+
+        // the longest label and context that can be encoded
+        let label = HkdfLabel {
+            length: u16::MAX,
+            label: &[b'a'; 249],
+            context: &[0; 255],
+        };
+        let mut writer = Writer::new(&mut buf);
+        label.encode(&mut writer).unwrap();
+        assert_eq!(writer.len(), HkdfLabel::MAX_LEN);
+    }
+
+    #[test]
+    #[should_panic]
+    fn long_label() {
+        super::expand_label::<16>(&[0; 32], &[b'a'; 250], b"");
+    }
+
+    /// Decodes a hex test vector.
+    fn hex<const N: usize>(hex: &str) -> [u8; N] {
+        let mut bytes = [0; N];
+        crylib::encoding::hex::decode(hex.as_bytes(), &mut bytes).unwrap();
+        bytes
+    }
+
+    // test vectors from https://datatracker.ietf.org/doc/html/rfc8448#section-3
+    #[test]
+    fn full_handshake() {
+        let shared_secret: [u8; 32] =
+            hex("8bd4054fb55b9d63fdfbacf9f04b9f0d35e6d63f537563efd46272900f89492d");
+        let server_hello_hash =
+            hex("860c06edc07858ee8e78f0e7428c58edd6b43f2ca3e6e95f02ed063cf0e1cad8");
+        let server_finished_hash =
+            hex("9608102a0f1ccc6db6250b7b7e417b1a000eaada3daae4777a7686c9ff83df13");
+        let client_finished_hash =
+            hex("209145a96ee8e2a122ff810047cc952684658d6049e86429426db87c54ad143d");
+
+        let early_secret = super::EarlySecret::new(None);
+        assert_eq!(
+            *early_secret.secret(),
+            hex("33ad0a1c607ec03b09e6cd9893680ce210adf300aa1f2660e1b22e10f170f92a")
+        );
+        assert_eq!(
+            super::derived(early_secret.secret()),
+            hex("6f2615a108c702c5678f54fc9dbab69716c076189c48250cebeac3576c3611ba")
+        );
+
+        let handshake = early_secret.handshake_secrets(&shared_secret, &server_hello_hash);
+        assert_eq!(
+            *handshake.secret(),
+            hex("1dc826e93606aa6fdc0aadc12f741b01046aa6b99f691ed221a9f0ca043fbeac")
+        );
+        assert_eq!(
+            handshake.client,
+            hex("b3eddb126e067f35a780b3abf45e2d8f3b1a950738f52e9600746a0e27a55a21")
+        );
+        assert_eq!(
+            handshake.server,
+            hex("b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38")
+        );
+        assert_eq!(
+            super::traffic_key(&handshake.client),
+            hex("dbfaa693d1762c5b666af5d950258d01")
+        );
+        assert_eq!(
+            super::traffic_iv(&handshake.client),
+            hex("5bd3c71b836e0b76bb73265f")
+        );
+        assert_eq!(
+            super::derived(handshake.secret()),
+            hex("43de77e0c77713859a944db9db2590b53190a65b3ee2e4f12dd7a0bb7ce254b4")
+        );
+
+        let master_secret = handshake.master_secret();
+        assert_eq!(
+            *master_secret.secret(),
+            hex("18df06843d13a08bf2a449844c5f8a478001bc4d4c627984d5a41da8d0402919")
+        );
+        let application = master_secret.traffic_secrets(&server_finished_hash);
+        assert_eq!(
+            application.client,
+            hex("9e40646ce79a7f9dc05af8889bce6552875afa0b06df0087f792ebb7c17504a5")
+        );
+        assert_eq!(
+            application.server,
+            hex("a11af9f05531f856ad47116b45a950328204b4f44bfb6b3a4b4f1f3fcb631643")
+        );
+        assert_eq!(
+            super::traffic_key(&application.server),
+            hex("9f02283b6c9c07efc26bb9f2ac92e356")
+        );
+        assert_eq!(
+            super::traffic_iv(&application.server),
+            hex("cf782b88dd83549aadf1e984")
+        );
+        assert_eq!(
+            master_secret.exporter_master_secret(&server_finished_hash),
+            hex("fe22f881176eda18eb8f44529e6792c50c9a3f89452f68d8ae311b4309d3cf50")
+        );
+        let resumption_master_secret =
+            master_secret.resumption_master_secret(&client_finished_hash);
+        assert_eq!(
+            resumption_master_secret,
+            hex("7df235f2031d2a051287d02b0241b0bfdaf86cc856231f2d5aba46c434ec196c")
+        );
+        assert_eq!(
+            super::resumption_psk(&resumption_master_secret, &[0, 0]),
+            hex("4ecd0eb6ec3b4d87f5d6028f922ca4c5851a277fd41311c9e62d2c9492e1c4f3")
+        );
+    }
+
+    // test vectors from https://datatracker.ietf.org/doc/html/rfc8448#section-4
+    #[test]
+    fn resumed_handshake() {
+        let psk = hex::<32>("4ecd0eb6ec3b4d87f5d6028f922ca4c5851a277fd41311c9e62d2c9492e1c4f3");
+        let client_hello_hash =
+            hex("08ad0fa05d7c7233b1775ba2ff9f4c5b8b59276b7f227f13a976245f5d960913");
+
+        let early_secret = super::EarlySecret::new(Some(&psk[..]));
+        assert_eq!(
+            *early_secret.secret(),
+            hex("9b2188e9b2fc6d64d71dc329900e20bb41915000f678aa839cbb797cb7d8332c")
+        );
+        assert_eq!(
+            early_secret.binder_key(false),
+            hex("69fe131a3bbad5d63c64eebcc30e395b9d8107726a13d074e389dbc8a4e47256")
+        );
+        assert_eq!(
+            early_secret.client_early_traffic_secret(&client_hello_hash),
+            hex("3fbbe6a60deb66c30a32795aba0eff7eaa10105586e7be5c09678d63b6caab62")
+        );
+        assert_eq!(
+            early_secret.early_exporter_master_secret(&client_hello_hash),
+            hex("b2026866610937d7423e5be90862ccf24c0e6091186d34f812089ff5be2ef7df")
+        );
+        assert_ne!(
+            early_secret.binder_key(true),
+            early_secret.binder_key(false)
+        );
+    }
+
+    // computed with Python's hmac and hashlib, following RFC 8446 sections 7.2 and 7.5
+    #[test]
+    fn after_handshake() {
+        let server_secret = hex("a11af9f05531f856ad47116b45a950328204b4f44bfb6b3a4b4f1f3fcb631643");
+        assert_eq!(
+            super::next_traffic_secret(&server_secret),
+            hex("51921b8aa3001976eb401d0a4319a8516416a6c56001a357e5d162031e84f916")
+        );
+
+        let exporter_master_secret =
+            hex("fe22f881176eda18eb8f44529e6792c50c9a3f89452f68d8ae311b4309d3cf50");
+        assert_eq!(
+            super::export::<20>(&exporter_master_secret, b"EXPERIMENTAL turtls", b"context"),
+            hex::<20>("043158cb86c0176ce969bc95d4c475f24ff94448")
+        );
+    }
 }
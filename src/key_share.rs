@@ -0,0 +1,188 @@
+//! Ephemeral key shares for the key exchange.
+//!
+//! A client generates an [`EphemeralKey`] for every group it offers with [`KeyShares::generate`].
+//! Once the `ServerHello` names a group, [`KeyShares::select`] keeps the key for that group and
+//! drops the rest. Private keys are held in [`Zeroizing`] containers, so dropping them destroys
+//! them.
+use crate::cipher_suites::NamedGroup;
+use crate::codec::{Codec, DecodeError, EncodeError, Reader, Writer};
+use crate::signature::random_scalar;
+use crate::zeroizing::Zeroizing;
+use crylib::ec::{ecdh, EllipticCurve, Secp256r1};
+use crylib::finite_field::FieldElement;
+
+type Secp256r1Scalar = FieldElement<<Secp256r1 as EllipticCurve>::Order>;
+
+/// The groups that key shares can be generated for, in order of preference.
+pub const SUPPORTED_GROUPS: [NamedGroup; 1] = [NamedGroup::Secp256r1];
+
+/// A group and a public key for it, as sent in the `key_share` extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyShareEntry {
+    pub group: u16,
+    pub key_exchange: Vec<u8>,
+}
+
+impl Codec<'_> for KeyShareEntry {
+    fn encode(&self, writer: &mut Writer) -> Result<(), EncodeError> {
+        writer.write_u16(self.group)?;
+        writer.write_u16_prefixed(|writer| writer.write_slice(&self.key_exchange))
+    }
+
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let group = reader.read_u16()?;
+        let key_exchange = reader.read_u16_prefixed()?.read_rest().to_vec();
+        Ok(Self {
+            group,
+            key_exchange,
+        })
+    }
+}
+
+/// An ephemeral private key for one group.
+pub enum EphemeralKey {
+    Secp256r1(Zeroizing<Secp256r1Scalar>),
+}
+
+impl EphemeralKey {
+    /// Generates a new private key for `group`.
+    pub fn generate(group: NamedGroup) -> Result<Self, KeyShareError> {
+        match group {
+            NamedGroup::Secp256r1 => Ok(Self::Secp256r1(Zeroizing::new(random_scalar()?))),
+            _ => Err(KeyShareError::UnsupportedGroup),
+        }
+    }
+
+    pub const fn group(&self) -> NamedGroup {
+        match self {
+            Self::Secp256r1(_) => NamedGroup::Secp256r1,
+        }
+    }
+
+    /// Returns the entry that offers the public key that goes with `self`.
+    pub fn entry(&self) -> KeyShareEntry {
+        let key_exchange = match self {
+            Self::Secp256r1(key) => {
+                let public_key = ecdh::public_key::<Secp256r1>(key).expect("private key isn't 0");
+                ecdh::encode_point(&public_key).to_vec()
+            },
+        };
+        KeyShareEntry {
+            group: self.group() as u16,
+            key_exchange,
+        }
+    }
+
+    /// Returns the shared secret with the peer whose public key is `key_exchange`.
+    ///
+    /// Returns `None` if `key_exchange` isn't a valid public key for the group.
+    pub fn shared_secret(&self, key_exchange: &[u8]) -> Option<Zeroizing<[u8; 32]>> {
+        match self {
+            // the shared secret is only the x coordinate (RFC 8446 section 7.4.2)
+            Self::Secp256r1(key) => ecdh::shared_secret::<Secp256r1>(key, key_exchange)
+                .ok()
+                .map(Zeroizing::new),
+        }
+    }
+}
+
+/// The private keys of the key shares offered in a `ClientHello`.
+pub struct KeyShares {
+    keys: Vec<EphemeralKey>,
+}
+
+impl KeyShares {
+    /// Generates a private key for each group in `groups`.
+    pub fn generate(groups: &[NamedGroup]) -> Result<Self, KeyShareError> {
+        let keys = groups
+            .iter()
+            .map(|&group| EphemeralKey::generate(group))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { keys })
+    }
+
+    /// Returns the entries that offer the public keys that go with the private keys.
+    pub fn entries(&self) -> Vec<KeyShareEntry> {
+        self.keys.iter().map(EphemeralKey::entry).collect()
+    }
+
+    /// Returns the groups that key shares were generated for.
+    pub fn groups(&self) -> impl Iterator<Item = NamedGroup> + '_ {
+        self.keys.iter().map(EphemeralKey::group)
+    }
+
+    /// Returns the key for `group`, destroying the others.
+    ///
+    /// Returns `None` if no key share was generated for `group`.
+    pub fn select(self, group: u16) -> Option<EphemeralKey> {
+        self.keys
+            .into_iter()
+            .find(|key| key.group() as u16 == group)
+    }
+}
+
+/// The error that is returned when a key share can't be generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KeyShareError {
+    UnsupportedGroup,
+    Rng,
+}
+
+impl From<getrandom::Error> for KeyShareError {
+    fn from(_: getrandom::Error) -> Self {
+        Self::Rng
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EphemeralKey, KeyShareEntry, KeyShareError, KeyShares, SUPPORTED_GROUPS};
+    use crate::cipher_suites::NamedGroup;
+    use crate::codec::{Codec, Reader, Writer};
+
+    #[test]
+    fn key_exchange() {
+        let client = KeyShares::generate(&SUPPORTED_GROUPS).unwrap();
+        let entries = client.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].group, NamedGroup::Secp256r1 as u16);
+        assert_eq!(entries[0].key_exchange.len(), 65);
+
+        let server = EphemeralKey::generate(NamedGroup::Secp256r1).unwrap();
+        let server_secret = server.shared_secret(&entries[0].key_exchange).unwrap();
+        let client = client.select(NamedGroup::Secp256r1 as u16).unwrap();
+        let client_secret = client.shared_secret(&server.entry().key_exchange).unwrap();
+        assert_eq!(*client_secret, *server_secret);
+
+        let mut off_curve = server.entry().key_exchange;
+        off_curve[64] ^= 1;
+        assert!(client.shared_secret(&off_curve).is_none());
+    }
+
+    #[test]
+    fn select() {
+        let keys = KeyShares::generate(&SUPPORTED_GROUPS).unwrap();
+        assert!(keys.select(NamedGroup::X25519 as u16).is_none());
+    }
+
+    #[test]
+    fn unsupported_group() {
+        assert_eq!(
+            KeyShares::generate(&[NamedGroup::Secp256r1, NamedGroup::X448]).err(),
+            Some(KeyShareError::UnsupportedGroup)
+        );
+    }
+
+    #[test]
+    fn entry_codec() {
+        let entry = KeyShareEntry {
+            group: NamedGroup::X25519 as u16,
+            key_exchange: vec![0xaa; 32],
+        };
+        let mut buf = [0; 36];
+        let mut writer = Writer::new(&mut buf);
+        entry.encode(&mut writer).unwrap();
+        assert_eq!(writer.written()[..4], [0x00, 0x1d, 0x00, 0x20]);
+        assert_eq!(KeyShareEntry::decode(&mut Reader::new(&buf)), Ok(entry));
+    }
+}
@@ -8,51 +8,401 @@
 
 mod aead;
 mod alert;
+mod blocking;
+mod certificate;
 mod cipher_suites;
+mod client;
 mod client_hello;
+mod codec;
+mod connection;
+#[cfg(feature = "dissect")]
+pub mod dissect;
 mod extensions;
 mod handshake;
+#[cfg(feature = "testing")]
+pub mod key_schedule;
+#[cfg(not(feature = "testing"))]
 mod key_schedule;
+mod key_share;
+mod name;
+mod oid;
+mod padding;
+mod pin;
+#[cfg(any(feature = "probe", feature = "dissect"))]
+mod pretty;
+mod private_key;
+#[cfg(feature = "probe")]
+pub mod probe;
+mod psk;
+mod public_key;
 mod record;
+mod server;
 mod server_hello;
+mod signature;
+mod sni;
+#[cfg(any(test, feature = "testing"))]
+pub mod test_util;
+mod ticket;
+mod transcript;
 mod versions;
+mod zeroizing;
 
-use aead::{AeadReader, AeadWriter};
-use cipher_suites::GroupKeys;
-use client_hello::ClientHello;
-use record::Message;
+pub use blocking::{complete_handshake, connect, HandshakeError};
+pub use certificate::{Certificate, CertificateError, SystemClock, TimeProvider, ValidityChecker};
+pub use client::ClientConnection;
+#[cfg(feature = "dangerous_handoff")]
+pub use connection::HandoffError;
+pub use connection::{ChannelBindingError, ConnectionError};
+pub use name::{Attribute, AttributeValue, DistinguishedName, Rdn};
+pub use padding::PaddingPolicy;
+pub use pin::{certificate_pin, spki_pin, PinnedCertVerifier};
+pub use private_key::{EcPrivateKey, PrivateKey, PrivateKeyError};
+pub use psk::{ExternalPsk, PskError, PskHash, PskKeyExchangeMode};
+pub use public_key::{PublicKey, PublicKeyError};
+pub use server::{ServerConfig, ServerConnection};
+pub use signature::SignatureError;
+pub use sni::{CertifiedKeySet, UnknownNamePolicy};
+pub use ticket::{SessionTicket, SessionTicketError};
+
+use record::RecordHeader;
 use std::ffi::c_void;
+use std::slice;
 
-pub struct State {
-    aead_writer: AeadWriter,
-    aead_reader: AeadReader,
+/// The write callback of a connection: given a file descriptor, a buffer, and its length, it
+/// returns how many bytes were written, or a negative value on failure.
+// TODO: use c_size_t and c_ssize_t once stabilized
+pub type WriteFn = extern "C" fn(i32, *const c_void, usize) -> isize;
+/// The read callback of a connection: given a file descriptor, a buffer, and its length, it
+/// returns how many bytes were read, zero at the end of the stream, or a negative value on failure.
+pub type ReadFn = extern "C" fn(i32, *mut c_void, usize) -> isize;
+/// The certificate callback of a connection: given the DER certificates the server sent, leaf
+/// first, as an array of pointers and an array of lengths, along with their count, it returns
+/// whether to trust them.
+pub type VerifyFn = extern "C" fn(*const *const u8, *const usize, usize) -> bool;
 
-    group_keys: GroupKeys,
+/// A connection established by [`client_shake_hands`].
+pub struct State {
+    conn: ClientConnection,
+    fd: i32,
+    write: WriteFn,
+    read: ReadFn,
+    /// Application data that has been received, but didn't fit in the caller's buffer.
+    received: Vec<u8>,
 }
 
+/// The result of [`client_shake_hands`].
 #[repr(C)]
 pub enum ShakeResult {
+    /// The handshake succeeded. The connection has to be freed with [`free_state`].
     Ok(*mut State),
+    /// Random numbers couldn't be generated.
     RngError,
+    /// A callback failed, or the server closed the stream.
+    IoError,
+    /// The handshake failed, and the server was sent an alert saying why.
+    HandshakeFailed,
 }
 
+/// Runs a TLS 1.3 handshake with the server at the other end of `fd`.
+///
+/// Bytes are moved with `write` and `read`, which are always passed `fd`. The certificates the
+/// server sends are handed to `verify`.
 #[no_mangle]
 pub extern "C" fn client_shake_hands(
-    // TODO: use c_size_t and c_ssize_t once stabilized
     fd: i32,
-    write: extern "C" fn(i32, *const c_void, usize) -> isize,
-    read: extern "C" fn(i32, *mut c_void, usize) -> isize,
+    write: WriteFn,
+    read: ReadFn,
+    verify: VerifyFn,
 ) -> ShakeResult {
-    let Ok(client_hello) = ClientHello::new() else {
+    let conn = ClientConnection::new(move |certs| {
+        let ptrs: Vec<*const u8> = certs.iter().map(|cert| cert.as_ptr()).collect();
+        let lens: Vec<usize> = certs.iter().map(|cert| cert.len()).collect();
+        verify(ptrs.as_ptr(), lens.as_ptr(), certs.len())
+    });
+    let Ok(conn) = conn else {
         return ShakeResult::RngError;
     };
-    write(
+    let mut state = State {
+        conn,
         fd,
-        client_hello.as_ref() as *const [u8] as *const c_void,
-        client_hello.len(),
-    );
+        write,
+        read,
+        received: Vec::new(),
+    };
+    while state.conn.is_handshaking() {
+        if !state.flush() {
+            return ShakeResult::IoError;
+        }
+        match state.read_record() {
+            Some(Ok(())) => {},
+            Some(Err(_)) => {
+                // let the server know why, if it's still listening
+                state.flush();
+                return ShakeResult::HandshakeFailed;
+            },
+            None => return ShakeResult::IoError,
+        }
+    }
+    // our `Finished`
+    if !state.flush() {
+        return ShakeResult::IoError;
+    }
+    if state.conn.is_closed() {
+        return ShakeResult::HandshakeFailed;
+    }
+    ShakeResult::Ok(Box::into_raw(Box::new(state)))
+}
+
+/// Encrypts the `len` bytes at `buf` and sends them.
+///
+/// Returns `false` if the connection has been closed or the data couldn't be sent.
+///
+/// # Safety
+///
+/// `state` must have been returned by [`client_shake_hands`], and not freed. `buf` must point to
+/// `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn send_data(state: *mut State, buf: *const c_void, len: usize) -> bool {
+    // SAFETY: the caller guarantees that `state` is live and `buf` is readable for `len` bytes
+    let (state, data) = unsafe { (&mut *state, slice::from_raw_parts(buf as *const u8, len)) };
+    state.conn.write(data).is_ok() && state.flush()
+}
+
+/// Receives application data into the `len` bytes at `buf`.
+///
+/// Returns how many bytes were received, zero once the server has closed the connection, or a
+/// negative value on failure. Data that doesn't fit is kept for the next call.
+///
+/// # Safety
+///
+/// `state` must have been returned by [`client_shake_hands`], and not freed. `buf` must point to
+/// `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn receive_data(state: *mut State, buf: *mut c_void, len: usize) -> isize {
+    // SAFETY: the caller guarantees that `state` is live and `buf` is writable for `len` bytes
+    let (state, buf) = unsafe { (&mut *state, slice::from_raw_parts_mut(buf as *mut u8, len)) };
+    loop {
+        if state.received.is_empty() {
+            state.received = state.conn.take_received();
+        }
+        if !state.received.is_empty() || state.conn.is_closed() {
+            let len = state.received.len().min(buf.len());
+            buf[..len].copy_from_slice(&state.received[..len]);
+            state.received.drain(..len);
+            return len as isize;
+        }
+        match state.read_record() {
+            Some(Ok(())) => {},
+            // a server that closes the stream without a `close_notify` may have cut data off
+            Some(Err(_)) | None => {
+                state.flush();
+                return -1;
+            },
+        }
+        // key updates are answered
+        if !state.flush() {
+            return -1;
+        }
+    }
+}
+
+/// Frees a connection returned by [`client_shake_hands`].
+///
+/// # Safety
+///
+/// `state` must have been returned by [`client_shake_hands`], and not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn free_state(state: *mut State) {
+    // SAFETY: the caller guarantees that `state` came from `Box::into_raw`, and is only freed once
+    drop(unsafe { Box::from_raw(state) });
+}
+
+impl State {
+    /// Sends everything the connection has queued, returning whether that worked.
+    fn flush(&mut self) -> bool {
+        let outgoing = self.conn.take_outgoing();
+        let mut written = 0;
+        while written < outgoing.len() {
+            let rest = &outgoing[written..];
+            let n = (self.write)(self.fd, rest.as_ptr() as *const c_void, rest.len());
+            if n <= 0 {
+                return false;
+            }
+            written += n as usize;
+        }
+        true
+    }
+
+    /// Reads one record and processes it.
+    ///
+    /// Returns `None` if a callback failed or the stream ended. Records are read one at a time,
+    /// so nothing past the end of the record is taken from the stream.
+    fn read_record(&mut self) -> Option<Result<(), ConnectionError>> {
+        let mut header = [0; RecordHeader::SIZE];
+        self.read_exact(&mut header)?;
+        // the header is checked before its fragment is read
+        let len = match self.conn.read_header(&header) {
+            Ok(header) => header.len as usize,
+            Err(err) => return Some(Err(err)),
+        };
+        let mut record = vec![0; RecordHeader::SIZE + len];
+        record[..RecordHeader::SIZE].copy_from_slice(&header);
+        self.read_exact(&mut record[RecordHeader::SIZE..])?;
+        Some(self.conn.read_tls(&mut record).map(|_| ()))
+    }
+
+    /// Fills `buf` with `read`, returning `None` if it fails or the stream ends first.
+    fn read_exact(&mut self, buf: &mut [u8]) -> Option<()> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let rest = &mut buf[filled..];
+            let n = (self.read)(self.fd, rest.as_mut_ptr() as *mut c_void, rest.len());
+            if n <= 0 {
+                return None;
+            }
+            filled += n as usize;
+        }
+        Some(())
+    }
+}
+
+// A config is shared between threads behind an `Arc`, and a connection is moved to whichever
+// thread serves it. This fails to compile if either stops being possible.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ServerConfig>();
+    assert_send_sync::<ClientConnection>();
+    assert_send_sync::<ServerConnection>();
+};
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::ffi::c_void;
+    use std::io::{Read, Write};
+    use std::mem::ManuallyDrop;
+    use std::os::fd::{AsRawFd, FromRawFd};
+    use std::os::unix::net::UnixStream;
+    use std::slice;
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::{client_shake_hands, free_state, receive_data, send_data, ShakeResult};
+    use crate::connection::tests::{cert_key, CERT};
+    use crate::{ServerConfig, ServerConnection};
+
+    /// Borrows the socket `fd` without closing it afterwards.
+    fn socket(fd: i32) -> ManuallyDrop<UnixStream> {
+        // SAFETY: the tests only pass the file descriptors of sockets they keep open
+        ManuallyDrop::new(unsafe { UnixStream::from_raw_fd(fd) })
+    }
+
+    extern "C" fn write(fd: i32, buf: *const c_void, len: usize) -> isize {
+        // SAFETY: `client_shake_hands` passes buffers that are readable for `len` bytes
+        let buf = unsafe { slice::from_raw_parts(buf as *const u8, len) };
+        socket(fd).write(buf).map_or(-1, |n| n as isize)
+    }
+
+    extern "C" fn read(fd: i32, buf: *mut c_void, len: usize) -> isize {
+        // SAFETY: `client_shake_hands` passes buffers that are writable for `len` bytes
+        let buf = unsafe { slice::from_raw_parts_mut(buf as *mut u8, len) };
+        socket(fd).read(buf).map_or(-1, |n| n as isize)
+    }
+
+    extern "C" fn verify(certs: *const *const u8, lens: *const usize, count: usize) -> bool {
+        // SAFETY: `client_shake_hands` passes `count` certificates, each with its length
+        let (certs, lens) = unsafe {
+            (
+                slice::from_raw_parts(certs, count),
+                slice::from_raw_parts(lens, count),
+            )
+        };
+        // SAFETY: as above
+        count == 1 && unsafe { slice::from_raw_parts(certs[0], lens[0]) } == CERT
+    }
+
+    extern "C" fn reject(_: *const *const u8, _: *const usize, _: usize) -> bool {
+        false
+    }
+
+    /// Runs a server on `stream` until the handshake is done, then echoes one message.
+    fn serve(mut stream: UnixStream) {
+        let config = ServerConfig::new(vec![CERT.to_vec()], cert_key());
+        let mut server = ServerConnection::new(Arc::new(config));
+        let mut buf = vec![0; 0x10000];
+        let mut len = 0;
+        loop {
+            stream.write_all(&server.take_outgoing()).unwrap();
+            let received = server.take_received();
+            if !received.is_empty() {
+                server.write(&received).unwrap();
+                stream.write_all(&server.take_outgoing()).unwrap();
+                return;
+            }
+            let read = stream.read(&mut buf[len..]).unwrap();
+            if read == 0 {
+                return;
+            }
+            len += read;
+            match server.read_tls(&mut buf[..len]) {
+                Ok(used) => {
+                    buf.copy_within(used..len, 0);
+                    len -= used;
+                },
+                Err(_) => {
+                    let _ = stream.write_all(&server.take_outgoing());
+                    return;
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn client_shake_hands_over_callbacks() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let server = thread::spawn(move || serve(server));
+
+        let ShakeResult::Ok(state) = client_shake_hands(client.as_raw_fd(), write, read, verify)
+        else {
+            panic!("the handshake failed");
+        };
+        // SAFETY: `state` was just returned by `client_shake_hands`, and the buffers are live
+        unsafe {
+            assert!(send_data(state, b"hello".as_ptr() as *const c_void, 5));
+            let mut buf = [0; 16];
+            let len = receive_data(state, buf.as_mut_ptr() as *mut c_void, buf.len());
+            assert_eq!(&buf[..len as usize], b"hello");
+            free_state(state);
+        }
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn rejected_certificate() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let server = thread::spawn(move || serve(server));
+        let result = client_shake_hands(client.as_raw_fd(), write, read, reject);
+        assert!(matches!(result, ShakeResult::HandshakeFailed));
+        server.join().unwrap();
+    }
 
-    let mut buf = [0u8; Message::MAX_SIZE];
-    read(fd, &mut buf as *mut u8 as *mut c_void, buf.len());
-    todo!()
+    #[test]
+    fn invalid_record_header() {
+        let (client, mut server) = UnixStream::pair().unwrap();
+        let server = thread::spawn(move || {
+            // a handshake record that is longer than any plaintext record may be
+            server.write_all(&[0x16, 0x03, 0x03, 0xff, 0xff]).unwrap();
+            let mut received = Vec::new();
+            server.read_to_end(&mut received).unwrap();
+            received
+        });
+        let result = client_shake_hands(client.as_raw_fd(), write, read, verify);
+        assert!(matches!(result, ShakeResult::HandshakeFailed));
+        drop(client);
+        // the `ClientHello`, then a `record_overflow` alert
+        let received = server.join().unwrap();
+        assert_eq!(
+            received[received.len() - 7..],
+            [0x15, 0x03, 0x01, 0, 2, 2, 22]
+        );
+    }
 }
@@ -0,0 +1,357 @@
+//! X.500 distinguished names, such as the issuer and subject of a certificate (RFC 5280 section
+//! 4.1.2.4).
+//!
+//! A `Name` is a sequence of relative distinguished names (RDNs), most significant first, and
+//! each RDN is a set of attributes, which is almost always just one. Values that are
+//! `UTF8String`s, `PrintableString`s or `IA5String`s are decoded to text, and are checked to be
+//! valid for their type. Values of any other type are kept as their DER encoding.
+//!
+//! [`DistinguishedName`] displays as the string form of RFC 4514, which lists the RDNs least
+//! significant first, such as `CN=example.com,O=Example,C=US`.
+use std::fmt;
+
+use crate::certificate::CertificateError;
+use crylib::asn1::{self, InvalidDer};
+
+/// The attribute types with short names in RFC 4514 section 3.
+const SHORT_NAMES: [(&[u8], &str); 9] = [
+    (&[0x55, 0x04, 0x03], "CN"),
+    (&[0x55, 0x04, 0x07], "L"),
+    (&[0x55, 0x04, 0x08], "ST"),
+    (&[0x55, 0x04, 0x0a], "O"),
+    (&[0x55, 0x04, 0x0b], "OU"),
+    (&[0x55, 0x04, 0x06], "C"),
+    (&[0x55, 0x04, 0x09], "STREET"),
+    (
+        &[0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x19],
+        "DC",
+    ),
+    (
+        &[0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x01],
+        "UID",
+    ),
+];
+
+/// The `commonName` attribute type.
+const COMMON_NAME: &[u8] = SHORT_NAMES[0].0;
+
+/// A parsed X.500 `Name`, which borrows the DER it was parsed from.
+///
+/// ```
+/// # fn example(der: &[u8]) -> Result<(), turtls::CertificateError> {
+/// let cert = turtls::Certificate::parse(der)?;
+/// // e.g. "CN=example.com,O=Example"
+/// println!("subject: {}", cert.subject()?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistinguishedName<'a> {
+    rdns: Vec<Rdn<'a>>,
+}
+
+/// A relative distinguished name: the attributes at one level of a [`DistinguishedName`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rdn<'a> {
+    attributes: Vec<Attribute<'a>>,
+}
+
+/// An attribute type and its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attribute<'a> {
+    oid: &'a [u8],
+    value: AttributeValue<'a>,
+}
+
+/// The value of an [`Attribute`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeValue<'a> {
+    /// A `UTF8String`.
+    Utf8String(&'a str),
+    /// A `PrintableString`, which only has letters, digits, spaces and `'()+,-./:=?`.
+    PrintableString(&'a str),
+    /// An `IA5String`, which is ASCII.
+    Ia5String(&'a str),
+    /// A value of another type, such as a `BMPString`, as its whole DER encoding.
+    Other(&'a [u8]),
+}
+
+impl<'a> DistinguishedName<'a> {
+    /// Parses the DER encoding of a `Name`.
+    pub fn parse(der: &'a [u8]) -> Result<Self, CertificateError> {
+        let mut reader = asn1::Reader::new(der);
+        let mut name = reader.read_sequence()?;
+        reader.finish()?;
+        let mut rdns = Vec::new();
+        while !name.is_empty() {
+            rdns.push(Rdn::parse(&mut name)?);
+        }
+        Ok(Self { rdns })
+    }
+
+    /// Returns the RDNs, most significant first, which is the order they are encoded in.
+    pub fn rdns(&self) -> &[Rdn<'a>] {
+        &self.rdns
+    }
+
+    /// Returns the least significant common name, if there is one that is text.
+    pub fn common_name(&self) -> Option<&'a str> {
+        self.rdns
+            .iter()
+            .rev()
+            .flat_map(|rdn| &rdn.attributes)
+            .filter(|attribute| attribute.oid == COMMON_NAME)
+            .find_map(|attribute| attribute.value.as_str())
+    }
+}
+
+impl<'a> Rdn<'a> {
+    fn parse(reader: &mut asn1::Reader<'a>) -> Result<Self, InvalidDer> {
+        let mut set = reader.read_set()?;
+        let mut attributes = Vec::new();
+        while !set.is_empty() {
+            let mut attribute = set.read_sequence()?;
+            let oid = attribute.read_oid()?;
+            let value = AttributeValue::parse(attribute.read_any()?)?;
+            attribute.finish()?;
+            attributes.push(Attribute { oid, value });
+        }
+        // an RDN has at least one attribute
+        match attributes.is_empty() {
+            true => Err(InvalidDer),
+            false => Ok(Self { attributes }),
+        }
+    }
+
+    /// Returns the attributes, which are almost always just one.
+    pub fn attributes(&self) -> &[Attribute<'a>] {
+        &self.attributes
+    }
+}
+
+impl<'a> Attribute<'a> {
+    /// Returns the encoded OID of the attribute type, without its tag and length.
+    pub const fn oid(&self) -> &'a [u8] {
+        self.oid
+    }
+
+    /// Returns the short name of the attribute type, such as `CN`, if RFC 4514 gives it one.
+    pub fn short_name(&self) -> Option<&'static str> {
+        SHORT_NAMES
+            .iter()
+            .find(|(oid, _)| *oid == self.oid)
+            .map(|(_, name)| *name)
+    }
+
+    /// Returns the value of the attribute.
+    pub const fn value(&self) -> AttributeValue<'a> {
+        self.value
+    }
+}
+
+impl<'a> AttributeValue<'a> {
+    fn parse(element: asn1::Element<'a>) -> Result<Self, InvalidDer> {
+        let text = match element.tag {
+            asn1::UTF8_STRING | asn1::PRINTABLE_STRING | asn1::IA5_STRING => {
+                std::str::from_utf8(element.contents).map_err(|_| InvalidDer)?
+            },
+            _ => return Ok(Self::Other(element.encoding)),
+        };
+        match element.tag {
+            asn1::UTF8_STRING => Ok(Self::Utf8String(text)),
+            asn1::PRINTABLE_STRING if text.bytes().all(is_printable) => {
+                Ok(Self::PrintableString(text))
+            },
+            asn1::IA5_STRING if text.is_ascii() => Ok(Self::Ia5String(text)),
+            _ => Err(InvalidDer),
+        }
+    }
+
+    /// Returns the value as text, unless it is of a type that isn't decoded.
+    pub const fn as_str(&self) -> Option<&'a str> {
+        match self {
+            Self::Utf8String(text) | Self::PrintableString(text) | Self::Ia5String(text) => {
+                Some(text)
+            },
+            Self::Other(_) => None,
+        }
+    }
+}
+
+/// Returns whether `byte` is allowed in a `PrintableString` (X.680 section 41.4).
+fn is_printable(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b" '()+,-./:=?".contains(&byte)
+}
+
+impl fmt::Display for DistinguishedName<'_> {
+    /// Writes the name as in RFC 4514, least significant RDN first.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, rdn) in self.rdns.iter().rev().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{rdn}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Rdn<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, attribute) in self.attributes.iter().enumerate() {
+            if i > 0 {
+                f.write_str("+")?;
+            }
+            write!(f, "{attribute}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Attribute<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // types without a short name are written as dotted decimal
+        match self.short_name() {
+            Some(name) => f.write_str(name)?,
+            None => write_dotted(f, self.oid)?,
+        }
+        f.write_str("=")?;
+        match self.value {
+            AttributeValue::Other(der) => {
+                // values that aren't strings are written as `#` and the hex of their encoding
+                f.write_str("#")?;
+                der.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+            },
+            _ => write_escaped(f, self.value.as_str().unwrap_or_default()),
+        }
+    }
+}
+
+/// Writes the encoded OID `oid` in dotted decimal, such as `2.5.4.3`.
+fn write_dotted(f: &mut fmt::Formatter<'_>, oid: &[u8]) -> fmt::Result {
+    let mut arc = 0u128;
+    let mut first = true;
+    for &byte in oid {
+        arc = (arc << 7) | (byte & 0x7f) as u128;
+        if byte & 0x80 != 0 {
+            continue;
+        }
+        if first {
+            // the first two arcs share the first subidentifier
+            let top = (arc / 40).min(2);
+            write!(f, "{top}.{}", arc - top * 40)?;
+            first = false;
+        } else {
+            write!(f, ".{arc}")?;
+        }
+        arc = 0;
+    }
+    Ok(())
+}
+
+/// Writes `value` with the characters RFC 4514 section 2.4 requires to be escaped, escaped.
+fn write_escaped(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    let last = value.len().saturating_sub(1);
+    for (i, c) in value.char_indices() {
+        match c {
+            '"' | '+' | ',' | ';' | '<' | '>' | '\\' => write!(f, "\\{c}")?,
+            '#' if i == 0 => f.write_str("\\#")?,
+            ' ' if i == 0 || i == last => f.write_str("\\ ")?,
+            '\0' => f.write_str("\\00")?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AttributeValue, DistinguishedName};
+    use crate::certificate::CertificateError;
+    use crylib::asn1::{self, Writer};
+
+    /// The RDNs of a `Name`, whose attributes are each given as the last arc of an OID under
+    /// `2.5.4`, a tag, and contents.
+    type Rdns<'a> = &'a [&'a [(u8, u8, &'a [u8])]];
+
+    /// Encodes a `Name` with the RDNs `rdns`.
+    fn encode(rdns: Rdns) -> Vec<u8> {
+        let mut buf = [0; 512];
+        let mut writer = Writer::new(&mut buf);
+        writer
+            .write_sequence(|writer| {
+                for rdn in rdns {
+                    writer.write_set(|writer| {
+                        for (arc, tag, contents) in *rdn {
+                            writer.write_sequence(|writer| {
+                                writer.write_oid(&[0x55, 0x04, *arc])?;
+                                writer.write_element(*tag, contents)
+                            })?;
+                        }
+                        Ok(())
+                    })?;
+                }
+                Ok(())
+            })
+            .unwrap();
+        writer.written().to_vec()
+    }
+
+    #[test]
+    fn rfc_4514() {
+        let der = encode(&[
+            &[(6, asn1::PRINTABLE_STRING, b"US")],
+            &[(10, asn1::UTF8_STRING, "Exämple, Inc.".as_bytes())],
+            &[
+                (3, asn1::UTF8_STRING, b"example.com"),
+                (11, asn1::PRINTABLE_STRING, b"Web"),
+            ],
+        ]);
+        let name = DistinguishedName::parse(&der).unwrap();
+        assert_eq!(name.rdns().len(), 3);
+        assert_eq!(
+            name.rdns()[0].attributes()[0].value(),
+            AttributeValue::PrintableString("US")
+        );
+        assert_eq!(name.common_name(), Some("example.com"));
+        assert_eq!(
+            name.to_string(),
+            // the attributes of an RDN are sorted by their encodings
+            "OU=Web+CN=example.com,O=Exämple\\, Inc.,C=US"
+        );
+
+        // special characters are escaped, and unknown types are dotted decimal
+        let der = encode(&[&[(42, asn1::UTF8_STRING, b"#a+b=\"c\" ")]]);
+        let name = DistinguishedName::parse(&der).unwrap();
+        assert_eq!(name.common_name(), None);
+        assert_eq!(name.to_string(), "2.5.4.42=\\#a\\+b=\\\"c\\\"\\ ");
+
+        // values that aren't strings are written as hex
+        let der = encode(&[&[(3, 0x1e, &[0x00, 0x41])]]);
+        let name = DistinguishedName::parse(&der).unwrap();
+        assert_eq!(name.rdns()[0].attributes()[0].short_name(), Some("CN"));
+        assert_eq!(name.to_string(), "CN=#1e020041");
+
+        assert_eq!(
+            DistinguishedName::parse(&encode(&[])).unwrap().to_string(),
+            ""
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let cases: [Rdns; 4] = [
+            // an RDN with no attributes
+            &[&[]],
+            &[&[(3, asn1::PRINTABLE_STRING, b"under_score")]],
+            &[&[(3, asn1::UTF8_STRING, b"\xff")]],
+            &[&[(3, asn1::IA5_STRING, "é".as_bytes())]],
+        ];
+        for rdns in cases {
+            assert_eq!(
+                DistinguishedName::parse(&encode(rdns)),
+                Err(CertificateError::InvalidEncoding)
+            );
+        }
+    }
+}
@@ -0,0 +1,483 @@
+//! The object identifiers of the algorithms found in certificates and key files, and what they
+//! map to.
+//!
+//! An `AlgorithmIdentifier` is an OID followed by parameters whose type depends on the OID.
+//! [`KeyAlgorithm`] reads the ones of keys, for both [`PublicKey`](crate::PublicKey) and
+//! [`PrivateKey`](crate::PrivateKey) parsing. [`SignatureAlgorithm`] reads the ones of certificate
+//! signatures, and maps them to the [`SignatureScheme`] that [`signature`](crate::signature)
+//! verifies with `crylib`.
+//!
+//! Identifiers that aren't recognized, or whose parameters ask for something TLS 1.3 doesn't
+//! allow, such as RSASSA-PSS with SHA-1, are [`AlgorithmError::UnsupportedAlgorithm`] rather than
+//! invalid, so that callers can tell a certificate they can't use from a broken one.
+use crate::cipher_suites::SignatureScheme;
+use crylib::asn1::{self, InvalidDer};
+
+/// `id-ecPublicKey` (RFC 5480)
+pub const EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+/// `secp256r1` (RFC 5480)
+pub const SECP256R1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+/// `secp384r1` (RFC 5480)
+pub const SECP384R1: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x22];
+/// `secp521r1` (RFC 5480)
+pub const SECP521R1: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x23];
+/// `id-Ed25519` (RFC 8410)
+pub const ED25519: &[u8] = &[0x2b, 0x65, 0x70];
+/// `rsaEncryption` (RFC 3279)
+pub const RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+/// `id-RSASSA-PSS` (RFC 4055)
+pub const RSASSA_PSS: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0a];
+/// `id-mgf1` (RFC 4055)
+pub const MGF1: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x08];
+
+/// `ecdsa-with-SHA256` (RFC 5758)
+pub const ECDSA_WITH_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+/// `ecdsa-with-SHA384` (RFC 5758)
+pub const ECDSA_WITH_SHA384: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03];
+/// `ecdsa-with-SHA512` (RFC 5758)
+pub const ECDSA_WITH_SHA512: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x04];
+/// `sha256WithRSAEncryption` (RFC 4055)
+pub const SHA256_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b];
+/// `sha384WithRSAEncryption` (RFC 4055)
+pub const SHA384_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c];
+/// `sha512WithRSAEncryption` (RFC 4055)
+pub const SHA512_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d];
+
+/// `md5WithRSAEncryption` (RFC 3279), which is only recognized for channel bindings
+pub const MD5_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x04];
+/// `sha1WithRSAEncryption` (RFC 3279), which is only recognized for channel bindings
+pub const SHA1_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x05];
+/// `ecdsa-with-SHA1` (RFC 3279), which is only recognized for channel bindings
+pub const ECDSA_WITH_SHA1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x01];
+
+/// `id-sha256` (RFC 5754)
+pub const SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+/// `id-sha384` (RFC 5754)
+pub const SHA384: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02];
+/// `id-sha512` (RFC 5754)
+pub const SHA512: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03];
+
+/// An elliptic curve that keys can be on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Curve {
+    Secp256r1,
+    Secp384r1,
+    Secp521r1,
+}
+
+impl Curve {
+    /// Returns the curve whose OID is `oid`.
+    pub fn from_oid(oid: &[u8]) -> Result<Self, AlgorithmError> {
+        match oid {
+            SECP256R1 => Ok(Self::Secp256r1),
+            SECP384R1 => Ok(Self::Secp384r1),
+            SECP521R1 => Ok(Self::Secp521r1),
+            _ => Err(AlgorithmError::UnsupportedAlgorithm),
+        }
+    }
+}
+
+/// A hash function that signatures can be made over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    /// Reads the contents of a hash's `AlgorithmIdentifier`, whose parameters are `NULL` or
+    /// absent (RFC 5754 section 2).
+    fn parse(mut algorithm: asn1::Reader) -> Result<Self, AlgorithmError> {
+        let hash = match algorithm.read_oid()? {
+            SHA256 => Self::Sha256,
+            SHA384 => Self::Sha384,
+            SHA512 => Self::Sha512,
+            _ => return Err(AlgorithmError::UnsupportedAlgorithm),
+        };
+        algorithm.read_optional(asn1::NULL)?;
+        algorithm.finish()?;
+        Ok(hash)
+    }
+
+    /// Returns the hash that the `tls-server-end-point` channel binding of a certificate uses,
+    /// given the contents of the certificate's `signatureAlgorithm` (RFC 5929 section 4.1).
+    ///
+    /// That is the hash of the signature, except that MD5 and SHA-1 are replaced by SHA-256.
+    /// Signatures that don't use exactly one hash, such as Ed25519, have no binding, and are
+    /// [`AlgorithmError::UnsupportedAlgorithm`] like algorithms that aren't recognized.
+    pub fn for_end_point(algorithm: &[u8]) -> Result<Self, AlgorithmError> {
+        let oid = asn1::Reader::new(algorithm).read_oid()?;
+        if [MD5_WITH_RSA, SHA1_WITH_RSA, ECDSA_WITH_SHA1].contains(&oid) {
+            return Ok(Self::Sha256);
+        }
+        match SignatureAlgorithm::parse(asn1::Reader::new(algorithm))? {
+            SignatureAlgorithm::Ecdsa(hash)
+            | SignatureAlgorithm::RsaPkcs1(hash)
+            | SignatureAlgorithm::RsaPss(hash) => Ok(hash),
+            SignatureAlgorithm::Ed25519 => Err(AlgorithmError::UnsupportedAlgorithm),
+        }
+    }
+
+    /// The length of the hash in bytes.
+    const fn len(self) -> usize {
+        match self {
+            Self::Sha256 => 32,
+            Self::Sha384 => 48,
+            Self::Sha512 => 64,
+        }
+    }
+}
+
+/// The type of a key, from the `AlgorithmIdentifier` of a `SubjectPublicKeyInfo` or a PKCS#8
+/// `PrivateKeyInfo`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAlgorithm {
+    /// An elliptic curve key, for ECDSA.
+    Ec(Curve),
+    Ed25519,
+    /// An RSA key, for any RSA signature.
+    Rsa,
+    /// An RSA key that may only make RSASSA-PSS signatures, with the given hash if the key is
+    /// restricted to one.
+    RsaPss(Option<HashAlgorithm>),
+}
+
+impl KeyAlgorithm {
+    /// Reads the contents of a key's `AlgorithmIdentifier`.
+    pub fn parse(mut algorithm: asn1::Reader) -> Result<Self, AlgorithmError> {
+        let key = match algorithm.read_oid()? {
+            EC_PUBLIC_KEY => Self::Ec(Curve::from_oid(algorithm.read_oid()?)?),
+            // the parameters must be absent
+            ED25519 => Self::Ed25519,
+            // the parameters must be `NULL`
+            RSA_ENCRYPTION => {
+                algorithm.read(asn1::NULL)?;
+                Self::Rsa
+            },
+            // absent parameters leave the key unrestricted (RFC 4055 section 1.2)
+            RSASSA_PSS if algorithm.is_empty() => Self::RsaPss(None),
+            RSASSA_PSS => Self::RsaPss(Some(pss_params(algorithm.read_sequence()?)?)),
+            _ => return Err(AlgorithmError::UnsupportedAlgorithm),
+        };
+        algorithm.finish()?;
+        Ok(key)
+    }
+
+    /// Reads the algorithm of a DER `SubjectPublicKeyInfo`, returning it along with the encoded
+    /// key.
+    pub fn from_spki(spki: &[u8]) -> Result<(Self, &[u8]), AlgorithmError> {
+        let mut reader = asn1::Reader::new(spki);
+        let mut info = reader.read_sequence()?;
+        reader.finish()?;
+        let algorithm = info.read_sequence()?;
+        let key = info.read_bit_string()?;
+        info.finish()?;
+        Ok((Self::parse(algorithm)?, key))
+    }
+}
+
+/// The algorithm of a signature in a certificate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureAlgorithm {
+    Ecdsa(HashAlgorithm),
+    /// RSASSA-PKCS1-v1_5.
+    RsaPkcs1(HashAlgorithm),
+    /// RSASSA-PSS, with MGF1 over the same hash and a salt as long as the hash.
+    RsaPss(HashAlgorithm),
+    Ed25519,
+}
+
+impl SignatureAlgorithm {
+    /// Reads the contents of a signature's `AlgorithmIdentifier`.
+    pub fn parse(mut algorithm: asn1::Reader) -> Result<Self, AlgorithmError> {
+        let signature = match algorithm.read_oid()? {
+            // the parameters must be absent (RFC 5758 section 3.2)
+            ECDSA_WITH_SHA256 => Self::Ecdsa(HashAlgorithm::Sha256),
+            ECDSA_WITH_SHA384 => Self::Ecdsa(HashAlgorithm::Sha384),
+            ECDSA_WITH_SHA512 => Self::Ecdsa(HashAlgorithm::Sha512),
+            ED25519 => Self::Ed25519,
+            // the parameters must be `NULL` (RFC 4055 section 5)
+            oid @ (SHA256_WITH_RSA | SHA384_WITH_RSA | SHA512_WITH_RSA) => {
+                algorithm.read(asn1::NULL)?;
+                Self::RsaPkcs1(match oid {
+                    SHA256_WITH_RSA => HashAlgorithm::Sha256,
+                    SHA384_WITH_RSA => HashAlgorithm::Sha384,
+                    _ => HashAlgorithm::Sha512,
+                })
+            },
+            // the parameters must be present: their defaults are SHA-1
+            RSASSA_PSS => Self::RsaPss(pss_params(algorithm.read_sequence()?)?),
+            _ => return Err(AlgorithmError::UnsupportedAlgorithm),
+        };
+        algorithm.finish()?;
+        Ok(signature)
+    }
+
+    /// Returns the scheme that signatures of this algorithm by a key of type `key` are verified
+    /// with.
+    ///
+    /// TLS 1.3 ties each ECDSA hash to one curve, so ECDSA with another pairing is
+    /// [`AlgorithmError::UnsupportedAlgorithm`]. A key that can't make this kind of signature at
+    /// all is [`AlgorithmError::KeyMismatch`].
+    pub fn scheme(self, key: KeyAlgorithm) -> Result<SignatureScheme, AlgorithmError> {
+        use HashAlgorithm::{Sha256, Sha384, Sha512};
+        use SignatureScheme as S;
+
+        let scheme = match (self, key) {
+            (Self::Ecdsa(Sha256), KeyAlgorithm::Ec(Curve::Secp256r1)) => S::EcdsaSecp256r1Sha256,
+            (Self::Ecdsa(Sha384), KeyAlgorithm::Ec(Curve::Secp384r1)) => S::EcdsaSecp384r1Sha384,
+            (Self::Ecdsa(Sha512), KeyAlgorithm::Ec(Curve::Secp521r1)) => S::EcdsaSecp512r1Sha512,
+            (Self::Ecdsa(_), KeyAlgorithm::Ec(_)) => {
+                return Err(AlgorithmError::UnsupportedAlgorithm)
+            },
+            (Self::Ed25519, KeyAlgorithm::Ed25519) => S::Ed25519,
+            (Self::RsaPkcs1(hash), KeyAlgorithm::Rsa) => match hash {
+                Sha256 => S::RsaPkcs1Sha256,
+                Sha384 => S::RsaPkcs1Sha384,
+                Sha512 => S::RsaPkcs1Sha512,
+            },
+            (Self::RsaPss(hash), KeyAlgorithm::Rsa) => match hash {
+                Sha256 => S::RsaPssRsaeSha256,
+                Sha384 => S::RsaPssRsaeSha384,
+                Sha512 => S::RsaPssRsaeSha512,
+            },
+            (Self::RsaPss(hash), KeyAlgorithm::RsaPss(allowed))
+                if allowed.is_none_or(|allowed| allowed == hash) =>
+            {
+                match hash {
+                    Sha256 => S::RsaPssPssSha256,
+                    Sha384 => S::RsaPssPssSha384,
+                    Sha512 => S::RsaPssPssSha512,
+                }
+            },
+            _ => return Err(AlgorithmError::KeyMismatch),
+        };
+        Ok(scheme)
+    }
+}
+
+/// Reads `RSASSA-PSS-params` (RFC 4055 section 3.1), returning the hash they use.
+///
+/// Only the parameters TLS 1.3 allows are supported: MGF1 with the same hash as the message, a
+/// salt as long as the hash, and the default trailer field (RFC 8446 section 4.2.3).
+fn pss_params(mut params: asn1::Reader) -> Result<HashAlgorithm, AlgorithmError> {
+    // every field defaults to something based on SHA-1, which isn't supported
+    let hash = params.read_optional(asn1::context(0))?;
+    let mask_gen = params.read_optional(asn1::context(1))?;
+    let salt_len = params.read_optional(asn1::context(2))?;
+    let trailer = params.read_optional(asn1::context(3))?;
+    params.finish()?;
+
+    let (Some(hash), Some(mask_gen), Some(salt_len)) = (hash, mask_gen, salt_len) else {
+        return Err(AlgorithmError::UnsupportedAlgorithm);
+    };
+    let hash = HashAlgorithm::parse(explicit(hash)?.read_sequence()?)?;
+
+    let mut mask_gen = explicit(mask_gen)?.read_sequence()?;
+    if mask_gen.read_oid()? != MGF1 {
+        return Err(AlgorithmError::UnsupportedAlgorithm);
+    }
+    let mask_gen_hash = HashAlgorithm::parse(mask_gen.read_sequence()?)?;
+    mask_gen.finish()?;
+
+    let mut salt_len = explicit(salt_len)?;
+    let salt_len = salt_len.read_unsigned()?;
+    // the default of 1 can't be encoded in DER, so any trailer field is a different one
+    if mask_gen_hash != hash || salt_len != [hash.len() as u8] || trailer.is_some() {
+        return Err(AlgorithmError::UnsupportedAlgorithm);
+    }
+    Ok(hash)
+}
+
+/// Returns a reader over the single element of an explicitly tagged field.
+fn explicit(field: &[u8]) -> Result<asn1::Reader<'_>, InvalidDer> {
+    let mut reader = asn1::Reader::new(field);
+    let element = reader.read_any()?;
+    reader.finish()?;
+    Ok(asn1::Reader::new(element.encoding))
+}
+
+/// The error that is returned when an `AlgorithmIdentifier` can't be mapped to an algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AlgorithmError {
+    /// The identifier isn't valid DER, or its parameters don't have the type its OID requires.
+    InvalidEncoding,
+    /// The algorithm, the curve, or the hash isn't one that can be used.
+    UnsupportedAlgorithm,
+    /// The signature algorithm can't be used with the type of key it is checked with.
+    KeyMismatch,
+}
+
+impl From<InvalidDer> for AlgorithmError {
+    fn from(_: InvalidDer) -> Self {
+        Self::InvalidEncoding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AlgorithmError, Curve, HashAlgorithm, KeyAlgorithm, SignatureAlgorithm};
+    use crate::cipher_suites::SignatureScheme;
+    use crylib::asn1;
+
+    // the contents of the `AlgorithmIdentifier` OpenSSL writes for RSASSA-PSS with SHA-256
+    const PSS_SHA256: [u8; 65] = [
+        0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0a, 0x30, 0x34, 0xa0, 0x0f,
+        0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00,
+        0xa1, 0x1c, 0x30, 0x1a, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x08,
+        0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00,
+        0xa2, 0x03, 0x02, 0x01, 0x20,
+    ];
+    // `ecdsa-with-SHA256`
+    const ECDSA_SHA256: [u8; 10] = [0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+    // `sha256WithRSAEncryption`, with its `NULL` parameters
+    const RSA_SHA256: [u8; 13] = [
+        0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b, 0x05, 0x00,
+    ];
+
+    fn signature(algorithm: &[u8]) -> Result<SignatureAlgorithm, AlgorithmError> {
+        SignatureAlgorithm::parse(asn1::Reader::new(algorithm))
+    }
+
+    #[test]
+    fn signature_algorithms() {
+        assert_eq!(
+            signature(&ECDSA_SHA256),
+            Ok(SignatureAlgorithm::Ecdsa(HashAlgorithm::Sha256))
+        );
+        assert_eq!(
+            signature(&RSA_SHA256),
+            Ok(SignatureAlgorithm::RsaPkcs1(HashAlgorithm::Sha256))
+        );
+        assert_eq!(
+            signature(&PSS_SHA256),
+            Ok(SignatureAlgorithm::RsaPss(HashAlgorithm::Sha256))
+        );
+        assert_eq!(
+            KeyAlgorithm::parse(asn1::Reader::new(&PSS_SHA256)),
+            Ok(KeyAlgorithm::RsaPss(Some(HashAlgorithm::Sha256)))
+        );
+
+        // the parameters are required
+        assert_eq!(
+            signature(&RSA_SHA256[..11]),
+            Err(AlgorithmError::InvalidEncoding)
+        );
+        assert_eq!(
+            signature(&PSS_SHA256[..11]),
+            Err(AlgorithmError::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        // `sha1WithRSAEncryption`
+        let mut sha1 = RSA_SHA256;
+        sha1[10] = 0x05;
+        assert_eq!(signature(&sha1), Err(AlgorithmError::UnsupportedAlgorithm));
+
+        // a salt one byte shorter than the hash
+        let mut short_salt = PSS_SHA256;
+        short_salt[64] = 0x1f;
+        assert_eq!(
+            signature(&short_salt),
+            Err(AlgorithmError::UnsupportedAlgorithm)
+        );
+
+        // MGF1 with SHA-384
+        let mut mixed = PSS_SHA256;
+        mixed[57] = 0x02;
+        assert_eq!(signature(&mixed), Err(AlgorithmError::UnsupportedAlgorithm));
+
+        // the defaults are SHA-1
+        let mut defaults = PSS_SHA256[..13].to_vec();
+        defaults[12] = 0x00;
+        assert_eq!(
+            signature(&defaults),
+            Err(AlgorithmError::UnsupportedAlgorithm)
+        );
+
+        // `id-ecPublicKey` on brainpoolP256r1
+        let brainpool = [
+            0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x09, 0x2b, 0x24, 0x03,
+            0x03, 0x02, 0x08, 0x01, 0x01, 0x07,
+        ];
+        assert_eq!(
+            KeyAlgorithm::parse(asn1::Reader::new(&brainpool)),
+            Err(AlgorithmError::UnsupportedAlgorithm)
+        );
+    }
+
+    #[test]
+    fn end_point_hashes() {
+        assert_eq!(
+            HashAlgorithm::for_end_point(&ECDSA_SHA256),
+            Ok(HashAlgorithm::Sha256)
+        );
+        assert_eq!(
+            HashAlgorithm::for_end_point(&PSS_SHA256),
+            Ok(HashAlgorithm::Sha256)
+        );
+        // `sha384WithRSAEncryption`
+        let mut sha384 = RSA_SHA256;
+        sha384[10] = 0x0c;
+        assert_eq!(
+            HashAlgorithm::for_end_point(&sha384),
+            Ok(HashAlgorithm::Sha384)
+        );
+        // `md5WithRSAEncryption` and `sha1WithRSAEncryption` are bound with SHA-256
+        for last_arc in [0x04, 0x05] {
+            let mut legacy = RSA_SHA256;
+            legacy[10] = last_arc;
+            assert_eq!(
+                HashAlgorithm::for_end_point(&legacy),
+                Ok(HashAlgorithm::Sha256)
+            );
+        }
+        // `id-Ed25519` has no binding
+        assert_eq!(
+            HashAlgorithm::for_end_point(&[0x06, 0x03, 0x2b, 0x65, 0x70]),
+            Err(AlgorithmError::UnsupportedAlgorithm)
+        );
+        assert_eq!(
+            HashAlgorithm::for_end_point(&[0x05, 0x00]),
+            Err(AlgorithmError::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn schemes() {
+        use HashAlgorithm::{Sha256, Sha384};
+
+        let p256 = KeyAlgorithm::Ec(Curve::Secp256r1);
+        assert_eq!(
+            SignatureAlgorithm::Ecdsa(Sha256).scheme(p256),
+            Ok(SignatureScheme::EcdsaSecp256r1Sha256)
+        );
+        assert_eq!(
+            SignatureAlgorithm::Ecdsa(Sha384).scheme(p256),
+            Err(AlgorithmError::UnsupportedAlgorithm)
+        );
+        assert_eq!(
+            SignatureAlgorithm::RsaPss(Sha256).scheme(KeyAlgorithm::Rsa),
+            Ok(SignatureScheme::RsaPssRsaeSha256)
+        );
+        assert_eq!(
+            SignatureAlgorithm::RsaPss(Sha256).scheme(KeyAlgorithm::RsaPss(None)),
+            Ok(SignatureScheme::RsaPssPssSha256)
+        );
+        assert_eq!(
+            SignatureAlgorithm::RsaPss(Sha256).scheme(KeyAlgorithm::RsaPss(Some(Sha384))),
+            Err(AlgorithmError::KeyMismatch)
+        );
+        assert_eq!(
+            SignatureAlgorithm::RsaPkcs1(Sha256).scheme(KeyAlgorithm::RsaPss(None)),
+            Err(AlgorithmError::KeyMismatch)
+        );
+        assert_eq!(
+            SignatureAlgorithm::Ed25519.scheme(p256),
+            Err(AlgorithmError::KeyMismatch)
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Length hiding: padding records of application data, and splitting them at random boundaries,
+//! so that their sizes say less about what is being sent (RFC 8446 section 5.4).
+//!
+//! A [`PaddingPolicy`] is passed to `write_all_padded` on a connection. Padding costs bandwidth:
+//! [`PaddingPolicy::FULL`] makes every record as long as a record can be, while
+//! [`PaddingPolicy::block`] only rounds lengths up. Neither hides how many records were sent, which
+//! is what [`PaddingPolicy::with_random_splits`] blurs.
+use getrandom::getrandom;
+
+use crate::record::RecordHeader;
+
+/// How records of application data are padded and split.
+///
+/// ```
+/// use turtls::PaddingPolicy;
+///
+/// // every record has 256, 512, ... bytes of content and padding
+/// let policy = PaddingPolicy::block(256).with_random_splits();
+/// assert_ne!(policy, PaddingPolicy::NONE);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PaddingPolicy {
+    /// The content of each record is padded up to a multiple of this many bytes.
+    block_size: u16,
+    random_splits: bool,
+}
+
+impl PaddingPolicy {
+    /// No padding, and records as long as they can be, which is what `write` does.
+    pub const NONE: Self = Self::block(1);
+    /// Every record is padded to the longest a record can be.
+    pub const FULL: Self = Self::block(RecordHeader::MAX_PLAINTEXT_LEN);
+
+    /// Pads the content of each record up to a multiple of `block_size` bytes, or to the longest a
+    /// record can be if that is less.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if `block_size` is zero, or longer than a record can be (16 KiB).
+    pub const fn block(block_size: u16) -> Self {
+        assert!(
+            block_size != 0 && block_size <= RecordHeader::MAX_PLAINTEXT_LEN,
+            "the block size has to be between 1 byte and 16 KiB"
+        );
+        Self {
+            block_size,
+            random_splits: false,
+        }
+    }
+
+    /// Also splits data into records at random boundaries, rather than only where records are
+    /// full.
+    pub const fn with_random_splits(self) -> Self {
+        Self {
+            random_splits: true,
+            ..self
+        }
+    }
+
+    /// Returns the length of the content and of the padding of each record `len` bytes of data
+    /// are sent in.
+    pub(crate) fn records(self, len: usize) -> Result<Vec<(usize, usize)>, getrandom::Error> {
+        let max_len = RecordHeader::MAX_PLAINTEXT_LEN as usize;
+        let mut records = Vec::new();
+        let mut rest = len;
+        while rest > 0 {
+            let mut content_len = rest.min(max_len);
+            if self.random_splits {
+                let mut random = [0; 2];
+                getrandom(&mut random)?;
+                content_len = 1 + u16::from_be_bytes(random) as usize % content_len;
+            }
+            let padded_len = content_len
+                .next_multiple_of(self.block_size as usize)
+                .min(max_len);
+            records.push((content_len, padded_len - content_len));
+            rest -= content_len;
+        }
+        Ok(records)
+    }
+}
+
+impl Default for PaddingPolicy {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PaddingPolicy;
+
+    #[test]
+    fn records() {
+        assert_eq!(PaddingPolicy::NONE.records(0), Ok(vec![]));
+        assert_eq!(PaddingPolicy::NONE.records(5), Ok(vec![(5, 0)]));
+        assert_eq!(
+            PaddingPolicy::NONE.records(0x4001),
+            Ok(vec![(0x4000, 0), (1, 0)])
+        );
+        assert_eq!(PaddingPolicy::block(256).records(5), Ok(vec![(5, 251)]));
+        assert_eq!(PaddingPolicy::block(256).records(256), Ok(vec![(256, 0)]));
+        // the last block is cut short at the longest a record can be
+        assert_eq!(
+            PaddingPolicy::block(0x3000).records(0x3001),
+            Ok(vec![(0x3001, 0xfff)])
+        );
+        assert_eq!(PaddingPolicy::FULL.records(5), Ok(vec![(5, 0x3ffb)]));
+    }
+
+    #[test]
+    fn random_splits() {
+        let policy = PaddingPolicy::block(16).with_random_splits();
+        let records = policy.records(0x10000).unwrap();
+        assert_eq!(records.iter().map(|(len, _)| len).sum::<usize>(), 0x10000);
+        for (len, padding) in records {
+            assert!(len > 0);
+            assert_eq!((len + padding) % 16, 0);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_block() {
+        PaddingPolicy::block(0);
+    }
+}
@@ -0,0 +1,136 @@
+//! Public key pinning: trusting a server because of which key is in its chain, rather than which
+//! authority signed it.
+//!
+//! A pin is the base64 SHA-256 hash of a DER `SubjectPublicKeyInfo`, which is the format of HPKP's
+//! `pin-sha256` and of the pins most mobile platforms are configured with. Pinning the key rather
+//! than the certificate keeps the pin valid when the certificate is reissued for the same key.
+use crylib::encoding::{base64, InvalidEncoding};
+use crylib::hash::{Hasher, Sha256};
+
+use crate::certificate::{Certificate, CertificateError};
+
+/// Returns the pin of `spki`, which is a DER `SubjectPublicKeyInfo`.
+pub fn spki_pin(spki: &[u8]) -> String {
+    let mut pin = [0; base64::encoded_len(32)];
+    base64::encode(&Sha256::hash(spki), &mut pin).to_string()
+}
+
+/// Returns the pin of the public key of the DER certificate `cert`.
+pub fn certificate_pin(cert: &[u8]) -> Result<String, CertificateError> {
+    Ok(spki_pin(Certificate::parse(cert)?.spki()))
+}
+
+/// A certificate verifier that trusts chains that lead to a pinned key.
+///
+/// A chain is trusted if one of its certificates has a pinned key, and every certificate before
+/// it is signed by the one after it. The pinned certificate doesn't need to be the last one, so
+/// pinning an intermediate works whether or not the server also sends the root.
+///
+/// Nothing but the signatures is checked: names and validity periods are up to the caller, for
+/// example with a [`ValidityChecker`](crate::ValidityChecker).
+///
+/// ```
+/// use turtls::{ClientConnection, PinnedCertVerifier};
+///
+/// let pins = PinnedCertVerifier::new(["jDvRGxrCsKm1TJCIKYQcqyQX3z3pugLuhn1pBOPwdS4="]).unwrap();
+/// let client = ClientConnection::new(move |certs| pins.verify(certs));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinnedCertVerifier {
+    /// The SHA-256 hashes of the pinned keys.
+    pins: Vec<[u8; 32]>,
+}
+
+impl PinnedCertVerifier {
+    /// Creates a verifier that trusts the keys whose pins are `pins`.
+    ///
+    /// Returns [`InvalidEncoding`] if any of them isn't the base64 encoding of 32 bytes.
+    pub fn new(pins: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self, InvalidEncoding> {
+        let pins = pins
+            .into_iter()
+            .map(|pin| {
+                let pin = pin.as_ref().as_bytes();
+                let mut hash = [0; 32];
+                if base64::decoded_len(pin)? != 32 {
+                    return Err(InvalidEncoding);
+                }
+                base64::decode(pin, &mut hash)?;
+                Ok(hash)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { pins })
+    }
+
+    /// Returns whether to trust `chain`, which is the DER certificates a peer sent, leaf first.
+    pub fn verify(&self, chain: &[&[u8]]) -> bool {
+        let Ok(chain) = chain
+            .iter()
+            .map(|cert| Certificate::parse(cert))
+            .collect::<Result<Vec<_>, _>>()
+        else {
+            return false;
+        };
+        for (i, cert) in chain.iter().enumerate() {
+            if self.pins.contains(&Sha256::hash(cert.spki())) {
+                return true;
+            }
+            let signed = chain
+                .get(i + 1)
+                .is_some_and(|issuer| cert.verify_signed_by(issuer.spki()).is_ok());
+            if !signed {
+                return false;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{certificate_pin, spki_pin, PinnedCertVerifier};
+    use crate::certificate::Certificate;
+    use crate::connection::tests::CERT;
+    use crylib::encoding::InvalidEncoding;
+
+    #[test]
+    fn pins() {
+        let pin = certificate_pin(&CERT).unwrap();
+        assert_eq!(pin, spki_pin(Certificate::parse(&CERT).unwrap().spki()));
+        assert_eq!(pin.len(), 44);
+        // the pin of an empty key, which is the SHA-256 hash of nothing
+        assert_eq!(
+            spki_pin(b""),
+            "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+        );
+    }
+
+    #[test]
+    fn verify() {
+        let pin = certificate_pin(&CERT).unwrap();
+        let pinned = PinnedCertVerifier::new([&pin]).unwrap();
+        assert!(pinned.verify(&[&CERT]));
+        // the pinned certificate, after a leaf it signed
+        assert!(pinned.verify(&[&CERT, &CERT]));
+        assert!(!pinned.verify(&[]));
+
+        let other = PinnedCertVerifier::new([spki_pin(b"")]).unwrap();
+        assert!(!other.verify(&[&CERT]));
+        assert!(!other.verify(&[&CERT, &CERT]));
+
+        // a leaf with another key, which the pinned certificate didn't sign
+        let mut other_key = CERT;
+        other_key[150] ^= 1;
+        assert!(!pinned.verify(&[&other_key]));
+        assert!(!pinned.verify(&[&other_key, &CERT]));
+        assert!(!pinned.verify(&[&CERT[..100]]));
+    }
+
+    #[test]
+    fn invalid_pins() {
+        assert_eq!(PinnedCertVerifier::new(["AAAA"]), Err(InvalidEncoding));
+        assert_eq!(
+            PinnedCertVerifier::new(["not base64 at all, but 44 characters long!!!"]),
+            Err(InvalidEncoding)
+        );
+    }
+}
@@ -0,0 +1,768 @@
+//! Readable `Debug` output for handshake messages and their extensions.
+//!
+//! Code points are printed by their names in the TLS registries, such as
+//! `TLS_AES_128_GCM_SHA256`, and opaque fields, such as randoms and keys, in hex. Anything that
+//! can't be parsed is printed in hex as well, so malformed messages can still be looked at.
+//!
+//! `{:?}` prints a message on one line, which is what `turtls-probe` puts in its report, and
+//! `{:#?}` spreads it over several, which is what the `dissect` module prints.
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::client_hello::ClientHelloRef;
+use crate::codec::{Codec, DecodeError, Reader};
+use crate::handshake::Handshake;
+use crate::psk::OfferedPsks;
+use crate::server_hello::ServerHelloRef;
+use crate::ticket::NewSessionTicket;
+
+/// Bytes that are printed in hex.
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl fmt::Debug for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("(empty)");
+        }
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+/// A code point, printed by name if it's known, and in hex otherwise.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CodePoint {
+    value: u16,
+    name: Option<&'static str>,
+}
+
+impl CodePoint {
+    pub fn cipher_suite(value: u16) -> Self {
+        let name = match value {
+            0x1301 => "TLS_AES_128_GCM_SHA256",
+            0x1302 => "TLS_AES_256_GCM_SHA384",
+            0x1303 => "TLS_CHACHA20_POLY1305_SHA256",
+            0x1304 => "TLS_AES_128_CCM_SHA256",
+            0x1305 => "TLS_AES_128_CCM_8_SHA256",
+            _ => return Self::unknown(value),
+        };
+        Self::known(value, name)
+    }
+
+    pub fn named_group(value: u16) -> Self {
+        let name = match value {
+            0x17 => "secp256r1",
+            0x18 => "secp384r1",
+            0x19 => "secp521r1",
+            0x1d => "x25519",
+            0x1e => "x448",
+            0x100 => "ffdhe2048",
+            0x101 => "ffdhe3072",
+            0x102 => "ffdhe4096",
+            0x103 => "ffdhe6144",
+            0x104 => "ffdhe8192",
+            _ => return Self::unknown(value),
+        };
+        Self::known(value, name)
+    }
+
+    pub fn signature_scheme(value: u16) -> Self {
+        let name = match value {
+            0x201 => "rsa_pkcs1_sha1",
+            0x203 => "ecdsa_sha1",
+            0x401 => "rsa_pkcs1_sha256",
+            0x501 => "rsa_pkcs1_sha384",
+            0x601 => "rsa_pkcs1_sha512",
+            0x403 => "ecdsa_secp256r1_sha256",
+            0x503 => "ecdsa_secp384r1_sha384",
+            0x603 => "ecdsa_secp521r1_sha512",
+            0x804 => "rsa_pss_rsae_sha256",
+            0x805 => "rsa_pss_rsae_sha384",
+            0x806 => "rsa_pss_rsae_sha512",
+            0x807 => "ed25519",
+            0x808 => "ed448",
+            0x809 => "rsa_pss_pss_sha256",
+            0x80a => "rsa_pss_pss_sha384",
+            0x80b => "rsa_pss_pss_sha512",
+            _ => return Self::unknown(value),
+        };
+        Self::known(value, name)
+    }
+
+    pub fn version(value: u16) -> Self {
+        let name = match value {
+            0x300 => "SSLv3",
+            0x301 => "TLSv1.0",
+            0x302 => "TLSv1.1",
+            0x303 => "TLSv1.2",
+            0x304 => "TLSv1.3",
+            _ => return Self::unknown(value),
+        };
+        Self::known(value, name)
+    }
+
+    pub fn extension(value: u16) -> Self {
+        let name = match value {
+            0 => "server_name",
+            1 => "max_fragment_length",
+            5 => "status_request",
+            10 => "supported_groups",
+            13 => "signature_algorithms",
+            14 => "use_srtp",
+            15 => "heartbeat",
+            16 => "application_layer_protocol_negotiation",
+            18 => "signed_certificate_timestamp",
+            19 => "client_certificate_type",
+            20 => "server_certificate_type",
+            21 => "padding",
+            23 => "extended_master_secret",
+            35 => "session_ticket",
+            41 => "pre_shared_key",
+            42 => "early_data",
+            43 => "supported_versions",
+            44 => "cookie",
+            45 => "psk_key_exchange_modes",
+            47 => "certificate_authorities",
+            48 => "oid_filters",
+            49 => "post_handshake_auth",
+            50 => "signature_algorithms_cert",
+            51 => "key_share",
+            0xff01 => "renegotiation_info",
+            _ => return Self::unknown(value),
+        };
+        Self::known(value, name)
+    }
+
+    pub fn psk_key_exchange_mode(value: u8) -> Self {
+        let name = match value {
+            0 => "psk_ke",
+            1 => "psk_dhe_ke",
+            _ => return Self::unknown(value.into()),
+        };
+        Self::known(value.into(), name)
+    }
+
+    pub fn content_type(value: u8) -> Self {
+        let name = match value {
+            20 => "change_cipher_spec",
+            21 => "alert",
+            22 => "handshake",
+            23 => "application_data",
+            _ => return Self::unknown(value.into()),
+        };
+        Self::known(value.into(), name)
+    }
+
+    pub fn shake_type(value: u8) -> Self {
+        let name = match value {
+            1 => "client_hello",
+            2 => "server_hello",
+            4 => "new_session_ticket",
+            5 => "end_of_early_data",
+            8 => "encrypted_extensions",
+            11 => "certificate",
+            13 => "certificate_request",
+            15 => "certificate_verify",
+            20 => "finished",
+            24 => "key_update",
+            254 => "message_hash",
+            _ => return Self::unknown(value.into()),
+        };
+        Self::known(value.into(), name)
+    }
+
+    pub fn alert_level(value: u8) -> Self {
+        let name = match value {
+            1 => "warning",
+            2 => "fatal",
+            _ => return Self::unknown(value.into()),
+        };
+        Self::known(value.into(), name)
+    }
+
+    pub fn alert_description(value: u8) -> Self {
+        let name = match value {
+            0 => "close_notify",
+            10 => "unexpected_message",
+            20 => "bad_record_mac",
+            22 => "record_overflow",
+            40 => "handshake_failure",
+            42 => "bad_certificate",
+            43 => "unsupported_certificate",
+            44 => "certificate_revoked",
+            45 => "certificate_expired",
+            46 => "certificate_unknown",
+            47 => "illegal_parameter",
+            48 => "unknown_ca",
+            49 => "access_denied",
+            50 => "decode_error",
+            51 => "decrypt_error",
+            70 => "protocol_version",
+            71 => "insufficient_security",
+            80 => "internal_error",
+            86 => "inappropriate_fallback",
+            90 => "user_canceled",
+            109 => "missing_extension",
+            110 => "unsupported_extension",
+            112 => "unrecognized_name",
+            113 => "bad_certificate_status_response",
+            115 => "unknown_psk_identity",
+            116 => "certificate_required",
+            120 => "no_application_protocol",
+            _ => return Self::unknown(value.into()),
+        };
+        Self::known(value.into(), name)
+    }
+
+    const fn known(value: u16, name: &'static str) -> Self {
+        Self {
+            value,
+            name: Some(name),
+        }
+    }
+
+    const fn unknown(value: u16) -> Self {
+        Self { value, name: None }
+    }
+
+    /// Returns the name of the code point, or its value in hex if it isn't known.
+    pub fn name(&self) -> Cow<'static, str> {
+        match self.name {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(format!("{:#x}", self.value)),
+        }
+    }
+}
+
+impl fmt::Debug for CodePoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name())
+    }
+}
+
+impl fmt::Display for CodePoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name())
+    }
+}
+
+/// A complete handshake message, printed with its fields.
+#[derive(Clone, Copy)]
+pub struct HandshakeMessage<'a> {
+    pub shake_type: u8,
+    pub body: &'a [u8],
+    /// Whether the client sent the message, which decides how some extensions are parsed.
+    pub from_client: bool,
+}
+
+impl<'a> HandshakeMessage<'a> {
+    /// Splits the first message off of `msgs`, returning it and the rest of `msgs`, or [`None`]
+    /// if it isn't complete.
+    pub fn split(msgs: &'a [u8], from_client: bool) -> Option<(Self, &'a [u8])> {
+        let [shake_type, len_1, len_2, len_3, ..] = *msgs else {
+            return None;
+        };
+        let len = u32::from_be_bytes([0, len_1, len_2, len_3]) as usize;
+        let (body, rest) = msgs[Handshake::PREFIX_SIZE..].split_at_checked(len)?;
+        let msg = Self {
+            shake_type,
+            body,
+            from_client,
+        };
+        Some((msg, rest))
+    }
+
+    fn parse(&self) -> Result<Parsed<'a>, DecodeError> {
+        let mut reader = Reader::new(self.body);
+        let extensions = |data| Extensions::new(data, self.from_client);
+        let parsed = match self.shake_type {
+            1 => return ClientHelloRef::parse(self.body).map(Parsed::ClientHello),
+            2 => {
+                return ServerHelloRef::parse_from_handshake(self.body)
+                    .map(Parsed::ServerHello)
+                    .map_err(|_| DecodeError)
+            },
+            4 => return NewSessionTicket::decode(&mut reader).map(Parsed::NewSessionTicket),
+            5 => Parsed::EndOfEarlyData,
+            8 => Parsed::EncryptedExtensions(extensions(reader.read_u16_prefixed()?.read_rest())),
+            11 => {
+                let context = reader.read_u8_prefixed()?.read_rest();
+                let mut list = reader.read_u24_prefixed()?;
+                let mut entries = Vec::new();
+                while !list.is_empty() {
+                    let cert = list.read_u24_prefixed()?.read_rest();
+                    let extensions = extensions(list.read_u16_prefixed()?.read_rest());
+                    entries.push(CertificateEntry {
+                        cert_data: Hex(cert),
+                        extensions,
+                    });
+                }
+                Parsed::Certificate { context, entries }
+            },
+            13 => Parsed::CertificateRequest {
+                context: reader.read_u8_prefixed()?.read_rest(),
+                extensions: extensions(reader.read_u16_prefixed()?.read_rest()),
+            },
+            15 => Parsed::CertificateVerify {
+                algorithm: CodePoint::signature_scheme(reader.read_u16()?),
+                signature: reader.read_u16_prefixed()?.read_rest(),
+            },
+            20 => Parsed::Finished(reader.read_slice(self.body.len())?),
+            24 => Parsed::KeyUpdate(reader.read_u8()?),
+            _ => return Err(DecodeError),
+        };
+        reader.finish()?;
+        Ok(parsed)
+    }
+}
+
+impl fmt::Debug for HandshakeMessage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.parse() {
+            Ok(parsed) => parsed.fmt(f),
+            Err(DecodeError) => f
+                .debug_tuple(&CodePoint::shake_type(self.shake_type).name())
+                .field(&Hex(self.body))
+                .finish(),
+        }
+    }
+}
+
+/// The fields of a handshake message.
+enum Parsed<'a> {
+    ClientHello(ClientHelloRef<'a>),
+    ServerHello(ServerHelloRef<'a>),
+    NewSessionTicket(NewSessionTicket),
+    EndOfEarlyData,
+    EncryptedExtensions(Extensions<'a>),
+    Certificate {
+        context: &'a [u8],
+        entries: Vec<CertificateEntry<'a>>,
+    },
+    CertificateRequest {
+        context: &'a [u8],
+        extensions: Extensions<'a>,
+    },
+    CertificateVerify {
+        algorithm: CodePoint,
+        signature: &'a [u8],
+    },
+    Finished(&'a [u8]),
+    KeyUpdate(u8),
+}
+
+impl fmt::Debug for Parsed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ClientHello(client_hello) => client_hello.fmt(f),
+            Self::ServerHello(server_hello) => server_hello.fmt(f),
+            Self::NewSessionTicket(ticket) => f
+                .debug_struct("NewSessionTicket")
+                .field("ticket_lifetime", &ticket.lifetime)
+                .field("ticket_age_add", &ticket.age_add)
+                .field("ticket_nonce", &Hex(&ticket.nonce))
+                .field("ticket", &Hex(&ticket.ticket))
+                .finish_non_exhaustive(),
+            Self::EndOfEarlyData => f.write_str("EndOfEarlyData"),
+            Self::EncryptedExtensions(extensions) => f
+                .debug_struct("EncryptedExtensions")
+                .field("extensions", extensions)
+                .finish(),
+            Self::Certificate { context, entries } => f
+                .debug_struct("Certificate")
+                .field("certificate_request_context", &Hex(context))
+                .field("certificate_list", entries)
+                .finish(),
+            Self::CertificateRequest {
+                context,
+                extensions,
+            } => f
+                .debug_struct("CertificateRequest")
+                .field("certificate_request_context", &Hex(context))
+                .field("extensions", extensions)
+                .finish(),
+            Self::CertificateVerify {
+                algorithm,
+                signature,
+            } => f
+                .debug_struct("CertificateVerify")
+                .field("algorithm", algorithm)
+                .field("signature", &Hex(signature))
+                .finish(),
+            Self::Finished(verify_data) => f
+                .debug_struct("Finished")
+                .field("verify_data", &Hex(verify_data))
+                .finish(),
+            Self::KeyUpdate(request_update) => {
+                let request_update = match request_update {
+                    0 => Cow::Borrowed("update_not_requested"),
+                    1 => Cow::Borrowed("update_requested"),
+                    other => Cow::Owned(format!("{other:#x}")),
+                };
+                f.debug_struct("KeyUpdate")
+                    .field("request_update", &format_args!("{request_update}"))
+                    .finish()
+            },
+        }
+    }
+}
+
+struct CertificateEntry<'a> {
+    cert_data: Hex<'a>,
+    extensions: Extensions<'a>,
+}
+
+impl fmt::Debug for CertificateEntry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CertificateEntry")
+            .field("cert_data", &self.cert_data)
+            .field("extensions", &self.extensions)
+            .finish()
+    }
+}
+
+/// The extensions block of a handshake message, printed as a list of extensions.
+#[derive(Clone, Copy)]
+pub struct Extensions<'a> {
+    data: &'a [u8],
+    from_client: bool,
+}
+
+impl<'a> Extensions<'a> {
+    /// `data` is the contents of the block, without its length. `from_client` is whether the
+    /// client sent it, which decides how some extensions are parsed.
+    pub fn new(data: &'a [u8], from_client: bool) -> Self {
+        Self { data, from_client }
+    }
+}
+
+impl fmt::Debug for Extensions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut list = f.debug_list();
+        let mut rest = self.data;
+        while !rest.is_empty() {
+            let mut reader = Reader::new(rest);
+            let extension = reader.read_u16().and_then(|ext_type| {
+                let data = reader.read_u16_prefixed()?.read_rest();
+                Ok((ext_type, data))
+            });
+            let Ok((ext_type, data)) = extension else {
+                // whatever is left can't be split into extensions
+                list.entry(&Hex(rest));
+                break;
+            };
+            list.entry(&Extension {
+                ext_type,
+                data,
+                from_client: self.from_client,
+            });
+            rest = reader.read_rest();
+        }
+        list.finish()
+    }
+}
+
+/// One extension, printed as its name, followed by its contents in parentheses unless it's
+/// empty.
+struct Extension<'a> {
+    ext_type: u16,
+    data: &'a [u8],
+    from_client: bool,
+}
+
+impl<'a> Extension<'a> {
+    fn parse(&self) -> Result<ExtensionData<'a>, DecodeError> {
+        let mut reader = Reader::new(self.data);
+        let data = match self.ext_type {
+            _ if self.data.is_empty() => return Ok(ExtensionData::Empty),
+            0 if self.from_client => {
+                let mut list = reader.read_u16_prefixed()?;
+                let mut names = Vec::new();
+                while !list.is_empty() {
+                    let _name_type = list.read_u8()?;
+                    let name = list.read_u16_prefixed()?.read_rest();
+                    names.push(String::from_utf8_lossy(name));
+                }
+                ExtensionData::ServerNames(names)
+            },
+            10 => ExtensionData::List(code_points(
+                reader.read_u16_prefixed()?,
+                CodePoint::named_group,
+            )?),
+            13 | 50 => ExtensionData::List(code_points(
+                reader.read_u16_prefixed()?,
+                CodePoint::signature_scheme,
+            )?),
+            43 if self.from_client => {
+                ExtensionData::List(code_points(reader.read_u8_prefixed()?, CodePoint::version)?)
+            },
+            43 => ExtensionData::One(CodePoint::version(reader.read_u16()?)),
+            45 => {
+                let modes = reader.read_u8_prefixed()?.read_rest();
+                let modes = modes.iter().copied();
+                ExtensionData::List(modes.map(CodePoint::psk_key_exchange_mode).collect())
+            },
+            51 if self.from_client => {
+                let mut list = reader.read_u16_prefixed()?;
+                let mut shares = Vec::new();
+                while !list.is_empty() {
+                    shares.push(KeyShare::decode(&mut list)?);
+                }
+                ExtensionData::KeyShares(shares)
+            },
+            // a `HelloRetryRequest` only names the group it wants a share for
+            51 if self.data.len() == 2 => {
+                ExtensionData::One(CodePoint::named_group(reader.read_u16()?))
+            },
+            51 => ExtensionData::KeyShare(KeyShare::decode(&mut reader)?),
+            41 if self.from_client => {
+                let psks = OfferedPsks::decode(&mut reader)?;
+                ExtensionData::Psks(Psks {
+                    identities: psks
+                        .identities
+                        .iter()
+                        .map(|identity| PskIdentity {
+                            identity: Hex(identity.identity),
+                            obfuscated_ticket_age: identity.obfuscated_ticket_age,
+                        })
+                        .collect(),
+                    binders: psks.binders.iter().map(|binder| Hex(binder)).collect(),
+                })
+            },
+            41 => ExtensionData::SelectedIdentity(reader.read_u16()?),
+            _ => return Ok(ExtensionData::Opaque(Hex(self.data))),
+        };
+        reader.finish()?;
+        Ok(data)
+    }
+}
+
+impl fmt::Debug for Extension<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = CodePoint::extension(self.ext_type).name();
+        match self.parse() {
+            Ok(ExtensionData::Empty) => f.write_str(&name),
+            Ok(data) => f.debug_tuple(&name).field(&data).finish(),
+            Err(DecodeError) => f.debug_tuple(&name).field(&Hex(self.data)).finish(),
+        }
+    }
+}
+
+/// Reads a list of two-byte code points.
+fn code_points(
+    mut list: Reader,
+    code_point: impl Fn(u16) -> CodePoint,
+) -> Result<Vec<CodePoint>, DecodeError> {
+    let mut code_points = Vec::new();
+    while !list.is_empty() {
+        code_points.push(code_point(list.read_u16()?));
+    }
+    Ok(code_points)
+}
+
+/// The contents of an extension.
+enum ExtensionData<'a> {
+    Empty,
+    One(CodePoint),
+    List(Vec<CodePoint>),
+    ServerNames(Vec<Cow<'a, str>>),
+    KeyShare(KeyShare<'a>),
+    KeyShares(Vec<KeyShare<'a>>),
+    Psks(Psks<'a>),
+    SelectedIdentity(u16),
+    Opaque(Hex<'a>),
+}
+
+impl fmt::Debug for ExtensionData<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => Ok(()),
+            Self::One(code_point) => code_point.fmt(f),
+            Self::List(code_points) => code_points.fmt(f),
+            Self::ServerNames(names) => names.fmt(f),
+            Self::KeyShare(share) => share.fmt(f),
+            Self::KeyShares(shares) => shares.fmt(f),
+            Self::Psks(psks) => psks.fmt(f),
+            Self::SelectedIdentity(identity) => identity.fmt(f),
+            Self::Opaque(data) => data.fmt(f),
+        }
+    }
+}
+
+struct KeyShare<'a> {
+    group: CodePoint,
+    key_exchange: Hex<'a>,
+}
+
+impl fmt::Debug for KeyShare<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyShare")
+            .field("group", &self.group)
+            .field("key_exchange", &self.key_exchange)
+            .finish()
+    }
+}
+
+impl<'a> KeyShare<'a> {
+    fn decode(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        Ok(Self {
+            group: CodePoint::named_group(reader.read_u16()?),
+            key_exchange: Hex(reader.read_u16_prefixed()?.read_rest()),
+        })
+    }
+}
+
+struct Psks<'a> {
+    identities: Vec<PskIdentity<'a>>,
+    binders: Vec<Hex<'a>>,
+}
+
+impl fmt::Debug for Psks<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Psks")
+            .field("identities", &self.identities)
+            .field("binders", &self.binders)
+            .finish()
+    }
+}
+
+struct PskIdentity<'a> {
+    identity: Hex<'a>,
+    obfuscated_ticket_age: u32,
+}
+
+impl fmt::Debug for PskIdentity<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PskIdentity")
+            .field("identity", &self.identity)
+            .field("obfuscated_ticket_age", &self.obfuscated_ticket_age)
+            .finish()
+    }
+}
+
+impl fmt::Debug for ClientHelloRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cipher_suites: Vec<_> = self
+            .cipher_suites
+            .iter()
+            .map(|&suite| CodePoint::cipher_suite(suite))
+            .collect();
+        f.debug_struct("ClientHello")
+            .field("legacy_version", &CodePoint::version(self.legacy_version))
+            .field("random", &Hex(self.random_bytes))
+            .field("legacy_session_id", &Hex(self.session_id))
+            .field("cipher_suites", &cipher_suites)
+            .field("legacy_compression_methods", &Hex(self.compression_methods))
+            .field("extensions", &Extensions::new(self.extensions, true))
+            .finish()
+    }
+}
+
+impl fmt::Debug for ServerHelloRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = if *self.random_bytes == ServerHelloRef::HELLO_RETRY_RANDOM {
+            "HelloRetryRequest"
+        } else {
+            "ServerHello"
+        };
+        f.debug_struct(name)
+            .field("random", &Hex(self.random_bytes))
+            .field("legacy_session_id_echo", &Hex(self.session_id))
+            .field(
+                "cipher_suite",
+                &CodePoint::cipher_suite(self.cipher_suite as u16),
+            )
+            .field("extensions", &Extensions::new(self.extensions, false))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_points() {
+        assert_eq!(
+            format!("{:?}", CodePoint::cipher_suite(0x1301)),
+            "TLS_AES_128_GCM_SHA256"
+        );
+        assert_eq!(format!("{}", CodePoint::named_group(0x1d)), "x25519");
+        assert_eq!(
+            format!("{:?}", CodePoint::signature_scheme(0x1234)),
+            "0x1234"
+        );
+        assert_eq!(
+            format!("{:?}", CodePoint::alert_description(50)),
+            "decode_error"
+        );
+        assert_eq!(format!("{:?}", Hex(&[0x0a, 0xff])), "0aff");
+        assert_eq!(format!("{:?}", Hex(&[])), "(empty)");
+    }
+
+    #[test]
+    fn extensions() {
+        let server = [
+            0x00, 0x2b, 0x00, 0x02, 0x03, 0x04, // supported_versions
+            0x00, 0x33, 0x00, 0x02, 0x00, 0x17, // key_share, as in a `HelloRetryRequest`
+            0xaa, 0xaa, 0x00, 0x01, 0xff, // unknown
+            0x00, 0x2a, 0x00, 0x00, // early_data
+        ];
+        assert_eq!(
+            format!("{:?}", Extensions::new(&server, false)),
+            "[supported_versions(TLSv1.3), key_share(secp256r1), 0xaaaa(ff), early_data]"
+        );
+
+        let client = [
+            0x00, 0x2b, 0x00, 0x03, 0x02, 0x03, 0x04, // supported_versions
+            0x00, 0x2d, 0x00, 0x02, 0x01, 0x01, // psk_key_exchange_modes
+            0x00, 0x0a, 0x00, 0x03, 0x00, 0x02, 0x00, // supported_groups, cut off
+            0x00, 0x0d, 0x00, // truncated
+        ];
+        assert_eq!(
+            format!("{:?}", Extensions::new(&client, true)),
+            "[supported_versions([TLSv1.3]), psk_key_exchange_modes([psk_dhe_ke]), \
+             supported_groups(000200), 000d00]"
+        );
+    }
+
+    #[test]
+    fn messages() {
+        let mut hello_retry_request = vec![0x02, 0x00, 0x00, 0x00, 0x03, 0x03];
+        hello_retry_request.extend_from_slice(&ServerHelloRef::HELLO_RETRY_RANDOM);
+        hello_retry_request.extend_from_slice(&[0x00, 0x13, 0x01, 0x00, 0x00, 0x06]);
+        hello_retry_request.extend_from_slice(&[0x00, 0x33, 0x00, 0x02, 0x00, 0x17]);
+        hello_retry_request[3] = (hello_retry_request.len() - Handshake::PREFIX_SIZE) as u8;
+        let finished = [0x14, 0x00, 0x00, 0x02, 0x01, 0x02];
+
+        let mut msgs = [&hello_retry_request[..], &finished].concat();
+        let (msg, rest) = HandshakeMessage::split(&msgs, false).unwrap();
+        assert_eq!(
+            format!("{msg:?}"),
+            "HelloRetryRequest { random: cf21ad74e59a6111be1d8c021e65b891c2a211167abb8c5e079e09e2c8a8339c, \
+             legacy_session_id_echo: (empty), cipher_suite: TLS_AES_128_GCM_SHA256, \
+             extensions: [key_share(secp256r1)] }"
+        );
+        let (msg, rest) = HandshakeMessage::split(rest, false).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(format!("{msg:?}"), "Finished { verify_data: 0102 }");
+        assert_eq!(format!("{msg:#?}"), "Finished {\n    verify_data: 0102,\n}");
+
+        // incomplete
+        msgs.pop();
+        assert!(HandshakeMessage::split(&msgs[hello_retry_request.len()..], false).is_none());
+
+        // malformed and unknown messages are printed in hex
+        let certificate_verify = HandshakeMessage {
+            shake_type: 15,
+            body: &[0x04],
+            from_client: false,
+        };
+        assert_eq!(format!("{certificate_verify:?}"), "certificate_verify(04)");
+        let unknown = HandshakeMessage {
+            shake_type: 99,
+            body: &[0x01, 0x02],
+            from_client: true,
+        };
+        assert_eq!(format!("{unknown:?}"), "0x63(0102)");
+    }
+}
@@ -0,0 +1,707 @@
+//! What `turtls-probe` does: connect to a server, and report what was negotiated and which
+//! certificates were sent.
+//!
+//! This lives in the library, behind the `probe` feature, because it prints the handshake
+//! messages with the private `pretty` module. The binary only parses its arguments and prints the
+//! [`Report`].
+//!
+//! The negotiated parameters are the ones the [`ClientConnection`] read from the `ServerHello`.
+//! The client only offers TLS 1.3 with `TLS_AES_128_GCM_SHA256` and secp256r1 for now, so those
+//! are all a server can choose. Whether the server answers that offer with a
+//! `HelloRetryRequest` is reported, but the client can't answer one, so the handshake then fails.
+//!
+//! If the handshake succeeds, the probe waits briefly for a session ticket, then connects again
+//! and offers it. The report says whether the server accepted it.
+//!
+//! Unless the host is an IP address, it is sent as the server name, so servers that host several
+//! names send the certificate for that one.
+//!
+//! Certificates are accepted whatever they are, since the point is to see them. Each one is
+//! checked against the key of the next one in the chain. If trust anchors are given, the chain is
+//! also reported as trusted if it is signed, link by link, up to one of them, and every
+//! certificate up to there is within its validity period. Names and extensions aren't looked at.
+//!
+//! The handshake messages that were sent in plaintext, which are the `ClientHello` and the
+//! `ServerHello` or `HelloRetryRequest`, are included in the report as printed by the `pretty`
+//! module.
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crylib::encoding::{base64, pem, InvalidEncoding};
+
+use crate::blocking::{complete_handshake, HandshakeError};
+use crate::certificate::{Certificate, CertificateError, ValidityChecker};
+use crate::client::ClientConnection;
+use crate::connection::ConnectionError;
+use crate::pretty::{CodePoint, HandshakeMessage};
+use crate::public_key::{EcPublicKey, PublicKey};
+use crate::record::{self, ContentType, Message};
+use crate::ticket::SessionTicket;
+
+/// How long to wait for the server before giving up.
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait for a session ticket once the handshake is done. Servers that send tickets
+/// send them right away, so this only matters for those that don't.
+const TICKET_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What was learned about a server.
+#[derive(Debug)]
+pub struct Report {
+    /// The host that was connected to.
+    pub host: String,
+    /// The port that was connected to.
+    pub port: u16,
+    /// The name that was sent in the `server_name` extension, if any.
+    pub server_name: Option<String>,
+    /// Why the handshake failed, or `None` if it succeeded.
+    pub error: Option<String>,
+    /// The protocol version the server chose, or `None` if it didn't get that far.
+    pub version: Option<String>,
+    /// The cipher suite the server chose, or `None` if it didn't get that far.
+    pub cipher_suite: Option<String>,
+    /// The group of the key exchange, or `None` if the server didn't get that far.
+    pub group: Option<String>,
+    /// Whether the server answered with a `HelloRetryRequest`.
+    pub hello_retry_request: bool,
+    /// The certificates the server sent, leaf first.
+    pub certificates: Vec<CertificateReport>,
+    /// Whether the certificates are signed up to one of the trust anchors and are valid now, or
+    /// `None` if there weren't any anchors.
+    pub trusted: Option<bool>,
+    /// Whether the server accepted the session ticket it sent when the probe connected again, or
+    /// `None` if the handshake failed or it didn't send one.
+    pub resumed: Option<bool>,
+    /// The plaintext handshake messages, in the order they were sent, each prefixed with
+    /// `"client: "` or `"server: "`.
+    pub handshake: Vec<String>,
+}
+
+/// One certificate of the chain a server sent.
+#[derive(Debug)]
+pub struct CertificateReport {
+    /// The DER certificate.
+    pub der: Vec<u8>,
+    /// The type of the certificate's key, such as `"secp256r1"`, or `None` if it couldn't be
+    /// parsed.
+    pub key: Option<String>,
+    /// Whether the certificate is signed by the next one.
+    pub issuer: IssuerCheck,
+}
+
+/// The result of checking a certificate's signature against the key of its issuer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssuerCheck {
+    /// The certificate is signed by the next one in the chain.
+    Verified,
+    /// The certificate is the last one, and is signed by its own key.
+    SelfSigned,
+    /// The certificate is the last one, so its issuer wasn't sent.
+    NoIssuer,
+    /// The signature doesn't match the issuer's key.
+    Invalid,
+    /// The signature algorithm or the issuer's key can't be verified with yet.
+    Unsupported,
+}
+
+impl IssuerCheck {
+    /// The name of the result in the JSON report.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Verified => "verified",
+            Self::SelfSigned => "self_signed",
+            Self::NoIssuer => "no_issuer",
+            Self::Invalid => "invalid",
+            Self::Unsupported => "unsupported",
+        }
+    }
+}
+
+impl Report {
+    /// Formats the report as a single JSON object.
+    ///
+    /// Values that aren't known are `null`.
+    pub fn to_json(&self) -> String {
+        let optional =
+            |value: &Option<String>| value.as_deref().map_or("null".to_string(), json_string);
+        let certificates: Vec<String> = self
+            .certificates
+            .iter()
+            .map(|cert| {
+                let mut der = vec![0; base64::encoded_len(cert.der.len())];
+                format!(
+                    r#"{{"der":{},"key":{},"issuer":{}}}"#,
+                    json_string(base64::encode(&cert.der, &mut der)),
+                    cert.key.as_deref().map_or("null".to_string(), json_string),
+                    json_string(cert.issuer.as_str()),
+                )
+            })
+            .collect();
+        let handshake: Vec<String> = self.handshake.iter().map(|msg| json_string(msg)).collect();
+        format!(
+            concat!(
+                r#"{{"host":{},"port":{},"server_name":{},"connected":{},"error":{},"#,
+                r#""version":{},"cipher_suite":{},"group":{},"hello_retry_request":{},"#,
+                r#""certificates":[{}],"trusted":{},"resumed":{},"handshake":[{}]}}"#,
+            ),
+            json_string(&self.host),
+            self.port,
+            optional(&self.server_name),
+            self.error.is_none(),
+            optional(&self.error),
+            optional(&self.version),
+            optional(&self.cipher_suite),
+            optional(&self.group),
+            self.hello_retry_request,
+            certificates.join(","),
+            self.trusted
+                .map_or("null".to_string(), |trusted| trusted.to_string()),
+            self.resumed
+                .map_or("null".to_string(), |resumed| resumed.to_string()),
+            handshake.join(","),
+        )
+    }
+}
+
+/// Connects to `host` on `port` and does a handshake, reporting how it went.
+///
+/// `trust_anchors` are the DER certificates the chain is checked against. If there are none, the
+/// chain isn't reported as either trusted or untrusted.
+pub fn probe(host: &str, port: u16, trust_anchors: &[Vec<u8>]) -> Report {
+    let mut report = Report {
+        host: host.to_string(),
+        port,
+        // IP addresses can't be sent as server names (RFC 6066 section 3)
+        server_name: match host.parse::<IpAddr>() {
+            Ok(_) => None,
+            Err(_) => Some(host.to_string()),
+        },
+        error: None,
+        version: None,
+        cipher_suite: None,
+        group: None,
+        hello_retry_request: false,
+        certificates: Vec::new(),
+        trusted: None,
+        resumed: None,
+        handshake: Vec::new(),
+    };
+    let ticket = match connect(host, port) {
+        Ok(mut stream) => probe_stream(&mut report, &mut stream),
+        Err(err) => {
+            report.error = Some(err.to_string());
+            None
+        },
+    };
+    if let Some(ticket) = ticket {
+        report.resumed = Some(resume(host, port, ticket));
+    }
+    if !trust_anchors.is_empty() {
+        let validity = ValidityChecker::new();
+        report.trusted = Some(is_trusted(&report.certificates, trust_anchors, &validity));
+    }
+    report
+}
+
+/// Opens a TCP connection to `host` on `port`.
+fn connect(host: &str, port: u16) -> io::Result<TcpStream> {
+    let stream = TcpStream::connect((host, port))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    Ok(stream)
+}
+
+/// Reads the trust anchors from the `CERTIFICATE` blocks of a PEM file.
+///
+/// Returns [`InvalidEncoding`] if there are no such blocks, or one of them isn't valid base64.
+pub fn parse_trust_anchors(mut file: &[u8]) -> Result<Vec<Vec<u8>>, InvalidEncoding> {
+    const LABEL: &str = "CERTIFICATE";
+    const END: &[u8] = b"-----END CERTIFICATE-----";
+
+    let mut anchors = Vec::new();
+    while let Some(end) = file.windows(END.len()).position(|window| window == END) {
+        let block = &file[..end + END.len()];
+        let mut der = vec![0; pem::decoded_len(block, LABEL)?];
+        let len = pem::decode(block, LABEL, &mut der)?.len();
+        der.truncate(len);
+        anchors.push(der);
+        file = &file[end + END.len()..];
+    }
+    match anchors.is_empty() {
+        true => Err(InvalidEncoding),
+        false => Ok(anchors),
+    }
+}
+
+/// Does a handshake over `stream`, filling in `report`.
+///
+/// Returns the first session ticket the server sent, if the handshake succeeded and it sent one.
+fn probe_stream(report: &mut Report, stream: &mut TcpStream) -> Option<SessionTicket> {
+    let chain = Arc::new(Mutex::new(Vec::new()));
+    let received = Arc::clone(&chain);
+    let verify_certs = move |certs: &[&[u8]]| {
+        *received.lock().unwrap() = certs.iter().map(|cert| cert.to_vec()).collect();
+        true
+    };
+    let conn = match &report.server_name {
+        Some(server_name) => ClientConnection::with_server_name(server_name, verify_certs),
+        None => ClientConnection::new(verify_certs),
+    };
+    let mut ticket = None;
+    let result = match conn {
+        Ok(mut conn) => {
+            let result = handshake(&mut conn, stream, &mut report.handshake);
+            if result.is_ok() && stream.set_read_timeout(Some(TICKET_TIMEOUT)).is_ok() {
+                ticket = read_ticket(&mut conn, stream);
+            }
+            if let Some(negotiated) = conn.negotiated() {
+                let version = CodePoint::version(negotiated.version as u16);
+                let cipher_suite = CodePoint::cipher_suite(negotiated.cipher_suite as u16);
+                report.version = Some(version.name().into_owned());
+                report.cipher_suite = Some(cipher_suite.name().into_owned());
+                report.group = negotiated
+                    .group
+                    .map(|group| CodePoint::named_group(group as u16).name().into_owned());
+            }
+            result
+        },
+        Err(err) => Err(HandshakeError::Tls(err)),
+    };
+
+    if let Err(err) = result {
+        report.hello_retry_request =
+            matches!(err, HandshakeError::Tls(ConnectionError::HelloRetryRequest));
+        report.error = Some(err.to_string());
+    }
+    let chain = chain.lock().unwrap();
+    report.certificates = check_chain(&chain);
+    ticket
+}
+
+/// Reads records from `stream` until the server sends a session ticket, returning the first one.
+///
+/// Returns `None` if the stream ends, times out, or breaks the connection first.
+fn read_ticket(conn: &mut ClientConnection, stream: &mut impl Read) -> Option<SessionTicket> {
+    let mut buf = vec![0; Message::MAX_SIZE];
+    let mut len = 0;
+    loop {
+        if let Some(ticket) = conn.take_tickets().into_iter().next() {
+            return Some(ticket);
+        }
+        let read = stream.read(&mut buf[len..]).ok()?;
+        if read == 0 {
+            return None;
+        }
+        len += read;
+        let used = conn.read_tls(&mut buf[..len]).ok()?;
+        buf.copy_within(used..len, 0);
+        len -= used;
+    }
+}
+
+/// Connects to `host` on `port` again, offering `ticket`, and returns whether the server accepted
+/// it.
+///
+/// A second handshake that fails counts as the ticket not being accepted.
+fn resume(host: &str, port: u16, ticket: SessionTicket) -> bool {
+    let (Ok(mut stream), Ok(mut conn)) = (
+        connect(host, port),
+        ClientConnection::resume(ticket, |_| true),
+    ) else {
+        return false;
+    };
+    handshake(&mut conn, &mut stream, &mut Vec::new()).is_ok()
+        && conn
+            .negotiated()
+            .is_some_and(|negotiated| negotiated.psk_accepted)
+}
+
+/// Moves records between `conn` and `stream` until the handshake is over, printing the plaintext
+/// handshake messages into `messages`.
+fn handshake(
+    conn: &mut ClientConnection,
+    stream: &mut (impl Read + Write),
+    messages: &mut Vec<String>,
+) -> Result<(), HandshakeError> {
+    let mut recorded = Recorded {
+        stream,
+        sent: Transcript::new(true),
+        received: Transcript::new(false),
+        messages,
+    };
+    complete_handshake(conn, &mut recorded)
+}
+
+/// A stream that prints the plaintext handshake messages in the bytes that pass through it.
+///
+/// Bytes are printed as they are read, before the connection decrypts anything in place.
+struct Recorded<'a, S> {
+    stream: &'a mut S,
+    sent: Transcript,
+    received: Transcript,
+    messages: &'a mut Vec<String>,
+}
+
+impl<S: Read> Read for Recorded<'_, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.stream.read(buf)?;
+        self.received.push(&buf[..read], self.messages);
+        Ok(read)
+    }
+}
+
+impl<S: Write> Write for Recorded<'_, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.stream.write(buf)?;
+        self.sent.push(&buf[..written], self.messages);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Prints the plaintext handshake messages in the records one side sent.
+struct Transcript {
+    from_client: bool,
+    /// The start of a record that hasn't been received whole yet.
+    records: Vec<u8>,
+    /// The start of a handshake message that hasn't been received whole yet.
+    handshake: Vec<u8>,
+    /// Whether the records are encrypted now, so there is nothing left to print.
+    encrypted: bool,
+}
+
+impl Transcript {
+    fn new(from_client: bool) -> Self {
+        Self {
+            from_client,
+            records: Vec::new(),
+            handshake: Vec::new(),
+            encrypted: false,
+        }
+    }
+
+    /// Adds the next `bytes` that were sent, printing any messages they complete into `messages`.
+    fn push(&mut self, bytes: &[u8], messages: &mut Vec<String>) {
+        if self.encrypted {
+            return;
+        }
+        self.records.extend_from_slice(bytes);
+        let sender = match self.from_client {
+            true => "client",
+            false => "server",
+        };
+        loop {
+            // encrypted records are rejected as unexpected, which is where this stops
+            let len = match record::record_len(&self.records, false) {
+                Ok(Some(len)) => len,
+                Ok(None) => return,
+                Err(_) => break,
+            };
+            if self.records[0] == ContentType::Handshake as u8 {
+                let fragment = &self.records[record::RecordHeader::SIZE..len];
+                self.handshake.extend_from_slice(fragment);
+            }
+            self.records.drain(..len);
+
+            let mut rest = &self.handshake[..];
+            while let Some((msg, next)) = HandshakeMessage::split(rest, self.from_client) {
+                messages.push(format!("{sender}: {msg:?}"));
+                rest = next;
+            }
+            let used = self.handshake.len() - rest.len();
+            self.handshake.drain(..used);
+        }
+        self.encrypted = true;
+        self.records = Vec::new();
+        self.handshake = Vec::new();
+    }
+}
+
+/// Describes each certificate of `chain`, checking its signature against the key of the next one.
+fn check_chain(chain: &[Vec<u8>]) -> Vec<CertificateReport> {
+    chain
+        .iter()
+        .enumerate()
+        .map(|(i, cert)| {
+            let issuer = match chain.get(i + 1) {
+                Some(issuer) => check_signature(cert, issuer),
+                None => match check_signature(cert, cert) {
+                    IssuerCheck::Verified => IssuerCheck::SelfSigned,
+                    _ => IssuerCheck::NoIssuer,
+                },
+            };
+            CertificateReport {
+                der: cert.clone(),
+                key: PublicKey::from_certificate(cert)
+                    .ok()
+                    .map(|key| key_type(&key)),
+                issuer,
+            }
+        })
+        .collect()
+}
+
+/// Returns whether the chain described by `certificates` is signed, link by link, up to one of
+/// `anchors`, and whether `validity` accepts every certificate up to there.
+///
+/// The chain can end with an anchor, or with a certificate an anchor signed. Anchors that weren't
+/// sent aren't checked for validity, since they are trusted as they are.
+fn is_trusted(
+    certificates: &[CertificateReport],
+    anchors: &[Vec<u8>],
+    validity: &ValidityChecker,
+) -> bool {
+    for cert in certificates {
+        if validity.check(&cert.der).is_err() {
+            return false;
+        }
+        let anchored = anchors.iter().any(|anchor| {
+            *anchor == cert.der || check_signature(&cert.der, anchor) == IssuerCheck::Verified
+        });
+        if anchored {
+            return true;
+        }
+        if cert.issuer != IssuerCheck::Verified {
+            return false;
+        }
+    }
+    false
+}
+
+/// Checks whether `cert` is signed by the key of `issuer`.
+fn check_signature(cert: &[u8], issuer: &[u8]) -> IssuerCheck {
+    let (Ok(cert), Ok(issuer)) = (Certificate::parse(cert), Certificate::parse(issuer)) else {
+        return IssuerCheck::Invalid;
+    };
+    match cert.verify_signed_by(issuer.spki()) {
+        Ok(()) => IssuerCheck::Verified,
+        Err(CertificateError::UnsupportedAlgorithm | CertificateError::InvalidIssuerKey) => {
+            IssuerCheck::Unsupported
+        },
+        Err(_) => IssuerCheck::Invalid,
+    }
+}
+
+/// Names the type of `key`.
+fn key_type(key: &PublicKey) -> String {
+    match key {
+        PublicKey::Ec(EcPublicKey::Secp256r1(_)) => "secp256r1".to_string(),
+        PublicKey::Ec(EcPublicKey::Secp384r1(_)) => "secp384r1".to_string(),
+        PublicKey::Ec(EcPublicKey::Secp521r1(_)) => "secp521r1".to_string(),
+        PublicKey::Ed25519(_) => "ed25519".to_string(),
+        PublicKey::Rsa(key) => format!("rsa{}", key.bits()),
+    }
+}
+
+/// Quotes `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for char in s.chars() {
+        match char {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            char if char.is_control() => quoted.push_str(&format!("\\u{:04x}", char as u32)),
+            char => quoted.push(char),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificate::TimeProvider;
+    use crate::connection::tests::{cert_key, CERT};
+    use crate::server::{ServerConfig, ServerConnection};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn json_escaping() {
+        assert_eq!(json_string("a\"b\\c\nd\u{1}"), r#""a\"b\\c\nd\u0001""#);
+    }
+
+    #[test]
+    fn chain() {
+        let report = check_chain(&[CERT.to_vec()]);
+        assert_eq!(report[0].issuer, IssuerCheck::SelfSigned);
+        assert_eq!(report[0].key.as_deref(), Some("secp256r1"));
+
+        // the same certificate, with one bit of its serial number flipped
+        let mut tampered = CERT.to_vec();
+        tampered[14] ^= 1;
+        assert_eq!(check_signature(&tampered, &CERT), IssuerCheck::Invalid);
+        assert_eq!(check_chain(&[tampered])[0].issuer, IssuerCheck::NoIssuer);
+        assert_eq!(
+            check_chain(&[CERT.to_vec(), CERT.to_vec()])[0].issuer,
+            IssuerCheck::Verified
+        );
+    }
+
+    struct FixedTime(i64);
+
+    impl TimeProvider for FixedTime {
+        fn now(&self) -> i64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn trust() {
+        let validity = ValidityChecker::new();
+        let chain = check_chain(&[CERT.to_vec()]);
+        assert!(is_trusted(&chain, &[CERT.to_vec()], &validity));
+        assert!(!is_trusted(&chain, &[], &validity));
+        assert!(!is_trusted(&[], &[CERT.to_vec()], &validity));
+
+        let mut tampered = CERT.to_vec();
+        tampered[14] ^= 1;
+        assert!(!is_trusted(
+            &check_chain(&[tampered.clone()]),
+            &[CERT.to_vec()],
+            &validity
+        ));
+        // the leaf isn't signed by the certificate after it
+        let chain = check_chain(&[tampered, CERT.to_vec()]);
+        assert!(!is_trusted(&chain, &[CERT.to_vec()], &validity));
+
+        // `CERT` expires at the start of 2034
+        let chain = check_chain(&[CERT.to_vec()]);
+        let expired = ValidityChecker::with_time_provider(FixedTime(2019686401));
+        assert!(!is_trusted(&chain, &[CERT.to_vec()], &expired));
+        let chain = check_chain(&[CERT.to_vec(), CERT.to_vec()]);
+        assert!(!is_trusted(&chain, &[CERT.to_vec()], &expired));
+    }
+
+    #[test]
+    fn trust_anchors() {
+        let mut encoded = vec![0; base64::encoded_len(CERT.len())];
+        let encoded = base64::encode(&CERT, &mut encoded);
+        let block = format!("-----BEGIN CERTIFICATE-----\n{encoded}\n-----END CERTIFICATE-----\n");
+        let file = format!("first\n{block}second\n{block}");
+        assert_eq!(
+            parse_trust_anchors(file.as_bytes()),
+            Ok(vec![CERT.to_vec(), CERT.to_vec()])
+        );
+        assert_eq!(
+            parse_trust_anchors(b"no certificates"),
+            Err(InvalidEncoding)
+        );
+        let invalid = block.replace('M', "!");
+        assert_eq!(
+            parse_trust_anchors(invalid.as_bytes()),
+            Err(InvalidEncoding)
+        );
+    }
+
+    fn config() -> ServerConfig {
+        ServerConfig::new(vec![CERT.to_vec()], cert_key())
+    }
+
+    /// Accepts a connection on a new local port for each of `configs`, and does a handshake on
+    /// it with that config.
+    fn serve(configs: Vec<Arc<ServerConfig>>) -> (u16, thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            for config in configs {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut conn = ServerConnection::new(config);
+                let mut buf = vec![0; Message::MAX_SIZE];
+                let mut len = 0;
+                while conn.is_handshaking() {
+                    let read = stream.read(&mut buf[len..]).unwrap();
+                    len += read;
+                    let used = conn.read_tls(&mut buf[..len]).unwrap();
+                    buf.copy_within(used..len, 0);
+                    len -= used;
+                    // this includes the session ticket, once the handshake is done
+                    stream.write_all(&conn.take_outgoing()).unwrap();
+                }
+            }
+        });
+        (port, server)
+    }
+
+    fn serve_once() -> (u16, thread::JoinHandle<()>) {
+        serve(vec![Arc::new(config())])
+    }
+
+    #[test]
+    fn probe_local_server() {
+        let (port, server) = serve_once();
+        let report = probe("127.0.0.1", port, &[]);
+        server.join().unwrap();
+        assert_eq!(report.error, None);
+        assert_eq!(report.server_name, None);
+        assert_eq!(report.version.as_deref(), Some("TLSv1.3"));
+        assert_eq!(
+            report.cipher_suite.as_deref(),
+            Some("TLS_AES_128_GCM_SHA256")
+        );
+        assert_eq!(report.group.as_deref(), Some("secp256r1"));
+        assert!(!report.hello_retry_request);
+        assert_eq!(report.certificates.len(), 1);
+        assert_eq!(report.certificates[0].der, CERT);
+        assert_eq!(report.trusted, None);
+        assert_eq!(report.resumed, None);
+
+        let json = report.to_json();
+        assert!(json.starts_with(r#"{"host":"127.0.0.1","#));
+        assert!(json.contains(r#""server_name":null,"connected":true,"error":null,"#));
+        assert!(json.contains(r#""version":"TLSv1.3","cipher_suite":"TLS_AES_128_GCM_SHA256","#));
+        assert!(json.contains(r#""issuer":"self_signed""#));
+        assert!(json.contains(r#""trusted":null,"resumed":null,"#));
+
+        assert_eq!(report.handshake.len(), 2);
+        assert!(report.handshake[0].starts_with("client: ClientHello { legacy_version: TLSv1.2,"));
+        assert!(report.handshake[0].contains("supported_versions([TLSv1.3])"));
+        assert!(!report.handshake[0].contains("server_name"));
+        assert!(report.handshake[1].starts_with("server: ServerHello { random: "));
+        assert!(report.handshake[1].contains("cipher_suite: TLS_AES_128_GCM_SHA256"));
+        assert!(json.contains(r#""handshake":["client: ClientHello {"#));
+    }
+
+    #[test]
+    fn probe_server_name() {
+        let (port, server) = serve_once();
+        let report = probe("localhost", port, &[CERT.to_vec()]);
+        server.join().unwrap();
+        assert_eq!(report.error, None);
+        assert_eq!(report.server_name.as_deref(), Some("localhost"));
+        assert!(report.handshake[0].contains(r#"server_name(["localhost"])"#));
+        assert_eq!(report.trusted, Some(true));
+        assert!(report.to_json().contains(r#""trusted":true"#));
+    }
+
+    fn resuming_config() -> Arc<ServerConfig> {
+        let mut config = config();
+        config.enable_resumption(3600);
+        Arc::new(config)
+    }
+
+    #[test]
+    fn probe_resumption() {
+        let config = resuming_config();
+        let (port, server) = serve(vec![config.clone(), config]);
+        let report = probe("localhost", port, &[]);
+        server.join().unwrap();
+        assert_eq!(report.error, None);
+        assert_eq!(report.resumed, Some(true));
+        assert!(report.to_json().contains(r#""resumed":true,"#));
+
+        // a server that doesn't know the ticket does a full handshake instead
+        let (port, server) = serve(vec![resuming_config(), resuming_config()]);
+        let report = probe("localhost", port, &[]);
+        server.join().unwrap();
+        assert_eq!(report.error, None);
+        assert_eq!(report.resumed, Some(false));
+    }
+}
@@ -0,0 +1,303 @@
+//! Externally provisioned pre-shared keys (RFC 8446 section 2.2, RFC 9257).
+//!
+//! An [`ExternalPsk`] is agreed on out of band, by devices flashed with the same key or services
+//! given it by their deployment, and can authenticate a handshake on its own, without any
+//! certificates. The client offers the identity of its PSK in the `pre_shared_key` extension,
+//! along with a binder that proves it knows the key, and the server accepts it by echoing its
+//! index.
+//!
+//! Which key exchanges a PSK can be used with is offered in the `psk_key_exchange_modes`
+//! extension. [`PskKeyExchangeMode::PskDheKe`] still does an (EC)DHE key exchange, so a leaked
+//! PSK doesn't expose past connections. [`PskKeyExchangeMode::PskKe`] skips it, which saves
+//! constrained devices the scalar multiplications, but gives up forward secrecy.
+use crylib::hash::{Hasher, Sha256};
+use crylib::hmac::InvalidMac;
+
+use crate::codec::{Codec, DecodeError, EncodeError, Reader, Writer};
+use crate::key_schedule::{self, EarlySecret, HASH_LEN};
+use crate::zeroizing;
+
+/// How the key exchange uses a PSK, as offered in the `psk_key_exchange_modes` extension.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PskKeyExchangeMode {
+    /// The PSK is the only input to the key schedule, without forward secrecy.
+    PskKe = 0,
+    /// The PSK is combined with an (EC)DHE shared secret.
+    PskDheKe = 1,
+}
+
+/// The hash function a PSK is used with.
+///
+/// A PSK can only be used with the cipher suites that share its hash function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PskHash {
+    /// SHA-256, which `TLS_AES_128_GCM_SHA256` uses.
+    Sha256,
+}
+
+/// A PSK that was provisioned out of band.
+///
+/// The key is zeroed when the PSK is dropped.
+pub struct ExternalPsk {
+    identity: Vec<u8>,
+    key: Vec<u8>,
+    hash: PskHash,
+}
+
+impl ExternalPsk {
+    /// The shortest key that is accepted, so that it has at least 128 bits of entropy
+    /// (RFC 9257 section 6).
+    pub const MIN_KEY_LEN: usize = 16;
+
+    /// Creates a PSK that the peer knows as `identity`.
+    ///
+    /// `identity` can't be empty, and has to fit in the `pre_shared_key` extension.
+    pub fn new(identity: Vec<u8>, key: Vec<u8>, hash: PskHash) -> Result<Self, PskError> {
+        // the identity shares the extension with its length prefixes, age, and binder
+        const MAX_IDENTITY_LEN: usize = u16::MAX as usize - 2 - 2 - 4 - 2 - 1 - HASH_LEN;
+
+        if identity.is_empty() || identity.len() > MAX_IDENTITY_LEN {
+            return Err(PskError::InvalidIdentity);
+        }
+        if key.len() < Self::MIN_KEY_LEN {
+            return Err(PskError::KeyTooShort);
+        }
+        Ok(Self {
+            identity,
+            key,
+            hash,
+        })
+    }
+
+    /// Returns the identity the peer knows the PSK as.
+    pub fn identity(&self) -> &[u8] {
+        &self.identity
+    }
+
+    /// Returns the hash function the PSK is used with.
+    pub const fn hash(&self) -> PskHash {
+        self.hash
+    }
+
+    /// Extracts the early secret from the key.
+    pub fn early_secret(&self) -> EarlySecret {
+        EarlySecret::new(Some(&self.key))
+    }
+
+    /// Returns what a client offers the PSK with.
+    pub fn offer(&self) -> PskOffer<'_> {
+        PskOffer {
+            identity: PskIdentity {
+                identity: &self.identity,
+                obfuscated_ticket_age: 0,
+            },
+            binder_key: self.early_secret().binder_key(true),
+        }
+    }
+}
+
+/// A PSK as a client offers it: an external PSK, or a session ticket.
+pub struct PskOffer<'a> {
+    /// The identity that is sent in the `pre_shared_key` extension.
+    pub identity: PskIdentity<'a>,
+    /// The key the binder is computed with.
+    pub binder_key: [u8; HASH_LEN],
+}
+
+/// Returns the binder of a `ClientHello` for the PSK whose binder key is `binder_key`.
+///
+/// `partial_client_hello` is the `ClientHello`, including its header, up to but not including
+/// the list of binders (RFC 8446 section 4.2.11.2).
+pub fn binder(binder_key: &[u8; HASH_LEN], partial_client_hello: &[u8]) -> [u8; HASH_LEN] {
+    key_schedule::finished_verify_data(binder_key, &Sha256::hash(partial_client_hello))
+}
+
+/// Checks that `binder` is the binder of a `ClientHello` for the PSK whose binder key is
+/// `binder_key`.
+pub fn verify_binder(
+    binder_key: &[u8; HASH_LEN],
+    partial_client_hello: &[u8],
+    binder: &[u8],
+) -> Result<(), InvalidMac> {
+    key_schedule::verify_finished(binder_key, &Sha256::hash(partial_client_hello), binder)
+}
+
+impl Drop for ExternalPsk {
+    fn drop(&mut self) {
+        zeroizing::zeroize(&mut self.key);
+    }
+}
+
+/// An identity offered in the `pre_shared_key` extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PskIdentity<'a> {
+    pub identity: &'a [u8],
+    /// The age of a session ticket, obfuscated by the server. External PSKs always use 0.
+    pub obfuscated_ticket_age: u32,
+}
+
+impl<'a> Codec<'a> for PskIdentity<'a> {
+    fn encode(&self, writer: &mut Writer) -> Result<(), EncodeError> {
+        writer.write_u16_prefixed(|writer| writer.write_slice(self.identity))?;
+        writer.write_slice(&self.obfuscated_ticket_age.to_be_bytes())
+    }
+
+    fn decode(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        let identity = reader.read_u16_prefixed()?.read_rest();
+        if identity.is_empty() {
+            return Err(DecodeError);
+        }
+        let obfuscated_ticket_age = u32::from_be_bytes(*reader.read_array()?);
+        Ok(Self {
+            identity,
+            obfuscated_ticket_age,
+        })
+    }
+}
+
+/// The data of a client's `pre_shared_key` extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OfferedPsks<'a> {
+    pub identities: Vec<PskIdentity<'a>>,
+    /// The binder of each identity, in the same order.
+    pub binders: Vec<&'a [u8]>,
+}
+
+impl OfferedPsks<'_> {
+    /// The shortest binder that can be encoded.
+    const MIN_BINDER_LEN: usize = 32;
+
+    /// Returns the length of the encoded list of binders, which ends the `ClientHello`.
+    pub fn binders_len(&self) -> usize {
+        2 + self
+            .binders
+            .iter()
+            .map(|binder| 1 + binder.len())
+            .sum::<usize>()
+    }
+}
+
+impl<'a> Codec<'a> for OfferedPsks<'a> {
+    fn encode(&self, writer: &mut Writer) -> Result<(), EncodeError> {
+        writer.write_u16_prefixed(|writer| {
+            self.identities
+                .iter()
+                .try_for_each(|identity| identity.encode(writer))
+        })?;
+        writer.write_u16_prefixed(|writer| {
+            self.binders.iter().try_for_each(|binder| {
+                writer.write_u8_prefixed(|writer| writer.write_slice(binder))
+            })
+        })
+    }
+
+    fn decode(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        let mut identities = Vec::new();
+        let mut encoded = reader.read_u16_prefixed()?;
+        while !encoded.is_empty() {
+            identities.push(PskIdentity::decode(&mut encoded)?);
+        }
+        let mut binders = Vec::new();
+        let mut encoded = reader.read_u16_prefixed()?;
+        while !encoded.is_empty() {
+            let binder = encoded.read_u8_prefixed()?.read_rest();
+            if binder.len() < Self::MIN_BINDER_LEN {
+                return Err(DecodeError);
+            }
+            binders.push(binder);
+        }
+        // both lists have at least one entry
+        if identities.is_empty() || binders.is_empty() {
+            return Err(DecodeError);
+        }
+        Ok(Self {
+            identities,
+            binders,
+        })
+    }
+}
+
+/// The error that is returned when a PSK can't be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PskError {
+    /// The identity is empty or too long.
+    InvalidIdentity,
+    /// The key is shorter than [`ExternalPsk::MIN_KEY_LEN`].
+    KeyTooShort,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExternalPsk, OfferedPsks, PskError, PskHash, PskIdentity};
+    use crate::codec::{Codec, Reader, Writer};
+
+    #[test]
+    fn binder() {
+        let key = (0..16).collect();
+        let psk = ExternalPsk::new(b"client1".to_vec(), key, PskHash::Sha256).unwrap();
+        // computed with Python's hmac and hashlib, following RFC 8446 section 7.1
+        let expected = [
+            0xac, 0xf1, 0xb0, 0x6e, 0x71, 0xbe, 0xa5, 0xd4, 0x4e, 0x0d, 0x90, 0xe1, 0x1c, 0xe8,
+            0x3c, 0x12, 0x88, 0x06, 0xd1, 0x49, 0x9d, 0xad, 0x1b, 0xee, 0xa3, 0xc0, 0x51, 0x95,
+            0x9b, 0x41, 0x2d, 0xf3,
+        ];
+        assert_eq!(
+            super::binder(&psk.offer().binder_key, b"partial ClientHello"),
+            expected
+        );
+    }
+
+    #[test]
+    fn new() {
+        assert_eq!(
+            ExternalPsk::new(Vec::new(), vec![0; 32], PskHash::Sha256).err(),
+            Some(PskError::InvalidIdentity)
+        );
+        assert_eq!(
+            ExternalPsk::new(vec![0; 0x10000], vec![0; 32], PskHash::Sha256).err(),
+            Some(PskError::InvalidIdentity)
+        );
+        assert_eq!(
+            ExternalPsk::new(b"client1".to_vec(), vec![0; 15], PskHash::Sha256).err(),
+            Some(PskError::KeyTooShort)
+        );
+    }
+
+    #[test]
+    fn offered_psks_codec() {
+        let binder = [0xbb; 32];
+        let offered = OfferedPsks {
+            identities: vec![
+                PskIdentity {
+                    identity: b"client1",
+                    obfuscated_ticket_age: 0,
+                },
+                PskIdentity {
+                    identity: b"ticket",
+                    obfuscated_ticket_age: 0x01020304,
+                },
+            ],
+            binders: vec![&binder, &binder],
+        };
+        let mut buf = [0; 100];
+        let mut writer = Writer::new(&mut buf);
+        offered.encode(&mut writer).unwrap();
+        let len = writer.len();
+        assert_eq!(buf[..4], [0x00, 0x19, 0x00, 0x07]);
+        assert_eq!(offered.binders_len(), 68);
+        assert_eq!(buf[len - 68..][..3], [0x00, 0x42, 0x20]);
+
+        let mut reader = Reader::new(&buf[..len]);
+        assert_eq!(OfferedPsks::decode(&mut reader), Ok(offered));
+        assert!(reader.is_empty());
+
+        // binders must be at least as long as a SHA-256 hash
+        let short = [
+            0x00, 0x07, 0x00, 0x01, 0xaa, 0, 0, 0, 0, 0x00, 0x02, 0x01, 0xbb,
+        ];
+        assert!(OfferedPsks::decode(&mut Reader::new(&short)).is_err());
+        // and there has to be at least one PSK
+        assert!(OfferedPsks::decode(&mut Reader::new(&[0, 0, 0, 0])).is_err());
+    }
+}
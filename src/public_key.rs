@@ -0,0 +1,305 @@
+//! Public keys, as found in the `SubjectPublicKeyInfo` of certificates.
+//!
+//! A [`PublicKey`] is parsed once per certificate, then used both to verify the peer's signatures
+//! and to tell the application which key the peer has.
+//!
+//! secp384r1, secp521r1, Ed25519, and RSA keys are recognized, but `crylib` can only verify
+//! secp256r1 signatures so far; verifying with any other key returns
+//! [`SignatureError::UnsupportedScheme`].
+use crate::alert::AlertDescription;
+use crate::certificate::{Certificate, CertificateError};
+use crate::cipher_suites::decode_secp256r1_point;
+use crate::oid::{AlgorithmError, Curve, KeyAlgorithm};
+use crate::signature::{Signature, SignatureError};
+use crylib::asn1::{self, InvalidDer};
+use crylib::ec::{ecdsa, AffinePoint, Secp256r1};
+use crylib::hash::{Hasher, Sha256};
+
+/// A public key of any supported type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublicKey {
+    /// An elliptic curve key, for ECDSA.
+    Ec(EcPublicKey),
+    /// An Ed25519 key, as its 32-byte encoding.
+    Ed25519([u8; 32]),
+    /// An RSA key.
+    Rsa(RsaPublicKey),
+}
+
+/// An elliptic curve public key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EcPublicKey {
+    Secp256r1(AffinePoint<Secp256r1>),
+    /// An uncompressed point, which `crylib` can't check is on the curve yet.
+    Secp384r1([u8; 97]),
+    /// An uncompressed point, which `crylib` can't check is on the curve yet.
+    Secp521r1([u8; 133]),
+}
+
+/// An RSA public key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsaPublicKey {
+    modulus: Vec<u8>,
+    exponent: Vec<u8>,
+}
+
+impl PublicKey {
+    /// Parses a DER `SubjectPublicKeyInfo`.
+    pub fn from_spki(spki: &[u8]) -> Result<Self, PublicKeyError> {
+        match KeyAlgorithm::from_spki(spki)? {
+            (KeyAlgorithm::Ec(curve), key) => Ok(Self::Ec(EcPublicKey::parse(curve, key)?)),
+            (KeyAlgorithm::Ed25519, key) => key
+                .try_into()
+                .map(Self::Ed25519)
+                .map_err(|_| PublicKeyError::InvalidKey),
+            (KeyAlgorithm::Rsa, key) => Ok(Self::Rsa(RsaPublicKey::parse(key)?)),
+            // a `PublicKey` has no way to carry the restriction to RSASSA-PSS
+            (KeyAlgorithm::RsaPss(_), _) => Err(PublicKeyError::UnsupportedAlgorithm),
+        }
+    }
+
+    /// Parses the public key of a DER X.509 certificate.
+    ///
+    /// Only the path to the key is parsed; validating the rest of the certificate is up to the
+    /// certificate verifier.
+    pub fn from_certificate(cert: &[u8]) -> Result<Self, PublicKeyError> {
+        Self::from_spki(Certificate::parse(cert)?.spki())
+    }
+
+    /// Verifies that `signature` is a signature of `data` by the owner of `self`.
+    pub fn verify(&self, signature: &Signature, data: &[u8]) -> Result<(), SignatureError> {
+        match (self, signature) {
+            (Self::Ec(EcPublicKey::Secp256r1(key)), Signature::EcdsaSecp256r1Sha256(sig)) => {
+                ecdsa::verify_signature::<Secp256r1>(data, &key.as_projective(), Sha256::hash, sig)
+                    .map(|_| ())
+                    .map_err(|_| SignatureError::BadSignature)
+            },
+            (Self::Ed25519(_), Signature::Ed25519(_)) => Err(SignatureError::UnsupportedScheme),
+            _ => Err(SignatureError::KeyMismatch),
+        }
+    }
+}
+
+impl EcPublicKey {
+    /// Parses the encoded point `key` on `curve`.
+    pub fn parse(curve: Curve, key: &[u8]) -> Result<Self, PublicKeyError> {
+        match curve {
+            Curve::Secp256r1 => decode_secp256r1_point(key)
+                .map(Self::Secp256r1)
+                .ok_or(PublicKeyError::InvalidKey),
+            Curve::Secp384r1 => uncompressed_point(key).map(Self::Secp384r1),
+            Curve::Secp521r1 => uncompressed_point(key).map(Self::Secp521r1),
+        }
+    }
+}
+
+/// Checks that `key` looks like an uncompressed point, without checking that it's on the curve.
+fn uncompressed_point<const N: usize>(key: &[u8]) -> Result<[u8; N], PublicKeyError> {
+    match key {
+        [4, ..] => key.try_into().map_err(|_| PublicKeyError::InvalidKey),
+        _ => Err(PublicKeyError::InvalidKey),
+    }
+}
+
+impl RsaPublicKey {
+    /// Parses a DER `RSAPublicKey`.
+    fn parse(key: &[u8]) -> Result<Self, PublicKeyError> {
+        let mut reader = asn1::Reader::new(key);
+        let mut key = reader.read_sequence()?;
+        reader.finish()?;
+        let modulus = key.read_unsigned()?;
+        let exponent = key.read_unsigned()?;
+        key.finish()?;
+        Self::new(modulus, exponent)
+    }
+
+    /// Creates a key from its big-endian modulus and public exponent, without leading zeros.
+    pub fn new(modulus: &[u8], exponent: &[u8]) -> Result<Self, PublicKeyError> {
+        // both are odd in every RSA key, and an exponent of 1 wouldn't encrypt anything
+        let is_odd = |int: &[u8]| int.last().is_some_and(|byte| byte & 1 == 1);
+        if !is_odd(modulus) || !is_odd(exponent) || exponent == [1] {
+            return Err(PublicKeyError::InvalidKey);
+        }
+        Ok(Self {
+            modulus: modulus.to_vec(),
+            exponent: exponent.to_vec(),
+        })
+    }
+
+    /// Returns the big-endian modulus, without leading zeros.
+    pub fn modulus(&self) -> &[u8] {
+        &self.modulus
+    }
+
+    /// Returns the big-endian public exponent, without leading zeros.
+    pub fn exponent(&self) -> &[u8] {
+        &self.exponent
+    }
+
+    /// Returns the size of the modulus in bits.
+    pub fn bits(&self) -> usize {
+        // the modulus is odd, so it isn't empty
+        self.modulus.len() * 8 - self.modulus[0].leading_zeros() as usize
+    }
+}
+
+/// The error that is returned when a public key can't be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PublicKeyError {
+    /// The `SubjectPublicKeyInfo` isn't valid DER.
+    InvalidEncoding,
+    /// The key is of a type or on a curve that isn't recognized.
+    UnsupportedAlgorithm,
+    /// The key is encoded correctly, but isn't a valid key of its type.
+    InvalidKey,
+}
+
+impl PublicKeyError {
+    /// The alert to send in response to this error.
+    pub const fn alert(self) -> AlertDescription {
+        match self {
+            Self::InvalidEncoding | Self::InvalidKey => AlertDescription::BadCert,
+            Self::UnsupportedAlgorithm => AlertDescription::UnsupportedCert,
+        }
+    }
+}
+
+impl From<InvalidDer> for PublicKeyError {
+    fn from(_: InvalidDer) -> Self {
+        Self::InvalidEncoding
+    }
+}
+
+impl From<AlgorithmError> for PublicKeyError {
+    fn from(err: AlgorithmError) -> Self {
+        match err {
+            AlgorithmError::InvalidEncoding => Self::InvalidEncoding,
+            // a key algorithm is never checked against a key
+            AlgorithmError::UnsupportedAlgorithm | AlgorithmError::KeyMismatch => {
+                Self::UnsupportedAlgorithm
+            },
+        }
+    }
+}
+
+impl From<CertificateError> for PublicKeyError {
+    fn from(err: CertificateError) -> Self {
+        match err {
+            CertificateError::UnsupportedAlgorithm => Self::UnsupportedAlgorithm,
+            _ => Self::InvalidEncoding,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EcPublicKey, PublicKey, PublicKeyError};
+    use crate::signature::{Signature, SignatureError};
+
+    // generated with pyca/cryptography
+    const SECP384R1_SPKI: [u8; 120] = [
+        0x30, 0x76, 0x30, 0x10, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x05,
+        0x2b, 0x81, 0x04, 0x00, 0x22, 0x03, 0x62, 0x00, 0x04, 0x7a, 0xe9, 0xe1, 0xdb, 0x11, 0x60,
+        0x79, 0x4c, 0x70, 0xc1, 0xef, 0x07, 0x1b, 0xe5, 0x19, 0x1f, 0x6c, 0x66, 0x24, 0x0f, 0x46,
+        0x08, 0xfb, 0xe8, 0xa4, 0x4e, 0xcd, 0x36, 0xc3, 0xed, 0x5d, 0xb9, 0x75, 0x00, 0xf2, 0xe4,
+        0xd8, 0x4e, 0x3f, 0xb4, 0x58, 0x50, 0xfd, 0x6f, 0x43, 0x03, 0x30, 0x2b, 0xd8, 0xac, 0x21,
+        0x44, 0x4c, 0xd8, 0x0d, 0x24, 0x15, 0x38, 0xd8, 0x03, 0x4b, 0x39, 0x9a, 0x0d, 0xee, 0x36,
+        0x36, 0xeb, 0x5c, 0xb0, 0x0b, 0x94, 0x99, 0xd0, 0x61, 0xa5, 0x71, 0xdb, 0x0f, 0x14, 0xbb,
+        0x47, 0xed, 0x79, 0x45, 0x39, 0x48, 0xed, 0x63, 0xf3, 0xa6, 0xcc, 0xbb, 0xf6, 0xe7, 0x4f,
+    ];
+    const ED25519_SPKI: [u8; 44] = [
+        0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00, 0x03, 0xa1, 0x07,
+        0xbf, 0xf3, 0xce, 0x10, 0xbe, 0x1d, 0x70, 0xdd, 0x18, 0xe7, 0x4b, 0xc0, 0x99, 0x67, 0xe4,
+        0xd6, 0x30, 0x9b, 0xa5, 0x0d, 0x5f, 0x1d, 0xdc, 0x86, 0x64, 0x12, 0x55, 0x31, 0xb8,
+    ];
+    const RSA_SPKI: [u8; 162] = [
+        0x30, 0x81, 0x9f, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01,
+        0x01, 0x05, 0x00, 0x03, 0x81, 0x8d, 0x00, 0x30, 0x81, 0x89, 0x02, 0x81, 0x81, 0x00, 0xf9,
+        0xc0, 0x0c, 0x1b, 0x2f, 0x49, 0x9c, 0xe0, 0x11, 0x87, 0xa4, 0x11, 0xf5, 0x19, 0x42, 0x82,
+        0x58, 0xa9, 0x2b, 0x21, 0x1e, 0x66, 0x4e, 0x18, 0xf6, 0xbb, 0x4d, 0x14, 0xe2, 0x18, 0x48,
+        0x79, 0xe8, 0x93, 0x21, 0x3c, 0xa4, 0x78, 0xc3, 0x3e, 0x56, 0xec, 0x7a, 0xd4, 0xd3, 0x36,
+        0xf3, 0xd6, 0x91, 0x00, 0xdb, 0xd1, 0x8a, 0x06, 0x04, 0xde, 0xd5, 0xe7, 0x20, 0xcf, 0xa3,
+        0xcd, 0x28, 0xad, 0x09, 0x2b, 0x84, 0xf7, 0xfa, 0x92, 0x89, 0xb7, 0xc0, 0xc3, 0x66, 0x52,
+        0xe8, 0xea, 0x38, 0xec, 0x7b, 0x5a, 0xc7, 0x61, 0x66, 0x71, 0x37, 0x15, 0x78, 0x1b, 0x6f,
+        0x21, 0xfd, 0x6b, 0x69, 0x15, 0x90, 0x1d, 0x82, 0x28, 0x35, 0x75, 0x65, 0x08, 0xe7, 0xcb,
+        0x32, 0x5e, 0x89, 0xf2, 0x00, 0xea, 0x36, 0x3e, 0xa8, 0x57, 0x76, 0x1c, 0x07, 0x07, 0x0c,
+        0xd2, 0xc1, 0xa0, 0xb0, 0x64, 0x58, 0x09, 0x02, 0x03, 0x01, 0x00, 0x01,
+    ];
+    const X25519_SPKI: [u8; 44] = [
+        0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x6e, 0x03, 0x21, 0x00, 0x8f, 0x40, 0xc5,
+        0xad, 0xb6, 0x8f, 0x25, 0x62, 0x4a, 0xe5, 0xb2, 0x14, 0xea, 0x76, 0x7a, 0x6e, 0xc9, 0x4d,
+        0x82, 0x9d, 0x3d, 0x7b, 0x5e, 0x1a, 0xd1, 0xba, 0x6f, 0x3e, 0x21, 0x38, 0x28, 0x5f,
+    ];
+
+    #[test]
+    fn from_spki() {
+        let PublicKey::Ec(EcPublicKey::Secp384r1(point)) =
+            PublicKey::from_spki(&SECP384R1_SPKI).unwrap()
+        else {
+            panic!("not a secp384r1 key");
+        };
+        assert_eq!(point, SECP384R1_SPKI[23..]);
+
+        let ed25519 = PublicKey::from_spki(&ED25519_SPKI).unwrap();
+        assert_eq!(
+            ed25519,
+            PublicKey::Ed25519(ED25519_SPKI[12..].try_into().unwrap())
+        );
+
+        let PublicKey::Rsa(rsa) = PublicKey::from_spki(&RSA_SPKI).unwrap() else {
+            panic!("not an RSA key");
+        };
+        assert_eq!(rsa.modulus(), &RSA_SPKI[29..][..128]);
+        assert_eq!(rsa.exponent(), [0x01, 0x00, 0x01]);
+        assert_eq!(rsa.bits(), 1024);
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            PublicKey::from_spki(&X25519_SPKI),
+            Err(PublicKeyError::UnsupportedAlgorithm)
+        );
+        assert_eq!(
+            PublicKey::from_spki(&ED25519_SPKI[..43]),
+            Err(PublicKeyError::InvalidEncoding)
+        );
+
+        let mut trailing = ED25519_SPKI.to_vec();
+        trailing.push(0);
+        assert_eq!(
+            PublicKey::from_spki(&trailing),
+            Err(PublicKeyError::InvalidEncoding)
+        );
+
+        // a compressed point
+        let mut compressed = SECP384R1_SPKI;
+        compressed[23] = 0x02;
+        assert_eq!(
+            PublicKey::from_spki(&compressed),
+            Err(PublicKeyError::InvalidKey)
+        );
+
+        assert_eq!(
+            PublicKey::from_certificate(&ED25519_SPKI),
+            Err(PublicKeyError::InvalidEncoding)
+        );
+
+        // an even exponent
+        let mut even = RSA_SPKI;
+        even[161] = 0x00;
+        assert_eq!(PublicKey::from_spki(&even), Err(PublicKeyError::InvalidKey));
+    }
+
+    #[test]
+    fn key_mismatch() {
+        let ed25519 = PublicKey::from_spki(&ED25519_SPKI).unwrap();
+        let rsa = PublicKey::from_spki(&RSA_SPKI).unwrap();
+        let sig = Signature::Ed25519([0; 64]);
+        assert_eq!(rsa.verify(&sig, b""), Err(SignatureError::KeyMismatch));
+        assert_eq!(
+            ed25519.verify(&sig, b""),
+            Err(SignatureError::UnsupportedScheme)
+        );
+    }
+}
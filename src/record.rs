@@ -1,11 +1,12 @@
 use crylib::aead;
 
-use crate::aead::AeadWriter;
-use crate::versions::LEGACY_PROTO_VERS;
-use crate::State;
+use crate::aead::{AeadReader, AeadWriter, SequenceExhausted};
+use crate::alert::AlertDescription;
+use crate::codec::{EncodeError, Reader, Writer};
+use crate::versions::{ProtocolVersion, LEGACY_PROTO_VERS};
 
 #[repr(u8)]
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ContentType {
     Invalid = 0,
     ChangeCipherSpec = 20,
@@ -14,13 +15,109 @@ pub enum ContentType {
     ApplicationData = 23,
 }
 
+impl TryFrom<u8> for ContentType {
+    type Error = AlertDescription;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            20 => Ok(Self::ChangeCipherSpec),
+            21 => Ok(Self::Alert),
+            22 => Ok(Self::Handshake),
+            23 => Ok(Self::ApplicationData),
+            _ => Err(AlertDescription::UnexpectedMessage),
+        }
+    }
+}
+
+/// The header of a received record.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RecordHeader {
+    pub content_type: ContentType,
+    pub len: u16,
+}
+
+impl RecordHeader {
+    pub const SIZE: usize = Message::PREFIIX_SIZE;
+    /// The largest fragment a plaintext record may hold.
+    pub const MAX_PLAINTEXT_LEN: u16 = 0x4000;
+    /// The largest fragment an encrypted record may hold with the AEADs this crate supports.
+    ///
+    /// A decrypted fragment may hold at most [`Self::MAX_PLAINTEXT_LEN`] bytes of content and
+    /// padding, plus the content type. Every supported AEAD adds a [`aead::TAG_SIZE`]-byte tag, so
+    /// anything longer than this would overflow once decrypted.
+    pub const MAX_SUPPORTED_CIPHERTEXT_LEN: u16 =
+        Self::MAX_PLAINTEXT_LEN + 1 + aead::TAG_SIZE as u16;
+
+    /// Parses and validates a record header.
+    ///
+    /// `encrypted` is whether the peer has started encrypting its records. Once it has, only
+    /// application data and compatibility change-cipher-spec records are allowed; before that,
+    /// application data is not.
+    ///
+    /// Returns the alert to send if the header is invalid.
+    pub fn parse(header: &[u8], encrypted: bool) -> Result<Self, AlertDescription> {
+        let mut reader = Reader::new(header);
+        let content_type = reader.read_u8();
+        let version = reader.read_u16();
+        let len = reader.read_u16();
+        let (Ok(content_type), Ok(version), Ok(len)) = (content_type, version, len) else {
+            return Err(AlertDescription::DecodeError);
+        };
+
+        let content_type = ContentType::try_from(content_type)?;
+        let allowed = match content_type {
+            ContentType::ChangeCipherSpec => true,
+            ContentType::ApplicationData => encrypted,
+            ContentType::Alert | ContentType::Handshake => !encrypted,
+            ContentType::Invalid => false,
+        };
+        if !allowed {
+            return Err(AlertDescription::UnexpectedMessage);
+        }
+
+        // `legacy_record_version` is otherwise ignored, but anything that isn't TLS is garbage
+        if version >> 8 != 0x03 {
+            return Err(AlertDescription::DecodeError);
+        }
+
+        let max_len = match content_type {
+            ContentType::ApplicationData => Self::MAX_SUPPORTED_CIPHERTEXT_LEN,
+            _ => Self::MAX_PLAINTEXT_LEN,
+        };
+        if len > max_len {
+            return Err(AlertDescription::RecordOverflow);
+        }
+        // only application data may be empty
+        if len == 0 && content_type != ContentType::ApplicationData {
+            return Err(AlertDescription::DecodeError);
+        }
+
+        Ok(Self { content_type, len })
+    }
+}
+
+/// Returns the length of the record at the front of `buf`, including its header.
+///
+/// Returns `Ok(None)` if the record hasn't been completely received yet, and the alert to send if
+/// its header is invalid. See [`RecordHeader::parse`] for what `encrypted` means.
+pub fn record_len(buf: &[u8], encrypted: bool) -> Result<Option<usize>, AlertDescription> {
+    let Some(header) = buf.get(..RecordHeader::SIZE) else {
+        return Ok(None);
+    };
+    let header = RecordHeader::parse(header, encrypted)?;
+    let len = RecordHeader::SIZE + header.len as usize;
+    Ok((buf.len() >= len).then_some(len))
+}
+
 pub struct Message {
     buf: [u8; Message::MAX_SIZE],
     len: usize,
 }
 
 impl Message {
-    pub const MAX_SIZE: usize = 0x4006;
+    /// The size of the largest record this crate sends: a full fragment,
+    /// plus the content type and tag that encrypting it adds.
+    pub const MAX_SIZE: usize =
+        Self::PREFIIX_SIZE + RecordHeader::MAX_PLAINTEXT_LEN as usize + 1 + aead::TAG_SIZE;
     pub const PREFIIX_SIZE: usize = 5;
 
     pub fn start(msg_type: ContentType) -> Self {
@@ -50,12 +147,25 @@ impl Message {
         self.len += slice.len();
     }
 
+    /// Appends the output of `f` to the message.
+    pub fn write(
+        &mut self,
+        f: impl FnOnce(&mut Writer) -> Result<(), EncodeError>,
+    ) -> Result<(), EncodeError> {
+        let len = self.len;
+        let mut writer = Writer::new(&mut self.buf[len..]);
+        f(&mut writer)?;
+        self.len += writer.len();
+        Ok(())
+    }
+
     pub fn extend(&mut self, amt: usize) {
         self.len += amt;
     }
 
+    /// Writes the length of the fragment into the header.
     pub fn finish(&mut self) {
-        let len = self.len;
+        let len = self.len - Self::PREFIIX_SIZE;
         self[3..5].copy_from_slice(&(len as u16).to_be_bytes());
     }
 }
@@ -92,6 +202,74 @@ impl AsMut<[u8]> for Message {
     }
 }
 
+/// The error that is returned when an encrypted record can't be opened.
+///
+/// A bad tag and a malformed plaintext are deliberately indistinguishable, so that a peer
+/// can't learn which check failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DecryptError;
+
+impl DecryptError {
+    /// The alert to send in response to this error.
+    pub const fn alert(self) -> AlertDescription {
+        AlertDescription::BadRecordMac
+    }
+}
+
+impl std::fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("record could not be decrypted")
+    }
+}
+
+impl std::error::Error for DecryptError {}
+
+/// Decrypts the fragment of an encrypted record in place.
+///
+/// `header` is the record header, which is authenticated as additional data. On success, this
+/// returns the record's real content type and the length of its content, which starts at the
+/// beginning of `fragment`.
+///
+/// A plaintext without a content type is reported as a [`DecryptError`] rather than as an
+/// `unexpected_message`, so every failure takes the same path. On failure, `fragment` is zeroed, so
+/// no unauthenticated or partially processed plaintext is left behind regardless of how the
+/// underlying AEAD behaves.
+pub fn open_record(
+    reader: &mut AeadReader,
+    header: &[u8; RecordHeader::SIZE],
+    fragment: &mut [u8],
+) -> Result<(ContentType, usize), DecryptError> {
+    let result = open_record_unscrubbed(reader, header, fragment);
+    if result.is_err() {
+        fragment.fill(0);
+    }
+    result
+}
+
+fn open_record_unscrubbed(
+    reader: &mut AeadReader,
+    header: &[u8; RecordHeader::SIZE],
+    fragment: &mut [u8],
+) -> Result<(ContentType, usize), DecryptError> {
+    let Some(tagless_len) = fragment.len().checked_sub(aead::TAG_SIZE) else {
+        return Err(DecryptError);
+    };
+    let (msg, tag) = fragment.split_at_mut(tagless_len);
+    // we can safely unwrap because `tag` is guaranteed to have a length of `TAG_SIZE`
+    let tag: &[u8; aead::TAG_SIZE] = (&*tag).try_into().unwrap();
+    reader
+        .decrypt_inline(msg, header, tag)
+        .map_err(|_| DecryptError)?;
+
+    // the content type is the last non-zero byte; everything after it is padding
+    let content_len = msg
+        .iter()
+        .rposition(|&byte| byte != 0)
+        .ok_or(DecryptError)?;
+    let content_type = ContentType::try_from(msg[content_len]).map_err(|_| DecryptError)?;
+    Ok((content_type, content_len))
+}
+
 pub struct EncryptedMessage {
     msg: Message,
     content_type: ContentType,
@@ -99,28 +277,52 @@ pub struct EncryptedMessage {
 }
 
 impl EncryptedMessage {
+    /// Starts an encrypted record whose content is of type `content_type`.
+    ///
+    /// `padding` zeros are added after the content type when the record is finished,
+    /// to hide the length of the content.
     pub fn start(content_type: ContentType, padding: usize) -> Self {
+        let mut msg = Message::start(ContentType::ApplicationData);
+        // encrypted records always claim to be TLS 1.2
+        msg[1..3].copy_from_slice(&ProtocolVersion::TlsOnePointTwo.to_be_bytes());
         Self {
-            msg: Message::start(ContentType::ApplicationData),
+            msg,
             content_type,
             padding,
         }
     }
 
-    pub fn finish(&mut self, state: &mut State) {
+    /// Appends the content type and padding, then encrypts the record in place.
+    ///
+    /// Returns [`SequenceExhausted`] if `writer` can't encrypt any more records.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if the content and padding together are longer than
+    /// [`RecordHeader::MAX_PLAINTEXT_LEN`].
+    pub fn finish(&mut self, writer: &mut AeadWriter) -> Result<(), SequenceExhausted> {
+        let content_len = self.len() - Message::PREFIIX_SIZE;
+        assert!(
+            content_len + self.padding <= RecordHeader::MAX_PLAINTEXT_LEN as usize,
+            "record content and padding are too long"
+        );
+
         let content_type = self.content_type;
         self.push(content_type as u8);
 
         let padding = self.padding;
-        self.extend(padding + aead::TAG_SIZE);
-
-        let (add_data, encrypted_data) = self.msg.split_at_mut(Message::PREFIIX_SIZE);
-        let tag = state.aead_writer.encrypt_inline(encrypted_data, add_data);
-
-        let tagless_len = self.len() - aead::TAG_SIZE;
-        self[tagless_len..].copy_from_slice(&tag);
+        let padding_start = self.len();
+        self.extend(padding);
+        let tagless_len = self.len();
+        self[padding_start..tagless_len].fill(0);
+        self.extend(aead::TAG_SIZE);
 
+        // the header is authenticated, so its length has to be written first
         self.msg.finish();
+        let (header, inner_plaintext) = self.msg[..tagless_len].split_at_mut(Message::PREFIIX_SIZE);
+        let tag = writer.encrypt_inline(inner_plaintext, header)?;
+        self[tagless_len..][..aead::TAG_SIZE].copy_from_slice(&tag);
+        Ok(())
     }
 }
 
@@ -137,3 +339,233 @@ impl std::ops::DerefMut for EncryptedMessage {
         &mut self.msg
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aead::TrafficKeys;
+    use crylib::aead::gcm::{Aes128, Gcm};
+    use crylib::aead::Aead;
+
+    const KEY: [u8; 16] = [
+        0xfe, 0xff, 0xe9, 0x92, 0x86, 0x65, 0x73, 0x1c, 0x6d, 0x6a, 0x8f, 0x94, 0x67, 0x30, 0x83,
+        0x08,
+    ];
+    const IV: [u8; 12] = [
+        0xca, 0xfe, 0xba, 0xbe, 0xfa, 0xce, 0xdb, 0xad, 0xde, 0xca, 0xf8, 0x88,
+    ];
+    const HEADER: [u8; RecordHeader::SIZE] = [0x17, 0x03, 0x03, 0x00, 0x19];
+
+    /// Encrypts `inner_plaintext` the way a peer would, returning the fragment.
+    fn seal(inner_plaintext: &[u8]) -> Vec<u8> {
+        let mut fragment = inner_plaintext.to_vec();
+        let tag = Gcm::<Aes128>::new(KEY).encrypt_inline(&mut fragment, &HEADER, &IV);
+        fragment.extend_from_slice(&tag);
+        fragment
+    }
+
+    fn reader() -> AeadReader {
+        AeadReader::new(TrafficKeys::aes_128_gcm(KEY, IV))
+    }
+
+    #[test]
+    fn open() {
+        let mut fragment = seal(b"hello\x16\x00\x00\x00");
+        assert_eq!(
+            open_record(&mut reader(), &HEADER, &mut fragment),
+            Ok((ContentType::Handshake, 5))
+        );
+        assert_eq!(&fragment[..5], b"hello");
+    }
+
+    #[test]
+    fn open_failures() {
+        let mut fragment = seal(b"hello\x16");
+        fragment[0] ^= 1;
+        assert_eq!(
+            open_record(&mut reader(), &HEADER, &mut fragment),
+            Err(DecryptError)
+        );
+        assert!(fragment.iter().all(|&byte| byte == 0));
+
+        // authentic, but without a content type
+        let mut fragment = seal(&[0; 8]);
+        assert_eq!(
+            open_record(&mut reader(), &HEADER, &mut fragment),
+            Err(DecryptError)
+        );
+        assert!(fragment.iter().all(|&byte| byte == 0));
+
+        // authentic, but with an unknown content type; the decrypted content must not survive
+        let mut fragment = seal(b"hello\x42");
+        assert_eq!(
+            open_record(&mut reader(), &HEADER, &mut fragment),
+            Err(DecryptError)
+        );
+        assert!(fragment.iter().all(|&byte| byte == 0));
+
+        let mut fragment = [0xff; aead::TAG_SIZE - 1];
+        assert_eq!(
+            open_record(&mut reader(), &HEADER, &mut fragment),
+            Err(DecryptError)
+        );
+        assert!(fragment.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn seal_record() {
+        let mut msg = EncryptedMessage::start(ContentType::Handshake, 3);
+        msg.extend_from_slice(b"hello");
+        msg.finish(&mut AeadWriter::new(TrafficKeys::aes_128_gcm(KEY, IV)))
+            .unwrap();
+
+        assert_eq!(msg[..RecordHeader::SIZE], HEADER);
+        assert_eq!(msg[RecordHeader::SIZE..], seal(b"hello\x16\x00\x00\x00"));
+
+        let mut record = msg.to_vec();
+        let (header, fragment) = record.split_at_mut(RecordHeader::SIZE);
+        assert_eq!(
+            open_record(&mut reader(), (&*header).try_into().unwrap(), fragment),
+            Ok((ContentType::Handshake, 5))
+        );
+        assert_eq!(&fragment[..5], b"hello");
+    }
+
+    #[test]
+    fn seal_sequence() {
+        let mut writer = AeadWriter::new(TrafficKeys::aes_128_gcm(KEY, IV));
+        let mut reader = reader();
+        for content in [&b"first"[..], b"second", b""] {
+            let mut msg = EncryptedMessage::start(ContentType::ApplicationData, 0);
+            msg.extend_from_slice(content);
+            msg.finish(&mut writer).unwrap();
+
+            let mut record = msg.to_vec();
+            let (header, fragment) = record.split_at_mut(RecordHeader::SIZE);
+            assert_eq!(
+                open_record(&mut reader, (&*header).try_into().unwrap(), fragment),
+                Ok((ContentType::ApplicationData, content.len()))
+            );
+            assert_eq!(&fragment[..content.len()], content);
+        }
+    }
+
+    #[test]
+    fn seal_full() {
+        let mut msg = EncryptedMessage::start(ContentType::ApplicationData, 0x100);
+        msg.extend_from_slice(&[0x42; RecordHeader::MAX_PLAINTEXT_LEN as usize - 0x100]);
+        msg.finish(&mut AeadWriter::new(TrafficKeys::aes_128_gcm(KEY, IV)))
+            .unwrap();
+        assert_eq!(msg.len(), Message::MAX_SIZE);
+        assert_eq!(
+            RecordHeader::parse(&msg[..RecordHeader::SIZE], true),
+            Ok(RecordHeader {
+                content_type: ContentType::ApplicationData,
+                len: RecordHeader::MAX_SUPPORTED_CIPHERTEXT_LEN,
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn seal_too_long() {
+        let mut msg = EncryptedMessage::start(ContentType::ApplicationData, 1);
+        msg.extend_from_slice(&[0x42; RecordHeader::MAX_PLAINTEXT_LEN as usize]);
+        msg.finish(&mut AeadWriter::new(TrafficKeys::aes_128_gcm(KEY, IV)))
+            .unwrap();
+    }
+
+    #[test]
+    fn plaintext_len() {
+        let mut msg = Message::start(ContentType::Alert);
+        msg.extend_from_slice(&[0x02, 0x32]);
+        msg.finish();
+        assert_eq!(*msg, [0x15, 0x03, 0x01, 0x00, 0x02, 0x02, 0x32]);
+    }
+
+    #[test]
+    fn deframe() {
+        let record = [0x16, 0x03, 0x03, 0x00, 0x02, 0xaa, 0xbb, 0x16, 0x03];
+        for len in 0..7 {
+            assert_eq!(record_len(&record[..len], false), Ok(None));
+        }
+        assert_eq!(record_len(&record[..7], false), Ok(Some(7)));
+        assert_eq!(record_len(&record, false), Ok(Some(7)));
+        assert_eq!(
+            record_len(&record, true),
+            Err(AlertDescription::UnexpectedMessage)
+        );
+    }
+
+    #[test]
+    fn parse_header() {
+        assert_eq!(
+            RecordHeader::parse(&[0x16, 0x03, 0x03, 0x00, 0x20], false),
+            Ok(RecordHeader {
+                content_type: ContentType::Handshake,
+                len: 0x20
+            })
+        );
+        assert_eq!(
+            RecordHeader::parse(&[0x17, 0x03, 0x03, 0x40, 0x11], true),
+            Ok(RecordHeader {
+                content_type: ContentType::ApplicationData,
+                len: 0x4011
+            })
+        );
+    }
+
+    #[test]
+    fn truncated_header() {
+        for len in 0..RecordHeader::SIZE {
+            let header = &[0x16, 0x03, 0x03, 0x00, 0x20][..len];
+            assert_eq!(
+                RecordHeader::parse(header, false),
+                Err(AlertDescription::DecodeError)
+            );
+        }
+    }
+
+    #[test]
+    fn bad_header() {
+        // unknown content type
+        assert_eq!(
+            RecordHeader::parse(&[0x42, 0x03, 0x03, 0x00, 0x20], false),
+            Err(AlertDescription::UnexpectedMessage)
+        );
+        // plaintext handshake after encryption has started
+        assert_eq!(
+            RecordHeader::parse(&[0x16, 0x03, 0x03, 0x00, 0x20], true),
+            Err(AlertDescription::UnexpectedMessage)
+        );
+        // application data before encryption has started
+        assert_eq!(
+            RecordHeader::parse(&[0x17, 0x03, 0x03, 0x00, 0x20], false),
+            Err(AlertDescription::UnexpectedMessage)
+        );
+        // not a TLS version
+        assert_eq!(
+            RecordHeader::parse(&[0x16, 0x47, 0x45, 0x00, 0x20], false),
+            Err(AlertDescription::DecodeError)
+        );
+        // too long
+        assert_eq!(
+            RecordHeader::parse(&[0x16, 0x03, 0x03, 0x40, 0x01], false),
+            Err(AlertDescription::RecordOverflow)
+        );
+        // too long to decrypt to at most 2^14 + 1 bytes
+        assert_eq!(
+            RecordHeader::parse(&[0x17, 0x03, 0x03, 0x40, 0x12], true),
+            Err(AlertDescription::RecordOverflow)
+        );
+        assert_eq!(
+            RecordHeader::parse(&[0x17, 0x03, 0x03, 0x41, 0x00], true),
+            Err(AlertDescription::RecordOverflow)
+        );
+        // empty handshake fragment
+        assert_eq!(
+            RecordHeader::parse(&[0x16, 0x03, 0x03, 0x00, 0x00], false),
+            Err(AlertDescription::DecodeError)
+        );
+    }
+}
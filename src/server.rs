@@ -0,0 +1,1894 @@
+//! The server side of a TLS 1.3 connection.
+//!
+//! Like [`ClientConnection`](crate::client::ClientConnection), [`ServerConnection`] doesn't do
+//! any I/O itself: records received from the client are passed to [`ServerConnection::read_tls`],
+//! and whatever has to be sent back is taken from [`ServerConnection::take_outgoing`].
+//!
+//! Only `TLS_AES_128_GCM_SHA256` and secp256r1 are accepted, since they are all `crylib`
+//! implements so far. A client that supports secp256r1 but didn't send a key share for it is
+//! asked for one with a `HelloRetryRequest`. Clients that offer PSKs aren't, since their binders
+//! would cover the `HelloRetryRequest`, and PSKs offered in a second `ClientHello` are ignored.
+//! Early data isn't supported.
+//!
+//! The certificate and key are shared by every connection through [`ServerConfig`], which can
+//! also require clients to authenticate with certificates of their own. Servers can also accept
+//! external PSKs, in which case the PSK authenticates both sides and no certificates are sent.
+//! Servers with only PSKs and no certificate turn away clients that don't offer one of them.
+//!
+//! The certificate can also be picked by the name the client asks for in its `server_name`
+//! extension, with [`ServerConfig::set_named_certs`]. Clients that ask for another name, or
+//! for none, can be turned away instead of being sent the default certificate, with
+//! [`ServerConfig::set_unknown_name_policy`].
+//!
+//! With [`ServerConfig::enable_resumption`], every client is sent a session ticket once its
+//! handshake is done. Offering the ticket later resumes the connection, authenticated by the
+//! PSK the first handshake left behind. Of the PSKs a client offers, the first one the server
+//! can use is chosen, and its binder is checked before anything else is done with it.
+//!
+//! With the `dangerous_handoff` feature, an established connection can be exported to bytes and
+//! imported by another process, along with its socket, so that a server can restart without
+//! dropping its connections.
+use std::mem;
+use std::sync::Arc;
+
+use crate::cipher_suites::{CipherSuite, NamedGroup, SignatureScheme};
+use crate::client_hello::ClientHelloRef;
+use crate::codec::{Codec, Reader, TlsVec, Writer};
+#[cfg(feature = "dangerous_handoff")]
+use crate::connection::HandoffError;
+use crate::connection::{
+    handshake_message, CertVerifier, CertifiedKey, ConnectionCore, ConnectionError, Handshaker,
+    CLIENT_CONTEXT, SERVER_CONTEXT,
+};
+use crate::extensions::Extension;
+use crate::handshake::{Handshake, ShakeType};
+use crate::key_schedule::{EarlySecret, HandshakeSecrets, TrafficSecrets, HASH_LEN};
+use crate::key_share::{EphemeralKey, KeyShareEntry};
+use crate::padding::PaddingPolicy;
+use crate::private_key::PrivateKey;
+use crate::psk::{self, ExternalPsk, OfferedPsks, PskHash, PskIdentity, PskKeyExchangeMode};
+use crate::public_key::PublicKey;
+use crate::record::ContentType;
+use crate::server_hello::ServerHello;
+use crate::sni::{CertifiedKeySet, UnknownNamePolicy};
+use crate::ticket::{NewSessionTicket, TicketStore};
+use crate::versions::ProtocolVersion;
+use crate::zeroizing::Zeroizing;
+
+/// What every connection of a server has in common.
+pub struct ServerConfig {
+    /// The certificate for clients whose server name isn't one of `named_certs`.
+    cert: Option<CertifiedKey>,
+    /// The certificates for the server names clients ask for.
+    named_certs: CertifiedKeySet,
+    /// Whether clients whose name isn't one of `named_certs` get `cert` or are rejected.
+    unknown_names: UnknownNamePolicy,
+    /// Called with the name of every client that isn't in `named_certs`.
+    on_unknown_name: Option<Box<UnknownNameFn>>,
+    /// Decides whether to trust client certificates, if they are required.
+    client_auth: Option<Box<CertVerifier>>,
+    psks: Vec<ExternalPsk>,
+    /// The key exchanges PSKs can be used with, in order of preference.
+    psk_modes: Vec<PskKeyExchangeMode>,
+    /// The session tickets that have been issued, if resumption is enabled.
+    tickets: Option<TicketStore>,
+    /// Whether connections keep a copy of their handshake messages.
+    retain_messages: bool,
+}
+
+impl ServerConfig {
+    /// Creates a configuration that authenticates the server with `cert_chain`, leaf first.
+    ///
+    /// `key` is the private key of the leaf.
+    pub fn new(cert_chain: Vec<Vec<u8>>, key: PrivateKey) -> Self {
+        Self {
+            cert: Some(CertifiedKey { cert_chain, key }),
+            named_certs: CertifiedKeySet::new(),
+            unknown_names: UnknownNamePolicy::ServeDefault,
+            on_unknown_name: None,
+            client_auth: None,
+            psks: Vec::new(),
+            psk_modes: vec![PskKeyExchangeMode::PskDheKe],
+            tickets: None,
+            retain_messages: false,
+        }
+    }
+
+    /// Creates a configuration without a certificate, which only accepts clients that offer one
+    /// of `psks`.
+    ///
+    /// See [`ServerConfig::set_psks`] for what `modes` is.
+    pub fn with_psks(psks: Vec<ExternalPsk>, modes: &[PskKeyExchangeMode]) -> Self {
+        let mut config = Self {
+            cert: None,
+            named_certs: CertifiedKeySet::new(),
+            unknown_names: UnknownNamePolicy::ServeDefault,
+            on_unknown_name: None,
+            client_auth: None,
+            psks: Vec::new(),
+            psk_modes: Vec::new(),
+            tickets: None,
+            retain_messages: false,
+        };
+        config.set_psks(psks, modes);
+        config
+    }
+
+    /// Authenticates the server with the certificate `certs` has for the name a client asks for
+    /// in its `server_name` extension, replacing any that were set before.
+    ///
+    /// Clients that don't send a name, or send one that isn't in `certs`, get the certificate the
+    /// configuration was created with. Configurations created with [`ServerConfig::with_psks`]
+    /// have none, so those clients have to offer a PSK.
+    pub fn set_named_certs(&mut self, certs: CertifiedKeySet) {
+        self.named_certs = certs;
+    }
+
+    /// Decides what happens to clients that ask for a name [`ServerConfig::set_named_certs`]
+    /// has no certificate for, or don't ask for one. By default, they get the certificate the
+    /// configuration was created with.
+    ///
+    /// Clients that are rejected are sent an `unrecognized_name` alert, and the handshake fails
+    /// with [`ConnectionError::UnrecognizedName`].
+    pub fn set_unknown_name_policy(&mut self, policy: UnknownNamePolicy) {
+        self.unknown_names = policy;
+    }
+
+    /// Calls `log` with the name every client asks for that [`ServerConfig::set_named_certs`]
+    /// has no certificate for, or `None` if a client doesn't ask for one, before the
+    /// [`UnknownNamePolicy`] is applied. Clients that are authenticated by a PSK aren't logged.
+    ///
+    /// The name is in lowercase. It hasn't been checked any further than being ASCII, so it
+    /// shouldn't be written anywhere that would interpret it.
+    pub fn on_unknown_name(&mut self, log: impl Fn(Option<&str>) + Send + Sync + 'static) {
+        self.on_unknown_name = Some(Box::new(log));
+    }
+
+    /// Accepts clients that offer one of `psks`, which authenticates them instead of a certificate.
+    ///
+    /// `modes` are the key exchanges the PSKs can be used with, in order of preference. The first
+    /// one the client also offered is used, but `psk_dhe_ke` is skipped if the client didn't send
+    /// a secp256r1 key share. They also apply to resumption, which otherwise only uses
+    /// `psk_dhe_ke`.
+    pub fn set_psks(&mut self, psks: Vec<ExternalPsk>, modes: &[PskKeyExchangeMode]) {
+        self.psks = psks;
+        self.psk_modes = modes.to_vec();
+    }
+
+    /// Sends every client a session ticket once its handshake is done, which it can use to
+    /// resume the connection for `ticket_lifetime` seconds.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if `ticket_lifetime` is longer than seven days.
+    pub fn enable_resumption(&mut self, ticket_lifetime: u32) {
+        self.tickets = Some(TicketStore::new(ticket_lifetime));
+    }
+
+    /// Makes connections keep a copy of every handshake message, to be returned by
+    /// [`ServerConnection::handshake_messages`] once the handshake is done.
+    pub fn retain_handshake_messages(&mut self) {
+        self.retain_messages = true;
+    }
+
+    /// Requires clients to send certificates, unless they are authenticated by a PSK.
+    ///
+    /// `verify_certs` is given the DER certificates a client sends, leaf first,
+    /// and returns whether to trust them.
+    pub fn require_client_cert(
+        &mut self,
+        verify_certs: impl Fn(&[&[u8]]) -> bool + Send + Sync + 'static,
+    ) {
+        self.client_auth = Some(Box::new(verify_certs));
+    }
+}
+
+/// Called with the server name a client asked for, if any, that the server has no certificate for.
+type UnknownNameFn = dyn Fn(Option<&str>) + Send + Sync;
+
+/// A TLS 1.3 connection to a client.
+pub struct ServerConnection {
+    core: ConnectionCore,
+    handshaker: ServerHandshaker,
+}
+
+struct ServerHandshaker {
+    state: State,
+    config: Arc<ServerConfig>,
+    /// The host name the client sent in its `server_name` extension, in lowercase.
+    server_name: Option<String>,
+}
+
+enum State {
+    WaitClientHello,
+    /// Waiting for the `ClientHello` that answers a `HelloRetryRequest`.
+    WaitSecondClientHello,
+    /// Waiting for the client's `Certificate`, which was asked for.
+    WaitCertificate(ClientFlight),
+    WaitCertificateVerify(ClientFlight),
+    WaitFinished(ClientFlight),
+    Connected(TrafficSecrets),
+    /// The connection failed while a message was being processed.
+    Closed,
+}
+
+/// What is known while the client's encrypted handshake messages are being received.
+struct ClientFlight {
+    secrets: HandshakeSecrets,
+    /// The application secrets, which are already known since they don't depend on the client's
+    /// second flight.
+    application: TrafficSecrets,
+}
+
+impl ServerConnection {
+    /// Creates a connection that waits for a `ClientHello`.
+    pub fn new(config: Arc<ServerConfig>) -> Self {
+        let mut core = ConnectionCore::default();
+        if config.retain_messages {
+            core.transcript.retain_messages(Vec::new());
+        }
+        Self {
+            core,
+            handshaker: ServerHandshaker {
+                state: State::WaitClientHello,
+                config,
+                server_name: None,
+            },
+        }
+    }
+
+    /// Returns whether the handshake is still in progress.
+    pub fn is_handshaking(&self) -> bool {
+        !self.core.is_closed() && !self.handshaker.is_connected()
+    }
+
+    /// Returns whether the connection has been closed, either by an error or by the client.
+    pub fn is_closed(&self) -> bool {
+        self.core.is_closed()
+    }
+
+    /// Returns the public key of the client's certificate, if it sent one.
+    ///
+    /// The client has only proven that it owns the key once the handshake is done. Clients that
+    /// are authenticated by a PSK aren't asked for a certificate.
+    pub fn peer_public_key(&self) -> Option<&PublicKey> {
+        self.core.peer_public_key.as_ref()
+    }
+
+    /// Returns the host name the client asked for in its `server_name` extension, in lowercase,
+    /// once its `ClientHello` has been read.
+    pub fn server_name(&self) -> Option<&str> {
+        self.handshaker.server_name.as_deref()
+    }
+
+    /// Returns every handshake message that was sent or received, in order and as serialized,
+    /// including their headers.
+    ///
+    /// Returns `None` until the handshake is done, or if the [`ServerConfig`] didn't
+    /// [retain them](ServerConfig::retain_handshake_messages). Messages sent after the handshake,
+    /// such as session tickets, aren't included.
+    pub fn handshake_messages(&self) -> Option<&[Vec<u8>]> {
+        self.core.handshake_messages(&self.handshaker)
+    }
+
+    /// Exports `K_LEN` bytes of keying material for the application, as described in RFC 8446
+    /// section 7.5. The client exports the same bytes for the same `label` and `context`.
+    ///
+    /// Returns [`ConnectionError::Handshaking`] until the handshake is done.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if `label` is longer than 249 bytes.
+    pub fn export_keying_material<const K_LEN: usize>(
+        &self,
+        label: &[u8],
+        context: &[u8],
+    ) -> Result<[u8; K_LEN], ConnectionError> {
+        self.core
+            .export_keying_material(&self.handshaker, label, context)
+    }
+
+    /// Takes the bytes that need to be sent to the client.
+    pub fn take_outgoing(&mut self) -> Vec<u8> {
+        self.core.take_outgoing()
+    }
+
+    /// Takes the application data received from the client.
+    pub fn take_received(&mut self) -> Vec<u8> {
+        self.core.take_received()
+    }
+
+    /// Processes the whole records at the front of `buf`, returning how many bytes were used.
+    ///
+    /// The bytes of a record that hasn't been completely received are left for the next call.
+    /// Records are decrypted in place, so `buf` is overwritten.
+    ///
+    /// On failure, the connection is closed and the alert for the error is queued.
+    pub fn read_tls(&mut self, buf: &mut [u8]) -> Result<usize, ConnectionError> {
+        self.core.read_tls(&mut self.handshaker, buf)
+    }
+
+    /// Encrypts `data` and queues it to be sent.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), ConnectionError> {
+        self.core.write(&self.handshaker, data)
+    }
+
+    /// Encrypts `data` and queues it to be sent, padded and split into records as `policy` says,
+    /// to hide its length from anyone watching the connection.
+    ///
+    /// The client removes the padding, so it receives `data` as if it had been written with
+    /// [`ServerConnection::write`].
+    pub fn write_all_padded(
+        &mut self,
+        data: &[u8],
+        policy: PaddingPolicy,
+    ) -> Result<(), ConnectionError> {
+        self.core.write_padded(&self.handshaker, data, policy)
+    }
+}
+
+#[cfg(feature = "dangerous_handoff")]
+impl ServerConnection {
+    /// The version of the format of [`ServerConnection::dangerous_export`].
+    const HANDOFF_VERSION: u8 = 1;
+
+    /// Encodes the traffic secrets and sequence numbers of an established connection, so that
+    /// another process can continue it with [`ServerConnection::dangerous_import`], and closes
+    /// this copy without sending anything.
+    ///
+    /// **The bytes are every key of the connection.** Anyone who reads them can decrypt
+    /// everything sent on it from then on, and impersonate either side. They must only be passed
+    /// to the process that takes over the connection, over a channel nobody else can read, such
+    /// as a Unix socket that the socket of the connection is passed over too. Importing them more
+    /// than once reuses nonces, which breaks the encryption altogether.
+    ///
+    /// Everything queued to be sent must have been taken with
+    /// [`ServerConnection::take_outgoing`] and sent, and everything received must have been taken
+    /// with [`ServerConnection::take_received`]. Bytes read from the socket but not yet passed to
+    /// [`ServerConnection::read_tls`] have to be handed over by the caller. The client's
+    /// certificate, the handshake messages, and the session ticket store aren't part of the
+    /// export.
+    pub fn dangerous_export(&mut self) -> Result<Vec<u8>, HandoffError> {
+        let mut record_layer = Vec::new();
+        self.core
+            .dangerous_export(&self.handshaker, &mut record_layer)?;
+        let State::Connected(secrets) = &self.handshaker.state else {
+            unreachable!("only established connections are exported");
+        };
+        let server_name = self.handshaker.server_name.as_deref().unwrap_or_default();
+
+        let mut bytes = vec![Self::HANDOFF_VERSION];
+        bytes.extend_from_slice(&(CipherSuite::Aes128GcmSha256 as u16).to_be_bytes());
+        bytes.extend_from_slice(&secrets.client);
+        bytes.extend_from_slice(&secrets.server);
+        bytes.extend_from_slice(&record_layer);
+        // host names are at most 255 bytes
+        bytes.push(server_name.len() as u8);
+        bytes.extend_from_slice(server_name.as_bytes());
+        Ok(bytes)
+    }
+
+    /// Continues a connection exported by [`ServerConnection::dangerous_export`], with `config`
+    /// for anything that needs it from then on.
+    ///
+    /// See [`ServerConnection::dangerous_export`] for why the bytes have to be kept secret and
+    /// must only be imported once.
+    pub fn dangerous_import(config: Arc<ServerConfig>, bytes: &[u8]) -> Result<Self, HandoffError> {
+        let mut reader = Reader::new(bytes);
+        if reader.read_u8()? != Self::HANDOFF_VERSION
+            || reader.read_u16()? != CipherSuite::Aes128GcmSha256 as u16
+        {
+            return Err(HandoffError::UnsupportedVersion);
+        }
+        let secrets = TrafficSecrets {
+            client: *reader.read_array()?,
+            server: *reader.read_array()?,
+        };
+        let core = ConnectionCore::dangerous_import(&mut reader, &secrets.client, &secrets.server)?;
+        let server_name = reader.read_u8_prefixed()?.read_rest();
+        reader.finish()?;
+        let server_name = match server_name {
+            [] => None,
+            name if name.is_ascii() => Some(String::from_utf8_lossy(name).into_owned()),
+            _ => return Err(HandoffError::Malformed),
+        };
+        Ok(Self {
+            core,
+            handshaker: ServerHandshaker {
+                state: State::Connected(secrets),
+                config,
+                server_name,
+            },
+        })
+    }
+}
+
+impl Handshaker for ServerHandshaker {
+    fn is_connected(&self) -> bool {
+        matches!(self.state, State::Connected(_))
+    }
+
+    fn read_handshake(
+        &mut self,
+        core: &mut ConnectionCore,
+        msg: &[u8],
+    ) -> Result<(), ConnectionError> {
+        let msg_type = msg[0];
+        self.state = match mem::replace(&mut self.state, State::Closed) {
+            State::WaitClientHello if msg_type == ShakeType::ClientHello as u8 => {
+                self.read_client_hello(core, msg, false)?
+            },
+            State::WaitSecondClientHello if msg_type == ShakeType::ClientHello as u8 => {
+                self.read_client_hello(core, msg, true)?
+            },
+            State::WaitCertificate(flight) if msg_type == ShakeType::Certificate as u8 => {
+                let Some(verify_certs) = &self.config.client_auth else {
+                    unreachable!("certificates are only asked for when they are required");
+                };
+                // the `CertificateRequest` had an empty context
+                if !core.read_certificate(msg, &[], verify_certs)? {
+                    return Err(ConnectionError::CertificateRequired);
+                }
+                State::WaitCertificateVerify(flight)
+            },
+            State::WaitCertificateVerify(flight)
+                if msg_type == ShakeType::CertificateVerify as u8 =>
+            {
+                core.read_certificate_verify(msg, CLIENT_CONTEXT)?;
+                State::WaitFinished(flight)
+            },
+            State::WaitFinished(flight) if msg_type == ShakeType::Finished as u8 => {
+                core.read_finished(msg, &flight.secrets.client)?;
+                core.set_reader_secret(&flight.application.client);
+                if let Some(tickets) = &self.config.tickets {
+                    let resumption_master_secret = flight
+                        .secrets
+                        .master_secret()
+                        .resumption_master_secret(&core.transcript_hash());
+                    let ticket = tickets.issue(&resumption_master_secret)?;
+                    // messages after the handshake aren't part of the transcript
+                    core.send(ContentType::Handshake, &new_session_ticket(&ticket));
+                }
+                State::Connected(flight.application)
+            },
+            State::Connected(mut secrets) if msg_type == ShakeType::KeyUpdate as u8 => {
+                let body = &msg[Handshake::PREFIX_SIZE..];
+                core.read_key_update(body, &mut secrets.server, &mut secrets.client)?;
+                State::Connected(secrets)
+            },
+            _ => return Err(ConnectionError::UnexpectedMessage),
+        };
+        Ok(())
+    }
+}
+
+/// A PSK the client offered that the server accepted.
+struct AcceptedPsk {
+    /// The index of the PSK in the client's `pre_shared_key` extension.
+    index: u16,
+    early_secret: EarlySecret,
+    mode: PskKeyExchangeMode,
+}
+
+impl ServerHandshaker {
+    /// Negotiates the connection with the client's offer, then queues the whole server flight.
+    ///
+    /// `retried` is whether this answers a `HelloRetryRequest`, which is only sent once. Clients
+    /// that still don't send a secp256r1 key share are then turned away.
+    fn read_client_hello(
+        &mut self,
+        core: &mut ConnectionCore,
+        msg: &[u8],
+        retried: bool,
+    ) -> Result<State, ConnectionError> {
+        const SERVER_NAME: u16 = Extension::ServerName as u16;
+        const SUPPORTED_VERSIONS: u16 = Extension::SupportedVersions as u16;
+        const SUPPORTED_GROUPS: u16 = Extension::SupportedGroups as u16;
+        const SIGNATURE_ALGORITHMS: u16 = Extension::SignatureAlgorithms as u16;
+        const KEY_SHARE: u16 = Extension::KeyShare as u16;
+        const PSK_EXCHANGE_MODES: u16 = Extension::PskExchangeModes as u16;
+        const PRE_SHARED_KEY: u16 = Extension::PreSharedKey as u16;
+
+        let client_hello = ClientHelloRef::parse(&msg[Handshake::PREFIX_SIZE..])?;
+
+        let mut seen = Vec::new();
+        let mut server_name = None;
+        let mut versions = None;
+        let mut groups = None;
+        let mut schemes = None;
+        let mut key_shares = None;
+        let mut psk_modes = None;
+        let mut offered_psks = None;
+        let mut extensions = Reader::new(client_hello.extensions);
+        while !extensions.is_empty() {
+            let extension = extensions.read_u16()?;
+            let mut data = extensions.read_u16_prefixed()?;
+            if seen.contains(&extension) {
+                return Err(ConnectionError::IllegalParameter);
+            }
+            seen.push(extension);
+            match extension {
+                SERVER_NAME => server_name = read_server_name(&mut data)?,
+                SUPPORTED_VERSIONS => {
+                    versions = Some(TlsVec::<u8, u16>::decode(&mut data)?.into_inner());
+                },
+                SUPPORTED_GROUPS => {
+                    groups = Some(TlsVec::<u16, u16>::decode(&mut data)?.into_inner());
+                },
+                SIGNATURE_ALGORITHMS => {
+                    schemes = Some(TlsVec::<u16, u16>::decode(&mut data)?.into_inner());
+                },
+                KEY_SHARE => {
+                    key_shares =
+                        Some(TlsVec::<u16, KeyShareEntry>::decode(&mut data)?.into_inner());
+                },
+                PSK_EXCHANGE_MODES => {
+                    psk_modes = Some(TlsVec::<u8, u8>::decode(&mut data)?.into_inner());
+                },
+                PRE_SHARED_KEY => {
+                    // the binders are computed over everything before them, so nothing can follow
+                    if !extensions.is_empty() {
+                        return Err(ConnectionError::IllegalParameter);
+                    }
+                    offered_psks = Some(OfferedPsks::decode(&mut data)?);
+                },
+                // extensions we don't understand are ignored (RFC 8446 section 4.2)
+                _ => continue,
+            }
+            data.finish()?;
+        }
+
+        // SSL 3.0 and earlier are too old to even be negotiated down to (RFC 8446 appendix D.5)
+        if client_hello.legacy_version <= ProtocolVersion::SslThreePointZero as u16 {
+            return Err(ConnectionError::ProtocolVersion);
+        }
+        // without the extension, the client only supports TLS 1.2 or earlier
+        let versions = versions.ok_or(ConnectionError::ProtocolVersion)?;
+        if !versions.contains(&(ProtocolVersion::TlsOnePointThree as u16)) {
+            return Err(ConnectionError::ProtocolVersion);
+        }
+        if !client_hello
+            .cipher_suites
+            .contains(&(CipherSuite::Aes128GcmSha256 as u16))
+        {
+            return Err(ConnectionError::NoCommonCipherSuite);
+        }
+        // TLS 1.3 clients only offer the "null" compression method
+        if client_hello.compression_methods != [0] {
+            return Err(ConnectionError::IllegalParameter);
+        }
+        let client_share = key_shares.as_ref().and_then(|shares| {
+            shares
+                .iter()
+                .find(|share| share.group == NamedGroup::Secp256r1 as u16)
+        });
+        let offered_psk = offered_psks.is_some();
+        let psk = match offered_psks {
+            // the binders of a second `ClientHello` would cover the first one too
+            Some(_) if retried => None,
+            Some(offered) => {
+                // a PSK can't be used without knowing how the client wants to use it
+                let modes = psk_modes.ok_or(ConnectionError::MissingExtension)?;
+                self.select_psk(&offered, &modes, client_share.is_some(), msg)?
+            },
+            None => None,
+        };
+        self.server_name = server_name;
+        let named_cert = self
+            .server_name
+            .as_deref()
+            .and_then(|name| self.config.named_certs.get(name));
+        // a PSK authenticates the server instead of its certificate
+        let cert = match psk {
+            Some(_) => None,
+            None => {
+                let cert = match named_cert {
+                    Some(cert) => cert,
+                    None => self.unknown_name()?,
+                };
+                let schemes = schemes.ok_or(ConnectionError::MissingExtension)?;
+                match cert.key.scheme() {
+                    Some(scheme) if schemes.contains(&(scheme as u16)) => {},
+                    _ => return Err(ConnectionError::NoCommonSignatureScheme),
+                }
+                Some(cert)
+            },
+        };
+
+        let supports_secp256r1 = groups
+            .as_ref()
+            .is_some_and(|groups| groups.contains(&(NamedGroup::Secp256r1 as u16)));
+        if client_share.is_none() && key_shares.is_some() && supports_secp256r1 && psk.is_none() {
+            if retried {
+                // the client had to send the key share that was asked for
+                return Err(ConnectionError::IllegalParameter);
+            }
+            if !offered_psk {
+                return Ok(self.send_hello_retry_request(core, msg, client_hello.session_id));
+            }
+        }
+
+        let (server_share, shared_secret) = match &psk {
+            // `psk_ke` has no shared secret, so zeros take its place (RFC 8446 section 7.1)
+            Some(psk) if psk.mode == PskKeyExchangeMode::PskKe => {
+                (None, Zeroizing::new([0; HASH_LEN]))
+            },
+            _ => {
+                let (Some(_), Some(_)) = (groups, &key_shares) else {
+                    return Err(ConnectionError::MissingExtension);
+                };
+                let client_share = client_share.ok_or(ConnectionError::NoCommonGroup)?;
+                let key = EphemeralKey::generate(NamedGroup::Secp256r1)?;
+                let shared_secret = key
+                    .shared_secret(&client_share.key_exchange)
+                    .ok_or(ConnectionError::IllegalParameter)?;
+                (Some(key.entry()), shared_secret)
+            },
+        };
+        // the client's next messages are encrypted
+        core.check_record_boundary()?;
+        core.transcript.update(msg);
+
+        let server_hello = ServerHello::new(
+            client_hello.session_id,
+            CipherSuite::Aes128GcmSha256,
+            server_share.as_ref(),
+            psk.as_ref().map(|psk| psk.index),
+        )?;
+        core.send_plaintext_handshake(&server_hello);
+        let early_secret = match psk {
+            Some(psk) => psk.early_secret,
+            None => EarlySecret::new(None),
+        };
+        let secrets = early_secret.handshake_secrets(&*shared_secret, &core.transcript_hash());
+        core.set_reader_secret(&secrets.client);
+        core.set_writer_secret(&secrets.server);
+        // the name is only acknowledged if it was used to pick the certificate
+        let name_used = cert.is_some() && named_cert.is_some();
+        self.send_flight(core, secrets, cert, name_used)
+    }
+
+    /// Queues a `HelloRetryRequest` that asks the client for a secp256r1 key share, in response to
+    /// the `ClientHello` `msg`.
+    fn send_hello_retry_request(
+        &self,
+        core: &mut ConnectionCore,
+        msg: &[u8],
+        session_id: &[u8],
+    ) -> State {
+        core.transcript.update(msg);
+        // the first `ClientHello` is only remembered by its hash (RFC 8446 section 4.4.1)
+        core.transcript.replace_with_message_hash();
+        let hello_retry_request = ServerHello::hello_retry_request(
+            session_id,
+            CipherSuite::Aes128GcmSha256,
+            NamedGroup::Secp256r1,
+        );
+        core.send_plaintext_handshake(&hello_retry_request);
+        State::WaitSecondClientHello
+    }
+
+    /// Returns the default certificate for a client whose server name has none of its own, after
+    /// logging the name, unless the configuration's policy rejects the client.
+    fn unknown_name(&self) -> Result<&CertifiedKey, ConnectionError> {
+        let config = &*self.config;
+        let server_name = self.server_name.as_deref();
+        if let Some(log) = &config.on_unknown_name {
+            log(server_name);
+        }
+        if config.unknown_names.rejects(server_name) {
+            return Err(ConnectionError::UnrecognizedName);
+        }
+        config.cert.as_ref().ok_or(ConnectionError::PskRequired)
+    }
+
+    /// Picks the first PSK the client offered that the server can use, along with the first of
+    /// the server's key exchanges that the client offered it for.
+    ///
+    /// `has_key_share` is whether the client sent a secp256r1 key share, without which
+    /// `psk_dhe_ke` can't be used. `msg` is the whole `ClientHello`, whose binder for the chosen
+    /// PSK is checked. Returns `None` if no PSK can be used.
+    fn select_psk(
+        &self,
+        offered: &OfferedPsks,
+        client_modes: &[u8],
+        has_key_share: bool,
+        msg: &[u8],
+    ) -> Result<Option<AcceptedPsk>, ConnectionError> {
+        if offered.binders.len() != offered.identities.len() {
+            return Err(ConnectionError::IllegalParameter);
+        }
+        let mode = self.config.psk_modes.iter().copied().find(|&mode| {
+            client_modes.contains(&(mode as u8))
+                && (mode == PskKeyExchangeMode::PskKe || has_key_share)
+        });
+        let Some(mode) = mode else {
+            return Ok(None);
+        };
+        let selected = offered
+            .identities
+            .iter()
+            .enumerate()
+            .find_map(|(i, identity)| self.find_psk(identity).map(|found| (i, found)));
+        let Some((index, (early_secret, external))) = selected else {
+            return Ok(None);
+        };
+
+        // the binders end the message, and are computed over everything before them
+        let partial_client_hello = &msg[..msg.len() - offered.binders_len()];
+        psk::verify_binder(
+            &early_secret.binder_key(external),
+            partial_client_hello,
+            offered.binders[index],
+        )
+        .map_err(|_| ConnectionError::BadBinder)?;
+        // the ticket is only used up once the client has proven it knows its PSK, since anyone who
+        // saw the ticket on the wire could otherwise burn it
+        if !external {
+            let identity = offered.identities[index].identity;
+            let redeemed = self
+                .config
+                .tickets
+                .as_ref()
+                .is_some_and(|tickets| tickets.redeem(identity));
+            // another connection redeemed the ticket first
+            if !redeemed {
+                return Ok(None);
+            }
+        }
+        Ok(Some(AcceptedPsk {
+            index: index as u16,
+            early_secret,
+            mode,
+        }))
+    }
+
+    /// Looks for the PSK the client offered as `offered`, first among the external PSKs and then
+    /// among the session tickets, returning its early secret and whether it is external.
+    ///
+    /// Tickets that are found aren't redeemed yet, since the binder hasn't been checked.
+    fn find_psk(&self, offered: &PskIdentity) -> Option<(EarlySecret, bool)> {
+        let external = self.config.psks.iter().find(|psk| {
+            // the cipher suite is always `TLS_AES_128_GCM_SHA256`
+            psk.identity() == offered.identity && psk.hash() == PskHash::Sha256
+        });
+        if let Some(psk) = external {
+            return Some((psk.early_secret(), true));
+        }
+        let psk = self.config.tickets.as_ref()?.find(offered)?;
+        Some((EarlySecret::new(Some(&*psk)), false))
+    }
+
+    /// Queues the server's encrypted handshake messages, from `EncryptedExtensions` to `Finished`.
+    ///
+    /// `cert` is the certificate that authenticates the server, or `None` if a PSK does. Clients
+    /// are only asked for certificates of their own in the first case. If `name_used`, the
+    /// client's `server_name` picked the certificate, which is acknowledged with an empty
+    /// `server_name` extension (RFC 6066 section 3).
+    fn send_flight(
+        &self,
+        core: &mut ConnectionCore,
+        secrets: HandshakeSecrets,
+        cert: Option<&CertifiedKey>,
+        name_used: bool,
+    ) -> Result<State, ConnectionError> {
+        // none of the other extensions that are understood have a response here
+        let encrypted_extensions: &[u8] = match name_used {
+            true => &[0x00, 0x04, 0x00, 0x00, 0x00, 0x00],
+            false => &[0x00, 0x00],
+        };
+        core.send_handshake(&handshake_message(
+            ShakeType::EncryptedExtensions,
+            encrypted_extensions,
+        ));
+        let client_auth = cert.is_some() && self.config.client_auth.is_some();
+        if client_auth {
+            core.send_handshake(&certificate_request());
+        }
+        if let Some(cert) = cert {
+            core.send_certificate(&[], &cert.cert_chain);
+            core.send_certificate_verify(&cert.key, SERVER_CONTEXT)?;
+        }
+        core.send_finished(&secrets.server);
+
+        let transcript_hash = core.transcript_hash();
+        let application = secrets.application_secrets(&transcript_hash);
+        core.set_writer_secret(&application.server);
+        let exporter_master_secret = secrets
+            .master_secret()
+            .exporter_master_secret(&transcript_hash);
+        core.exporter_master_secret = Some(Zeroizing::new(exporter_master_secret));
+        let flight = ClientFlight {
+            secrets,
+            application,
+        };
+        Ok(match client_auth {
+            true => State::WaitCertificate(flight),
+            false => State::WaitFinished(flight),
+        })
+    }
+}
+
+/// Reads the `ServerNameList` of a `server_name` extension (RFC 6066 section 3), returning its
+/// host name in lowercase.
+///
+/// Names of types other than `host_name` are skipped. Host names have to be ASCII, since
+/// internationalized names are sent as A-labels.
+fn read_server_name(data: &mut Reader) -> Result<Option<String>, ConnectionError> {
+    const HOST_NAME: u8 = 0;
+
+    let mut names = data.read_u16_prefixed()?;
+    if names.is_empty() {
+        return Err(ConnectionError::Decode);
+    }
+    let mut host_name = None;
+    while !names.is_empty() {
+        let name_type = names.read_u8()?;
+        let name = names.read_u16_prefixed()?.read_rest();
+        if name_type != HOST_NAME {
+            continue;
+        }
+        // there can only be one name of each type
+        if host_name.is_some() || name.is_empty() || !name.is_ascii() {
+            return Err(ConnectionError::IllegalParameter);
+        }
+        let name = std::str::from_utf8(name).expect("ASCII is UTF-8");
+        host_name = Some(name.to_ascii_lowercase());
+    }
+    Ok(host_name)
+}
+
+/// Returns a `CertificateRequest` message that asks for a certificate whose signatures can be
+/// verified.
+fn certificate_request() -> Vec<u8> {
+    let mut extensions = Extension::SignatureAlgorithms.to_be_bytes().to_vec();
+    extensions.extend_from_slice(&[0x00, 0x04, 0x00, 0x02]);
+    extensions.extend_from_slice(&(SignatureScheme::EcdsaSecp256r1Sha256 as u16).to_be_bytes());
+
+    // the context is only needed for post-handshake authentication
+    let mut body = vec![0];
+    body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+    body.extend_from_slice(&extensions);
+    handshake_message(ShakeType::CertificateRequest, &body)
+}
+
+/// Returns the `NewSessionTicket` message for `ticket`.
+fn new_session_ticket(ticket: &NewSessionTicket) -> Vec<u8> {
+    let mut buf = [0; 64];
+    let mut writer = Writer::new(&mut buf);
+    ticket
+        .encode(&mut writer)
+        .expect("tickets fit in the buffer");
+    handshake_message(ShakeType::NewSessionTicket, writer.written())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+    use std::sync::{Arc, Mutex};
+
+    use super::{ServerConfig, ServerConnection};
+    use crate::alert::AlertDescription;
+    use crate::cipher_suites::NamedGroup;
+    use crate::client::ClientConnection;
+    use crate::codec::Reader;
+    use crate::connection::tests::{cert_key, CERT};
+    use crate::connection::{handshake_message, ChannelBindingError, ConnectionError};
+    use crate::extensions::Extension;
+    use crate::handshake::{Handshake, ShakeType};
+    use crate::key_schedule::EarlySecret;
+    use crate::key_share::EphemeralKey;
+    use crate::padding::PaddingPolicy;
+    use crate::private_key::{EcPrivateKey, PrivateKey};
+    use crate::psk::{self, ExternalPsk, PskHash, PskIdentity, PskKeyExchangeMode, PskOffer};
+    use crate::public_key::PublicKey;
+    use crate::record::{ContentType, Message};
+    use crate::server_hello::ServerHelloRef;
+    use crate::signature::SignatureError;
+    use crate::sni::{CertifiedKeySet, UnknownNamePolicy};
+    use crate::test_util::handshake as connect;
+    use crate::ticket::SessionTicket;
+    use crylib::big_int::UBigInt;
+    use crylib::finite_field::FieldElement;
+    use crylib::hash::{Hasher, Sha256};
+
+    fn config() -> ServerConfig {
+        ServerConfig::new(vec![CERT.to_vec()], cert_key())
+    }
+
+    fn client() -> ClientConnection {
+        ClientConnection::new(|certs| certs == [&CERT[..]]).unwrap()
+    }
+
+    /// Returns the record of a `ClientHello` offering `cipher_suite` and `session_id`, with the
+    /// extensions `extensions`, each given as its type and data.
+    fn client_hello(cipher_suite: u16, session_id: &[u8], extensions: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut body = vec![0x03, 0x03];
+        body.extend_from_slice(&[0x42; 32]);
+        body.push(session_id.len() as u8);
+        body.extend_from_slice(session_id);
+        body.extend_from_slice(&[0x00, 0x02]);
+        body.extend_from_slice(&cipher_suite.to_be_bytes());
+        body.extend_from_slice(&[0x01, 0x00]);
+
+        let mut encoded = Vec::new();
+        for (extension, data) in extensions {
+            encoded.extend_from_slice(&extension.to_be_bytes());
+            encoded.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            encoded.extend_from_slice(data);
+        }
+        body.extend_from_slice(&(encoded.len() as u16).to_be_bytes());
+        body.extend_from_slice(&encoded);
+
+        let mut record = Message::start(ContentType::Handshake);
+        record.extend_from_slice(&handshake_message(ShakeType::ClientHello, &body));
+        record.finish();
+        record.to_vec()
+    }
+
+    /// Returns the data of a `key_share` extension holding a new secp256r1 key share.
+    fn key_share() -> Vec<u8> {
+        let key = EphemeralKey::generate(NamedGroup::Secp256r1).unwrap();
+        let mut data = vec![0x00, 0x45];
+        data.extend_from_slice(&(NamedGroup::Secp256r1 as u16).to_be_bytes());
+        data.extend_from_slice(&[0x00, 0x41]);
+        data.extend_from_slice(&key.entry().key_exchange);
+        data
+    }
+
+    #[test]
+    fn handshake() {
+        let mut client = client();
+        let mut server = ServerConnection::new(Arc::new(config()));
+        assert!(server.is_handshaking());
+        assert_eq!(server.write(b"hello"), Err(ConnectionError::Handshaking));
+        assert_eq!(
+            server.export_keying_material::<32>(b"EXPORTER-test", b""),
+            Err(ConnectionError::Handshaking)
+        );
+
+        assert_eq!(connect(&mut client, &mut server), Ok(()));
+        assert!(!client.is_handshaking());
+        assert!(!server.is_handshaking());
+        assert_eq!(
+            client.peer_public_key(),
+            Some(&PublicKey::from_spki(&CERT[97..][..91]).unwrap())
+        );
+        assert_eq!(server.peer_public_key(), None);
+
+        // both sides export the same keying material, which depends on the label and context
+        let exported = client.export_keying_material::<32>(b"EXPORTER-test", b"context");
+        assert!(exported.is_ok());
+        assert_eq!(
+            server.export_keying_material::<32>(b"EXPORTER-test", b"context"),
+            exported
+        );
+        assert_ne!(
+            server.export_keying_material::<32>(b"EXPORTER-test", b""),
+            exported
+        );
+
+        client.write(b"hello").unwrap();
+        let mut record = client.take_outgoing();
+        assert_eq!(server.read_tls(&mut record), Ok(record.len()));
+        assert_eq!(server.take_received(), b"hello");
+
+        server.write(b"world").unwrap();
+        let mut record = server.take_outgoing();
+        assert_eq!(client.read_tls(&mut record), Ok(record.len()));
+        assert_eq!(client.take_received(), b"world");
+    }
+
+    #[test]
+    fn named_certs() {
+        // the chains are told apart by their length
+        let mut certs = CertifiedKeySet::new();
+        certs.add("*.example.com", vec![CERT.to_vec(); 2], cert_key());
+        certs.add("Example.com", vec![CERT.to_vec(); 3], cert_key());
+        let mut config = config();
+        config.set_named_certs(certs);
+        let config = Arc::new(config);
+
+        let cases = [
+            (Some("www.EXAMPLE.com"), 2),
+            (Some("example.com"), 3),
+            (Some("a.b.example.com"), 1),
+            (Some("example.org"), 1),
+            (None, 1),
+        ];
+        for (server_name, chain_len) in cases {
+            let verify_certs = move |certs: &[&[u8]]| certs.len() == chain_len;
+            let mut client = match server_name {
+                Some(name) => ClientConnection::with_server_name(name, verify_certs).unwrap(),
+                None => ClientConnection::new(verify_certs).unwrap(),
+            };
+            let mut server = ServerConnection::new(config.clone());
+            // the client accepts the acknowledgement of the name, since it sent one
+            assert_eq!(connect(&mut client, &mut server), Ok(()));
+            assert_eq!(
+                server.server_name(),
+                server_name.map(str::to_ascii_lowercase).as_deref()
+            );
+        }
+    }
+
+    #[test]
+    fn server_name_extension() {
+        let key_share = key_share();
+        let extensions = |server_name: &'static [u8]| -> [(u16, &[u8]); 5] {
+            [
+                (SERVER_NAME, server_name),
+                (SUPPORTED_VERSIONS, &[0x02, 0x03, 0x04]),
+                (SUPPORTED_GROUPS, &[0x00, 0x02, 0x00, 0x17]),
+                (SIGNATURE_ALGORITHMS, &[0x00, 0x02, 0x04, 0x03]),
+                (KEY_SHARE, &key_share),
+            ]
+        };
+        // names of other types are skipped
+        let mut record = client_hello(
+            0x1301,
+            &[],
+            &extensions(b"\x00\x0a\x01\x00\x01x\x00\x00\x03A.b"),
+        );
+        let mut server = ServerConnection::new(Arc::new(config()));
+        assert_eq!(server.read_tls(&mut record), Ok(record.len()));
+        assert_eq!(server.server_name(), Some("a.b"));
+
+        let failures: [(&[u8], _); 4] = [
+            (b"\x00\x00", ConnectionError::Decode),
+            (b"\x00\x03\x00\x00\x00", ConnectionError::IllegalParameter),
+            (
+                b"\x00\x08\x00\x00\x01a\x00\x00\x01b",
+                ConnectionError::IllegalParameter,
+            ),
+            (
+                b"\x00\x04\x00\x00\x01\xff",
+                ConnectionError::IllegalParameter,
+            ),
+        ];
+        for (server_name, err) in failures {
+            let mut record = client_hello(0x1301, &[], &extensions(server_name));
+            let mut server = ServerConnection::new(Arc::new(config()));
+            assert_eq!(server.read_tls(&mut record), Err(err));
+        }
+    }
+
+    #[test]
+    fn unknown_name_policy() {
+        let logged = Arc::new(Mutex::new(Vec::new()));
+        let config = |policy| {
+            let mut certs = CertifiedKeySet::new();
+            certs.add("example.com", vec![CERT.to_vec()], cert_key());
+            let mut config = config();
+            config.set_named_certs(certs);
+            config.set_unknown_name_policy(policy);
+            let logged = Arc::clone(&logged);
+            config.on_unknown_name(move |name| {
+                logged.lock().unwrap().push(name.map(str::to_string));
+            });
+            Arc::new(config)
+        };
+        // whether clients without a name, and with a name that has no certificate, are rejected
+        let cases = [
+            (UnknownNamePolicy::ServeDefault, false, false),
+            (UnknownNamePolicy::RejectUnknown, false, true),
+            (UnknownNamePolicy::RequireKnown, true, true),
+        ];
+        for (policy, without_name, unknown_name) in cases {
+            let config = config(policy);
+            for (server_name, rejected) in [(None, without_name), (Some("Other.com"), unknown_name)]
+            {
+                let mut client = match server_name {
+                    Some(name) => ClientConnection::with_server_name(name, |_| true).unwrap(),
+                    None => ClientConnection::new(|_| true).unwrap(),
+                };
+                let mut server = ServerConnection::new(Arc::clone(&config));
+                if !rejected {
+                    assert_eq!(connect(&mut client, &mut server), Ok(()));
+                    continue;
+                }
+                assert_eq!(
+                    connect(&mut client, &mut server),
+                    Err(ConnectionError::UnrecognizedName)
+                );
+                // the server's error stopped the pump before its alert was delivered
+                let mut alert = server.take_outgoing();
+                assert_eq!(
+                    client.read_tls(&mut alert),
+                    Err(ConnectionError::PeerAlert(
+                        AlertDescription::UnrecognizedName as u8
+                    ))
+                );
+            }
+            // a name that has a certificate is neither rejected nor logged
+            let mut client = ClientConnection::with_server_name("example.com", |_| true).unwrap();
+            let mut server = ServerConnection::new(config);
+            assert_eq!(connect(&mut client, &mut server), Ok(()));
+            assert_eq!(
+                mem::take(&mut *logged.lock().unwrap()),
+                [None, Some("other.com".to_string())]
+            );
+        }
+
+        // clients authenticated by a PSK don't need a certificate, so their name isn't checked
+        let modes = [PskKeyExchangeMode::PskDheKe];
+        let mut psk_config = ServerConfig::with_psks(vec![psk(b"client1", 2)], &modes);
+        psk_config.set_unknown_name_policy(UnknownNamePolicy::RequireKnown);
+        let psk = Arc::new(psk(b"client1", 2));
+        let mut client = ClientConnection::with_psk(psk, &modes, |_| false).unwrap();
+        let mut server = ServerConnection::new(Arc::new(psk_config));
+        assert_eq!(connect(&mut client, &mut server), Ok(()));
+    }
+
+    #[test]
+    #[cfg(feature = "dangerous_handoff")]
+    fn handoff() {
+        use crate::connection::HandoffError;
+
+        let config = Arc::new(config());
+        let mut client =
+            ClientConnection::with_server_name("localhost", |certs| certs == [&CERT[..]]).unwrap();
+        let mut server = ServerConnection::new(config.clone());
+        assert_eq!(
+            server.dangerous_export().err(),
+            Some(HandoffError::Handshaking)
+        );
+        assert_eq!(connect(&mut client, &mut server), Ok(()));
+        let exported = server.export_keying_material::<16>(b"label", b"");
+
+        // use a few sequence numbers in each direction first
+        for _ in 0..3 {
+            server.write(b"before").unwrap();
+            client.write(b"before").unwrap();
+        }
+        let mut to_client = server.take_outgoing();
+        client.read_tls(&mut to_client).unwrap();
+        let mut to_server = client.take_outgoing();
+        server.read_tls(&mut to_server).unwrap();
+        assert_eq!(server.dangerous_export().err(), Some(HandoffError::Pending));
+        assert_eq!(server.take_received(), b"beforebeforebefore");
+        assert_eq!(client.take_received(), b"beforebeforebefore");
+
+        let bytes = server.dangerous_export().unwrap();
+        // the old copy can't use the keys anymore
+        assert!(server.is_closed());
+        assert_eq!(server.write(b"after"), Err(ConnectionError::Closed));
+        assert_eq!(server.dangerous_export().err(), Some(HandoffError::Closed));
+
+        let mut server = ServerConnection::dangerous_import(config.clone(), &bytes).unwrap();
+        assert!(!server.is_handshaking());
+        assert_eq!(server.server_name(), Some("localhost"));
+        assert_eq!(server.export_keying_material::<16>(b"label", b""), exported);
+        client.write(b"to server").unwrap();
+        let mut to_server = client.take_outgoing();
+        server.read_tls(&mut to_server).unwrap();
+        assert_eq!(server.take_received(), b"to server");
+        server.write(b"to client").unwrap();
+        let mut to_client = server.take_outgoing();
+        client.read_tls(&mut to_client).unwrap();
+        assert_eq!(client.take_received(), b"to client");
+
+        let mut version = bytes.clone();
+        version[0] = 2;
+        let mut name = bytes.clone();
+        *name.last_mut().unwrap() = 0xff;
+        let cases = [
+            (&version[..], HandoffError::UnsupportedVersion),
+            (&name, HandoffError::Malformed),
+            (&bytes[..bytes.len() - 1], HandoffError::Malformed),
+        ];
+        for (bytes, err) in cases {
+            assert_eq!(
+                ServerConnection::dangerous_import(config.clone(), bytes).err(),
+                Some(err)
+            );
+        }
+    }
+
+    #[test]
+    fn channel_binding() {
+        let mut client = client();
+        let mut server = ServerConnection::new(Arc::new(config()));
+        assert_eq!(
+            client.tls_server_end_point(),
+            Err(ChannelBindingError::Handshaking)
+        );
+        assert_eq!(connect(&mut client, &mut server), Ok(()));
+        // `CERT` is signed with ECDSA and SHA-256
+        assert_eq!(
+            client.tls_server_end_point(),
+            Ok(Sha256::hash(&CERT).to_vec())
+        );
+
+        // a server authenticated by a PSK has no certificate to bind to
+        let modes = [PskKeyExchangeMode::PskDheKe];
+        let psk_config = ServerConfig::with_psks(vec![psk(b"client1", 2)], &modes);
+        let psk = Arc::new(psk(b"client1", 2));
+        let mut client = ClientConnection::with_psk(psk, &modes, |_| false).unwrap();
+        let mut server = ServerConnection::new(Arc::new(psk_config));
+        assert_eq!(connect(&mut client, &mut server), Ok(()));
+        assert_eq!(
+            client.tls_server_end_point(),
+            Err(ChannelBindingError::NoCertificate)
+        );
+
+        // nor does one whose certificate was rejected
+        let mut client = ClientConnection::new(|_| false).unwrap();
+        let mut server = ServerConnection::new(Arc::new(config()));
+        assert_eq!(
+            connect(&mut client, &mut server),
+            Err(ConnectionError::CertificateRejected)
+        );
+        assert_eq!(
+            client.tls_server_end_point(),
+            Err(ChannelBindingError::Handshaking)
+        );
+    }
+
+    #[test]
+    fn handshake_messages() {
+        let mut retaining_config = config();
+        retaining_config.retain_handshake_messages();
+        let mut retaining_client = client();
+        retaining_client.retain_handshake_messages();
+        let mut server = ServerConnection::new(Arc::new(retaining_config));
+        assert_eq!(retaining_client.handshake_messages(), None);
+
+        assert_eq!(connect(&mut retaining_client, &mut server), Ok(()));
+        let messages = server.handshake_messages().unwrap();
+        assert_eq!(retaining_client.handshake_messages(), Some(messages));
+        let types: Vec<_> = messages.iter().map(|msg| msg[0]).collect();
+        assert_eq!(
+            types,
+            [
+                ShakeType::ClientHello,
+                ShakeType::ServerHello,
+                ShakeType::EncryptedExtensions,
+                ShakeType::Certificate,
+                ShakeType::CertificateVerify,
+                ShakeType::Finished,
+                ShakeType::Finished,
+            ]
+            .map(|msg_type| msg_type as u8)
+        );
+
+        // without being asked to, neither side keeps them
+        let mut default_client = client();
+        let mut default_server = ServerConnection::new(Arc::new(config()));
+        assert_eq!(connect(&mut default_client, &mut default_server), Ok(()));
+        assert_eq!(default_client.handshake_messages(), None);
+        assert_eq!(default_server.handshake_messages(), None);
+    }
+
+    #[test]
+    fn client_auth() {
+        let mut config = config();
+        config.require_client_cert(|certs| certs == [&CERT[..]]);
+        let config = Arc::new(config);
+
+        let mut trusted = client();
+        trusted.set_client_cert(vec![CERT.to_vec()], cert_key());
+        let mut server = ServerConnection::new(config.clone());
+        assert_eq!(connect(&mut trusted, &mut server), Ok(()));
+        assert!(!server.is_handshaking());
+        assert_eq!(
+            server.peer_public_key(),
+            Some(&PublicKey::from_spki(&CERT[97..][..91]).unwrap())
+        );
+
+        // the client has to prove that it owns the key of its certificate
+        let mut impostor = client();
+        let other_key = PrivateKey::Ec(EcPrivateKey::Secp256r1(FieldElement::new(UBigInt([
+            1, 2, 3, 4,
+        ]))));
+        impostor.set_client_cert(vec![CERT.to_vec()], other_key);
+        let mut server = ServerConnection::new(config.clone());
+        assert_eq!(
+            connect(&mut impostor, &mut server),
+            Err(ConnectionError::Signature(SignatureError::BadSignature))
+        );
+
+        // and to send a certificate at all
+        let mut anonymous = client();
+        let mut server = ServerConnection::new(config);
+        assert_eq!(
+            connect(&mut anonymous, &mut server),
+            Err(ConnectionError::CertificateRequired)
+        );
+        let mut alert = server.take_outgoing();
+        assert_eq!(
+            anonymous.read_tls(&mut alert),
+            Err(ConnectionError::PeerAlert(116))
+        );
+    }
+
+    #[test]
+    fn rejected_client_cert() {
+        let mut config = config();
+        config.require_client_cert(|_| false);
+        let mut client = client();
+        client.set_client_cert(vec![CERT.to_vec()], cert_key());
+        let mut server = ServerConnection::new(Arc::new(config));
+        assert_eq!(
+            connect(&mut client, &mut server),
+            Err(ConnectionError::CertificateRejected)
+        );
+    }
+
+    fn psk(identity: &[u8], key: u8) -> ExternalPsk {
+        ExternalPsk::new(identity.to_vec(), vec![key; 32], PskHash::Sha256).unwrap()
+    }
+
+    /// Returns the data of the extension `extension` of the `ServerHello` that starts `flight`.
+    fn server_hello_extension(flight: &[u8], extension: u16) -> Option<Vec<u8>> {
+        let server_hello = &flight[Message::PREFIIX_SIZE..];
+        let len = u32::from_be_bytes([0, server_hello[1], server_hello[2], server_hello[3]]);
+        let body = &server_hello[Handshake::PREFIX_SIZE..][..len as usize];
+        let server_hello = ServerHelloRef::parse_from_handshake(body).unwrap();
+        let mut extensions = Reader::new(server_hello.extensions);
+        while !extensions.is_empty() {
+            let found = extensions.read_u16().unwrap() == extension;
+            let data = extensions.read_u16_prefixed().unwrap();
+            if found {
+                return Some(data.read_rest().to_vec());
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn psk_handshake() {
+        use PskKeyExchangeMode::{PskDheKe, PskKe};
+
+        let cases: [(&[_], &[_], bool); 3] = [
+            (&[PskDheKe, PskKe], &[PskDheKe, PskKe], true),
+            // the server's preference wins
+            (&[PskKe, PskDheKe], &[PskDheKe, PskKe], false),
+            (&[PskDheKe, PskKe], &[PskKe], false),
+        ];
+        for (server_modes, client_modes, dhe) in cases {
+            let psks = vec![psk(b"other", 1), psk(b"client1", 2)];
+            let config = Arc::new(ServerConfig::with_psks(psks, server_modes));
+            let psk = Arc::new(psk(b"client1", 2));
+            let mut client = ClientConnection::with_psk(psk, client_modes, |_| false).unwrap();
+            let mut server = ServerConnection::new(config);
+
+            let mut client_hello = client.take_outgoing();
+            assert_eq!(server.read_tls(&mut client_hello), Ok(client_hello.len()));
+            let mut flight = server.take_outgoing();
+            assert_eq!(server_hello_extension(&flight, KEY_SHARE).is_some(), dhe);
+            assert_eq!(client.read_tls(&mut flight), Ok(flight.len()));
+            let mut flight = client.take_outgoing();
+            assert_eq!(server.read_tls(&mut flight), Ok(flight.len()));
+            assert!(!client.is_handshaking());
+            assert!(!server.is_handshaking());
+            // the PSK authenticated both sides, so neither sent a certificate
+            assert_eq!(client.peer_public_key(), None);
+            assert_eq!(server.peer_public_key(), None);
+
+            client.write(b"hello").unwrap();
+            let mut record = client.take_outgoing();
+            assert_eq!(server.read_tls(&mut record), Ok(record.len()));
+            assert_eq!(server.take_received(), b"hello");
+        }
+    }
+
+    #[test]
+    fn psk_with_cert() {
+        let mut config = config();
+        config.require_client_cert(|certs| certs == [&CERT[..]]);
+        config.set_psks(vec![psk(b"client1", 2)], &[PskKeyExchangeMode::PskDheKe]);
+        let config = Arc::new(config);
+        let modes = [PskKeyExchangeMode::PskDheKe];
+
+        // clients with the PSK aren't asked for a certificate
+        let mut client =
+            ClientConnection::with_psk(Arc::new(psk(b"client1", 2)), &modes, |_| false).unwrap();
+        let mut server = ServerConnection::new(config.clone());
+        assert_eq!(connect(&mut client, &mut server), Ok(()));
+        assert_eq!(client.peer_public_key(), None);
+
+        // and other clients fall back to certificates
+        let unknown = Arc::new(psk(b"client2", 2));
+        let mut client =
+            ClientConnection::with_psk(unknown, &modes, |certs| certs == [&CERT[..]]).unwrap();
+        client.set_client_cert(vec![CERT.to_vec()], cert_key());
+        let mut server = ServerConnection::new(config);
+        assert_eq!(connect(&mut client, &mut server), Ok(()));
+        assert!(client.peer_public_key().is_some());
+        assert!(server.peer_public_key().is_some());
+    }
+
+    #[test]
+    fn rejected_psk() {
+        use PskKeyExchangeMode::{PskDheKe, PskKe};
+
+        let psks = vec![psk(b"client1", 2)];
+        let config = Arc::new(ServerConfig::with_psks(psks, &[PskDheKe]));
+        let failures = [
+            // the right identity with the wrong key
+            (
+                Some((psk(b"client1", 3), PskDheKe)),
+                ConnectionError::BadBinder,
+            ),
+            (
+                Some((psk(b"client2", 2), PskDheKe)),
+                ConnectionError::PskRequired,
+            ),
+            // a key exchange the server doesn't allow
+            (
+                Some((psk(b"client1", 2), PskKe)),
+                ConnectionError::PskRequired,
+            ),
+            (None, ConnectionError::PskRequired),
+        ];
+        for (psk, err) in failures {
+            let mut client = match psk {
+                Some((psk, mode)) => {
+                    ClientConnection::with_psk(Arc::new(psk), &[mode], |_| false).unwrap()
+                },
+                None => client(),
+            };
+            let mut server = ServerConnection::new(config.clone());
+            let mut client_hello = client.take_outgoing();
+            assert_eq!(server.read_tls(&mut client_hello), Err(err));
+        }
+    }
+
+    /// Returns the record of a `ClientHello` that offers the PSKs in `offered`, whose binders are
+    /// computed with the binder keys of the offers.
+    fn resumption_client_hello(offered: &[PskOffer]) -> Vec<u8> {
+        let mut identities = Vec::new();
+        let mut binders = Vec::new();
+        for offer in offered {
+            let identity = offer.identity.identity;
+            identities.extend_from_slice(&(identity.len() as u16).to_be_bytes());
+            identities.extend_from_slice(identity);
+            identities.extend_from_slice(&offer.identity.obfuscated_ticket_age.to_be_bytes());
+            binders.push(0x20);
+            binders.extend_from_slice(&[0; 32]);
+        }
+        let mut offered_psks = (identities.len() as u16).to_be_bytes().to_vec();
+        offered_psks.extend_from_slice(&identities);
+        offered_psks.extend_from_slice(&(binders.len() as u16).to_be_bytes());
+        offered_psks.extend_from_slice(&binders);
+
+        let key_share = key_share();
+        let mut record = client_hello(
+            0x1301,
+            &[],
+            &[
+                (SUPPORTED_VERSIONS, &[0x02, 0x03, 0x04]),
+                (SUPPORTED_GROUPS, &[0x00, 0x02, 0x00, 0x17]),
+                (SIGNATURE_ALGORITHMS, &[0x00, 0x02, 0x04, 0x03]),
+                (KEY_SHARE, &key_share),
+                (PSK_EXCHANGE_MODES, &[0x01, 0x01]),
+                (PRE_SHARED_KEY, &offered_psks),
+            ],
+        );
+        let binders_start = record.len() - binders.len();
+        let partial_client_hello = record[Message::PREFIIX_SIZE..binders_start - 2].to_vec();
+        for (i, offer) in offered.iter().enumerate() {
+            let binder = psk::binder(&offer.binder_key, &partial_client_hello);
+            record[binders_start + 33 * i + 1..][..32].copy_from_slice(&binder);
+        }
+        record
+    }
+
+    /// Runs a full handshake with a server using `config`, returning the ticket the client is
+    /// sent afterwards.
+    fn first_ticket(config: &Arc<ServerConfig>) -> SessionTicket {
+        let mut client = client();
+        let mut server = ServerConnection::new(config.clone());
+        assert_eq!(connect(&mut client, &mut server), Ok(()));
+        let mut ticket = server.take_outgoing();
+        assert_eq!(client.read_tls(&mut ticket), Ok(ticket.len()));
+        let mut tickets = client.take_tickets();
+        assert_eq!(tickets.len(), 1);
+        tickets.pop().unwrap()
+    }
+
+    #[test]
+    fn padded_writes() {
+        let mut client = client();
+        let mut server = ServerConnection::new(Arc::new(config()));
+        assert_eq!(
+            server.write_all_padded(b"hello", PaddingPolicy::FULL),
+            Err(ConnectionError::Handshaking)
+        );
+        assert_eq!(connect(&mut client, &mut server), Ok(()));
+
+        // the header, the content and padding, the content type, and the tag
+        for (policy, len) in [
+            (PaddingPolicy::NONE, 5 + 5 + 1 + 16),
+            (PaddingPolicy::block(256), 5 + 256 + 1 + 16),
+            (PaddingPolicy::FULL, 5 + 0x4000 + 1 + 16),
+        ] {
+            client.write_all_padded(b"hello", policy).unwrap();
+            let mut record = client.take_outgoing();
+            assert_eq!(record.len(), len);
+            assert_eq!(server.read_tls(&mut record), Ok(len));
+            assert_eq!(server.take_received(), b"hello");
+        }
+
+        let data = vec![0x42; 0x10000];
+        let policy = PaddingPolicy::block(16).with_random_splits();
+        server.write_all_padded(&data, policy).unwrap();
+        let mut records = server.take_outgoing();
+        assert_eq!(client.read_tls(&mut records), Ok(records.len()));
+        assert_eq!(client.take_received(), data);
+    }
+
+    #[test]
+    fn resumption() {
+        let mut config = config();
+        config.enable_resumption(3600);
+        let config = Arc::new(config);
+
+        // the ticket from the first handshake authenticates the server instead of its certificate
+        let ticket = first_ticket(&config);
+        let mut client = ClientConnection::resume(ticket, |_| false).unwrap();
+        let mut client_hello = client.take_outgoing();
+        let mut replayed = client_hello.clone();
+        let mut server = ServerConnection::new(config.clone());
+        assert_eq!(server.read_tls(&mut client_hello), Ok(client_hello.len()));
+        let mut flight = server.take_outgoing();
+        assert_eq!(
+            server_hello_extension(&flight, PRE_SHARED_KEY),
+            Some(vec![0, 0])
+        );
+        assert!(server_hello_extension(&flight, KEY_SHARE).is_some());
+        assert_eq!(client.read_tls(&mut flight), Ok(flight.len()));
+        let mut finished = client.take_outgoing();
+        assert_eq!(server.read_tls(&mut finished), Ok(finished.len()));
+        assert!(!client.is_handshaking());
+        assert!(!server.is_handshaking());
+        assert_eq!(client.peer_public_key(), None);
+        assert_eq!(server.peer_public_key(), None);
+
+        // resumed connections are sent a new ticket too
+        let mut ticket = server.take_outgoing();
+        assert_eq!(client.read_tls(&mut ticket), Ok(ticket.len()));
+        assert_eq!(client.take_tickets().len(), 1);
+
+        client.write(b"hello").unwrap();
+        let mut record = client.take_outgoing();
+        assert_eq!(server.read_tls(&mut record), Ok(record.len()));
+        assert_eq!(server.take_received(), b"hello");
+
+        // tickets are single use, so replaying the `ClientHello` gets a full handshake
+        let mut server = ServerConnection::new(config.clone());
+        assert_eq!(server.read_tls(&mut replayed), Ok(replayed.len()));
+        let flight = server.take_outgoing();
+        assert_eq!(server_hello_extension(&flight, PRE_SHARED_KEY), None);
+    }
+
+    #[test]
+    fn resumption_server_name() {
+        let mut config = config();
+        config.enable_resumption(3600);
+        let config = Arc::new(config);
+
+        let mut client =
+            ClientConnection::with_server_name("localhost", |certs| certs == [&CERT[..]]).unwrap();
+        let mut server = ServerConnection::new(config.clone());
+        assert_eq!(connect(&mut client, &mut server), Ok(()));
+        let mut ticket = server.take_outgoing();
+        assert_eq!(client.read_tls(&mut ticket), Ok(ticket.len()));
+        let ticket = client.take_tickets().pop().unwrap();
+        assert_eq!(ticket.server_name(), Some("localhost"));
+
+        // the name is sent again, since the ticket is only good for the same server
+        let mut client = ClientConnection::resume(ticket, |_| false).unwrap();
+        let mut client_hello = client.take_outgoing();
+        assert!(client_hello.windows(9).any(|window| window == b"localhost"));
+        let mut server = ServerConnection::new(config);
+        assert_eq!(server.read_tls(&mut client_hello), Ok(client_hello.len()));
+        let flight = server.take_outgoing();
+        assert_eq!(
+            server_hello_extension(&flight, PRE_SHARED_KEY),
+            Some(vec![0, 0])
+        );
+    }
+
+    #[test]
+    fn resumption_from_bytes() {
+        let mut config = config();
+        config.enable_resumption(3600);
+        let config = Arc::new(config);
+
+        let mut client =
+            ClientConnection::with_server_name("localhost", |certs| certs == [&CERT[..]]).unwrap();
+        let mut server = ServerConnection::new(config.clone());
+        assert_eq!(connect(&mut client, &mut server), Ok(()));
+        let bytes = client
+            .take_tickets()
+            .pop()
+            .unwrap()
+            .to_bytes_containing_secrets();
+
+        // a client that was never connected resumes, without being sent a certificate
+        let ticket = SessionTicket::from_bytes_containing_secrets(&bytes).unwrap();
+        assert_eq!(ticket.server_name(), Some("localhost"));
+        let mut client = ClientConnection::resume(ticket, |_| false).unwrap();
+        let mut server = ServerConnection::new(config);
+        assert_eq!(connect(&mut client, &mut server), Ok(()));
+        assert!(!client.is_handshaking());
+    }
+
+    #[test]
+    fn first_known_ticket() {
+        let mut config = config();
+        config.enable_resumption(3600);
+        let config = Arc::new(config);
+
+        let ticket = first_ticket(&config);
+        let unknown = PskOffer {
+            identity: PskIdentity {
+                identity: b"unknown",
+                obfuscated_ticket_age: 0,
+            },
+            binder_key: [1; 32],
+        };
+        let mut client_hello = resumption_client_hello(&[unknown, ticket.offer()]);
+        let mut server = ServerConnection::new(config.clone());
+        assert_eq!(server.read_tls(&mut client_hello), Ok(client_hello.len()));
+        let flight = server.take_outgoing();
+        assert_eq!(
+            server_hello_extension(&flight, PRE_SHARED_KEY),
+            Some(vec![0, 1])
+        );
+    }
+
+    #[test]
+    fn rejected_ticket() {
+        let mut config = config();
+        config.enable_resumption(3600);
+        let config = Arc::new(config);
+
+        // a client that claims to have held the ticket for a minute, when it was just issued
+        let ticket = first_ticket(&config);
+        let mut offer = ticket.offer();
+        offer.identity.obfuscated_ticket_age =
+            offer.identity.obfuscated_ticket_age.wrapping_add(60_000);
+        let mut client_hello = resumption_client_hello(&[offer]);
+        let mut server = ServerConnection::new(config.clone());
+        assert_eq!(server.read_tls(&mut client_hello), Ok(client_hello.len()));
+        let flight = server.take_outgoing();
+        assert_eq!(server_hello_extension(&flight, PRE_SHARED_KEY), None);
+
+        // a client that saw the ticket, but doesn't know its PSK
+        let ticket = first_ticket(&config);
+        let forged = PskOffer {
+            binder_key: EarlySecret::new(Some(&[8; 32])).binder_key(false),
+            ..ticket.offer()
+        };
+        let mut client_hello = resumption_client_hello(&[forged]);
+        let mut server = ServerConnection::new(config.clone());
+        assert_eq!(
+            server.read_tls(&mut client_hello),
+            Err(ConnectionError::BadBinder)
+        );
+
+        // can't use the ticket up, so its owner can still resume with it
+        let mut client_hello = resumption_client_hello(&[ticket.offer()]);
+        let mut server = ServerConnection::new(config.clone());
+        assert_eq!(server.read_tls(&mut client_hello), Ok(client_hello.len()));
+        let flight = server.take_outgoing();
+        assert_eq!(
+            server_hello_extension(&flight, PRE_SHARED_KEY),
+            Some(vec![0, 0])
+        );
+    }
+
+    const SERVER_NAME: u16 = Extension::ServerName as u16;
+    const SUPPORTED_VERSIONS: u16 = Extension::SupportedVersions as u16;
+    const SUPPORTED_GROUPS: u16 = Extension::SupportedGroups as u16;
+    const SIGNATURE_ALGORITHMS: u16 = Extension::SignatureAlgorithms as u16;
+    const KEY_SHARE: u16 = Extension::KeyShare as u16;
+    const PSK_EXCHANGE_MODES: u16 = Extension::PskExchangeModes as u16;
+    const PRE_SHARED_KEY: u16 = Extension::PreSharedKey as u16;
+
+    #[test]
+    fn session_id_echo() {
+        let key_share = key_share();
+        let extensions: [(u16, &[u8]); 4] = [
+            (SUPPORTED_VERSIONS, &[0x02, 0x03, 0x04]),
+            (SUPPORTED_GROUPS, &[0x00, 0x02, 0x00, 0x17]),
+            (SIGNATURE_ALGORITHMS, &[0x00, 0x02, 0x04, 0x03]),
+            (KEY_SHARE, &key_share),
+        ];
+        // clients in middlebox compatibility mode send a session ID, which has to be echoed
+        let mut record = client_hello(0x1301, &[7; 32], &extensions);
+        let mut server = ServerConnection::new(Arc::new(config()));
+        assert_eq!(server.read_tls(&mut record), Ok(record.len()));
+
+        let outgoing = server.take_outgoing();
+        let server_hello = &outgoing[Message::PREFIIX_SIZE..];
+        assert_eq!(server_hello[0], ShakeType::ServerHello as u8);
+        let len = u32::from_be_bytes([0, server_hello[1], server_hello[2], server_hello[3]]);
+        let body = &server_hello[Handshake::PREFIX_SIZE..][..len as usize];
+        let server_hello = ServerHelloRef::parse_from_handshake(body).unwrap();
+        assert_eq!(server_hello.session_id, [7; 32]);
+    }
+
+    #[test]
+    fn hello_retry_request() {
+        let versions: (u16, &[u8]) = (SUPPORTED_VERSIONS, &[0x02, 0x03, 0x04]);
+        let groups: (u16, &[u8]) = (SUPPORTED_GROUPS, &[0x00, 0x02, 0x00, 0x17]);
+        let schemes: (u16, &[u8]) = (SIGNATURE_ALGORITHMS, &[0x00, 0x02, 0x04, 0x03]);
+        let no_shares: (u16, &[u8]) = (KEY_SHARE, &[0x00, 0x00]);
+        let key_share = key_share();
+        let key_shares: (u16, &[u8]) = (KEY_SHARE, &key_share);
+
+        // a client that supports secp256r1 but didn't send a key share for it is asked for one
+        let mut first = client_hello(0x1301, &[7; 32], &[versions, groups, schemes, no_shares]);
+        let mut server = ServerConnection::new(Arc::new(config()));
+        assert_eq!(server.read_tls(&mut first), Ok(first.len()));
+        let hello_retry_request = server.take_outgoing();
+        let body = &hello_retry_request[Message::PREFIIX_SIZE + Handshake::PREFIX_SIZE..];
+        let parsed = ServerHelloRef::parse_from_handshake(body).unwrap();
+        assert_eq!(*parsed.random_bytes, ServerHelloRef::HELLO_RETRY_RANDOM);
+        assert_eq!(parsed.session_id, [7; 32]);
+        // nothing else is sent until the client answers
+        assert_eq!(
+            hello_retry_request.len(),
+            Message::PREFIIX_SIZE + Handshake::PREFIX_SIZE + body.len()
+        );
+        assert_eq!(
+            server_hello_extension(&hello_retry_request, KEY_SHARE),
+            Some(vec![0x00, 0x17])
+        );
+        assert_eq!(
+            server_hello_extension(&hello_retry_request, SUPPORTED_VERSIONS),
+            Some(vec![0x03, 0x04])
+        );
+
+        // the answer gets a real `ServerHello`
+        let mut second = client_hello(0x1301, &[7; 32], &[versions, groups, schemes, key_shares]);
+        assert_eq!(server.read_tls(&mut second), Ok(second.len()));
+        let flight = server.take_outgoing();
+        let server_hello = &flight[Message::PREFIIX_SIZE..];
+        let len = u32::from_be_bytes([0, server_hello[1], server_hello[2], server_hello[3]]);
+        let body = &server_hello[Handshake::PREFIX_SIZE..][..len as usize];
+        let parsed = ServerHelloRef::parse_from_handshake(body).unwrap();
+        assert_ne!(*parsed.random_bytes, ServerHelloRef::HELLO_RETRY_RANDOM);
+        assert!(server_hello_extension(&flight, KEY_SHARE).is_some());
+
+        // but only once
+        let mut server = ServerConnection::new(Arc::new(config()));
+        let mut first = client_hello(0x1301, &[], &[versions, groups, schemes, no_shares]);
+        assert_eq!(server.read_tls(&mut first), Ok(first.len()));
+        let mut second = first.clone();
+        assert_eq!(
+            server.read_tls(&mut second),
+            Err(ConnectionError::IllegalParameter)
+        );
+
+        // clients that don't support secp256r1 at all have nothing to retry with
+        let mut server = ServerConnection::new(Arc::new(config()));
+        let mut record = client_hello(
+            0x1301,
+            &[],
+            &[
+                versions,
+                (SUPPORTED_GROUPS, &[0x00, 0x02, 0x00, 0x1d]),
+                schemes,
+                no_shares,
+            ],
+        );
+        assert_eq!(
+            server.read_tls(&mut record),
+            Err(ConnectionError::NoCommonGroup)
+        );
+    }
+
+    #[test]
+    fn unsupported_client_hello() {
+        let key_share = key_share();
+        let versions: (u16, &[u8]) = (SUPPORTED_VERSIONS, &[0x02, 0x03, 0x04]);
+        let groups: (u16, &[u8]) = (SUPPORTED_GROUPS, &[0x00, 0x02, 0x00, 0x17]);
+        let schemes: (u16, &[u8]) = (SIGNATURE_ALGORITHMS, &[0x00, 0x02, 0x04, 0x03]);
+        let key_shares: (u16, &[u8]) = (KEY_SHARE, &key_share);
+        let mut offered_psk = vec![0x00, 0x0b, 0x00, 0x05];
+        offered_psk.extend_from_slice(b"other");
+        offered_psk.extend_from_slice(&[0, 0, 0, 0, 0x00, 0x21, 0x20]);
+        offered_psk.extend_from_slice(&[0; 32]);
+        let psk: (u16, &[u8]) = (PRE_SHARED_KEY, &offered_psk);
+
+        let failures = [
+            (
+                client_hello(0x1302, &[], &[versions, groups, schemes, key_shares]),
+                ConnectionError::NoCommonCipherSuite,
+            ),
+            (
+                client_hello(0x1301, &[], &[groups, schemes, key_shares]),
+                ConnectionError::ProtocolVersion,
+            ),
+            (
+                {
+                    let mut record =
+                        client_hello(0x1301, &[], &[versions, groups, schemes, key_shares]);
+                    // a `legacy_version` of SSL 3.0
+                    record[Message::PREFIIX_SIZE + Handshake::PREFIX_SIZE + 1] = 0x00;
+                    record
+                },
+                ConnectionError::ProtocolVersion,
+            ),
+            (
+                client_hello(
+                    0x1301,
+                    &[],
+                    &[
+                        (SUPPORTED_VERSIONS, &[0x02, 0x03, 0x03]),
+                        groups,
+                        schemes,
+                        key_shares,
+                    ],
+                ),
+                ConnectionError::ProtocolVersion,
+            ),
+            (
+                client_hello(
+                    0x1301,
+                    &[],
+                    &[
+                        versions,
+                        groups,
+                        (SIGNATURE_ALGORITHMS, &[0x00, 0x02, 0x08, 0x07]),
+                        key_shares,
+                    ],
+                ),
+                ConnectionError::NoCommonSignatureScheme,
+            ),
+            (
+                client_hello(0x1301, &[], &[versions, groups, schemes]),
+                ConnectionError::MissingExtension,
+            ),
+            (
+                // an x25519 key share, from a client that doesn't support secp256r1
+                client_hello(
+                    0x1301,
+                    &[],
+                    &[
+                        versions,
+                        (SUPPORTED_GROUPS, &[0x00, 0x02, 0x00, 0x1d]),
+                        schemes,
+                        (KEY_SHARE, &[0x00, 0x06, 0x00, 0x1d, 0x00, 0x02, 0xaa, 0xbb]),
+                    ],
+                ),
+                ConnectionError::NoCommonGroup,
+            ),
+            (
+                client_hello(
+                    0x1301,
+                    &[],
+                    &[versions, groups, schemes, key_shares, versions],
+                ),
+                ConnectionError::IllegalParameter,
+            ),
+            // the PSK has to come last
+            (
+                client_hello(0x1301, &[], &[versions, groups, schemes, psk, key_shares]),
+                ConnectionError::IllegalParameter,
+            ),
+            // along with the key exchanges it can be used with
+            (
+                client_hello(0x1301, &[], &[versions, groups, schemes, key_shares, psk]),
+                ConnectionError::MissingExtension,
+            ),
+        ];
+        for (mut record, err) in failures {
+            let mut server = ServerConnection::new(Arc::new(config()));
+            assert_eq!(server.read_tls(&mut record), Err(err));
+            assert!(server.is_closed());
+
+            // nothing has been encrypted yet, so neither is the alert
+            let description = err.alert().unwrap() as u8;
+            let mut alert = Message::start(ContentType::Alert);
+            alert.extend_from_slice(&[2, description]);
+            alert.finish();
+            assert_eq!(server.take_outgoing(), alert.to_vec());
+        }
+    }
+}
@@ -1,7 +1,10 @@
-use crate::cipher_suites::CipherSuite;
-use crate::client_hello::ClientHelloRef;
+use crate::alert::AlertDescription;
+use crate::cipher_suites::{CipherSuite, NamedGroup};
+use crate::codec::{DecodeError, Reader};
+use crate::extensions;
 use crate::handshake::{Handshake, ShakeType};
-use crate::versions::{ProtocolVersion, LEGACY_PROTO_VERS};
+use crate::key_share::KeyShareEntry;
+use crate::versions::ProtocolVersion;
 use getrandom::{getrandom, Error};
 
 pub struct ServerHello {
@@ -9,18 +12,59 @@ pub struct ServerHello {
 }
 
 impl ServerHello {
-    pub fn new(client_hello: &ClientHelloRef) -> Result<Self, Error> {
+    /// Creates a `ServerHello` that negotiates TLS 1.3 with `cipher_suite`, answering the client's
+    /// key share with `server_share`.
+    ///
+    /// `session_id` is the client's `legacy_session_id`, which has to be echoed back.
+    /// `selected_psk` is the index of the PSK that was accepted, if any. Only handshakes that use
+    /// a PSK can leave out the key share.
+    pub fn new(
+        session_id: &[u8],
+        cipher_suite: CipherSuite,
+        server_share: Option<&KeyShareEntry>,
+        selected_psk: Option<u16>,
+    ) -> Result<Self, Error> {
         let mut server_hello = Self::start();
         server_hello.legacy_protocol_version();
         server_hello.random_bytes()?;
-        server_hello.legacy_session_id_echo(client_hello.session_id);
-        server_hello.cipher_suite(client_hello.cipher_suites);
+        server_hello.legacy_session_id_echo(session_id);
+        server_hello.cipher_suite(cipher_suite);
         server_hello.legacy_compression_method();
-        server_hello.extensions(client_hello.extensions);
+        server_hello.extensions(|server_hello| {
+            if let Some(server_share) = server_share {
+                extensions::key_share_server_hello(server_hello, server_share);
+            }
+            if let Some(selected_psk) = selected_psk {
+                extensions::pre_shared_key_server_hello(server_hello, selected_psk);
+            }
+        });
         server_hello.finish();
         Ok(server_hello)
     }
 
+    /// Creates a `HelloRetryRequest` that negotiates TLS 1.3 with `cipher_suite`, and asks the
+    /// client to send a key share for `group`.
+    ///
+    /// `session_id` is the client's `legacy_session_id`, which has to be echoed back.
+    pub fn hello_retry_request(
+        session_id: &[u8],
+        cipher_suite: CipherSuite,
+        group: NamedGroup,
+    ) -> Self {
+        let mut hello_retry_request = Self::start();
+        hello_retry_request.legacy_protocol_version();
+        // the random is what tells it apart from a `ServerHello`
+        hello_retry_request.extend_from_slice(&ServerHelloRef::HELLO_RETRY_RANDOM);
+        hello_retry_request.legacy_session_id_echo(session_id);
+        hello_retry_request.cipher_suite(cipher_suite);
+        hello_retry_request.legacy_compression_method();
+        hello_retry_request.extensions(|hello_retry_request| {
+            extensions::key_share_hello_retry_request(hello_retry_request, group);
+        });
+        hello_retry_request.finish();
+        hello_retry_request
+    }
+
     fn start() -> Self {
         Self {
             shake: Handshake::start(ShakeType::ServerHello),
@@ -28,7 +72,8 @@ impl ServerHello {
     }
 
     fn legacy_protocol_version(&mut self) {
-        self.extend_from_slice(&LEGACY_PROTO_VERS.to_be_bytes());
+        // the real version is in the `supported_versions` extension
+        self.extend_from_slice(&ProtocolVersion::TlsOnePointTwo.to_be_bytes());
     }
 
     fn random_bytes(&mut self) -> Result<(), Error> {
@@ -37,21 +82,29 @@ impl ServerHello {
         getrandom(&mut self[len - 32..])
     }
 
-    fn legacy_session_id_echo(&mut self, client_ses_id: &[u8]) {
-        self.extend_from_slice(client_ses_id);
+    fn legacy_session_id_echo(&mut self, session_id: &[u8]) {
+        self.push(session_id.len() as u8);
+        self.extend_from_slice(session_id);
     }
 
-    fn cipher_suite(&mut self, client_cipher_suites: &[CipherSuite]) {
-        let cipher_suite: CipherSuite = todo!();
-        self.push(cipher_suite as u8);
+    fn cipher_suite(&mut self, cipher_suite: CipherSuite) {
+        self.extend_from_slice(&cipher_suite.to_be_bytes());
     }
 
     fn legacy_compression_method(&mut self) {
         self.push(0x00);
     }
 
-    fn extensions(&mut self, extensions: &[u8]) {
-        todo!();
+    /// Writes `supported_versions`, followed by the extensions `f` writes.
+    fn extensions(&mut self, f: impl FnOnce(&mut Self)) {
+        self.extend_from_slice(&[0, 0]);
+        let original_len = self.len();
+
+        extensions::supported_versions_server(self);
+        f(self);
+
+        let extensions_len = ((self.len() - original_len) as u16).to_be_bytes();
+        self[original_len - 2..][..2].copy_from_slice(&extensions_len);
     }
 }
 
@@ -68,34 +121,79 @@ impl std::ops::DerefMut for ServerHello {
     }
 }
 
+/// A parsed `ServerHello` message, or a `HelloRetryRequest`, which has the same format.
 pub struct ServerHelloRef<'a> {
-    random_bytes: &'a [u8],
-    cipher_suite: CipherSuite,
-    extensions: &'a [u8],
+    pub random_bytes: &'a [u8; 32],
+    pub session_id: &'a [u8],
+    pub cipher_suite: CipherSuite,
+    pub extensions: &'a [u8],
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SerHelloParseError {
     MissingData,
     InvalidLengthEncoding,
     InvalidCipherSuite,
+    InvalidCompressionMethod,
+}
+
+impl SerHelloParseError {
+    /// The alert to send in response to this error.
+    pub const fn alert(self) -> AlertDescription {
+        match self {
+            Self::MissingData | Self::InvalidLengthEncoding => AlertDescription::DecodeError,
+            // the server picked a cipher suite or compression method we didn't offer
+            Self::InvalidCipherSuite | Self::InvalidCompressionMethod => {
+                AlertDescription::IllegalParam
+            },
+        }
+    }
+}
+
+impl From<DecodeError> for SerHelloParseError {
+    fn from(_: DecodeError) -> Self {
+        Self::MissingData
+    }
 }
 
 impl<'a> ServerHelloRef<'a> {
-    fn parse_from_handshake(data: &'a [u8]) -> Result<Self, SerHelloParseError> {
-        let random_bytes = &data[2..34];
-        let session_id_len = data[35];
-        if session_id_len > 32 {
+    /// The `random_bytes` that mark a `ServerHello` as a `HelloRetryRequest`.
+    pub const HELLO_RETRY_RANDOM: [u8; 32] = [
+        0xcf, 0x21, 0xad, 0x74, 0xe5, 0x9a, 0x61, 0x11, 0xbe, 0x1d, 0x8c, 0x02, 0x1e, 0x65, 0xb8,
+        0x91, 0xc2, 0xa2, 0x11, 0x16, 0x7a, 0xbb, 0x8c, 0x5e, 0x07, 0x9e, 0x09, 0xe2, 0xc8, 0xa8,
+        0x33, 0x9c,
+    ];
+
+    /// Parses the body of a `ServerHello` message.
+    pub fn parse_from_handshake(data: &'a [u8]) -> Result<Self, SerHelloParseError> {
+        let mut reader = Reader::new(data);
+        let _legacy_version = reader.read_u16()?;
+        let random_bytes = reader.read_array()?;
+
+        let session_id = reader.read_u8_prefixed()?;
+        if session_id.remaining() > 32 {
             return Err(SerHelloParseError::InvalidLengthEncoding);
         };
-        let cipher_suite =
-            if data[36 + session_id_len as usize] == CipherSuite::Aes128GcmSha256 as u8 {
-                CipherSuite::Aes128GcmSha256
-            } else {
-                return Err(SerHelloParseError::InvalidCipherSuite);
-            };
-        let extensions = &data[36 + session_id_len as usize + 2..];
+        let session_id = session_id.read_rest();
+
+        let cipher_suite = if reader.read_u16()? == CipherSuite::Aes128GcmSha256 as u16 {
+            CipherSuite::Aes128GcmSha256
+        } else {
+            return Err(SerHelloParseError::InvalidCipherSuite);
+        };
+        // only the "null" compression method is ever offered
+        if reader.read_u8()? != 0 {
+            return Err(SerHelloParseError::InvalidCompressionMethod);
+        }
+
+        let extensions = reader.read_u16_prefixed()?;
+        let extensions = extensions.read_rest();
+        if !reader.is_empty() {
+            return Err(SerHelloParseError::InvalidLengthEncoding);
+        }
         Ok(Self {
             random_bytes,
+            session_id,
             cipher_suite,
             extensions,
         })
@@ -0,0 +1,367 @@
+//! Signatures as they appear in `CertificateVerify` messages.
+//!
+//! Each [`SignatureScheme`] fixes its own wire format, and none of them is a raw `r || s`:
+//! - ECDSA signatures are DER-encoded `Ecdsa-Sig-Value`s.
+//! - EdDSA signatures have a fixed length: 64 bytes for Ed25519 and 114 for Ed448.
+//! - RSASSA-PSS signatures are as long as the modulus, with the PSS parameters implied by the
+//!   scheme rather than encoded.
+//!
+//! [`sign`] and [`verify`] dispatch on the scheme, so `CertificateVerify` and certificate
+//! validation share one implementation of each.
+//!
+//! `crylib` doesn't implement RSA yet, so the RSA schemes are rejected as unsupported.
+//! EdDSA signatures are only checked for length until it implements EdDSA.
+use std::cell::Cell;
+
+use crate::alert::AlertDescription;
+use crate::cipher_suites::SignatureScheme;
+use crate::codec::{DecodeError, EncodeError, Reader, Writer};
+use crate::public_key::PublicKey;
+use crylib::big_int::UBigInt;
+use crylib::ec::{ecdsa, EllipticCurve, Secp256r1};
+use crylib::finite_field::FieldElement;
+use crylib::hash::{Hasher, Sha256};
+use getrandom::getrandom;
+
+type Secp256r1Scalar = FieldElement<<Secp256r1 as EllipticCurve>::Order>;
+type EcdsaSecp256r1Sig = ecdsa::Signature<<Secp256r1 as EllipticCurve>::Order>;
+
+/// A signature, along with the scheme that made it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signature {
+    EcdsaSecp256r1Sha256(EcdsaSecp256r1Sig),
+    Ed25519([u8; 64]),
+    Ed448([u8; 114]),
+}
+
+impl Signature {
+    pub const fn scheme(&self) -> SignatureScheme {
+        match self {
+            Self::EcdsaSecp256r1Sha256(_) => SignatureScheme::EcdsaSecp256r1Sha256,
+            Self::Ed25519(_) => SignatureScheme::Ed25519,
+            Self::Ed448(_) => SignatureScheme::Ed448,
+        }
+    }
+
+    /// Appends the `algorithm` and `signature` fields of a `CertificateVerify` message to
+    /// `writer`.
+    pub fn encode(&self, writer: &mut Writer) -> Result<(), EncodeError> {
+        writer.write_u16(self.scheme() as u16)?;
+        writer.write_u16_prefixed(|writer| match self {
+            Self::EcdsaSecp256r1Sha256(sig) => {
+                let mut der = [0; EcdsaSecp256r1Sig::DER_MAX_LEN];
+                writer.write_slice(sig.to_der(&mut der))
+            },
+            Self::Ed25519(sig) => writer.write_slice(sig),
+            Self::Ed448(sig) => writer.write_slice(sig),
+        })
+    }
+
+    /// Reads the `algorithm` and `signature` fields of a `CertificateVerify` message from the
+    /// front of `reader`.
+    pub fn decode(reader: &mut Reader) -> Result<Self, SignatureError> {
+        let scheme = reader.read_u16()?;
+        let sig = reader.read_u16_prefixed()?.read_rest();
+        Self::parse(scheme, sig)
+    }
+
+    /// Verifies that `self` is a signature of `data` by the owner of `spki`.
+    ///
+    /// `spki` is a DER `SubjectPublicKeyInfo`, as found in certificates.
+    pub fn verify(&self, spki: &[u8], data: &[u8]) -> Result<(), SignatureError> {
+        PublicKey::from_spki(spki)
+            .map_err(|_| SignatureError::InvalidPublicKey)?
+            .verify(self, data)
+    }
+
+    /// Parses `sig`, which is in the wire format of the scheme numbered `scheme`.
+    pub fn parse(scheme: u16, sig: &[u8]) -> Result<Self, SignatureError> {
+        if scheme == SignatureScheme::EcdsaSecp256r1Sha256 as u16 {
+            ecdsa::Signature::from_der(sig)
+                .map(Self::EcdsaSecp256r1Sha256)
+                .map_err(|_| SignatureError::InvalidEncoding)
+        } else if scheme == SignatureScheme::Ed25519 as u16 {
+            sig.try_into()
+                .map(Self::Ed25519)
+                .map_err(|_| SignatureError::InvalidEncoding)
+        } else if scheme == SignatureScheme::Ed448 as u16 {
+            sig.try_into()
+                .map(Self::Ed448)
+                .map_err(|_| SignatureError::InvalidEncoding)
+        } else {
+            Err(SignatureError::UnsupportedScheme)
+        }
+    }
+}
+
+/// A private key that signatures can be made with.
+pub enum SigningKey {
+    Secp256r1(Secp256r1Scalar),
+}
+
+impl SigningKey {
+    /// The scheme this crate signs with when using this key.
+    pub const fn scheme(&self) -> SignatureScheme {
+        match self {
+            Self::Secp256r1(_) => SignatureScheme::EcdsaSecp256r1Sha256,
+        }
+    }
+}
+
+/// Signs `data` with `key`, using `scheme`.
+///
+/// Returns [`SignError::UnsupportedScheme`] if `key` can't be used with `scheme`.
+pub fn sign(
+    scheme: SignatureScheme,
+    key: &SigningKey,
+    data: &[u8],
+) -> Result<Signature, SignError> {
+    match (scheme, key) {
+        (SignatureScheme::EcdsaSecp256r1Sha256, SigningKey::Secp256r1(key)) => {
+            // `ecdsa::sign` can't handle a failing generator, so a failure is recorded and the
+            // signature made with the placeholder nonce is thrown away
+            let rng_failed = Cell::new(false);
+            let sig = ecdsa::sign::<Secp256r1>(data, key, Sha256::hash, || {
+                random_scalar().unwrap_or_else(|_| {
+                    rng_failed.set(true);
+                    FieldElement::ONE
+                })
+            });
+            match rng_failed.get() {
+                true => Err(SignError::RngError),
+                false => Ok(Signature::EcdsaSecp256r1Sha256(sig)),
+            }
+        },
+        _ => Err(SignError::UnsupportedScheme),
+    }
+}
+
+/// Verifies that `sig` is a signature of `data` made with `scheme` by the owner of `spki`.
+///
+/// `sig` is in the wire format of `scheme`, and `spki` is a DER `SubjectPublicKeyInfo`,
+/// as found in certificates.
+pub fn verify(
+    scheme: SignatureScheme,
+    spki: &[u8],
+    data: &[u8],
+    sig: &[u8],
+) -> Result<(), SignatureError> {
+    Signature::parse(scheme as u16, sig)?.verify(spki, data)
+}
+
+/// Returns a uniformly random non-zero scalar.
+pub fn random_scalar() -> Result<Secp256r1Scalar, getrandom::Error> {
+    loop {
+        let mut bytes = [0; 32];
+        getrandom(&mut bytes)?;
+        // rejecting out-of-range values keeps the result uniform;
+        // for secp256r1, a retry is almost never needed
+        if let Ok(scalar) = FieldElement::try_new(UBigInt::<4>::from_be_bytes(bytes)) {
+            if scalar != FieldElement::ZERO {
+                return Ok(scalar);
+            }
+        }
+    }
+}
+
+/// The error that is returned when a signature can't be made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SignError {
+    UnsupportedScheme,
+    RngError,
+}
+
+impl SignError {
+    /// The alert to send in response to this error.
+    pub const fn alert(self) -> AlertDescription {
+        match self {
+            // we can't sign with any scheme the peer accepts
+            Self::UnsupportedScheme => AlertDescription::HandshakeFailure,
+            Self::RngError => AlertDescription::InternalError,
+        }
+    }
+}
+
+/// The error that is returned when a signature can't be decoded or verified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SignatureError {
+    /// The message ended before the signature did.
+    MissingData,
+    /// The signature isn't in the format its scheme requires.
+    InvalidEncoding,
+    /// The signature scheme isn't one that can be verified.
+    UnsupportedScheme,
+    /// The public key couldn't be parsed.
+    InvalidPublicKey,
+    /// The public key is of a different type than the scheme signs with.
+    KeyMismatch,
+    /// The signature doesn't match the data and key.
+    BadSignature,
+}
+
+impl SignatureError {
+    /// The alert to send in response to this error.
+    pub const fn alert(self) -> AlertDescription {
+        match self {
+            Self::MissingData | Self::InvalidEncoding => AlertDescription::DecodeError,
+            // the peer picked a scheme we didn't offer
+            Self::UnsupportedScheme => AlertDescription::IllegalParam,
+            Self::InvalidPublicKey => AlertDescription::BadCert,
+            // the peer picked a scheme its key can't sign with
+            Self::KeyMismatch => AlertDescription::IllegalParam,
+            Self::BadSignature => AlertDescription::DecryptErorr,
+        }
+    }
+}
+
+impl From<DecodeError> for SignatureError {
+    fn from(_: DecodeError) -> Self {
+        Self::MissingData
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EcdsaSecp256r1Sig, SignError, Signature, SignatureError, SigningKey};
+    use crate::cipher_suites::SignatureScheme;
+    use crate::codec::{Reader, Writer};
+    use crylib::big_int::UBigInt;
+    use crylib::finite_field::FieldElement;
+
+    // generated with pyca/cryptography
+    const ECDSA_DER: [u8; 8] = [0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x7f];
+
+    #[test]
+    fn round_trip() {
+        let ecdsa = EcdsaSecp256r1Sig::from_der(&ECDSA_DER).unwrap();
+        let mut ecdsa_encoding = vec![0x04, 0x03, 0x00, 0x08];
+        ecdsa_encoding.extend_from_slice(&ECDSA_DER);
+
+        let mut ed25519_encoding = vec![0x08, 0x07, 0x00, 0x40];
+        ed25519_encoding.extend_from_slice(&[0xaa; 64]);
+
+        let signatures = [
+            (Signature::EcdsaSecp256r1Sha256(ecdsa), ecdsa_encoding),
+            (Signature::Ed25519([0xaa; 64]), ed25519_encoding),
+        ];
+        for (signature, encoding) in signatures {
+            let mut buf = [0; 128];
+            let mut writer = Writer::new(&mut buf);
+            signature.encode(&mut writer).unwrap();
+            assert_eq!(writer.written(), encoding);
+
+            let mut reader = Reader::new(&encoding);
+            assert_eq!(Signature::decode(&mut reader), Ok(signature));
+            assert!(reader.is_empty());
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let invalid: [(&[u8], SignatureError); 5] = [
+            // truncated
+            (&[0x04, 0x03, 0x00, 0x08, 0x30], SignatureError::MissingData),
+            // raw `r || s` instead of DER
+            (
+                &[0x04, 0x03, 0x00, 0x04, 0x00, 0x01, 0x00, 0x7f],
+                SignatureError::InvalidEncoding,
+            ),
+            // trailing data after the DER
+            (
+                &[
+                    0x04, 0x03, 0x00, 0x09, 0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x7f, 0x00,
+                ],
+                SignatureError::InvalidEncoding,
+            ),
+            // Ed25519 signatures must be 64 bytes
+            (
+                &[0x08, 0x07, 0x00, 0x01, 0x00],
+                SignatureError::InvalidEncoding,
+            ),
+            // RSASSA-PSS
+            (
+                &[0x08, 0x04, 0x00, 0x01, 0x00],
+                SignatureError::UnsupportedScheme,
+            ),
+        ];
+        for (encoding, err) in invalid {
+            assert_eq!(Signature::decode(&mut Reader::new(encoding)), Err(err));
+        }
+    }
+
+    const DATA: &[u8] = b"the quick brown fox";
+
+    // generated with pyca/cryptography, using the key from the NIST ECDSA test vectors
+    const SPKI: [u8; 91] = [
+        0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08,
+        0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00, 0x04, 0x1c, 0xcb, 0xe9,
+        0x1c, 0x07, 0x5f, 0xc7, 0xf4, 0xf0, 0x33, 0xbf, 0xa2, 0x48, 0xdb, 0x8f, 0xcc, 0xd3, 0x56,
+        0x5d, 0xe9, 0x4b, 0xbf, 0xb1, 0x2f, 0x3c, 0x59, 0xff, 0x46, 0xc2, 0x71, 0xbf, 0x83, 0xce,
+        0x40, 0x14, 0xc6, 0x88, 0x11, 0xf9, 0xa2, 0x1a, 0x1f, 0xdb, 0x2c, 0x0e, 0x61, 0x13, 0xe0,
+        0x6d, 0xb7, 0xca, 0x93, 0xb7, 0x40, 0x4e, 0x78, 0xdc, 0x7c, 0xcd, 0x5c, 0xa8, 0x9a, 0x4c,
+        0xa9,
+    ];
+    const SIG: [u8; 72] = [
+        0x30, 0x46, 0x02, 0x21, 0x00, 0xe0, 0xbe, 0x40, 0x73, 0x01, 0x07, 0x97, 0xdc, 0xa5, 0x94,
+        0xd7, 0xc6, 0xf3, 0x07, 0x45, 0xa2, 0x5c, 0x75, 0x9a, 0x9e, 0x10, 0xc3, 0xa2, 0x50, 0x7a,
+        0x5d, 0xbd, 0x7b, 0x27, 0x8f, 0x84, 0x98, 0x02, 0x21, 0x00, 0x83, 0x7b, 0x4a, 0x20, 0x87,
+        0xe9, 0xa4, 0x68, 0x15, 0xe3, 0xff, 0xc3, 0x10, 0xbf, 0xc7, 0xae, 0xbf, 0xae, 0x26, 0x9a,
+        0x87, 0x2e, 0x33, 0xec, 0x7b, 0x9c, 0xc6, 0x15, 0x21, 0x67, 0x12, 0xeb,
+    ];
+    const ECDSA: SignatureScheme = SignatureScheme::EcdsaSecp256r1Sha256;
+
+    fn signing_key() -> SigningKey {
+        SigningKey::Secp256r1(FieldElement::new(UBigInt([
+            0xca54a56dda72b464,
+            0x5b44c8130b4e3eac,
+            0x1f4fa8ee59f4771a,
+            0x519b423d715f8b58,
+        ])))
+    }
+
+    #[test]
+    fn verify() {
+        assert_eq!(super::verify(ECDSA, &SPKI, DATA, &SIG), Ok(()));
+        assert_eq!(
+            super::verify(ECDSA, &SPKI, b"the quick brown fix", &SIG),
+            Err(SignatureError::BadSignature)
+        );
+
+        let mut spki = SPKI;
+        spki[90] ^= 1;
+        assert_eq!(
+            super::verify(ECDSA, &spki, DATA, &SIG),
+            Err(SignatureError::InvalidPublicKey)
+        );
+        assert_eq!(
+            super::verify(ECDSA, &SPKI[..90], DATA, &SIG),
+            Err(SignatureError::InvalidPublicKey)
+        );
+        assert_eq!(
+            super::verify(ECDSA, &SPKI, DATA, &SIG[..71]),
+            Err(SignatureError::InvalidEncoding)
+        );
+        assert_eq!(
+            super::verify(SignatureScheme::RsaPssRsaeSha256, &SPKI, DATA, &SIG),
+            Err(SignatureError::UnsupportedScheme)
+        );
+    }
+
+    #[test]
+    fn sign() {
+        let Ok(Signature::EcdsaSecp256r1Sha256(sig)) = super::sign(ECDSA, &signing_key(), DATA)
+        else {
+            panic!("signing failed");
+        };
+        let mut der = [0; EcdsaSecp256r1Sig::DER_MAX_LEN];
+        assert_eq!(
+            super::verify(ECDSA, &SPKI, DATA, sig.to_der(&mut der)),
+            Ok(())
+        );
+
+        assert_eq!(
+            super::sign(SignatureScheme::Ed25519, &signing_key(), DATA),
+            Err(SignError::UnsupportedScheme)
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! Choosing the server's certificate by the name the client asks for in its `server_name`
+//! extension (RFC 6066 section 3).
+//!
+//! A [`CertifiedKeySet`] maps host names to certificate chains. Names are compared without regard
+//! to ASCII case. A name whose first label is `*` is a wildcard, which stands for exactly one
+//! label, as in certificates (RFC 6125 section 6.4.3): `*.example.com` matches `www.example.com`,
+//! but neither `example.com` nor `a.b.example.com`. A name that has both an exact and a wildcard
+//! entry gets the exact one.
+//!
+//! Clients that don't send a name, or send one that isn't in the set, get the certificate the
+//! [`ServerConfig`](crate::ServerConfig) was created with, unless its [`UnknownNamePolicy`] turns
+//! them away with an `unrecognized_name` alert instead.
+use std::collections::HashMap;
+
+use crate::connection::CertifiedKey;
+use crate::private_key::PrivateKey;
+
+/// Certificate chains, each for the host names it is added with.
+///
+/// ```no_run
+/// use turtls::{CertifiedKeySet, PrivateKey, ServerConfig};
+///
+/// # fn load(_: &str) -> (Vec<Vec<u8>>, PrivateKey) { unimplemented!() }
+/// let mut certs = CertifiedKeySet::new();
+/// let (chain, key) = load("example.com");
+/// certs.add("example.com", chain, key);
+/// let (chain, key) = load("wildcard.example.com");
+/// certs.add("*.example.com", chain, key);
+///
+/// let (chain, key) = load("default");
+/// let mut config = ServerConfig::new(chain, key);
+/// config.set_named_certs(certs);
+/// ```
+#[derive(Default)]
+pub struct CertifiedKeySet {
+    /// The certificates for exact names, by their lowercase name.
+    exact: HashMap<String, CertifiedKey>,
+    /// The certificates for wildcard names, by their lowercase name without the `*.`.
+    wildcard: HashMap<String, CertifiedKey>,
+}
+
+impl CertifiedKeySet {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends `cert_chain`, leaf first, to clients that ask for `name`, replacing any chain that
+    /// was added for the same name.
+    ///
+    /// `key` is the private key of the leaf. `name` is a DNS host name, or a wildcard such as
+    /// `*.example.com`.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if `name` isn't ASCII, is empty or longer than 255 bytes, has an
+    /// empty label, or has a `*` anywhere but as its whole first label.
+    pub fn add(&mut self, name: &str, cert_chain: Vec<Vec<u8>>, key: PrivateKey) {
+        assert!(
+            name.is_ascii() && (1..=255).contains(&name.len()),
+            "a host name is between 1 and 255 ASCII bytes long"
+        );
+        let name = name.to_ascii_lowercase();
+        let (map, rest) = match name.strip_prefix("*.") {
+            Some(rest) => (&mut self.wildcard, rest),
+            None => (&mut self.exact, &name[..]),
+        };
+        assert!(
+            rest.split('.')
+                .all(|label| !label.is_empty() && !label.contains('*')),
+            "a host name has no empty labels, and a wildcard is only allowed as the first label"
+        );
+        map.insert(rest.to_string(), CertifiedKey { cert_chain, key });
+    }
+
+    /// Returns whether no certificates have been added.
+    pub fn is_empty(&self) -> bool {
+        self.exact.is_empty() && self.wildcard.is_empty()
+    }
+
+    /// Returns the certificate for `server_name`, which is lowercase, if there is one.
+    pub(crate) fn get(&self, server_name: &str) -> Option<&CertifiedKey> {
+        if let Some(cert) = self.exact.get(server_name) {
+            return Some(cert);
+        }
+        // the wildcard stands for the first label, which can't be empty
+        let (first, parent) = server_name.split_once('.')?;
+        match first.is_empty() {
+            true => None,
+            false => self.wildcard.get(parent),
+        }
+    }
+}
+
+/// What a server does with a client whose name has no certificate in its [`CertifiedKeySet`].
+///
+/// Whatever the policy, clients that are authenticated by a PSK don't need a certificate, so the
+/// name they ask for isn't checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum UnknownNamePolicy {
+    /// Sends the default certificate, whether the client asked for another name or for none.
+    #[default]
+    ServeDefault,
+    /// Rejects clients that ask for a name that isn't in the set, but sends the default
+    /// certificate to clients that don't ask for any, such as those connecting by IP address.
+    RejectUnknown,
+    /// Rejects clients that don't ask for a name in the set, including those that don't send a
+    /// `server_name` extension at all.
+    RequireKnown,
+}
+
+impl UnknownNamePolicy {
+    /// Returns whether a client that asked for `server_name`, which has no certificate, is
+    /// rejected.
+    pub(crate) fn rejects(self, server_name: Option<&str>) -> bool {
+        match self {
+            Self::ServeDefault => false,
+            Self::RejectUnknown => server_name.is_some(),
+            Self::RequireKnown => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CertifiedKeySet;
+    use crate::connection::tests::cert_key;
+
+    /// Returns a set with a chain of one "certificate" for each of `names`, which is the name.
+    fn set(names: &[&str]) -> CertifiedKeySet {
+        let mut set = CertifiedKeySet::new();
+        for name in names {
+            set.add(name, vec![name.as_bytes().to_vec()], cert_key());
+        }
+        set
+    }
+
+    fn lookup<'a>(set: &'a CertifiedKeySet, server_name: &str) -> Option<&'a [u8]> {
+        set.get(server_name).map(|cert| &cert.cert_chain[0][..])
+    }
+
+    #[test]
+    fn matching() {
+        let set = set(&[
+            "example.com",
+            "*.example.com",
+            "WWW.Example.com",
+            "*.a.example.org",
+        ]);
+        assert!(!set.is_empty());
+        assert_eq!(lookup(&set, "example.com"), Some(&b"example.com"[..]));
+        assert_eq!(
+            lookup(&set, "mail.example.com"),
+            Some(&b"*.example.com"[..])
+        );
+        // exact names win, and were lowercased when they were added
+        assert_eq!(
+            lookup(&set, "www.example.com"),
+            Some(&b"WWW.Example.com"[..])
+        );
+        assert_eq!(
+            lookup(&set, "b.a.example.org"),
+            Some(&b"*.a.example.org"[..])
+        );
+
+        // a wildcard stands for exactly one label
+        assert_eq!(lookup(&set, "a.b.example.com"), None);
+        assert_eq!(lookup(&set, ".example.com"), None);
+        assert_eq!(lookup(&set, "a.example.org"), None);
+        assert_eq!(lookup(&set, "example.net"), None);
+        assert!(CertifiedKeySet::new().is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn wildcard_inside() {
+        set(&["www.*.example.com"]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn partial_wildcard() {
+        set(&["w*.example.com"]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_label() {
+        set(&["example..com"]);
+    }
+}
@@ -0,0 +1,179 @@
+//! Helpers for testing code that uses TLS connections, without opening sockets.
+//!
+//! [`duplex`] makes two in-memory transports that behave like the ends of a socket pair, which
+//! can be handed to code that does its own I/O. [`handshake`] skips the transports altogether,
+//! and moves records straight between a client and a server until neither has anything to say.
+//!
+//! This module is only built with the `testing` feature.
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::client::ClientConnection;
+use crate::connection::ConnectionError;
+use crate::server::ServerConnection;
+
+/// Bytes written to one end of a [`duplex`] pair, waiting to be read at the other.
+#[derive(Default)]
+struct Pipe {
+    state: Mutex<PipeState>,
+    readable: Condvar,
+}
+
+#[derive(Default)]
+struct PipeState {
+    buf: VecDeque<u8>,
+    /// Whether the end that writes into the pipe has been dropped.
+    closed: bool,
+}
+
+impl Pipe {
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.readable.notify_all();
+    }
+}
+
+/// One end of an in-memory transport made by [`duplex`].
+///
+/// Reads block until the other end writes something, possibly from another thread, and return
+/// `0` once the other end has been dropped and everything it wrote has been read. Writes never
+/// block, and fail with [`io::ErrorKind::BrokenPipe`] once the other end has been dropped.
+pub struct Duplex {
+    incoming: Arc<Pipe>,
+    outgoing: Arc<Pipe>,
+}
+
+/// Returns the two ends of an in-memory transport: what is written to one is read from the other.
+///
+/// ```
+/// use std::io::{Read, Write};
+///
+/// let (mut a, mut b) = turtls::test_util::duplex();
+/// a.write_all(b"hello").unwrap();
+/// drop(a);
+/// let mut received = Vec::new();
+/// b.read_to_end(&mut received).unwrap();
+/// assert_eq!(received, b"hello");
+/// ```
+pub fn duplex() -> (Duplex, Duplex) {
+    let a_to_b = Arc::new(Pipe::default());
+    let b_to_a = Arc::new(Pipe::default());
+    let a = Duplex {
+        incoming: Arc::clone(&b_to_a),
+        outgoing: Arc::clone(&a_to_b),
+    };
+    let b = Duplex {
+        incoming: a_to_b,
+        outgoing: b_to_a,
+    };
+    (a, b)
+}
+
+impl Read for Duplex {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut state = self.incoming.state.lock().unwrap();
+        while state.buf.is_empty() && !state.closed {
+            state = self.incoming.readable.wait(state).unwrap();
+        }
+        let len = buf.len().min(state.buf.len());
+        for (dst, src) in buf.iter_mut().zip(state.buf.drain(..len)) {
+            *dst = src;
+        }
+        Ok(len)
+    }
+}
+
+impl Write for Duplex {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // the other end closes the pipe it reads from when it is dropped
+        let mut state = self.outgoing.state.lock().unwrap();
+        if state.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        state.buf.extend(buf);
+        self.outgoing.readable.notify_all();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Duplex {
+    fn drop(&mut self) {
+        self.outgoing.close();
+        self.incoming.close();
+    }
+}
+
+/// Moves records between `client` and `server` until neither has anything left to send, which
+/// includes whatever they send once the handshake is done, such as session tickets.
+///
+/// Returns the first error either side runs into. The alert it queued for the other side is left
+/// to be taken with `take_outgoing`.
+pub fn handshake(
+    client: &mut ClientConnection,
+    server: &mut ServerConnection,
+) -> Result<(), ConnectionError> {
+    loop {
+        let mut to_server = client.take_outgoing();
+        server.read_tls(&mut to_server)?;
+        let mut to_client = server.take_outgoing();
+        client.read_tls(&mut to_client)?;
+        if to_server.is_empty() && to_client.is_empty() {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{ErrorKind, Read, Write};
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::{duplex, handshake};
+    use crate::client::ClientConnection;
+    use crate::connection::tests::{cert_key, CERT};
+    use crate::server::{ServerConfig, ServerConnection};
+
+    #[test]
+    fn duplex_pair() {
+        let (mut a, mut b) = duplex();
+        a.write_all(b"ping").unwrap();
+        b.write_all(b"pong").unwrap();
+        let mut buf = [0; 4];
+        b.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+        a.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"pong");
+
+        // a read blocks until the other thread writes
+        let writer = thread::spawn(move || b.write_all(b"late").unwrap());
+        a.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"late");
+        writer.join().unwrap();
+
+        // `b` has been dropped
+        assert_eq!(a.read(&mut buf).unwrap(), 0);
+        assert_eq!(a.write(b"lost").unwrap_err().kind(), ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn handshake_with_ticket() {
+        let mut config = ServerConfig::new(vec![CERT.to_vec()], cert_key());
+        config.enable_resumption(3600);
+        let mut client = ClientConnection::new(|certs| certs == [&CERT[..]]).unwrap();
+        let mut server = ServerConnection::new(Arc::new(config));
+        assert_eq!(handshake(&mut client, &mut server), Ok(()));
+        assert!(!client.is_handshaking());
+        assert!(!server.is_handshaking());
+        // the ticket the server sent after the handshake was delivered too
+        assert_eq!(client.take_tickets().len(), 1);
+    }
+}
@@ -0,0 +1,531 @@
+//! Session tickets, which let clients resume a connection without a full handshake
+//! (RFC 8446 section 4.6.1).
+//!
+//! The server keeps the PSK of every ticket it issues in a [`TicketStore`], under a random
+//! identity, so the ticket the client holds has nothing secret in it. Tickets can only be redeemed
+//! once, so a ticket that is seen on the wire can't be used to replay the handshake it resumed.
+//! A ticket is only redeemed once the client has proven that it knows its PSK, though, since
+//! anyone who saw the identity could otherwise use it up.
+//!
+//! Clients report how long they have held a ticket, obfuscated by adding the ticket's
+//! `ticket_age_add`. A ticket whose age doesn't roughly match how long ago the server issued it
+//! isn't accepted.
+//!
+//! The client keeps each ticket it is sent as a [`SessionTicket`], along with the PSK it derives
+//! for it. A `SessionTicket` can be turned into bytes and back, to resume from another process or
+//! after a restart, but the bytes contain the PSK, so they have to be kept as secret as a key.
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use getrandom::getrandom;
+
+use crate::cipher_suites::CipherSuite;
+use crate::codec::{Codec, DecodeError, EncodeError, Reader, Writer};
+use crate::key_schedule::{self, EarlySecret, HASH_LEN};
+use crate::psk::{PskIdentity, PskOffer};
+use crate::zeroizing::Zeroizing;
+
+/// The body of a `NewSessionTicket` message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewSessionTicket {
+    /// How long the ticket can be used for, in seconds.
+    pub lifetime: u32,
+    /// What the client adds to the age of the ticket, in milliseconds, when it offers it.
+    pub age_add: u32,
+    /// What the PSK of the ticket is derived with, along with the resumption master secret.
+    pub nonce: Vec<u8>,
+    /// The identity that the client offers the PSK with.
+    pub ticket: Vec<u8>,
+}
+
+impl Codec<'_> for NewSessionTicket {
+    fn encode(&self, writer: &mut Writer) -> Result<(), EncodeError> {
+        writer.write_slice(&self.lifetime.to_be_bytes())?;
+        writer.write_slice(&self.age_add.to_be_bytes())?;
+        writer.write_u8_prefixed(|writer| writer.write_slice(&self.nonce))?;
+        writer.write_u16_prefixed(|writer| writer.write_slice(&self.ticket))?;
+        // no extensions
+        writer.write_u16(0)
+    }
+
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let lifetime = u32::from_be_bytes(*reader.read_array()?);
+        let age_add = u32::from_be_bytes(*reader.read_array()?);
+        let nonce = reader.read_u8_prefixed()?.read_rest().to_vec();
+        let ticket = reader.read_u16_prefixed()?.read_rest().to_vec();
+        // the only extension is `early_data`, which isn't supported
+        reader.read_u16_prefixed()?;
+        if ticket.is_empty() {
+            return Err(DecodeError);
+        }
+        Ok(Self {
+            lifetime,
+            age_add,
+            nonce,
+            ticket,
+        })
+    }
+}
+
+/// A session ticket a client was sent, which can resume the connection it came from.
+///
+/// The PSK of the ticket is zeroed when it is dropped.
+pub struct SessionTicket {
+    ticket: Vec<u8>,
+    psk: Zeroizing<[u8; HASH_LEN]>,
+    age_add: u32,
+    lifetime: Duration,
+    received: Instant,
+    /// The name the connection the ticket came from sent, if any.
+    server_name: Option<String>,
+}
+
+impl SessionTicket {
+    /// Keeps the ticket `msg`, which was sent on the connection with the resumption master
+    /// secret `resumption_master_secret` and the server name `server_name`.
+    ///
+    /// Returns `None` if the ticket has a lifetime of zero, which means it can't be used at all.
+    pub fn new(
+        msg: &NewSessionTicket,
+        resumption_master_secret: &[u8; HASH_LEN],
+        server_name: Option<&str>,
+    ) -> Option<Self> {
+        if msg.lifetime == 0 {
+            return None;
+        }
+        // clients can't keep tickets for longer than seven days, whatever the server says
+        let lifetime = msg.lifetime.min(TicketStore::MAX_LIFETIME);
+        Some(Self {
+            ticket: msg.ticket.clone(),
+            psk: Zeroizing::new(key_schedule::resumption_psk(
+                resumption_master_secret,
+                &msg.nonce,
+            )),
+            age_add: msg.age_add,
+            lifetime: Duration::from_secs(lifetime.into()),
+            received: Instant::now(),
+            server_name: server_name.map(str::to_string),
+        })
+    }
+
+    /// Returns the name the connection the ticket came from sent, which resuming it sends again
+    /// (RFC 8446 section 4.6.1).
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// Returns whether the lifetime of the ticket has run out, after which the server won't
+    /// accept it.
+    pub fn is_expired(&self) -> bool {
+        self.received.elapsed() > self.lifetime
+    }
+
+    /// Returns what the ticket is offered with, including its obfuscated age.
+    pub fn offer(&self) -> PskOffer<'_> {
+        // lifetimes of up to seven days fit in a `u32` of milliseconds
+        let age = self.received.elapsed().as_millis() as u32;
+        PskOffer {
+            identity: PskIdentity {
+                identity: &self.ticket,
+                obfuscated_ticket_age: age.wrapping_add(self.age_add),
+            },
+            binder_key: self.early_secret().binder_key(false),
+        }
+    }
+
+    /// Extracts the early secret from the PSK of the ticket.
+    pub fn early_secret(&self) -> EarlySecret {
+        EarlySecret::new(Some(&*self.psk))
+    }
+
+    /// The version of the format of [`SessionTicket::to_bytes_containing_secrets`].
+    const FORMAT_VERSION: u8 = 1;
+
+    /// Encodes the ticket, to be turned back into one with
+    /// [`SessionTicket::from_bytes_containing_secrets`], possibly by another process.
+    ///
+    /// The bytes contain the PSK of the ticket, with which anyone can resume the connection it
+    /// came from as the client. They have to be stored like a private key, and overwritten once
+    /// they aren't needed anymore.
+    ///
+    /// When the ticket was received is encoded as wall-clock time, so the ticket keeps aging
+    /// while it is stored, and changes to the system clock change its age.
+    pub fn to_bytes_containing_secrets(&self) -> Vec<u8> {
+        let received = SystemTime::now() - self.received.elapsed();
+        // tickets received before 1970 aren't worth supporting
+        let received = received.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let server_name = self.server_name.as_deref().unwrap_or_default();
+
+        let mut bytes = Vec::new();
+        bytes.push(Self::FORMAT_VERSION);
+        bytes.extend_from_slice(&(CipherSuite::Aes128GcmSha256 as u16).to_be_bytes());
+        // a `u64` of milliseconds lasts for half a billion years
+        bytes.extend_from_slice(&(received.as_millis() as u64).to_be_bytes());
+        // lifetimes are at most seven days
+        bytes.extend_from_slice(&(self.lifetime.as_secs() as u32).to_be_bytes());
+        bytes.extend_from_slice(&self.age_add.to_be_bytes());
+        bytes.extend_from_slice(&*self.psk);
+        // the ticket came in a `u16` length prefix, and the name is at most 255 bytes
+        bytes.extend_from_slice(&(self.ticket.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&self.ticket);
+        bytes.push(server_name.len() as u8);
+        bytes.extend_from_slice(server_name.as_bytes());
+        bytes
+    }
+
+    /// Decodes a ticket encoded by [`SessionTicket::to_bytes_containing_secrets`].
+    ///
+    /// Returns [`SessionTicketError::Expired`] if the lifetime of the ticket ran out while it
+    /// was stored.
+    pub fn from_bytes_containing_secrets(bytes: &[u8]) -> Result<Self, SessionTicketError> {
+        let mut reader = Reader::new(bytes);
+        if reader.read_u8()? != Self::FORMAT_VERSION
+            || reader.read_u16()? != CipherSuite::Aes128GcmSha256 as u16
+        {
+            return Err(SessionTicketError::UnsupportedVersion);
+        }
+        let received = u64::from_be_bytes(*reader.read_array()?);
+        let lifetime = u32::from_be_bytes(*reader.read_array()?);
+        let age_add = u32::from_be_bytes(*reader.read_array()?);
+        let psk = Zeroizing::new(*reader.read_array::<HASH_LEN>()?);
+        let ticket = reader.read_u16_prefixed()?.read_rest().to_vec();
+        let server_name = reader.read_u8_prefixed()?.read_rest();
+        reader.finish()?;
+        if lifetime == 0 || lifetime > TicketStore::MAX_LIFETIME || ticket.is_empty() {
+            return Err(SessionTicketError::Malformed);
+        }
+        let server_name = match server_name {
+            [] => None,
+            name if name.is_ascii() => Some(String::from_utf8_lossy(name).into_owned()),
+            _ => return Err(SessionTicketError::Malformed),
+        };
+
+        let received = UNIX_EPOCH + Duration::from_millis(received);
+        // a ticket from the future, after the clock was turned back, is treated as brand new
+        let age = SystemTime::now()
+            .duration_since(received)
+            .unwrap_or_default();
+        let lifetime = Duration::from_secs(lifetime.into());
+        if age > lifetime {
+            return Err(SessionTicketError::Expired);
+        }
+        Ok(Self {
+            ticket,
+            psk,
+            age_add,
+            lifetime,
+            // the monotonic clock might not reach back that far, so soon after booting
+            received: Instant::now()
+                .checked_sub(age)
+                .ok_or(SessionTicketError::Expired)?,
+            server_name,
+        })
+    }
+}
+
+/// The error that is returned when an encoded [`SessionTicket`] can't be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SessionTicketError {
+    /// The bytes are truncated, or have a field that is out of range.
+    Malformed,
+    /// The ticket was encoded by a version of this crate with a different format, or for a cipher
+    /// suite that isn't supported.
+    UnsupportedVersion,
+    /// The lifetime of the ticket has run out.
+    Expired,
+}
+
+impl From<DecodeError> for SessionTicketError {
+    fn from(_: DecodeError) -> Self {
+        Self::Malformed
+    }
+}
+
+/// A ticket that was issued, but hasn't been redeemed yet.
+struct StoredTicket {
+    psk: Zeroizing<[u8; HASH_LEN]>,
+    age_add: u32,
+    issued: Instant,
+}
+
+/// The tickets a server has issued, which are shared by all of its connections.
+pub struct TicketStore {
+    /// How long tickets can be used for, in seconds.
+    lifetime: u32,
+    tickets: Mutex<HashMap<Vec<u8>, StoredTicket>>,
+}
+
+impl TicketStore {
+    /// The longest lifetime a ticket can have, which is seven days.
+    pub const MAX_LIFETIME: u32 = 7 * 24 * 60 * 60;
+    /// How many tickets are kept at most. Once there are this many, the oldest is dropped to make
+    /// room for the next one.
+    const MAX_TICKETS: usize = 1024;
+    /// How far the age the client reports can be from the one the server measures, in
+    /// milliseconds. This has to allow for the round trip, and for the clocks running at slightly
+    /// different rates.
+    const MAX_AGE_SKEW: u32 = 10_000;
+    const TICKET_LEN: usize = 32;
+
+    /// Creates a store whose tickets can be used for `lifetime` seconds.
+    ///
+    /// # Panics
+    ///
+    /// The function will panic if `lifetime` is longer than [`TicketStore::MAX_LIFETIME`].
+    pub fn new(lifetime: u32) -> Self {
+        assert!(
+            lifetime <= Self::MAX_LIFETIME,
+            "tickets can't be used for more than seven days"
+        );
+        Self {
+            lifetime,
+            tickets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Issues a ticket for the connection with the resumption master secret
+    /// `resumption_master_secret`.
+    pub fn issue(
+        &self,
+        resumption_master_secret: &[u8; HASH_LEN],
+    ) -> Result<NewSessionTicket, getrandom::Error> {
+        let mut ticket = vec![0; Self::TICKET_LEN];
+        getrandom(&mut ticket)?;
+        let mut age_add = [0; 4];
+        getrandom(&mut age_add)?;
+        let age_add = u32::from_be_bytes(age_add);
+        // only one ticket is issued per connection, so the nonce doesn't need to tell them apart
+        let nonce = Vec::new();
+        let psk = key_schedule::resumption_psk(resumption_master_secret, &nonce);
+
+        let mut tickets = self.tickets.lock().unwrap_or_else(PoisonError::into_inner);
+        let lifetime = Duration::from_secs(self.lifetime.into());
+        tickets.retain(|_, stored| stored.issued.elapsed() <= lifetime);
+        if tickets.len() >= Self::MAX_TICKETS {
+            let oldest = tickets
+                .iter()
+                .min_by_key(|(_, stored)| stored.issued)
+                .map(|(ticket, _)| ticket.clone());
+            if let Some(oldest) = oldest {
+                tickets.remove(&oldest);
+            }
+        }
+        tickets.insert(
+            ticket.clone(),
+            StoredTicket {
+                psk: Zeroizing::new(psk),
+                age_add,
+                issued: Instant::now(),
+            },
+        );
+        Ok(NewSessionTicket {
+            lifetime: self.lifetime,
+            age_add,
+            nonce,
+            ticket,
+        })
+    }
+
+    /// Looks up the ticket `offered`, returning its PSK.
+    ///
+    /// Returns `None` if there is no such ticket, or if it has expired or the age the client
+    /// reports for it is off. The ticket stays in the store until it is redeemed with
+    /// [`TicketStore::redeem`], which is only done once the client has proven it knows the PSK.
+    pub fn find(&self, offered: &PskIdentity) -> Option<Zeroizing<[u8; HASH_LEN]>> {
+        let tickets = self.tickets.lock().unwrap_or_else(PoisonError::into_inner);
+        let stored = tickets.get(offered.identity)?;
+
+        let age = stored.issued.elapsed();
+        if age > Duration::from_secs(self.lifetime.into()) {
+            return None;
+        }
+        // lifetimes of up to seven days fit in a `u32` of milliseconds
+        let age = age.as_millis() as u32;
+        let client_age = offered.obfuscated_ticket_age.wrapping_sub(stored.age_add);
+        if client_age.abs_diff(age) > Self::MAX_AGE_SKEW {
+            return None;
+        }
+        Some(Zeroizing::new(*stored.psk))
+    }
+
+    /// Redeems the ticket `identity`, so that it can't be used again.
+    ///
+    /// Returns `false` if the ticket has already been redeemed, by another connection that
+    /// offered it at the same time.
+    pub fn redeem(&self, identity: &[u8]) -> bool {
+        let mut tickets = self.tickets.lock().unwrap_or_else(PoisonError::into_inner);
+        tickets.remove(identity).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{NewSessionTicket, SessionTicket, SessionTicketError, TicketStore};
+    use crate::codec::{Codec, Reader, Writer};
+    use crate::key_schedule;
+    use crate::psk::PskIdentity;
+
+    #[test]
+    fn new_session_ticket_codec() {
+        let ticket = NewSessionTicket {
+            lifetime: 7200,
+            age_add: 0x01020304,
+            nonce: vec![0],
+            ticket: vec![0xaa; 4],
+        };
+        let mut buf = [0; 20];
+        let mut writer = Writer::new(&mut buf);
+        ticket.encode(&mut writer).unwrap();
+        assert_eq!(
+            writer.written(),
+            [
+                0x00, 0x00, 0x1c, 0x20, 0x01, 0x02, 0x03, 0x04, 0x01, 0x00, 0x00, 0x04, 0xaa, 0xaa,
+                0xaa, 0xaa, 0x00, 0x00
+            ]
+        );
+        let mut reader = Reader::new(writer.written());
+        assert_eq!(NewSessionTicket::decode(&mut reader), Ok(ticket));
+        assert!(reader.is_empty());
+
+        // a ticket can't be empty
+        let empty = [0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0x00, 0x00, 0x00, 0x00];
+        assert!(NewSessionTicket::decode(&mut Reader::new(&empty)).is_err());
+    }
+
+    #[test]
+    fn redeem() {
+        let store = TicketStore::new(3600);
+        let ticket = store.issue(&[7; 32]).unwrap();
+        assert_eq!(ticket.lifetime, 3600);
+        let offered = PskIdentity {
+            identity: &ticket.ticket,
+            obfuscated_ticket_age: ticket.age_add.wrapping_add(100),
+        };
+        let psk = key_schedule::resumption_psk(&[7; 32], &ticket.nonce);
+        assert_eq!(store.find(&offered).as_deref(), Some(&psk));
+        // finding a ticket doesn't use it up
+        assert_eq!(store.find(&offered).as_deref(), Some(&psk));
+        // but tickets are single use
+        assert!(store.redeem(&ticket.ticket));
+        assert!(store.find(&offered).is_none());
+        assert!(!store.redeem(&ticket.ticket));
+
+        // the client can't claim to have held the ticket much longer than it has
+        let ticket = store.issue(&[7; 32]).unwrap();
+        let offered = PskIdentity {
+            identity: &ticket.ticket,
+            obfuscated_ticket_age: ticket.age_add.wrapping_add(60_000),
+        };
+        assert!(store.find(&offered).is_none());
+    }
+
+    #[test]
+    fn session_ticket() {
+        let msg = NewSessionTicket {
+            lifetime: 3600,
+            age_add: 0xffff_fff0,
+            nonce: vec![1],
+            ticket: vec![0xaa; 4],
+        };
+        let ticket = SessionTicket::new(&msg, &[7; 32], None).unwrap();
+        assert!(!ticket.is_expired());
+        assert_eq!(
+            *ticket.psk,
+            key_schedule::resumption_psk(&[7; 32], &msg.nonce)
+        );
+
+        // the server can find it again, with an age that is close enough
+        let store = TicketStore::new(3600);
+        let issued = store.issue(&[7; 32]).unwrap();
+        let ticket = SessionTicket::new(&issued, &[7; 32], None).unwrap();
+        let offer = ticket.offer();
+        assert_eq!(offer.identity.identity, issued.ticket);
+        assert_eq!(store.find(&offer.identity).as_deref(), Some(&*ticket.psk));
+
+        // tickets with no lifetime are discarded right away
+        let msg = NewSessionTicket { lifetime: 0, ..msg };
+        assert!(SessionTicket::new(&msg, &[7; 32], None).is_none());
+    }
+
+    #[test]
+    fn session_ticket_bytes() {
+        let msg = NewSessionTicket {
+            lifetime: 3600,
+            age_add: 0x0102_0304,
+            nonce: vec![1],
+            ticket: vec![0xaa; 4],
+        };
+        let ticket = SessionTicket::new(&msg, &[7; 32], Some("example.com")).unwrap();
+        let bytes = ticket.to_bytes_containing_secrets();
+        let decoded = SessionTicket::from_bytes_containing_secrets(&bytes).unwrap();
+        assert_eq!(decoded.ticket, ticket.ticket);
+        assert_eq!(*decoded.psk, *ticket.psk);
+        assert_eq!(decoded.age_add, ticket.age_add);
+        assert_eq!(decoded.lifetime, ticket.lifetime);
+        assert_eq!(decoded.server_name(), Some("example.com"));
+        // the age survives, to within the millisecond the encoding rounds to
+        let age = decoded.received.elapsed();
+        assert!(age < ticket.received.elapsed() + Duration::from_millis(2));
+        assert!(!decoded.is_expired());
+
+        let without_name = SessionTicket::new(&msg, &[7; 32], None).unwrap();
+        let bytes = without_name.to_bytes_containing_secrets();
+        let decoded = SessionTicket::from_bytes_containing_secrets(&bytes).unwrap();
+        assert_eq!(decoded.server_name(), None);
+        // version, cipher suite, received, lifetime, age_add, psk, ticket, name
+        assert_eq!(bytes.len(), 1 + 2 + 8 + 4 + 4 + 32 + 2 + 4 + 1);
+
+        let mut expired = bytes.clone();
+        expired[3..11].copy_from_slice(&1000u64.to_be_bytes());
+        let mut version = bytes.clone();
+        version[0] = 2;
+        let mut suite = bytes.clone();
+        suite[2] = 0x02;
+        let mut no_lifetime = bytes.clone();
+        no_lifetime[11..15].fill(0);
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        let cases = [
+            (&expired[..], SessionTicketError::Expired),
+            (&version, SessionTicketError::UnsupportedVersion),
+            (&suite, SessionTicketError::UnsupportedVersion),
+            (&no_lifetime, SessionTicketError::Malformed),
+            (&trailing, SessionTicketError::Malformed),
+            (&bytes[..bytes.len() - 1], SessionTicketError::Malformed),
+        ];
+        for (bytes, err) in cases {
+            assert_eq!(
+                SessionTicket::from_bytes_containing_secrets(bytes).err(),
+                Some(err)
+            );
+        }
+    }
+
+    #[test]
+    fn capacity() {
+        let store = TicketStore::new(3600);
+        for _ in 0..TicketStore::MAX_TICKETS {
+            store.issue(&[7; 32]).unwrap();
+        }
+        let last = store.issue(&[7; 32]).unwrap();
+        // the oldest ticket made room for the last one
+        assert_eq!(
+            store.tickets.lock().unwrap().len(),
+            TicketStore::MAX_TICKETS
+        );
+        let offered = PskIdentity {
+            identity: &last.ticket,
+            obfuscated_ticket_age: last.age_add,
+        };
+        assert!(store.find(&offered).is_some());
+    }
+
+    #[test]
+    #[should_panic]
+    fn lifetime_too_long() {
+        TicketStore::new(TicketStore::MAX_LIFETIME + 1);
+    }
+}
@@ -0,0 +1,131 @@
+//! The transcript hash (RFC 8446 section 4.4.1).
+//!
+//! Every handshake message is hashed as soon as it is sent or received, so the messages
+//! themselves don't have to be kept until the handshake is over. Applications that want them
+//! anyway, to archive exactly what was negotiated, can have them retained as well.
+use crylib::hash::{Hasher, Sha256};
+
+use crate::handshake::{Handshake, ShakeType};
+use crate::key_schedule::HASH_LEN;
+
+/// A running hash of the handshake messages of a connection.
+///
+/// The hash is that of `TLS_AES_128_GCM_SHA256`, the only supported cipher suite.
+#[derive(Clone)]
+pub struct TranscriptHash {
+    hash: Sha256,
+    /// Copies of the messages, if they are being retained.
+    messages: Option<Vec<Vec<u8>>>,
+}
+
+impl TranscriptHash {
+    /// Creates the hash of an empty transcript.
+    pub fn new() -> Self {
+        Self {
+            hash: Sha256::new(),
+            messages: None,
+        }
+    }
+
+    /// Adds the whole handshake message `msg`, including its header.
+    pub fn update(&mut self, msg: &[u8]) {
+        self.hash.update_with(msg);
+        if let Some(messages) = &mut self.messages {
+            messages.push(msg.to_vec());
+        }
+    }
+
+    /// Keeps a copy of every message added from now on.
+    ///
+    /// `earlier` must be the messages that have already been added, in order.
+    pub fn retain_messages(&mut self, earlier: Vec<Vec<u8>>) {
+        self.messages = Some(earlier);
+    }
+
+    /// Returns the retained messages, in the order they were added, or `None` if they aren't
+    /// being retained.
+    pub fn messages(&self) -> Option<&[Vec<u8>]> {
+        self.messages.as_deref()
+    }
+
+    /// Returns the hash of every message so far.
+    ///
+    /// The key schedule needs the hash at several points of the handshake, so this leaves the
+    /// transcript open to more messages.
+    pub fn current(&self) -> [u8; HASH_LEN] {
+        self.hash.clone().finish()
+    }
+
+    /// Replaces the messages so far, which must be just the first `ClientHello`, with a synthetic
+    /// `message_hash` message that holds their hash.
+    ///
+    /// This is what happens when the server answers with a `HelloRetryRequest`, so that it doesn't
+    /// have to remember the first `ClientHello`. Retained messages are left as they were sent,
+    /// since `message_hash` never goes over the wire.
+    pub fn replace_with_message_hash(&mut self) {
+        let mut message_hash = [0; Handshake::PREFIX_SIZE + HASH_LEN];
+        message_hash[0] = ShakeType::MessageHash as u8;
+        message_hash[3] = HASH_LEN as u8;
+        message_hash[Handshake::PREFIX_SIZE..].copy_from_slice(&self.current());
+        self.hash = Sha256::new();
+        self.hash.update_with(&message_hash);
+    }
+}
+
+impl Default for TranscriptHash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current() {
+        let msgs: [&[u8]; 3] = [&[1, 0, 0, 2, 0xaa, 0xbb], &[2, 0, 0, 0], &[8; 100]];
+        let mut transcript = TranscriptHash::new();
+        assert_eq!(transcript.current(), Sha256::hash(&[]));
+
+        let mut all = Vec::new();
+        for msg in msgs {
+            transcript.update(msg);
+            all.extend_from_slice(msg);
+            // taking a snapshot doesn't affect the later ones
+            assert_eq!(transcript.current(), Sha256::hash(&all));
+            assert_eq!(transcript.current(), Sha256::hash(&all));
+        }
+    }
+
+    #[test]
+    fn message_hash() {
+        let client_hello = [1, 0, 0, 3, 0x03, 0x03, 0x42];
+        let retry_request = [2, 0, 0, 1, 0x17];
+        let mut transcript = TranscriptHash::new();
+        transcript.update(&client_hello);
+        transcript.replace_with_message_hash();
+        transcript.update(&retry_request);
+
+        let mut expected = vec![254, 0, 0, 32];
+        expected.extend_from_slice(&Sha256::hash(&client_hello));
+        expected.extend_from_slice(&retry_request);
+        assert_eq!(transcript.current(), Sha256::hash(&expected));
+    }
+
+    #[test]
+    fn retained() {
+        let client_hello = [1, 0, 0, 3, 0x03, 0x03, 0x42];
+        let retry_request = [2, 0, 0, 1, 0x17];
+        let mut transcript = TranscriptHash::new();
+        assert_eq!(transcript.messages(), None);
+        transcript.update(&client_hello);
+        transcript.retain_messages(vec![client_hello.to_vec()]);
+        transcript.replace_with_message_hash();
+        transcript.update(&retry_request);
+        assert_eq!(
+            transcript.messages(),
+            Some(&[client_hello.to_vec(), retry_request.to_vec()][..])
+        );
+    }
+}
@@ -1,3 +1,4 @@
+#[derive(Clone, Copy)]
 #[repr(u16)]
 pub enum ProtocolVersion {
     SslThreePointZero = 0x0300,
@@ -0,0 +1,75 @@
+//! A container for secrets that zeroes them when they're dropped.
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+/// Holds a secret, overwriting it with zeros when it's dropped.
+///
+/// Only `Copy` types can be held, since they have no destructor that could see the zeros and own
+/// no memory that would be left behind. Copies made of the secret aren't zeroed, so it should be
+/// wrapped as soon as it's created and used by reference from then on.
+pub struct Zeroizing<T: Copy>(T);
+
+impl<T: Copy> Zeroizing<T> {
+    pub const fn new(secret: T) -> Self {
+        Self(secret)
+    }
+
+    /// Overwrites the secret with zeros, which might not be a valid `T`.
+    fn zeroize(&mut self) {
+        let bytes = ptr::from_mut(&mut self.0).cast::<u8>();
+        for i in 0..size_of::<T>() {
+            // SAFETY: `bytes` points to the `size_of::<T>()` bytes of `self.0`, which is `Copy`,
+            // so nothing runs on the zeros when they're dropped. Volatile writes can't be skipped
+            // for being to memory that's about to be freed.
+            unsafe { ptr::write_volatile(bytes.add(i), 0) };
+        }
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+impl<T: Copy> Drop for Zeroizing<T> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<T: Copy> Deref for Zeroizing<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: Copy> DerefMut for Zeroizing<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Overwrites `bytes` with zeros, for secrets whose length isn't known at compile time.
+pub fn zeroize(bytes: &mut [u8]) {
+    for byte in bytes {
+        // SAFETY: `byte` is a valid reference to a `u8`. As above, volatile writes can't be
+        // skipped.
+        unsafe { ptr::write_volatile(byte, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zeroizing;
+
+    #[test]
+    fn zeroize() {
+        let mut secret = Zeroizing::new([0xaa_u64; 4]);
+        assert_eq!(*secret, [0xaa; 4]);
+        secret.zeroize();
+        assert_eq!(*secret, [0; 4]);
+
+        let mut secret = vec![0xaa; 5];
+        super::zeroize(&mut secret);
+        assert_eq!(secret, [0; 5]);
+    }
+}